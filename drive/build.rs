@@ -0,0 +1,75 @@
+//! Build script: generate the symbolic register table from `src/hw_mtl.rs`.
+//!
+//! Every `pub const NAME: usize = ...;` in the "BAR0 MMIO Register Map"
+//! section becomes a `RegInfo` entry. The subsystem comes from the nearest
+//! `// --- Subsystem ---` header and the description from the first line of
+//! the constant's doc comment (undocumented constants directly following a
+//! documented one share its description). `*_BASE` constants are subsystem
+//! anchors, not registers, and are skipped.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let src = "src/hw_mtl.rs";
+    println!("cargo:rerun-if-changed={}", src);
+
+    let text = fs::read_to_string(src).expect("read hw_mtl.rs");
+    let mut out = String::from("pub static REGISTERS: &[RegInfo] = &[\n");
+
+    let mut in_map = false;
+    let mut header_closed = false;
+    let mut subsystem = String::new();
+    let mut doc: Option<String> = None;
+    let mut carried: Option<String> = None;
+
+    for line in text.lines() {
+        let line = line.trim();
+
+        if line.starts_with("// ") && line.contains("BAR0 MMIO Register Map") {
+            in_map = true;
+            continue;
+        }
+        if !in_map {
+            continue;
+        }
+        if line.starts_with("// ====") {
+            if header_closed {
+                // Start of the next section: the register map is over
+                break;
+            }
+            header_closed = true;
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix("// ---") {
+            subsystem = header.trim_end_matches('-').trim().to_string();
+            doc = None;
+            carried = None;
+        } else if let Some(d) = line.strip_prefix("///") {
+            if doc.is_none() {
+                doc = Some(d.trim().to_string());
+            }
+        } else if let Some(rest) = line.strip_prefix("pub const ") {
+            let name = rest.split(':').next().unwrap_or("").trim();
+            let is_usize = rest.contains(": usize");
+            let description = doc.take().or_else(|| carried.clone()).unwrap_or_default();
+            carried = Some(description.clone());
+
+            if is_usize && !name.ends_with("_BASE") {
+                out.push_str(&format!(
+                    "    RegInfo {{ offset: {}, name: {:?}, subsystem: {:?}, description: {:?} }},\n",
+                    name, name, subsystem, description
+                ));
+            }
+        } else if line.is_empty() {
+            carried = None;
+        }
+    }
+
+    out.push_str("];\n");
+
+    let dest = Path::new(&env::var("OUT_DIR").expect("OUT_DIR")).join("register_map.rs");
+    fs::write(dest, out).expect("write register_map.rs");
+}
@@ -2,16 +2,34 @@
 //!
 //! Implements the full startup sequence for the Meteor Lake NPU:
 //!
-//! 1. Power Up: Release from reset, verify power via Buttress
+//! 1. Power Up: Release from reset, verify power via Buttress, decode the
+//!    tile fuse and request a workpoint for the usable tiles
 //! 2. Firmware Load: Copy firmware to DMA buffer, write address to NPU
 //! 3. Boot Trigger: Ring the doorbell, wait for 0xF00D
 //! 4. Nudge Strategy: If NPU hesitates (0xCAFE), retry the doorbell
 //!
+//! With `with_interrupts` the waits in steps 3–4 end as soon as the NPU
+//! raises an interrupt; otherwise they sleep out the poll interval.
+//!
 //! Based on reverse engineering of Linux ivpu driver boot path:
 //!   ivpu_hw_40xx.c → ivpu_boot_fw(), ivpu_hw_40xx_run_boot_fw()
+//!
+//! A successful boot yields a `BootedNpu`, which owns everything the running
+//! device references by physical address (firmware image, command queue)
+//! and borrows the MMIO mapping, so none of them can go away while the NPU
+//! is running.
+//!
+//! Warm restarts skip the disk: `cold_reset()` cycles reset and repeats
+//! step 1, then `reboot_with()` repeats steps 3–4 with the firmware image
+//! already in DMA memory. Off Redox the fake BAR answers like hardware
+//! would (power drops in reset, the firmware comes up on the doorbell), so
+//! the restart paths run in mock mode and in tests.
 
 use crate::dma::{self, DmaBuffer};
+use crate::fw_image::FirmwareInfo;
 use crate::hw_mtl::*;
+use crate::inference::CommandQueue;
+use crate::irq::{Interrupts, IrqEvent};
 use crate::mmio::MmioRegion;
 use log::{debug, error, info, warn};
 use std::thread;
@@ -29,32 +47,50 @@ pub enum BootResult {
 /// Full boot orchestrator.
 pub struct BootSequence<'a> {
     mmio: &'a MmioRegion,
+    /// Register offsets of the device being booted
+    regs: &'static RegisterMap,
+    /// Tiles on a full part of the device being booted
+    max_tiles: u8,
+    /// Wakes the boot wait early; `None` polls
+    irq: Option<&'a Interrupts>,
 }
 
 impl<'a> BootSequence<'a> {
     pub fn new(mmio: &'a MmioRegion) -> Self {
-        Self { mmio }
+        Self { mmio, regs: &REGS_MTL, max_tiles: NPU_TILES_MTL, irq: None }
+    }
+
+    /// Use the register map and tile fuse of this device generation
+    /// (Meteor Lake by default).
+    pub fn with_device(mut self, device_id: u16) -> Result<Self, UnsupportedGeneration> {
+        self.regs = RegisterMap::for_device(device_id)?;
+        self.max_tiles = npu_max_tiles(device_id);
+        Ok(self)
+    }
+
+    /// Wait for the firmware on the device's interrupts instead of polling.
+    pub fn with_interrupts(mut self, irq: &'a Interrupts) -> Self {
+        self.irq = Some(irq);
+        self
     }
 
     /// Execute the complete boot sequence.
     ///
-    /// Returns both the boot result and the firmware DMA buffer.
-    /// The caller MUST keep the returned `DmaBuffer` alive for the entire
-    /// lifetime of the driver — the NPU continues to reference the firmware
-    /// at its physical address after boot.
-    pub fn execute(&self, fw_path: &str) -> Result<(BootResult, DmaBuffer), BootError> {
+    /// On success the firmware buffer and `queue` are handed to the returned
+    /// `BootedNpu`, which keeps them alive until the device is shut down.
+    pub fn execute(&self, fw_path: &str, queue: CommandQueue) -> Result<BootedNpu<'a>, BootError> {
         info!("╔══════════════════════════════════════════╗");
         info!("║   Intel NPU Boot Sequence Starting...    ║");
         info!("╚══════════════════════════════════════════╝");
 
         // Step 1: Power up the NPU
-        self.power_up()?;
+        let tiles = self.power_up()?;
 
         // Step 2: Load firmware into DMA buffer
-        let fw_buffer = self.load_firmware(fw_path)?;
+        let (fw_buffer, fw_info) = self.load_firmware(fw_path)?;
 
         // Step 3: Tell NPU where the firmware lives
-        self.set_firmware_address(&fw_buffer)?;
+        self.set_firmware_address(&fw_buffer, &fw_info)?;
 
         // Step 4: Trigger boot and wait for handshake
         let result = self.trigger_and_wait()?;
@@ -72,35 +108,59 @@ impl<'a> BootSequence<'a> {
             }
         }
 
-        // Return fw_buffer to caller — it MUST stay alive while NPU is running.
-        // Dropping it would free the physical memory the NPU is still reading.
-        Ok((result, fw_buffer))
+        Ok(BootedNpu::new(self, result, tiles, fw_buffer, fw_info, queue))
+    }
+
+    /// Hold the NPU in reset, release it and power it up again.
+    ///
+    /// Interrupts are masked first and stay masked until the next doorbell;
+    /// whatever the firmware was doing is lost. Follow with `reboot_with`.
+    pub fn cold_reset(&self) -> Result<TileConfig, BootError> {
+        info!("🔁 Cold reset: asserting reset for {}ms...", RESET_HOLD_MS);
+        self.mmio.write32(self.regs.global_int_mask, 0xFFFF_FFFF);
+        self.mmio.write32(self.regs.ipc_int_mask, 0xFFFF_FFFF);
+        self.mmio.write32(self.regs.cpr_rst_set, 0x1);
+        #[cfg(not(target_os = "redox"))]
+        self.mock_reset_asserted();
+        thread::sleep(Duration::from_millis(RESET_HOLD_MS));
+
+        // Released the way a cold boot does it: D0i3 exit, clocks, then reset
+        self.power_up()
+    }
+
+    /// Boot firmware that is already in DMA memory (steps 3–4), e.g. the
+    /// image kept by a `BootedNpu`, after a `cold_reset`.
+    pub fn reboot_with(&self, fw_buffer: &DmaBuffer, fw_info: &FirmwareInfo) -> Result<BootResult, BootError> {
+        self.set_firmware_address(fw_buffer, fw_info)?;
+        self.trigger_and_wait()
     }
 
     // ================================================================
     // Step 1: Power Up
     // ================================================================
 
-    fn power_up(&self) -> Result<(), BootError> {
+    fn power_up(&self) -> Result<TileConfig, BootError> {
         info!("🔌 [1/4] Power-up sequence...");
 
         // Read initial status
-        let initial = self.mmio.read32(HOST_SS_FW_STATUS);
+        let initial = self.mmio.read32(self.regs.fw_status);
         debug!("  Initial FW_STATUS: {:#010x} ({})", initial, decode_fw_status(initial));
 
         // Exit D0i3 power gating state (must happen before any other power ops)
         info!("  Exiting D0i3 power state...");
-        self.mmio.write32(BUTTRESS_VPU_D0I3_CONTROL, 0x0);
+        self.mmio.write32(self.regs.d0i3_control, 0x0);
         thread::sleep(Duration::from_millis(10));
 
         // Enable clocks FIRST (Linux ivpu driver: clocks before reset release)
         info!("  Enabling clocks...");
-        self.mmio.write32(HOST_SS_CLK_EN, 0x1);
+        self.mmio.write32(self.regs.clk_en, 0x1);
         thread::sleep(Duration::from_millis(10));
 
         // THEN release NPU from reset
         info!("  Clearing reset...");
-        self.mmio.write32(HOST_SS_CPR_RST_CLR, 0x1);
+        self.mmio.write32(self.regs.cpr_rst_clr, 0x1);
+        #[cfg(not(target_os = "redox"))]
+        self.mock_reset_released();
 
         // Delay for hardware to stabilize after reset release
         thread::sleep(Duration::from_millis(50));
@@ -108,7 +168,7 @@ impl<'a> BootSequence<'a> {
         // Poll Buttress for power confirmation
         info!("  Polling Buttress for power status...");
         let buttress_result = self.mmio.poll_until(
-            BUTTRESS_VPU_STATUS,
+            self.regs.vpu_status,
             |val| val & 0x1 != 0, // Bit 0 = powered
             POLL_INTERVAL_MS,
             POWER_UP_TIMEOUT_MS,
@@ -129,50 +189,73 @@ impl<'a> BootSequence<'a> {
         }
 
         // Read tile fuse to know what we're working with
-        let tile_fuse = self.mmio.read32(BUTTRESS_TILE_FUSE);
+        let tile_fuse = self.mmio.read32(self.regs.tile_fuse);
+        let tiles = TileConfig::from_fuse(tile_fuse, self.max_tiles);
         debug!("  Tile fuse: {:#010x}", tile_fuse);
+        if tiles.count == 0 {
+            error!("  ❌ Tile fuse {:#010x} leaves no usable tiles", tile_fuse);
+            return Err(BootError::NoTiles { fuse: tile_fuse });
+        }
+        if tiles.is_partial() {
+            warn!("  ⚠️  Partial part: {}", tiles);
+        } else {
+            info!("  Tiles: {}", tiles);
+        }
+        self.request_workpoint(&tiles);
 
         // NOTE: Interrupts are unmasked later in trigger_and_wait(), just before
         // ringing the doorbell. Unmasking too early can cause spurious IRQs
         // before the firmware is loaded.
 
         info!("  ✅ Power-up complete.");
-        Ok(())
+        Ok(tiles)
+    }
+
+    /// Tell the firmware which tiles it may use. Booting a partial part with
+    /// the full-part config makes the firmware touch fused-off tiles and hang.
+    fn request_workpoint(&self, tiles: &TileConfig) {
+        self.mmio.write32(self.regs.wp_req_payload1, tiles.enabled_tiles);
+        self.mmio.write32(self.regs.wp_req_cmd, WP_REQ_CMD_SEND);
+        debug!("  Workpoint request: tile config {:#04x}", tiles.enabled_tiles);
     }
 
     // ================================================================
     // Step 2: Load Firmware
     // ================================================================
 
-    fn load_firmware(&self, fw_path: &str) -> Result<DmaBuffer, BootError> {
+    fn load_firmware(&self, fw_path: &str) -> Result<(DmaBuffer, FirmwareInfo), BootError> {
         info!("📦 [2/4] Loading firmware: {}", fw_path);
 
-        let fw_buffer = dma::load_firmware(fw_path).map_err(|e| BootError::FirmwareLoad(e))?;
+        let (fw_buffer, fw_info) = dma::load_firmware(fw_path).map_err(|e| BootError::FirmwareLoad(e))?;
 
+        info!(
+            "  Firmware {} (VPU {}), entry point {:#x}",
+            fw_info.version, fw_info.vpu_version, fw_info.entry_point
+        );
         info!(
             "  ✅ Firmware in DMA: phys={:#010x}, size={} bytes",
             fw_buffer.phys_addr, fw_buffer.size
         );
 
-        Ok(fw_buffer)
+        Ok((fw_buffer, fw_info))
     }
 
     // ================================================================
     // Step 3: Set Firmware Address
     // ================================================================
 
-    fn set_firmware_address(&self, fw_buffer: &DmaBuffer) -> Result<(), BootError> {
+    fn set_firmware_address(&self, fw_buffer: &DmaBuffer, fw_info: &FirmwareInfo) -> Result<(), BootError> {
         info!("📍 [3/4] Writing firmware address to NPU registers...");
 
         // Write the 64-bit physical address where firmware lives
         self.mmio
-            .write32(HOST_SS_LOADING_ADDR_LO, fw_buffer.phys_lo());
+            .write32(self.regs.loading_addr_lo, fw_buffer.phys_lo());
         self.mmio
-            .write32(HOST_SS_LOADING_ADDR_HI, fw_buffer.phys_hi());
+            .write32(self.regs.loading_addr_hi, fw_buffer.phys_hi());
 
         // Verify the write (read back)
-        let readback_lo = self.mmio.read32(HOST_SS_LOADING_ADDR_LO);
-        let readback_hi = self.mmio.read32(HOST_SS_LOADING_ADDR_HI);
+        let readback_lo = self.mmio.read32(self.regs.loading_addr_lo);
+        let readback_hi = self.mmio.read32(self.regs.loading_addr_hi);
 
         debug!(
             "  Readback: LO={:#010x} (expected {:#010x})",
@@ -191,6 +274,10 @@ impl<'a> BootSequence<'a> {
         }
 
         info!("  ✅ Firmware address set: {:#018x}", fw_buffer.phys_addr);
+
+        // Where the firmware starts executing, from its header
+        self.mmio.write32(self.regs.entry_point, fw_info.entry_point_reg());
+        debug!("  Entry point: {:#010x}", fw_info.entry_point_reg());
         Ok(())
     }
 
@@ -204,14 +291,16 @@ impl<'a> BootSequence<'a> {
         // Unmask interrupts NOW — firmware is loaded and address is set,
         // so the NPU can signal us back via IPC after we ring the doorbell.
         info!("  Unmasking global + IPC interrupts...");
-        self.mmio.write32(BUTTRESS_GLOBAL_INT_MASK, 0x0);
-        self.mmio.write32(IPC_INT_MASK, 0x0);
+        self.mmio.write32(self.regs.global_int_mask, 0x0);
+        self.mmio.write32(self.regs.ipc_int_mask, 0x0);
 
         // Ring the doorbell — bit 31 must be set (IPC_DRBL_TRIGGER)
-        self.mmio.write32(IPC_HOST_2_DEVICE_DRBL, IPC_DRBL_TRIGGER);
+        self.mmio.write32(self.regs.h2d_doorbell, IPC_DRBL_TRIGGER);
+        #[cfg(not(target_os = "redox"))]
+        self.mock_firmware_boot();
 
         // Initial delay — let the NPU start processing
-        thread::sleep(Duration::from_millis(NUDGE_DELAY_MS));
+        self.wait(Duration::from_millis(NUDGE_DELAY_MS));
 
         // Poll for firmware status with nudge retries
         let mut nudge_count = 0u32;
@@ -221,7 +310,7 @@ impl<'a> BootSequence<'a> {
         loop {
             // Hard global timeout — prevents infinite loop on unknown status
             if boot_start.elapsed() >= boot_timeout {
-                let last = self.mmio.read32(HOST_SS_FW_STATUS);
+                let last = self.mmio.read32(self.regs.fw_status);
                 error!(
                     "  ❌ Boot timed out after {}ms (last status: {:#010x} = {})",
                     FW_BOOT_TIMEOUT_MS, last, decode_fw_status(last)
@@ -230,7 +319,7 @@ impl<'a> BootSequence<'a> {
                 return Err(BootError::Timeout { last_status: last });
             }
 
-            let raw_status = self.mmio.read32(HOST_SS_FW_STATUS);
+            let raw_status = self.mmio.read32(self.regs.fw_status);
             let status_code = raw_status & FW_STATUS_MASK;
 
             debug!(
@@ -244,7 +333,7 @@ impl<'a> BootSequence<'a> {
                 // ===== SUCCESS =====
                 FW_STATUS_READY => {
                     info!("  🎉 Firmware reports READY (0xF00D)!");
-                    let fw_version = self.mmio.read32(HOST_SS_FW_VERSION);
+                    let fw_version = self.mmio.read32(self.regs.fw_version);
                     return Ok(BootResult::Ready { fw_version });
                 }
 
@@ -275,74 +364,319 @@ impl<'a> BootSequence<'a> {
                     );
 
                     // Re-ring the doorbell (bit 31 = trigger)
-                    self.mmio.write32(IPC_HOST_2_DEVICE_DRBL, IPC_DRBL_TRIGGER);
-                    thread::sleep(Duration::from_millis(NUDGE_DELAY_MS * (nudge_count as u64 + 1)));
+                    self.mmio.write32(self.regs.h2d_doorbell, IPC_DRBL_TRIGGER);
+                    self.wait(Duration::from_millis(NUDGE_DELAY_MS * (nudge_count as u64 + 1)));
                 }
 
                 // ===== IN PROGRESS =====
                 FW_STATUS_BEEF | FW_STATUS_FACE => {
                     debug!("  ⏳ Boot in progress...");
-                    thread::sleep(Duration::from_millis(POLL_INTERVAL_MS * 10));
+                    self.wait(Duration::from_millis(POLL_INTERVAL_MS * 10));
                 }
 
                 // ===== NOT INITIALIZED / UNKNOWN =====
                 _ => {
                     if raw_status == 0x0000_0000 {
                         // Still waiting to wake up
-                        thread::sleep(Duration::from_millis(POLL_INTERVAL_MS * 5));
+                        self.wait(Duration::from_millis(POLL_INTERVAL_MS * 5));
                     } else {
                         debug!(
                             "  Unknown status {:#010x}, continuing to poll...",
                             raw_status
                         );
-                        thread::sleep(Duration::from_millis(POLL_INTERVAL_MS * 10));
+                        self.wait(Duration::from_millis(POLL_INTERVAL_MS * 10));
                     }
                 }
             }
 
             // Boot count sanity check
-            let boot_count = self.mmio.read32(HOST_SS_BOOT_COUNT);
+            let boot_count = self.mmio.read32(self.regs.boot_count);
             if boot_count > 100 {
                 warn!("  Boot count high ({}), NPU may be in a loop", boot_count);
             }
         }
     }
 
+    /// Sleep up to `timeout`, returning early if the NPU interrupts.
+    fn wait(&self, timeout: Duration) {
+        let Some(irq) = self.irq else {
+            thread::sleep(timeout);
+            return;
+        };
+        for event in irq.wait(self.mmio, self.regs, timeout) {
+            match event {
+                IrqEvent::Error(_) => warn!("  ⚠️  NPU raised an {}", event),
+                _ => debug!("  IRQ: {}", event),
+            }
+        }
+    }
+
+    /// Register the command queue and completion ring with the firmware.
+    fn register_queue(&self, queue: &CommandQueue) {
+        // The NPU reads commands from this DMA address when the doorbell is rung
+        let queue_phys = queue.phys_addr();
+        self.mmio.write32(self.regs.h2d_data[0], queue_phys as u32);
+        self.mmio.write32(self.regs.h2d_data[1], (queue_phys >> 32) as u32);
+        info!(
+            "Command queue registered with NPU: DATA0={:#010x}, DATA1={:#010x}",
+            queue_phys as u32,
+            (queue_phys >> 32) as u32
+        );
+        // ...and posts job completions here
+        let ring_phys = queue.completion_ring().phys_addr();
+        self.mmio.write32(self.regs.h2d_data[2], ring_phys as u32);
+        self.mmio.write32(self.regs.h2d_data[3], (ring_phys >> 32) as u32);
+    }
+
+    // ================================================================
+    // Mock hardware (status transitions the real NPU makes on its own)
+    // ================================================================
+
+    /// In reset the Buttress drops its power bit and running firmware
+    /// stops. A crashed image keeps its DEAD status, which is how the
+    /// simulator scripts firmware that will not come back.
+    #[cfg(not(target_os = "redox"))]
+    fn mock_reset_asserted(&self) {
+        let power = self.mmio.read32(self.regs.vpu_status);
+        self.mmio.write32(self.regs.vpu_status, power & !0x1);
+        if self.mmio.read32(self.regs.fw_status) & FW_STATUS_MASK == FW_STATUS_READY {
+            self.mmio.write32(self.regs.fw_status, 0);
+        }
+    }
+
+    /// Power comes back once reset is released.
+    #[cfg(not(target_os = "redox"))]
+    fn mock_reset_released(&self) {
+        let power = self.mmio.read32(self.regs.vpu_status);
+        self.mmio.write32(self.regs.vpu_status, power | 0x1);
+    }
+
+    /// Firmware that has not started yet boots straight to READY on the
+    /// doorbell, and says so with an interrupt; any status already set (by
+    /// a simulator) is left alone.
+    #[cfg(not(target_os = "redox"))]
+    fn mock_firmware_boot(&self) {
+        if self.mmio.read32(self.regs.fw_status) == 0 {
+            let boots = self.mmio.read32(self.regs.boot_count);
+            self.mmio.write32(self.regs.boot_count, boots.wrapping_add(1));
+            self.mmio.write32(self.regs.fw_status, FW_STATUS_READY);
+            if let Some(irq) = self.irq {
+                irq.raise();
+            }
+        }
+    }
+
     // ================================================================
     // Diagnostics
     // ================================================================
 
     fn dump_diagnostics(&self) {
         error!("=== NPU Diagnostic Dump ===");
-        error!(
-            "  FW_STATUS    : {:#010x} ({})",
-            self.mmio.read32(HOST_SS_FW_STATUS),
-            decode_fw_status(self.mmio.read32(HOST_SS_FW_STATUS))
-        );
-        error!(
-            "  FW_VERSION   : {:#010x}",
-            self.mmio.read32(HOST_SS_FW_VERSION)
-        );
-        error!(
-            "  BOOT_COUNT   : {}",
-            self.mmio.read32(HOST_SS_BOOT_COUNT)
-        );
-        error!(
-            "  BUTTRESS     : {:#010x}",
-            self.mmio.read32(BUTTRESS_VPU_STATUS)
-        );
-        error!(
-            "  GEN_CTRL     : {:#010x}",
-            self.mmio.read32(HOST_SS_GEN_CTRL)
-        );
-        error!(
-            "  GLOBAL_INT   : {:#010x}",
-            self.mmio.read32(BUTTRESS_GLOBAL_INT_STS)
-        );
+        for offset in [
+            self.regs.fw_status,
+            self.regs.fw_version,
+            self.regs.boot_count,
+            self.regs.vpu_status,
+            self.regs.tile_fuse,
+            self.regs.wp_req_payload1,
+            self.regs.gen_ctrl,
+            self.regs.global_int_sts,
+        ] {
+            error!("  {}", format_reg(self.regs, offset, self.mmio.read32(offset)));
+        }
         error!("=== End Diagnostic Dump ===");
     }
 }
 
+// ============================================================
+// Booted Device
+// ============================================================
+
+/// A running NPU and the DMA memory it references.
+///
+/// The firmware buffer is private and only released by dropping the whole
+/// `BootedNpu`, and the `'a` borrow keeps the MMIO mapping alive for as long
+/// as the device runs. Teardown (explicit `shutdown()` or drop on an error
+/// path) always masks interrupts, puts the NPU back in reset, and only then
+/// frees the queue and finally the firmware.
+///
+/// Booting and shutting down in order compiles:
+///
+/// ```no_run
+/// use intel_npu::boot::BootSequence;
+/// use intel_npu::inference::CommandQueue;
+/// use intel_npu::mmio::MmioRegion;
+///
+/// fn run(mmio: MmioRegion, queue: CommandQueue) {
+///     let mut booted = BootSequence::new(&mmio).execute("vpu.bin", queue).unwrap();
+///     booted.queue_mut();
+///     booted.shutdown();
+///     drop(mmio);
+/// }
+/// ```
+///
+/// Freeing the firmware early does not:
+///
+/// ```compile_fail,E0616
+/// use intel_npu::boot::BootSequence;
+/// use intel_npu::inference::CommandQueue;
+/// use intel_npu::mmio::MmioRegion;
+///
+/// fn run(mmio: MmioRegion, queue: CommandQueue) {
+///     let booted = BootSequence::new(&mmio).execute("vpu.bin", queue).unwrap();
+///     drop(booted.firmware); // field `firmware` is private
+/// }
+/// ```
+///
+/// Neither does unmapping BAR0 under a running device:
+///
+/// ```compile_fail,E0505
+/// use intel_npu::boot::BootSequence;
+/// use intel_npu::inference::CommandQueue;
+/// use intel_npu::mmio::MmioRegion;
+///
+/// fn run(mmio: MmioRegion, queue: CommandQueue) {
+///     let mut booted = BootSequence::new(&mmio).execute("vpu.bin", queue).unwrap();
+///     drop(mmio); // cannot move out of `mmio` because it is borrowed
+///     booted.queue_mut();
+/// }
+/// ```
+pub struct BootedNpu<'a> {
+    mmio: &'a MmioRegion,
+    regs: &'static RegisterMap,
+    irq: Option<&'a Interrupts>,
+    result: BootResult,
+    tiles: TileConfig,
+    // Fields drop in declaration order: the queue goes before the firmware
+    queue: CommandQueue,
+    firmware: DmaBuffer,
+    fw_info: FirmwareInfo,
+}
+
+impl<'a> BootedNpu<'a> {
+    /// Take ownership of a booted device and register `queue` with it.
+    fn new(
+        boot: &BootSequence<'a>,
+        result: BootResult,
+        tiles: TileConfig,
+        firmware: DmaBuffer,
+        fw_info: FirmwareInfo,
+        mut queue: CommandQueue,
+    ) -> Self {
+        queue.set_register_map(boot.regs);
+        boot.register_queue(&queue);
+        Self { mmio: boot.mmio, regs: boot.regs, irq: boot.irq, result, tiles, queue, firmware, fw_info }
+    }
+
+    /// Restart firmware that died, without reloading it from disk.
+    ///
+    /// See `Restarter::restart`. Jobs that were in flight when it died are
+    /// not completed.
+    pub fn recover(&mut self) -> Result<&BootResult, BootError> {
+        let (queue, mut restarter) = self.split();
+        restarter.restart(queue)?;
+        Ok(&self.result)
+    }
+
+    /// Lend out the command queue together with a `Restarter`, so the
+    /// holder of the queue (the scheme) can also restart the device.
+    pub fn split(&mut self) -> (&mut CommandQueue, Restarter<'_>) {
+        let boot = BootSequence { mmio: self.mmio, regs: self.regs, max_tiles: self.tiles.max_tiles, irq: self.irq };
+        let restarter = Restarter {
+            boot,
+            firmware: &self.firmware,
+            fw_info: &self.fw_info,
+            result: &mut self.result,
+            tiles: &mut self.tiles,
+        };
+        (&mut self.queue, restarter)
+    }
+
+    pub fn result(&self) -> &BootResult {
+        &self.result
+    }
+
+    /// Header of the firmware image the device runs.
+    pub fn firmware_info(&self) -> &FirmwareInfo {
+        &self.fw_info
+    }
+
+    /// Tiles the firmware was configured for.
+    pub fn tiles(&self) -> TileConfig {
+        self.tiles
+    }
+
+    /// MMIO mapping of the device (outlives the `BootedNpu` borrow).
+    pub fn mmio(&self) -> &'a MmioRegion {
+        self.mmio
+    }
+
+    pub fn queue(&self) -> &CommandQueue {
+        &self.queue
+    }
+
+    pub fn queue_mut(&mut self) -> &mut CommandQueue {
+        &mut self.queue
+    }
+
+    /// Stop the device and release its memory.
+    ///
+    /// Returns the MMIO mapping so a recovery path can boot again with a
+    /// fresh `BootSequence`.
+    pub fn shutdown(self) -> &'a MmioRegion {
+        let mmio = self.mmio;
+        drop(self);
+        mmio
+    }
+
+    /// Mask interrupts and hold the NPU in reset so it stops touching DMA.
+    fn quiesce(&self) {
+        info!("🛑 NPU shutdown: masking interrupts, asserting reset...");
+        self.mmio.write32(self.regs.global_int_mask, 0xFFFF_FFFF);
+        self.mmio.write32(self.regs.ipc_int_mask, 0xFFFF_FFFF);
+        self.mmio.write32(self.regs.cpr_rst_set, 0x1);
+        for data in self.regs.h2d_data {
+            self.mmio.write32(data, 0);
+        }
+    }
+}
+
+/// Restarts a `BootedNpu` in place from the firmware image it keeps.
+pub struct Restarter<'b> {
+    boot: BootSequence<'b>,
+    firmware: &'b DmaBuffer,
+    fw_info: &'b FirmwareInfo,
+    result: &'b mut BootResult,
+    tiles: &'b mut TileConfig,
+}
+
+impl Restarter<'_> {
+    /// Cold-reset the NPU, boot the retained firmware image again and
+    /// re-register `queue` once the firmware answers.
+    ///
+    /// Nothing on the device survives, so jobs in flight never complete
+    /// (see `CommandQueue::abort_in_flight`).
+    pub fn restart(&mut self, queue: &CommandQueue) -> Result<&BootResult, BootError> {
+        warn!("🔄 Restarting NPU: reset, firmware address, doorbell...");
+        *self.tiles = self.boot.cold_reset()?;
+        *self.result = self.boot.reboot_with(self.firmware, self.fw_info)?;
+        self.boot.register_queue(queue);
+        Ok(self.result)
+    }
+}
+
+impl Drop for BootedNpu<'_> {
+    fn drop(&mut self) {
+        self.quiesce();
+        // Field drops follow: command queue, then firmware
+        debug!(
+            "Releasing command queue ({:#010x}) and firmware ({:#010x})",
+            self.queue.phys_addr(),
+            self.firmware.phys_addr
+        );
+    }
+}
+
 // ============================================================
 // Error Types
 // ============================================================
@@ -350,6 +684,7 @@ impl<'a> BootSequence<'a> {
 #[derive(Debug)]
 pub enum BootError {
     PowerUpTimeout,
+    NoTiles { fuse: u32 },
     FirmwareLoad(dma::DmaError),
     AddressReadbackMismatch,
     FirmwareDead,
@@ -362,6 +697,7 @@ impl std::fmt::Display for BootError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::PowerUpTimeout => write!(f, "NPU power-up timed out"),
+            Self::NoTiles { fuse } => write!(f, "Tile fuse {:#010x} leaves no usable tiles", fuse),
             Self::FirmwareLoad(e) => write!(f, "Firmware load failed: {}", e),
             Self::AddressReadbackMismatch => {
                 write!(f, "Firmware address readback mismatch (MMIO write failure)")
@@ -24,9 +24,12 @@
 //!   └──────────────┘
 //! ```
 
+use crate::fw_image::{self, FirmwareInfo, FwImageError};
 use crate::hw_mtl::DMA_ALIGNMENT;
 use log::{debug, error, info};
 use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 /// A physically contiguous DMA buffer accessible by both CPU and NPU.
 ///
@@ -40,6 +43,8 @@ pub struct DmaBuffer {
     pub(crate) phys_addr: u64,
     /// Buffer size in bytes
     pub(crate) size: usize,
+    /// Zero the whole buffer on drop (firmware, model weights, user data)
+    pub(crate) sensitive: bool,
     /// Keeps the backing file alive — dropping this unmaps the memory
     #[cfg(target_os = "redox")]
     _file: std::fs::File,
@@ -83,6 +88,29 @@ impl DmaBuffer {
         }
     }
 
+    /// Allocate a DMA buffer holding sensitive data.
+    ///
+    /// The full buffer is zeroed with volatile writes when it is dropped, so
+    /// firmware, model weights and user-derived inputs do not linger in
+    /// physical memory. Use `set_sensitive(false)` to skip the scrub for a
+    /// buffer whose contents turn out not to matter (e.g. large scratch
+    /// buffers where the zeroing cost is not worth it).
+    pub fn new_sensitive(size: usize) -> Result<Self, DmaError> {
+        let mut buf = Self::new(size)?;
+        buf.sensitive = true;
+        Ok(buf)
+    }
+
+    /// Whether the buffer is scrubbed on drop.
+    pub fn is_sensitive(&self) -> bool {
+        self.sensitive
+    }
+
+    /// Enable or skip zero-on-drop for this buffer.
+    pub fn set_sensitive(&mut self, sensitive: bool) {
+        self.sensitive = sensitive;
+    }
+
     /// Redox-specific allocation using the phys_contiguous scheme.
     #[cfg(target_os = "redox")]
     fn alloc_redox(size: usize) -> Result<Self, DmaError> {
@@ -134,10 +162,13 @@ impl DmaBuffer {
             std::ptr::write_bytes(virt_addr as *mut u8, 0, size);
         }
 
+        STATS.record_alloc(size);
+
         Ok(Self {
             virt_addr,
             phys_addr,
             size,
+            sensitive: false,
             _file: file,
         })
     }
@@ -159,10 +190,13 @@ impl DmaBuffer {
             aligned_ptr, aligned_ptr, size
         );
 
+        STATS.record_alloc(size);
+
         Ok(Self {
             virt_addr: aligned_ptr,
             phys_addr: aligned_ptr as u64, // In mock mode, virt == "phys"
             size,
+            sensitive: false,
             _backing: backing,
         })
     }
@@ -286,6 +320,22 @@ impl DmaBuffer {
         }
         std::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
     }
+
+    /// Scrub the buffer contents (volatile zeroing of the full buffer).
+    ///
+    /// Unlike `zero()`, the time spent is accounted in the DMA stats so the
+    /// cost of scrubbing sensitive buffers stays visible.
+    pub fn scrub(&self) -> Duration {
+        let start = Instant::now();
+        self.zero();
+        let elapsed = start.elapsed();
+        STATS.record_scrub(self.size, elapsed);
+        debug!(
+            "Scrubbed DMA buffer: virt={:#x}, size={:#x} in {:?}",
+            self.virt_addr, self.size, elapsed
+        );
+        elapsed
+    }
 }
 
 impl Drop for DmaBuffer {
@@ -295,6 +345,14 @@ impl Drop for DmaBuffer {
             self.virt_addr, self.phys_addr, self.size
         );
 
+        if self.sensitive {
+            self.scrub();
+        }
+        STATS.record_free(self.size);
+
+        #[cfg(all(test, not(target_os = "redox")))]
+        tests::inspect_on_drop(&self._backing);
+
         #[cfg(target_os = "redox")]
         {
             // On Redox, unmapping happens when _file is dropped,
@@ -305,17 +363,100 @@ impl Drop for DmaBuffer {
 }
 
 // ============================================================
-// Firmware Loader
+// DMA Accounting
 // ============================================================
 
-/// Expected firmware magic bytes: "VPU!" (0x56, 0x50, 0x55, 0x21)
-const FW_MAGIC: [u8; 4] = [0x56, 0x50, 0x55, 0x21];
+/// Process-wide DMA allocation and scrubbing counters.
+struct DmaCounters {
+    allocations: AtomicU64,
+    frees: AtomicU64,
+    live_bytes: AtomicU64,
+    scrubs: AtomicU64,
+    scrubbed_bytes: AtomicU64,
+    scrub_nanos: AtomicU64,
+}
+
+static STATS: DmaCounters = DmaCounters {
+    allocations: AtomicU64::new(0),
+    frees: AtomicU64::new(0),
+    live_bytes: AtomicU64::new(0),
+    scrubs: AtomicU64::new(0),
+    scrubbed_bytes: AtomicU64::new(0),
+    scrub_nanos: AtomicU64::new(0),
+};
+
+impl DmaCounters {
+    fn record_alloc(&self, size: usize) {
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        self.live_bytes.fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    fn record_free(&self, size: usize) {
+        self.frees.fetch_add(1, Ordering::Relaxed);
+        self.live_bytes.fetch_sub(size as u64, Ordering::Relaxed);
+    }
+
+    fn record_scrub(&self, size: usize, elapsed: Duration) {
+        self.scrubs.fetch_add(1, Ordering::Relaxed);
+        self.scrubbed_bytes.fetch_add(size as u64, Ordering::Relaxed);
+        self.scrub_nanos.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Snapshot of DMA accounting stats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+pub struct DmaStats {
+    pub allocations: u64,
+    pub frees: u64,
+    pub live_bytes: u64,
+    pub scrubs: u64,
+    pub scrubbed_bytes: u64,
+    /// Reported in whole microseconds (`scrub_us`)
+    #[cfg_attr(test, serde(rename = "scrub_us", deserialize_with = "micros"))]
+    pub scrub_time: Duration,
+}
+
+#[cfg(test)]
+pub(crate) fn micros<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+    <u64 as serde::Deserialize>::deserialize(d).map(Duration::from_micros)
+}
+
+impl DmaStats {
+    pub fn to_json(self) -> String {
+        format!(
+            "{{\"allocations\":{},\"frees\":{},\"live_bytes\":{},\"scrubs\":{},\"scrubbed_bytes\":{},\"scrub_us\":{}}}",
+            self.allocations,
+            self.frees,
+            self.live_bytes,
+            self.scrubs,
+            self.scrubbed_bytes,
+            self.scrub_time.as_micros()
+        )
+    }
+}
+
+/// Current DMA accounting stats.
+pub fn stats() -> DmaStats {
+    DmaStats {
+        allocations: STATS.allocations.load(Ordering::Relaxed),
+        frees: STATS.frees.load(Ordering::Relaxed),
+        live_bytes: STATS.live_bytes.load(Ordering::Relaxed),
+        scrubs: STATS.scrubs.load(Ordering::Relaxed),
+        scrubbed_bytes: STATS.scrubbed_bytes.load(Ordering::Relaxed),
+        scrub_time: Duration::from_nanos(STATS.scrub_nanos.load(Ordering::Relaxed)),
+    }
+}
+
+// ============================================================
+// Firmware Loader
+// ============================================================
 
 /// Load firmware binary from disk into a DMA buffer.
 ///
-/// Validates the firmware magic header before loading to prevent
-/// loading corrupted or non-firmware files into DMA.
-pub fn load_firmware(path: &str) -> Result<DmaBuffer, DmaError> {
+/// The header and section table are validated (see `fw_image`) before
+/// anything is copied, so truncated or non-firmware files never reach DMA.
+pub fn load_firmware(path: &str) -> Result<(DmaBuffer, FirmwareInfo), DmaError> {
     info!("Loading firmware from: {}", path);
 
     let fw_data = std::fs::read(path).map_err(|e| DmaError::FirmwareRead(e))?;
@@ -331,25 +472,20 @@ pub fn load_firmware(path: &str) -> Result<DmaBuffer, DmaError> {
         });
     }
 
-    // Validate firmware magic header
-    if fw_data.len() < 4 || fw_data[0..4] != FW_MAGIC {
-        let got = if fw_data.len() >= 4 {
-            format!("{:02x} {:02x} {:02x} {:02x}", fw_data[0], fw_data[1], fw_data[2], fw_data[3])
-        } else {
-            format!("(only {} bytes)", fw_data.len())
-        };
-        error!("Firmware magic mismatch: expected 'VPU!' (56 50 55 21), got {}", got);
-        return Err(DmaError::FirmwareBadMagic);
-    }
+    // Validate header and sections
+    let fw_info = fw_image::parse(&fw_data).map_err(|e| {
+        error!("Firmware image rejected: {}", e);
+        DmaError::FirmwareInvalid(e)
+    })?;
 
     info!(
-        "Firmware loaded: {} bytes ({:.2} MB), magic OK",
+        "Firmware loaded: {} bytes ({:.2} MB), header OK",
         fw_data.len(),
         fw_data.len() as f64 / (1024.0 * 1024.0)
     );
 
     // Allocate DMA buffer sized to firmware
-    let buf = DmaBuffer::new(fw_data.len())?;
+    let buf = DmaBuffer::new_sensitive(fw_data.len())?;
 
     // Copy firmware into DMA buffer
     buf.write_bytes(0, &fw_data)?;
@@ -359,7 +495,7 @@ pub fn load_firmware(path: &str) -> Result<DmaBuffer, DmaError> {
         buf.phys_addr
     );
 
-    Ok(buf)
+    Ok((buf, fw_info))
 }
 
 // ============================================================
@@ -381,11 +517,14 @@ pub enum DmaError {
     ZeroSize,
     FirmwareRead(io::Error),
     FirmwareEmpty,
-    FirmwareBadMagic,
+    FirmwareInvalid(FwImageError),
     FirmwareTooLarge {
         actual: usize,
         max: usize,
     },
+    PoolExhausted {
+        size: usize,
+    },
 }
 
 impl std::fmt::Display for DmaError {
@@ -402,12 +541,88 @@ impl std::fmt::Display for DmaError {
             Self::ZeroSize => write!(f, "Cannot allocate zero-size DMA buffer"),
             Self::FirmwareRead(e) => write!(f, "Failed to read firmware file: {}", e),
             Self::FirmwareEmpty => write!(f, "Firmware file is empty"),
-            Self::FirmwareBadMagic => write!(f, "Firmware magic header invalid (expected 'VPU!')"),
+            Self::FirmwareInvalid(e) => write!(f, "{}", e),
             Self::FirmwareTooLarge { actual, max } => {
                 write!(f, "Firmware too large: {} bytes (max {})", actual, max)
             }
+            Self::PoolExhausted { size } => write!(f, "DMA pool has no free buffer of {} bytes", size),
         }
     }
 }
 
 impl std::error::Error for DmaError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    thread_local! {
+        /// Contents of the aligned backing region captured during the last drop
+        static LAST_DROPPED: RefCell<Option<Vec<u8>>> = const { RefCell::new(None) };
+    }
+
+    /// Test-only hook: snapshot the backing allocation as Drop leaves it.
+    pub(super) fn inspect_on_drop(backing: &[u8]) {
+        LAST_DROPPED.with(|d| *d.borrow_mut() = Some(backing.to_vec()));
+    }
+
+    fn take_last_dropped() -> Vec<u8> {
+        LAST_DROPPED.with(|d| d.borrow_mut().take()).expect("drop hook not called")
+    }
+
+    #[test]
+    fn test_sensitive_buffer_zeroed_on_drop() {
+        let buf = DmaBuffer::new_sensitive(8192).unwrap();
+        buf.write_bytes(0, &[0xAB; 8192]).unwrap();
+        let before = stats().scrubs;
+        drop(buf);
+
+        let backing = take_last_dropped();
+        assert!(backing.iter().all(|&b| b == 0));
+        assert!(stats().scrubs > before);
+    }
+
+    #[test]
+    fn test_plain_buffer_not_scrubbed() {
+        let buf = DmaBuffer::new(4096).unwrap();
+        buf.write_bytes(0, &[0x5A; 16]).unwrap();
+        drop(buf);
+
+        let backing = take_last_dropped();
+        assert!(backing.windows(16).any(|w| w == [0x5A; 16]));
+    }
+
+    #[test]
+    fn test_scrub_skippable() {
+        let mut buf = DmaBuffer::new_sensitive(4096).unwrap();
+        assert!(buf.is_sensitive());
+        buf.set_sensitive(false);
+        buf.write_bytes(0, &[0x11; 16]).unwrap();
+        drop(buf);
+
+        let backing = take_last_dropped();
+        assert!(backing.windows(16).any(|w| w == [0x11; 16]));
+    }
+
+    #[test]
+    fn test_explicit_scrub() {
+        let buf = DmaBuffer::new(4096).unwrap();
+        buf.write_bytes(100, &[0xFF; 64]).unwrap();
+        buf.scrub();
+        assert!(buf.read_all().iter().all(|&b| b == 0));
+        assert!(stats().scrubbed_bytes >= 4096);
+    }
+
+    #[test]
+    fn test_firmware_buffer_is_sensitive() {
+        let path = std::env::temp_dir().join(format!("npu-fw-test-{}.bin", std::process::id()));
+        std::fs::write(&path, fw_image::build_image("test", &[0u8; 256])).unwrap();
+
+        let (buf, info) = load_firmware(path.to_str().unwrap()).unwrap();
+        assert!(buf.is_sensitive());
+        assert_eq!(info.version, "test");
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,280 @@
+//! DMA Buffer Pool — Reusable Job Buffers
+//!
+//! Every `DmaBuffer::new` on Redox is a `memory:phys_contiguous` open, an
+//! `fmap` and an address lookup, and each one carves another contiguous
+//! run out of physical memory. Per-job buffers are short-lived and come in
+//! a handful of sizes, so the pool allocates them once, in size classes,
+//! and hands them out again and again:
+//!
+//! ```text
+//!   class    4 KiB  [■■■□□□□□□□ ...]   ■ = checked out
+//!   class   64 KiB  [■□□□□□□□ ...]     □ = free, zeroed
+//!   class    1 MiB  [□□□□□□□□]
+//!   larger          → direct allocation, freed on drop
+//! ```
+//!
+//! A request takes a buffer from the smallest class that fits and still
+//! has one free. `PooledBuffer` derefs to `DmaBuffer`, reports the
+//! requested size (page-aligned, as a direct allocation would) and goes
+//! back to its class when dropped — zeroed first, so nothing one job wrote
+//! is visible to the next.
+
+use crate::dma::{DmaBuffer, DmaError};
+use crate::hw_mtl::DMA_ALIGNMENT;
+use log::{debug, info, warn};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// A class of equally sized pool buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeClass {
+    /// Bytes per buffer (rounded up to `DMA_ALIGNMENT`)
+    pub size: usize,
+    /// Buffers allocated up front
+    pub count: usize,
+}
+
+/// Classes the driver runs with: small tensors, feature maps, frames.
+pub const DEFAULT_CLASSES: &[SizeClass] = &[
+    SizeClass { size: 4 * 1024, count: 32 },
+    SizeClass { size: 64 * 1024, count: 16 },
+    SizeClass { size: 1024 * 1024, count: 8 },
+];
+
+/// Free buffers of one class.
+struct Class {
+    size: usize,
+    total: usize,
+    free: Vec<DmaBuffer>,
+}
+
+struct PoolInner {
+    /// Ascending by size
+    classes: Mutex<Vec<Class>>,
+    /// Signalled whenever a buffer is returned
+    returned: Condvar,
+}
+
+/// Pre-allocated DMA buffers handed out as `PooledBuffer`s.
+///
+/// Cloning is cheap and shares the same buffers.
+#[derive(Clone)]
+pub struct DmaPool {
+    inner: Arc<PoolInner>,
+}
+
+impl DmaPool {
+    /// Allocate every buffer of `classes` now.
+    pub fn new(classes: &[SizeClass]) -> Result<Self, DmaError> {
+        let mut sorted: Vec<SizeClass> = classes
+            .iter()
+            .map(|c| SizeClass { size: align(c.size), count: c.count })
+            .filter(|c| c.size > 0)
+            .collect();
+        sorted.sort_by_key(|c| c.size);
+
+        let mut built = Vec::with_capacity(sorted.len());
+        for class in sorted {
+            let free = (0..class.count).map(|_| DmaBuffer::new(class.size)).collect::<Result<Vec<_>, _>>()?;
+            built.push(Class { size: class.size, total: class.count, free });
+        }
+        let bytes: usize = built.iter().map(|c| c.size * c.total).sum();
+        info!("DMA pool: {} classes, {} KB pre-allocated", built.len(), bytes / 1024);
+
+        Ok(Self { inner: Arc::new(PoolInner { classes: Mutex::new(built), returned: Condvar::new() }) })
+    }
+
+    /// A pool with no classes: every request is a direct allocation.
+    pub fn unpooled() -> Self {
+        Self { inner: Arc::new(PoolInner { classes: Mutex::new(Vec::new()), returned: Condvar::new() }) }
+    }
+
+    /// Take a buffer of at least `size` bytes.
+    ///
+    /// Larger than every class: allocated directly. Otherwise fails with
+    /// `PoolExhausted` if every class that fits is checked out.
+    pub fn get(&self, size: usize) -> Result<PooledBuffer, DmaError> {
+        self.get_timeout(size, Duration::ZERO)
+    }
+
+    /// Like `get`, but wait up to `timeout` for a buffer to come back
+    /// when the classes that fit are exhausted.
+    pub fn get_timeout(&self, size: usize, timeout: Duration) -> Result<PooledBuffer, DmaError> {
+        if size == 0 {
+            return Err(DmaError::ZeroSize);
+        }
+        let deadline = Instant::now() + timeout;
+        let mut classes = self.inner.classes.lock().unwrap_or_else(|e| e.into_inner());
+
+        if classes.last().is_none_or(|c| c.size < size) {
+            drop(classes);
+            debug!("DMA pool: {} bytes is larger than every class, allocating directly", size);
+            return Ok(PooledBuffer { buf: Some(DmaBuffer::new_sensitive(size)?), pool: None, capacity: 0 });
+        }
+
+        loop {
+            if let Some(class) = classes.iter_mut().find(|c| c.size >= size && !c.free.is_empty()) {
+                let capacity = class.size;
+                let mut buf = class.free.pop().expect("class has a free buffer");
+                buf.size = align(size);
+                return Ok(PooledBuffer { buf: Some(buf), pool: Some(self.inner.clone()), capacity });
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                warn!("DMA pool exhausted for a {} byte buffer", size);
+                return Err(DmaError::PoolExhausted { size });
+            }
+            classes = self
+                .inner
+                .returned
+                .wait_timeout(classes, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// `(class size, free buffers, total buffers)` per class, smallest first.
+    pub fn usage(&self) -> Vec<(usize, usize, usize)> {
+        let classes = self.inner.classes.lock().unwrap_or_else(|e| e.into_inner());
+        classes.iter().map(|c| (c.size, c.free.len(), c.total)).collect()
+    }
+}
+
+/// A DMA buffer borrowed from a `DmaPool` (or allocated directly when too
+/// large for it). Zeroed and returned to its class on drop.
+pub struct PooledBuffer {
+    buf: Option<DmaBuffer>,
+    /// None for direct allocations
+    pool: Option<Arc<PoolInner>>,
+    /// Full size of the pool buffer
+    capacity: usize,
+}
+
+impl PooledBuffer {
+    /// Whether the buffer came from a size class (vs. a direct allocation).
+    pub fn is_pooled(&self) -> bool {
+        self.pool.is_some()
+    }
+}
+
+impl std::ops::Deref for PooledBuffer {
+    type Target = DmaBuffer;
+
+    fn deref(&self) -> &DmaBuffer {
+        self.buf.as_ref().expect("buffer present until drop")
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        let (Some(mut buf), Some(pool)) = (self.buf.take(), self.pool.take()) else {
+            // Direct allocation: freed (and scrubbed) by DmaBuffer's Drop
+            return;
+        };
+        buf.size = self.capacity;
+        buf.scrub();
+
+        let mut classes = pool.classes.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(class) = classes.iter_mut().find(|c| c.size == self.capacity) {
+            class.free.push(buf);
+        }
+        drop(classes);
+        pool.returned.notify_one();
+    }
+}
+
+/// Round `size` up to the DMA page size.
+fn align(size: usize) -> usize {
+    (size + DMA_ALIGNMENT - 1) & !(DMA_ALIGNMENT - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: usize = DMA_ALIGNMENT;
+
+    fn small_pool() -> DmaPool {
+        DmaPool::new(&[SizeClass { size: 4 * PAGE, count: 1 }, SizeClass { size: PAGE, count: 2 }]).unwrap()
+    }
+
+    #[test]
+    fn test_smallest_fitting_class_chosen() {
+        let pool = small_pool();
+        assert_eq!(pool.usage(), vec![(PAGE, 2, 2), (4 * PAGE, 1, 1)]);
+
+        let small = pool.get(100).unwrap();
+        assert!(small.is_pooled());
+        // Reports the requested size, page-aligned, like a direct allocation
+        assert_eq!(small.size, PAGE);
+        let medium = pool.get(PAGE + 1).unwrap();
+        assert_eq!(medium.size, 2 * PAGE);
+        assert_eq!(pool.usage(), vec![(PAGE, 1, 2), (4 * PAGE, 0, 1)]);
+
+        // Larger than every class: allocated directly, not counted
+        let big = pool.get(8 * PAGE).unwrap();
+        assert!(!big.is_pooled());
+        assert_eq!(big.size, 8 * PAGE);
+
+        drop((small, medium, big));
+        assert_eq!(pool.usage(), vec![(PAGE, 2, 2), (4 * PAGE, 1, 1)]);
+    }
+
+    #[test]
+    fn test_full_class_spills_into_larger_one() {
+        let pool = small_pool();
+        let _a = pool.get(PAGE).unwrap();
+        let _b = pool.get(PAGE).unwrap();
+        let c = pool.get(PAGE).unwrap();
+        assert_eq!(c.size, PAGE);
+        assert_eq!(pool.usage(), vec![(PAGE, 0, 2), (4 * PAGE, 0, 1)]);
+    }
+
+    #[test]
+    fn test_exhausted_pool_errors_or_waits() {
+        let pool = DmaPool::new(&[SizeClass { size: PAGE, count: 1 }]).unwrap();
+        let held = pool.get(PAGE).unwrap();
+        assert!(matches!(pool.get(PAGE), Err(DmaError::PoolExhausted { size: PAGE })));
+        assert!(matches!(
+            pool.get_timeout(PAGE, Duration::from_millis(20)),
+            Err(DmaError::PoolExhausted { .. })
+        ));
+
+        // A buffer returned by another thread wakes the waiter
+        let releaser = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            drop(held);
+        });
+        let got = pool.get_timeout(PAGE, Duration::from_secs(5)).unwrap();
+        assert!(got.is_pooled());
+        releaser.join().unwrap();
+    }
+
+    #[test]
+    fn test_buffers_zeroed_before_reuse() {
+        let pool = DmaPool::new(&[SizeClass { size: 2 * PAGE, count: 1 }]).unwrap();
+        let first = pool.get(2 * PAGE).unwrap();
+        let phys = first.phys_addr;
+        first.write_bytes(0, &[0xAB; 2 * PAGE]).unwrap();
+        drop(first);
+
+        // Same memory, smaller request: nothing of the last job is left,
+        // including past the end of what this job asked for
+        let second = pool.get(PAGE).unwrap();
+        assert_eq!(second.phys_addr, phys);
+        assert_eq!(second.size, PAGE);
+        drop(second);
+        let whole = pool.get(2 * PAGE).unwrap();
+        assert!(whole.read_all().iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_unpooled_allocates_directly() {
+        let pool = DmaPool::unpooled();
+        let buf = pool.get(10).unwrap();
+        assert!(!buf.is_pooled());
+        assert!(buf.is_sensitive());
+        assert!(matches!(pool.get(0), Err(DmaError::ZeroSize)));
+    }
+}
@@ -0,0 +1,462 @@
+//! Persistent NPU Event Log — Postmortem Lifecycle Records
+//!
+//! Records boot attempts, state transitions, recoveries, queue overflows,
+//! scheme client activity and power transitions into a compact ring file,
+//! so that after an overnight crash there is something to look at besides
+//! whatever scrolled past in the console.
+//!
+//! File layout:
+//!   - The file is an array of fixed-size 32-byte records
+//!   - Slot = sequence number modulo capacity (ring behaviour)
+//!   - Each record carries a magic, a sequence number and a CRC32
+//!
+//! Records are self-delimiting: a torn or truncated write only invalidates
+//! the record it belongs to, and replay simply skips it.
+
+use log::{info, warn};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default event log location.
+pub const DEFAULT_EVENT_LOG_PATH: &str = "/var/log/eva/npu-events.bin";
+
+/// Default ring capacity (4 MB).
+pub const DEFAULT_EVENT_LOG_BYTES: usize = 4 * 1024 * 1024;
+
+/// Size of one on-disk record.
+pub const RECORD_SIZE: usize = 32;
+
+/// Record magic ("NE" little-endian).
+const RECORD_MAGIC: u16 = 0x454E;
+
+/// Kind of lifecycle event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(serde::Deserialize), serde(try_from = "String"))]
+#[repr(u16)]
+pub enum EventKind {
+    /// Boot attempt. code = 0 ok / 1 ambiguous / 2 failed, value = duration (ms)
+    BootAttempt = 1,
+    /// StatusMonitor state change. code = raw FW_STATUS
+    StateTransition = 2,
+    /// Watchdog recovery. code = 0 ok / 1 failed, value = attempt number
+    WatchdogRecovery = 3,
+    /// Command queue overflow. value = job id rejected
+    QueueOverflow = 4,
+    /// `npu:` scheme client opened a handle. code = uid, value = handle id
+    ClientConnect = 5,
+    /// `npu:` scheme client closed a handle. code = uid, value = handle id
+    ClientDisconnect = 6,
+    /// Power state transition. code = new power state (0 = D0, 3 = D0i3)
+    PowerTransition = 7,
+}
+
+impl EventKind {
+    fn from_u16(v: u16) -> Option<Self> {
+        match v {
+            1 => Some(EventKind::BootAttempt),
+            2 => Some(EventKind::StateTransition),
+            3 => Some(EventKind::WatchdogRecovery),
+            4 => Some(EventKind::QueueOverflow),
+            5 => Some(EventKind::ClientConnect),
+            6 => Some(EventKind::ClientDisconnect),
+            7 => Some(EventKind::PowerTransition),
+            _ => None,
+        }
+    }
+
+    /// Short name used in reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::BootAttempt => "boot",
+            EventKind::StateTransition => "state",
+            EventKind::WatchdogRecovery => "recovery",
+            EventKind::QueueOverflow => "queue_overflow",
+            EventKind::ClientConnect => "client_connect",
+            EventKind::ClientDisconnect => "client_disconnect",
+            EventKind::PowerTransition => "power",
+        }
+    }
+}
+
+/// From `name()`, for reading reports back in tests.
+#[cfg(test)]
+impl TryFrom<String> for EventKind {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, String> {
+        (1..=7).filter_map(EventKind::from_u16).find(|k| k.name() == name).ok_or(format!("unknown event kind {}", name))
+    }
+}
+
+/// A single decoded event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+pub struct NpuEvent {
+    /// Monotonic sequence number (ordering across ring wrap)
+    pub seq: u32,
+    /// Wall clock time, milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub kind: EventKind,
+    pub code: u32,
+    pub value: u64,
+}
+
+impl NpuEvent {
+    /// Serialize into a fixed-size record.
+    ///
+    /// Layout: magic u16 | kind u16 | seq u32 | timestamp u64 | code u32 |
+    /// value u64 | crc32 u32 (over the first 28 bytes).
+    fn encode(&self) -> [u8; RECORD_SIZE] {
+        let mut rec = [0u8; RECORD_SIZE];
+        rec[0..2].copy_from_slice(&RECORD_MAGIC.to_le_bytes());
+        rec[2..4].copy_from_slice(&(self.kind as u16).to_le_bytes());
+        rec[4..8].copy_from_slice(&self.seq.to_le_bytes());
+        rec[8..16].copy_from_slice(&self.timestamp_ms.to_le_bytes());
+        rec[16..20].copy_from_slice(&self.code.to_le_bytes());
+        rec[20..28].copy_from_slice(&self.value.to_le_bytes());
+        let crc = crc32(&rec[0..28]);
+        rec[28..32].copy_from_slice(&crc.to_le_bytes());
+        rec
+    }
+
+    /// Decode a record, returning `None` for empty, torn or foreign slots.
+    fn decode(rec: &[u8]) -> Option<Self> {
+        if rec.len() < RECORD_SIZE {
+            return None;
+        }
+        let magic = u16::from_le_bytes([rec[0], rec[1]]);
+        if magic != RECORD_MAGIC {
+            return None;
+        }
+        let crc = u32::from_le_bytes([rec[28], rec[29], rec[30], rec[31]]);
+        if crc != crc32(&rec[0..28]) {
+            return None;
+        }
+        let kind = EventKind::from_u16(u16::from_le_bytes([rec[2], rec[3]]))?;
+        Some(Self {
+            seq: u32::from_le_bytes([rec[4], rec[5], rec[6], rec[7]]),
+            timestamp_ms: u64::from_le_bytes(rec[8..16].try_into().ok()?),
+            kind,
+            code: u32::from_le_bytes([rec[16], rec[17], rec[18], rec[19]]),
+            value: u64::from_le_bytes(rec[20..28].try_into().ok()?),
+        })
+    }
+
+    /// One-line human-readable description.
+    pub fn describe(&self) -> String {
+        match self.kind {
+            EventKind::BootAttempt => {
+                let result = match self.code {
+                    0 => "ready",
+                    1 => "ambiguous",
+                    _ => "FAILED",
+                };
+                format!("boot attempt: {} in {} ms", result, self.value)
+            }
+            EventKind::StateTransition => {
+                format!("state → {} (raw={:#010x})", crate::hw_mtl::decode_fw_status(self.code), self.code)
+            }
+            EventKind::WatchdogRecovery => format!(
+                "watchdog recovery attempt {}: {}",
+                self.value,
+                if self.code == 0 { "ok" } else { "FAILED" }
+            ),
+            EventKind::QueueOverflow => format!("command queue full, rejected job #{}", self.value),
+            EventKind::ClientConnect => format!("client uid={} opened handle {}", self.code, self.value),
+            EventKind::ClientDisconnect => format!("client uid={} closed handle {}", self.code, self.value),
+            EventKind::PowerTransition => format!("power → D{}", if self.code == 0 { "0".to_string() } else { format!("0i{}", self.code) }),
+        }
+    }
+
+    /// Render as a JSON object (no serde dependency in the driver).
+    pub fn to_json(self) -> String {
+        format!(
+            "{{\"seq\":{},\"timestamp_ms\":{},\"kind\":\"{}\",\"code\":{},\"value\":{}}}",
+            self.seq,
+            self.timestamp_ms,
+            self.kind.name(),
+            self.code,
+            self.value
+        )
+    }
+}
+
+struct LogInner {
+    file: File,
+    next_seq: u32,
+}
+
+/// Append-only ring file of NPU events.
+///
+/// `record` takes `&self` so the log can be shared by the status monitor,
+/// the boot path and the scheme handler at the same time.
+pub struct EventLog {
+    path: PathBuf,
+    capacity: u32,
+    inner: Mutex<LogInner>,
+}
+
+impl EventLog {
+    /// Open (or create) an event log capped at `max_bytes`.
+    pub fn open<P: AsRef<Path>>(path: P, max_bytes: usize) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        let capacity = (max_bytes / RECORD_SIZE).max(1) as u32;
+
+        // Resume after the highest valid sequence number already on disk
+        let next_seq = read_events(&path)
+            .map(|events| events.last().map(|e| e.seq.wrapping_add(1)).unwrap_or(0))
+            .unwrap_or(0);
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&path)?;
+
+        info!(
+            "NPU event log: {} ({} slots, next seq {})",
+            path.display(),
+            capacity,
+            next_seq
+        );
+
+        Ok(Self {
+            path,
+            capacity,
+            inner: Mutex::new(LogInner { file, next_seq }),
+        })
+    }
+
+    /// Open the log at `NPU_EVENT_LOG` or the default path.
+    ///
+    /// Returns `None` (after a warning) when the file can't be opened, so a
+    /// read-only or missing /var/log never prevents the driver from running.
+    pub fn open_default() -> Option<Self> {
+        let path = std::env::var("NPU_EVENT_LOG").unwrap_or_else(|_| DEFAULT_EVENT_LOG_PATH.to_string());
+        match Self::open(&path, DEFAULT_EVENT_LOG_BYTES) {
+            Ok(log) => Some(log),
+            Err(e) => {
+                warn!("⚠️  NPU event log disabled ({}): {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Append an event. Failures are logged, never propagated.
+    pub fn record(&self, kind: EventKind, code: u32, value: u64) {
+        let mut inner = match self.inner.lock() {
+            Ok(inner) => inner,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        let event = NpuEvent {
+            seq: inner.next_seq,
+            timestamp_ms: now_ms(),
+            kind,
+            code,
+            value,
+        };
+
+        let offset = (event.seq % self.capacity) as u64 * RECORD_SIZE as u64;
+        let result = inner
+            .file
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| inner.file.write_all(&event.encode()))
+            .and_then(|_| inner.file.sync_data());
+
+        match result {
+            Ok(()) => inner.next_seq = inner.next_seq.wrapping_add(1),
+            Err(e) => warn!("Failed to write NPU event ({}): {}", kind.name(), e),
+        }
+    }
+
+    /// Read back all valid events in sequence order.
+    pub fn events(&self) -> std::io::Result<Vec<NpuEvent>> {
+        read_events(&self.path)
+    }
+}
+
+/// Replay an event log file, skipping torn/truncated records.
+pub fn read_events<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<NpuEvent>> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+
+    let mut events: Vec<NpuEvent> = data
+        .chunks(RECORD_SIZE)
+        .filter_map(NpuEvent::decode)
+        .collect();
+    events.sort_by_key(|e| e.seq);
+    Ok(events)
+}
+
+/// Parse a `--since` argument: relative (`30s`, `15m`, `2h`, `7d`) or an
+/// absolute Unix timestamp in seconds. Returns epoch milliseconds.
+pub fn parse_since(arg: &str) -> Option<u64> {
+    let arg = arg.trim();
+    let unit = match arg.chars().last()? {
+        's' => Some(1),
+        'm' => Some(60),
+        'h' => Some(3600),
+        'd' => Some(86400),
+        _ => None,
+    };
+
+    match unit {
+        Some(secs) => {
+            let n: u64 = arg[..arg.len() - 1].parse().ok()?;
+            Some(now_ms().saturating_sub(n * secs * 1000))
+        }
+        None => arg.parse::<u64>().ok().map(|s| s * 1000),
+    }
+}
+
+/// Pretty-print events (optionally only those at or after `since_ms`).
+pub fn print_events(events: &[NpuEvent], since_ms: Option<u64>) {
+    let since = since_ms.unwrap_or(0);
+    let shown: Vec<&NpuEvent> = events.iter().filter(|e| e.timestamp_ms >= since).collect();
+
+    println!("📜 NPU Event Log ({} events)", shown.len());
+    for e in shown {
+        let secs = e.timestamp_ms / 1000;
+        println!(
+            "  #{:<8} {}.{:03}  {:<18} {}",
+            e.seq,
+            secs,
+            e.timestamp_ms % 1000,
+            e.kind.name(),
+            e.describe()
+        );
+    }
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// CRC-32 (IEEE, reflected), bitwise — records are tiny so no table needed.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_log(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("npu_events_{}_{}.bin", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_crc32_known_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_record_roundtrip() {
+        let path = temp_log("roundtrip");
+        let log = EventLog::open(&path, 4096).unwrap();
+        log.record(EventKind::BootAttempt, 0, 1234);
+        log.record(EventKind::StateTransition, 0xF00D, 0);
+
+        let events = log.events().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, EventKind::BootAttempt);
+        assert_eq!(events[0].value, 1234);
+        assert_eq!(events[1].code, 0xF00D);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_ring_wraps_and_keeps_newest() {
+        let path = temp_log("ring");
+        let log = EventLog::open(&path, RECORD_SIZE * 4).unwrap();
+        for i in 0..10 {
+            log.record(EventKind::QueueOverflow, 0, i);
+        }
+
+        let events = log.events().unwrap();
+        let values: Vec<u64> = events.iter().map(|e| e.value).collect();
+        assert_eq!(values, vec![6, 7, 8, 9]);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), (RECORD_SIZE * 4) as u64);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_replay_truncated_file() {
+        let path = temp_log("truncated");
+        {
+            let log = EventLog::open(&path, 4096).unwrap();
+            for i in 0..5 {
+                log.record(EventKind::ClientConnect, 0, i);
+            }
+        }
+
+        // Simulate a crash mid-write: cut the last record in half
+        let data = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &data[..RECORD_SIZE * 4 + RECORD_SIZE / 2]).unwrap();
+
+        let events = read_events(&path).unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events.last().unwrap().value, 3);
+
+        // Reopening resumes after the last intact record
+        let log = EventLog::open(&path, 4096).unwrap();
+        log.record(EventKind::ClientDisconnect, 0, 99);
+        let events = log.events().unwrap();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events.last().unwrap().seq, 4);
+        assert_eq!(events.last().unwrap().kind, EventKind::ClientDisconnect);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_corrupted_record_is_skipped() {
+        let path = temp_log("corrupt");
+        {
+            let log = EventLog::open(&path, 4096).unwrap();
+            for i in 0..3 {
+                log.record(EventKind::PowerTransition, 0, i);
+            }
+        }
+
+        let mut data = std::fs::read(&path).unwrap();
+        data[RECORD_SIZE + 20] ^= 0xFF; // flip a payload byte of record #1
+        std::fs::write(&path, &data).unwrap();
+
+        let events = read_events(&path).unwrap();
+        let seqs: Vec<u32> = events.iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, vec![0, 2]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("1700000000"), Some(1_700_000_000_000));
+        let an_hour_ago = parse_since("1h").unwrap();
+        assert!(now_ms() - an_hour_ago >= 3_600_000);
+        assert_eq!(parse_since("abc"), None);
+    }
+}
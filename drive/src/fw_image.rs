@@ -0,0 +1,385 @@
+//! Firmware Image Parser (`--firmware-info`)
+//!
+//! Reads the header of an Intel VPU firmware file (`vpu_40xx_v*.bin`) and
+//! checks it before anything is copied to DMA memory. The layout follows
+//! the Linux ivpu driver (`struct vpu_firmware_header` in vpu_boot_api.h,
+//! checks from `ivpu_fw_parse()`):
+//!
+//! ```text
+//! 0x0000  header (packed, little-endian), padded to 4 KiB
+//! 0x1000  firmware version string, padded to 4 KiB
+//! 0x2000  image, `image_size` bytes, loaded at `image_load_address`
+//! ```
+//!
+//! A truncated file or a header whose sections point past the end of it is
+//! rejected here with the offending numbers, instead of being booted and
+//! failing later as 0x0BAD.
+
+use std::fmt;
+
+/// Header area at the start of the file
+pub const FW_HEADER_SIZE: usize = 0x1000;
+
+/// Firmware version string area after the header
+pub const FW_VERSION_AREA_SIZE: usize = 0x1000;
+
+/// File offset of the firmware image
+pub const FW_IMAGE_OFFSET: usize = FW_HEADER_SIZE + FW_VERSION_AREA_SIZE;
+
+/// Header format this parser understands (`VPU_FW_HEADER_VERSION`)
+pub const FW_HEADER_VERSION: u32 = 0x1;
+
+/// Number of API version words (`VPU_FW_API_VER_NUM`)
+pub const FW_API_VERSIONS: usize = 16;
+
+// Field offsets in the packed header
+const OFF_HEADER_VERSION: usize = 0;
+const OFF_IMAGE_FORMAT: usize = 4;
+const OFF_LOAD_ADDRESS: usize = 8;
+const OFF_IMAGE_SIZE: usize = 16;
+const OFF_ENTRY_POINT: usize = 20;
+const OFF_VPU_VERSION: usize = 28;
+const VPU_VERSION_SIZE: usize = 32;
+const OFF_COMPRESSION: usize = 60;
+const OFF_VERSION_LOAD_ADDRESS: usize = 64;
+const OFF_VERSION_SIZE: usize = 72;
+const OFF_BOOT_PARAMS_ADDRESS: usize = 76;
+const OFF_API_VERSION: usize = 84;
+const OFF_RUNTIME_SIZE: usize = 148;
+const OFF_SHAVE_NN_SIZE: usize = 152;
+const OFF_RO_SECTION_START: usize = 188;
+const OFF_RO_SECTION_SIZE: usize = 196;
+
+/// One region of the image described by the header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: &'static str,
+    /// Where the section starts in the file, if it is stored there
+    pub file_offset: Option<usize>,
+    /// NPU address the section is loaded at (0 if not loaded)
+    pub address: u64,
+    pub size: u64,
+}
+
+/// A firmware header that passed validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareInfo {
+    pub header_version: u32,
+    pub image_format: u32,
+    /// Firmware build string from the version area
+    pub version: String,
+    /// Hardware the image was built for, e.g. "40xx"
+    pub vpu_version: String,
+    /// API versions the firmware implements, (major, minor) per interface
+    pub api_versions: Vec<(u16, u16)>,
+    pub load_address: u64,
+    pub entry_point: u64,
+    pub image_size: u32,
+    /// Memory the firmware runs in, starting at `load_address`
+    pub runtime_size: u32,
+    pub shave_nn_size: u32,
+    pub boot_params_address: u64,
+    pub sections: Vec<Section>,
+    /// Length of the whole file
+    pub file_size: usize,
+}
+
+/// Why a firmware file was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FwImageError {
+    /// The file ends before `needed` bytes
+    Truncated { len: usize, needed: usize },
+    UnsupportedHeaderVersion { version: u32 },
+    /// Compressed images would have to be unpacked before DMA
+    Compressed { kind: u32 },
+    /// A section starts or ends outside the region that must contain it
+    BadSection { name: &'static str, start: u64, end: u64, limit_start: u64, limit_end: u64 },
+    /// The entry point is outside the runtime region or does not fit
+    /// `HOST_SS_ENTRY_POINT`
+    BadEntryPoint { entry_point: u64, runtime_start: u64, runtime_end: u64 },
+}
+
+impl fmt::Display for FwImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated { len, needed } => {
+                write!(f, "Firmware file truncated: {} bytes, expected at least {}", len, needed)
+            }
+            Self::UnsupportedHeaderVersion { version } => {
+                write!(f, "Unsupported firmware header version {} (expected {})", version, FW_HEADER_VERSION)
+            }
+            Self::Compressed { kind } => write!(f, "Compressed firmware (type {}) is not supported", kind),
+            Self::BadSection { name, start, end, limit_start, limit_end } => write!(
+                f,
+                "Firmware {} section {:#x}..{:#x} lies outside {:#x}..{:#x}",
+                name, start, end, limit_start, limit_end
+            ),
+            Self::BadEntryPoint { entry_point, runtime_start, runtime_end } => write!(
+                f,
+                "Firmware entry point {:#x} is outside the 32-bit runtime region {:#x}..{:#x}",
+                entry_point, runtime_start, runtime_end
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FwImageError {}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+/// Text up to the first NUL
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+/// `start..start+size` must lie within `limit_start..limit_end`.
+fn check_within(name: &'static str, start: u64, size: u64, limit_start: u64, limit_end: u64) -> Result<(), FwImageError> {
+    let end = start.saturating_add(size);
+    if start < limit_start || end > limit_end {
+        return Err(FwImageError::BadSection { name, start, end, limit_start, limit_end });
+    }
+    Ok(())
+}
+
+/// Parse and validate a whole firmware file.
+pub fn parse(data: &[u8]) -> Result<FirmwareInfo, FwImageError> {
+    // The image follows both fixed-size areas, so anything shorter has none
+    if data.len() <= FW_IMAGE_OFFSET {
+        return Err(FwImageError::Truncated { len: data.len(), needed: FW_IMAGE_OFFSET + 1 });
+    }
+
+    let header_version = read_u32(data, OFF_HEADER_VERSION);
+    if header_version != FW_HEADER_VERSION {
+        return Err(FwImageError::UnsupportedHeaderVersion { version: header_version });
+    }
+    let compression = read_u32(data, OFF_COMPRESSION);
+    if compression != 0 {
+        return Err(FwImageError::Compressed { kind: compression });
+    }
+
+    let load_address = read_u64(data, OFF_LOAD_ADDRESS);
+    let image_size = read_u32(data, OFF_IMAGE_SIZE);
+    let entry_point = read_u64(data, OFF_ENTRY_POINT);
+    let version_size = read_u32(data, OFF_VERSION_SIZE);
+    let runtime_size = read_u32(data, OFF_RUNTIME_SIZE);
+    let ro_start = read_u64(data, OFF_RO_SECTION_START);
+    let ro_size = read_u32(data, OFF_RO_SECTION_SIZE);
+
+    // Sections stored in the file
+    let image_file_end = FW_IMAGE_OFFSET + image_size as usize;
+    if image_file_end > data.len() {
+        return Err(FwImageError::Truncated { len: data.len(), needed: image_file_end });
+    }
+    check_within("version", FW_HEADER_SIZE as u64, version_size as u64, FW_HEADER_SIZE as u64, FW_IMAGE_OFFSET as u64)?;
+
+    // Sections in NPU memory: the image runs in place, so it and its
+    // read-only part must fit the runtime region, as must the entry point
+    let runtime_end = load_address.saturating_add(runtime_size as u64);
+    check_within("image", load_address, image_size as u64, load_address, runtime_end)?;
+    if ro_size > 0 {
+        check_within("read-only", ro_start, ro_size as u64, load_address, load_address.saturating_add(image_size as u64))?;
+    }
+    if entry_point < load_address || entry_point >= runtime_end || entry_point > u32::MAX as u64 {
+        return Err(FwImageError::BadEntryPoint { entry_point, runtime_start: load_address, runtime_end });
+    }
+
+    let mut sections = vec![
+        Section {
+            name: "version",
+            file_offset: Some(FW_HEADER_SIZE),
+            address: read_u64(data, OFF_VERSION_LOAD_ADDRESS),
+            size: version_size as u64,
+        },
+        Section { name: "image", file_offset: Some(FW_IMAGE_OFFSET), address: load_address, size: image_size as u64 },
+    ];
+    if ro_size > 0 {
+        sections.push(Section {
+            name: "read-only",
+            file_offset: Some(FW_IMAGE_OFFSET + (ro_start - load_address) as usize),
+            address: ro_start,
+            size: ro_size as u64,
+        });
+    }
+
+    let api_versions = (0..FW_API_VERSIONS)
+        .map(|i| read_u32(data, OFF_API_VERSION + 4 * i))
+        .filter(|&v| v != 0)
+        .map(|v| ((v >> 16) as u16, v as u16))
+        .collect();
+
+    Ok(FirmwareInfo {
+        header_version,
+        image_format: read_u32(data, OFF_IMAGE_FORMAT),
+        version: c_string(&data[FW_HEADER_SIZE..FW_HEADER_SIZE + version_size as usize]),
+        vpu_version: c_string(&data[OFF_VPU_VERSION..OFF_VPU_VERSION + VPU_VERSION_SIZE]),
+        api_versions,
+        load_address,
+        entry_point,
+        image_size,
+        runtime_size,
+        shave_nn_size: read_u32(data, OFF_SHAVE_NN_SIZE),
+        boot_params_address: read_u64(data, OFF_BOOT_PARAMS_ADDRESS),
+        sections,
+        file_size: data.len(),
+    })
+}
+
+impl FirmwareInfo {
+    /// Entry point as written to `HOST_SS_ENTRY_POINT` (checked to fit by `parse`).
+    pub fn entry_point_reg(&self) -> u32 {
+        self.entry_point as u32
+    }
+}
+
+impl fmt::Display for FirmwareInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_unknown = |s: &str| if s.is_empty() { "(none)".to_string() } else { s.to_string() };
+        writeln!(f, "Firmware    : {}", or_unknown(&self.version))?;
+        writeln!(f, "VPU version : {}", or_unknown(&self.vpu_version))?;
+        writeln!(f, "Header      : v{}, image format {}", self.header_version, self.image_format)?;
+        let apis: Vec<String> = self.api_versions.iter().map(|(major, minor)| format!("{}.{}", major, minor)).collect();
+        writeln!(f, "API         : {}", if apis.is_empty() { "(none)".to_string() } else { apis.join(" ") })?;
+        writeln!(f, "Load address: {:#x}", self.load_address)?;
+        writeln!(f, "Entry point : {:#x}", self.entry_point)?;
+        writeln!(f, "Runtime     : {} KB (SHAVE NN {} KB)", self.runtime_size / 1024, self.shave_nn_size / 1024)?;
+        writeln!(f, "Boot params : {:#x}", self.boot_params_address)?;
+        writeln!(f, "File size   : {} bytes", self.file_size)?;
+        writeln!(f, "{:<10} {:>10} {:>12} {:>10}", "SECTION", "FILE OFF", "ADDRESS", "SIZE")?;
+        for section in &self.sections {
+            let offset = section.file_offset.map_or("-".to_string(), |o| format!("{:#x}", o));
+            writeln!(f, "{:<10} {:>10} {:>#12x} {:>10}", section.name, offset, section.address, section.size)?;
+        }
+        Ok(())
+    }
+}
+
+/// NPU address the mock image is linked at
+#[cfg(any(test, not(target_os = "redox")))]
+pub const MOCK_LOAD_ADDRESS: u64 = 0x8480_0000;
+
+/// Build a valid firmware file around `image`, entered at its first byte,
+/// for mock mode and tests.
+#[cfg(any(test, not(target_os = "redox")))]
+pub fn build_image(version: &str, image: &[u8]) -> Vec<u8> {
+    let mut data = vec![0u8; FW_IMAGE_OFFSET];
+    let mut put = |offset: usize, bytes: &[u8]| data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    put(OFF_HEADER_VERSION, &FW_HEADER_VERSION.to_le_bytes());
+    put(OFF_LOAD_ADDRESS, &MOCK_LOAD_ADDRESS.to_le_bytes());
+    put(OFF_IMAGE_SIZE, &(image.len() as u32).to_le_bytes());
+    put(OFF_ENTRY_POINT, &MOCK_LOAD_ADDRESS.to_le_bytes());
+    put(OFF_VPU_VERSION, b"40xx");
+    put(OFF_VERSION_SIZE, &(version.len() as u32 + 1).to_le_bytes());
+    put(OFF_API_VERSION, &0x0003_0016u32.to_le_bytes());
+    // Room for the image plus a little runtime memory after it
+    put(OFF_RUNTIME_SIZE, &(image.len() as u32 + 0x1_0000).to_le_bytes());
+    put(FW_HEADER_SIZE, version.as_bytes());
+    data.extend_from_slice(image);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image() -> Vec<u8> {
+        build_image("20240611_MTL_CI_RK", &[0x5A; 0x3000])
+    }
+
+    fn set_u32(data: &mut [u8], offset: usize, value: u32) {
+        data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn set_u64(data: &mut [u8], offset: usize, value: u64) {
+        data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn test_parses_built_image() {
+        let info = parse(&image()).unwrap();
+        assert_eq!(info.version, "20240611_MTL_CI_RK");
+        assert_eq!(info.vpu_version, "40xx");
+        assert_eq!(info.api_versions, vec![(3, 0x16)]);
+        assert_eq!(info.entry_point, MOCK_LOAD_ADDRESS);
+        assert_eq!(info.entry_point_reg(), 0x8480_0000);
+        assert_eq!(info.image_size, 0x3000);
+        assert_eq!(info.sections[1], Section { name: "image", file_offset: Some(0x2000), address: MOCK_LOAD_ADDRESS, size: 0x3000 });
+
+        let report = info.to_string();
+        assert!(report.contains("Entry point : 0x84800000"), "{}", report);
+        assert!(report.contains("API         : 3.22"), "{}", report);
+    }
+
+    #[test]
+    fn test_rejects_truncated_files() {
+        let full = image();
+        // Cut inside the header areas
+        assert_eq!(parse(&full[..64]).unwrap_err(), FwImageError::Truncated { len: 64, needed: FW_IMAGE_OFFSET + 1 });
+        // Cut inside the image
+        let cut = FW_IMAGE_OFFSET + 0x1000;
+        assert_eq!(parse(&full[..cut]).unwrap_err(), FwImageError::Truncated { len: cut, needed: FW_IMAGE_OFFSET + 0x3000 });
+    }
+
+    #[test]
+    fn test_rejects_old_magic_and_compression() {
+        let mut legacy = vec![0u8; FW_IMAGE_OFFSET + 16];
+        legacy[0..4].copy_from_slice(b"VPU!");
+        assert!(matches!(parse(&legacy), Err(FwImageError::UnsupportedHeaderVersion { version: 0x2155_5056 })));
+
+        let mut data = image();
+        set_u32(&mut data, OFF_COMPRESSION, 2);
+        assert_eq!(parse(&data).unwrap_err(), FwImageError::Compressed { kind: 2 });
+    }
+
+    #[test]
+    fn test_rejects_bad_section_offsets() {
+        // Version string spilling into the image area
+        let mut data = image();
+        set_u32(&mut data, OFF_VERSION_SIZE, FW_VERSION_AREA_SIZE as u32 + 1);
+        assert!(matches!(parse(&data), Err(FwImageError::BadSection { name: "version", .. })));
+
+        // Image larger than the memory it runs in
+        let mut data = image();
+        set_u32(&mut data, OFF_RUNTIME_SIZE, 0x1000);
+        assert_eq!(
+            parse(&data).unwrap_err(),
+            FwImageError::BadSection {
+                name: "image",
+                start: MOCK_LOAD_ADDRESS,
+                end: MOCK_LOAD_ADDRESS + 0x3000,
+                limit_start: MOCK_LOAD_ADDRESS,
+                limit_end: MOCK_LOAD_ADDRESS + 0x1000,
+            }
+        );
+
+        // Read-only section past the end of the image
+        let mut data = image();
+        set_u64(&mut data, OFF_RO_SECTION_START, MOCK_LOAD_ADDRESS + 0x2000);
+        set_u32(&mut data, OFF_RO_SECTION_SIZE, 0x2000);
+        assert!(matches!(parse(&data), Err(FwImageError::BadSection { name: "read-only", .. })));
+
+        // ...and inside it is listed with its file offset
+        set_u32(&mut data, OFF_RO_SECTION_SIZE, 0x1000);
+        let info = parse(&data).unwrap();
+        assert_eq!(info.sections[2].file_offset, Some(FW_IMAGE_OFFSET + 0x2000));
+    }
+
+    #[test]
+    fn test_rejects_entry_point_outside_runtime() {
+        let mut data = image();
+        set_u64(&mut data, OFF_ENTRY_POINT, MOCK_LOAD_ADDRESS - 4);
+        assert!(matches!(parse(&data), Err(FwImageError::BadEntryPoint { .. })));
+
+        // In range, but too wide for the 32-bit register
+        let mut data = image();
+        set_u64(&mut data, OFF_LOAD_ADDRESS, 0x1_0000_0000);
+        set_u64(&mut data, OFF_ENTRY_POINT, 0x1_0000_0000);
+        assert!(matches!(parse(&data), Err(FwImageError::BadEntryPoint { entry_point: 0x1_0000_0000, .. })));
+    }
+}
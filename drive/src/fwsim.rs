@@ -0,0 +1,564 @@
+//! Firmware Simulator (host-side)
+//!
+//! Stands in for the NPU firmware so job handling can be exercised without
+//! hardware. FwSim owns a zeroed fake BAR0; the driver talks to it through
+//! a normal `MmioRegion`. `service()` plays the firmware side of one
+//! doorbell: it takes the descriptor the driver just queued, decides its
+//! fate and posts a completion in the queue's completion ring (drained by
+//! `CommandQueue::poll_completions`) and in the device→host IPC registers
+//! (read by `inference::read_completion`).
+
+use crate::hw_mtl::*;
+use crate::inference::CommandQueue;
+use crate::mmio::MmioRegion;
+use log::debug;
+
+/// Fake BAR0 size (matches the mock PCI BAR)
+const SIM_BAR_SIZE: usize = 1024 * 1024;
+
+/// Simulated NPU firmware.
+pub struct FwSim {
+    /// Backing store for the fake BAR0; must outlive `mmio`
+    _bar: Box<[u32]>,
+    mmio: MmioRegion,
+    /// Register layout of the simulated generation
+    regs: &'static RegisterMap,
+    /// Jobs needing more than this many bytes fail with OUT_OF_RESOURCES
+    mem_budget: Option<usize>,
+    /// Successful jobs copy their input into their output
+    echo: bool,
+}
+
+impl FwSim {
+    /// Create a Meteor Lake simulator with powered-on, ready firmware and
+    /// no memory limit.
+    pub fn new() -> Self {
+        Self::for_device(PCI_DEVICE_MTL_NPU)
+    }
+
+    /// Like `new`, with the register map of `device_id`'s generation.
+    /// Panics for a generation without one.
+    pub fn for_device(device_id: u16) -> Self {
+        let regs = RegisterMap::for_device(device_id).expect("simulated NPU generation has a register map");
+        let mut bar = vec![0u32; SIM_BAR_SIZE / 4].into_boxed_slice();
+        // SAFETY: the boxed slice is never resized or moved out of its heap
+        // allocation, and is dropped after `mmio` (declaration order).
+        let mut mmio = unsafe { MmioRegion::new(bar.as_mut_ptr() as *mut u8, SIM_BAR_SIZE) };
+        mmio.set_register_map(regs);
+
+        mmio.write32(regs.vpu_status, 0x0000_0001);
+        mmio.write32(regs.fw_status, FW_STATUS_READY);
+
+        Self { _bar: bar, mmio, regs, mem_budget: None, echo: false }
+    }
+
+    /// MMIO view of the fake BAR0, for the driver side.
+    pub fn mmio(&self) -> &MmioRegion {
+        &self.mmio
+    }
+
+    /// Simulate a part with only the tiles in `enabled` (bit per tile) out
+    /// of `max_tiles`; a full mask leaves the fuse unprogrammed.
+    pub fn set_tiles(&mut self, enabled: u32, max_tiles: u8) {
+        let full = (1u32 << max_tiles) - 1;
+        let fuse = if enabled & full == full {
+            0
+        } else {
+            TILE_FUSE_VALID | ((full & !enabled) << TILE_FUSE_CONFIG_SHIFT)
+        };
+        self.mmio.write32(self.regs.tile_fuse, fuse);
+    }
+
+    /// Tile config the driver sent in its workpoint request, if any.
+    pub fn requested_tiles(&self) -> Option<u32> {
+        if self.mmio.read32(self.regs.wp_req_cmd) & WP_REQ_CMD_SEND == 0 {
+            return None;
+        }
+        Some(self.mmio.read32(self.regs.wp_req_payload1))
+    }
+
+    /// Reject jobs larger than `bytes` (`None` = unlimited).
+    pub fn set_mem_budget(&mut self, bytes: Option<usize>) {
+        self.mem_budget = bytes;
+    }
+
+    /// Make successful jobs echo their input into the output buffer, so
+    /// tests can check data made it through DMA memory both ways.
+    pub fn set_echo(&mut self, echo: bool) {
+        self.echo = echo;
+    }
+
+    /// Firmware crash: FW_STATUS reads DEAD until `restart()`.
+    pub fn crash(&self) {
+        self.mmio.write32(self.regs.fw_status, FW_STATUS_DEAD);
+    }
+
+    /// Let the next boot attempt find the firmware READY again.
+    pub fn restart(&self) {
+        self.mmio.write32(self.regs.fw_status, FW_STATUS_READY);
+    }
+
+    /// Process the job behind a rung host→device doorbell.
+    ///
+    /// Returns the posted `(job_id, status)`, or `None` if the doorbell
+    /// was not rung.
+    pub fn service(&self, queue: &CommandQueue) -> Option<(u32, u32)> {
+        if self.mmio.read32(self.regs.h2d_doorbell) & IPC_DRBL_TRIGGER == 0 {
+            return None;
+        }
+        self.mmio.write32(self.regs.h2d_doorbell, 0);
+
+        let cmd = queue.last_descriptor()?;
+        let job_id = cmd.job_id;
+        let status = match self.mem_budget {
+            Some(budget) if cmd.total_size() > budget => JOB_STATUS_OUT_OF_RESOURCES,
+            _ => JOB_STATUS_SUCCESS,
+        };
+        debug!("FwSim: job #{} needs {} bytes -> {}", job_id, cmd.total_size(), decode_job_status(status));
+
+        if self.echo && status == JOB_STATUS_SUCCESS {
+            let (input_size, output_size) = (cmd.input_size, cmd.output_size);
+            let input = ((cmd.input_addr_hi as u64) << 32 | cmd.input_addr_lo as u64) as *const u8;
+            let output = ((cmd.output_addr_hi as u64) << 32 | cmd.output_addr_lo as u64) as *mut u8;
+            // SAFETY: mock DMA buffers have phys == virt, and the driver keeps
+            // a submitted job's buffers alive until its completion is read.
+            unsafe { std::ptr::copy_nonoverlapping(input, output, input_size.min(output_size) as usize) };
+        }
+
+        queue.completion_ring().post(job_id, status);
+        self.mmio.write32(self.regs.d2h_data[0], job_id);
+        self.mmio.write32(self.regs.d2h_data[1], status);
+        self.mmio.write32(self.regs.d2h_doorbell, IPC_DRBL_TRIGGER);
+        Some((job_id, status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dma_pool::{DmaPool, PooledBuffer};
+    use crate::inference::{prepare_input, prepare_output, read_completion, InferenceError};
+    use std::time::Duration;
+
+    const PAGE: usize = DMA_ALIGNMENT;
+
+    fn job(model: usize, input: usize, output: usize) -> (PooledBuffer, PooledBuffer, PooledBuffer) {
+        let pool = DmaPool::unpooled();
+        (
+            prepare_output(&pool, model).unwrap(),
+            prepare_input(&pool, &vec![0u8; input]).unwrap(),
+            prepare_output(&pool, output).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_job_within_budget_completes() {
+        let mut sim = FwSim::new();
+        sim.set_mem_budget(Some(16 * PAGE));
+        let mut queue = CommandQueue::new(4).unwrap();
+
+        let (model, input, output) = job(8 * PAGE, PAGE, PAGE);
+        let job_id = queue.submit(sim.mmio(), &model, &input, &output).unwrap();
+
+        assert_eq!(sim.service(&queue), Some((job_id, JOB_STATUS_SUCCESS)));
+        let (done_id, status) = read_completion(sim.mmio(), &REGS_MTL).unwrap();
+        assert_eq!(done_id, job_id);
+        assert!(queue.complete(done_id, status).is_ok());
+
+        // Completion was acknowledged
+        assert!(read_completion(sim.mmio(), &REGS_MTL).is_none());
+    }
+
+    #[test]
+    fn test_firmware_oom_maps_to_typed_error() {
+        let mut sim = FwSim::new();
+        sim.set_mem_budget(Some(4 * PAGE));
+        let mut queue = CommandQueue::new(4).unwrap();
+
+        // Passes the host-side budget, but the firmware has less memory
+        let (model, input, output) = job(8 * PAGE, PAGE, PAGE);
+        let job_id = queue.submit(sim.mmio(), &model, &input, &output).unwrap();
+        sim.service(&queue).unwrap();
+
+        let (done_id, status) = read_completion(sim.mmio(), &REGS_MTL).unwrap();
+        assert_eq!(status, JOB_STATUS_OUT_OF_RESOURCES);
+        match queue.complete(done_id, status) {
+            Err(InferenceError::DeviceOutOfMemory { requested, available_hint }) => {
+                assert_eq!(requested, 10 * PAGE);
+                assert_eq!(available_hint, Some(queue.memory_budget()));
+            }
+            other => panic!("expected DeviceOutOfMemory, got {:?}", other),
+        }
+        assert_eq!(job_id, done_id);
+    }
+
+    #[test]
+    fn test_completions_drained_from_ring() {
+        let sim = FwSim::new();
+        let mut queue = CommandQueue::new(2).unwrap();
+        let (model, input, output) = job(PAGE, PAGE, PAGE);
+
+        // More jobs than ring slots: each is drained before the ring wraps
+        for _ in 0..3 {
+            let job_id = queue.submit(sim.mmio(), &model, &input, &output).unwrap();
+            assert!(queue.poll_completions().is_empty());
+            sim.service(&queue).unwrap();
+            let result = queue.wait_for_completion(job_id, Duration::from_secs(1)).unwrap();
+            assert_eq!((result.job_id, result.status), (job_id, JOB_STATUS_SUCCESS));
+        }
+        let stats = queue.stats();
+        assert_eq!((stats.completed, stats.in_flight), (3, 0));
+    }
+
+    #[test]
+    fn test_mock_completion_delay_and_timeout() {
+        let sim = FwSim::new();
+        let mut queue = CommandQueue::new(4).unwrap();
+        let (model, input, output) = job(PAGE, PAGE, PAGE);
+
+        queue.set_mock_latency(Some(Duration::from_millis(100)));
+        let slow = queue.submit(sim.mmio(), &model, &input, &output).unwrap();
+        queue.set_mock_latency(Some(Duration::ZERO));
+        let fast = queue.submit(sim.mmio(), &model, &input, &output).unwrap();
+
+        // Submitted second, done first
+        assert_eq!(queue.poll_completions(), vec![fast]);
+        assert_eq!(queue.stats().in_flight, 1);
+        assert!(matches!(
+            queue.wait_for_completion(slow, Duration::from_millis(10)),
+            Err(InferenceError::Timeout { job_id }) if job_id == slow
+        ));
+
+        let result = queue.wait_for_completion(slow, Duration::from_secs(2)).unwrap();
+        assert!(result.elapsed >= Duration::from_millis(100));
+        assert_eq!(queue.take_result(fast).unwrap().unwrap().job_id, fast);
+        assert!(queue.take_result(fast).is_none());
+        let stats = queue.stats();
+        assert_eq!((stats.completed, stats.in_flight), (2, 0));
+    }
+
+    #[test]
+    fn test_full_queue_rejects_submission() {
+        let sim = FwSim::new();
+        let mut queue = CommandQueue::new(4).unwrap();
+        queue.set_mock_latency(Some(Duration::ZERO));
+        let (model, input, output) = job(PAGE, PAGE, PAGE);
+
+        for remaining in (1..=4).rev() {
+            assert_eq!(queue.capacity_remaining(), remaining);
+            queue.submit(sim.mmio(), &model, &input, &output).unwrap();
+        }
+        assert_eq!(queue.capacity_remaining(), 0);
+        let last = queue.last_descriptor().unwrap().job_id;
+
+        // The N+1th job would overwrite the first job's descriptor
+        assert!(matches!(queue.submit(sim.mmio(), &model, &input, &output), Err(InferenceError::QueueFull)));
+        let stats = queue.stats();
+        assert_eq!((stats.total_submitted, stats.write_idx), (4, 0));
+        assert_eq!({ queue.last_descriptor().unwrap().job_id }, last);
+
+        // Completions hand the slots back
+        assert_eq!(queue.poll_completions().len(), 4);
+        assert_eq!(queue.capacity_remaining(), 4);
+        queue.submit(sim.mmio(), &model, &input, &output).unwrap();
+    }
+
+    #[test]
+    fn test_oversized_job_rejected_before_hardware() {
+        let sim = FwSim::new();
+        let mut queue = CommandQueue::new(4).unwrap();
+        queue.set_memory_budget(4 * PAGE);
+
+        let (model, input, output) = job(8 * PAGE, PAGE, PAGE);
+        let err = queue.submit(sim.mmio(), &model, &input, &output).unwrap_err();
+        assert!(err.is_out_of_memory());
+        assert!(err.to_string().contains("out of memory"));
+
+        // Doorbell never rung, no job ID consumed
+        assert_eq!(sim.mmio().read32(IPC_HOST_2_DEVICE_DRBL), 0);
+        assert!(sim.service(&queue).is_none());
+        assert_eq!(queue.stats().total_submitted, 0);
+    }
+
+    #[test]
+    fn test_other_failures_stay_npu_errors() {
+        let err = InferenceError::from_job_status(7, JOB_STATUS_PROCESSING_ERR, Some(PAGE), PAGE);
+        assert!(matches!(err, InferenceError::NpuError { job_id: 7, status: JOB_STATUS_PROCESSING_ERR }));
+        assert!(!err.is_out_of_memory());
+    }
+
+    #[test]
+    fn test_booted_npu_owns_queue_and_quiesces_on_shutdown() {
+        let sim = FwSim::new();
+        let fw_path = std::env::temp_dir().join(format!("fwsim_{}.bin", std::process::id()));
+        std::fs::write(&fw_path, crate::fw_image::build_image("fwsim", &[0u8; PAGE])).unwrap();
+
+        let queue = CommandQueue::new(4).unwrap();
+        let queue_phys = queue.phys_addr();
+        let mut booted = crate::boot::BootSequence::new(sim.mmio())
+            .execute(fw_path.to_str().unwrap(), queue)
+            .unwrap();
+        std::fs::remove_file(&fw_path).ok();
+
+        assert!(matches!(booted.result(), crate::boot::BootResult::Ready { .. }));
+        assert_eq!(sim.mmio().read32(IPC_HOST_2_DEVICE_DATA0), queue_phys as u32);
+        assert_eq!(sim.mmio().read32(IPC_HOST_2_DEVICE_DATA2), booted.queue().completion_ring().phys_addr() as u32);
+        assert_eq!(sim.mmio().read32(BUTTRESS_GLOBAL_INT_MASK), 0);
+        assert_eq!(booted.queue_mut().stats().total_submitted, 0);
+
+        let mmio = booted.shutdown();
+        assert_eq!(mmio.read32(BUTTRESS_GLOBAL_INT_MASK), 0xFFFF_FFFF);
+        assert_eq!(mmio.read32(IPC_INT_MASK), 0xFFFF_FFFF);
+        assert_eq!(mmio.read32(HOST_SS_CPR_RST_SET), 0x1);
+        assert_eq!(mmio.read32(IPC_HOST_2_DEVICE_DATA0), 0);
+        assert_eq!(mmio.read32(IPC_HOST_2_DEVICE_DATA2), 0);
+    }
+
+    fn write_fw_image(name: &str) -> std::path::PathBuf {
+        let fw_path = std::env::temp_dir().join(format!("fwsim_{}_{}.bin", name, std::process::id()));
+        std::fs::write(&fw_path, crate::fw_image::build_image("fwsim", &[0u8; PAGE])).unwrap();
+        fw_path
+    }
+
+    #[test]
+    fn test_boot_requests_fused_tile_mask() {
+        let fw_path = write_fw_image("tiles");
+        let cases = [
+            (PCI_DEVICE_MTL_NPU, 0b01, 1),
+            (PCI_DEVICE_MTL_NPU, 0b11, 2),
+            (PCI_DEVICE_ARL_NPU, 0b10, 1),
+        ];
+
+        for (device_id, enabled, count) in cases {
+            let mut sim = FwSim::for_device(device_id);
+            sim.set_tiles(enabled, npu_max_tiles(device_id));
+            let fuse = sim.mmio().read32(BUTTRESS_TILE_FUSE);
+
+            let booted = crate::boot::BootSequence::new(sim.mmio())
+                .with_device(device_id)
+                .unwrap()
+                .execute(fw_path.to_str().unwrap(), CommandQueue::new(4).unwrap())
+                .unwrap();
+
+            let tiles = booted.tiles();
+            assert_eq!((tiles.enabled_tiles, tiles.count), (enabled, count), "fuse {:#x}", fuse);
+            assert_eq!(tiles, TileConfig::from_fuse(fuse, npu_max_tiles(device_id)));
+            assert_eq!(sim.requested_tiles(), Some(enabled), "fuse {:#x}", fuse);
+        }
+        std::fs::remove_file(&fw_path).ok();
+    }
+
+    #[test]
+    fn test_arrow_lake_boots_on_the_meteor_lake_map() {
+        let fw_path = write_fw_image("arl");
+        let sim = FwSim::for_device(PCI_DEVICE_ARL_NPU);
+        let mut booted = crate::boot::BootSequence::new(sim.mmio())
+            .with_device(PCI_DEVICE_ARL_NPU)
+            .unwrap()
+            .execute(fw_path.to_str().unwrap(), CommandQueue::new(4).unwrap())
+            .unwrap();
+        std::fs::remove_file(&fw_path).ok();
+
+        // 37xx-compatible: the queue is registered at the Meteor Lake offsets
+        let queue_phys = booted.queue().phys_addr() as u32;
+        assert_eq!(sim.mmio().read32(IPC_HOST_2_DEVICE_DATA0), queue_phys);
+        assert_ne!(sim.mmio().read32(HOST_SS_LOADING_ADDR_LO), 0);
+
+        let (model, input, output) = job(PAGE, PAGE, PAGE);
+        let job_id = booted.queue_mut().submit(sim.mmio(), &model, &input, &output).unwrap();
+        assert_eq!(sim.service(booted.queue()), Some((job_id, JOB_STATUS_SUCCESS)));
+        assert_eq!(read_completion(sim.mmio(), &REGS_ARL), Some((job_id, JOB_STATUS_SUCCESS)));
+    }
+
+    #[test]
+    fn test_lunar_lake_is_refused_before_any_register_access() {
+        let sim = FwSim::new();
+        let err = crate::boot::BootSequence::new(sim.mmio()).with_device(PCI_DEVICE_LNL_NPU).err();
+        assert_eq!(err, Some(UnsupportedGeneration(HwGeneration::Lnl)));
+
+        let mut monitor = crate::status::StatusMonitor::new(sim.mmio());
+        assert!(monitor.set_device(PCI_DEVICE_LNL_NPU).is_err());
+    }
+
+    #[test]
+    fn test_fully_fused_part_refuses_to_boot() {
+        let fw_path = write_fw_image("no_tiles");
+        let mut sim = FwSim::new();
+        sim.set_tiles(0, NPU_TILES_MTL);
+
+        let result = crate::boot::BootSequence::new(sim.mmio())
+            .execute(fw_path.to_str().unwrap(), CommandQueue::new(4).unwrap());
+        std::fs::remove_file(&fw_path).ok();
+
+        assert!(matches!(result, Err(crate::boot::BootError::NoTiles { .. })));
+        assert_eq!(sim.requested_tiles(), None);
+    }
+
+    #[test]
+    fn test_cold_reset_and_reboot_with_retained_image() {
+        let sim = FwSim::new();
+        let boot = crate::boot::BootSequence::new(sim.mmio());
+        let firmware = crate::dma::DmaBuffer::new(PAGE).unwrap();
+        let fw_info = crate::fw_image::parse(&crate::fw_image::build_image("fwsim", &[0u8; PAGE])).unwrap();
+
+        // Reset stops the running firmware; power is back once it is released
+        let tiles = boot.cold_reset().unwrap();
+        assert_eq!(tiles.count, NPU_TILES_MTL);
+        assert_eq!(sim.mmio().read32(HOST_SS_CPR_RST_SET), 0x1);
+        assert_eq!(sim.mmio().read32(BUTTRESS_VPU_STATUS) & 0x1, 0x1);
+        assert_eq!(sim.mmio().read32(HOST_SS_FW_STATUS), 0);
+        assert_eq!(sim.mmio().read32(BUTTRESS_GLOBAL_INT_MASK), 0xFFFF_FFFF);
+
+        // The doorbell brings the mock firmware up from the given image
+        let result = boot.reboot_with(&firmware, &fw_info).unwrap();
+        assert!(matches!(result, crate::boot::BootResult::Ready { .. }));
+        assert_eq!(sim.mmio().read32(HOST_SS_LOADING_ADDR_LO), firmware.phys_lo());
+        assert_eq!(sim.mmio().read32(HOST_SS_ENTRY_POINT), fw_info.entry_point_reg());
+        assert_eq!(sim.mmio().read32(HOST_SS_BOOT_COUNT), 1);
+
+        // A crashed image stays DEAD across the reset
+        sim.crash();
+        boot.cold_reset().unwrap();
+        assert!(matches!(boot.reboot_with(&firmware, &fw_info), Err(crate::boot::BootError::FirmwareDead)));
+    }
+
+    #[test]
+    fn test_boot_wait_wakes_on_interrupt() {
+        let firmware = crate::dma::DmaBuffer::new(PAGE).unwrap();
+        let fw_info = crate::fw_image::parse(&crate::fw_image::build_image("fwsim", &[0u8; PAGE])).unwrap();
+
+        // Firmware busy booting that reports READY, with an interrupt, 30ms
+        // after the doorbell; returns how long the driver took to notice
+        let boot_once = |with_irq: bool| {
+            let sim = FwSim::new();
+            sim.mmio().write32(HOST_SS_FW_STATUS, FW_STATUS_BEEF);
+            let irq = crate::irq::Interrupts::for_line(None).unwrap();
+            let mut boot = crate::boot::BootSequence::new(sim.mmio());
+            if with_irq {
+                boot = boot.with_interrupts(&irq);
+            }
+
+            let injector = irq.injector();
+            // SAFETY: a second view of the fake BAR, standing in for the
+            // device; the thread is joined before `sim` is dropped
+            let device = unsafe { MmioRegion::new(sim.mmio().base_ptr(), sim.mmio().size()) };
+            let firmware_side = std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(30));
+                device.write32(HOST_SS_FW_STATUS, FW_STATUS_READY);
+                let _ = injector.send(());
+            });
+
+            let start = std::time::Instant::now();
+            let result = boot.reboot_with(&firmware, &fw_info).unwrap();
+            let took = start.elapsed();
+            firmware_side.join().unwrap();
+            assert!(matches!(result, crate::boot::BootResult::Ready { .. }));
+            took
+        };
+
+        // Woken by the interrupt, well before the initial nudge delay is over
+        let woken = boot_once(true);
+        assert!(woken >= Duration::from_millis(30), "booted in {:?}", woken);
+        assert!(woken < Duration::from_millis(NUDGE_DELAY_MS / 2), "booted in {:?}", woken);
+
+        // Polling only looks again once the delay has passed
+        let polled = boot_once(false);
+        assert!(polled >= Duration::from_millis(NUDGE_DELAY_MS), "booted in {:?}", polled);
+    }
+
+    fn fast_recovery(max_attempts: u32) -> crate::status::RecoveryPolicy {
+        crate::status::RecoveryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        }
+    }
+
+    #[test]
+    fn test_watchdog_recovers_dead_firmware() {
+        let fw_path = write_fw_image("watchdog");
+        let sim = FwSim::new();
+        let mut booted = crate::boot::BootSequence::new(sim.mmio())
+            .execute(fw_path.to_str().unwrap(), CommandQueue::new(4).unwrap())
+            .unwrap();
+        std::fs::remove_file(&fw_path).ok();
+        let fw_addr = sim.mmio().read32(HOST_SS_LOADING_ADDR_LO);
+        let queue_phys = booted.queue().phys_addr() as u32;
+
+        let log_path = std::env::temp_dir().join(format!("fwsim_watchdog_{}.log", std::process::id()));
+        std::fs::remove_file(&log_path).ok();
+        let log = crate::events::EventLog::open(&log_path, 4096).unwrap();
+        let mut monitor = crate::status::StatusMonitor::new(sim.mmio());
+        monitor.set_event_log(&log);
+        monitor.set_recovery_policy(fast_recovery(3));
+        assert_eq!(monitor.watchdog(|| booted.recover().map(|_| ())).unwrap(), crate::status::NpuState::Ready);
+        assert_eq!(monitor.recovery_attempts(), 0);
+
+        // First restart finds the firmware still dead, the second succeeds
+        sim.crash();
+        sim.mmio().write32(HOST_SS_LOADING_ADDR_LO, 0);
+        let mut calls = 0;
+        let state = monitor.watchdog(|| {
+            calls += 1;
+            if calls == 2 {
+                sim.restart();
+            }
+            booted.recover().map(|_| ())
+        });
+
+        assert_eq!(state.unwrap(), crate::status::NpuState::Ready);
+        assert_eq!(monitor.recovery_attempts(), 2);
+        assert!(monitor.last_recovery_time().is_some());
+        assert_eq!(sim.mmio().read32(HOST_SS_LOADING_ADDR_LO), fw_addr);
+        assert_eq!(sim.mmio().read32(IPC_HOST_2_DEVICE_DATA0), queue_phys);
+        assert!(matches!(booted.result(), crate::boot::BootResult::Ready { .. }));
+
+        let recoveries: Vec<(u32, u64)> = log
+            .events()
+            .unwrap()
+            .iter()
+            .filter(|e| e.kind == crate::events::EventKind::WatchdogRecovery)
+            .map(|e| (e.code, e.value))
+            .collect();
+        assert_eq!(recoveries, vec![(1, 1), (0, 2)]);
+        let device = crate::status::DeviceSummary { device_id: 0, name: String::new(), bdf: String::new(), bar0_size: 0 };
+        assert!(monitor.diagnostics(device, "test", &[]).to_json().contains("\"recovery_attempts\":2"));
+        std::fs::remove_file(&log_path).ok();
+    }
+
+    #[test]
+    fn test_watchdog_gives_up_after_max_attempts() {
+        let fw_path = write_fw_image("watchdog_dead");
+        let sim = FwSim::new();
+        let mut booted = crate::boot::BootSequence::new(sim.mmio())
+            .execute(fw_path.to_str().unwrap(), CommandQueue::new(4).unwrap())
+            .unwrap();
+        std::fs::remove_file(&fw_path).ok();
+
+        let mut monitor = crate::status::StatusMonitor::new(sim.mmio());
+        monitor.set_recovery_policy(fast_recovery(2));
+        sim.crash();
+        let err = monitor.watchdog(|| booted.recover().map(|_| ())).unwrap_err();
+
+        assert_eq!(err.attempts, 2);
+        assert_eq!(err.last_error, crate::boot::BootError::FirmwareDead.to_string());
+        assert_eq!(monitor.recovery_attempts(), 2);
+        assert_eq!(monitor.poll(), crate::status::NpuState::Dead);
+    }
+
+    #[test]
+    fn test_recovery_backoff_doubles_up_to_cap() {
+        let policy = crate::status::RecoveryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(5), Duration::from_secs(8));
+        assert_eq!(policy.backoff(40), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn test_budget_per_generation() {
+        assert_eq!(npu_memory_budget(PCI_DEVICE_MTL_NPU), NPU_MEM_BUDGET_MTL);
+        assert_eq!(npu_memory_budget(PCI_DEVICE_LNL_NPU), NPU_MEM_BUDGET_LNL);
+        assert_eq!(npu_memory_budget(0xFFFF), NPU_MEM_BUDGET_MTL);
+    }
+}
@@ -3,8 +3,8 @@
 //! Reverse-engineered from Linux kernel driver: drivers/accel/ivpu/
 //! Sources: ivpu_hw_40xx.c, ivpu_hw_reg_io.h, ivpu_ipc.h
 //!
-//! ⚠️  These offsets target Meteor Lake (PCI 0x7D1D).
-//!     Arrow Lake uses 0xAD1D with slightly different offsets.
+//! ⚠️  These offsets target Meteor Lake (PCI 0x7D1D). Arrow Lake (0xAD1D)
+//!     and Lunar Lake (0x6467) move them; see `RegisterMap`.
 
 // ============================================================
 // PCI Identity
@@ -14,10 +14,10 @@ pub const PCI_VENDOR_INTEL: u16 = 0x8086;
 /// Meteor Lake NPU (VPU 4.0)
 pub const PCI_DEVICE_MTL_NPU: u16 = 0x7D1D;
 
-/// Arrow Lake NPU (VPU 4.0) — future support
+/// Arrow Lake NPU (VPU 4.0)
 pub const PCI_DEVICE_ARL_NPU: u16 = 0xAD1D;
 
-/// Lunar Lake NPU (VPU 5.0) — future support
+/// Lunar Lake NPU (VPU 5.0)
 pub const PCI_DEVICE_LNL_NPU: u16 = 0x6467;
 
 /// All supported device IDs
@@ -40,6 +40,10 @@ pub const BUTTRESS_GLOBAL_INT_MASK: usize = BUTTRESS_BASE + 0x0020;
 /// Global interrupt status
 pub const BUTTRESS_GLOBAL_INT_STS: usize = BUTTRESS_BASE + 0x0024;
 
+/// Global interrupt status: error sources (ATS, CFI0/1, IMR0/1,
+/// surveillance), bits 1–6. Bit 0 is a frequency change, not an error.
+pub const BUTTRESS_INT_STS_ERR_MASK: u32 = 0x0000_007E;
+
 /// Tile fuse register (indicates active tiles)
 pub const BUTTRESS_TILE_FUSE: usize = BUTTRESS_BASE + 0x0050;
 
@@ -52,7 +56,7 @@ pub const BUTTRESS_VPU_D0I3_CONTROL: usize = BUTTRESS_BASE + 0x0118;
 /// Frequency control (PLL)
 pub const BUTTRESS_VPU_IP_RESET: usize = BUTTRESS_BASE + 0x0160;
 
-/// Workpoint frequency
+/// Workpoint request (PAYLOAD0 = frequency, PAYLOAD1 = tile config)
 pub const BUTTRESS_WP_REQ_PAYLOAD0: usize = BUTTRESS_BASE + 0x0200;
 pub const BUTTRESS_WP_REQ_PAYLOAD1: usize = BUTTRESS_BASE + 0x0204;
 pub const BUTTRESS_WP_REQ_CMD: usize = BUTTRESS_BASE + 0x0208;
@@ -77,6 +81,7 @@ pub const IPC_HOST_2_DEVICE_DATA1: usize = IPC_BASE + 0x0014;
 pub const IPC_HOST_2_DEVICE_DATA2: usize = IPC_BASE + 0x0018;
 pub const IPC_HOST_2_DEVICE_DATA3: usize = IPC_BASE + 0x001C;
 
+/// IPC data payload registers, device -> host
 pub const IPC_DEVICE_2_HOST_DATA0: usize = IPC_BASE + 0x0020;
 pub const IPC_DEVICE_2_HOST_DATA1: usize = IPC_BASE + 0x0024;
 
@@ -136,6 +141,66 @@ pub const FW_STATUS_BEEF: u32 = 0xBEEF_0000;
 pub const FW_STATUS_OBAD: u32 = 0x0BAD_0000;
 pub const FW_STATUS_FACE: u32 = 0xFACE_0000;
 
+// ============================================================
+// Tile Fuse / Workpoint Fields
+// ============================================================
+// ivpu_hw_40xx_reg.h `BUTTRESS_TILE_FUSE`: bit 0 = VALID, bits 1..6 =
+// CONFIG, one bit per tile, set when the tile is fused off.
+
+/// Fuse value has been programmed
+pub const TILE_FUSE_VALID: u32 = 0x1;
+
+/// Fused-off tile mask (shifted)
+pub const TILE_FUSE_CONFIG_SHIFT: u32 = 1;
+pub const TILE_FUSE_CONFIG_MASK: u32 = 0x3F << TILE_FUSE_CONFIG_SHIFT;
+
+/// Send bit of `BUTTRESS_WP_REQ_CMD`
+pub const WP_REQ_CMD_SEND: u32 = 0x1;
+
+/// Tiles on a full part: Meteor/Arrow Lake (NPU 3720)
+pub const NPU_TILES_MTL: u8 = 2;
+
+/// Tiles on a full part: Lunar Lake (NPU 4000)
+pub const NPU_TILES_LNL: u8 = 6;
+
+// ============================================================
+// D0i3 Control Fields
+// ============================================================
+// ivpu_hw_40xx_reg.h `VPU_40XX_BUTTRESS_D0I3_CONTROL`.
+
+/// A D0i3 entry or exit is still being carried out
+pub const D0I3_CONTROL_INPROGRESS: u32 = 0x1;
+
+/// Set to enter D0i3, clear to leave it
+pub const D0I3_CONTROL_I3: u32 = 0x4;
+
+// ============================================================
+// Job Status Codes
+// ============================================================
+// Completion status of a submitted job, as reported by the firmware
+// (vpu_jsm_api.h `VPU_JSM_STATUS_*`, surfaced by the ivpu UAPI).
+
+/// Job completed successfully
+pub const JOB_STATUS_SUCCESS: u32 = 0x0;
+
+/// Firmware could not parse the command
+pub const JOB_STATUS_PARSING_ERR: u32 = 0x1;
+
+/// Job failed during execution
+pub const JOB_STATUS_PROCESSING_ERR: u32 = 0x2;
+
+/// Job was preempted
+pub const JOB_STATUS_PREEMPTED: u32 = 0x3;
+
+/// Job was aborted
+pub const JOB_STATUS_ABORTED: u32 = 0x4;
+
+/// Not enough device memory / SRAM to set up the job
+pub const JOB_STATUS_OUT_OF_RESOURCES: u32 = 0xA;
+
+/// ivpu UAPI `DRM_IVPU_JOB_STATUS_ABORTED` (context reset after a fault)
+pub const JOB_STATUS_UAPI_ABORTED: u32 = 0x100;
+
 // ============================================================
 // PCI Config Space
 // ============================================================
@@ -151,6 +216,9 @@ pub const PCI_CMD_MEMORY_SPACE: u16 = 0x0002;
 /// PCI Command: I/O Space Enable (bit 0)
 pub const PCI_CMD_IO_SPACE: u16 = 0x0001;
 
+/// PCI Interrupt Line register offset (legacy IRQ; 0 or 0xFF = none routed)
+pub const PCI_INTERRUPT_LINE: usize = 0x3C;
+
 // ============================================================
 // DMA / Memory Constants
 // ============================================================
@@ -166,6 +234,21 @@ pub const CMD_QUEUE_SIZE: usize = 256;
 /// Single command descriptor size (64 bytes)
 pub const CMD_DESC_SIZE: usize = 64;
 
+/// Completion ring entry: job_id (u32) + status (u32)
+pub const COMPLETION_ENTRY_SIZE: usize = 8;
+
+/// Per-job NPU memory budget (model + input + output), Meteor Lake.
+///
+/// Enforced host-side before submission so oversized jobs fail with a
+/// clear message instead of an opaque firmware status.
+pub const NPU_MEM_BUDGET_MTL: usize = 1024 * 1024 * 1024;
+
+/// Per-job NPU memory budget, Arrow Lake (same NPU 3720 as Meteor Lake)
+pub const NPU_MEM_BUDGET_ARL: usize = 1024 * 1024 * 1024;
+
+/// Per-job NPU memory budget, Lunar Lake (NPU 4000)
+pub const NPU_MEM_BUDGET_LNL: usize = 2 * 1024 * 1024 * 1024;
+
 // ============================================================
 // Timing Constants
 // ============================================================
@@ -184,6 +267,186 @@ pub const NUDGE_DELAY_MS: u64 = 300;
 /// Maximum nudge retries
 pub const NUDGE_MAX_RETRIES: u32 = 5;
 
+/// How long a cold reset holds the NPU in reset (milliseconds)
+pub const RESET_HOLD_MS: u64 = 10;
+
+/// Maximum wait for the Buttress to finish a D0i3 entry or exit (milliseconds)
+pub const D0I3_TIMEOUT_MS: u64 = 100;
+
+// ============================================================
+// Per-Generation Register Maps
+// ============================================================
+// The constants above describe Meteor Lake. A map is the three subsystem
+// bases applied to the Meteor Lake offsets, so a later part that moves the
+// blocks within BAR0 only needs its bases.
+//
+// Arrow Lake is 37xx-compatible and uses the Meteor Lake map. Lunar Lake
+// (40xx) has no map until its offsets come from a datasheet: probing one
+// fails rather than poking guessed addresses.
+
+/// NPU hardware generation, from the PCI device ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HwGeneration {
+    /// Meteor Lake (NPU 3720)
+    Mtl,
+    /// Arrow Lake (NPU 3720)
+    Arl,
+    /// Lunar Lake (NPU 4000)
+    Lnl,
+}
+
+impl HwGeneration {
+    /// Generation of a device ID; unknown IDs get the Meteor Lake map.
+    pub fn from_device(device_id: u16) -> Self {
+        match device_id {
+            PCI_DEVICE_ARL_NPU => HwGeneration::Arl,
+            PCI_DEVICE_LNL_NPU => HwGeneration::Lnl,
+            _ => HwGeneration::Mtl,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HwGeneration::Mtl => "Meteor Lake",
+            HwGeneration::Arl => "Arrow Lake",
+            HwGeneration::Lnl => "Lunar Lake",
+        }
+    }
+
+    pub fn regs(self) -> Result<&'static RegisterMap, UnsupportedGeneration> {
+        match self {
+            HwGeneration::Mtl => Ok(&REGS_MTL),
+            HwGeneration::Arl => Ok(&REGS_ARL),
+            HwGeneration::Lnl => Err(UnsupportedGeneration(self)),
+        }
+    }
+}
+
+impl std::fmt::Display for HwGeneration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A generation the driver recognises but has no verified register map for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedGeneration(pub HwGeneration);
+
+impl std::fmt::Display for UnsupportedGeneration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unsupported NPU generation: {} (register offsets not verified)", self.0)
+    }
+}
+
+impl std::error::Error for UnsupportedGeneration {}
+
+/// BAR0 offsets of the registers the driver touches, for one generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterMap {
+    pub generation: HwGeneration,
+    /// Where the buttress, IPC and host subsystem blocks start
+    pub buttress_base: usize,
+    pub ipc_base: usize,
+    pub host_ss_base: usize,
+
+    // --- Buttress ---
+    pub global_int_mask: usize,
+    pub global_int_sts: usize,
+    pub tile_fuse: usize,
+    pub vpu_status: usize,
+    pub d0i3_control: usize,
+    pub wp_req_payload0: usize,
+    pub wp_req_payload1: usize,
+    pub wp_req_cmd: usize,
+
+    // --- IPC ---
+    pub h2d_doorbell: usize,
+    pub d2h_doorbell: usize,
+    /// Host -> device payload DATA0..DATA3
+    pub h2d_data: [usize; 4],
+    /// Device -> host payload DATA0..DATA1
+    pub d2h_data: [usize; 2],
+    pub ipc_int_mask: usize,
+
+    // --- Host Subsystem ---
+    pub gen_ctrl: usize,
+    pub clk_en: usize,
+    pub cpr_rst_set: usize,
+    pub cpr_rst_clr: usize,
+    pub loading_addr_lo: usize,
+    pub loading_addr_hi: usize,
+    pub entry_point: usize,
+    pub fw_status: usize,
+    pub fw_version: usize,
+    pub boot_count: usize,
+}
+
+impl RegisterMap {
+    /// Meteor Lake offsets moved to the given subsystem bases.
+    const fn with_bases(generation: HwGeneration, buttress: usize, ipc: usize, host_ss: usize) -> Self {
+        const fn rebase(reg: usize, from: usize, to: usize) -> usize {
+            reg - from + to
+        }
+        Self {
+            generation,
+            buttress_base: buttress,
+            ipc_base: ipc,
+            host_ss_base: host_ss,
+            global_int_mask: rebase(BUTTRESS_GLOBAL_INT_MASK, BUTTRESS_BASE, buttress),
+            global_int_sts: rebase(BUTTRESS_GLOBAL_INT_STS, BUTTRESS_BASE, buttress),
+            tile_fuse: rebase(BUTTRESS_TILE_FUSE, BUTTRESS_BASE, buttress),
+            vpu_status: rebase(BUTTRESS_VPU_STATUS, BUTTRESS_BASE, buttress),
+            d0i3_control: rebase(BUTTRESS_VPU_D0I3_CONTROL, BUTTRESS_BASE, buttress),
+            wp_req_payload0: rebase(BUTTRESS_WP_REQ_PAYLOAD0, BUTTRESS_BASE, buttress),
+            wp_req_payload1: rebase(BUTTRESS_WP_REQ_PAYLOAD1, BUTTRESS_BASE, buttress),
+            wp_req_cmd: rebase(BUTTRESS_WP_REQ_CMD, BUTTRESS_BASE, buttress),
+            h2d_doorbell: rebase(IPC_HOST_2_DEVICE_DRBL, IPC_BASE, ipc),
+            d2h_doorbell: rebase(IPC_DEVICE_2_HOST_DRBL, IPC_BASE, ipc),
+            h2d_data: [
+                rebase(IPC_HOST_2_DEVICE_DATA0, IPC_BASE, ipc),
+                rebase(IPC_HOST_2_DEVICE_DATA1, IPC_BASE, ipc),
+                rebase(IPC_HOST_2_DEVICE_DATA2, IPC_BASE, ipc),
+                rebase(IPC_HOST_2_DEVICE_DATA3, IPC_BASE, ipc),
+            ],
+            d2h_data: [rebase(IPC_DEVICE_2_HOST_DATA0, IPC_BASE, ipc), rebase(IPC_DEVICE_2_HOST_DATA1, IPC_BASE, ipc)],
+            ipc_int_mask: rebase(IPC_INT_MASK, IPC_BASE, ipc),
+            gen_ctrl: rebase(HOST_SS_GEN_CTRL, HOST_SS_BASE, host_ss),
+            clk_en: rebase(HOST_SS_CLK_EN, HOST_SS_BASE, host_ss),
+            cpr_rst_set: rebase(HOST_SS_CPR_RST_SET, HOST_SS_BASE, host_ss),
+            cpr_rst_clr: rebase(HOST_SS_CPR_RST_CLR, HOST_SS_BASE, host_ss),
+            loading_addr_lo: rebase(HOST_SS_LOADING_ADDR_LO, HOST_SS_BASE, host_ss),
+            loading_addr_hi: rebase(HOST_SS_LOADING_ADDR_HI, HOST_SS_BASE, host_ss),
+            entry_point: rebase(HOST_SS_ENTRY_POINT, HOST_SS_BASE, host_ss),
+            fw_status: rebase(HOST_SS_FW_STATUS, HOST_SS_BASE, host_ss),
+            fw_version: rebase(HOST_SS_FW_VERSION, HOST_SS_BASE, host_ss),
+            boot_count: rebase(HOST_SS_BOOT_COUNT, HOST_SS_BASE, host_ss),
+        }
+    }
+
+    /// Where a Meteor Lake register offset (e.g. from `REGISTERS`) lives
+    /// on this generation.
+    pub fn rebase(&self, mtl_offset: usize) -> usize {
+        if mtl_offset >= HOST_SS_BASE {
+            mtl_offset - HOST_SS_BASE + self.host_ss_base
+        } else if mtl_offset >= IPC_BASE {
+            mtl_offset - IPC_BASE + self.ipc_base
+        } else {
+            mtl_offset - BUTTRESS_BASE + self.buttress_base
+        }
+    }
+
+    /// Map for a PCI device ID (Meteor Lake if unknown).
+    pub fn for_device(device_id: u16) -> Result<&'static Self, UnsupportedGeneration> {
+        HwGeneration::from_device(device_id).regs()
+    }
+}
+
+/// Meteor Lake (NPU 3720): the offsets above
+pub const REGS_MTL: RegisterMap = RegisterMap::with_bases(HwGeneration::Mtl, BUTTRESS_BASE, IPC_BASE, HOST_SS_BASE);
+
+/// Arrow Lake (NPU 3720): 37xx-compatible, same layout as Meteor Lake
+pub const REGS_ARL: RegisterMap = RegisterMap::with_bases(HwGeneration::Arl, BUTTRESS_BASE, IPC_BASE, HOST_SS_BASE);
+
 // ============================================================
 // Utility
 // ============================================================
@@ -202,6 +465,95 @@ pub fn decode_fw_status(raw: u32) -> &'static str {
     }
 }
 
+/// Decode a job completion status to a human-readable string
+pub fn decode_job_status(status: u32) -> &'static str {
+    match status {
+        JOB_STATUS_SUCCESS => "SUCCESS",
+        JOB_STATUS_PARSING_ERR => "PARSING_ERR — malformed command",
+        JOB_STATUS_PROCESSING_ERR => "PROCESSING_ERR — execution failed",
+        JOB_STATUS_PREEMPTED => "PREEMPTED",
+        JOB_STATUS_ABORTED | JOB_STATUS_UAPI_ABORTED => "ABORTED",
+        JOB_STATUS_OUT_OF_RESOURCES => "OUT_OF_RESOURCES — device memory exhausted",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Whether a job status means the device ran out of memory
+pub fn is_oom_status(status: u32) -> bool {
+    status == JOB_STATUS_OUT_OF_RESOURCES
+}
+
+/// Per-job memory budget for a device generation (Meteor Lake if unknown)
+pub fn npu_memory_budget(device_id: u16) -> usize {
+    match device_id {
+        PCI_DEVICE_ARL_NPU => NPU_MEM_BUDGET_ARL,
+        PCI_DEVICE_LNL_NPU => NPU_MEM_BUDGET_LNL,
+        _ => NPU_MEM_BUDGET_MTL,
+    }
+}
+
+/// Tiles on a full part of a device generation (Meteor Lake if unknown)
+pub fn npu_max_tiles(device_id: u16) -> u8 {
+    match device_id {
+        PCI_DEVICE_LNL_NPU => NPU_TILES_LNL,
+        _ => NPU_TILES_MTL,
+    }
+}
+
+/// Usable tiles, decoded from `BUTTRESS_TILE_FUSE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+pub struct TileConfig {
+    /// One bit per usable tile
+    pub enabled_tiles: u32,
+    pub count: u8,
+    /// Tiles on a full part of this generation
+    pub max_tiles: u8,
+}
+
+impl TileConfig {
+    /// All tiles of a full part.
+    pub fn full(max_tiles: u8) -> Self {
+        let mask = (1u32 << max_tiles) - 1;
+        Self { enabled_tiles: mask, count: max_tiles, max_tiles }
+    }
+
+    /// Decode a fuse value. Like ivpu, an unprogrammed fuse (VALID clear)
+    /// means a full part.
+    pub fn from_fuse(fuse: u32, max_tiles: u8) -> Self {
+        let full = Self::full(max_tiles);
+        if fuse & TILE_FUSE_VALID == 0 {
+            return full;
+        }
+        let disabled = (fuse & TILE_FUSE_CONFIG_MASK) >> TILE_FUSE_CONFIG_SHIFT;
+        let enabled_tiles = full.enabled_tiles & !disabled;
+        Self { enabled_tiles, count: enabled_tiles.count_ones() as u8, max_tiles }
+    }
+
+    pub fn is_partial(&self) -> bool {
+        self.count < self.max_tiles
+    }
+
+    /// Scale a full-part figure (throughput, frequency budget) to the
+    /// usable tiles.
+    pub fn scale(&self, full_part_value: u64) -> u64 {
+        full_part_value * self.count as u64 / self.max_tiles.max(1) as u64
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"enabled_tiles\":{},\"count\":{},\"max_tiles\":{},\"expected_throughput_pct\":{}}}",
+            self.enabled_tiles, self.count, self.max_tiles, self.scale(100)
+        )
+    }
+}
+
+impl std::fmt::Display for TileConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{} tiles (mask {:#04x})", self.count, self.max_tiles, self.enabled_tiles)
+    }
+}
+
 /// Check if a PCI device ID is a supported NPU
 pub fn is_supported_device(device_id: u16) -> Option<&'static str> {
     SUPPORTED_DEVICES
@@ -209,3 +561,207 @@ pub fn is_supported_device(device_id: u16) -> Option<&'static str> {
         .find(|(id, _)| *id == device_id)
         .map(|(_, name)| *name)
 }
+
+// ============================================================
+// Symbolic Register Map
+// ============================================================
+
+/// Symbolic information about one MMIO register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegInfo {
+    pub offset: usize,
+    pub name: &'static str,
+    pub subsystem: &'static str,
+    pub description: &'static str,
+}
+
+// Generated by build.rs from the register constants and their doc comments.
+include!(concat!(env!("OUT_DIR"), "/register_map.rs"));
+
+/// Look up the register at a BAR0 offset of `regs`' generation. The
+/// entry keeps its Meteor Lake offset (`REGISTERS` is keyed by those).
+pub fn lookup(regs: &RegisterMap, offset: usize) -> Option<RegInfo> {
+    REGISTERS.iter().find(|r| regs.rebase(r.offset) == offset).copied()
+}
+
+/// Format an offset as `NAME (0x...)`, or just the hex offset if unknown.
+pub fn reg_name(regs: &RegisterMap, offset: usize) -> String {
+    match lookup(regs, offset) {
+        Some(info) => format!("{} ({:#07x})", info.name, offset),
+        None => format!("{:#07x}", offset),
+    }
+}
+
+/// Decode a register value into its known bitfields, if any. `offset` is
+/// the Meteor Lake offset (`RegInfo::offset`).
+pub fn decode_reg_value(offset: usize, value: u32) -> Option<String> {
+    let flag = |set: bool, on: &str, off: &str| Some(if set { on } else { off }.to_string());
+
+    match offset {
+        HOST_SS_FW_STATUS => Some(decode_fw_status(value).to_string()),
+        BUTTRESS_VPU_STATUS => flag(value & 0x1 != 0, "POWERED_ON", "POWERED_OFF"),
+        IPC_HOST_2_DEVICE_DRBL | IPC_DEVICE_2_HOST_DRBL => flag(value & IPC_DRBL_TRIGGER != 0, "RUNG", "IDLE"),
+        HOST_SS_CPR_RST_CLR | HOST_SS_CPR_RST_SET => flag(value & 0x1 != 0, "RESET_BIT_SET", "RESET_BIT_CLEAR"),
+        BUTTRESS_VPU_D0I3_CONTROL => Some(format!(
+            "{}{}",
+            if value & D0I3_CONTROL_I3 != 0 { "D0I3" } else { "D0" },
+            if value & D0I3_CONTROL_INPROGRESS != 0 { ", IN_PROGRESS" } else { "" }
+        )),
+        BUTTRESS_TILE_FUSE if value & TILE_FUSE_VALID == 0 => Some("NOT_PROGRAMMED (all tiles)".to_string()),
+        BUTTRESS_TILE_FUSE => Some(format!(
+            "VALID, fused off {:#04x}",
+            (value & TILE_FUSE_CONFIG_MASK) >> TILE_FUSE_CONFIG_SHIFT
+        )),
+        _ => None,
+    }
+}
+
+/// Format a register and its value, e.g. `HOST_SS_FW_STATUS (0x80060) = 0xf00d0000 [READY ...]`.
+pub fn format_reg(regs: &RegisterMap, offset: usize, value: u32) -> String {
+    match lookup(regs, offset).and_then(|info| decode_reg_value(info.offset, value)) {
+        Some(decoded) => format!("{} = {:#010x} [{}]", reg_name(regs, offset), value, decoded),
+        None => format!("{} = {:#010x}", reg_name(regs, offset), value),
+    }
+}
+
+/// Decode the PCI command register bits.
+pub fn decode_pci_command(cmd: u16) -> String {
+    let mut bits = Vec::new();
+    if cmd & PCI_CMD_IO_SPACE != 0 {
+        bits.push("IO_SPACE");
+    }
+    if cmd & PCI_CMD_MEMORY_SPACE != 0 {
+        bits.push("MEMORY_SPACE");
+    }
+    if cmd & PCI_CMD_BUS_MASTER != 0 {
+        bits.push("BUS_MASTER");
+    }
+    if bits.is_empty() {
+        "none".to_string()
+    } else {
+        bits.join("|")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every register offset constant in the MMIO map must appear exactly
+    /// once in the generated table, so the map cannot silently drift.
+    #[test]
+    fn test_register_table_complete() {
+        let source = include_str!("hw_mtl.rs");
+        let start = source.find("// BAR0 MMIO Register Map").unwrap();
+        let end = start + source[start..].find("// Firmware Status Codes").unwrap();
+
+        let consts: Vec<&str> = source[start..end]
+            .lines()
+            .filter_map(|l| l.trim().strip_prefix("pub const "))
+            .filter(|rest| rest.contains(": usize"))
+            .map(|rest| rest.split(':').next().unwrap().trim())
+            .filter(|name| !name.ends_with("_BASE"))
+            .collect();
+
+        assert!(!consts.is_empty());
+        for name in &consts {
+            let count = REGISTERS.iter().filter(|r| r.name == *name).count();
+            assert_eq!(count, 1, "{} appears {} times in the register table", name, count);
+        }
+        assert_eq!(REGISTERS.len(), consts.len());
+    }
+
+    #[test]
+    fn test_offsets_unique() {
+        for reg in REGISTERS {
+            assert_eq!(REGISTERS.iter().filter(|r| r.offset == reg.offset).count(), 1, "{}", reg.name);
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        let info = lookup(&REGS_MTL, HOST_SS_FW_STATUS).unwrap();
+        assert_eq!(info.name, "HOST_SS_FW_STATUS");
+        assert!(info.subsystem.starts_with("Host Subsystem"));
+        assert!(info.description.starts_with("Firmware status"));
+
+        // Undocumented registers share the description of their group
+        assert_eq!(lookup(&REGS_MTL, IPC_HOST_2_DEVICE_DATA3).unwrap().description, "IPC data payload registers (8x 32-bit)");
+
+        assert!(lookup(&REGS_MTL, 0x1234_5678).is_none());
+        assert_eq!(reg_name(&REGS_MTL, 0x0008_0060), "HOST_SS_FW_STATUS (0x80060)");
+
+        // Offsets are those of the map's generation
+        let moved = RegisterMap { host_ss_base: 0x9_0000, ..REGS_MTL };
+        assert_eq!(lookup(&moved, 0x9_0060).unwrap().name, "HOST_SS_FW_STATUS");
+        assert!(lookup(&moved, HOST_SS_FW_STATUS).is_none());
+        assert_eq!(lookup(&REGS_ARL, HOST_SS_FW_STATUS).unwrap().name, "HOST_SS_FW_STATUS");
+    }
+
+    #[test]
+    fn test_decode_values() {
+        assert!(format_reg(&REGS_MTL, HOST_SS_FW_STATUS, FW_STATUS_READY).contains("READY"));
+        assert!(format_reg(&REGS_MTL, BUTTRESS_VPU_STATUS, 1).contains("POWERED_ON"));
+        assert_eq!(format_reg(&REGS_MTL, HOST_SS_CLK_EN, 1), "HOST_SS_CLK_EN (0x80004) = 0x00000001");
+        assert_eq!(decode_pci_command(0x0006), "MEMORY_SPACE|BUS_MASTER");
+        assert_eq!(decode_pci_command(0), "none");
+        assert!(format_reg(&REGS_MTL, BUTTRESS_TILE_FUSE, 0x5).contains("fused off 0x02"));
+        assert!(format_reg(&REGS_MTL, BUTTRESS_VPU_D0I3_CONTROL, D0I3_CONTROL_I3).ends_with("[D0I3]"));
+        assert!(format_reg(&REGS_MTL, BUTTRESS_VPU_D0I3_CONTROL, D0I3_CONTROL_INPROGRESS).ends_with("[D0, IN_PROGRESS]"));
+    }
+
+    #[test]
+    fn test_tile_fuse_decoding() {
+        // Unprogrammed fuse: full part
+        assert_eq!(TileConfig::from_fuse(0, NPU_TILES_MTL), TileConfig::full(2));
+
+        let one = TileConfig::from_fuse(TILE_FUSE_VALID | (0b10 << TILE_FUSE_CONFIG_SHIFT), NPU_TILES_MTL);
+        assert_eq!((one.enabled_tiles, one.count), (0b01, 1));
+        assert!(one.is_partial());
+        assert_eq!(one.scale(1000), 500);
+
+        let lnl = TileConfig::from_fuse(TILE_FUSE_VALID | (0b111100 << TILE_FUSE_CONFIG_SHIFT), NPU_TILES_LNL);
+        assert_eq!((lnl.enabled_tiles, lnl.count), (0b000011, 2));
+        assert_eq!(lnl.to_string(), "2/6 tiles (mask 0x03)");
+        assert!(lnl.to_json().contains("\"expected_throughput_pct\":33"));
+        assert_eq!(npu_max_tiles(PCI_DEVICE_LNL_NPU), NPU_TILES_LNL);
+    }
+
+    #[test]
+    fn test_register_map_per_generation() {
+        let mtl = RegisterMap::for_device(PCI_DEVICE_MTL_NPU).unwrap();
+        assert_eq!(mtl.generation, HwGeneration::Mtl);
+        assert_eq!(mtl.fw_status, HOST_SS_FW_STATUS);
+        assert_eq!(mtl.h2d_doorbell, IPC_HOST_2_DEVICE_DRBL);
+        assert_eq!(mtl.h2d_data[3], IPC_HOST_2_DEVICE_DATA3);
+        assert_eq!(mtl.tile_fuse, BUTTRESS_TILE_FUSE);
+
+        // Arrow Lake is 37xx: the Meteor Lake layout under its own name
+        let arl = RegisterMap::for_device(PCI_DEVICE_ARL_NPU).unwrap();
+        assert_eq!(arl.generation, HwGeneration::Arl);
+        assert_eq!(RegisterMap { generation: HwGeneration::Mtl, ..*arl }, REGS_MTL);
+
+        // Moving a block moves every register in it, nothing else
+        let moved = RegisterMap::with_bases(HwGeneration::Mtl, BUTTRESS_BASE, 0x7_4000, 0x9_0000);
+        assert_eq!(moved.rebase(HOST_SS_FW_STATUS), moved.fw_status);
+        assert_eq!(moved.fw_status, 0x9_0060);
+        assert_eq!(moved.rebase(IPC_HOST_2_DEVICE_DATA2), moved.h2d_data[2]);
+        assert_eq!(moved.vpu_status, mtl.vpu_status);
+        assert_eq!(mtl.rebase(BUTTRESS_TILE_FUSE), BUTTRESS_TILE_FUSE);
+
+        // No guessed offsets for Lunar Lake
+        assert_eq!(RegisterMap::for_device(PCI_DEVICE_LNL_NPU), Err(UnsupportedGeneration(HwGeneration::Lnl)));
+        assert!(UnsupportedGeneration(HwGeneration::Lnl).to_string().contains("Lunar Lake"));
+        assert_eq!(RegisterMap::for_device(0xFFFF), Ok(&REGS_MTL));
+        assert_eq!(HwGeneration::from_device(0xFFFF), HwGeneration::Mtl);
+    }
+
+    #[test]
+    fn test_register_maps_fit_mock_bar() {
+        for regs in [&REGS_MTL, &REGS_ARL] {
+            let mut offsets = vec![regs.fw_status, regs.boot_count, regs.ipc_int_mask, regs.wp_req_cmd];
+            offsets.extend(regs.h2d_data);
+            assert!(offsets.iter().all(|&o| o < 1024 * 1024 && o % 4 == 0), "{}", regs.generation);
+        }
+    }
+}
@@ -19,11 +19,27 @@
 //! - Where the model weights are (DMA address)
 //! - Where the input data is (DMA address)
 //! - Where to write the output (DMA address)
+//!
+//! Finished jobs come back through a second ring, also in DMA memory: the
+//! firmware appends `(job_id, status)` entries and the driver drains them
+//! with `CommandQueue::poll_completions`, clearing each slot it reads.
+//! A command slot is reused only after its job has completed; when the
+//! next one is still busy, `submit` fails with `QueueFull` rather than
+//! overwriting a descriptor the NPU may not have read yet.
+//!
+//! The queue also owns the device's `PowerManager`: `suspend_if_idle` puts
+//! an idle NPU into D0i3, and `submit` wakes it before ringing the doorbell.
 
 use crate::dma::{DmaBuffer, DmaError};
+use crate::dma_pool::{DmaPool, PooledBuffer};
 use crate::hw_mtl::*;
 use crate::mmio::MmioRegion;
-use log::{debug, error, info};
+use crate::power::{PowerError, PowerManager};
+use log::{debug, error, info, warn};
+#[cfg(not(target_os = "redox"))]
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 /// Type of inference operation.
 #[repr(u32)]
@@ -113,6 +129,21 @@ impl CommandDescriptor {
         );
         unsafe { std::mem::transmute_copy(self) }
     }
+
+    /// Deserialize a descriptor read back from the command queue.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < CMD_DESC_SIZE {
+            return None;
+        }
+        // read_unaligned: the byte slice has no alignment guarantee
+        Some(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const Self) })
+    }
+
+    /// Total bytes the job needs on the device (model + input + output).
+    pub fn total_size(&self) -> usize {
+        let (model, input, output) = (self.model_size, self.input_size, self.output_size);
+        model as usize + input as usize + output as usize
+    }
 }
 
 impl std::fmt::Debug for CommandDescriptor {
@@ -144,16 +175,117 @@ impl std::fmt::Debug for CommandDescriptor {
     }
 }
 
+/// Ring the firmware posts job completions into.
+///
+/// Each entry is `job_id` then `status` (little-endian u32). Job IDs start
+/// at 1, so a zero `job_id` marks an empty slot; the driver zeroes every
+/// entry it consumes to hand the slot back to the firmware.
+pub struct CompletionRing {
+    buf: DmaBuffer,
+    capacity: usize,
+    /// Next slot the driver reads
+    read_idx: usize,
+    /// Next slot the firmware writes (mock and simulator only)
+    #[cfg(not(target_os = "redox"))]
+    fw_idx: Cell<usize>,
+}
+
+impl CompletionRing {
+    fn new(capacity: usize) -> Result<Self, DmaError> {
+        let size = capacity.checked_mul(COMPLETION_ENTRY_SIZE)
+            .ok_or(DmaError::OutOfBounds { offset: 0, len: capacity, capacity: COMPLETION_ENTRY_SIZE })?;
+        Ok(Self {
+            buf: DmaBuffer::new(size)?,
+            capacity,
+            read_idx: 0,
+            #[cfg(not(target_os = "redox"))]
+            fw_idx: Cell::new(0),
+        })
+    }
+
+    /// Physical address handed to the firmware at boot.
+    pub fn phys_addr(&self) -> u64 {
+        self.buf.phys_addr
+    }
+
+    /// Take the next posted completion, if any.
+    fn pop(&mut self) -> Option<(u32, u32)> {
+        let offset = self.read_idx * COMPLETION_ENTRY_SIZE;
+        let job_id = self.buf.read_u32(offset).ok()?;
+        if job_id == 0 {
+            return None;
+        }
+        let status = self.buf.read_u32(offset + 4).ok()?;
+        self.buf.write_u32(offset, 0).ok()?;
+        self.read_idx = (self.read_idx + 1) % self.capacity;
+        Some((job_id, status))
+    }
+
+    /// Post a completion the way the firmware would.
+    #[cfg(not(target_os = "redox"))]
+    pub(crate) fn post(&self, job_id: u32, status: u32) {
+        let offset = self.fw_idx.get() * COMPLETION_ENTRY_SIZE;
+        let _ = self.buf.write_u32(offset + 4, status);
+        // job_id last: it is what makes the entry visible
+        let _ = self.buf.write_u32(offset, job_id);
+        self.fw_idx.set((self.fw_idx.get() + 1) % self.capacity);
+    }
+}
+
+/// A job the firmware finished successfully.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobResult {
+    pub job_id: u32,
+    pub status: u32,
+    /// Time from submission to the driver seeing the completion
+    pub elapsed: Duration,
+}
+
+/// Bookkeeping for a submitted job.
+struct PendingJob {
+    /// Device memory requested (model + input + output)
+    requested: usize,
+    submitted: Instant,
+    /// Ring slot holding its descriptor
+    slot: usize,
+}
+
 /// The command queue ring buffer in DMA memory.
 pub struct CommandQueue {
     /// DMA buffer holding the ring of command descriptors
     ring: DmaBuffer,
     /// Current write position (index into ring)
     write_idx: usize,
+    /// Job whose descriptor occupies each ring slot (0 = free). A slot is
+    /// released when its job completes, so the ring never holds more jobs
+    /// than the completion ring has room for.
+    slots: Vec<u32>,
     /// Maximum number of entries
     capacity: usize,
     /// Next job ID to assign
     next_job_id: u32,
+    /// Per-job device memory budget (model + input + output)
+    mem_budget: usize,
+    /// Where the doorbell is on this device
+    regs: &'static RegisterMap,
+    /// Jobs awaiting completion, by job ID
+    pending: HashMap<u32, PendingJob>,
+    /// Where the firmware reports finished jobs
+    completions: CompletionRing,
+    /// Drained completions not yet collected with `take_result`
+    finished: HashMap<u32, Result<JobResult, InferenceError>>,
+    /// Jobs completed (successfully or not) since creation
+    completed: usize,
+    /// Where job buffers come from (unpooled until `set_pool`)
+    pool: DmaPool,
+    /// D0i3 entry when idle, wake-up on submission
+    power: PowerManager,
+    /// Mock firmware: delay before newly submitted jobs complete
+    #[cfg(not(target_os = "redox"))]
+    mock_latency: Option<Duration>,
+    /// Mock firmware: jobs still "running", with when they finish
+    #[cfg(not(target_os = "redox"))]
+    mock_running: Vec<(u32, Instant)>,
 }
 
 impl CommandQueue {
@@ -182,11 +314,94 @@ impl CommandQueue {
         Ok(Self {
             ring,
             write_idx: 0,
+            slots: vec![0; capacity],
             capacity,
             next_job_id: 1,
+            mem_budget: NPU_MEM_BUDGET_MTL,
+            regs: &REGS_MTL,
+            pending: HashMap::new(),
+            completions: CompletionRing::new(capacity)?,
+            finished: HashMap::new(),
+            completed: 0,
+            pool: DmaPool::unpooled(),
+            power: PowerManager::new(),
+            #[cfg(not(target_os = "redox"))]
+            mock_latency: None,
+            #[cfg(not(target_os = "redox"))]
+            mock_running: Vec::new(),
         })
     }
 
+    /// Take job buffers from `pool` instead of allocating each one.
+    pub fn set_pool(&mut self, pool: DmaPool) {
+        self.pool = pool;
+    }
+
+    /// Pool that job buffers (model, input, output) are taken from.
+    pub fn pool(&self) -> &DmaPool {
+        &self.pool
+    }
+
+    /// Set the per-job memory budget (see `npu_memory_budget`).
+    pub fn set_memory_budget(&mut self, bytes: usize) {
+        info!("Command queue memory budget: {} MB", bytes / (1024 * 1024));
+        self.mem_budget = bytes;
+    }
+
+    /// Ring the doorbell of this device generation (Meteor Lake by
+    /// default; `BootedNpu` sets it when it takes the queue).
+    pub fn set_register_map(&mut self, regs: &'static RegisterMap) {
+        self.regs = regs;
+        self.power.set_register_map(regs);
+    }
+
+    /// Power management of the device the queue submits to.
+    pub fn power(&self) -> &PowerManager {
+        &self.power
+    }
+
+    /// Configure power management (e.g. the idle timeout).
+    pub fn power_mut(&mut self) -> &mut PowerManager {
+        &mut self.power
+    }
+
+    /// Put the NPU into D0i3 if no job is in flight and none was submitted
+    /// for the idle timeout; `true` if it went to sleep now.
+    pub fn suspend_if_idle(&mut self, mmio: &MmioRegion) -> Result<bool, PowerError> {
+        self.power.suspend_if_idle(mmio, self.pending.len())
+    }
+
+    /// Current per-job memory budget in bytes.
+    pub fn memory_budget(&self) -> usize {
+        self.mem_budget
+    }
+
+    /// Check a job's buffers against the memory budget.
+    ///
+    /// Returns the total size on success.
+    pub fn check_memory(
+        &self,
+        model: &DmaBuffer,
+        input: &DmaBuffer,
+        output: &DmaBuffer,
+    ) -> Result<usize, InferenceError> {
+        let requested = model.size.checked_add(input.size)
+            .and_then(|n| n.checked_add(output.size))
+            .unwrap_or(usize::MAX);
+
+        if requested > self.mem_budget {
+            error!(
+                "Job rejected before submission: needs {} bytes, NPU budget is {} bytes",
+                requested, self.mem_budget
+            );
+            return Err(InferenceError::DeviceOutOfMemory {
+                requested,
+                available_hint: Some(self.mem_budget),
+            });
+        }
+        Ok(requested)
+    }
+
     /// Submit an inference job to the queue.
     ///
     /// Returns the job_id that can be used to track completion.
@@ -197,6 +412,23 @@ impl CommandQueue {
         input: &DmaBuffer,
         output: &DmaBuffer,
     ) -> Result<u32, InferenceError> {
+        // Fail fast on oversized jobs, before touching the hardware
+        let requested = self.check_memory(model, input, output)?;
+
+        // Never overwrite a descriptor the NPU has not finished with
+        let slot = self.write_idx;
+        if self.slots[slot] != 0 {
+            warn!(
+                "Command queue full: slot {} still holds job #{} ({} jobs in flight)",
+                slot, self.slots[slot], self.pending.len()
+            );
+            return Err(InferenceError::QueueFull);
+        }
+
+        // A sleeping NPU would never see the doorbell
+        self.power.ensure_awake(mmio).map_err(InferenceError::Power)?;
+        self.power.touch();
+
         let job_id = self.next_job_id;
         self.next_job_id += 1;
 
@@ -223,12 +455,156 @@ impl CommandQueue {
         self.write_idx = (self.write_idx + 1) % self.capacity;
 
         // Ring the doorbell to notify NPU — bit 31 must be set
-        mmio.write32(IPC_HOST_2_DEVICE_DRBL, IPC_DRBL_TRIGGER);
+        mmio.write32(self.regs.h2d_doorbell, IPC_DRBL_TRIGGER);
         debug!("  Doorbell rung for job #{}", job_id);
 
+        self.slots[slot] = job_id;
+        self.pending.insert(job_id, PendingJob { requested, submitted: Instant::now(), slot });
+        #[cfg(not(target_os = "redox"))]
+        if let Some(latency) = self.mock_latency {
+            self.mock_running.push((job_id, Instant::now() + latency));
+        }
         Ok(job_id)
     }
 
+    /// Handle a job completion reported by the firmware.
+    pub fn complete(&mut self, job_id: u32, status: u32) -> Result<(), InferenceError> {
+        self.finish(job_id, status).map(|_| ())
+    }
+
+    fn finish(&mut self, job_id: u32, status: u32) -> Result<JobResult, InferenceError> {
+        let pending = self.pending.remove(&job_id);
+        if let Some(p) = &pending {
+            self.slots[p.slot] = 0;
+            self.completed += 1;
+        }
+        if status == JOB_STATUS_SUCCESS {
+            let elapsed = pending.map(|p| p.submitted.elapsed()).unwrap_or_default();
+            return Ok(JobResult { job_id, status, elapsed });
+        }
+
+        let err = InferenceError::from_job_status(job_id, status, pending.map(|p| p.requested), self.mem_budget);
+        error!("{}", err);
+        Err(err)
+    }
+
+    /// Drain the completion ring.
+    ///
+    /// Returns the IDs of the jobs that finished; collect each outcome with
+    /// `take_result`.
+    pub fn poll_completions(&mut self) -> Vec<u32> {
+        #[cfg(not(target_os = "redox"))]
+        self.run_mock_firmware();
+
+        let mut done = Vec::new();
+        while let Some((job_id, status)) = self.completions.pop() {
+            if !self.pending.contains_key(&job_id) {
+                // Already handled via `complete`, or from before a reset
+                debug!("Ignoring completion for unknown job #{}", job_id);
+                continue;
+            }
+            let result = self.finish(job_id, status);
+            self.finished.insert(job_id, result);
+            done.push(job_id);
+        }
+        done
+    }
+
+    /// Outcome of a job drained by `poll_completions`, once.
+    pub fn take_result(&mut self, job_id: u32) -> Option<Result<JobResult, InferenceError>> {
+        self.finished.remove(&job_id)
+    }
+
+    /// Poll until `job_id` completes or `timeout` passes.
+    ///
+    /// Completions of other jobs seen meanwhile stay available through
+    /// `take_result`.
+    pub fn wait_for_completion(&mut self, job_id: u32, timeout: Duration) -> Result<JobResult, InferenceError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            self.poll_completions();
+            if let Some(result) = self.take_result(job_id) {
+                return result;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(InferenceError::Timeout { job_id });
+            }
+            std::thread::sleep((deadline - now).min(Duration::from_millis(POLL_INTERVAL_MS)));
+        }
+    }
+
+    /// Fail every job in flight with ABORTED after the device was reset
+    /// under them; returns their IDs. Outcomes are collected with
+    /// `take_result` as usual, and late completions are ignored.
+    pub fn abort_in_flight(&mut self) -> Vec<u32> {
+        #[cfg(not(target_os = "redox"))]
+        self.mock_running.clear();
+
+        let ids: Vec<u32> = self.pending.keys().copied().collect();
+        for &job_id in &ids {
+            let result = self.finish(job_id, JOB_STATUS_ABORTED);
+            self.finished.insert(job_id, result);
+        }
+        if !ids.is_empty() {
+            warn!("Aborted {} job(s) lost in an NPU reset", ids.len());
+        }
+        ids
+    }
+
+    /// Id the next accepted job will be given.
+    pub fn next_job_id(&self) -> u32 {
+        self.next_job_id
+    }
+
+    /// Jobs submitted but not yet completed.
+    pub fn in_flight(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Jobs that can be submitted before `submit` returns `QueueFull`.
+    ///
+    /// Counts free slots from the write position on; a job that completes
+    /// out of order frees its slot only once the ring wraps back to it.
+    pub fn capacity_remaining(&self) -> usize {
+        (0..self.capacity)
+            .take_while(|i| self.slots[(self.write_idx + i) % self.capacity] == 0)
+            .count()
+    }
+
+    /// Ring the firmware posts completions into.
+    pub fn completion_ring(&self) -> &CompletionRing {
+        &self.completions
+    }
+
+    /// Have the mock firmware complete each job `latency` after it is
+    /// submitted (`None`, the default, leaves jobs to a simulator).
+    ///
+    /// Applies to jobs submitted from now on, so changing it between
+    /// submissions makes jobs finish out of order.
+    #[cfg(not(target_os = "redox"))]
+    pub fn set_mock_latency(&mut self, latency: Option<Duration>) {
+        self.mock_latency = latency;
+    }
+
+    /// Post completions for mock jobs whose time is up.
+    #[cfg(not(target_os = "redox"))]
+    fn run_mock_firmware(&mut self) {
+        let now = Instant::now();
+        let (due, running): (Vec<_>, Vec<_>) = self.mock_running.drain(..).partition(|(_, at)| *at <= now);
+        self.mock_running = running;
+        for (job_id, _) in due {
+            self.completions.post(job_id, JOB_STATUS_SUCCESS);
+        }
+    }
+
+    /// Descriptor most recently written to the ring.
+    pub(crate) fn last_descriptor(&self) -> Option<CommandDescriptor> {
+        let slot = (self.write_idx + self.capacity - 1) % self.capacity;
+        let bytes = self.ring.read_bytes(slot * CMD_DESC_SIZE, CMD_DESC_SIZE).ok()?;
+        CommandDescriptor::from_bytes(&bytes)
+    }
+
     /// Get the physical address of the command queue (for NPU registration).
     pub fn phys_addr(&self) -> u64 {
         self.ring.phys_addr
@@ -240,6 +616,8 @@ impl CommandQueue {
             capacity: self.capacity,
             write_idx: self.write_idx,
             total_submitted: self.next_job_id as usize - 1,
+            completed: self.completed,
+            in_flight: self.pending.len(),
         }
     }
 }
@@ -250,14 +628,16 @@ pub struct QueueStats {
     pub capacity: usize,
     pub write_idx: usize,
     pub total_submitted: usize,
+    pub completed: usize,
+    pub in_flight: usize,
 }
 
 impl std::fmt::Display for QueueStats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Queue: write_idx={}, capacity={}, total_submitted={}",
-            self.write_idx, self.capacity, self.total_submitted
+            "Queue: write_idx={}, capacity={}, total_submitted={}, completed={}, in_flight={}",
+            self.write_idx, self.capacity, self.total_submitted, self.completed, self.in_flight
         )
     }
 }
@@ -267,20 +647,37 @@ impl std::fmt::Display for QueueStats {
 // ============================================================
 
 /// Prepare an input buffer from raw data (e.g., audio samples, image pixels).
-pub fn prepare_input(data: &[u8]) -> Result<DmaBuffer, DmaError> {
-    let buf = DmaBuffer::new(data.len())?;
+///
+/// Inputs may carry user data (audio features, screen pixels); pool
+/// buffers are zeroed when returned, direct ones scrubbed when freed.
+pub fn prepare_input(pool: &DmaPool, data: &[u8]) -> Result<PooledBuffer, DmaError> {
+    let buf = pool.get(data.len())?;
     buf.write_bytes(0, data)?;
     debug!("Input buffer: {} bytes at phys={:#x}", data.len(), buf.phys_addr);
     Ok(buf)
 }
 
-/// Allocate an output buffer of the given size.
-pub fn prepare_output(size: usize) -> Result<DmaBuffer, DmaError> {
-    let buf = DmaBuffer::new(size)?;
+/// Take an output buffer of the given size from `pool`.
+pub fn prepare_output(pool: &DmaPool, size: usize) -> Result<PooledBuffer, DmaError> {
+    let buf = pool.get(size)?;
     debug!("Output buffer: {} bytes at phys={:#x}", size, buf.phys_addr);
     Ok(buf)
 }
 
+/// Take a pending job completion from the device→host IPC registers.
+///
+/// The firmware posts `(job_id, status)` in DATA0/DATA1 and rings the
+/// device→host doorbell; clearing the doorbell acknowledges it.
+pub fn read_completion(mmio: &MmioRegion, regs: &RegisterMap) -> Option<(u32, u32)> {
+    if mmio.read32(regs.d2h_doorbell) & IPC_DRBL_TRIGGER == 0 {
+        return None;
+    }
+    let job_id = mmio.read32(regs.d2h_data[0]);
+    let status = mmio.read32(regs.d2h_data[1]);
+    mmio.write32(regs.d2h_doorbell, 0);
+    Some((job_id, status))
+}
+
 /// Read inference results from an output buffer.
 ///
 /// Uses volatile reads to ensure hardware-written DMA data is read correctly.
@@ -299,6 +696,30 @@ pub enum InferenceError {
     BufferTooLarge,
     Timeout { job_id: u32 },
     NpuError { job_id: u32, status: u32 },
+    /// Job does not fit in device memory. `available_hint` is the budget
+    /// the driver knows about, if any.
+    DeviceOutOfMemory { requested: usize, available_hint: Option<usize> },
+    /// The NPU could not be woken from D0i3 for the job
+    Power(PowerError),
+}
+
+impl InferenceError {
+    /// Map a firmware job status to a typed error.
+    pub fn from_job_status(job_id: u32, status: u32, requested: Option<usize>, budget: usize) -> Self {
+        if is_oom_status(status) {
+            Self::DeviceOutOfMemory {
+                requested: requested.unwrap_or(0),
+                available_hint: Some(budget),
+            }
+        } else {
+            Self::NpuError { job_id, status }
+        }
+    }
+
+    /// Whether retrying on another device (e.g. the CPU) could succeed.
+    pub fn is_out_of_memory(&self) -> bool {
+        matches!(self, Self::DeviceOutOfMemory { .. })
+    }
 }
 
 impl std::fmt::Display for InferenceError {
@@ -309,8 +730,16 @@ impl std::fmt::Display for InferenceError {
             Self::BufferTooLarge => write!(f, "DMA buffer exceeds u32::MAX (4 GB limit for NPU descriptors)"),
             Self::Timeout { job_id } => write!(f, "Inference job #{} timed out", job_id),
             Self::NpuError { job_id, status } => {
-                write!(f, "NPU error on job #{}: status={:#010x}", job_id, status)
+                write!(f, "NPU error on job #{}: status={:#010x} ({})", job_id, status, decode_job_status(*status))
+            }
+            Self::DeviceOutOfMemory { requested, available_hint } => {
+                write!(f, "NPU out of memory: job needs {} MB", requested / (1024 * 1024))?;
+                if let Some(budget) = available_hint {
+                    write!(f, ", device budget is {} MB", budget / (1024 * 1024))?;
+                }
+                write!(f, " (use a smaller model or run it on the CPU)")
             }
+            Self::Power(e) => write!(f, "NPU did not wake up: {}", e),
         }
     }
 }
@@ -0,0 +1,261 @@
+//! NPU Interrupts — Wake on IRQ Instead of Polling
+//!
+//! On Redox the PCI interrupt line is a file in the `irq:` scheme: a read
+//! blocks until the line fires, and writing the count back acknowledges it.
+//! A reader thread blocks on that file and hands each interrupt to the
+//! driver thread over a channel. `MmioRegion` is not `Sync`, so the thread
+//! never touches registers; the driver thread reads them when it wakes and
+//! turns the interrupt into `IrqEvent`s (firmware status change, job
+//! completion, error), then lets the thread acknowledge the line.
+//!
+//! Off Redox the source is simulated: the mock firmware raises an
+//! interrupt when it comes up, and tests inject their own. Without a
+//! source (no line routed, or the `irq:` file can't be opened) callers
+//! sleep out their poll interval as before.
+
+use crate::hw_mtl::*;
+use crate::mmio::MmioRegion;
+use log::{debug, warn};
+use std::cell::Cell;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// What an interrupt meant, read from the device once it fired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqEvent {
+    /// FW_STATUS changed (boot progress, READY, DEAD, ...)
+    FwStatus(u32),
+    /// The firmware rang the device→host doorbell; the completion is left
+    /// in DATA0/DATA1 for `inference::read_completion`
+    JobComplete,
+    /// Error bits set in the Buttress interrupt status (already cleared)
+    Error(u32),
+}
+
+impl std::fmt::Display for IrqEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FwStatus(status) => write!(f, "firmware status {:#010x} ({})", status, decode_fw_status(*status)),
+            Self::JobComplete => write!(f, "job completion"),
+            Self::Error(bits) => write!(f, "error interrupt {:#06x}", bits),
+        }
+    }
+}
+
+/// Interrupts of one NPU, delivered to the driver thread.
+pub struct Interrupts {
+    /// One message per interrupt
+    fired: Receiver<()>,
+    /// Tells the reader thread an interrupt was handled and may be acknowledged
+    #[cfg(target_os = "redox")]
+    handled: Sender<()>,
+    /// The simulated line
+    #[cfg(not(target_os = "redox"))]
+    raise: Sender<()>,
+    /// FW_STATUS when last looked at, to report changes only
+    last_status: Cell<u32>,
+    /// The reader thread is gone; waits fall back to sleeping
+    lost: Cell<bool>,
+}
+
+impl Interrupts {
+    /// The interrupt source for the NPU's legacy interrupt `line`, or
+    /// `None` to poll: no line routed, or its `irq:` file can't be opened.
+    #[cfg(target_os = "redox")]
+    pub fn for_line(line: Option<u8>) -> Option<Self> {
+        use log::info;
+
+        let Some(line) = line else {
+            warn!("⚠️  No interrupt line routed to the NPU, polling instead");
+            return None;
+        };
+        match Self::open(line) {
+            Ok(irq) => {
+                info!("🔔 Waiting on irq:{} for NPU interrupts", line);
+                Some(irq)
+            }
+            Err(e) => {
+                warn!("⚠️  Cannot open irq:{} ({}), polling instead", line, e);
+                None
+            }
+        }
+    }
+
+    #[cfg(target_os = "redox")]
+    fn open(line: u8) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new().read(true).write(true).open(format!("irq:{}", line))?;
+        let (fired_tx, fired) = mpsc::channel();
+        let (handled, handled_rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("npu-irq".to_string())
+            .spawn(move || forward(file, fired_tx, handled_rx))?;
+        Ok(Self { fired, handled, last_status: Cell::new(0), lost: Cell::new(false) })
+    }
+
+    /// The simulated interrupt source of the mock device (`line` is not
+    /// used; the mock PCI device has none).
+    #[cfg(not(target_os = "redox"))]
+    pub fn for_line(_line: Option<u8>) -> Option<Self> {
+        let (raise, fired) = mpsc::channel();
+        Some(Self { fired, raise, last_status: Cell::new(0), lost: Cell::new(false) })
+    }
+
+    /// Fire the simulated line, as the mock firmware does when it comes up.
+    #[cfg(not(target_os = "redox"))]
+    pub fn raise(&self) {
+        let _ = self.raise.send(());
+    }
+
+    /// A handle that fires the simulated line from another thread.
+    #[cfg(test)]
+    pub fn injector(&self) -> Sender<()> {
+        self.raise.clone()
+    }
+
+    /// Block until the NPU raises an interrupt or `timeout` passes, then
+    /// report what changed on the device.
+    ///
+    /// Registers are read either way, so a change the device made without
+    /// interrupting (or while masked) still shows up, one timeout late.
+    pub fn wait(&self, mmio: &MmioRegion, regs: &RegisterMap, timeout: Duration) -> Vec<IrqEvent> {
+        let mut fired = 0;
+        if self.lost.get() {
+            thread::sleep(timeout);
+        } else {
+            match self.fired.recv_timeout(timeout) {
+                Ok(()) => fired = 1 + self.fired.try_iter().count(),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    warn!("⚠️  NPU interrupt thread stopped, polling instead");
+                    self.lost.set(true);
+                }
+            }
+        }
+
+        let events = self.classify(mmio, regs);
+        debug!("IRQ: {} fired, {:?}", fired, events);
+        #[cfg(target_os = "redox")]
+        for _ in 0..fired {
+            let _ = self.handled.send(());
+        }
+        events
+    }
+
+    /// Read what the device has to say; error bits are cleared, the
+    /// doorbell is left to whoever takes the completion.
+    fn classify(&self, mmio: &MmioRegion, regs: &RegisterMap) -> Vec<IrqEvent> {
+        let mut events = Vec::new();
+
+        let status = mmio.read32(regs.fw_status);
+        if self.last_status.replace(status) != status {
+            events.push(IrqEvent::FwStatus(status));
+        }
+
+        let int_sts = mmio.read32(regs.global_int_sts);
+        if int_sts & BUTTRESS_INT_STS_ERR_MASK != 0 {
+            events.push(IrqEvent::Error(int_sts & BUTTRESS_INT_STS_ERR_MASK));
+            // Write-1-to-clear on hardware; the fake BAR just keeps the value
+            mmio.write32(regs.global_int_sts, int_sts);
+            #[cfg(not(target_os = "redox"))]
+            mmio.write32(regs.global_int_sts, 0);
+        }
+
+        if mmio.read32(regs.d2h_doorbell) & IPC_DRBL_TRIGGER != 0 {
+            events.push(IrqEvent::JobComplete);
+        }
+        events
+    }
+}
+
+/// Reader thread: block on the `irq:` file and pass each interrupt on.
+///
+/// The line is acknowledged only once the driver thread has handled the
+/// interrupt; a level-triggered line acknowledged earlier fires again
+/// straight away.
+#[cfg(target_os = "redox")]
+fn forward(mut file: std::fs::File, fired: Sender<()>, handled: Receiver<()>) {
+    use std::io::{Read, Write};
+
+    let mut count = [0u8; 8];
+    loop {
+        match file.read(&mut count) {
+            Ok(0) => return,
+            Ok(_) => {}
+            Err(e) => {
+                warn!("⚠️  Reading the NPU interrupt line failed: {}", e);
+                return;
+            }
+        }
+        // Either side gone means the driver is shutting down
+        if fired.send(()).is_err() || handled.recv().is_err() {
+            return;
+        }
+        if let Err(e) = file.write(&count) {
+            warn!("⚠️  Acknowledging the NPU interrupt failed: {}", e);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// A zeroed fake BAR0 and its MMIO view
+    fn bar() -> (Box<[u32]>, MmioRegion) {
+        let mut bar = vec![0u32; 1024 * 1024 / 4].into_boxed_slice();
+        // SAFETY: the boxed slice outlives the region in every test below
+        let mmio = unsafe { MmioRegion::new(bar.as_mut_ptr() as *mut u8, 1024 * 1024) };
+        (bar, mmio)
+    }
+
+    #[test]
+    fn test_injected_interrupt_wakes_wait_early() {
+        let (_bar, mmio) = bar();
+        let irq = Interrupts::for_line(None).unwrap();
+        let injector = irq.injector();
+
+        let fire = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            injector.send(()).unwrap();
+        });
+        let start = Instant::now();
+        irq.wait(&mmio, &REGS_MTL, Duration::from_secs(5));
+        let woke = start.elapsed();
+        fire.join().unwrap();
+
+        assert!(woke >= Duration::from_millis(20), "woke after {:?}", woke);
+        assert!(woke < Duration::from_secs(1), "woke after {:?}", woke);
+
+        // Nothing pending: the full timeout
+        let start = Instant::now();
+        assert!(irq.wait(&mmio, &REGS_MTL, Duration::from_millis(30)).is_empty());
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn test_events_read_from_registers() {
+        let (_bar, mmio) = bar();
+        let irq = Interrupts::for_line(None).unwrap();
+
+        mmio.write32(HOST_SS_FW_STATUS, FW_STATUS_READY);
+        mmio.write32(IPC_DEVICE_2_HOST_DRBL, IPC_DRBL_TRIGGER);
+        mmio.write32(BUTTRESS_GLOBAL_INT_STS, 0x1 | 0x4);
+        irq.raise();
+        let events = irq.wait(&mmio, &REGS_MTL, Duration::from_secs(1));
+        assert_eq!(events, vec![IrqEvent::FwStatus(FW_STATUS_READY), IrqEvent::Error(0x4), IrqEvent::JobComplete]);
+
+        // Reported once: the status didn't change and the error was cleared;
+        // the doorbell stays rung until the completion is taken
+        irq.raise();
+        irq.raise();
+        assert_eq!(irq.wait(&mmio, &REGS_MTL, Duration::from_secs(1)), vec![IrqEvent::JobComplete]);
+        assert_eq!(mmio.read32(BUTTRESS_GLOBAL_INT_STS), 0);
+
+        mmio.write32(HOST_SS_FW_STATUS, FW_STATUS_DEAD);
+        mmio.write32(IPC_DEVICE_2_HOST_DRBL, 0);
+        assert_eq!(irq.wait(&mmio, &REGS_MTL, Duration::from_millis(1)), vec![IrqEvent::FwStatus(FW_STATUS_DEAD)]);
+    }
+}
@@ -0,0 +1,25 @@
+//! Intel NPU driver for Redox OS, as a library
+//!
+//! The `intel-npu` daemon (`main.rs`) is built on these modules; the
+//! library target also lets rustdoc compile the examples in their docs,
+//! including the `compile_fail` ones that pin down `BootedNpu`'s ownership
+//! rules.
+
+pub mod boot;
+pub mod dma;
+pub mod dma_pool;
+pub mod events;
+pub mod fw_image;
+#[cfg(test)]
+mod fwsim;
+pub mod hw_mtl;
+pub mod inference;
+pub mod irq;
+pub mod mmio;
+pub mod model_cache;
+pub mod pci;
+pub mod power;
+pub mod regdump;
+#[cfg(any(target_os = "redox", test))]
+pub mod scheme;
+pub mod status;
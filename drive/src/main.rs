@@ -9,26 +9,41 @@
 //!   - Loads Intel VPU firmware and monitors health
 //!
 //! Usage:
-//!   intel-npu [--firmware PATH] [--test] [--diagnostics]
+//!   intel-npu [--firmware PATH] [--test] [--diagnostics [--json]]
+//!             [--event-log PATH] [--events [--since TIME]]
+//!             [--dump-regs [START-END | START+LEN]] [--reset]
+//!             [--firmware-info PATH] [--idle-suspend SECS]
+//!             [--submit-group GID]
+//!
+//! `--diagnostics --json` (or `--diagnostics-json`) prints one JSON
+//! document instead of the report box, with the recent state transitions
+//! and lifecycle events, for bug reports and eva-daemon.
+//!
+//! `--firmware-info PATH` prints the header and sections of a firmware
+//! file without touching the hardware.
+//!
+//! `--reset` cold-resets the NPU before booting it, for a device left
+//! wedged by a previous driver instance. A running driver is reset by
+//! writing `reset` to `npu:control`.
+//!
+//! `--idle-suspend SECS` puts the NPU into D0i3 after that long without a
+//! job (default 30, 0 keeps it powered); the next submission wakes it.
+//!
+//! `--submit-group GID` lets members of that group submit jobs through
+//! `npu:submit` (otherwise only root can); status stays readable by all.
 //!
 //! On Redox OS, this runs as a daemon via redox-daemon.
 //! On other OS, it runs in mock mode for development/testing.
 
-mod boot;
-mod dma;
-mod hw_mtl;
-mod inference;
-mod mmio;
-mod pci;
+use intel_npu::boot::BootSequence;
+use intel_npu::events::{EventKind, EventLog};
+use intel_npu::hw_mtl::*;
+use intel_npu::inference::CommandQueue;
+use intel_npu::status::{DeviceSummary, StatusMonitor};
+use intel_npu::{boot, dma_pool, events, fw_image, irq, pci, power, regdump};
 #[cfg(target_os = "redox")]
-mod scheme;
-mod status;
-
-use boot::BootSequence;
-use hw_mtl::*;
-use inference::CommandQueue;
+use intel_npu::{model_cache, scheme};
 use log::{error, info, warn};
-use status::StatusMonitor;
 
 /// Default firmware paths to search
 const FW_SEARCH_PATHS: &[&str] = &[
@@ -51,11 +66,93 @@ fn main() {
     let args: Vec<String> = std::env::args().collect();
     let test_mode = args.iter().any(|a| a == "--test");
     let diag_mode = args.iter().any(|a| a == "--diagnostics");
-    let fw_path = args
-        .iter()
-        .position(|a| a == "--firmware")
-        .and_then(|i| args.get(i + 1))
-        .map(|s| s.as_str());
+    let diag_json = args.iter().any(|a| a == "--diagnostics-json")
+        || (diag_mode && args.iter().any(|a| a == "--json"));
+    let events_mode = args.iter().any(|a| a == "--events");
+    let reset = args.iter().any(|a| a == "--reset");
+    let fw_path = arg_value(&args, "--firmware");
+    let event_log_path = arg_value(&args, "--event-log");
+    let idle_timeout = match arg_value(&args, "--idle-suspend") {
+        Some(s) => match s.parse::<u64>() {
+            Ok(0) => None,
+            Ok(secs) => Some(std::time::Duration::from_secs(secs)),
+            Err(_) => {
+                error!("❌ Invalid --idle-suspend value: {} (seconds, 0 to never suspend)", s);
+                std::process::exit(1);
+            }
+        },
+        None => Some(power::DEFAULT_IDLE_TIMEOUT),
+    };
+    let submit_group = arg_value(&args, "--submit-group").map(|gid| match gid.parse::<u32>() {
+        Ok(gid) => gid,
+        Err(_) => {
+            error!("❌ Invalid --submit-group value: {} (numeric gid)", gid);
+            std::process::exit(1);
+        }
+    });
+    // --dump-regs takes an optional raw range after the named registers
+    let dump_regs = match args.iter().position(|a| a == "--dump-regs") {
+        Some(i) => match args.get(i + 1).filter(|v| !v.starts_with("--")) {
+            Some(s) => match regdump::parse_range(s) {
+                Some(range) => Some(Some(range)),
+                None => {
+                    error!("❌ Invalid --dump-regs range: {} (use e.g. 0x80000-0x80100 or 0x73000+0x40)", s);
+                    std::process::exit(1);
+                }
+            },
+            None => Some(None),
+        },
+        None => None,
+    };
+
+    // --firmware-info only reads the file; no hardware access, no banner
+    if let Some(path) = arg_value(&args, "--firmware-info") {
+        let parsed = std::fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| fw_image::parse(&data).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(fw_info) => {
+                print!("{}", fw_info);
+                std::process::exit(0);
+            }
+            Err(e) => {
+                error!("❌ Cannot read firmware {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // --events only reads the log file; no hardware access, no banner
+    if events_mode {
+        let path = event_log_path
+            .map(|s| s.to_string())
+            .or_else(|| std::env::var("NPU_EVENT_LOG").ok())
+            .unwrap_or_else(|| events::DEFAULT_EVENT_LOG_PATH.to_string());
+        let since = match arg_value(&args, "--since") {
+            Some(s) => match events::parse_since(s) {
+                Some(ms) => Some(ms),
+                None => {
+                    error!("❌ Invalid --since value: {} (use e.g. 30m, 2h, 1d or a Unix timestamp)", s);
+                    std::process::exit(1);
+                }
+            },
+            None => None,
+        };
+        match events::read_events(&path) {
+            Ok(list) => {
+                events::print_events(&list, since);
+                std::process::exit(0);
+            }
+            Err(e) => {
+                error!("❌ Cannot read event log {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(path) = event_log_path {
+        std::env::set_var("NPU_EVENT_LOG", path);
+    }
 
     // === Banner ===
     println!();
@@ -79,7 +176,7 @@ fn main() {
     // === Run the driver ===
     // We use a separate scope so that all resources (DMA buffers, MMIO mappings)
     // are properly dropped BEFORE process exit, preventing resource leaks.
-    let exit_code = match run_driver(fw_path, test_mode, diag_mode) {
+    let exit_code = match run_driver(fw_path, test_mode, diag_mode, diag_json, dump_regs, reset, ServiceOptions { idle_timeout, submit_group }) {
         Ok(()) => {
             info!("Driver shut down cleanly.");
             0
@@ -94,11 +191,26 @@ fn main() {
     std::process::exit(exit_code);
 }
 
+/// How the driver runs once the NPU is up.
+struct ServiceOptions {
+    /// `--idle-suspend`; `None` keeps the NPU in D0
+    idle_timeout: Option<std::time::Duration>,
+    /// `--submit-group`
+    submit_group: Option<u32>,
+}
+
 fn run_driver(
     fw_path_override: Option<&str>,
     test_mode: bool,
     diag_mode: bool,
+    diag_json: bool,
+    dump_regs: Option<Option<std::ops::Range<usize>>>,
+    reset: bool,
+    service: ServiceOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    // Persistent lifecycle log (optional — the driver runs without it)
+    let event_log = EventLog::open_default();
+
     // ================================================================
     // Step 1: PCI Discovery
     // ================================================================
@@ -110,21 +222,50 @@ fn run_driver(
     println!("   Device : {} (ID: {:#06x})", npu.device_name, npu.device_id);
     println!("   PCI BDF: {}", npu.bdf);
     println!("   BAR0   : {:#x} ({} KB)", npu.bar0_phys, npu.bar0_size / 1024);
+    println!("   Regs   : {} map", npu.regs.generation);
     println!();
 
+    // Register dump only: reads BAR0, writes nothing, no boot
+    if let Some(raw) = dump_regs {
+        print!("{}", regdump::dump_table(&npu.mmio, npu.regs, raw)?);
+        return Ok(());
+    }
+
     // ================================================================
     // Step 2: Initial Status Check
     // ================================================================
     info!("━━━ Phase 2: Initial Status ━━━");
 
     let mut monitor = StatusMonitor::new(&npu.mmio);
+    monitor.set_device(npu.device_id)?;
+    if let Some(log) = &event_log {
+        monitor.set_event_log(log);
+    }
     let initial_state = monitor.poll();
 
     println!("📊 Initial NPU State: {}", initial_state);
     println!("   Raw FW_STATUS : {:#010x}", monitor.raw_status());
     println!("   Buttress      : {:#010x}", monitor.buttress_status());
+    println!("   Tiles         : {}", monitor.tile_config());
     println!();
 
+    // Machine-readable diagnostics including the last 50 events
+    if diag_json {
+        let recent = event_log
+            .as_ref()
+            .and_then(|log| log.events().ok())
+            .unwrap_or_default();
+        let start = recent.len().saturating_sub(50);
+        let device = DeviceSummary {
+            device_id: npu.device_id,
+            name: npu.device_name.to_string(),
+            bdf: npu.bdf.clone(),
+            bar0_size: npu.bar0_size as u64,
+        };
+        println!("{}", monitor.diagnostics(device, VERSION, &recent[start..]).to_json());
+        return Ok(());
+    }
+
     // If diagnostics only, print and exit
     if diag_mode {
         monitor.print_diagnostics();
@@ -163,17 +304,49 @@ fn run_driver(
     // ================================================================
     info!("━━━ Phase 4: Boot Sequence ━━━");
 
-    let boot = BootSequence::new(&npu.mmio);
-    let (boot_result, _fw_buffer) = boot.execute(&fw_path)?;
-
-    // IMPORTANT: _fw_buffer must remain alive for the entire driver lifetime.
-    // The NPU references the firmware at its physical DMA address.
-    // Dropping it would cause a use-after-free on the hardware DMA path.
+    // Allocated up front so the booted device owns it from the start
+    let mut cmd_queue = CommandQueue::new(CMD_QUEUE_SIZE)?;
+    cmd_queue.set_memory_budget(npu_memory_budget(npu.device_id));
+    // Job buffers are reused instead of mapped fresh for every inference
+    cmd_queue.set_pool(dma_pool::DmaPool::new(dma_pool::DEFAULT_CLASSES)?);
+    // Idle NPU goes to D0i3; the next submission wakes it
+    cmd_queue.power_mut().set_idle_timeout(service.idle_timeout);
+
+    // The boot wait (and the mock heartbeat) wake on interrupts when there are any
+    let irq = irq::Interrupts::for_line(npu.irq_line);
+    let mut boot = BootSequence::new(&npu.mmio).with_device(npu.device_id)?;
+    if let Some(irq) = &irq {
+        boot = boot.with_interrupts(irq);
+    }
+    if reset {
+        info!("Cold reset requested (--reset)");
+        boot.cold_reset()?;
+    }
+    let boot_start = std::time::Instant::now();
+    let boot_outcome = boot.execute(&fw_path, cmd_queue);
+    if let Some(log) = &event_log {
+        let code = match &boot_outcome {
+            Ok(booted) => match booted.result() {
+                boot::BootResult::Ready { .. } => 0,
+                boot::BootResult::Ambiguous { .. } => 1,
+            },
+            Err(_) => 2,
+        };
+        log.record(EventKind::BootAttempt, code, boot_start.elapsed().as_millis() as u64);
+        if code != 2 {
+            log.record(EventKind::PowerTransition, 0, 0);
+        }
+    }
+    // Owns the firmware and command queue; dropping it (or shutdown())
+    // quiesces the NPU before any DMA memory is released.
+    let booted = boot_outcome?;
 
-    match &boot_result {
+    match booted.result() {
         boot::BootResult::Ready { fw_version } => {
             println!("🎉 NPU BOOT SUCCESSFUL!");
             println!("   Firmware Version: {:#010x}", fw_version);
+            println!("   Firmware Build  : {}", booted.firmware_info().version);
+            println!("   Tiles           : {}", booted.tiles());
         }
         boot::BootResult::Ambiguous { status } => {
             println!("⚠️  NPU boot ambiguous: {:#010x}", status);
@@ -182,24 +355,12 @@ fn run_driver(
     println!();
 
     // ================================================================
-    // Step 5: Initialize Command Queue
+    // Step 5: Command Queue
     // ================================================================
-    info!("━━━ Phase 5: Command Queue Init ━━━");
+    info!("━━━ Phase 5: Command Queue ━━━");
 
-    let mut cmd_queue = CommandQueue::new(CMD_QUEUE_SIZE)?;
     println!("📋 Command Queue ready ({} slots)", CMD_QUEUE_SIZE);
-    println!("   Physical Address: {:#010x}", cmd_queue.phys_addr());
-
-    // Register the command queue physical address with the NPU hardware.
-    // The NPU reads commands from this DMA address when the doorbell is rung.
-    let queue_phys = cmd_queue.phys_addr();
-    npu.mmio.write32(IPC_HOST_2_DEVICE_DATA0, queue_phys as u32);
-    npu.mmio.write32(IPC_HOST_2_DEVICE_DATA1, (queue_phys >> 32) as u32);
-    info!(
-        "Command queue registered with NPU: DATA0={:#010x}, DATA1={:#010x}",
-        queue_phys as u32,
-        (queue_phys >> 32) as u32
-    );
+    println!("   Physical Address: {:#010x}", booted.queue().phys_addr());
     println!();
 
     // ================================================================
@@ -210,24 +371,68 @@ fn run_driver(
     #[cfg(target_os = "redox")]
     {
         use syscall::Scheme;
-        let mut scheme = scheme::NpuScheme::new(&npu.mmio, &mut cmd_queue, &mut monitor);
-        
+        let mut booted = booted;
+        let mmio = booted.mmio();
+
+        // Models uploaded before a restart are reused without re-sending
+        let cache_dir = std::env::var("NPU_MODEL_CACHE").unwrap_or_else(|_| model_cache::DEFAULT_CACHE_DIR.to_string());
+        let model_cache = match model_cache::ModelCache::open(&cache_dir, model_cache::DEFAULT_CACHE_BUDGET) {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                warn!("⚠️  Model cache disabled ({}): {}", cache_dir, e);
+                None
+            }
+        };
+        let (queue, restarter) = booted.split();
+        let mut scheme = scheme::NpuScheme::new(mmio, queue, &mut monitor, event_log.as_ref(), model_cache)
+            .with_restarter(restarter);
+        if let Some(gid) = service.submit_group {
+            scheme = scheme.with_submit_group(gid);
+        }
+
         // Open the scheme file to register 'npu:'
-        let mut socket = syscall::open(":npu", syscall::O_CREAT | syscall::O_RDWR | syscall::O_CLOEXEC)
-            .map_err(|e| format!("Failed to create npu: scheme: {:?}", e))?;
+        let flags = syscall::O_CREAT | syscall::O_RDWR | syscall::O_CLOEXEC;
+        let socket = syscall::open(":npu", flags).map_err(|e| format!("Failed to create npu: scheme: {:?}", e))?;
 
         info!("🚀 Scheme 'npu:' registered. Listening for requests...");
 
+        // Blocking reads of running jobs are held here and answered once
+        // the job is done, so other clients are served meanwhile. While
+        // any are held the socket is polled instead of waited on.
+        let mut waiting: Vec<syscall::Packet> = Vec::new();
+        let mut polling = false;
         loop {
-            let mut packet = syscall::Packet::default();
-            if syscall::read(socket, &mut packet).map_err(|e| format!("Failed to read scheme packet: {:?}", e))? == 0 {
-                break;
+            if polling != !waiting.is_empty() {
+                polling = !waiting.is_empty();
+                let mode = if polling { flags | syscall::O_NONBLOCK } else { flags };
+                syscall::fcntl(socket, syscall::F_SETFL, mode).map_err(|e| format!("Failed to set scheme socket flags: {:?}", e))?;
             }
 
-            scheme.handle(&mut packet);
+            let mut packet = syscall::Packet::default();
+            match syscall::read(socket, &mut packet) {
+                Ok(0) => break,
+                Ok(_) => waiting.push(packet),
+                Err(e) if e.errno == syscall::EAGAIN => std::thread::sleep(std::time::Duration::from_millis(POLL_INTERVAL_MS)),
+                Err(e) => return Err(format!("Failed to read scheme packet: {:?}", e).into()),
+            }
 
-            syscall::write(socket, &packet).map_err(|e| format!("Failed to write scheme packet: {:?}", e))?;
+            // The new request, then the held reads again
+            let mut held = Vec::new();
+            for request in waiting.drain(..) {
+                let mut packet = request;
+                scheme.handle(&mut packet);
+                let result = syscall::Error::demux(packet.a).map_err(|e| e.errno);
+                if request.a == syscall::SYS_READ && scheme.should_wait(request.b, result) {
+                    held.push(request);
+                } else {
+                    syscall::write(socket, &packet).map_err(|e| format!("Failed to write scheme packet: {:?}", e))?;
+                }
+            }
+            waiting = held;
+            scheme.suspend_if_idle();
         }
+        drop(scheme);
+        booted.shutdown();
     }
 
     #[cfg(not(target_os = "redox"))]
@@ -235,22 +440,51 @@ fn run_driver(
         println!("╔══════════════════════════════════════════════════╗");
         println!("║   🟢 NPU Driver Active (Mock Loop)             ║");
         println!("╚══════════════════════════════════════════════════╝");
+        if let Some(gid) = service.submit_group {
+            warn!("⚠️  No npu: scheme off Redox, --submit-group {} has no effect", gid);
+        }
 
+        let mut booted = booted;
         let mut loop_count: u64 = 0;
         loop {
-            let state = monitor.poll();
+            // A DEAD NPU is restarted here; only a failed recovery ends the loop
+            let state = monitor.watchdog(|| booted.recover().map(|_| ()))?;
+            let mmio = booted.mmio();
+            match booted.queue_mut().suspend_if_idle(mmio) {
+                Ok(true) => {
+                    if let Some(log) = &event_log {
+                        log.record(EventKind::PowerTransition, power::PowerState::D0i3.event_code(), 0);
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => warn!("⚠️  NPU D0i3 entry failed: {}", e),
+            }
             if loop_count % 12 == 0 {
                 info!("Heartbeat: state={}, uptime={:.0}s", state, monitor.uptime().as_secs_f64());
             }
-            if state == status::NpuState::Dead { return Err("NPU died".into()); }
             loop_count += 1;
-            std::thread::sleep(std::time::Duration::from_secs(5));
+            // A status change interrupt brings the watchdog round early
+            let heartbeat = std::time::Duration::from_secs(5);
+            match &irq {
+                Some(irq) => {
+                    irq.wait(&npu.mmio, npu.regs, heartbeat);
+                }
+                None => std::thread::sleep(heartbeat),
+            }
         }
     }
 
     Ok(())
 }
 
+/// Value following a CLI flag, if present.
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.as_str())
+}
+
 /// Search for firmware binary in standard locations.
 fn find_firmware() -> Result<String, Box<dyn std::error::Error>> {
     for path in FW_SEARCH_PATHS {
@@ -268,11 +502,8 @@ fn find_firmware() -> Result<String, Box<dyn std::error::Error>> {
 
         std::fs::create_dir_all("firmware")?;
 
-        // Create a minimal fake firmware (just a header for testing)
-        let mut mock_fw = vec![0u8; 4096];
-        // Magic bytes that the NPU expects at firmware start
-        mock_fw[0..4].copy_from_slice(&[0x56, 0x50, 0x55, 0x21]); // "VPU!"
-        mock_fw[4..8].copy_from_slice(&0x0001_0000u32.to_le_bytes()); // Version 1.0
+        // Create a minimal fake firmware (a valid header around an empty image)
+        let mock_fw = fw_image::build_image("mock", &[0u8; 4096]);
 
         std::fs::write(mock_path, &mock_fw)?;
         info!("Created mock firmware: {} ({} bytes)", mock_path, mock_fw.len());
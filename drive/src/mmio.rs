@@ -4,8 +4,13 @@
 //! via memory-mapped BAR0 region. On Redox, this is obtained by
 //! opening the PCI BAR file and mmap'ing it.
 
+use crate::hw_mtl::RegisterMap;
+use std::ops::Range;
 use std::sync::atomic::{fence, Ordering};
 
+/// Registers read between fences by `MmioRegion::dump`
+const DUMP_CHUNK_WORDS: usize = 4096;
+
 /// Raw MMIO region mapped into our virtual address space.
 ///
 /// # Safety
@@ -14,6 +19,8 @@ use std::sync::atomic::{fence, Ordering};
 pub struct MmioRegion {
     base: *mut u8,
     size: usize,
+    /// Names registers in the trace log; raw offsets until it is known
+    regs: Option<&'static RegisterMap>,
 }
 
 // Safety: MmioRegion can be sent to another thread (ownership transfer).
@@ -30,7 +37,19 @@ impl MmioRegion {
     /// - `size` must not exceed the mapped region
     /// - The region must remain mapped for the lifetime of this struct
     pub unsafe fn new(base: *mut u8, size: usize) -> Self {
-        Self { base, size }
+        Self { base, size, regs: None }
+    }
+
+    /// Name registers by this generation's map in the trace log.
+    pub fn set_register_map(&mut self, regs: &'static RegisterMap) {
+        self.regs = Some(regs);
+    }
+
+    fn describe(&self, offset: usize, value: u32) -> String {
+        match self.regs {
+            Some(regs) => crate::hw_mtl::format_reg(regs, offset, value),
+            None => format!("{:#07x} = {:#010x}", offset, value),
+        }
     }
 
     /// Read a 32-bit register at `offset` bytes from base.
@@ -56,11 +75,13 @@ impl MmioRegion {
         // Memory fence before read to ensure ordering
         fence(Ordering::SeqCst);
 
-        unsafe {
+        let value = unsafe {
             let ptr = self.base.add(offset) as *const u32;
             // Volatile read: compiler cannot optimize this away
             std::ptr::read_volatile(ptr)
-        }
+        };
+        log::trace!("MMIO rd {}", self.describe(offset, value));
+        value
     }
 
     /// Write a 32-bit value to register at `offset` bytes from base.
@@ -83,6 +104,7 @@ impl MmioRegion {
             return;
         }
 
+        log::trace!("MMIO wr {}", self.describe(offset, value));
         unsafe {
             let ptr = self.base.add(offset) as *mut u32;
             // Volatile write: ensures the write hits the hardware
@@ -166,6 +188,34 @@ impl MmioRegion {
         self.size
     }
 
+    /// Read every 32-bit register in `range` (byte offsets, start rounded
+    /// down and end up to 4 bytes).
+    ///
+    /// Unlike `read32`, this neither logs nor fences each register: reads go
+    /// in chunks of `DUMP_CHUNK_WORDS`, so a whole BAR takes milliseconds.
+    pub fn dump(&self, range: Range<usize>) -> Result<Vec<(usize, u32)>, DumpError> {
+        let start = range.start & !3;
+        let end = range.end.checked_add(3).map(|e| e & !3).unwrap_or(usize::MAX);
+        if start >= end {
+            return Err(DumpError::Empty { start: range.start, end: range.end });
+        }
+        if end > self.size {
+            return Err(DumpError::OutOfBounds { start, end, size: self.size });
+        }
+
+        let offsets: Vec<usize> = (start..end).step_by(4).collect();
+        let mut values = Vec::with_capacity(offsets.len());
+        for chunk in offsets.chunks(DUMP_CHUNK_WORDS) {
+            fence(Ordering::SeqCst);
+            for &offset in chunk {
+                // SAFETY: offset + 4 <= end <= size, checked above
+                let value = unsafe { std::ptr::read_volatile(self.base.add(offset) as *const u32) };
+                values.push((offset, value));
+            }
+        }
+        Ok(values)
+    }
+
     /// Dump a range of registers for debugging.
     pub fn dump_range(&self, start_offset: usize, count: usize) {
         log::debug!("=== MMIO Dump: {:#x} to {:#x} ===", start_offset, start_offset + count * 4);
@@ -174,7 +224,7 @@ impl MmioRegion {
             if offset + 4 <= self.size {
                 let val = self.read32(offset);
                 if val != 0 {
-                    log::debug!("  [{:#06x}] = {:#010x}", offset, val);
+                    log::debug!("  {}", self.describe(offset, val));
                 }
             }
         }
@@ -187,3 +237,25 @@ impl Drop for MmioRegion {
         // Note: actual munmap is handled by the file descriptor owner (DmaBuffer / BAR file)
     }
 }
+
+/// A register dump range that cannot be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DumpError {
+    Empty { start: usize, end: usize },
+    OutOfBounds { start: usize, end: usize, size: usize },
+}
+
+impl std::fmt::Display for DumpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DumpError::Empty { start, end } => write!(f, "Empty register range {:#x}..{:#x}", start, end),
+            DumpError::OutOfBounds { start, end, size } => write!(
+                f,
+                "Register range {:#x}..{:#x} exceeds BAR0 size {:#x}",
+                start, end, size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DumpError {}
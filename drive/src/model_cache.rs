@@ -0,0 +1,464 @@
+//! Model Cache — content-addressed model blobs that survive restarts
+//!
+//! Clients identify a model by the SHA-256 of its bytes. The first upload
+//! is written to the cache directory; after a driver restart the blob is
+//! read back and pinned into DMA memory lazily, the first time a job asks
+//! for it, so the client never has to re-send gigabytes of weights.
+//!
+//! ```text
+//!   <cache dir>/
+//!     index              "<sha256 hex> <size> <last used ms>" per line
+//!     <sha256 hex>.blob  raw model bytes
+//! ```
+//!
+//! Every file is written to a `.tmp` sibling, fsync'd and renamed into
+//! place, so a crash leaves either the old or the new version — never a
+//! torn one. Blobs are re-hashed when loaded; a corrupt blob is deleted
+//! and reported as a cache miss so the client uploads it again instead of
+//! the job failing.
+
+use crate::dma::{DmaBuffer, DmaError};
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default on-disk location of cached models
+#[cfg(target_os = "redox")]
+pub const DEFAULT_CACHE_DIR: &str = "/var/cache/intel-npu/models";
+
+/// Default on-disk budget (8 GB)
+#[cfg(target_os = "redox")]
+pub const DEFAULT_CACHE_BUDGET: u64 = 8 * 1024 * 1024 * 1024;
+
+const INDEX_FILE: &str = "index";
+
+/// SHA-256 of a model's bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModelHash(pub [u8; 32]);
+
+impl ModelHash {
+    pub fn of(bytes: &[u8]) -> Self {
+        Self(Sha256::digest(bytes).into())
+    }
+
+    pub fn to_hex(self) -> String {
+        self.0.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 64 {
+            return None;
+        }
+        let mut out = [0u8; 32];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+        }
+        Some(Self(out))
+    }
+}
+
+/// Answer to a submit-by-hash request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheState {
+    /// The driver has the model; submit jobs by hash
+    Warm,
+    /// Unknown or corrupt; the client must send the bytes
+    Cold,
+}
+
+impl CacheState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheState::Warm => "warm",
+            CacheState::Cold => "cold",
+        }
+    }
+}
+
+struct Entry {
+    /// Model size in bytes (the DMA buffer is page-rounded)
+    size: u64,
+    /// Unix milliseconds, for LRU eviction
+    last_used: u64,
+    /// Pinned copy, loaded on first use
+    pinned: Option<DmaBuffer>,
+}
+
+/// Persistent, content-addressed model cache.
+pub struct ModelCache {
+    dir: PathBuf,
+    budget: u64,
+    entries: HashMap<ModelHash, Entry>,
+    /// Last timestamp handed out; keeps LRU order strict within one ms
+    clock: u64,
+}
+
+impl ModelCache {
+    /// Open (or create) the cache in `dir`, dropping index entries whose
+    /// blob is gone and blobs or temp files the index does not know about.
+    pub fn open(dir: impl Into<PathBuf>, budget: u64) -> Result<Self, CacheError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut entries = HashMap::new();
+        if let Ok(index) = fs::read_to_string(dir.join(INDEX_FILE)) {
+            for line in index.lines() {
+                let mut fields = line.split_whitespace();
+                let parsed = (|| {
+                    let hash = ModelHash::from_hex(fields.next()?)?;
+                    let size = fields.next()?.parse().ok()?;
+                    let last_used = fields.next()?.parse().ok()?;
+                    Some((hash, size, last_used))
+                })();
+                match parsed {
+                    Some((hash, size, last_used)) => {
+                        entries.insert(hash, Entry { size, last_used, pinned: None });
+                    }
+                    None => warn!("Model cache: skipping malformed index line {:?}", line),
+                }
+            }
+        }
+
+        let mut cache = Self { dir, budget, entries, clock: 0 };
+        cache.entries.retain(|hash, entry| {
+            let blob = cache.dir.join(format!("{}.blob", hash.to_hex()));
+            let ok = fs::metadata(&blob).map(|m| m.len() == entry.size).unwrap_or(false);
+            if !ok {
+                warn!("Model cache: dropping {} (blob missing or truncated)", hash.to_hex());
+                let _ = fs::remove_file(blob);
+            }
+            ok
+        });
+        cache.clock = cache.entries.values().map(|e| e.last_used).max().unwrap_or(0);
+
+        for file in fs::read_dir(&cache.dir)? {
+            let path = file?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            let known = name
+                .strip_suffix(".blob")
+                .and_then(ModelHash::from_hex)
+                .is_some_and(|h| cache.entries.contains_key(&h));
+            if name != INDEX_FILE && !known {
+                let _ = fs::remove_file(&path);
+            }
+        }
+
+        cache.evict_to_budget(None)?;
+        cache.save_index()?;
+        info!(
+            "Model cache: {} entries, {} MB on disk in {}",
+            cache.entries.len(),
+            cache.disk_usage() / (1024 * 1024),
+            cache.dir.display()
+        );
+        Ok(cache)
+    }
+
+    /// Whether a client must upload the model.
+    pub fn state(&self, hash: &ModelHash) -> CacheState {
+        if self.entries.contains_key(hash) {
+            CacheState::Warm
+        } else {
+            CacheState::Cold
+        }
+    }
+
+    /// Pinned DMA copy of a cached model, loading and verifying it from
+    /// disk on first use. A corrupt blob is removed and reported as
+    /// `Corrupt`; callers answer "cold" so the client re-uploads.
+    pub fn get(&mut self, hash: &ModelHash) -> Result<&DmaBuffer, CacheError> {
+        let size = self.entries.get(hash).ok_or(CacheError::NotCached)?.size;
+        let now = self.tick();
+
+        if self.entries[hash].pinned.is_none() {
+            let bytes = match fs::read(self.blob_path(hash)) {
+                Ok(bytes) if bytes.len() as u64 == size && ModelHash::of(&bytes) == *hash => bytes,
+                Ok(_) | Err(_) => {
+                    warn!("Model cache: {} failed verification, requesting a fresh upload", hash.to_hex());
+                    self.remove(hash)?;
+                    return Err(CacheError::Corrupt(*hash));
+                }
+            };
+            let buf = DmaBuffer::new_sensitive(bytes.len())?;
+            buf.write_bytes(0, &bytes)?;
+            info!("Model cache: pinned {} ({} bytes) from disk", hash.to_hex(), size);
+            self.entries.get_mut(hash).expect("checked above").pinned = Some(buf);
+        }
+
+        let entry = self.entries.get_mut(hash).expect("checked above");
+        entry.last_used = now;
+        self.save_index()?;
+        Ok(self.entries[hash].pinned.as_ref().expect("pinned above"))
+    }
+
+    /// Store and pin an uploaded model. When `expected` is given, the
+    /// bytes must hash to it.
+    pub fn insert(&mut self, bytes: &[u8], expected: Option<ModelHash>) -> Result<ModelHash, CacheError> {
+        let hash = ModelHash::of(bytes);
+        if expected.is_some_and(|e| e != hash) {
+            return Err(CacheError::HashMismatch);
+        }
+        if bytes.len() as u64 > self.budget {
+            return Err(CacheError::TooLarge { size: bytes.len() as u64, budget: self.budget });
+        }
+
+        if !self.entries.contains_key(&hash) {
+            write_atomic(&self.blob_path(&hash), bytes)?;
+            let last_used = self.tick();
+            self.entries.insert(hash, Entry { size: bytes.len() as u64, last_used, pinned: None });
+            self.evict_to_budget(Some(hash))?;
+            self.save_index()?;
+        }
+
+        let entry = self.entries.get_mut(&hash).expect("inserted above");
+        if entry.pinned.is_none() {
+            let buf = DmaBuffer::new_sensitive(bytes.len())?;
+            buf.write_bytes(0, bytes)?;
+            entry.pinned = Some(buf);
+        }
+        Ok(hash)
+    }
+
+    /// Remove least-recently-used entries until the disk budget is met.
+    /// `keep` is never evicted. Returns the evicted hashes.
+    pub fn evict_to_budget(&mut self, keep: Option<ModelHash>) -> Result<Vec<ModelHash>, CacheError> {
+        let mut evicted = Vec::new();
+        while self.disk_usage() > self.budget {
+            let victim = self
+                .entries
+                .iter()
+                .filter(|(h, _)| Some(**h) != keep)
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(h, _)| *h);
+            let Some(victim) = victim else { break };
+            info!("Model cache: evicting {} to stay within budget", victim.to_hex());
+            self.remove(&victim)?;
+            evicted.push(victim);
+        }
+        Ok(evicted)
+    }
+
+    /// Bytes of model blobs on disk.
+    pub fn disk_usage(&self) -> u64 {
+        self.entries.values().map(|e| e.size).sum()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether the model is already in DMA memory.
+    pub fn is_pinned(&self, hash: &ModelHash) -> bool {
+        self.entries.get(hash).is_some_and(|e| e.pinned.is_some())
+    }
+
+    fn remove(&mut self, hash: &ModelHash) -> Result<(), CacheError> {
+        self.entries.remove(hash);
+        match fs::remove_file(self.blob_path(hash)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        self.save_index()
+    }
+
+    fn blob_path(&self, hash: &ModelHash) -> PathBuf {
+        self.dir.join(format!("{}.blob", hash.to_hex()))
+    }
+
+    fn tick(&mut self) -> u64 {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+        self.clock = now.max(self.clock + 1);
+        self.clock
+    }
+
+    fn save_index(&self) -> Result<(), CacheError> {
+        let mut index = String::new();
+        for (hash, entry) in &self.entries {
+            index.push_str(&format!("{} {} {}\n", hash.to_hex(), entry.size, entry.last_used));
+        }
+        write_atomic(&self.dir.join(INDEX_FILE), index.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Write via a fsync'd temp file and rename, then sync the directory so
+/// the rename itself is durable.
+fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+    if let Some(dir) = path.parent() {
+        // Directories can't be opened for sync on every platform
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+// ============================================================
+// Error Types
+// ============================================================
+
+#[derive(Debug)]
+pub enum CacheError {
+    Io(io::Error),
+    Dma(DmaError),
+    /// No entry for this hash
+    NotCached,
+    /// Blob failed verification and was removed
+    Corrupt(ModelHash),
+    /// Uploaded bytes do not match the announced hash
+    HashMismatch,
+    /// Model alone exceeds the cache budget
+    TooLarge { size: u64, budget: u64 },
+}
+
+impl std::fmt::Display for CacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Model cache I/O error: {}", e),
+            Self::Dma(e) => write!(f, "Model cache DMA error: {}", e),
+            Self::NotCached => write!(f, "Model not in cache"),
+            Self::Corrupt(h) => write!(f, "Cached model {} is corrupt", h.to_hex()),
+            Self::HashMismatch => write!(f, "Uploaded model does not match its hash"),
+            Self::TooLarge { size, budget } => {
+                write!(f, "Model is {} bytes, larger than the {} byte cache budget", size, budget)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+impl From<io::Error> for CacheError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<DmaError> for CacheError {
+    fn from(e: DmaError) -> Self {
+        Self::Dma(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("npu-model-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn model(seed: u8, len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed)).collect()
+    }
+
+    #[test]
+    fn test_warm_start_after_restart() {
+        let dir = cache_dir("warm");
+        let weights = model(1, 10_000);
+
+        let hash = {
+            let mut cache = ModelCache::open(&dir, 1 << 20).unwrap();
+            assert_eq!(cache.state(&ModelHash::of(&weights)), CacheState::Cold);
+            cache.insert(&weights, Some(ModelHash::of(&weights))).unwrap()
+        };
+
+        // Restart: metadata survives, the blob is pinned lazily on first use
+        let mut cache = ModelCache::open(&dir, 1 << 20).unwrap();
+        assert_eq!(cache.state(&hash), CacheState::Warm);
+        assert!(!cache.is_pinned(&hash));
+        let buf = cache.get(&hash).unwrap();
+        assert_eq!(buf.read_bytes(0, weights.len()).unwrap(), weights);
+        assert!(cache.is_pinned(&hash));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_cold_start_and_stale_files() {
+        let dir = cache_dir("cold");
+        fs::create_dir_all(&dir).unwrap();
+        // Leftovers from a crash mid-write and an unindexed blob
+        fs::write(dir.join("index.tmp"), "garbage").unwrap();
+        fs::write(dir.join(format!("{}.blob", ModelHash::of(b"x").to_hex())), b"x").unwrap();
+
+        let mut cache = ModelCache::open(&dir, 1 << 20).unwrap();
+        assert_eq!(cache.len(), 0);
+        assert!(matches!(cache.get(&ModelHash::of(b"x")), Err(CacheError::NotCached)));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1, "only the index should remain");
+
+        assert!(matches!(
+            cache.insert(b"weights", Some(ModelHash::of(b"other"))),
+            Err(CacheError::HashMismatch)
+        ));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_corrupted_blob_requests_reupload() {
+        let dir = cache_dir("corrupt");
+        let weights = model(7, 4096);
+        let hash = ModelCache::open(&dir, 1 << 20).unwrap().insert(&weights, None).unwrap();
+
+        // Flip a byte without changing the size
+        let blob = dir.join(format!("{}.blob", hash.to_hex()));
+        let mut bytes = fs::read(&blob).unwrap();
+        bytes[100] ^= 0xFF;
+        fs::write(&blob, bytes).unwrap();
+
+        let mut cache = ModelCache::open(&dir, 1 << 20).unwrap();
+        assert_eq!(cache.state(&hash), CacheState::Warm);
+        assert!(matches!(cache.get(&hash), Err(CacheError::Corrupt(h)) if h == hash));
+        assert_eq!(cache.state(&hash), CacheState::Cold);
+        assert!(!blob.exists());
+
+        // A fresh upload recovers
+        cache.insert(&weights, Some(hash)).unwrap();
+        assert!(cache.get(&hash).is_ok());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_eviction_beyond_budget() {
+        let dir = cache_dir("evict");
+        let (a, b, c) = (model(1, 400), model(2, 400), model(3, 400));
+        let mut cache = ModelCache::open(&dir, 1000).unwrap();
+
+        let ha = cache.insert(&a, None).unwrap();
+        let hb = cache.insert(&b, None).unwrap();
+        cache.get(&ha).unwrap(); // a is now more recent than b
+        let hc = cache.insert(&c, None).unwrap();
+
+        assert_eq!(cache.state(&hb), CacheState::Cold);
+        assert_eq!(cache.state(&ha), CacheState::Warm);
+        assert_eq!(cache.state(&hc), CacheState::Warm);
+        assert_eq!(cache.disk_usage(), 800);
+
+        // Budget shrinks across a restart
+        drop(cache);
+        let cache = ModelCache::open(&dir, 500).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.state(&hc), CacheState::Warm);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
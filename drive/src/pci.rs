@@ -19,10 +19,14 @@ pub struct NpuDevice {
     pub device_id: u16,
     /// Device name (human readable)
     pub device_name: &'static str,
+    /// Register offsets for this device's generation
+    pub regs: &'static RegisterMap,
     /// BAR0 physical base address
     pub bar0_phys: u64,
     /// BAR0 size
     pub bar0_size: usize,
+    /// Legacy interrupt line from config space (`None` if none is routed)
+    pub irq_line: Option<u8>,
     /// MMIO region (mapped BAR0)
     pub mmio: MmioRegion,
     /// Mock BAR pointer for proper deallocation (non-Redox only)
@@ -103,20 +107,31 @@ fn discover_redox() -> Result<NpuDevice, PciError> {
 
         // Check if it's a supported NPU
         if let Some(name) = is_supported_device(device_id) {
-            info!("  ✅ Found: {} at PCI {}", name, bdf);
+            // Before touching the device: no map means no safe offsets
+            let regs = RegisterMap::for_device(device_id).map_err(|e| {
+                error!("  ❌ {} at PCI {}: {}", name, bdf, e);
+                PciError::UnsupportedGeneration(e)
+            })?;
+            info!("  ✅ Found: {} at PCI {} ({} register map)", name, bdf, regs.generation);
 
             // Enable Bus Mastering (CRITICAL for DMA)
             enable_bus_mastering_redox(&bdf, &config)?;
 
             // Map BAR0
-            let (mmio, bar0_phys, bar0_size) = map_bar0_redox(&bdf)?;
+            let (mut mmio, bar0_phys, bar0_size) = map_bar0_redox(&bdf)?;
+            mmio.set_register_map(regs);
+
+            let irq_line = config.get(PCI_INTERRUPT_LINE).copied().filter(|&line| line != 0 && line != 0xFF);
+            debug!("  Interrupt line: {:?}", irq_line);
 
             return Ok(NpuDevice {
                 bdf,
                 device_id,
                 device_name: name,
+                regs,
                 bar0_phys,
                 bar0_size,
+                irq_line,
                 mmio,
             });
         }
@@ -144,7 +159,7 @@ fn enable_bus_mastering_redox(bdf: &str, config: &[u8]) -> Result<(), PciError>
 
     // Read current PCI command register (offset 0x04, 2 bytes)
     let cmd = u16::from_le_bytes([config[4], config[5]]);
-    debug!("  Current PCI CMD: {:#06x}", cmd);
+    debug!("  Current PCI CMD: {:#06x} [{}]", cmd, decode_pci_command(cmd));
 
     // Set Bus Master + Memory Space Enable
     let new_cmd = cmd | PCI_CMD_BUS_MASTER | PCI_CMD_MEMORY_SPACE;
@@ -166,8 +181,8 @@ fn enable_bus_mastering_redox(bdf: &str, config: &[u8]) -> Result<(), PciError>
             .map_err(|e| PciError::ConfigWrite(e))?;
 
         info!(
-            "  ✅ Bus Mastering enabled (CMD: {:#06x} → {:#06x})",
-            cmd, new_cmd
+            "  ✅ Bus Mastering enabled (CMD: {:#06x} → {:#06x} [{}])",
+            cmd, new_cmd, decode_pci_command(new_cmd)
         );
     } else {
         info!("  Bus Mastering already enabled");
@@ -251,17 +266,19 @@ fn discover_mock() -> Result<NpuDevice, PciError> {
     }
 
     // Pre-populate some registers for testing
+    let regs = &REGS_MTL;
     unsafe {
         // Buttress VPU status: powered on
-        let buttress_status = ptr.add(BUTTRESS_VPU_STATUS) as *mut u32;
+        let buttress_status = ptr.add(regs.vpu_status) as *mut u32;
         std::ptr::write_volatile(buttress_status, 0x0000_0001);
 
         // FW status: not initialized
-        let fw_status = ptr.add(HOST_SS_FW_STATUS) as *mut u32;
+        let fw_status = ptr.add(regs.fw_status) as *mut u32;
         std::ptr::write_volatile(fw_status, 0x0000_0000);
     }
 
-    let mmio = unsafe { MmioRegion::new(ptr, bar_size) };
+    let mut mmio = unsafe { MmioRegion::new(ptr, bar_size) };
+    mmio.set_register_map(regs);
 
     // Store layout alongside the device so it can be freed properly.
     // The mock MMIO memory is freed via NpuDevice's Drop impl.
@@ -269,8 +286,11 @@ fn discover_mock() -> Result<NpuDevice, PciError> {
         bdf: "0000:00:0b.0".to_string(),
         device_id: PCI_DEVICE_MTL_NPU,
         device_name: "Meteor Lake NPU (MOCK)",
+        regs,
         bar0_phys: ptr as u64,
         bar0_size: bar_size,
+        // Interrupts are simulated (see `irq`)
+        irq_line: None,
         mmio,
         #[cfg(not(target_os = "redox"))]
         mock_bar_ptr: Some(ptr),
@@ -285,6 +305,8 @@ fn discover_mock() -> Result<NpuDevice, PciError> {
 pub enum PciError {
     SchemeFailed(io::Error),
     DeviceNotFound,
+    /// Recognised, but without verified register offsets
+    UnsupportedGeneration(UnsupportedGeneration),
     ConfigWrite(io::Error),
     BarOpen(io::Error),
     BarZeroSize,
@@ -298,6 +320,7 @@ impl std::fmt::Display for PciError {
         match self {
             Self::SchemeFailed(e) => write!(f, "PCI scheme access failed: {}", e),
             Self::DeviceNotFound => write!(f, "No supported Intel NPU found on PCI bus"),
+            Self::UnsupportedGeneration(e) => write!(f, "{}", e),
             Self::ConfigWrite(e) => write!(f, "PCI config write failed: {}", e),
             Self::BarOpen(e) => write!(f, "Failed to open BAR0: {}", e),
             Self::BarZeroSize => write!(f, "BAR0 has zero size"),
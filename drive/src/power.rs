@@ -0,0 +1,405 @@
+//! NPU Runtime Power Management — D0i3 When Idle
+//!
+//! Booting powers the NPU up once; left at that it stays in D0 for as long
+//! as the driver runs. `PowerManager` puts it into D0i3 (power gated, the
+//! firmware context kept by the Buttress) once no job has been submitted or
+//! in flight for the idle timeout, and `CommandQueue::submit` wakes it again
+//! before ringing the doorbell.
+//!
+//! Entry and exit use the handshake of the Linux ivpu driver
+//! (ivpu_hw_btrs.c → d0i3_drive()): wait for INPROGRESS to clear, flip I3
+//! in BUTTRESS_VPU_D0I3_CONTROL, wait for INPROGRESS to clear again. After
+//! the exit the firmware must report READY before it is given a job.
+//!
+//! Whether the device is asleep is read back from the register, not
+//! remembered, so a cold reset (which leaves D0i3 on its own) cannot put
+//! the manager out of step. Off Redox the fake BAR answers like hardware
+//! would: power and firmware status drop on entry and come back on exit,
+//! and a doorbell rung while asleep is lost.
+
+use crate::hw_mtl::*;
+use crate::mmio::MmioRegion;
+use log::{info, warn};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Idle time before the NPU enters D0i3, unless configured otherwise.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Device power state, from `BUTTRESS_VPU_D0I3_CONTROL`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerState {
+    /// Fully on
+    D0,
+    /// Power gated until the next submission
+    D0i3,
+}
+
+impl PowerState {
+    /// Plain name for machine-readable output (status files).
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerState::D0 => "D0",
+            PowerState::D0i3 => "D0I3",
+        }
+    }
+
+    /// Code of an `EventKind::PowerTransition` into this state.
+    pub fn event_code(self) -> u32 {
+        match self {
+            PowerState::D0 => 0,
+            PowerState::D0i3 => 3,
+        }
+    }
+}
+
+// ============================================================
+// Power Accounting
+// ============================================================
+
+/// Process-wide D0i3 counters.
+struct PowerCounters {
+    suspends: AtomicU64,
+    wakes: AtomicU64,
+    last_wake_micros: AtomicU64,
+    max_wake_micros: AtomicU64,
+}
+
+static STATS: PowerCounters = PowerCounters {
+    suspends: AtomicU64::new(0),
+    wakes: AtomicU64::new(0),
+    last_wake_micros: AtomicU64::new(0),
+    max_wake_micros: AtomicU64::new(0),
+};
+
+impl PowerCounters {
+    fn record_wake(&self, latency: Duration) {
+        let micros = latency.as_micros() as u64;
+        self.wakes.fetch_add(1, Ordering::Relaxed);
+        self.last_wake_micros.store(micros, Ordering::Relaxed);
+        self.max_wake_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+}
+
+/// Snapshot of D0i3 entries and wake-ups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+pub struct PowerStats {
+    pub suspends: u64,
+    pub wakes: u64,
+    /// From the start of the exit handshake to READY (`last_wake_us`)
+    #[cfg_attr(test, serde(rename = "last_wake_us", deserialize_with = "crate::dma::micros"))]
+    pub last_wake_latency: Duration,
+    #[cfg_attr(test, serde(rename = "max_wake_us", deserialize_with = "crate::dma::micros"))]
+    pub max_wake_latency: Duration,
+}
+
+impl PowerStats {
+    pub fn to_json(self) -> String {
+        format!(
+            "{{\"suspends\":{},\"wakes\":{},\"last_wake_us\":{},\"max_wake_us\":{}}}",
+            self.suspends,
+            self.wakes,
+            self.last_wake_latency.as_micros(),
+            self.max_wake_latency.as_micros()
+        )
+    }
+}
+
+/// Current D0i3 counters.
+pub fn stats() -> PowerStats {
+    PowerStats {
+        suspends: STATS.suspends.load(Ordering::Relaxed),
+        wakes: STATS.wakes.load(Ordering::Relaxed),
+        last_wake_latency: Duration::from_micros(STATS.last_wake_micros.load(Ordering::Relaxed)),
+        max_wake_latency: Duration::from_micros(STATS.max_wake_micros.load(Ordering::Relaxed)),
+    }
+}
+
+// ============================================================
+// Power Manager
+// ============================================================
+
+/// Idle tracking and the D0i3 entry/exit sequences for one device.
+pub struct PowerManager {
+    /// D0i3 control and status registers of this device generation
+    regs: &'static RegisterMap,
+    /// `None` keeps the device in D0
+    idle_timeout: Option<Duration>,
+    /// Last submission, or last time a job was seen in flight
+    last_activity: Instant,
+}
+
+impl PowerManager {
+    /// Meteor Lake registers, `DEFAULT_IDLE_TIMEOUT`.
+    pub fn new() -> Self {
+        Self { regs: &REGS_MTL, idle_timeout: Some(DEFAULT_IDLE_TIMEOUT), last_activity: Instant::now() }
+    }
+
+    /// Drive the D0i3 control of this device generation.
+    pub fn set_register_map(&mut self, regs: &'static RegisterMap) {
+        self.regs = regs;
+    }
+
+    /// Idle time before entering D0i3 (`None` never suspends).
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    /// Whether the device is in D0i3, read from the hardware.
+    pub fn state(&self, mmio: &MmioRegion) -> PowerState {
+        if mmio.read32(self.regs.d0i3_control) & D0I3_CONTROL_I3 != 0 {
+            PowerState::D0i3
+        } else {
+            PowerState::D0
+        }
+    }
+
+    /// Restart the idle timer (a job was submitted).
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    /// Enter D0i3 if nothing is in flight and nothing was submitted for the
+    /// idle timeout; `true` if the device went to sleep now.
+    pub fn suspend_if_idle(&mut self, mmio: &MmioRegion, in_flight: usize) -> Result<bool, PowerError> {
+        if in_flight > 0 {
+            // Still working: idle time counts from when the last job is done
+            self.touch();
+            return Ok(false);
+        }
+        let Some(timeout) = self.idle_timeout else {
+            return Ok(false);
+        };
+        if self.last_activity.elapsed() < timeout || self.state(mmio) == PowerState::D0i3 {
+            return Ok(false);
+        }
+        info!("💤 NPU idle for {:.0}s, entering D0i3...", self.last_activity.elapsed().as_secs_f64());
+        self.suspend(mmio)?;
+        Ok(true)
+    }
+
+    /// Enter D0i3 now. The caller makes sure no job is on the device.
+    pub fn suspend(&mut self, mmio: &MmioRegion) -> Result<(), PowerError> {
+        self.drive_d0i3(mmio, true)?;
+        #[cfg(not(target_os = "redox"))]
+        self.mock_d0i3_entered(mmio);
+        STATS.suspends.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Leave D0i3, if the device is in it, and wait for the firmware to
+    /// report READY. Returns how long that took, `None` if it was awake.
+    pub fn ensure_awake(&mut self, mmio: &MmioRegion) -> Result<Option<Duration>, PowerError> {
+        if self.state(mmio) == PowerState::D0 {
+            return Ok(None);
+        }
+        info!("⚡ Waking NPU from D0i3...");
+        let start = Instant::now();
+        self.drive_d0i3(mmio, false)?;
+        #[cfg(not(target_os = "redox"))]
+        self.mock_d0i3_exited(mmio);
+
+        // A firmware that died while asleep says so at once
+        let settled = |v: u32| matches!(v & FW_STATUS_MASK, FW_STATUS_READY | FW_STATUS_DEAD);
+        match mmio.poll_until(self.regs.fw_status, settled, POLL_INTERVAL_MS, FW_BOOT_TIMEOUT_MS) {
+            Ok(status) if status & FW_STATUS_MASK == FW_STATUS_READY => {}
+            Ok(last_status) | Err(last_status) => {
+                warn!("  ⚠️  NPU left D0i3 but firmware reports {}", decode_fw_status(last_status));
+                return Err(PowerError::NotReady { last_status });
+            }
+        }
+
+        let latency = start.elapsed();
+        STATS.record_wake(latency);
+        self.touch();
+        info!("  ✅ NPU awake after {}us", latency.as_micros());
+        Ok(Some(latency))
+    }
+
+    /// Enter (`enter`) or leave D0i3: wait out any transition in progress,
+    /// flip I3, wait for the Buttress to finish.
+    fn drive_d0i3(&self, mmio: &MmioRegion, enter: bool) -> Result<(), PowerError> {
+        let settled = |v: u32| v & D0I3_CONTROL_INPROGRESS == 0;
+        let stuck = |control| PowerError::Handshake { entering: enter, control };
+
+        let control = mmio
+            .poll_until(self.regs.d0i3_control, settled, POLL_INTERVAL_MS, D0I3_TIMEOUT_MS)
+            .map_err(stuck)?;
+        let control = if enter { control | D0I3_CONTROL_I3 } else { control & !D0I3_CONTROL_I3 };
+        mmio.write32(self.regs.d0i3_control, control);
+        mmio.poll_until(self.regs.d0i3_control, settled, POLL_INTERVAL_MS, D0I3_TIMEOUT_MS)
+            .map_err(stuck)?;
+        Ok(())
+    }
+
+    // ================================================================
+    // Mock hardware (what the Buttress and firmware do on their own)
+    // ================================================================
+
+    /// Power gated: the Buttress drops its power bit and the firmware stops
+    /// answering. A DEAD status is left for the wake-up to find.
+    #[cfg(not(target_os = "redox"))]
+    fn mock_d0i3_entered(&self, mmio: &MmioRegion) {
+        let power = mmio.read32(self.regs.vpu_status);
+        mmio.write32(self.regs.vpu_status, power & !0x1);
+        if mmio.read32(self.regs.fw_status) & FW_STATUS_MASK == FW_STATUS_READY {
+            mmio.write32(self.regs.fw_status, 0);
+        }
+    }
+
+    /// Power comes back and the firmware resumes to READY. A doorbell rung
+    /// while the IPC block was gated was never seen.
+    #[cfg(not(target_os = "redox"))]
+    fn mock_d0i3_exited(&self, mmio: &MmioRegion) {
+        let power = mmio.read32(self.regs.vpu_status);
+        mmio.write32(self.regs.vpu_status, power | 0x1);
+        mmio.write32(self.regs.h2d_doorbell, 0);
+        if mmio.read32(self.regs.fw_status) == 0 {
+            mmio.write32(self.regs.fw_status, FW_STATUS_READY);
+        }
+    }
+}
+
+impl Default for PowerManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================
+// Error Types
+// ============================================================
+
+#[derive(Debug)]
+pub enum PowerError {
+    /// The Buttress did not finish a D0i3 entry (or exit)
+    Handshake { entering: bool, control: u32 },
+    /// Out of D0i3, but the firmware did not come back READY
+    NotReady { last_status: u32 },
+}
+
+impl std::fmt::Display for PowerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Handshake { entering, control } => write!(
+                f,
+                "D0i3 {} did not complete within {}ms (D0I3_CONTROL={:#010x})",
+                if *entering { "entry" } else { "exit" },
+                D0I3_TIMEOUT_MS,
+                control
+            ),
+            Self::NotReady { last_status } => write!(
+                f,
+                "Firmware not ready after D0i3 exit: {:#010x} ({})",
+                last_status,
+                decode_fw_status(*last_status)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PowerError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dma_pool::{DmaPool, PooledBuffer};
+    use crate::fwsim::FwSim;
+    use crate::inference::{prepare_input, prepare_output, CommandQueue, InferenceError};
+    use crate::status::{NpuState, StatusMonitor};
+
+    fn job() -> (PooledBuffer, PooledBuffer, PooledBuffer) {
+        let pool = DmaPool::unpooled();
+        (
+            prepare_output(&pool, DMA_ALIGNMENT).unwrap(),
+            prepare_input(&pool, &[1u8; 16]).unwrap(),
+            prepare_output(&pool, DMA_ALIGNMENT).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_idle_suspend_then_wake_before_doorbell() {
+        let sim = FwSim::new();
+        let mut monitor = StatusMonitor::new(sim.mmio());
+        let mut queue = CommandQueue::new(4).unwrap();
+        queue.power_mut().set_idle_timeout(Some(Duration::ZERO));
+        let (model, input, output) = job();
+
+        // Never with a job on the device
+        let job_id = queue.submit(sim.mmio(), &model, &input, &output).unwrap();
+        assert!(!queue.suspend_if_idle(sim.mmio()).unwrap());
+        sim.service(&queue).unwrap();
+        queue.wait_for_completion(job_id, Duration::from_secs(1)).unwrap();
+
+        let before = stats();
+        assert!(queue.suspend_if_idle(sim.mmio()).unwrap());
+        assert_eq!(queue.power().state(sim.mmio()), PowerState::D0i3);
+        assert_eq!(monitor.poll(), NpuState::PoweredOff);
+        assert_eq!(sim.mmio().read32(REGS_MTL.vpu_status) & 0x1, 0);
+        // Already asleep
+        assert!(!queue.suspend_if_idle(sim.mmio()).unwrap());
+
+        // The doorbell is rung after the wake-up, so the firmware sees the job
+        let job_id = queue.submit(sim.mmio(), &model, &input, &output).unwrap();
+        assert_eq!(queue.power().state(sim.mmio()), PowerState::D0);
+        assert_eq!(monitor.poll(), NpuState::Ready);
+        assert_eq!(sim.service(&queue).map(|(id, _)| id), Some(job_id));
+        queue.wait_for_completion(job_id, Duration::from_secs(1)).unwrap();
+
+        let after = stats();
+        assert!(after.suspends > before.suspends);
+        assert!(after.wakes > before.wakes);
+        assert!(after.max_wake_latency >= after.last_wake_latency);
+    }
+
+    #[test]
+    fn test_idle_timeout() {
+        let sim = FwSim::new();
+        let mut power = PowerManager::new();
+        power.set_idle_timeout(Some(Duration::from_secs(3600)));
+        assert!(!power.suspend_if_idle(sim.mmio(), 0).unwrap());
+
+        power.set_idle_timeout(None);
+        power.last_activity = Instant::now() - Duration::from_secs(7200);
+        assert!(!power.suspend_if_idle(sim.mmio(), 0).unwrap());
+        assert_eq!(power.state(sim.mmio()), PowerState::D0);
+
+        // In-flight work restarts the timer
+        power.set_idle_timeout(Some(Duration::from_secs(3600)));
+        assert!(!power.suspend_if_idle(sim.mmio(), 1).unwrap());
+        assert!(!power.suspend_if_idle(sim.mmio(), 0).unwrap());
+        assert_eq!(power.ensure_awake(sim.mmio()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_firmware_dead_after_wake_fails_submission() {
+        let sim = FwSim::new();
+        let mut queue = CommandQueue::new(4).unwrap();
+        queue.power_mut().suspend(sim.mmio()).unwrap();
+        sim.crash();
+
+        let (model, input, output) = job();
+        match queue.submit(sim.mmio(), &model, &input, &output) {
+            Err(InferenceError::Power(PowerError::NotReady { last_status })) => assert_eq!(last_status, FW_STATUS_DEAD),
+            other => panic!("expected NotReady, got {:?}", other),
+        }
+        // Nothing was queued
+        assert_eq!(queue.stats().total_submitted, 0);
+        assert_eq!(sim.service(&queue), None);
+    }
+
+    #[test]
+    fn test_stuck_handshake() {
+        let sim = FwSim::new();
+        let mut power = PowerManager::new();
+        sim.mmio().write32(REGS_MTL.d0i3_control, D0I3_CONTROL_INPROGRESS);
+
+        match power.suspend(sim.mmio()) {
+            Err(PowerError::Handshake { entering: true, control }) => assert_eq!(control, D0I3_CONTROL_INPROGRESS),
+            other => panic!("expected a stuck entry, got {:?}", other),
+        }
+        // I3 was never requested
+        assert_eq!(power.state(sim.mmio()), PowerState::D0);
+    }
+}
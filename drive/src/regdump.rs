@@ -0,0 +1,132 @@
+//! Register Dump (`--dump-regs`)
+//!
+//! Prints every named register from `hw_mtl`, at the offsets of the
+//! device's generation, followed by an optional raw BAR0 range. Nothing is
+//! written, so it is safe to run against a device that failed to boot.
+
+use crate::hw_mtl::*;
+use crate::mmio::{DumpError, MmioRegion};
+use std::fmt::Write;
+use std::ops::Range;
+
+/// Parse a raw range: `START-END` (end exclusive) or `START+LEN`, each in
+/// hex (`0x...`) or decimal.
+pub fn parse_range(arg: &str) -> Option<Range<usize>> {
+    let number = |s: &str| {
+        let s = s.trim();
+        match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Some(hex) => usize::from_str_radix(hex, 16).ok(),
+            None => s.parse().ok(),
+        }
+    };
+
+    if let Some((start, len)) = arg.split_once('+') {
+        let start = number(start)?;
+        return Some(start..start.checked_add(number(len)?)?);
+    }
+    let (start, end) = arg.split_once('-')?;
+    Some(number(start)?..number(end)?)
+}
+
+/// Build the dump table: the named registers, then the non-zero words of
+/// `raw`. Fails before reading anything if `raw` is outside BAR0.
+pub fn dump_table(mmio: &MmioRegion, regs: &RegisterMap, raw: Option<Range<usize>>) -> Result<String, DumpError> {
+    let raw_words = match raw {
+        Some(range) => Some(mmio.dump(range)?),
+        None => None,
+    };
+    let named: Vec<(usize, &RegInfo)> = REGISTERS.iter().map(|r| (regs.rebase(r.offset), r)).collect();
+
+    let mut out = String::new();
+    let _ = writeln!(out, "{} registers", regs.generation);
+    let _ = writeln!(out, "{:<9} {:<26} {:<10} DECODED", "OFFSET", "REGISTER", "VALUE");
+    for &(offset, info) in &named {
+        let value = mmio.read32(offset);
+        push_row(&mut out, offset, info.name, value, decode_reg_value(info.offset, value));
+    }
+
+    if let Some(words) = raw_words {
+        let (first, last) = (words[0].0, words[words.len() - 1].0 + 4);
+        let nonzero: Vec<&(usize, u32)> = words.iter().filter(|(_, v)| *v != 0).collect();
+        let _ = writeln!(
+            out,
+            "--- {:#x}..{:#x}: {} registers, {} zero (not shown) ---",
+            first,
+            last,
+            words.len(),
+            words.len() - nonzero.len()
+        );
+        for &&(offset, value) in &nonzero {
+            match named.iter().find(|(o, _)| *o == offset) {
+                Some((_, info)) => push_row(&mut out, offset, info.name, value, decode_reg_value(info.offset, value)),
+                None => push_row(&mut out, offset, "", value, None),
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn push_row(out: &mut String, offset: usize, name: &str, value: u32, decoded: Option<String>) {
+    let _ = writeln!(
+        out,
+        "{:#09x} {:<26} {:#010x} {}",
+        offset,
+        name,
+        value,
+        decoded.unwrap_or_default()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fwsim::FwSim;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("0x80000-0x80070"), Some(0x80000..0x80070));
+        assert_eq!(parse_range("0x73000+0x40"), Some(0x73000..0x73040));
+        assert_eq!(parse_range("256+16"), Some(256..272));
+        assert_eq!(parse_range("0x80000"), None);
+        assert_eq!(parse_range("zz-0x10"), None);
+        assert_eq!(parse_range("0x1+0xffffffffffffffff"), None);
+    }
+
+    #[test]
+    fn test_named_registers_and_raw_range() {
+        let sim = FwSim::new();
+        let table = dump_table(sim.mmio(), &REGS_MTL, Some(0x80060..0x80070)).unwrap();
+
+        assert!(table.starts_with("Meteor Lake registers\n"));
+        assert!(table.contains("0x0080060 HOST_SS_FW_STATUS          0xf00d0000 READY"));
+        assert!(table.contains("0x0000114 BUTTRESS_VPU_STATUS        0x00000001 POWERED_ON"));
+        assert_eq!(table.lines().filter(|l| l.contains("HOST_SS_FW_STATUS")).count(), 2);
+        assert!(table.contains("--- 0x80060..0x80070: 4 registers, 3 zero (not shown) ---"));
+        assert_eq!(table.lines().count(), 2 + REGISTERS.len() + 2);
+    }
+
+    #[test]
+    fn test_other_generation_offsets() {
+        let sim = FwSim::for_device(PCI_DEVICE_ARL_NPU);
+        let table = dump_table(sim.mmio(), &REGS_ARL, None).unwrap();
+        let row = table.lines().find(|l| l.contains("HOST_SS_FW_STATUS")).unwrap();
+        assert!(row.starts_with(&format!("{:#09x}", REGS_ARL.fw_status)), "{}", row);
+        assert!(row.contains("READY"));
+    }
+
+    #[test]
+    fn test_rejects_ranges_beyond_bar() {
+        let sim = FwSim::new();
+        let size = sim.mmio().size();
+        assert_eq!(
+            dump_table(sim.mmio(), &REGS_MTL, Some(size - 4..size + 4)).unwrap_err(),
+            DumpError::OutOfBounds { start: size - 4, end: size + 4, size }
+        );
+        assert!(matches!(sim.mmio().dump(0x10..0x10), Err(DumpError::Empty { .. })));
+
+        // A whole-BAR dump is one read per word, all of them
+        let words = sim.mmio().dump(0..size).unwrap();
+        assert_eq!(words.len(), size / 4);
+        assert_eq!(words[0x80060 / 4], (0x80060, FW_STATUS_READY));
+    }
+}
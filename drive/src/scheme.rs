@@ -3,136 +3,1160 @@
 //! Exposes the NPU hardware via the `npu:` scheme, allowing other processes
 //! to submit inference jobs using simple file operations.
 //!
-//! Protocol:
-//!   - `open("npu:infer", O_RDWR)` -> returns a handle for inference
-//!   - `write(handle, cmd_buffer)` -> submits a job
-//!   - `read(handle, result_buffer)` -> waits for and reads result
-//!   - `fstat(handle)` -> returns job status
+//! Job submission (`npu:submit`; `npu:infer` is an alias):
+//!   - `open("npu:submit", O_RDWR)` -> a handle for a stream of jobs
+//!   - `write` a 16-byte header, then the model bytes, then the input bytes
+//!     (in as many writes as convenient). The job is queued as soon as the
+//!     last input byte arrives, and the next write starts another job's
+//!     header, up to `MAX_JOBS_PER_HANDLE` queued or unread. If the command
+//!     queue is full that write fails with `EAGAIN` and the job must be
+//!     written again from its header. The header write fails with `EAGAIN`
+//!     too while every DMA pool buffer the job fits in is taken by other
+//!     jobs, or the handle already has its maximum of jobs.
+//!   - `read` -> each job's output buffer in submission order
+//!     (`output_size` bytes, possibly over several reads), then EOF; the
+//!     read after an EOF moves on to the next job. A blocking read waits
+//!     while the job runs (with `O_NONBLOCK` it fails with `EAGAIN`). A
+//!     failed job reads as `ENOMEM` (device out of memory) or `EIO`.
+//!   - `close` forgets the handle's jobs: ones still being written are
+//!     dropped, ones on the device are left to finish and their output
+//!     discarded.
 //!
-//! Note: This module only compiles on Redox OS, as it depends on the
-//! `syscall` crate's `Scheme` trait.
+//! Access is decided at open from the caller's uid and gid: root may do
+//! anything, members of the submit group (`--submit-group`) may submit
+//! jobs and upload models, everyone else may only read status and
+//! history. Completions are handed only to the handle that submitted the
+//! job, so one client never sees another's output.
+//!
+//! ```text
+//!   offset  size  field (little-endian u32)
+//!   0       4     opcode       (InferenceOp; only Infer is accepted)
+//!   4       4     model_size
+//!   8       4     input_size
+//!   12      4     output_size
+//! ```
+//!
+//! Status (`npu:` or `npu:status`, anyone): `read` returns `key: value`
+//! lines with the StatusMonitor state, tiles, job counters and power state
+//! (`D0` or `D0I3`, entries into D0i3, last wake-up latency).
+//!
+//! Power: after each request the driver calls `suspend_if_idle`, which
+//! puts an NPU with nothing to do into D0i3; the next job wakes it.
+//!
+//! History (`npu:history`, anyone): `read` returns the recent state
+//! transitions, oldest first, one per line:
+//! `<unix ms> <ms since driver start> <STATE> <raw FW_STATUS>`.
+//!
+//! Control (`npu:control`, root only): `write` a command.
+//!   - `reset` -> cold-reset the NPU and boot the firmware image already in
+//!     memory. Jobs in flight read as `EIO`; a failed restart fails the
+//!     write with `EIO`. `ENOENT` on open if the driver cannot restart.
+//!
+//! Model cache (submit by hash):
+//!   - `open("npu:model/<sha256>")` then `read` -> `warm` (submit jobs by
+//!     hash, no upload) or `cold` (send the bytes)
+//!   - `write` the model bytes to the same handle; they are verified
+//!     against the hash and cached when the handle is closed
+//!
+//! The protocol itself lives in the inherent methods of `NpuScheme`, which
+//! work on plain errno values and build on any OS, so tests drive it
+//! against the firmware simulator. Only the `Scheme` trait adapter at the
+//! bottom depends on Redox's `syscall` crate.
 
-use std::collections::HashMap;
-use std::cell::{Cell, RefCell};
-use syscall::{Error, Result, Scheme, Stat, EBADF, EINVAL};
-use crate::inference::{CommandQueue, CommandDescriptor};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+#[cfg(not(target_os = "redox"))]
+use libc::{EACCES, EAGAIN, EBADF, EINVAL, EIO, ENOENT, ENOMEM};
+#[cfg(target_os = "redox")]
+use syscall::{EACCES, EAGAIN, EBADF, EINVAL, EIO, ENOENT, ENOMEM};
+use crate::boot::Restarter;
+use crate::dma::DmaError;
+use crate::dma_pool::PooledBuffer;
+use crate::events::{EventKind, EventLog};
+use crate::inference::{CommandQueue, InferenceError, InferenceOp, JobResult};
 use crate::mmio::MmioRegion;
+use crate::model_cache::{CacheError, CacheState, ModelCache, ModelHash};
+use crate::power::PowerState;
 use crate::status::StatusMonitor;
 
+/// Size of the job header written first on an `npu:submit` handle.
+pub const JOB_HEADER_SIZE: usize = 16;
+
+/// Jobs one `npu:submit` handle may have queued or unread at once.
+pub const MAX_JOBS_PER_HANDLE: usize = 8;
+
+/// Job header: what follows on the handle and how much output to expect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobHeader {
+    pub opcode: u32,
+    pub model_size: u32,
+    pub input_size: u32,
+    pub output_size: u32,
+}
+
+impl JobHeader {
+    /// Header for a standard inference job.
+    pub fn infer(model_size: u32, input_size: u32, output_size: u32) -> Self {
+        Self { opcode: InferenceOp::Infer as u32, model_size, input_size, output_size }
+    }
+
+    pub fn to_bytes(self) -> [u8; JOB_HEADER_SIZE] {
+        let mut bytes = [0u8; JOB_HEADER_SIZE];
+        for (i, field) in [self.opcode, self.model_size, self.input_size, self.output_size].iter().enumerate() {
+            bytes[i * 4..i * 4 + 4].copy_from_slice(&field.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Parse and sanity-check a header; every buffer must be non-empty.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < JOB_HEADER_SIZE {
+            return None;
+        }
+        let field = |i: usize| u32::from_le_bytes([bytes[i * 4], bytes[i * 4 + 1], bytes[i * 4 + 2], bytes[i * 4 + 3]]);
+        let header = Self { opcode: field(0), model_size: field(1), input_size: field(2), output_size: field(3) };
+        let sizes_ok = header.model_size > 0 && header.input_size > 0 && header.output_size > 0;
+        (header.opcode == InferenceOp::Infer as u32 && sizes_ok).then_some(header)
+    }
+
+    /// Model and input bytes that follow the header.
+    fn payload_size(&self) -> usize {
+        self.model_size as usize + self.input_size as usize
+    }
+
+    /// Device memory the job needs.
+    fn total_size(&self) -> usize {
+        self.payload_size() + self.output_size as usize
+    }
+}
+
+/// DMA memory staged for one job. Must outlive the job on the device.
+struct JobBuffers {
+    model: PooledBuffer,
+    input: PooledBuffer,
+    output: PooledBuffer,
+}
+
+/// The job being written on an `npu:submit` handle.
+enum Upload {
+    /// Collecting the header
+    Header(Vec<u8>),
+    /// Copying model then input bytes into DMA memory
+    Payload { header: JobHeader, buffers: Box<JobBuffers>, received: usize },
+}
+
+impl Upload {
+    fn new() -> Self {
+        Upload::Header(Vec::with_capacity(JOB_HEADER_SIZE))
+    }
+}
+
+/// A queued job, until its handle has read it back.
+enum JobState {
+    /// On the device
+    Submitted { job_id: u32, output_size: usize, buffers: JobBuffers },
+    /// Output copied out of DMA memory, being read by the client
+    Done { output: Vec<u8>, pos: usize },
+    /// The job failed; reads return this errno
+    Failed(i32),
+}
+
+/// An `npu:submit` handle: the job being written and the ones already
+/// queued, read back oldest first.
+struct JobStream {
+    upload: Upload,
+    jobs: VecDeque<JobState>,
+    /// The oldest job's EOF (or error) was returned; the next read moves
+    /// on once there is a job after it
+    read_out: bool,
+}
+
+impl JobStream {
+    fn new() -> Self {
+        Self { upload: Upload::new(), jobs: VecDeque::new(), read_out: false }
+    }
+}
+
+/// Who sent a request, from the scheme packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Caller {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// What a handle may be used for, decided from its caller at open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    /// Status and history
+    ReadOnly,
+    /// Also submit jobs and upload models
+    Submit,
+    /// Also reset the device
+    Control,
+}
+
+impl Access {
+    /// Root controls the device; members of `submit_group` may run jobs.
+    pub fn of(caller: Caller, submit_group: Option<u32>) -> Self {
+        if caller.uid == 0 {
+            Access::Control
+        } else if submit_group == Some(caller.gid) {
+            Access::Submit
+        } else {
+            Access::ReadOnly
+        }
+    }
+
+    /// What opening `path` takes.
+    pub fn required(path: &str) -> Self {
+        match path {
+            "control" => Access::Control,
+            "submit" | "infer" => Access::Submit,
+            _ if path.starts_with("model/") => Access::Submit,
+            _ => Access::ReadOnly,
+        }
+    }
+}
+
 /// A handle to an open NPU resource
-pub enum NpuHandle {
-    /// Global status handle (npu:)
-    Status,
-    /// Active inference session (npu:infer)
-    Inference {
-        job_id: Option<u32>,
+enum NpuHandle {
+    /// Global status handle (npu: / npu:status); text is built on first read
+    Status { text: Option<Vec<u8>>, pos: usize },
+    /// State transition history (npu:history); text is built on first read
+    History { text: Option<Vec<u8>>, pos: usize },
+    /// Inference jobs (npu:submit)
+    Job(Box<JobStream>),
+    /// Cache lookup / upload for one model (npu:model/<sha256>)
+    Model {
+        hash: ModelHash,
+        upload: Vec<u8>,
     },
+    /// Device commands (npu:control)
+    Control,
+}
+
+/// An open handle and who opened it.
+struct OpenHandle {
+    caller: Caller,
+    access: Access,
+    /// Reads of a running job fail with `EAGAIN` instead of waiting
+    nonblocking: bool,
+    kind: NpuHandle,
+}
+
+/// Which handle submitted each job on the device that has not been read
+/// back yet.
+#[derive(Default)]
+struct JobOwners(HashMap<u32, usize>);
+
+impl JobOwners {
+    fn claim(&mut self, job_id: u32, handle: usize) {
+        self.0.insert(job_id, handle);
+    }
+
+    fn owner(&self, job_id: u32) -> Option<usize> {
+        self.0.get(&job_id).copied()
+    }
+
+    /// The job was read back
+    fn release(&mut self, job_id: u32) {
+        self.0.remove(&job_id);
+    }
+
+    /// Jobs of `handle`, oldest first.
+    fn jobs_of(&self, handle: usize) -> Vec<u32> {
+        let mut jobs: Vec<u32> = self.0.iter().filter(|&(_, &h)| h == handle).map(|(&job_id, _)| job_id).collect();
+        jobs.sort_unstable();
+        jobs
+    }
+
+    /// Drop every claim of a closed handle.
+    fn forget(&mut self, handle: usize) {
+        self.0.retain(|_, h| *h != handle);
+    }
+}
+
+/// Open handles by ID, and the jobs each owns.
+#[derive(Default)]
+struct HandleTable {
+    handles: HashMap<usize, OpenHandle>,
+    next_id: usize,
+    owners: JobOwners,
+}
+
+impl HandleTable {
+    fn insert(&mut self, caller: Caller, access: Access, kind: NpuHandle) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.handles.insert(id, OpenHandle { caller, access, nonblocking: false, kind });
+        id
+    }
+
+    /// Remove a handle; its jobs no longer belong to anyone.
+    fn close(&mut self, id: usize) -> Option<OpenHandle> {
+        let handle = self.handles.remove(&id)?;
+        self.owners.forget(id);
+        Some(handle)
+    }
 }
 
 pub struct NpuScheme<'a> {
     /// Reference to the hardware MMIO
     mmio: &'a MmioRegion,
     /// Reference to the command queue
-    queue: &'a mut CommandQueue,
+    queue: RefCell<&'a mut CommandQueue>,
     /// Reference to status monitor
-    monitor: &'a mut StatusMonitor<'a>,
+    monitor: RefCell<&'a mut StatusMonitor<'a>>,
     /// Active handles (interior mutability for Scheme trait)
-    handles: RefCell<HashMap<usize, NpuHandle>>,
-    /// Next handle ID (interior mutability for Scheme trait)
-    next_id: Cell<usize>,
+    handles: RefCell<HandleTable>,
+    /// Group whose members may submit jobs without being root
+    submit_group: Option<u32>,
+    /// Buffers of jobs whose handle closed before they finished; freed
+    /// only once the device reports the job done
+    orphans: RefCell<HashMap<u32, JobBuffers>>,
+    /// Lifecycle event log (client connects/disconnects)
+    event_log: Option<&'a EventLog>,
+    /// Persistent model cache (None if the cache directory is unusable)
+    model_cache: Option<RefCell<ModelCache>>,
+    /// Restarts the device on a `reset` command (None = npu:control absent)
+    restarter: Option<RefCell<Restarter<'a>>>,
 }
 
 impl<'a> NpuScheme<'a> {
-    pub fn new(mmio: &'a MmioRegion, queue: &'a mut CommandQueue, monitor: &'a mut StatusMonitor<'a>) -> Self {
+    pub fn new(
+        mmio: &'a MmioRegion,
+        queue: &'a mut CommandQueue,
+        monitor: &'a mut StatusMonitor<'a>,
+        event_log: Option<&'a EventLog>,
+        model_cache: Option<ModelCache>,
+    ) -> Self {
         Self {
             mmio,
-            queue,
-            monitor,
-            handles: RefCell::new(HashMap::new()),
-            next_id: Cell::new(0),
+            queue: RefCell::new(queue),
+            monitor: RefCell::new(monitor),
+            handles: RefCell::new(HandleTable::default()),
+            submit_group: None,
+            orphans: RefCell::new(HashMap::new()),
+            event_log,
+            model_cache: model_cache.map(RefCell::new),
+            restarter: None,
+        }
+    }
+
+    /// Accept `reset` on npu:control, restarting the device with `restarter`.
+    pub fn with_restarter(mut self, restarter: Restarter<'a>) -> Self {
+        self.restarter = Some(RefCell::new(restarter));
+        self
+    }
+
+    /// Let members of group `gid` submit jobs and upload models.
+    pub fn with_submit_group(mut self, gid: u32) -> Self {
+        self.submit_group = Some(gid);
+        self
+    }
+
+    /// Warm if the model is cached and loads cleanly; a corrupt blob is
+    /// dropped and reported cold so the client uploads it again.
+    fn model_state(&self, hash: &ModelHash) -> CacheState {
+        let Some(cache) = &self.model_cache else {
+            return CacheState::Cold;
+        };
+        match cache.borrow_mut().get(hash) {
+            Ok(_) => CacheState::Warm,
+            Err(CacheError::NotCached) | Err(CacheError::Corrupt(_)) => CacheState::Cold,
+            Err(e) => {
+                log::warn!("Model cache lookup failed: {}", e);
+                CacheState::Cold
+            }
         }
     }
-}
 
-impl<'a> Scheme for NpuScheme<'a> {
-    fn open(&self, path: &str, _flags: usize, uid: u32, _gid: u32) -> Result<usize> {
-        // Security: Only root (uid 0) can access inference operations.
-        // Status is readable by anyone for monitoring.
-        if path == "infer" && uid != 0 {
-            log::warn!("Non-root user (uid={}) denied access to npu:infer", uid);
-            return Err(Error::new(syscall::EACCES));
+    /// Open `path`; returns the handle ID or an errno.
+    pub fn open_path(&self, path: &str, caller: Caller) -> Result<usize, i32> {
+        // Security: only root and the submit group can submit jobs or
+        // upload models, only root can reset. Status is readable by anyone
+        // for monitoring.
+        let access = Access::of(caller, self.submit_group);
+        if Access::required(path) > access {
+            log::warn!("uid={} gid={} denied access to npu:{}", caller.uid, caller.gid, path);
+            return Err(EACCES);
         }
 
         let handle = match path {
-            "" | "status" => NpuHandle::Status,
-            "infer" => NpuHandle::Inference { job_id: None },
-            _ => return Err(Error::new(syscall::ENOENT)),
+            "" | "status" => NpuHandle::Status { text: None, pos: 0 },
+            "history" => NpuHandle::History { text: None, pos: 0 },
+            "submit" | "infer" => NpuHandle::Job(Box::new(JobStream::new())),
+            "control" if self.restarter.is_some() => NpuHandle::Control,
+            _ => match path.strip_prefix("model/").and_then(ModelHash::from_hex) {
+                Some(hash) => NpuHandle::Model { hash, upload: Vec::new() },
+                None => return Err(ENOENT),
+            },
         };
 
-        let id = self.next_id.get();
-        self.next_id.set(id + 1);
-        self.handles.borrow_mut().insert(id, handle);
+        let id = self.handles.borrow_mut().insert(caller, access, handle);
+        if let Some(log) = self.event_log {
+            log.record(EventKind::ClientConnect, caller.uid, id as u64);
+        }
         Ok(id)
     }
 
-    fn read(&self, id: usize, buf: &mut [u8]) -> Result<usize> {
-        let mut handles = self.handles.borrow_mut();
-        let handle = handles.get_mut(&id).ok_or(Error::new(EBADF))?;
+    /// Reads of running jobs on `id` fail with `EAGAIN` (`O_NONBLOCK`).
+    pub fn set_nonblocking(&self, id: usize) -> Result<(), i32> {
+        self.handles.borrow_mut().handles.get_mut(&id).ok_or(EBADF)?.nonblocking = true;
+        Ok(())
+    }
+
+    /// Whether a read of `id` that returned `result` should be held and
+    /// retried once the job finishes, rather than answered now.
+    pub fn should_wait(&self, id: usize, result: Result<usize, i32>) -> bool {
+        let table = self.handles.borrow();
+        let Some(handle) = table.handles.get(&id) else {
+            return false;
+        };
+        result == Err(EAGAIN) && !handle.nonblocking && matches!(handle.kind, NpuHandle::Job(_))
+    }
+
+    pub fn read_handle(&self, id: usize, buf: &mut [u8]) -> Result<usize, i32> {
+        let mut table = self.handles.borrow_mut();
+        let HandleTable { handles, owners, .. } = &mut *table;
+        let handle = handles.get_mut(&id).ok_or(EBADF)?;
 
-        match handle {
-            NpuHandle::Status => {
-                // Use last_state instead of poll to avoid needing &mut
-                let status = format!("state: READY\nstats: OK\n");
-                let bytes = status.as_bytes();
+        match &mut handle.kind {
+            NpuHandle::Status { text, pos } => {
+                let text = text.get_or_insert_with(|| self.status_text().into_bytes());
+                Ok(copy_out(text, pos, buf))
+            }
+            NpuHandle::History { text, pos } => {
+                let text = text.get_or_insert_with(|| self.history_text().into_bytes());
+                Ok(copy_out(text, pos, buf))
+            }
+            NpuHandle::Job(stream) => self.read_job(id, stream, owners, buf),
+            NpuHandle::Model { hash, .. } => {
+                let reply = format!("{}\n", self.model_state(hash).as_str());
+                let bytes = reply.as_bytes();
                 let len = std::cmp::min(buf.len(), bytes.len());
                 buf[..len].copy_from_slice(&bytes[..len]);
                 Ok(len)
             }
-            NpuHandle::Inference { job_id } => {
-                if let Some(jid) = job_id {
-                    let msg = format!("Job #{} completed (MOCK RESULT)\n", jid);
-                    let bytes = msg.as_bytes();
-                    let len = std::cmp::min(buf.len(), bytes.len());
-                    buf[..len].copy_from_slice(&bytes[..len]);
-                    Ok(len)
-                } else {
-                    Err(Error::new(EINVAL))
+            NpuHandle::Control => Err(EBADF),
+        }
+    }
+
+    pub fn write_handle(&self, id: usize, buf: &[u8]) -> Result<usize, i32> {
+        let mut table = self.handles.borrow_mut();
+        let HandleTable { handles, owners, .. } = &mut *table;
+        let handle = handles.get_mut(&id).ok_or(EBADF)?;
+        let needed = match handle.kind {
+            NpuHandle::Job(_) | NpuHandle::Model { .. } => Access::Submit,
+            NpuHandle::Control => Access::Control,
+            NpuHandle::Status { .. } | NpuHandle::History { .. } => Access::ReadOnly,
+        };
+        if needed > handle.access {
+            return Err(EACCES);
+        }
+
+        match &mut handle.kind {
+            NpuHandle::Job(stream) => {
+                let written = self.write_job(id, stream, owners, buf);
+                if written.is_err() {
+                    // What was written of this job is dropped; the next
+                    // write starts over with a header
+                    stream.upload = Upload::new();
                 }
+                written
             }
+            NpuHandle::Model { upload, .. } => {
+                upload.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            NpuHandle::Control => match std::str::from_utf8(buf).map(str::trim) {
+                Ok("reset") => self.reset().map(|()| buf.len()),
+                _ => Err(EINVAL),
+            },
+            NpuHandle::Status { .. } | NpuHandle::History { .. } => Err(EBADF),
         }
     }
 
-    fn write(&self, id: usize, buf: &[u8]) -> Result<usize> {
-        let mut handles = self.handles.borrow_mut();
-        let handle = handles.get_mut(&id).ok_or(Error::new(EBADF))?;
+    /// Cold-reset the device and boot its firmware again; jobs that were
+    /// on it fail, and buffers of orphaned ones are released.
+    fn reset(&self) -> Result<(), i32> {
+        let restarter = self.restarter.as_ref().ok_or(ENOENT)?;
+        let mut queue = self.queue.borrow_mut();
+        log::warn!("Reset requested through npu:control");
+        let restarted = restarter.borrow_mut().restart(&queue).map(|_| ());
+
+        // The device was reset either way; nothing queued before survives
+        for job_id in queue.abort_in_flight() {
+            if self.orphans.borrow_mut().remove(&job_id).is_some() {
+                queue.take_result(job_id);
+            }
+        }
+        self.monitor.borrow_mut().poll();
+        restarted.map_err(|e| {
+            log::error!("NPU reset failed: {}", e);
+            EIO
+        })
+    }
 
-        match handle {
-            NpuHandle::Inference { job_id } => {
-                if buf.len() < std::mem::size_of::<CommandDescriptor>() {
-                    return Err(Error::new(EINVAL));
+    /// Put the NPU into D0i3 if it has had nothing to do for the idle
+    /// timeout.
+    pub fn suspend_if_idle(&self) {
+        match self.queue.borrow_mut().suspend_if_idle(self.mmio) {
+            Ok(true) => {
+                if let Some(log) = self.event_log {
+                    log.record(EventKind::PowerTransition, PowerState::D0i3.event_code(), 0);
                 }
+            }
+            Ok(false) => {}
+            Err(e) => log::warn!("NPU D0i3 entry failed: {}", e),
+        }
+    }
 
-                // In a real implementation, we'd parse the buffer into a job
-                // For now, we simulate submission
-                // let job = self.queue.submit(self.mmio, ...)?;
-                // *job_id = Some(job);
+    pub fn close_handle(&self, id: usize) -> Result<usize, i32> {
+        let handle = self.handles.borrow_mut().close(id).ok_or(EBADF)?;
+        match handle.kind {
+            NpuHandle::Model { hash, upload } => {
+                if let (Some(cache), false) = (&self.model_cache, upload.is_empty()) {
+                    if let Err(e) = cache.borrow_mut().insert(&upload, Some(hash)) {
+                        log::warn!("Model upload {} rejected: {}", hash.to_hex(), e);
+                        return Err(EINVAL);
+                    }
+                }
+            }
+            // A job still being written never reached the device and is
+            // dropped with the stream. The device may still write into the
+            // buffers of running ones; keep them until it's done
+            NpuHandle::Job(stream) => {
+                let mut orphans = self.orphans.borrow_mut();
+                for job in stream.jobs {
+                    if let JobState::Submitted { job_id, buffers, .. } = job {
+                        log::info!("Client closed job #{} before it finished", job_id);
+                        orphans.insert(job_id, buffers);
+                    }
+                }
+            }
+            _ => {}
+        }
+        if let Some(log) = self.event_log {
+            log.record(EventKind::ClientDisconnect, handle.caller.uid, id as u64);
+        }
+        Ok(0)
+    }
 
-                Ok(buf.len())
+    /// Size reported by fstat: the output length once a job is done.
+    pub fn handle_size(&self, id: usize) -> Result<u64, i32> {
+        match &self.handles.borrow().handles.get(&id).ok_or(EBADF)?.kind {
+            NpuHandle::Job(stream) => match stream.jobs.front() {
+                Some(JobState::Done { output, .. }) => Ok(output.len() as u64),
+                _ => Ok(0),
+            },
+            _ => Ok(0),
+        }
+    }
+
+    /// Feed header, model and input bytes; queues each job once complete
+    /// and claims it for handle `id`.
+    fn write_job(&self, id: usize, stream: &mut JobStream, owners: &mut JobOwners, buf: &[u8]) -> Result<usize, i32> {
+        let mut rest = buf;
+        while !rest.is_empty() {
+            match &mut stream.upload {
+                Upload::Header(partial) => {
+                    let take = (JOB_HEADER_SIZE - partial.len()).min(rest.len());
+                    partial.extend_from_slice(&rest[..take]);
+                    rest = &rest[take..];
+                    if partial.len() == JOB_HEADER_SIZE {
+                        let header = JobHeader::parse(partial).ok_or(EINVAL)?;
+                        if stream.jobs.len() >= MAX_JOBS_PER_HANDLE {
+                            log::warn!("Handle {} already has {} jobs queued or unread", id, stream.jobs.len());
+                            return Err(EAGAIN);
+                        }
+                        let buffers = self.stage(&header)?;
+                        stream.upload = Upload::Payload { header, buffers: Box::new(buffers), received: 0 };
+                    }
+                }
+                Upload::Payload { header, buffers, received } => {
+                    if rest.len() > header.payload_size() - *received {
+                        log::warn!("Job payload longer than its header says");
+                        return Err(EINVAL);
+                    }
+                    // Model bytes come first, then input
+                    let model_size = header.model_size as usize;
+                    let (target, offset, room) = if *received < model_size {
+                        (&buffers.model, *received, model_size - *received)
+                    } else {
+                        (&buffers.input, *received - model_size, header.payload_size() - *received)
+                    };
+                    let take = room.min(rest.len());
+                    target.write_bytes(offset, &rest[..take]).map_err(|_| EINVAL)?;
+                    *received += take;
+                    rest = &rest[take..];
+
+                    if *received == header.payload_size() {
+                        let mut queue = self.queue.borrow_mut();
+                        let asleep = queue.power().state(self.mmio) == PowerState::D0i3;
+                        let next_job_id = queue.next_job_id();
+                        let submitted = queue.submit(self.mmio, &buffers.model, &buffers.input, &buffers.output);
+                        drop(queue);
+                        if asleep && submitted.is_ok() {
+                            if let Some(log) = self.event_log {
+                                let latency = crate::power::stats().last_wake_latency;
+                                log.record(EventKind::PowerTransition, PowerState::D0.event_code(), latency.as_micros() as u64);
+                            }
+                        }
+                        let output_size = header.output_size as usize;
+                        let Upload::Payload { buffers, .. } = std::mem::replace(&mut stream.upload, Upload::new()) else {
+                            unreachable!()
+                        };
+                        match submitted {
+                            Ok(job_id) => {
+                                owners.claim(job_id, id);
+                                stream.jobs.push_back(JobState::Submitted { job_id, output_size, buffers: *buffers });
+                            }
+                            Err(e) => {
+                                log::warn!("Job submission failed: {}", e);
+                                if let (InferenceError::QueueFull, Some(log)) = (&e, self.event_log) {
+                                    log.record(EventKind::QueueOverflow, 0, next_job_id as u64);
+                                }
+                                return Err(match e {
+                                    InferenceError::QueueFull => EAGAIN,
+                                    e if e.is_out_of_memory() => ENOMEM,
+                                    _ => EIO,
+                                });
+                            }
+                        }
+                    }
+                }
             }
-            _ => Err(Error::new(EBADF)),
         }
+        Ok(buf.len())
     }
 
-    fn close(&self, id: usize) -> Result<usize> {
-        self.handles.borrow_mut().remove(&id).ok_or(Error::new(EBADF))?;
-        Ok(0)
+    /// Allocate DMA memory for a job, refusing oversized ones up front.
+    fn stage(&self, header: &JobHeader) -> Result<JobBuffers, i32> {
+        let budget = self.queue.borrow().memory_budget();
+        if header.total_size() > budget {
+            log::warn!("Job needs {} bytes, NPU budget is {} bytes", header.total_size(), budget);
+            return Err(ENOMEM);
+        }
+        // Pool buffers are zeroed on return, direct ones scrubbed when freed
+        let queue = self.queue.borrow();
+        let alloc = |size: u32| {
+            queue.pool().get(size as usize).map_err(|e| match e {
+                DmaError::PoolExhausted { .. } => EAGAIN,
+                e => {
+                    log::error!("Job buffer allocation failed: {}", e);
+                    ENOMEM
+                }
+            })
+        };
+        Ok(JobBuffers {
+            model: alloc(header.model_size)?,
+            input: alloc(header.input_size)?,
+            output: alloc(header.output_size)?,
+        })
+    }
+
+    /// Read the oldest unread job of handle `id`.
+    fn read_job(&self, id: usize, stream: &mut JobStream, owners: &mut JobOwners, buf: &mut [u8]) -> Result<usize, i32> {
+        if stream.read_out && stream.jobs.len() > 1 {
+            stream.jobs.pop_front();
+            stream.read_out = false;
+        }
+        // Nothing was submitted yet
+        let job = stream.jobs.front_mut().ok_or(EINVAL)?;
+
+        if let JobState::Submitted { job_id, output_size, buffers } = job {
+            let result = self.take_result(id, *job_id, owners)?.ok_or(EAGAIN)?;
+            owners.release(*job_id);
+            *job = match result {
+                Ok(_) => {
+                    self.monitor.borrow_mut().record_inference();
+                    match buffers.output.read_bytes(0, *output_size) {
+                        Ok(output) => JobState::Done { output, pos: 0 },
+                        Err(_) => JobState::Failed(EIO),
+                    }
+                }
+                Err(e) => JobState::Failed(if e.is_out_of_memory() { ENOMEM } else { EIO }),
+            };
+        }
+
+        match job {
+            JobState::Done { output, pos } => {
+                let len = copy_out(output, pos, buf);
+                stream.read_out |= *pos == output.len() && len == 0;
+                Ok(len)
+            }
+            JobState::Failed(errno) => {
+                stream.read_out = true;
+                Err(*errno)
+            }
+            JobState::Submitted { .. } => unreachable!(),
+        }
+    }
+
+    /// Outcome of `job_id` for handle `reader`, if the device has finished
+    /// it. Outcomes stay queued for their own handles; orphaned jobs are
+    /// released.
+    fn take_result(&self, reader: usize, job_id: u32, owners: &JobOwners) -> Result<Option<Result<JobResult, InferenceError>>, i32> {
+        if owners.owner(job_id) != Some(reader) {
+            log::warn!("Handle {} asked for job #{}, which is not its own", reader, job_id);
+            return Err(EBADF);
+        }
+        let mut queue = self.queue.borrow_mut();
+        for done_id in queue.poll_completions() {
+            if self.orphans.borrow_mut().remove(&done_id).is_some() {
+                queue.take_result(done_id);
+            }
+        }
+        Ok(queue.take_result(job_id))
+    }
+
+    fn status_text(&self) -> String {
+        let mut monitor = self.monitor.borrow_mut();
+        let state = monitor.poll();
+        let tiles = monitor.tile_config();
+        let queue = self.queue.borrow();
+        let power = crate::power::stats();
+        format!(
+            "state: {}\nfw_status: {:#010x}\ntiles: {}\ntile_mask: {:#x}\ninferences: {}\njobs_submitted: {}\njobs_in_flight: {}\npower: {}\nd0i3_entries: {}\nlast_wake_us: {}\n",
+            state.name(),
+            monitor.raw_status(),
+            tiles.count,
+            tiles.enabled_tiles,
+            monitor.total_inferences(),
+            queue.stats().total_submitted,
+            queue.in_flight(),
+            queue.power().state(self.mmio).name(),
+            power.suspends,
+            power.last_wake_latency.as_micros(),
+        )
     }
 
-    fn fstat(&self, id: usize, stat: &mut Stat) -> Result<usize> {
-        let handles = self.handles.borrow();
-        let _handle = handles.get(&id).ok_or(Error::new(EBADF))?;
+    fn history_text(&self) -> String {
+        let mut monitor = self.monitor.borrow_mut();
+        monitor.poll();
+        monitor
+            .history()
+            .iter()
+            .map(|t| format!("{} {} {} {:#010x}\n", t.timestamp_ms, t.uptime_ms, t.state.name(), t.raw))
+            .collect()
+    }
+}
+
+/// Copy the unread part of `data` into `buf`; 0 at end of data.
+fn copy_out(data: &[u8], pos: &mut usize, buf: &mut [u8]) -> usize {
+    let len = buf.len().min(data.len() - *pos);
+    buf[..len].copy_from_slice(&data[*pos..*pos + len]);
+    *pos += len;
+    len
+}
+
+#[cfg(target_os = "redox")]
+impl<'a> syscall::Scheme for NpuScheme<'a> {
+    fn open(&self, path: &str, flags: usize, uid: u32, gid: u32) -> syscall::Result<usize> {
+        let id = self.open_path(path, Caller { uid, gid }).map_err(syscall::Error::new)?;
+        if flags & syscall::O_NONBLOCK != 0 {
+            self.set_nonblocking(id).map_err(syscall::Error::new)?;
+        }
+        Ok(id)
+    }
+
+    fn read(&self, id: usize, buf: &mut [u8]) -> syscall::Result<usize> {
+        self.read_handle(id, buf).map_err(syscall::Error::new)
+    }
+
+    fn write(&self, id: usize, buf: &[u8]) -> syscall::Result<usize> {
+        self.write_handle(id, buf).map_err(syscall::Error::new)
+    }
+
+    fn close(&self, id: usize) -> syscall::Result<usize> {
+        self.close_handle(id).map_err(syscall::Error::new)
+    }
+
+    fn fstat(&self, id: usize, stat: &mut syscall::Stat) -> syscall::Result<usize> {
         stat.st_mode = syscall::MODE_FILE | 0o666;
-        stat.st_size = 0;
+        stat.st_size = self.handle_size(id).map_err(syscall::Error::new)?;
         Ok(0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fwsim::FwSim;
+    use crate::hw_mtl::{DMA_ALIGNMENT, FW_STATUS_READY, JOB_STATUS_OUT_OF_RESOURCES};
+
+    const ROOT: Caller = Caller { uid: 0, gid: 0 };
+    const USER: Caller = Caller { uid: 1000, gid: 1000 };
+
+    fn read_all(scheme: &NpuScheme, id: usize) -> Result<Vec<u8>, i32> {
+        let mut out = Vec::new();
+        let mut chunk = [0u8; 7];
+        loop {
+            match scheme.read_handle(id, &mut chunk)? {
+                0 => return Ok(out),
+                n => out.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+
+    #[test]
+    fn test_job_round_trip() {
+        let mut sim = FwSim::new();
+        sim.set_echo(true);
+        let mut queue = CommandQueue::new(4).unwrap();
+        let mut monitor = StatusMonitor::new(sim.mmio());
+        let scheme = NpuScheme::new(sim.mmio(), &mut queue, &mut monitor, None, None);
+
+        assert_eq!(scheme.open_path("submit", USER), Err(EACCES));
+        let id = scheme.open_path("submit", ROOT).unwrap();
+        // Nothing submitted yet
+        assert_eq!(scheme.read_handle(id, &mut [0u8; 8]), Err(EINVAL));
+
+        let header = JobHeader::infer(32, 5, 5).to_bytes();
+        let model = [0x42u8; 32];
+        // Split writes: half a header, the rest of it with part of the model
+        assert_eq!(scheme.write_handle(id, &header[..6]), Ok(6));
+        let mut second = header[6..].to_vec();
+        second.extend_from_slice(&model[..10]);
+        assert_eq!(scheme.write_handle(id, &second), Ok(second.len()));
+        assert_eq!(scheme.queue.borrow().stats().total_submitted, 0);
+        let mut last = model[10..].to_vec();
+        last.extend_from_slice(b"hello");
+        assert_eq!(scheme.write_handle(id, &last), Ok(last.len()));
+        assert_eq!(scheme.queue.borrow().stats().total_submitted, 1);
+        // The next bytes start another job; a bad header drops it
+        assert_eq!(scheme.write_handle(id, &[0xffu8; JOB_HEADER_SIZE]), Err(EINVAL));
+
+        // Still running
+        assert_eq!(scheme.read_handle(id, &mut [0u8; 8]), Err(EAGAIN));
+        sim.service(&scheme.queue.borrow()).unwrap();
+
+        assert_eq!(read_all(&scheme, id).unwrap(), b"hello");
+        assert_eq!(scheme.handle_size(id), Ok(5));
+        scheme.close_handle(id).unwrap();
+
+        let status_id = scheme.open_path("status", USER).unwrap();
+        let status = String::from_utf8(read_all(&scheme, status_id).unwrap()).unwrap();
+        assert!(status.starts_with("state: READY\n"), "{}", status);
+        assert!(status.contains("inferences: 1\n"));
+        assert!(status.contains("jobs_in_flight: 0\n"));
+        assert_eq!(scheme.write_handle(status_id, b"x"), Err(EBADF));
+
+        let history_id = scheme.open_path("history", USER).unwrap();
+        let history = String::from_utf8(read_all(&scheme, history_id).unwrap()).unwrap();
+        let states: Vec<&str> = history.lines().map(|l| l.split(' ').nth(2).unwrap()).collect();
+        assert_eq!(states, vec!["POWERED_OFF", "READY"], "{}", history);
+        assert!(history.ends_with(&format!(" READY {:#010x}\n", FW_STATUS_READY)), "{}", history);
+    }
+
+    #[test]
+    fn test_submission_wakes_idle_device() {
+        let mut sim = FwSim::new();
+        sim.set_echo(true);
+        let mut queue = CommandQueue::new(4).unwrap();
+        queue.power_mut().set_idle_timeout(Some(std::time::Duration::ZERO));
+        let mut monitor = StatusMonitor::new(sim.mmio());
+        let scheme = NpuScheme::new(sim.mmio(), &mut queue, &mut monitor, None, None);
+        let status = || {
+            let id = scheme.open_path("status", USER).unwrap();
+            String::from_utf8(read_all(&scheme, id).unwrap()).unwrap()
+        };
+
+        scheme.suspend_if_idle();
+        assert!(status().contains("power: D0I3\n"), "{}", status());
+
+        let id = scheme.open_path("submit", ROOT).unwrap();
+        let mut job = JobHeader::infer(4, 5, 5).to_bytes().to_vec();
+        job.extend_from_slice(b"\0\0\0\0awake");
+        scheme.write_handle(id, &job).unwrap();
+        sim.service(&scheme.queue.borrow()).unwrap();
+        assert_eq!(read_all(&scheme, id).unwrap(), b"awake");
+        assert!(status().contains("power: D0\n"), "{}", status());
+    }
+
+    #[test]
+    fn test_bad_jobs_rejected() {
+        let mut sim = FwSim::new();
+        // The firmware has less memory than the host-side budget
+        sim.set_mem_budget(Some(DMA_ALIGNMENT));
+        let mut queue = CommandQueue::new(4).unwrap();
+        queue.set_memory_budget(8 * DMA_ALIGNMENT);
+        let mut monitor = StatusMonitor::new(sim.mmio());
+        let scheme = NpuScheme::new(sim.mmio(), &mut queue, &mut monitor, None, None);
+
+        let submit = |header: JobHeader| {
+            let id = scheme.open_path("submit", ROOT).unwrap();
+            scheme.write_handle(id, &header.to_bytes())
+        };
+        // Only inference, and every buffer must be present
+        assert_eq!(submit(JobHeader { opcode: InferenceOp::Profile as u32, ..JobHeader::infer(4, 4, 4) }), Err(EINVAL));
+        assert_eq!(submit(JobHeader::infer(4, 0, 4)), Err(EINVAL));
+        // Bigger than the host budget, refused before allocating
+        assert_eq!(submit(JobHeader::infer(16 * DMA_ALIGNMENT as u32, 4, 4)), Err(ENOMEM));
+
+        let id = scheme.open_path("submit", ROOT).unwrap();
+        scheme.write_handle(id, &JobHeader::infer(4, 4, 4).to_bytes()).unwrap();
+        assert_eq!(scheme.write_handle(id, &[0u8; 9]), Err(EINVAL));
+
+        // Firmware out of memory reads back as ENOMEM
+        let id = scheme.open_path("submit", ROOT).unwrap();
+        let mut job = JobHeader::infer(4, 4, 4).to_bytes().to_vec();
+        job.extend_from_slice(&[1u8; 8]);
+        scheme.write_handle(id, &job).unwrap();
+        assert_eq!(sim.service(&scheme.queue.borrow()).map(|(_, s)| s), Some(JOB_STATUS_OUT_OF_RESOURCES));
+        assert_eq!(scheme.read_handle(id, &mut [0u8; 4]), Err(ENOMEM));
+        assert_eq!(scheme.queue.borrow().in_flight(), 0);
+    }
+
+    #[test]
+    fn test_completions_reach_the_right_handle() {
+        let mut sim = FwSim::new();
+        sim.set_echo(true);
+        let mut queue = CommandQueue::new(4).unwrap();
+        let mut monitor = StatusMonitor::new(sim.mmio());
+        let scheme = NpuScheme::new(sim.mmio(), &mut queue, &mut monitor, None, None);
+
+        let submit = |input: &[u8]| {
+            let id = scheme.open_path("submit", ROOT).unwrap();
+            let mut job = JobHeader::infer(4, input.len() as u32, input.len() as u32).to_bytes().to_vec();
+            job.extend_from_slice(&[0u8; 4]);
+            job.extend_from_slice(input);
+            scheme.write_handle(id, &job).unwrap();
+            id
+        };
+        let service = || sim.service(&scheme.queue.borrow()).unwrap();
+
+        let first = submit(b"one");
+        service();
+        let second = submit(b"two");
+        // Picks up (and keeps) the first job's completion
+        assert_eq!(scheme.read_handle(second, &mut [0u8; 4]), Err(EAGAIN));
+        service();
+        assert_eq!(read_all(&scheme, second).unwrap(), b"two");
+
+        // Closed while running: buffers held until the device is done
+        let orphan = submit(b"gone");
+        service();
+        scheme.close_handle(orphan).unwrap();
+        assert_eq!(scheme.orphans.borrow().len(), 1);
+
+        assert_eq!(read_all(&scheme, first).unwrap(), b"one");
+        assert!(scheme.orphans.borrow().is_empty());
+        assert_eq!(scheme.queue.borrow().in_flight(), 0);
+    }
+
+    #[test]
+    fn test_access_follows_caller() {
+        let member = Caller { uid: 1000, gid: 50 };
+        assert_eq!(Access::of(ROOT, None), Access::Control);
+        assert_eq!(Access::of(member, None), Access::ReadOnly);
+        assert_eq!(Access::of(member, Some(50)), Access::Submit);
+        assert_eq!(Access::required("status"), Access::ReadOnly);
+        assert_eq!(Access::required("infer"), Access::Submit);
+        assert_eq!(Access::required("model/00"), Access::Submit);
+        assert_eq!(Access::required("control"), Access::Control);
+
+        let mut table = HandleTable::default();
+        let a = table.insert(member, Access::Submit, NpuHandle::Status { text: None, pos: 0 });
+        let b = table.insert(USER, Access::ReadOnly, NpuHandle::Status { text: None, pos: 0 });
+        assert_ne!(a, b);
+        table.owners.claim(7, a);
+        table.owners.claim(3, a);
+        table.owners.claim(4, b);
+        assert_eq!(table.owners.jobs_of(a), vec![3, 7]);
+        table.owners.release(3);
+        assert_eq!(table.owners.jobs_of(a), vec![7]);
+
+        // Closing forgets the handle's jobs, and only those
+        let closed = table.close(a).unwrap();
+        assert_eq!((closed.caller, closed.access), (member, Access::Submit));
+        assert_eq!(table.owners.owner(7), None);
+        assert_eq!(table.owners.owner(4), Some(b));
+        assert!(table.close(a).is_none());
+    }
+
+    #[test]
+    fn test_submit_group_and_pipelined_jobs() {
+        use crate::dma_pool::{DmaPool, SizeClass};
+
+        let mut sim = FwSim::new();
+        sim.set_echo(true);
+        let mut queue = CommandQueue::new(16).unwrap();
+        queue.set_pool(DmaPool::new(&[SizeClass { size: DMA_ALIGNMENT, count: 32 }]).unwrap());
+        let mut monitor = StatusMonitor::new(sim.mmio());
+        let scheme = NpuScheme::new(sim.mmio(), &mut queue, &mut monitor, None, None).with_submit_group(50);
+        let job = |input: &[u8]| {
+            let mut job = JobHeader::infer(4, input.len() as u32, input.len() as u32).to_bytes().to_vec();
+            job.extend_from_slice(&[0u8; 4]);
+            job.extend_from_slice(input);
+            job
+        };
+
+        let member = Caller { uid: 1000, gid: 50 };
+        assert_eq!(scheme.open_path("control", member), Err(EACCES));
+        let mine = scheme.open_path("submit", member).unwrap();
+        let theirs = scheme.open_path("submit", ROOT).unwrap();
+
+        // Several jobs on one handle, read back in order
+        scheme.write_handle(mine, &job(b"one")).unwrap();
+        assert_eq!(scheme.read_handle(mine, &mut [0u8; 4]), Err(EAGAIN));
+        assert!(scheme.should_wait(mine, Err(EAGAIN)));
+        for (id, input) in [(mine, &b"two"[..]), (mine, b"three"), (theirs, b"root")] {
+            sim.service(&scheme.queue.borrow()).unwrap();
+            scheme.write_handle(id, &job(input)).unwrap();
+        }
+        sim.service(&scheme.queue.borrow()).unwrap();
+        assert_eq!(scheme.handles.borrow().owners.jobs_of(mine).len(), 3);
+
+        assert_eq!(read_all(&scheme, mine).unwrap(), b"one");
+        assert_eq!(read_all(&scheme, mine).unwrap(), b"two");
+        assert_eq!(scheme.handle_size(mine), Ok(3));
+        // Another handle's job can't be taken, even with its id
+        let root_job = scheme.handles.borrow().owners.jobs_of(theirs)[0];
+        assert_eq!(scheme.take_result(mine, root_job, &scheme.handles.borrow().owners).err(), Some(EBADF));
+        assert_eq!(read_all(&scheme, mine).unwrap(), b"three");
+        // EOF once the last job is read
+        assert_eq!(scheme.read_handle(mine, &mut [0u8; 4]), Ok(0));
+        assert_eq!(read_all(&scheme, theirs).unwrap(), b"root");
+
+        // Each handle has a bounded number of jobs queued or unread
+        for _ in 1..MAX_JOBS_PER_HANDLE {
+            scheme.write_handle(mine, &job(b"x")).unwrap();
+        }
+        assert_eq!(scheme.write_handle(mine, &job(b"y")), Err(EAGAIN));
+
+        // Non-blocking handles and other files are answered at once
+        scheme.set_nonblocking(mine).unwrap();
+        assert!(!scheme.should_wait(mine, Err(EAGAIN)));
+        let status = scheme.open_path("status", USER).unwrap();
+        assert!(!scheme.should_wait(status, Err(EAGAIN)));
+
+        // Closing drops a half-written job and forgets the running ones
+        scheme.write_handle(theirs, &job(b"half")[..JOB_HEADER_SIZE + 2]).unwrap();
+        let free = || scheme.queue.borrow().pool().usage()[0].1;
+        let before = free();
+        scheme.close_handle(theirs).unwrap();
+        assert_eq!(free(), before + 3);
+        scheme.close_handle(mine).unwrap();
+        assert_eq!(scheme.orphans.borrow().len(), MAX_JOBS_PER_HANDLE - 1);
+        assert!(scheme.handles.borrow().owners.0.is_empty());
+    }
+
+    #[test]
+    fn test_job_buffers_come_from_pool() {
+        use crate::dma_pool::{DmaPool, SizeClass};
+
+        let mut sim = FwSim::new();
+        sim.set_echo(true);
+        let mut queue = CommandQueue::new(4).unwrap();
+        // Room for exactly one job's three buffers
+        queue.set_pool(DmaPool::new(&[SizeClass { size: DMA_ALIGNMENT, count: 3 }]).unwrap());
+        let mut monitor = StatusMonitor::new(sim.mmio());
+        let scheme = NpuScheme::new(sim.mmio(), &mut queue, &mut monitor, None, None);
+
+        let header = JobHeader::infer(4, 3, 3).to_bytes();
+        let first = scheme.open_path("submit", ROOT).unwrap();
+        scheme.write_handle(first, &header).unwrap();
+        let second = scheme.open_path("submit", ROOT).unwrap();
+        assert_eq!(scheme.write_handle(second, &header), Err(EAGAIN));
+
+        scheme.write_handle(first, b"\0\0\0\0abc").unwrap();
+        sim.service(&scheme.queue.borrow()).unwrap();
+        assert_eq!(read_all(&scheme, first).unwrap(), b"abc");
+        assert_eq!(scheme.queue.borrow().pool().usage(), vec![(DMA_ALIGNMENT, 3, 3)]);
+
+        // Reused buffers start out zeroed
+        let third = scheme.open_path("submit", ROOT).unwrap();
+        scheme.write_handle(third, &JobHeader::infer(4, 3, 8).to_bytes()).unwrap();
+        scheme.write_handle(third, b"\0\0\0\0xyz").unwrap();
+        sim.service(&scheme.queue.borrow()).unwrap();
+        assert_eq!(read_all(&scheme, third).unwrap(), b"xyz\0\0\0\0\0");
+    }
+
+    #[test]
+    fn test_queue_overflow_is_logged() {
+        use crate::events::EventLog;
+
+        let path = std::env::temp_dir().join(format!("npu_scheme_overflow_{}.bin", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let log = EventLog::open(&path, 4096).unwrap();
+        let mut sim = FwSim::new();
+        sim.set_echo(true);
+        let mut queue = CommandQueue::new(1).unwrap();
+        let mut monitor = StatusMonitor::new(sim.mmio());
+        let scheme = NpuScheme::new(sim.mmio(), &mut queue, &mut monitor, Some(&log), None);
+
+        let mut job = JobHeader::infer(4, 3, 3).to_bytes().to_vec();
+        job.extend_from_slice(b"\0\0\0\0abc");
+        let first = scheme.open_path("submit", ROOT).unwrap();
+        scheme.write_handle(first, &job).unwrap();
+        let second = scheme.open_path("submit", ROOT).unwrap();
+        assert_eq!(scheme.write_handle(second, &job), Err(EAGAIN));
+
+        let overflows: Vec<u64> = log
+            .events()
+            .unwrap()
+            .iter()
+            .filter(|e| e.kind == EventKind::QueueOverflow)
+            .map(|e| e.value)
+            .collect();
+        assert_eq!(overflows, vec![2]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_control_reset_restarts_device() {
+        let mut sim = FwSim::new();
+        sim.set_echo(true);
+        let fw_path = std::env::temp_dir().join(format!("scheme_reset_{}.bin", std::process::id()));
+        std::fs::write(&fw_path, crate::fw_image::build_image("scheme", &[0u8; DMA_ALIGNMENT])).unwrap();
+        let mut booted = crate::boot::BootSequence::new(sim.mmio())
+            .execute(fw_path.to_str().unwrap(), CommandQueue::new(4).unwrap())
+            .unwrap();
+        std::fs::remove_file(&fw_path).ok();
+
+        // Without a restarter there is no control file
+        let mut queue = CommandQueue::new(4).unwrap();
+        let mut monitor = StatusMonitor::new(sim.mmio());
+        let plain = NpuScheme::new(sim.mmio(), &mut queue, &mut monitor, None, None);
+        assert_eq!(plain.open_path("control", ROOT), Err(ENOENT));
+        drop(plain);
+
+        let mut monitor = StatusMonitor::new(sim.mmio());
+        let (queue, restarter) = booted.split();
+        let scheme = NpuScheme::new(sim.mmio(), queue, &mut monitor, None, None).with_restarter(restarter);
+        assert_eq!(scheme.open_path("control", USER), Err(EACCES));
+        let control = scheme.open_path("control", ROOT).unwrap();
+        assert_eq!(scheme.write_handle(control, b"reboot"), Err(EINVAL));
+        assert_eq!(scheme.read_handle(control, &mut [0u8; 4]), Err(EBADF));
+
+        let submit = |input: &[u8]| {
+            let id = scheme.open_path("submit", ROOT).unwrap();
+            let mut job = JobHeader::infer(4, input.len() as u32, input.len() as u32).to_bytes().to_vec();
+            job.extend_from_slice(&[0u8; 4]);
+            job.extend_from_slice(input);
+            scheme.write_handle(id, &job).unwrap();
+            id
+        };
+        let running = submit(b"lost");
+        let orphan = submit(b"gone");
+        scheme.close_handle(orphan).unwrap();
+        sim.mmio().write32(crate::hw_mtl::IPC_HOST_2_DEVICE_DATA0, 0);
+
+        assert_eq!(scheme.write_handle(control, b"reset\n"), Ok(6));
+        // Jobs on the device were lost in the reset, the queue is registered again
+        assert_eq!(scheme.read_handle(running, &mut [0u8; 4]), Err(EIO));
+        assert!(scheme.orphans.borrow().is_empty());
+        assert_eq!(scheme.queue.borrow().in_flight(), 0);
+        assert_eq!(sim.mmio().read32(crate::hw_mtl::IPC_HOST_2_DEVICE_DATA0), scheme.queue.borrow().phys_addr() as u32);
+
+        let after = submit(b"back");
+        sim.service(&scheme.queue.borrow()).unwrap();
+        assert_eq!(read_all(&scheme, after).unwrap(), b"back");
+
+        // Firmware that stays dead fails the write
+        sim.crash();
+        assert_eq!(scheme.write_handle(control, b"reset"), Err(EIO));
+    }
+}
@@ -4,13 +4,22 @@
 //! Watches the FW_STATUS register for state changes, detects crashes,
 //! and provides an interface for querying NPU readiness.
 
+use crate::dma::DmaStats;
+use crate::events::{EventKind, EventLog, NpuEvent};
 use crate::hw_mtl::*;
 use crate::mmio::MmioRegion;
+use crate::power::PowerStats;
 use log::{debug, error, info, warn};
+use std::collections::VecDeque;
+use std::thread;
 use std::time::{Duration, Instant};
 
+/// State transitions kept for `history()`.
+pub const STATE_HISTORY_LEN: usize = 32;
+
 /// Current NPU state, derived from hardware registers.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(serde::Deserialize), serde(try_from = "String"))]
 pub enum NpuState {
     /// Not powered / not initialized
     PoweredOff,
@@ -26,6 +35,38 @@ pub enum NpuState {
     Unknown(u32),
 }
 
+impl NpuState {
+    /// Plain name for machine-readable output (status files, JSON).
+    pub fn name(&self) -> &'static str {
+        match self {
+            NpuState::PoweredOff => "POWERED_OFF",
+            NpuState::Booting => "BOOTING",
+            NpuState::Ready => "READY",
+            NpuState::Dead => "DEAD",
+            NpuState::Busy => "BUSY",
+            NpuState::Unknown(_) => "UNKNOWN",
+        }
+    }
+}
+
+/// From `name()`; the raw value of `Unknown` is not in the name.
+#[cfg(test)]
+impl TryFrom<String> for NpuState {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, String> {
+        match name.as_str() {
+            "POWERED_OFF" => Ok(NpuState::PoweredOff),
+            "BOOTING" => Ok(NpuState::Booting),
+            "READY" => Ok(NpuState::Ready),
+            "DEAD" => Ok(NpuState::Dead),
+            "BUSY" => Ok(NpuState::Busy),
+            "UNKNOWN" => Ok(NpuState::Unknown(0)),
+            _ => Err(format!("unknown NPU state {}", name)),
+        }
+    }
+}
+
 impl std::fmt::Display for NpuState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -39,42 +80,243 @@ impl std::fmt::Display for NpuState {
     }
 }
 
+/// How the watchdog brings a DEAD NPU back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoveryPolicy {
+    /// Restarts per DEAD transition before giving up
+    pub max_attempts: u32,
+    /// Wait before the first restart; doubles on each one after
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+impl RecoveryPolicy {
+    /// Wait before restart `attempt` (1-based).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32 << attempt.saturating_sub(1).min(16);
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// A state the monitor saw the NPU enter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+pub struct StateTransition {
+    /// Wall clock time, milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// Milliseconds since the monitor was created
+    pub uptime_ms: u64,
+    pub state: NpuState,
+    /// FW_STATUS value the state was decoded from
+    pub raw: u32,
+}
+
+impl StateTransition {
+    pub fn to_json(self) -> String {
+        format!(
+            "{{\"timestamp_ms\":{},\"uptime_ms\":{},\"state\":\"{}\",\"raw\":{}}}",
+            self.timestamp_ms,
+            self.uptime_ms,
+            self.state.name(),
+            self.raw
+        )
+    }
+}
+
+/// The PCI device a diagnostic report is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+pub struct DeviceSummary {
+    pub device_id: u16,
+    pub name: String,
+    /// PCI bus:device.function
+    pub bdf: String,
+    pub bar0_size: u64,
+}
+
+/// Everything `--diagnostics --json` reports.
+///
+/// Built by `StatusMonitor::diagnostics` and rendered by `to_json`; the
+/// field names are the JSON keys.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+pub struct Diagnostics {
+    pub driver_version: String,
+    pub device: DeviceSummary,
+    pub generation: String,
+    pub state: NpuState,
+    pub fw_status: u32,
+    pub fw_status_decoded: String,
+    pub fw_version: u32,
+    pub buttress_status: u32,
+    pub interrupt_status: u32,
+    /// Raw BUTTRESS_TILE_FUSE; `tiles` is its decoding
+    pub tile_fuse: u32,
+    pub tiles: TileConfig,
+    pub boot_count: u32,
+    pub uptime_secs: f64,
+    pub inferences: u64,
+    /// Transitions since the monitor was created (`history` keeps the last
+    /// `STATE_HISTORY_LEN`)
+    pub state_changes: u64,
+    pub recovery_attempts: u32,
+    pub last_recovery_secs_ago: Option<f64>,
+    /// Oldest first
+    pub history: Vec<StateTransition>,
+    pub dma: DmaStats,
+    /// D0i3 entries and wake latency
+    pub power: PowerStats,
+    pub events: Vec<NpuEvent>,
+}
+
+impl Diagnostics {
+    /// Render as a JSON object (no serde dependency in the driver).
+    pub fn to_json(&self) -> String {
+        let history: Vec<String> = self.history.iter().map(|t| t.to_json()).collect();
+        let events: Vec<String> = self.events.iter().map(|e| e.to_json()).collect();
+        format!(
+            "{{\"driver_version\":\"{}\",\"device\":{{\"device_id\":{},\"name\":\"{}\",\"bdf\":\"{}\",\"bar0_size\":{}}},\"generation\":\"{}\",\"state\":\"{}\",\"fw_status\":{},\"fw_status_decoded\":\"{}\",\"fw_version\":{},\"buttress_status\":{},\"interrupt_status\":{},\"tile_fuse\":{},\"tiles\":{},\"boot_count\":{},\"uptime_secs\":{:.1},\"inferences\":{},\"state_changes\":{},\"recovery_attempts\":{},\"last_recovery_secs_ago\":{},\"history\":[{}],\"dma\":{},\"power\":{},\"events\":[{}]}}",
+            self.driver_version,
+            self.device.device_id,
+            self.device.name,
+            self.device.bdf,
+            self.device.bar0_size,
+            self.generation,
+            self.state.name(),
+            self.fw_status,
+            self.fw_status_decoded,
+            self.fw_version,
+            self.buttress_status,
+            self.interrupt_status,
+            self.tile_fuse,
+            self.tiles.to_json(),
+            self.boot_count,
+            self.uptime_secs,
+            self.inferences,
+            self.state_changes,
+            self.recovery_attempts,
+            self.last_recovery_secs_ago.map_or("null".to_string(), |secs| format!("{:.1}", secs)),
+            history.join(","),
+            self.dma.to_json(),
+            self.power.to_json(),
+            events.join(",")
+        )
+    }
+}
+
+/// Every restart allowed by the `RecoveryPolicy` failed.
+#[derive(Debug)]
+pub struct RecoveryFailed {
+    pub attempts: u32,
+    pub last_error: String,
+}
+
+impl std::fmt::Display for RecoveryFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NPU recovery failed after {} attempts: {}", self.attempts, self.last_error)
+    }
+}
+
+impl std::error::Error for RecoveryFailed {}
+
 /// Status monitor that reads hardware state.
 pub struct StatusMonitor<'a> {
     mmio: &'a MmioRegion,
+    regs: &'static RegisterMap,
     last_state: NpuState,
     last_check: Instant,
-    state_changes: Vec<(Instant, NpuState)>,
+    /// The last `STATE_HISTORY_LEN` transitions, oldest first
+    history: VecDeque<StateTransition>,
+    /// Transitions ever seen, including those dropped from `history`
+    state_changes: u64,
     total_inferences: u64,
     uptime_start: Instant,
+    event_log: Option<&'a EventLog>,
+    /// Tiles on a full part, for decoding the tile fuse
+    max_tiles: u8,
+    recovery_policy: RecoveryPolicy,
+    /// Restarts attempted since the monitor was created
+    recovery_attempts: u32,
+    last_recovery_time: Option<Instant>,
 }
 
 impl<'a> StatusMonitor<'a> {
     pub fn new(mmio: &'a MmioRegion) -> Self {
         let now = Instant::now();
+        let initial = StateTransition {
+            timestamp_ms: crate::events::now_ms(),
+            uptime_ms: 0,
+            state: NpuState::PoweredOff,
+            raw: 0,
+        };
         Self {
             mmio,
+            regs: &REGS_MTL,
             last_state: NpuState::PoweredOff,
             last_check: now,
-            state_changes: vec![(now, NpuState::PoweredOff)],
+            history: VecDeque::from([initial]),
+            state_changes: 1,
             total_inferences: 0,
             uptime_start: now,
+            event_log: None,
+            max_tiles: NPU_TILES_MTL,
+            recovery_policy: RecoveryPolicy::default(),
+            recovery_attempts: 0,
+            last_recovery_time: None,
         }
     }
 
+    /// Replace the default watchdog recovery policy.
+    pub fn set_recovery_policy(&mut self, policy: RecoveryPolicy) {
+        self.recovery_policy = policy;
+    }
+
+    /// Read this device generation's registers and decode its tile fuse.
+    pub fn set_device(&mut self, device_id: u16) -> Result<(), UnsupportedGeneration> {
+        self.regs = RegisterMap::for_device(device_id)?;
+        self.max_tiles = npu_max_tiles(device_id);
+        Ok(())
+    }
+
+    /// Persist state transitions to the given event log.
+    pub fn set_event_log(&mut self, log: &'a EventLog) {
+        self.event_log = Some(log);
+    }
+
     /// Read the current NPU state from hardware.
     pub fn poll(&mut self) -> NpuState {
-        let raw = self.mmio.read32(HOST_SS_FW_STATUS);
+        let raw = self.mmio.read32(self.regs.fw_status);
         let state = self.decode_state(raw);
 
         if state != self.last_state {
-            let now = Instant::now();
             info!(
                 "NPU state change: {} → {} (raw={:#010x})",
                 self.last_state, state, raw
             );
-            self.state_changes.push((now, state));
+            if self.history.len() == STATE_HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.history.push_back(StateTransition {
+                timestamp_ms: crate::events::now_ms(),
+                uptime_ms: self.uptime().as_millis() as u64,
+                state,
+                raw,
+            });
+            self.state_changes += 1;
             self.last_state = state;
+            if let Some(log) = self.event_log {
+                log.record(EventKind::StateTransition, raw, 0);
+            }
         }
 
         self.last_check = Instant::now();
@@ -93,22 +335,27 @@ impl<'a> StatusMonitor<'a> {
 
     /// Get raw firmware status register value.
     pub fn raw_status(&self) -> u32 {
-        self.mmio.read32(HOST_SS_FW_STATUS)
+        self.mmio.read32(self.regs.fw_status)
     }
 
     /// Get firmware version (valid only after successful boot).
     pub fn fw_version(&self) -> u32 {
-        self.mmio.read32(HOST_SS_FW_VERSION)
+        self.mmio.read32(self.regs.fw_version)
     }
 
     /// Get Buttress power status.
     pub fn buttress_status(&self) -> u32 {
-        self.mmio.read32(BUTTRESS_VPU_STATUS)
+        self.mmio.read32(self.regs.vpu_status)
+    }
+
+    /// Usable tiles, from the tile fuse.
+    pub fn tile_config(&self) -> TileConfig {
+        TileConfig::from_fuse(self.mmio.read32(self.regs.tile_fuse), self.max_tiles)
     }
 
     /// Get interrupt status.
     pub fn interrupt_status(&self) -> u32 {
-        self.mmio.read32(BUTTRESS_GLOBAL_INT_STS)
+        self.mmio.read32(self.regs.global_int_sts)
     }
 
     /// Get uptime since monitor creation.
@@ -117,8 +364,14 @@ impl<'a> StatusMonitor<'a> {
     }
 
     /// Get number of state changes observed.
-    pub fn state_change_count(&self) -> usize {
-        self.state_changes.len()
+    pub fn state_change_count(&self) -> u64 {
+        self.state_changes
+    }
+
+    /// Recent state transitions, oldest first (at most `STATE_HISTORY_LEN`;
+    /// the first is the monitor's initial POWERED_OFF until it rolls off).
+    pub fn history(&self) -> Vec<StateTransition> {
+        self.history.iter().copied().collect()
     }
 
     /// Record a completed inference.
@@ -131,31 +384,127 @@ impl<'a> StatusMonitor<'a> {
         self.total_inferences
     }
 
+    /// Restarts attempted by the watchdog so far.
+    pub fn recovery_attempts(&self) -> u32 {
+        self.recovery_attempts
+    }
+
+    /// When the watchdog last restarted the NPU.
+    pub fn last_recovery_time(&self) -> Option<Instant> {
+        self.last_recovery_time
+    }
+
+    /// Poll the NPU and, if it died, restart it with `recover`, retrying
+    /// with backoff per the `RecoveryPolicy`.
+    ///
+    /// Returns the state after any recovery, or `RecoveryFailed` once every
+    /// attempt has failed (the caller should then shut the device down).
+    pub fn watchdog<E: std::fmt::Display>(
+        &mut self,
+        mut recover: impl FnMut() -> Result<(), E>,
+    ) -> Result<NpuState, RecoveryFailed> {
+        let state = self.poll();
+        if state != NpuState::Dead {
+            return Ok(state);
+        }
+
+        error!("☠️  NPU reported DEAD, starting recovery");
+        let policy = self.recovery_policy;
+        let mut last_error = String::from("no attempts allowed");
+        for attempt in 1..=policy.max_attempts {
+            thread::sleep(policy.backoff(attempt));
+            self.recovery_attempts += 1;
+            self.last_recovery_time = Some(Instant::now());
+
+            let outcome = match recover() {
+                Ok(()) => match self.poll() {
+                    NpuState::Dead => Err("still DEAD after restart".to_string()),
+                    state => Ok(state),
+                },
+                Err(e) => Err(e.to_string()),
+            };
+            if let Some(log) = self.event_log {
+                log.record(EventKind::WatchdogRecovery, outcome.is_err() as u32, attempt as u64);
+            }
+            match outcome {
+                Ok(state) => {
+                    info!("✅ NPU recovered on attempt {}/{} ({})", attempt, policy.max_attempts, state);
+                    return Ok(state);
+                }
+                Err(e) => {
+                    warn!("⚠️  Recovery attempt {}/{} failed: {}", attempt, policy.max_attempts, e);
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(RecoveryFailed { attempts: policy.max_attempts, last_error })
+    }
+
     /// Print a full diagnostic report.
     pub fn print_diagnostics(&self) {
-        let raw = self.mmio.read32(HOST_SS_FW_STATUS);
-        let fw_ver = self.mmio.read32(HOST_SS_FW_VERSION);
-        let buttress = self.mmio.read32(BUTTRESS_VPU_STATUS);
-        let int_sts = self.mmio.read32(BUTTRESS_GLOBAL_INT_STS);
-        let boot_count = self.mmio.read32(HOST_SS_BOOT_COUNT);
-        let gen_ctrl = self.mmio.read32(HOST_SS_GEN_CTRL);
+        let raw = self.mmio.read32(self.regs.fw_status);
+        let fw_ver = self.mmio.read32(self.regs.fw_version);
+        let buttress = self.mmio.read32(self.regs.vpu_status);
+        let int_sts = self.mmio.read32(self.regs.global_int_sts);
+        let boot_count = self.mmio.read32(self.regs.boot_count);
+        let gen_ctrl = self.mmio.read32(self.regs.gen_ctrl);
 
         println!("╔══════════════════════════════════════════╗");
         println!("║       Intel NPU Diagnostic Report        ║");
         println!("╠══════════════════════════════════════════╣");
         println!("║ State       : {:26} ║", format!("{}", self.last_state));
+        println!("║ Generation  : {:26} ║", self.regs.generation.name());
         println!("║ FW Status   : {:#010x} {:16} ║", raw, decode_fw_status(raw));
         println!("║ FW Version  : {:#010x}                    ║", fw_ver);
         println!("║ Buttress    : {:#010x}                    ║", buttress);
         println!("║ Interrupts  : {:#010x}                    ║", int_sts);
         println!("║ Boot Count  : {:10}                    ║", boot_count);
         println!("║ Gen Control : {:#010x}                    ║", gen_ctrl);
+        println!("║ Tiles       : {:26} ║", self.tile_config().to_string());
         println!("║ Uptime      : {:10.1}s                   ║", self.uptime().as_secs_f64());
         println!("║ Inferences  : {:10}                    ║", self.total_inferences);
-        println!("║ State Chgs  : {:10}                    ║", self.state_changes.len());
+        println!("║ State Chgs  : {:10}                    ║", self.state_changes);
+        println!("║ Recoveries  : {:10}                    ║", self.recovery_attempts);
+        match self.last_recovery_time {
+            Some(at) => println!("║ Last Recov. : {:10.1}s ago               ║", at.elapsed().as_secs_f64()),
+            None => println!("║ Last Recov. : {:>10}                    ║", "never"),
+        }
+        let power = crate::power::stats();
+        println!("║ D0i3 Entries: {:10}                    ║", power.suspends);
+        println!("║ Last Wake   : {:10}us                  ║", power.last_wake_latency.as_micros());
         println!("╚══════════════════════════════════════════╝");
     }
 
+    /// Snapshot everything for a machine-readable report of `device`,
+    /// including recent events.
+    pub fn diagnostics(&self, device: DeviceSummary, driver_version: &str, recent_events: &[NpuEvent]) -> Diagnostics {
+        let raw = self.mmio.read32(self.regs.fw_status);
+        Diagnostics {
+            driver_version: driver_version.to_string(),
+            device,
+            generation: self.regs.generation.to_string(),
+            state: self.last_state,
+            fw_status: raw,
+            fw_status_decoded: decode_fw_status(raw).to_string(),
+            fw_version: self.mmio.read32(self.regs.fw_version),
+            buttress_status: self.mmio.read32(self.regs.vpu_status),
+            interrupt_status: self.mmio.read32(self.regs.global_int_sts),
+            tile_fuse: self.mmio.read32(self.regs.tile_fuse),
+            tiles: self.tile_config(),
+            boot_count: self.mmio.read32(self.regs.boot_count),
+            uptime_secs: self.uptime().as_secs_f64(),
+            inferences: self.total_inferences,
+            state_changes: self.state_changes,
+            recovery_attempts: self.recovery_attempts,
+            last_recovery_secs_ago: self.last_recovery_time.map(|at| at.elapsed().as_secs_f64()),
+            history: self.history(),
+            dma: crate::dma::stats(),
+            power: crate::power::stats(),
+            events: recent_events.to_vec(),
+        }
+    }
+
     // ================================================================
     // Internal
     // ================================================================
@@ -170,3 +519,67 @@ impl<'a> StatusMonitor<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fwsim::FwSim;
+
+    #[test]
+    fn test_history_keeps_recent_transitions() {
+        let sim = FwSim::new();
+        let mut monitor = StatusMonitor::new(sim.mmio());
+        monitor.poll();
+        sim.crash();
+        monitor.poll();
+        monitor.poll();
+
+        let history = monitor.history();
+        let states: Vec<NpuState> = history.iter().map(|t| t.state).collect();
+        assert_eq!(states, vec![NpuState::PoweredOff, NpuState::Ready, NpuState::Dead]);
+        assert_eq!(history[2].raw, FW_STATUS_DEAD);
+        assert!(history.windows(2).all(|w| w[0].uptime_ms <= w[1].uptime_ms));
+
+        // Only the newest STATE_HISTORY_LEN are kept; the count goes on
+        for _ in 0..STATE_HISTORY_LEN {
+            sim.restart();
+            monitor.poll();
+            sim.crash();
+            monitor.poll();
+        }
+        assert_eq!(monitor.history().len(), STATE_HISTORY_LEN);
+        assert_eq!(monitor.history().last().unwrap().state, NpuState::Dead);
+        assert_eq!(monitor.state_change_count(), 3 + 2 * STATE_HISTORY_LEN as u64);
+    }
+
+    #[test]
+    fn test_diagnostics_json_round_trips() {
+        let mut sim = FwSim::new();
+        sim.set_tiles(0b01, NPU_TILES_MTL);
+        let mut monitor = StatusMonitor::new(sim.mmio());
+        monitor.poll();
+        monitor.record_inference();
+
+        let device = DeviceSummary {
+            device_id: PCI_DEVICE_MTL_NPU,
+            name: "Meteor Lake NPU".to_string(),
+            bdf: "00:0b.0".to_string(),
+            bar0_size: 16 * 1024 * 1024,
+        };
+        let event = NpuEvent { seq: 7, timestamp_ms: 1_700_000_000_000, kind: EventKind::BootAttempt, code: 0, value: 42 };
+        let report = monitor.diagnostics(device, "0.1.0", &[event]);
+        let parsed: Diagnostics = serde_json::from_str(&report.to_json()).unwrap();
+
+        // Rendered to 0.1 s and whole microseconds
+        assert!((parsed.uptime_secs - report.uptime_secs).abs() < 0.1);
+        let mut expected = report.clone();
+        expected.uptime_secs = parsed.uptime_secs;
+        expected.dma.scrub_time = Duration::from_micros(report.dma.scrub_time.as_micros() as u64);
+        assert_eq!(parsed, expected);
+
+        assert_eq!(parsed.state, NpuState::Ready);
+        assert_eq!(parsed.tiles.count, 1);
+        assert_ne!(parsed.tile_fuse, 0);
+        assert_eq!(parsed.history.len(), 2);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::status_indicator::EvaStatus;
+use serde::{Deserialize, Serialize};
+
+/// Accessibility settings (stored in the profile)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessibilityConfig {
+    /// Simple high-contrast layout, append-only plain-text output with tags
+    #[serde(default)]
+    pub enabled: bool,
+    /// Short tones on status changes (only played in accessibility mode)
+    #[serde(default = "default_earcons")]
+    pub earcons: bool,
+}
+
+fn default_earcons() -> bool {
+    true
+}
+
+impl Default for AccessibilityConfig {
+    fn default() -> Self {
+        Self { enabled: false, earcons: default_earcons() }
+    }
+}
+
+/// "accessibility mode on" / "desativar modo de acessibilidade"
+pub fn parse_toggle(text: &str) -> Option<bool> {
+    let text = text.to_lowercase();
+    if !text.contains("accessibility") && !text.contains("acessibilidade") {
+        return None;
+    }
+    let words: Vec<&str> = text.split(|c: char| !c.is_alphanumeric()).collect();
+    let has_word = |list: &[&str]| list.iter().any(|w| words.contains(w));
+    if has_word(&["off", "disable", "stop", "desativar", "desligar", "sair"]) {
+        Some(false)
+    } else if has_word(&["on", "enable", "start", "ativar", "ligar", "entrar"]) {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// What EVA says after switching accessibility mode
+pub fn toggle_reply(enabled: bool, portuguese: bool) -> &'static str {
+    match (enabled, portuguese) {
+        (true, false) => "Accessibility mode is on.",
+        (false, false) => "Accessibility mode is off.",
+        (true, true) => "Modo de acessibilidade ativado.",
+        (false, true) => "Modo de acessibilidade desativado.",
+    }
+}
+
+/// Kind of a conversation line, spelled out as a tag so nothing depends on
+/// color or emoji
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageKind {
+    User,
+    Eva,
+    System,
+    Error,
+}
+
+impl MessageKind {
+    pub fn tag(&self) -> &'static str {
+        match self {
+            MessageKind::User => "[YOU]",
+            MessageKind::Eva => "[EVA]",
+            MessageKind::System => "[INFO]",
+            MessageKind::Error => "[ERROR]",
+        }
+    }
+}
+
+/// Status name without icon
+pub fn status_name(status: EvaStatus) -> &'static str {
+    match status {
+        EvaStatus::Initializing => "Initializing",
+        EvaStatus::Idle => "Idle, say Hey EVA to start",
+        EvaStatus::Listening => "Listening",
+        EvaStatus::Processing => "Thinking",
+        EvaStatus::Speaking => "Speaking",
+        EvaStatus::Executing => "Running a command",
+        EvaStatus::Reconnecting => "Connection lost, reconnecting",
+        EvaStatus::Error => "Error",
+    }
+}
+
+/// Plain-text status line, written on every status change
+pub fn status_line(status: EvaStatus) -> String {
+    format!("[STATUS] {}", status_name(status))
+}
+
+/// Strip emoji/pictographs and ANSI escapes so a screen reader reads only text
+pub fn plain_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1B' {
+            // Skip "ESC [ ... letter"
+            for next in chars.by_ref() {
+                if next.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        let pictograph = matches!(c as u32, 0x2190..=0x21FF | 0x2300..=0x27BF | 0x2B00..=0x2BFF | 0xFE0F | 0x1F000..=0x1FAFF);
+        if !pictograph {
+            out.push(c);
+        }
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Non-visual status cue
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Earcon {
+    /// Rising two-note chime
+    Listening,
+    /// Single soft mid tone
+    Processing,
+    /// Falling two-note chime
+    Idle,
+    /// Low double buzz
+    Error,
+}
+
+impl Earcon {
+    pub fn for_status(status: EvaStatus) -> Option<Self> {
+        match status {
+            EvaStatus::Listening => Some(Earcon::Listening),
+            EvaStatus::Processing | EvaStatus::Executing => Some(Earcon::Processing),
+            EvaStatus::Idle => Some(Earcon::Idle),
+            EvaStatus::Error => Some(Earcon::Error),
+            EvaStatus::Initializing | EvaStatus::Speaking | EvaStatus::Reconnecting => None,
+        }
+    }
+
+    /// (frequency Hz, duration ms) notes
+    fn notes(&self) -> &'static [(f32, u32)] {
+        match self {
+            Earcon::Listening => &[(660.0, 80), (880.0, 80)],
+            Earcon::Processing => &[(523.0, 60)],
+            Earcon::Idle => &[(880.0, 80), (660.0, 80)],
+            Earcon::Error => &[(220.0, 120), (0.0, 60), (220.0, 120)],
+        }
+    }
+
+    /// Mono samples at `sample_rate`, with short fades to avoid clicks
+    pub fn samples(&self, sample_rate: u32) -> Vec<f32> {
+        let mut out = Vec::new();
+        for &(freq, ms) in self.notes() {
+            let len = (sample_rate * ms / 1000) as usize;
+            let fade = (sample_rate / 200) as usize; // 5 ms
+            for i in 0..len {
+                let envelope = (i.min(len - i) as f32 / fade as f32).min(1.0);
+                let phase = 2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32;
+                out.push(0.25 * envelope * phase.sin());
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toggle() {
+        assert_eq!(parse_toggle("EVA, turn on accessibility mode"), Some(true));
+        assert_eq!(parse_toggle("accessibility mode off"), Some(false));
+        assert_eq!(parse_toggle("ativar modo de acessibilidade"), Some(true));
+        assert_eq!(parse_toggle("desativar acessibilidade"), Some(false));
+        assert_eq!(parse_toggle("turn on the lights"), None);
+    }
+
+    #[test]
+    fn test_plain_text_drops_color_and_icons() {
+        assert_eq!(plain_text("\x1B[31m❌ Error\x1B[0m"), "Error");
+        assert_eq!(plain_text("⚠️  Stream error: reset"), "Stream error: reset");
+        assert_eq!(plain_text("Olá, você"), "Olá, você");
+    }
+
+    #[test]
+    fn test_earcons_are_distinct_and_bounded() {
+        let listening = Earcon::Listening.samples(16000);
+        let idle = Earcon::Idle.samples(16000);
+        assert_eq!(listening.len(), 16000 * 160 / 1000);
+        assert_ne!(listening, idle);
+        assert!(Earcon::Error.samples(16000).iter().all(|s| s.abs() <= 0.25));
+        assert_eq!(Earcon::for_status(EvaStatus::Speaking), None);
+    }
+}
@@ -1,13 +1,73 @@
-use std::time::Duration;
+//! Status animations
+//!
+//! `AnimationEngine` holds one animation per animated `EvaStatus`
+//! (listening, processing, speaking, executing) and picks the frame from
+//! the time spent in that status, so callers only ask for
+//! `current_symbol`. Frame sets can be swapped in the profile, either for
+//! a named spinner or for frames of one's own, and `reduced_motion` keeps
+//! every status on its plain icon.
+
+use crate::status_indicator::EvaStatus;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Named spinners a profile can pick, with their frame interval
+const PRESETS: &[(&str, &[&str], u64)] = &[
+    ("dots", &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"], 80),
+    ("line", &["-", "\\", "|", "/"], 130),
+    ("arc", &["◜", "◠", "◝", "◞", "◡", "◟"], 100),
+    ("circle", &["◐", "◓", "◑", "◒"], 200),
+    ("bar", &["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█", "▇", "▆", "▅", "▄", "▃", "▂"], 100),
+    ("pulse", &["·", "•", "●", "•"], 180),
+];
+
+/// Frames for one status in the profile: a preset name, or frames
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FrameSet {
+    /// "dots", "line", "arc", "circle", "bar" or "pulse", shown after the
+    /// status icon
+    Preset(String),
+    Custom {
+        frames: Vec<String>,
+        #[serde(default = "default_interval_ms")]
+        interval_ms: u64,
+    },
+}
+
+fn default_interval_ms() -> u64 {
+    120
+}
+
+/// Animation settings (stored in the profile)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnimationConfig {
+    /// Plain status icons, nothing moving
+    #[serde(default)]
+    pub reduced_motion: bool,
+    /// Frame sets replacing the built-in ones, keyed by status name
+    /// ("listening", "processing", "speaking", "executing")
+    #[serde(default)]
+    pub frames: BTreeMap<String, FrameSet>,
+}
 
 /// Animation frames for visual feedback
 pub struct Animation {
     frames: Vec<String>,
     current_frame: usize,
     frame_duration: Duration,
+    /// When the current frame came up, once `frame_at` started timing
+    shown_at: Option<Instant>,
 }
 
 impl Animation {
+    /// Frames shown `frame_duration` apart; `None` without frames or with
+    /// a zero duration
+    pub fn from_frames(frames: Vec<String>, frame_duration: Duration) -> Option<Self> {
+        (!frames.is_empty() && !frame_duration.is_zero()).then_some(Self { frames, current_frame: 0, frame_duration, shown_at: None })
+    }
+
     /// Listening animation
     pub fn listening() -> Self {
         Self {
@@ -22,6 +82,7 @@ impl Animation {
                 " 👂   ".to_string(),
             ],
             current_frame: 0,
+            shown_at: None,
             frame_duration: Duration::from_millis(150),
         }
     }
@@ -42,6 +103,7 @@ impl Animation {
                 "🧠⠏".to_string(),
             ],
             current_frame: 0,
+            shown_at: None,
             frame_duration: Duration::from_millis(80),
         }
     }
@@ -66,6 +128,7 @@ impl Animation {
                 "🗣️ ▂".to_string(),
             ],
             current_frame: 0,
+            shown_at: None,
             frame_duration: Duration::from_millis(100),
         }
     }
@@ -80,6 +143,7 @@ impl Animation {
                 "⚙️ ◒".to_string(),
             ],
             current_frame: 0,
+            shown_at: None,
             frame_duration: Duration::from_millis(200),
         }
     }
@@ -101,9 +165,105 @@ impl Animation {
         self.frame_duration
     }
 
+    /// The frame due at `now`: the first call shows the current frame and
+    /// starts the clock, later ones advance a frame per `frame_duration`
+    pub fn frame_at(&mut self, now: Instant) -> &str {
+        let shown_at = *self.shown_at.get_or_insert(now);
+        let steps = (now.saturating_duration_since(shown_at).as_nanos() / self.frame_duration().as_nanos()) as u64;
+        if steps > 0 {
+            for _ in 0..steps % self.frames.len() as u64 {
+                self.next_frame();
+            }
+            self.shown_at = Some(shown_at + self.frame_duration * steps.min(u32::MAX as u64) as u32);
+        }
+        self.current_frame()
+    }
+
     /// Reset to first frame
     pub fn reset(&mut self) {
         self.current_frame = 0;
+        self.shown_at = None;
+    }
+}
+
+/// Builds one of the built-in animations
+type BuiltIn = fn() -> Animation;
+
+/// The animated statuses, their profile names and built-in animations
+const ANIMATED: [(EvaStatus, &str, BuiltIn); 4] = [
+    (EvaStatus::Listening, "listening", Animation::listening),
+    (EvaStatus::Processing, "processing", Animation::processing),
+    (EvaStatus::Speaking, "speaking", Animation::speaking),
+    (EvaStatus::Executing, "executing", Animation::executing),
+];
+
+/// Icon put in front of a preset spinner
+fn status_icon(status: EvaStatus) -> &'static str {
+    match status {
+        EvaStatus::Listening => "👂",
+        EvaStatus::Processing => "🧠",
+        EvaStatus::Speaking => "🗣️ ",
+        _ => "⚙️ ",
+    }
+}
+
+/// The animation for every animated status, advanced by time
+pub struct AnimationEngine {
+    animations: Vec<(EvaStatus, Animation)>,
+    reduced_motion: bool,
+    /// Status of the last frame handed out; a new one starts from frame 0
+    shown: Option<EvaStatus>,
+}
+
+impl AnimationEngine {
+    /// Built-in frames, with `config` applied; an unknown status or preset
+    /// is an error naming it
+    pub fn new(config: &AnimationConfig) -> Result<Self, String> {
+        if let Some(unknown) = config.frames.keys().find(|name| !ANIMATED.iter().any(|(_, n, _)| n == name)) {
+            return Err(format!("no animation for status '{}' (listening, processing, speaking or executing)", unknown));
+        }
+        let mut animations = Vec::new();
+        for (status, name, built_in) in ANIMATED {
+            let animation = match config.frames.get(name) {
+                None => built_in(),
+                Some(FrameSet::Preset(preset)) => {
+                    let (_, frames, ms) = PRESETS
+                        .iter()
+                        .find(|(n, _, _)| n == preset)
+                        .ok_or_else(|| format!("unknown spinner '{}' for {}", preset, name))?;
+                    let frames = frames.iter().map(|f| format!("{}{}", status_icon(status), f)).collect();
+                    Animation::from_frames(frames, Duration::from_millis(*ms)).expect("presets have frames")
+                }
+                Some(FrameSet::Custom { frames, interval_ms }) => Animation::from_frames(frames.clone(), Duration::from_millis(*interval_ms))
+                    .ok_or_else(|| format!("the {} animation needs frames and an interval above 0", name))?,
+            };
+            animations.push((status, animation));
+        }
+        Ok(Self { animations, reduced_motion: config.reduced_motion, shown: None })
+    }
+
+    /// The frame to show for `status` now, `None` for statuses that keep
+    /// their plain icon (all of them with reduced motion)
+    pub fn current_symbol(&mut self, status: EvaStatus) -> Option<String> {
+        self.symbol_at(status, Instant::now())
+    }
+
+    pub fn symbol_at(&mut self, status: EvaStatus, now: Instant) -> Option<String> {
+        if self.reduced_motion {
+            return None;
+        }
+        let (_, animation) = self.animations.iter_mut().find(|(s, _)| *s == status)?;
+        if self.shown != Some(status) {
+            self.shown = Some(status);
+            animation.reset();
+        }
+        Some(animation.frame_at(now).to_string())
+    }
+}
+
+impl Default for AnimationEngine {
+    fn default() -> Self {
+        Self::new(&AnimationConfig::default()).expect("built-in animations")
     }
 }
 
@@ -139,6 +299,68 @@ mod tests {
         assert_eq!(anim.frame_duration(), Duration::from_millis(150));
     }
 
+    #[test]
+    fn test_frames_advance_with_time() {
+        let mut engine = AnimationEngine::default();
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        // Listening: 8 frames, 150ms apart
+        let first = engine.symbol_at(EvaStatus::Listening, at(0)).unwrap();
+        assert_eq!(first, "👂    ");
+        assert_eq!(engine.symbol_at(EvaStatus::Listening, at(149)).unwrap(), first);
+        assert_eq!(engine.symbol_at(EvaStatus::Listening, at(150)).unwrap(), " 👂   ");
+        // Several frames late: skips ahead rather than lagging behind
+        assert_eq!(engine.symbol_at(EvaStatus::Listening, at(450)).unwrap(), "   👂 ");
+        // A full cycle later: the same frame
+        assert_eq!(engine.symbol_at(EvaStatus::Listening, at(450 + 8 * 150)).unwrap(), "   👂 ");
+
+        assert_eq!(engine.symbol_at(EvaStatus::Idle, at(2000)), None);
+    }
+
+    #[test]
+    fn test_status_change_restarts_the_animation() {
+        let mut engine = AnimationEngine::default();
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        engine.symbol_at(EvaStatus::Processing, at(0));
+        assert_eq!(engine.symbol_at(EvaStatus::Processing, at(240)).unwrap(), "🧠⠸");
+        assert_eq!(engine.symbol_at(EvaStatus::Speaking, at(250)).unwrap(), "🗣️ ▁");
+        assert_eq!(engine.symbol_at(EvaStatus::Speaking, at(350)).unwrap(), "🗣️ ▂");
+        // Back to processing: from the first frame, not where it was left
+        assert_eq!(engine.symbol_at(EvaStatus::Processing, at(400)).unwrap(), "🧠⠋");
+        assert_eq!(engine.symbol_at(EvaStatus::Processing, at(480)).unwrap(), "🧠⠙");
+    }
+
+    #[test]
+    fn test_profile_frame_sets_and_reduced_motion() {
+        let config: AnimationConfig = serde_json::from_str(
+            r#"{"frames": {"processing": "line", "speaking": {"frames": ["a", "b"], "interval_ms": 50}}}"#,
+        )
+        .unwrap();
+        let mut engine = AnimationEngine::new(&config).unwrap();
+        let start = Instant::now();
+        assert_eq!(engine.symbol_at(EvaStatus::Processing, start).unwrap(), "🧠-");
+        assert_eq!(engine.symbol_at(EvaStatus::Processing, start + Duration::from_millis(130)).unwrap(), "🧠\\");
+        assert_eq!(engine.symbol_at(EvaStatus::Speaking, start).unwrap(), "a");
+        assert_eq!(engine.symbol_at(EvaStatus::Speaking, start + Duration::from_millis(50)).unwrap(), "b");
+        // Untouched statuses keep the built-in frames
+        assert_eq!(engine.symbol_at(EvaStatus::Executing, start).unwrap(), "⚙️ ◐");
+
+        let still = AnimationConfig { reduced_motion: true, ..config };
+        let mut engine = AnimationEngine::new(&still).unwrap();
+        assert_eq!(engine.symbol_at(EvaStatus::Listening, start), None);
+        assert_eq!(engine.symbol_at(EvaStatus::Speaking, start + Duration::from_secs(1)), None);
+
+        let typo: AnimationConfig = serde_json::from_str(r#"{"frames": {"processing": "spiral"}}"#).unwrap();
+        assert!(AnimationEngine::new(&typo).err().unwrap_or_default().contains("spiral"));
+        let unknown: AnimationConfig = serde_json::from_str(r#"{"frames": {"sleeping": "dots"}}"#).unwrap();
+        assert!(AnimationEngine::new(&unknown).err().unwrap_or_default().contains("sleeping"));
+        let empty: AnimationConfig = serde_json::from_str(r#"{"frames": {"speaking": {"frames": []}}}"#).unwrap();
+        assert!(AnimationEngine::new(&empty).is_err());
+    }
+
     #[test]
     fn test_reset() {
         let mut anim = Animation::processing();
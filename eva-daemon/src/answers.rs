@@ -0,0 +1,647 @@
+//! Answers that don't need the model
+//!
+//! Ahead of the Gemini call a question goes past a registry of local
+//! skills (time and date, arithmetic, unit conversion, system info) and a
+//! short-lived cache of what Gemini said to the very same question. Only
+//! what neither can answer goes out over the network, so "what time is it"
+//! works offline and costs no quota.
+
+use crate::command_parser::{CommandIntent, SystemOperation};
+use crate::offline::TurnRoute;
+use chrono::{DateTime, Datelike, NaiveDateTime, TimeZone, Utc};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Questions Gemini answered that are kept
+const CACHE_CAPACITY: usize = 32;
+
+/// How long a cached answer is trusted
+const CACHE_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Words that make a question depend on the conversation, the user or the
+/// moment, so the same words may need another answer next time
+const CONTEXT_WORDS: &[&str] = &[
+    "it", "that", "this", "he", "she", "they", "him", "her", "them", "i", "me", "my", "you", "your", "now", "today",
+    "tomorrow", "yesterday", "latest", "ele", "ela", "eles", "isso", "disso", "nisso", "eu", "meu", "minha", "você",
+    "voce", "agora", "hoje", "amanhã", "amanha", "ontem",
+];
+
+/// How a question is answered without the model
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer {
+    /// Worked out here: the time, a sum, a conversion
+    Local(String),
+    /// Read from this machine by the command executor
+    System(CommandIntent),
+    /// What Gemini said to the same question a moment ago
+    Cached(String),
+}
+
+/// What a skill gets to look at
+struct Question<'a> {
+    /// Lowercase words, punctuation dropped (see `normalize`)
+    text: &'a str,
+    route: &'a TurnRoute,
+    /// Wall-clock time in the user's time zone
+    now: NaiveDateTime,
+    portuguese: bool,
+}
+
+/// A kind of question EVA answers on its own
+struct Skill {
+    /// Phrases that mark the question; empty when the skill parses the
+    /// whole text instead
+    patterns: &'static [&'static str],
+    answer: fn(&Question) -> Option<Answer>,
+}
+
+/// Tried in order; the first skill with an answer serves the turn
+const SKILLS: &[Skill] = &[
+    Skill {
+        patterns: &[
+            "what time is it", "whats the time", "what is the time", "tell me the time", "current time", "que horas são",
+            "que horas sao", "que horas é", "que horas e",
+        ],
+        answer: time_of_day,
+    },
+    Skill {
+        patterns: &[
+            "what day is it", "what day is today", "whats the date", "what is the date", "whats todays date",
+            "what is todays date", "que dia é hoje", "que dia e hoje", "qual a data de hoje", "qual é a data", "qual e a data",
+        ],
+        answer: date,
+    },
+    Skill {
+        patterns: &[
+            "uptime", "how long has the system been up", "how long has the system been running", "how long have you been running",
+            "há quanto tempo o sistema está ligado", "ha quanto tempo o sistema esta ligado",
+        ],
+        answer: |_| Some(Answer::System(CommandIntent::System(SystemOperation::Uptime))),
+    },
+    // "how much memory is free": already a command, read locally rather
+    // than through a tool call
+    Skill {
+        patterns: &[],
+        answer: |q| match q.route {
+            TurnRoute::Command(intent @ CommandIntent::System(_)) => Some(Answer::System(intent.clone())),
+            _ => None,
+        },
+    },
+    Skill { patterns: &[], answer: conversion },
+    Skill { patterns: &[], answer: arithmetic },
+];
+
+/// Decides whether a turn is answered here, from the cache or by Gemini
+pub struct AnswerRouter {
+    cache: AnswerCache,
+}
+
+impl AnswerRouter {
+    pub fn new() -> Self {
+        Self { cache: AnswerCache::new(CACHE_CAPACITY, CACHE_TTL) }
+    }
+
+    /// The answer to `text` if it doesn't need the model, `None` if it
+    /// does. `route` is what the command parser made of it; only turns
+    /// headed for the model or asking for system info are looked at.
+    pub fn answer<Tz: TimeZone>(
+        &mut self,
+        text: &str,
+        route: &TurnRoute,
+        now: DateTime<Utc>,
+        tz: &Tz,
+        at: Instant,
+        portuguese: bool,
+    ) -> Option<Answer> {
+        if !matches!(route, TurnRoute::Model | TurnRoute::Command(CommandIntent::System(_))) {
+            return None;
+        }
+        let text = normalize(text);
+        let question = Question { text: &text, route, now: now.with_timezone(tz).naive_local(), portuguese };
+        SKILLS
+            .iter()
+            .filter(|skill| skill.patterns.is_empty() || skill.patterns.iter().any(|p| contains_phrase(&text, p)))
+            .find_map(|skill| (skill.answer)(&question))
+            .or_else(|| self.cache.get(&text, at).map(Answer::Cached))
+    }
+
+    /// Keep what Gemini answered to `question`, unless the answer may
+    /// change with the conversation
+    pub fn remember(&mut self, question: &str, answer: &str, at: Instant) {
+        let question = normalize(question);
+        if cacheable(&question) && !answer.trim().is_empty() {
+            self.cache.put(question, answer.to_string(), at);
+        }
+    }
+}
+
+impl Default for AnswerRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Recent question → answer pairs, most recently used first
+struct AnswerCache {
+    entries: VecDeque<(String, String, Instant)>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl AnswerCache {
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        Self { entries: VecDeque::new(), capacity, ttl }
+    }
+
+    fn get(&mut self, question: &str, at: Instant) -> Option<String> {
+        let ttl = self.ttl;
+        self.entries.retain(|(_, _, stored)| at.saturating_duration_since(*stored) < ttl);
+        let index = self.entries.iter().position(|(q, _, _)| q == question)?;
+        let entry = self.entries.remove(index)?;
+        let answer = entry.1.clone();
+        self.entries.push_front(entry);
+        Some(answer)
+    }
+
+    fn put(&mut self, question: String, answer: String, at: Instant) {
+        self.entries.retain(|(q, _, _)| *q != question);
+        self.entries.push_front((question, answer, at));
+        self.entries.truncate(self.capacity);
+    }
+}
+
+/// Lowercase words separated by single spaces; apostrophes go ("what's" is
+/// "whats"), other punctuation splits words, arithmetic signs are kept
+fn normalize(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.to_lowercase().chars() {
+        match c {
+            '\'' | '’' => {}
+            // Decimal point, kept only between digits
+            '.' if out.ends_with(|p: char| p.is_ascii_digit()) => out.push('.'),
+            '+' | '-' | '*' | '/' | '×' | '÷' | '%' | '(' | ')' => {
+                out.push(' ');
+                out.push(c);
+                out.push(' ');
+            }
+            c if c.is_alphanumeric() => out.push(c),
+            _ => out.push(' '),
+        }
+    }
+    // "5." at the end of a sentence is just 5
+    let words: Vec<&str> = out.split_whitespace().map(|w| w.trim_end_matches('.')).collect();
+    words.join(" ")
+}
+
+/// Whether `phrase` appears in `text` as whole words
+fn contains_phrase(text: &str, phrase: &str) -> bool {
+    format!(" {} ", text).contains(&format!(" {} ", phrase))
+}
+
+/// A self-contained question: asked with a question word and not
+/// leaning on anything said before
+fn cacheable(question: &str) -> bool {
+    const QUESTION_WORDS: &[&str] = &[
+        "what", "whats", "who", "whos", "where", "when", "why", "how", "which", "o que", "qual", "quais", "quem", "onde",
+        "quando", "por que", "como", "quanto", "quantos", "quantas",
+    ];
+    QUESTION_WORDS.iter().any(|w| question.starts_with(&format!("{} ", w)))
+        && !question.split(' ').any(|word| CONTEXT_WORDS.contains(&word))
+}
+
+fn time_of_day(q: &Question) -> Option<Answer> {
+    Some(Answer::Local(if q.portuguese {
+        format!("Agora são {}.", q.now.format("%-H:%M"))
+    } else {
+        format!("It's {}.", q.now.format("%-I:%M %p"))
+    }))
+}
+
+fn date(q: &Question) -> Option<Answer> {
+    const DIAS: [&str; 7] = ["segunda-feira", "terça-feira", "quarta-feira", "quinta-feira", "sexta-feira", "sábado", "domingo"];
+    const MESES: [&str; 12] =
+        ["janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto", "setembro", "outubro", "novembro", "dezembro"];
+    let d = q.now.date();
+    Some(Answer::Local(if q.portuguese {
+        format!(
+            "Hoje é {}, {} de {} de {}.",
+            DIAS[d.weekday().num_days_from_monday() as usize],
+            d.day(),
+            MESES[d.month0() as usize],
+            d.year()
+        )
+    } else {
+        format!("Today is {}.", d.format("%A, %B %-d, %Y"))
+    }))
+}
+
+/// A number written with digits or as words ("twenty one", "vinte e um")
+/// starting at `words[0]`, and how many words it took
+fn number(words: &[&str]) -> Option<(f64, usize)> {
+    if let Some(value) = words.first().and_then(|w| w.parse::<f64>().ok()) {
+        return Some((value, 1));
+    }
+    let mut total = 0.0;
+    let mut current = 0.0;
+    let mut used = 0;
+    let mut seen = false;
+    while let Some(&word) = words.get(used) {
+        match number_word(word) {
+            Some(NumberWord::Value(v)) => current += v,
+            Some(NumberWord::Times(v)) => {
+                current = current.max(1.0) * v;
+                if v >= 1000.0 {
+                    total += current;
+                    current = 0.0;
+                }
+            }
+            // "vinte e um", "one hundred and five": only between number words
+            None if seen && matches!(word, "e" | "and") && words.get(used + 1).and_then(|w| number_word(w)).is_some() => {}
+            None => break,
+        }
+        seen = true;
+        used += 1;
+    }
+    seen.then_some((total + current, used))
+}
+
+enum NumberWord {
+    Value(f64),
+    /// "hundred", "thousand": multiplies what came before
+    Times(f64),
+}
+
+fn number_word(word: &str) -> Option<NumberWord> {
+    const VALUES: &[(&[&str], f64)] = &[
+        (&["zero"], 0.0),
+        (&["one", "um", "uma"], 1.0),
+        (&["two", "dois", "duas"], 2.0),
+        (&["three", "três", "tres"], 3.0),
+        (&["four", "quatro"], 4.0),
+        (&["five", "cinco"], 5.0),
+        (&["six", "seis"], 6.0),
+        (&["seven", "sete"], 7.0),
+        (&["eight", "oito"], 8.0),
+        (&["nine", "nove"], 9.0),
+        (&["ten", "dez"], 10.0),
+        (&["eleven", "onze"], 11.0),
+        (&["twelve", "doze"], 12.0),
+        (&["thirteen", "treze"], 13.0),
+        (&["fourteen", "quatorze", "catorze"], 14.0),
+        (&["fifteen", "quinze"], 15.0),
+        (&["sixteen", "dezesseis"], 16.0),
+        (&["seventeen", "dezessete"], 17.0),
+        (&["eighteen", "dezoito"], 18.0),
+        (&["nineteen", "dezenove"], 19.0),
+        (&["twenty", "vinte"], 20.0),
+        (&["thirty", "trinta"], 30.0),
+        (&["forty", "quarenta"], 40.0),
+        (&["fifty", "cinquenta"], 50.0),
+        (&["sixty", "sessenta"], 60.0),
+        (&["seventy", "setenta"], 70.0),
+        (&["eighty", "oitenta"], 80.0),
+        (&["ninety", "noventa"], 90.0),
+        (&["cem", "cento"], 100.0),
+    ];
+    match word {
+        "hundred" => Some(NumberWord::Times(100.0)),
+        "thousand" | "mil" => Some(NumberWord::Times(1000.0)),
+        _ => VALUES.iter().find(|(names, _)| names.contains(&word)).map(|&(_, v)| NumberWord::Value(v)),
+    }
+}
+
+/// "what is 12 times 7", "quanto é 15% de 80", "calculate (2 + 3) / 4"
+fn arithmetic(q: &Question) -> Option<Answer> {
+    const PREFIXES: &[&str] =
+        &["what is", "whats", "how much is", "calculate", "compute", "quanto é", "quanto e", "quanto dá", "quanto da", "calcule", "calcula"];
+    let rest = PREFIXES.iter().find_map(|p| q.text.strip_prefix(p)?.strip_prefix(' '))?;
+    let words: Vec<&str> = rest.split(' ').collect();
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        if let Some((value, used)) = number(&words[i..]) {
+            tokens.push(Token::Number(value));
+            i += used;
+            continue;
+        }
+        // Longest operator phrase first: "divided by" before "by"
+        let (token, used) = match words[i..] {
+            ["multiplied" | "multiplicado", "by" | "por", ..] | ["dividido" | "divided", "por" | "by", ..] => {
+                (if words[i] == "multiplied" || words[i] == "multiplicado" { Token::Times } else { Token::Over }, 2)
+            }
+            ["percent" | "porcento", "of" | "de", ..] | ["%", "of" | "de", ..] => (Token::PercentOf, 2),
+            ["por", "cento", "de", ..] => (Token::PercentOf, 3),
+            ["+" | "plus" | "mais", ..] => (Token::Plus, 1),
+            ["-" | "minus" | "menos", ..] => (Token::Minus, 1),
+            ["*" | "x" | "×" | "times" | "vezes", ..] => (Token::Times, 1),
+            ["/" | "÷" | "over", ..] => (Token::Over, 1),
+            ["(", ..] => (Token::Open, 1),
+            [")", ..] => (Token::Close, 1),
+            _ => return None,
+        };
+        tokens.push(token);
+        i += used;
+    }
+    if !tokens.iter().any(|t| matches!(t, Token::Plus | Token::Minus | Token::Times | Token::Over | Token::PercentOf)) {
+        return None;
+    }
+
+    let mut parser = Expression { tokens: &tokens, at: 0 };
+    let value = parser.sum()?;
+    if parser.at != tokens.len() {
+        return None;
+    }
+    Some(Answer::Local(match value {
+        Ok(value) if q.portuguese => format!("Dá {}.", format_number(value)),
+        Ok(value) => format!("That's {}.", format_number(value)),
+        Err(()) if q.portuguese => "Não dá para dividir por zero.".to_string(),
+        Err(()) => "You can't divide by zero.".to_string(),
+    }))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Number(f64),
+    Plus,
+    Minus,
+    Times,
+    Over,
+    /// "15% of 80"
+    PercentOf,
+    Open,
+    Close,
+}
+
+/// Recursive descent over the tokens: `None` when they don't form an
+/// expression, `Some(Err(()))` on a division by zero
+struct Expression<'a> {
+    tokens: &'a [Token],
+    at: usize,
+}
+
+impl Expression<'_> {
+    fn next_if(&mut self, token: Token) -> bool {
+        let matched = self.tokens.get(self.at) == Some(&token);
+        self.at += matched as usize;
+        matched
+    }
+
+    fn sum(&mut self) -> Option<Result<f64, ()>> {
+        let mut value = self.product()?;
+        loop {
+            if self.next_if(Token::Plus) {
+                let rhs = self.product()?;
+                value = value.and_then(|v| rhs.map(|r| v + r));
+            } else if self.next_if(Token::Minus) {
+                let rhs = self.product()?;
+                value = value.and_then(|v| rhs.map(|r| v - r));
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Option<Result<f64, ()>> {
+        let mut value = self.factor()?;
+        loop {
+            if self.next_if(Token::Times) {
+                let rhs = self.factor()?;
+                value = value.and_then(|v| rhs.map(|r| v * r));
+            } else if self.next_if(Token::PercentOf) {
+                let rhs = self.factor()?;
+                value = value.and_then(|v| rhs.map(|r| v / 100.0 * r));
+            } else if self.next_if(Token::Over) {
+                let rhs = self.factor()?;
+                value = value.and_then(|v| rhs.and_then(|r| if r == 0.0 { Err(()) } else { Ok(v / r) }));
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Option<Result<f64, ()>> {
+        if self.next_if(Token::Minus) {
+            return self.factor().map(|v| v.map(|v| -v));
+        }
+        if self.next_if(Token::Open) {
+            let value = self.sum()?;
+            return self.next_if(Token::Close).then_some(value);
+        }
+        match self.tokens.get(self.at) {
+            Some(&Token::Number(value)) => {
+                self.at += 1;
+                Some(Ok(value))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Up to three decimals, trailing zeros dropped
+fn format_number(value: f64) -> String {
+    let text = format!("{:.3}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" { "0".to_string() } else { text.to_string() }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Dimension {
+    Length,
+    Mass,
+    Volume,
+    Temperature,
+}
+
+/// Names, dimension and size in the dimension's base unit (meters, kilograms,
+/// liters); temperatures use their own scale instead
+type Unit = (&'static [&'static str], Dimension, f64);
+
+const UNITS: &[Unit] = &[
+    (&["meter", "meters", "metre", "metres", "m", "metro", "metros"], Dimension::Length, 1.0),
+    (&["kilometer", "kilometers", "kilometre", "kilometres", "km", "quilômetro", "quilômetros", "quilometro", "quilometros"], Dimension::Length, 1000.0),
+    (&["centimeter", "centimeters", "centimetre", "centimetres", "cm", "centímetro", "centímetros", "centimetro", "centimetros"], Dimension::Length, 0.01),
+    (&["millimeter", "millimeters", "millimetre", "millimetres", "mm", "milímetro", "milímetros", "milimetro", "milimetros"], Dimension::Length, 0.001),
+    (&["mile", "miles", "milha", "milhas"], Dimension::Length, 1609.344),
+    (&["foot", "feet", "ft", "pé", "pés", "pe", "pes"], Dimension::Length, 0.3048),
+    (&["inch", "inches", "in", "polegada", "polegadas"], Dimension::Length, 0.0254),
+    (&["yard", "yards", "yd", "jarda", "jardas"], Dimension::Length, 0.9144),
+    (&["kilogram", "kilograms", "kg", "kilo", "kilos", "quilo", "quilos", "quilograma", "quilogramas"], Dimension::Mass, 1.0),
+    (&["gram", "grams", "g", "grama", "gramas"], Dimension::Mass, 0.001),
+    (&["pound", "pounds", "lb", "lbs", "libra", "libras"], Dimension::Mass, 0.45359237),
+    (&["ounce", "ounces", "oz", "onça", "onças"], Dimension::Mass, 0.028349523125),
+    (&["liter", "liters", "litre", "litres", "l", "litro", "litros"], Dimension::Volume, 1.0),
+    (&["milliliter", "milliliters", "millilitre", "millilitres", "ml", "mililitro", "mililitros"], Dimension::Volume, 0.001),
+    (&["gallon", "gallons", "galão", "galões", "galao", "galoes"], Dimension::Volume, 3.785411784),
+    (&["cup", "cups", "xícara", "xícaras", "xicara", "xicaras"], Dimension::Volume, 0.2365882365),
+    (&["celsius", "c", "centigrade"], Dimension::Temperature, 0.0),
+    (&["fahrenheit", "f"], Dimension::Temperature, 1.0),
+    (&["kelvin", "k"], Dimension::Temperature, 2.0),
+];
+
+/// The unit named at `words[0]`, skipping "degrees"; its table entry and
+/// how many words it took
+fn unit(words: &[&str]) -> Option<(&'static Unit, usize)> {
+    let skip = usize::from(matches!(words.first(), Some(&("degrees" | "degree" | "graus" | "grau"))));
+    let name = *words.get(skip)?;
+    UNITS.iter().find(|(names, _, _)| names.contains(&name)).map(|u| (u, skip + 1))
+}
+
+fn to_celsius(value: f64, scale: f64) -> f64 {
+    match scale as u8 {
+        1 => (value - 32.0) * 5.0 / 9.0,
+        2 => value - 273.15,
+        _ => value,
+    }
+}
+
+fn from_celsius(value: f64, scale: f64) -> f64 {
+    match scale as u8 {
+        1 => value * 9.0 / 5.0 + 32.0,
+        2 => value + 273.15,
+        _ => value,
+    }
+}
+
+/// "convert 5 km to miles", "how many feet in 3 meters", "20 graus celsius em fahrenheit"
+fn conversion(q: &Question) -> Option<Answer> {
+    let words: Vec<&str> = q.text.split(' ').collect();
+    // "how many feet in 3 meters": the target comes first
+    let asked = words.windows(2).position(|w| w == ["how", "many"]).map(|at| at + 2).or_else(|| {
+        words.iter().position(|w| matches!(*w, "quantos" | "quantas")).map(|at| at + 1)
+    });
+
+    let (start, (value, used)) = (0..words.len()).find_map(|i| number(&words[i..]).map(|n| (i, n)))?;
+    let (from, from_used) = unit(&words[start + used..])?;
+    let from_words = words[start + used..start + used + from_used].join(" ");
+    let (to, to_words) = match asked {
+        Some(at) if at < start => {
+            let (to, to_used) = unit(&words[at..])?;
+            (to, words[at..at + to_used].join(" "))
+        }
+        _ => {
+            let after = start + used + from_used;
+            if !matches!(words.get(after), Some(&("to" | "in" | "into" | "para" | "em"))) {
+                return None;
+            }
+            let (to, to_used) = unit(&words[after + 1..])?;
+            (to, words[after + 1..after + 1 + to_used].join(" "))
+        }
+    };
+    if from.1 != to.1 {
+        return None;
+    }
+    let converted = match from.1 {
+        Dimension::Temperature => from_celsius(to_celsius(value, from.2), to.2),
+        _ => value * from.2 / to.2,
+    };
+    let verb = if q.portuguese { "são" } else { "is" };
+    Some(Answer::Local(format!("{} {} {} {} {}.", format_number(value), from_words, verb, format_number(converted), to_words)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_parser::CommandParser;
+    use crate::gemini::{GeminiClient, GeminiConfig};
+    use crate::offline;
+    use crate::websocket::{Connector, Transport, WsFuture};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn ask(router: &mut AnswerRouter, text: &str, portuguese: bool) -> Option<Answer> {
+        let route = offline::route(&CommandParser::new(), text);
+        let now = Utc.with_ymd_and_hms(2026, 1, 5, 14, 7, 0).unwrap();
+        router.answer(text, &route, now, &Utc, Instant::now(), portuguese)
+    }
+
+    fn local(text: &str) -> String {
+        match ask(&mut AnswerRouter::new(), text, false) {
+            Some(Answer::Local(answer)) => answer,
+            other => panic!("{:?}: {:?}", text, other),
+        }
+    }
+
+    /// Counts the connections it is asked for; any connection fails
+    struct CountingConnector(Rc<Cell<usize>>);
+
+    impl Connector for CountingConnector {
+        fn connect<'a>(&'a self, _url: &'a str) -> WsFuture<'a, Box<dyn Transport>> {
+            self.0.set(self.0.get() + 1);
+            Box::pin(async { Err("offline".into()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_what_time_is_it_never_opens_a_websocket() {
+        let connects = Rc::new(Cell::new(0));
+        let mut router = AnswerRouter::new();
+        // What the main loop does with a typed line
+        for text in ["What time is it?", "what's the time", "Que horas são?", "tell me a story"] {
+            if ask(&mut router, text, false).is_none() {
+                let config = GeminiConfig { api_key: "test-key".to_string(), ..GeminiConfig::default() };
+                let _ = GeminiClient::connect_with(config, Box::new(CountingConnector(connects.clone()))).await;
+            }
+        }
+        assert_eq!(connects.get(), 1, "only the story needs Gemini");
+
+        assert_eq!(local("what time is it"), "It's 2:07 PM.");
+        assert_eq!(ask(&mut router, "que horas são", true), Some(Answer::Local("Agora são 14:07.".to_string())));
+        assert_eq!(local("what's the date?"), "Today is Monday, January 5, 2026.");
+        assert_eq!(
+            ask(&mut router, "que dia é hoje?", true),
+            Some(Answer::Local("Hoje é segunda-feira, 5 de janeiro de 2026.".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_local_skills() {
+        assert_eq!(local("what is 12 times 7"), "That's 84.");
+        assert_eq!(local("What's 2 + 3 * 4?"), "That's 14.");
+        assert_eq!(local("calculate (2 + 3) / 4"), "That's 1.25.");
+        assert_eq!(local("how much is fifteen percent of eighty"), "That's 12.");
+        assert_eq!(local("what is twenty one divided by zero"), "You can't divide by zero.");
+        assert_eq!(local("convert 5 km to miles"), "5 km is 3.107 miles.");
+        assert_eq!(local("how many feet in 3 meters"), "3 meters is 9.843 feet.");
+        assert_eq!(local("100 degrees fahrenheit in celsius"), "100 degrees fahrenheit is 37.778 celsius.");
+
+        let mut router = AnswerRouter::new();
+        assert_eq!(ask(&mut router, "quanto é vinte e um mais um", true), Some(Answer::Local("Dá 22.".to_string())));
+        assert_eq!(
+            ask(&mut router, "how much memory is free", false),
+            Some(Answer::System(CommandIntent::System(SystemOperation::MemoryInfo)))
+        );
+        assert_eq!(ask(&mut router, "what's the uptime", false), Some(Answer::System(CommandIntent::System(SystemOperation::Uptime))));
+
+        // Not for the skills: the model, or another command
+        assert_eq!(ask(&mut router, "what is the capital of France", false), None);
+        assert_eq!(ask(&mut router, "convert 5 km to pounds", false), None);
+        assert_eq!(ask(&mut router, "set a timer for 5 minutes", false), None);
+    }
+
+    #[test]
+    fn test_cache_serves_repeated_questions_until_they_expire() {
+        let mut router = AnswerRouter::new();
+        let start = Instant::now();
+        let route = TurnRoute::Model;
+        let now = Utc::now();
+        let ask = |router: &mut AnswerRouter, text: &str, at: Instant| router.answer(text, &route, now, &Utc, at, false);
+
+        router.remember("What is the capital of France?", "Paris.", start);
+        // Leans on the conversation: never kept
+        router.remember("what did she say", "Hello.", start);
+
+        assert_eq!(ask(&mut router, "what is the capital of france", start), Some(Answer::Cached("Paris.".to_string())));
+        assert_eq!(ask(&mut router, "what did she say", start), None);
+        assert_eq!(ask(&mut router, "what is the capital of france", start + CACHE_TTL), None);
+
+        // Least recently used goes first
+        let mut cache = AnswerCache::new(2, CACHE_TTL);
+        cache.put("a".to_string(), "1".to_string(), start);
+        cache.put("b".to_string(), "2".to_string(), start);
+        assert_eq!(cache.get("a", start), Some("1".to_string()));
+        cache.put("c".to_string(), "3".to_string(), start);
+        assert_eq!(cache.get("b", start), None);
+        assert_eq!(cache.get("a", start), Some("1".to_string()));
+    }
+}
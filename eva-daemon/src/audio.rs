@@ -1,146 +1,148 @@
 use std::sync::{Arc, Mutex};
 use std::collections::VecDeque;
 
-#[cfg(not(target_os = "redox"))]
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-
 pub const SAMPLE_RATE: u32 = 16000; // Gemini expects 16kHz
 pub const CHANNELS: u16 = 1;
 pub const CHUNK_SIZE: usize = 1600; // 100ms at 16kHz
 
+/// Rate `capture_chunk` delivers; desktop devices are opened at it
+pub const CAPTURE_SAMPLE_RATE: u32 = 48000;
+/// One `capture_chunk`: 100ms at the capture rate
+pub const CAPTURE_CHUNK_SIZE: usize = 4800;
+
+/// How often `capture_chunk` checks for a full chunk
+#[cfg(not(target_os = "redox"))]
+const CAPTURE_POLL: std::time::Duration = std::time::Duration::from_millis(10);
+
 /// Audio device manager
+///
+/// Off Redox, the `desktop-audio` feature captures and plays through the
+/// system devices (cpal). Without it, or when no device can be opened, the
+/// device runs in mock mode: capture yields silence in real time and
+/// playback is discarded.
 pub struct AudioDevice {
     #[cfg(not(target_os = "redox"))]
-    input_buffer: Arc<Mutex<VecDeque<f32>>>,
+    input_buffer: Arc<Mutex<RingBuffer>>,
     #[cfg(not(target_os = "redox"))]
     output_buffer: Arc<Mutex<VecDeque<f32>>>,
-
     #[cfg(not(target_os = "redox"))]
-    _input_stream: Option<cpal::Stream>,
+    mock: bool,
+    /// Rate the output stream was opened at
     #[cfg(not(target_os = "redox"))]
-    _output_stream: Option<cpal::Stream>,
+    output_rate: u32,
+
+    /// Input and output streams, kept alive while the device is
+    #[cfg(all(feature = "desktop-audio", not(target_os = "redox")))]
+    _streams: Option<(cpal::Stream, cpal::Stream)>,
 
     #[cfg(target_os = "redox")]
     input: Option<std::fs::File>,
     #[cfg(target_os = "redox")]
-    output: Option<std::fs::File>,
+    output: Option<Arc<Mutex<std::fs::File>>>,
 }
 
-impl AudioDevice {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+/// Write end of the speaker, shareable with a playback thread
+#[derive(Clone)]
+pub struct PlaybackSink {
+    /// Queue the output stream drains; `None` in mock mode
+    #[cfg(not(target_os = "redox"))]
+    buffer: Option<Arc<Mutex<VecDeque<f32>>>>,
+    #[cfg(target_os = "redox")]
+    output: Option<Arc<Mutex<std::fs::File>>>,
+}
+
+impl PlaybackSink {
+    pub fn write(&self, samples: &[f32]) -> std::io::Result<()> {
         #[cfg(not(target_os = "redox"))]
         {
-            let host = cpal::default_host();
-
-            // INPUT (Microphone)
-            let input_device = host.default_input_device()
-                .ok_or("No input device available")?;
-            println!("🎤 Microfone: {}", input_device.name().unwrap_or_default());
-
-            let input_config = input_device.default_input_config()?;
-            let in_sample_rate = input_config.sample_rate().0;
-            let in_channels = input_config.channels() as usize;
-            println!("   Input: {}Hz, {} canais", in_sample_rate, in_channels);
-
-            let input_buffer = Arc::new(Mutex::new(VecDeque::with_capacity(in_sample_rate as usize)));
-            let input_buffer_clone = Arc::clone(&input_buffer);
-
-            let input_stream_config: cpal::StreamConfig = input_config.into();
-            let input_stream = input_device.build_input_stream(
-                &input_stream_config,
-                move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    if let Ok(mut buffer) = input_buffer_clone.lock() {
-                        for chunk in data.chunks(in_channels) {
-                            let mono: f32 = chunk.iter().sum::<f32>() / in_channels as f32;
-                            buffer.push_back(mono);
-                            if buffer.len() > in_sample_rate as usize * 2 {
-                                buffer.pop_front();
-                            }
-                        }
-                    }
-                },
-                |err| eprintln!("❌ Input error: {}", err),
-                None,
-            )?;
-            input_stream.play()?;
-
-            // OUTPUT (Speaker)
-            let output_device = host.default_output_device()
-                .ok_or("No output device available")?;
-            println!("🔊 Speaker: {}", output_device.name().unwrap_or_default());
-
-            let output_config = output_device.default_output_config()?;
-            let out_sample_rate = output_config.sample_rate().0;
-            let out_channels = output_config.channels() as usize;
-            println!("   Output: {}Hz, {} canais", out_sample_rate, out_channels);
-
-            let output_buffer = Arc::new(Mutex::new(VecDeque::with_capacity(out_sample_rate as usize * 2)));
-            let output_buffer_clone = Arc::clone(&output_buffer);
-
-            let output_stream_config: cpal::StreamConfig = output_config.into();
-            let output_stream = output_device.build_output_stream(
-                &output_stream_config,
-                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                    if let Ok(mut buffer) = output_buffer_clone.lock() {
-                        for frame in data.chunks_mut(out_channels) {
-                            let sample = buffer.pop_front().unwrap_or(0.0);
-                            for s in frame.iter_mut() {
-                                *s = sample;
-                            }
-                        }
-                    }
-                },
-                |err| eprintln!("❌ Output error: {}", err),
-                None,
-            )?;
-            output_stream.play()?;
+            // Mock mode has no speaker to drain the queue
+            if let Some(mut buffer) = self.buffer.as_ref().and_then(|b| b.lock().ok()) {
+                buffer.extend(samples);
+            }
+            Ok(())
+        }
 
-            println!("✅ Áudio iniciado");
+        #[cfg(target_os = "redox")]
+        {
+            use std::io::Write;
+            if let Some(mut output) = self.output.as_ref().and_then(|o| o.lock().ok()) {
+                let buffer: Vec<u8> = samples
+                    .iter()
+                    .flat_map(|&s| ((s * 32767.0) as i16).to_le_bytes())
+                    .collect();
+                output.write_all(&buffer)?;
+                output.flush()?;
+            }
+            Ok(())
+        }
+    }
 
-            Ok(Self {
-                input_buffer,
-                output_buffer,
-                _input_stream: Some(input_stream),
-                _output_stream: Some(output_stream),
-            })
+    /// Samples written but not yet played
+    pub fn pending(&self) -> usize {
+        #[cfg(not(target_os = "redox"))]
+        {
+            self.buffer.as_ref().and_then(|b| b.lock().ok()).map_or(0, |b| b.len())
         }
 
+        // Redox writes block until audio:play has taken them, nothing is queued here
         #[cfg(target_os = "redox")]
         {
-            use std::fs::File;
-            let input = File::open("audio:record").ok();
-            let output = File::create("audio:play").ok();
-            Ok(Self { input, output })
+            0
         }
     }
 
-    pub async fn capture_chunk(&mut self) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+    /// Drop everything written but not yet played
+    pub fn clear(&self) {
+        #[cfg(not(target_os = "redox"))]
+        if let Some(mut buffer) = self.buffer.as_ref().and_then(|b| b.lock().ok()) {
+            buffer.clear();
+        }
+    }
+}
+
+/// Read end of the microphone, which can move to a capture task
+///
+/// Chunks go to whichever reader takes them first, so only one source
+/// should be read at a time.
+pub struct CaptureSource {
+    /// Queue the input stream fills; `None` in mock mode
+    #[cfg(not(target_os = "redox"))]
+    buffer: Option<Arc<Mutex<RingBuffer>>>,
+    #[cfg(target_os = "redox")]
+    input: Option<std::fs::File>,
+}
+
+impl CaptureSource {
+    /// Next 100ms of mono audio at `CAPTURE_SAMPLE_RATE`
+    pub async fn capture_chunk(&mut self) -> Result<Vec<f32>, Box<dyn std::error::Error + Send + Sync>> {
         #[cfg(not(target_os = "redox"))]
         {
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            let chunk_duration = std::time::Duration::from_millis(100);
+            let mut chunk = vec![0.0; CAPTURE_CHUNK_SIZE];
+            let Some(input_buffer) = &self.buffer else {
+                tokio::time::sleep(chunk_duration).await;
+                return Ok(chunk);
+            };
 
-            let samples = {
-                let mut buffer = self.input_buffer.lock().map_err(|e| format!("Lock: {}", e))?;
-                let len = buffer.len().min(CHUNK_SIZE);
-                let mut samples = Vec::with_capacity(len);
-                for _ in 0..len {
-                    if let Some(s) = buffer.pop_front() {
-                        samples.push(s);
+            // Wait for a full chunk; a stalled stream yields what arrived, padded with silence
+            let deadline = tokio::time::Instant::now() + chunk_duration * 5;
+            loop {
+                {
+                    let mut buffer = input_buffer.lock().map_err(|e| format!("Lock: {}", e))?;
+                    if buffer.len() >= CAPTURE_CHUNK_SIZE || tokio::time::Instant::now() >= deadline {
+                        buffer.read(&mut chunk);
+                        return Ok(chunk);
                     }
                 }
-                samples
-            };
-
-            let mut result = samples;
-            result.resize(CHUNK_SIZE, 0.0);
-            Ok(result)
+                tokio::time::sleep(CAPTURE_POLL).await;
+            }
         }
 
         #[cfg(target_os = "redox")]
         {
             use std::io::Read;
             if let Some(ref mut input) = self.input {
-                let mut buffer = vec![0u8; CHUNK_SIZE * 2];
+                let mut buffer = vec![0u8; CAPTURE_CHUNK_SIZE * 2];
                 input.read_exact(&mut buffer)?;
                 let samples: Vec<f32> = buffer
                     .chunks_exact(2)
@@ -148,35 +150,286 @@ impl AudioDevice {
                     .collect();
                 Ok(samples)
             } else {
-                Ok(vec![0.0; CHUNK_SIZE])
+                Ok(vec![0.0; CAPTURE_CHUNK_SIZE])
             }
         }
     }
+}
 
-    pub async fn play(&mut self, samples: &[f32]) -> Result<(), Box<dyn std::error::Error>> {
+impl AudioDevice {
+    /// Open the default devices
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::open(None)
+    }
+
+    /// Open the devices, capturing from the input named `microphone` (see
+    /// `list_devices`) when it is present
+    pub fn open(microphone: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
         #[cfg(not(target_os = "redox"))]
         {
-            if let Ok(mut buffer) = self.output_buffer.lock() {
-                for &s in samples {
-                    buffer.push_back(s);
+            // 2s of backlog before the oldest samples are dropped
+            let input_buffer = Arc::new(Mutex::new(RingBuffer::new(CAPTURE_SAMPLE_RATE as usize * 2)));
+            let output_buffer = Arc::new(Mutex::new(VecDeque::with_capacity(CAPTURE_SAMPLE_RATE as usize * 2)));
+
+            #[cfg(feature = "desktop-audio")]
+            match desktop::open_streams(microphone, &input_buffer, &output_buffer) {
+                Ok((input, output, output_rate)) => {
+                    println!("✅ Áudio iniciado");
+                    return Ok(Self { input_buffer, output_buffer, mock: false, output_rate, _streams: Some((input, output)) });
                 }
+                Err(e) => eprintln!("⚠️  Audio device unavailable ({}), using mock audio", e),
             }
-            Ok(())
+            #[cfg(not(feature = "desktop-audio"))]
+            let _ = microphone;
+
+            Ok(Self {
+                input_buffer,
+                output_buffer,
+                mock: true,
+                output_rate: crate::audio_player::PLAYBACK_SAMPLE_RATE,
+                #[cfg(feature = "desktop-audio")]
+                _streams: None,
+            })
         }
 
         #[cfg(target_os = "redox")]
         {
-            use std::io::Write;
-            if let Some(ref mut output) = self.output {
-                let buffer: Vec<u8> = samples
-                    .iter()
-                    .flat_map(|&s| ((s * 32767.0) as i16).to_le_bytes())
-                    .collect();
-                output.write_all(&buffer)?;
-                output.flush()?;
+            use std::fs::File;
+            let _ = microphone;
+            let input = File::open("audio:record").ok();
+            let output = File::create("audio:play").ok().map(|f| Arc::new(Mutex::new(f)));
+            Ok(Self { input, output })
+        }
+    }
+
+    /// Names of the available input devices, for pinning a microphone
+    pub fn list_devices() -> Vec<String> {
+        #[cfg(all(feature = "desktop-audio", not(target_os = "redox")))]
+        {
+            desktop::input_device_names()
+        }
+
+        #[cfg(not(all(feature = "desktop-audio", not(target_os = "redox"))))]
+        {
+            Vec::new()
+        }
+    }
+
+    /// True when no real device is behind this one
+    pub fn is_mock(&self) -> bool {
+        #[cfg(not(target_os = "redox"))]
+        {
+            self.mock
+        }
+
+        #[cfg(target_os = "redox")]
+        {
+            self.input.is_none()
+        }
+    }
+
+    /// Microphone handle for a capture task (see `listener`)
+    pub fn capture_source(&self) -> std::io::Result<CaptureSource> {
+        #[cfg(not(target_os = "redox"))]
+        {
+            Ok(CaptureSource { buffer: (!self.mock).then(|| Arc::clone(&self.input_buffer)) })
+        }
+
+        #[cfg(target_os = "redox")]
+        {
+            Ok(CaptureSource { input: self.input.as_ref().map(|f| f.try_clone()).transpose()? })
+        }
+    }
+
+    /// Rate the speaker plays at; reply audio is converted to it
+    pub fn playback_rate(&self) -> u32 {
+        #[cfg(not(target_os = "redox"))]
+        {
+            self.output_rate
+        }
+
+        // audio:play is fed reply audio as it comes
+        #[cfg(target_os = "redox")]
+        {
+            crate::audio_player::PLAYBACK_SAMPLE_RATE
+        }
+    }
+
+    /// Speaker handle for a playback thread (see `AudioPlayer`)
+    pub fn playback_sink(&self) -> PlaybackSink {
+        #[cfg(not(target_os = "redox"))]
+        {
+            PlaybackSink { buffer: (!self.mock).then(|| Arc::clone(&self.output_buffer)) }
+        }
+
+        #[cfg(target_os = "redox")]
+        {
+            PlaybackSink { output: self.output.clone() }
+        }
+    }
+
+    pub async fn play(&mut self, samples: &[f32]) -> Result<(), Box<dyn std::error::Error>> {
+        self.playback_sink().write(samples)?;
+        Ok(())
+    }
+
+    /// Drop everything queued for playback
+    pub fn stop_playback(&mut self) {
+        self.playback_sink().clear();
+    }
+
+    /// Samples still waiting to be played
+    pub fn pending_playback(&self) -> usize {
+        self.playback_sink().pending()
+    }
+}
+
+/// cpal streams for Linux, Windows and macOS
+#[cfg(all(feature = "desktop-audio", not(target_os = "redox")))]
+mod desktop {
+    use super::{LinearResampler, RingBuffer, CAPTURE_SAMPLE_RATE};
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    pub fn input_device_names() -> Vec<String> {
+        cpal::default_host()
+            .input_devices()
+            .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    /// `rate` if the device supports it (fewest channels, f32 preferred),
+    /// else the device's default configuration
+    fn pick_config(
+        ranges: Vec<cpal::SupportedStreamConfigRange>,
+        default: cpal::SupportedStreamConfig,
+        rate: u32,
+    ) -> cpal::StreamConfig {
+        let rate = cpal::SampleRate(rate);
+        ranges
+            .into_iter()
+            .filter(|r| r.min_sample_rate() <= rate && rate <= r.max_sample_rate())
+            .min_by_key(|r| (r.sample_format() != cpal::SampleFormat::F32, r.channels()))
+            .map(|r| r.with_sample_rate(rate).into())
+            .unwrap_or_else(|| default.into())
+    }
+
+    fn input_device(host: &cpal::Host, microphone: Option<&str>) -> Result<cpal::Device, Box<dyn std::error::Error>> {
+        if let Some(name) = microphone {
+            let pinned = host.input_devices()?.find(|d| d.name().map_or(false, |n| n == name));
+            match pinned {
+                Some(device) => return Ok(device),
+                None => eprintln!("⚠️  Microphone '{}' not found, using the default input", name),
             }
-            Ok(())
         }
+        Ok(host.default_input_device().ok_or("No input device available")?)
+    }
+
+    pub fn open_streams(
+        microphone: Option<&str>,
+        input_buffer: &Arc<Mutex<RingBuffer>>,
+        output_buffer: &Arc<Mutex<VecDeque<f32>>>,
+    ) -> Result<(cpal::Stream, cpal::Stream, u32), Box<dyn std::error::Error>> {
+        let host = cpal::default_host();
+
+        // INPUT (Microphone): mono at the capture rate, resampled if the
+        // device can't do it (e.g. 44.1 kHz only)
+        let input_device = input_device(&host, microphone)?;
+        println!("🎤 Microfone: {}", input_device.name().unwrap_or_default());
+
+        let input_config = pick_config(
+            input_device.supported_input_configs()?.collect(),
+            input_device.default_input_config()?,
+            CAPTURE_SAMPLE_RATE,
+        );
+        let in_channels = input_config.channels as usize;
+        println!("   Input: {}Hz, {} canais", input_config.sample_rate.0, in_channels);
+
+        let mut resampler = LinearResampler::new(input_config.sample_rate.0, CAPTURE_SAMPLE_RATE);
+        let input_buffer = Arc::clone(input_buffer);
+        let input_stream = input_device.build_input_stream(
+            &input_config,
+            move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                let mono: Vec<f32> = data
+                    .chunks(in_channels)
+                    .map(|frame| frame.iter().sum::<f32>() / in_channels as f32)
+                    .collect();
+                let samples = resampler.process(&mono);
+                if let Ok(mut buffer) = input_buffer.lock() {
+                    buffer.write(&samples);
+                }
+            },
+            |err| eprintln!("❌ Input error: {}", err),
+            None,
+        )?;
+        input_stream.play()?;
+
+        // OUTPUT (Speaker)
+        let output_device = host.default_output_device().ok_or("No output device available")?;
+        println!("🔊 Speaker: {}", output_device.name().unwrap_or_default());
+
+        let output_config = pick_config(
+            output_device.supported_output_configs()?.collect(),
+            output_device.default_output_config()?,
+            CAPTURE_SAMPLE_RATE,
+        );
+        let out_channels = output_config.channels as usize;
+        println!("   Output: {}Hz, {} canais", output_config.sample_rate.0, out_channels);
+
+        let output_buffer = Arc::clone(output_buffer);
+        let output_stream = output_device.build_output_stream(
+            &output_config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                if let Ok(mut buffer) = output_buffer.lock() {
+                    for frame in data.chunks_mut(out_channels) {
+                        let sample = buffer.pop_front().unwrap_or(0.0);
+                        for s in frame.iter_mut() {
+                            *s = sample;
+                        }
+                    }
+                }
+            },
+            |err| eprintln!("❌ Output error: {}", err),
+            None,
+        )?;
+        output_stream.play()?;
+
+        Ok((input_stream, output_stream, output_config.sample_rate.0))
+    }
+}
+
+/// Streaming linear-interpolation resampler, for devices that can't run at
+/// the capture rate. Keeps the last sample so consecutive buffers join up.
+pub struct LinearResampler {
+    /// Input samples advanced per output sample
+    step: f64,
+    /// Position of the next output, counted from `prev` (0.0) into the next buffer
+    pos: f64,
+    prev: f32,
+}
+
+impl LinearResampler {
+    pub fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self { step: from_rate as f64 / to_rate as f64, pos: 1.0, prev: 0.0 }
+    }
+
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        if self.step == 1.0 || input.is_empty() {
+            return input.to_vec();
+        }
+
+        let mut out = Vec::with_capacity((input.len() as f64 / self.step) as usize + 1);
+        while self.pos < input.len() as f64 {
+            let i = self.pos as usize;
+            let frac = (self.pos - i as f64) as f32;
+            let a = if i == 0 { self.prev } else { input[i - 1] };
+            out.push(a + (input[i] - a) * frac);
+            self.pos += self.step;
+        }
+        self.pos -= input.len() as f64;
+        self.prev = input[input.len() - 1];
+        out
     }
 }
 
@@ -200,10 +453,51 @@ impl RingBuffer {
         for i in 0..len { output[i] = self.buffer.pop_front().unwrap_or(0.0); }
         len
     }
+    /// Everything buffered, oldest first
+    pub fn drain(&mut self) -> Vec<f32> { self.buffer.drain(..).collect() }
     pub fn len(&self) -> usize { self.buffer.len() }
     pub fn is_empty(&self) -> bool { self.buffer.is_empty() }
     pub fn clear(&mut self) { self.buffer.clear(); }
 }
 
 pub const BUFFER_SIZE: usize = 16000;
+/// Audio kept from before the wake word fires, so the start of the request
+/// isn't clipped by detection latency
+pub const PRE_ROLL_MS: u32 = 300;
 pub const BIT_DEPTH: u16 = 16;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resample_44k1_to_48k() {
+        let tone: Vec<f32> = (0..44_100).map(|i| (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 44_100.0).sin()).collect();
+
+        // Fed in device-sized buffers, as the input callback sees it
+        let mut resampler = LinearResampler::new(44_100, CAPTURE_SAMPLE_RATE);
+        let out: Vec<f32> = tone.chunks(441).flat_map(|c| resampler.process(c)).collect();
+        assert!((out.len() as i64 - 48_000).abs() <= 1, "{} samples", out.len());
+
+        // Still 440 Hz: ~880 zero crossings in one second
+        let crossings = out.windows(2).filter(|w| (w[0] < 0.0) != (w[1] < 0.0)).count();
+        assert!((878..=882).contains(&crossings), "{} crossings", crossings);
+
+        let mut same = LinearResampler::new(48_000, 48_000);
+        assert_eq!(same.process(&tone[..10]), tone[..10].to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_mock_device_captures_silence() {
+        let mut device = AudioDevice::new().unwrap();
+        if !device.is_mock() {
+            return;
+        }
+        let chunk = device.capture_source().unwrap().capture_chunk().await.unwrap();
+        assert_eq!(chunk.len(), CAPTURE_CHUNK_SIZE);
+        assert!(chunk.iter().all(|&s| s == 0.0));
+
+        device.play(&[0.5; 100]).await.unwrap();
+        assert_eq!(device.pending_playback(), 0);
+    }
+}
@@ -0,0 +1,302 @@
+//! Shared analysis of the microphone for VAD and the wake word detector
+//!
+//! Both look at the same audio while EVA waits for the wake word: VAD for
+//! the room's level, the detector for the phrase. `AudioFrontend` computes
+//! the features once per chunk and hands the same `FeatureFrame` to both.
+//! Frames are streamed: the 10 ms envelope and the 32 ms spectral frames
+//! that a chunk completes are computed when it arrives and never again,
+//! where the detector used to re-analyze its whole two-second buffer on
+//! every chunk.
+//!
+//! Spectral frames overlap by half, but one starts every quarter hop
+//! (`SPECTRAL_PHASES`). The detector frames its window from the window's
+//! first sample, which moves by a chunk (a quarter hop off the 256-sample
+//! grid) every 100 ms; with the phases precomputed it finds exactly the
+//! frames it used to compute itself.
+
+/// Energy envelope resolution
+pub const ENVELOPE_MS: u32 = 10;
+/// Spectral frame length; frames overlap by half
+pub const SPECTRAL_FRAME_MS: u32 = 32;
+/// Values per spectral frame: energy, then band magnitudes
+pub const SPECTRAL_COEFFICIENTS: usize = 13;
+/// Spectral frames started per hop
+pub const SPECTRAL_PHASES: usize = 4;
+
+/// RMS level of `samples`
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|&s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// Sign changes per sample
+pub fn zero_crossing_rate(samples: &[f32]) -> f32 {
+    if samples.len() < 2 {
+        return 0.0;
+    }
+    let crossings = samples.windows(2).filter(|w| (w[1] >= 0.0) != (w[0] >= 0.0)).count();
+    crossings as f32 / samples.len() as f32
+}
+
+/// Samples in a 10 ms envelope frame
+pub fn envelope_len(sample_rate: u32) -> usize {
+    (sample_rate * ENVELOPE_MS / 1000).max(1) as usize
+}
+
+/// Samples in a spectral frame; the hop is half of it
+pub fn spectral_frame_len(sample_rate: u32) -> usize {
+    (sample_rate as usize * SPECTRAL_FRAME_MS as usize / 1000).max(2)
+}
+
+/// Energy of a windowed 32 ms frame, then the magnitude of each band
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpectralFrame {
+    /// Position of the frame's first sample in the stream
+    pub start: u64,
+    pub coefficients: Vec<f32>,
+}
+
+/// What one chunk of audio looks like
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureFrame<'a> {
+    /// The chunk itself
+    pub samples: &'a [f32],
+    /// Position of its first sample: samples analyzed since the frontend
+    /// started or was reset
+    pub position: u64,
+    pub rms: f32,
+    pub zcr: f32,
+    /// RMS of each envelope frame this chunk completed
+    pub envelope: Vec<f32>,
+    /// Spectral frames this chunk completed, on every phase
+    pub spectra: Vec<SpectralFrame>,
+}
+
+impl<'a> FeatureFrame<'a> {
+    /// Chunk level and zero-crossing rate only, for consumers that need
+    /// nothing else (VAD on captured speech, barge-in)
+    pub fn levels(samples: &'a [f32]) -> Self {
+        Self {
+            samples,
+            position: 0,
+            rms: rms(samples),
+            zcr: zero_crossing_rate(samples),
+            envelope: Vec::new(),
+            spectra: Vec::new(),
+        }
+    }
+}
+
+/// Streams a microphone's chunks into `FeatureFrame`s
+pub struct AudioFrontend {
+    envelope_len: usize,
+    frame_len: usize,
+    /// Hamming window over a spectral frame
+    window: Vec<f32>,
+    /// Cosine basis of each band magnitude
+    bands: Vec<Vec<f32>>,
+    /// Stream position of the next chunk
+    position: u64,
+    /// Audio not yet in a complete envelope frame
+    envelope_tail: Vec<f32>,
+    /// Audio from the start of the next spectral frame on, and where that is
+    spectral_tail: Vec<f32>,
+    spectral_tail_start: u64,
+}
+
+impl AudioFrontend {
+    pub fn new(sample_rate: u32) -> Self {
+        let frame_len = spectral_frame_len(sample_rate);
+        let window = (0..frame_len)
+            .map(|i| 0.54 - 0.46 * (2.0 * std::f32::consts::PI * i as f32 / frame_len as f32).cos())
+            .collect();
+        let bands = (1..SPECTRAL_COEFFICIENTS)
+            .map(|band| {
+                let freq = (band as f32 / SPECTRAL_COEFFICIENTS as f32) * 0.5;
+                let phase = 2.0 * std::f32::consts::PI * freq;
+                (0..frame_len).map(|j| (phase * j as f32).cos()).collect()
+            })
+            .collect();
+        Self {
+            envelope_len: envelope_len(sample_rate),
+            frame_len,
+            window,
+            bands,
+            position: 0,
+            envelope_tail: Vec::new(),
+            spectral_tail: Vec::new(),
+            spectral_tail_start: 0,
+        }
+    }
+
+    /// Features of the next chunk
+    pub fn analyze<'a>(&mut self, chunk: &'a [f32]) -> FeatureFrame<'a> {
+        let mut frame = FeatureFrame { position: self.position, ..FeatureFrame::levels(chunk) };
+        self.position += chunk.len() as u64;
+
+        self.envelope_tail.extend_from_slice(chunk);
+        let complete = self.envelope_tail.len() / self.envelope_len * self.envelope_len;
+        frame.envelope = self.envelope_tail[..complete].chunks(self.envelope_len).map(rms).collect();
+        self.envelope_tail.drain(..complete);
+
+        self.spectral_tail.extend_from_slice(chunk);
+        let step = (self.frame_len / 2 / SPECTRAL_PHASES).max(1);
+        let mut start = 0;
+        while start + self.frame_len <= self.spectral_tail.len() {
+            frame.spectra.push(SpectralFrame {
+                start: self.spectral_tail_start + start as u64,
+                coefficients: self.spectral_frame(&self.spectral_tail[start..start + self.frame_len]),
+            });
+            start += step;
+        }
+        self.spectral_tail.drain(..start);
+        self.spectral_tail_start += start as u64;
+
+        frame
+    }
+
+    fn spectral_frame(&self, samples: &[f32]) -> Vec<f32> {
+        let windowed: Vec<f32> = samples.iter().zip(&self.window).map(|(s, w)| s * w).collect();
+        let mut coefficients = Vec::with_capacity(SPECTRAL_COEFFICIENTS);
+        coefficients.push(windowed.iter().map(|s| s * s).sum::<f32>().sqrt());
+        for basis in &self.bands {
+            let magnitude = windowed.iter().zip(basis).map(|(s, c)| s * c).sum::<f32>().abs();
+            coefficients.push(magnitude / self.frame_len as f32);
+        }
+        coefficients
+    }
+
+    /// Forget partial frames: the next chunk starts a fresh stream at 0
+    pub fn reset(&mut self) {
+        self.position = 0;
+        self.envelope_tail.clear();
+        self.spectral_tail.clear();
+        self.spectral_tail_start = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{CHUNK_SIZE, SAMPLE_RATE};
+    use crate::vad::VAD;
+    use crate::wake_word::{DetectionStrategy, WakeWordConfig, WakeWordDetector};
+
+    #[test]
+    fn test_energy_calculation() {
+        assert_eq!(rms(&[0.0; 100]), 0.0);
+        assert_eq!(rms(&[1.0; 100]), 1.0);
+        assert_eq!(rms(&[0.5; 100]), 0.5);
+        assert_eq!(rms(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_zero_crossing_rate() {
+        // No crossings (DC signal)
+        assert_eq!(zero_crossing_rate(&[0.5; 100]), 0.0);
+
+        // Maximum crossings (alternating signal)
+        let alternating: Vec<f32> = (0..100).map(|i| if i % 2 == 0 { 1.0 } else { -1.0 }).collect();
+        assert!(zero_crossing_rate(&alternating) > 0.9);
+    }
+
+    #[test]
+    fn test_streaming_matches_whole_signal() {
+        let audio: Vec<f32> = (0..8000).map(|i| (i as f32 * 0.07).sin() * (i as f32 / 8000.0)).collect();
+        let whole = AudioFrontend::new(SAMPLE_RATE).analyze(&audio);
+
+        // Chunk boundaries that cut through frames
+        let mut frontend = AudioFrontend::new(SAMPLE_RATE);
+        let (mut envelope, mut spectra) = (Vec::new(), Vec::new());
+        for chunk in audio.chunks(700) {
+            let frame = frontend.analyze(chunk);
+            envelope.extend(frame.envelope);
+            spectra.extend(frame.spectra);
+        }
+        assert_eq!(envelope, whole.envelope);
+        assert_eq!(spectra, whole.spectra);
+        assert_eq!(envelope.len(), 8000 / 160);
+        assert_eq!(spectra.len(), (8000 - 512) / 64 + 1);
+        assert!(spectra.iter().enumerate().all(|(i, f)| f.start == i as u64 * 64 && f.coefficients.len() == SPECTRAL_COEFFICIENTS));
+
+        frontend.reset();
+        let frame = frontend.analyze(&audio[..400]);
+        assert_eq!(frame.position, 0);
+        assert!(frame.spectra.is_empty());
+        assert_eq!(frontend.analyze(&audio[..400]).position, 400);
+    }
+
+    /// Syllable-like bursts with pauses, repeated, over a little hiss
+    fn bursts() -> Vec<f32> {
+        let mut out = Vec::new();
+        for _ in 0..4 {
+            for freq in [0.05, 0.2, 0.12] {
+                out.extend((0..3200).map(|i| {
+                    let envelope = (i.min(3200 - i) as f32 / 400.0).min(1.0);
+                    0.5 * envelope * (2.0 * std::f32::consts::PI * freq * i as f32).sin()
+                }));
+                out.extend(vec![0.0; 800]);
+            }
+            out.extend(vec![0.0; 12_000]);
+        }
+        out.iter().enumerate().map(|(i, s)| s + if i % 2 == 0 { 0.003 } else { -0.003 }).collect()
+    }
+
+    /// VAD and the detector reach the same decisions from shared features
+    /// as from the samples (the listener restarts the frontend on a wake)
+    #[test]
+    fn test_shared_features_match_separate_analysis() {
+        let config = || WakeWordConfig { strategy: DetectionStrategy::Energy, cooldown_ms: 0, ..WakeWordConfig::default() };
+        let (mut shared_detector, mut separate_detector) = (WakeWordDetector::with_config(config()), WakeWordDetector::with_config(config()));
+        let (mut shared_vad, mut separate_vad) = (VAD::new(), VAD::new());
+        let mut frontend = AudioFrontend::new(SAMPLE_RATE);
+
+        let (mut shared, mut separate) = (Vec::new(), Vec::new());
+        for (i, chunk) in bursts().chunks(CHUNK_SIZE).enumerate() {
+            let features = frontend.analyze(chunk);
+            if shared_detector.detect_frame(&features) {
+                shared.push(i);
+                frontend.reset();
+            } else {
+                shared_vad.observe_background_frame(&features);
+            }
+            if separate_detector.detect(chunk) {
+                separate.push(i);
+            } else {
+                separate_vad.observe_background(chunk);
+            }
+            assert_eq!(shared_vad.energy_snapshot(), separate_vad.energy_snapshot(), "chunk {}", i);
+        }
+        assert_eq!(shared, separate);
+        assert!(!shared.is_empty());
+    }
+
+    /// Per-chunk work while waiting for the wake word, counted in spectral
+    /// frames (the expensive part: a windowed frame times 12 band bases)
+    #[test]
+    fn test_shared_frontend_computes_each_frame_once() {
+        let audio: Vec<f32> = (0..SAMPLE_RATE as usize * 10).map(|i| (i as f32 * 0.05).sin() * 0.3).collect();
+        let mut frontend = AudioFrontend::new(SAMPLE_RATE);
+        let mut detector = WakeWordDetector::new();
+        let mut vad = VAD::new();
+
+        let (frame_len, hop) = (spectral_frame_len(SAMPLE_RATE), spectral_frame_len(SAMPLE_RATE) / 2);
+        let (mut shared, mut separate) = (0, 0);
+        for (i, chunk) in audio.chunks(CHUNK_SIZE).enumerate() {
+            let features = frontend.analyze(chunk);
+            shared += features.spectra.len();
+            detector.detect_frame(&features);
+            vad.observe_background_frame(&features);
+
+            // The detector on its own re-analyzed its two-second buffer
+            let buffered = ((i + 1) * CHUNK_SIZE).min(SAMPLE_RATE as usize * 2);
+            separate += buffered.saturating_sub(frame_len).div_ceil(hop);
+        }
+
+        let chunks = audio.len() / CHUNK_SIZE;
+        assert!(shared <= chunks * CHUNK_SIZE * SPECTRAL_PHASES / hop, "{} frames for {} chunks", shared, chunks);
+        assert!(shared * 4 < separate, "shared {} vs separate {}", shared, separate);
+    }
+}
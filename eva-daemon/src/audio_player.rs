@@ -1,36 +1,570 @@
-use crate::audio::AudioDevice;
+use crate::audio::{AudioDevice, PlaybackSink};
+use crate::audio_processor::{DspChain, StageMetrics};
+use crate::command_parser::AudioOperation;
+use crate::resample::SincResampler;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Rate of the reply audio from Gemini and EVA-Mind, and of the queue;
+/// the playback thread converts it to the speaker's rate
+pub const PLAYBACK_SAMPLE_RATE: u32 = 24_000;
+
+/// Reply rates taken at their word; anything else is assumed to be 24 kHz
+const REPLY_RATES: std::ops::RangeInclusive<u32> = 8_000..=96_000;
+
+/// Crossfade at chunk joins and fades to/from silence (8 ms)
+const CROSSFADE: usize = PLAYBACK_SAMPLE_RATE as usize / 125;
+
+/// A step between chunks larger than this is a discontinuity, not speech
+const JOIN_STEP: f32 = 0.1;
+
+/// Audio the playback thread keeps ahead in the speaker (~100 ms); the
+/// rest stays in the queue where `flush` can drop it
+const SINK_LEAD: usize = PLAYBACK_SAMPLE_RATE as usize / 10;
+
+/// How often the playback thread checks for work
+const DRAIN_POLL: Duration = Duration::from_millis(5);
+
+/// Time-stretch frame (20 ms at 24 kHz) and its 50% overlap
+const STRETCH_FRAME: usize = 480;
+const STRETCH_HOP: usize = STRETCH_FRAME / 2;
+
+/// "volume up" / "volume down" step
+pub const VOLUME_STEP: f32 = 0.1;
+
+/// Reply playback speeds `AudioPlayer::set_speed` accepts
+pub const MIN_SPEED: f32 = 0.75;
+pub const MAX_SPEED: f32 = 1.5;
+
+/// Scale `samples` by `gain` (clamped to 0.0-1.0); the result never
+/// leaves -1.0..=1.0, so a hot chunk can't wrap around in the speaker
+pub fn apply_gain(samples: &mut [f32], gain: f32) {
+    let gain = gain.clamp(0.0, 1.0);
+    for sample in samples {
+        *sample = (*sample * gain).clamp(-1.0, 1.0);
+    }
+}
+
+/// Play `samples` `speed` times faster by resampling with linear
+/// interpolation; cheap, but the pitch moves with the speed
+pub fn resample_speed(samples: &[f32], speed: f32) -> Vec<f32> {
+    if (speed - 1.0).abs() < 0.01 || samples.len() < 2 {
+        return samples.to_vec();
+    }
+    let out_len = (samples.len() as f32 / speed) as usize;
+    (0..out_len)
+        .map(|i| {
+            let pos = i as f32 * speed;
+            let at = pos as usize;
+            let next = samples[(at + 1).min(samples.len() - 1)];
+            let frac = pos - at as f32;
+            samples[at] + (next - samples[at]) * frac
+        })
+        .collect()
+}
+
+/// Change duration by `rate` (>1 faster) without resampling, by windowed
+/// overlap-add. Pitch is preserved; quality drops the further from 1.0.
+pub fn time_stretch(samples: &[f32], rate: f32) -> Vec<f32> {
+    if (rate - 1.0).abs() < 0.01 || samples.len() < STRETCH_FRAME {
+        return samples.to_vec();
+    }
+
+    let window: Vec<f32> = (0..STRETCH_FRAME)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / STRETCH_FRAME as f32).cos())
+        .collect();
+    let out_len = (samples.len() as f32 / rate) as usize;
+    let mut out = vec![0.0f32; out_len + STRETCH_FRAME];
+    let mut norm = vec![0.0f32; out_len + STRETCH_FRAME];
+
+    let mut out_pos = 0;
+    while out_pos < out_len {
+        let in_pos = (out_pos as f32 * rate) as usize;
+        if in_pos + STRETCH_FRAME > samples.len() {
+            break;
+        }
+        for i in 0..STRETCH_FRAME {
+            out[out_pos + i] += samples[in_pos + i] * window[i];
+            norm[out_pos + i] += window[i];
+        }
+        out_pos += STRETCH_HOP;
+    }
+
+    out.truncate(out_len);
+    for (sample, weight) in out.iter_mut().zip(&norm) {
+        if *weight > 1e-3 {
+            *sample /= weight;
+        }
+    }
+    out
+}
+
+/// Reply audio waiting for the speaker, in arrival order
+///
+/// The end of the newest chunk is held back until the next one arrives, so
+/// joins that don't line up can be crossfaded instead of clicking. Audio
+/// that starts from silence fades in; the held tail fades out if the
+/// speaker is about to run dry.
+struct PlaybackQueue {
+    ready: VecDeque<Vec<f32>>,
+    tail: Vec<f32>,
+    /// Nothing is playing: the next chunk fades in
+    from_silence: bool,
+}
+
+impl PlaybackQueue {
+    fn new() -> Self {
+        Self { ready: VecDeque::new(), tail: Vec::new(), from_silence: true }
+    }
+
+    fn push(&mut self, mut samples: Vec<f32>) {
+        if samples.is_empty() {
+            return;
+        }
+
+        let tail = std::mem::take(&mut self.tail);
+        if let (Some(&last), true) = (tail.last(), samples.len() >= tail.len()) {
+            if (samples[0] - last).abs() > JOIN_STEP {
+                // Fade the held tail out over the start of the new chunk
+                let n = tail.len();
+                for (i, (s, t)) in samples.iter_mut().zip(&tail).enumerate() {
+                    let w = (i + 1) as f32 / (n + 1) as f32;
+                    *s = t * (1.0 - w) + *s * w;
+                }
+            } else {
+                samples.splice(0..0, tail);
+            }
+        } else if !tail.is_empty() {
+            samples.splice(0..0, tail);
+        } else if self.from_silence {
+            let n = CROSSFADE.min(samples.len());
+            for (i, s) in samples[..n].iter_mut().enumerate() {
+                *s *= (i + 1) as f32 / (n + 1) as f32;
+            }
+        }
+        self.from_silence = false;
+
+        let keep = CROSSFADE.min(samples.len());
+        self.tail = samples.split_off(samples.len() - keep);
+        if !samples.is_empty() {
+            self.ready.push_back(samples);
+        }
+    }
+
+    /// Up to `max` samples from the front of the queue
+    fn pop(&mut self, max: usize) -> Option<Vec<f32>> {
+        let mut chunk = self.ready.pop_front()?;
+        if chunk.len() > max {
+            self.ready.push_front(chunk.split_off(max));
+        }
+        Some(chunk)
+    }
+
+    /// The held tail, faded out; the next chunk will fade in
+    fn release_tail(&mut self) -> Option<Vec<f32>> {
+        if self.tail.is_empty() {
+            return None;
+        }
+        let mut tail = std::mem::take(&mut self.tail);
+        let n = tail.len();
+        for (i, s) in tail.iter_mut().enumerate() {
+            *s *= (n - i) as f32 / (n + 1) as f32;
+        }
+        self.from_silence = true;
+        Some(tail)
+    }
+
+    fn len(&self) -> usize {
+        self.ready.iter().map(Vec::len).sum::<usize>() + self.tail.len()
+    }
+
+    fn clear(&mut self) {
+        self.ready.clear();
+        self.tail.clear();
+        self.from_silence = true;
+    }
+}
+
+/// Samples at `PLAYBACK_SAMPLE_RATE` that `device_samples` at the
+/// speaker's rate last
+fn at_playback_rate(device_samples: usize, device_rate: u32) -> usize {
+    (device_samples as u64 * PLAYBACK_SAMPLE_RATE as u64 / device_rate.max(1) as u64) as usize
+}
+
+/// Feed the speaker from the queue until `running` is cleared
+///
+/// `gain` (f32 bits) is applied as the audio goes out, so a volume change
+/// or mute reaches a reply that is already queued. Audio is converted to
+/// `device_rate` on the way; the converter starts over whenever the
+/// speaker runs dry, so one reply's tail never bleeds into the next.
+fn spawn_drain(
+    queue: Arc<Mutex<PlaybackQueue>>,
+    sink: PlaybackSink,
+    device_rate: u32,
+    running: Arc<AtomicBool>,
+    gain: Arc<AtomicU32>,
+    error: Arc<Mutex<Option<String>>>,
+) -> Option<JoinHandle<()>> {
+    let drain = move || {
+        let mut converter = SincResampler::new(PLAYBACK_SAMPLE_RATE, device_rate);
+        while running.load(Ordering::Relaxed) {
+            let pending = at_playback_rate(sink.pending(), device_rate);
+            let Ok(mut queue) = queue.lock() else { return };
+            let next = if pending >= SINK_LEAD {
+                None
+            } else if let Some(chunk) = queue.pop(SINK_LEAD - pending) {
+                Some(chunk)
+            } else if pending < CROSSFADE {
+                queue.release_tail()
+            } else {
+                None
+            };
+
+            match next {
+                // Written under the lock, so a flush can't be overtaken
+                Some(chunk) => {
+                    let mut chunk = converter.process(&chunk);
+                    apply_gain(&mut chunk, f32::from_bits(gain.load(Ordering::Relaxed)));
+                    if let Err(e) = sink.write(&chunk) {
+                        if let Ok(mut error) = error.lock() {
+                            *error = Some(e.to_string());
+                        }
+                    }
+                }
+                None => {
+                    drop(queue);
+                    if pending == 0 {
+                        converter.reset();
+                    }
+                    std::thread::sleep(DRAIN_POLL);
+                }
+            }
+        }
+    };
+    match std::thread::Builder::new().name("eva-playback".into()).spawn(drain) {
+        Ok(handle) => Some(handle),
+        Err(e) => {
+            eprintln!("[AudioPlayer] Failed to start playback thread: {}", e);
+            None
+        }
+    }
+}
+
+/// How much reply audio is left to play, readable from other threads
+#[derive(Clone)]
+pub struct PlaybackDepth {
+    queue: Arc<Mutex<PlaybackQueue>>,
+    sink: PlaybackSink,
+    device_rate: u32,
+}
+
+impl PlaybackDepth {
+    pub fn queued_duration(&self) -> Duration {
+        let queued = self.queue.lock().map_or(0, |q| q.len()) + at_playback_rate(self.sink.pending(), self.device_rate);
+        Duration::from_secs_f64(queued as f64 / PLAYBACK_SAMPLE_RATE as f64)
+    }
+}
 
 /// Audio player for Gemini responses
+///
+/// Reply chunks are queued and a background thread feeds them to the
+/// speaker back to back, so the gaps between network reads don't reach it.
 pub struct AudioPlayer {
-    device: AudioDevice,
+    /// Kept open for as long as the player plays into it
+    _device: AudioDevice,
+    sink: PlaybackSink,
+    /// Rate the speaker plays at
+    device_rate: u32,
+    queue: Arc<Mutex<PlaybackQueue>>,
+    running: Arc<AtomicBool>,
+    drain: Option<JoinHandle<()>>,
+    /// Last speaker write failure on the playback thread
+    error: Arc<Mutex<Option<String>>>,
+    playback_chain: Option<DspChain>,
+    /// Local speaking rate, used when the voice service can't change it
+    playback_rate: f32,
+    /// RMS of the last buffer queued, for the barge-in echo gate
+    last_level: f32,
+    /// Gain the playback thread applies: `volume`, or 0.0 when muted
+    gain: Arc<AtomicU32>,
+    volume: f32,
+    muted: bool,
+    /// Reply playback speed (resampled), on top of `playback_rate`
+    speed: f32,
+    /// Converts reply chunks that aren't at `PLAYBACK_SAMPLE_RATE`; kept
+    /// across the chunks of a reply so their joins line up
+    reply_converter: Option<SincResampler>,
+    /// A reply without a usable rate was already reported
+    rate_defaulted: bool,
 }
 
 impl AudioPlayer {
     /// Create a new audio player
     pub fn new(device: AudioDevice) -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Self { device })
+        let sink = device.playback_sink();
+        let device_rate = device.playback_rate();
+        if device_rate != PLAYBACK_SAMPLE_RATE {
+            eprintln!("[AudioPlayer] Speaker runs at {} Hz, converting replies from {} Hz", device_rate, PLAYBACK_SAMPLE_RATE);
+        }
+        let queue = Arc::new(Mutex::new(PlaybackQueue::new()));
+        let running = Arc::new(AtomicBool::new(true));
+        let error = Arc::new(Mutex::new(None));
+        let gain = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+        let drain = spawn_drain(Arc::clone(&queue), sink.clone(), device_rate, Arc::clone(&running), Arc::clone(&gain), Arc::clone(&error));
+        Ok(Self {
+            _device: device,
+            sink,
+            device_rate,
+            queue,
+            running,
+            drain,
+            error,
+            playback_chain: None,
+            playback_rate: 1.0,
+            last_level: 0.0,
+            gain,
+            volume: 1.0,
+            muted: false,
+            speed: 1.0,
+            reply_converter: None,
+            rate_defaulted: false,
+        })
+    }
+
+    /// Set the DSP chain applied to every buffer before playback
+    pub fn set_playback_chain(&mut self, chain: DspChain) {
+        self.playback_chain = Some(chain);
+    }
+
+    /// Mutable access to the playback chain (runtime stage toggles)
+    pub fn playback_chain_mut(&mut self) -> Option<&mut DspChain> {
+        self.playback_chain.as_mut()
+    }
+
+    /// Per-stage metrics of the playback chain
+    pub fn playback_metrics(&self) -> Vec<StageMetrics> {
+        self.playback_chain.as_ref().map(|c| c.metrics()).unwrap_or_default()
+    }
+
+    /// Time-stretch playback by `rate` (1.0 = off)
+    pub fn set_playback_rate(&mut self, rate: f32) {
+        self.playback_rate = rate.clamp(0.5, 2.0);
+    }
+
+    /// Reply volume, 0.0 to 1.0; takes effect on audio already queued
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+        self.update_gain();
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    /// Silence replies without losing the volume
+    pub fn mute(&mut self) {
+        self.muted = true;
+        self.update_gain();
+    }
+
+    pub fn unmute(&mut self) {
+        self.muted = false;
+        self.update_gain();
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    fn update_gain(&self) {
+        let gain = if self.muted { 0.0 } else { self.volume };
+        self.gain.store(gain.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Play replies `speed` times faster (0.75-1.5, 1.0 = off), for long
+    /// answers; applies to audio queued from now on
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Carry out a voice audio command; the reply says what changed
+    pub fn apply(&mut self, op: AudioOperation, portuguese: bool) -> String {
+        match op {
+            AudioOperation::VolumeUp => self.change_volume(self.volume + VOLUME_STEP, portuguese),
+            AudioOperation::VolumeDown => self.change_volume(self.volume - VOLUME_STEP, portuguese),
+            AudioOperation::SetVolume { percent } => self.change_volume(percent as f32 / 100.0, portuguese),
+            AudioOperation::Mute => {
+                self.mute();
+                if portuguese { "Som desativado." } else { "Muted." }.to_string()
+            }
+            AudioOperation::Unmute => {
+                self.unmute();
+                if portuguese { "Som reativado." } else { "Sound back on." }.to_string()
+            }
+            AudioOperation::SetSpeed { speed } => {
+                self.set_speed(speed);
+                if portuguese {
+                    format!("Respostas em {}x.", self.speed)
+                } else {
+                    format!("Playing replies at {}x.", self.speed)
+                }
+            }
+        }
+    }
+
+    /// Asking for a volume means wanting to hear it: unmutes too
+    fn change_volume(&mut self, volume: f32, portuguese: bool) -> String {
+        // Round away float drift from repeated steps
+        self.set_volume((volume * 100.0).round() / 100.0);
+        self.unmute();
+        let percent = (self.volume * 100.0).round() as u32;
+        if portuguese {
+            format!("Volume em {}%.", percent)
+        } else {
+            format!("Volume {}%.", percent)
+        }
+    }
+
+    fn apply_chain(&mut self, samples: &mut [f32]) {
+        if let Some(chain) = self.playback_chain.as_mut() {
+            chain.process(samples);
+        }
+        if !samples.is_empty() {
+            self.last_level = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        }
+    }
+
+    /// Drop reply audio that hasn't reached the speaker yet; the ~100 ms
+    /// already handed over still plays out
+    pub fn flush(&self) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.clear();
+        }
+    }
+
+    /// Cut the reply off (barge-in): queued and buffered audio is dropped
+    pub fn stop(&mut self) {
+        // Hold the queue so the playback thread can't refill the speaker in between
+        let Ok(mut queue) = self.queue.lock() else { return };
+        queue.clear();
+        self.sink.clear();
+    }
+
+    /// Whether queued reply audio is still playing
+    pub fn is_playing(&self) -> bool {
+        self.sink.pending() > 0 || self.queue.lock().map_or(false, |q| q.len() > 0)
+    }
+
+    /// Whether reply audio has reached the speaker (queued audio may still
+    /// be waiting for the playback thread)
+    pub fn is_audible(&self) -> bool {
+        self.sink.pending() > 0
+    }
+
+    /// Reply audio left to play, queued and in the speaker
+    pub fn queued_duration(&self) -> Duration {
+        self.depth().queued_duration()
+    }
+
+    /// A handle on `queued_duration` for the metrics sampler
+    pub fn depth(&self) -> PlaybackDepth {
+        PlaybackDepth { queue: self.queue.clone(), sink: self.sink.clone(), device_rate: self.device_rate }
     }
 
-    /// Play audio response from base64 encoded data
-    pub async fn play_response(&mut self, audio_data: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Decode base64
+    /// Speaker failure since the last call, if any
+    pub fn take_error(&self) -> Option<String> {
+        self.error.lock().ok().and_then(|mut e| e.take())
+    }
+
+    /// Level of the audio going out right now, 0.0 when silent
+    pub fn playback_level(&self) -> f32 {
+        if self.is_playing() {
+            self.last_level * f32::from_bits(self.gain.load(Ordering::Relaxed))
+        } else {
+            0.0
+        }
+    }
+
+    fn enqueue(&self, samples: Vec<f32>) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.push(samples);
+        }
+    }
+
+    /// Queue a base64-encoded PCM chunk of a reply (Gemini `inline_data`)
+    /// recorded at `rate` (`InlineData::sample_rate`); missing or unusable
+    /// rates are taken to be 24 kHz
+    ///
+    /// Returns at once, so it can be called for each chunk of a streamed
+    /// reply (`StreamEvent::Audio`) as it arrives.
+    pub fn enqueue_base64(&mut self, audio_data: &str, rate: Option<u32>) -> Result<(), Box<dyn std::error::Error>> {
         let audio_bytes = BASE64.decode(audio_data)?;
-        
-        // Convert bytes to f32 samples
-        let samples = self.bytes_to_samples(&audio_bytes);
-        
-        // Play through audio device
-        self.device.play(&samples).await?;
-        
+        let rate = self.reply_rate(rate);
+        self.enqueue_pcm_at(&audio_bytes, rate);
         Ok(())
     }
 
-    /// Play raw PCM audio bytes
-    pub async fn play_pcm(&mut self, audio_bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
-        let samples = self.bytes_to_samples(audio_bytes);
-        self.device.play(&samples).await?;
-        Ok(())
+    /// Queue raw 16-bit PCM bytes of a reply (EVA-Mind, 24 kHz)
+    pub fn enqueue_pcm(&mut self, audio_bytes: &[u8]) {
+        self.enqueue_pcm_at(audio_bytes, PLAYBACK_SAMPLE_RATE);
+    }
+
+    /// `rate` if it is one a reply could be at, else 24 kHz (reported once
+    /// until a usable rate comes along)
+    fn reply_rate(&mut self, rate: Option<u32>) -> u32 {
+        match rate.filter(|r| REPLY_RATES.contains(r)) {
+            Some(rate) => {
+                self.rate_defaulted = false;
+                rate
+            }
+            None => {
+                if !self.rate_defaulted {
+                    match rate {
+                        Some(rate) => eprintln!("[AudioPlayer] Unusable reply audio rate {} Hz, assuming {} Hz", rate, PLAYBACK_SAMPLE_RATE),
+                        None => eprintln!("[AudioPlayer] Reply audio without a rate, assuming {} Hz", PLAYBACK_SAMPLE_RATE),
+                    }
+                    self.rate_defaulted = true;
+                }
+                PLAYBACK_SAMPLE_RATE
+            }
+        }
+    }
+
+    /// Queue 16-bit PCM recorded at `rate`, converted to the queue's rate
+    fn enqueue_pcm_at(&mut self, audio_bytes: &[u8], rate: u32) {
+        let mut samples = self.bytes_to_samples(audio_bytes);
+        if rate != PLAYBACK_SAMPLE_RATE {
+            // A chunk arriving after the last one played out starts a new reply
+            let new_reply = !self.is_playing();
+            let converter = match self.reply_converter.take() {
+                Some(mut converter) if converter.converts(rate, PLAYBACK_SAMPLE_RATE) => {
+                    if new_reply {
+                        converter.reset();
+                    }
+                    converter
+                }
+                _ => SincResampler::new(rate, PLAYBACK_SAMPLE_RATE),
+            };
+            samples = self.reply_converter.insert(converter).process(&samples);
+        }
+        let stretched = time_stretch(&samples, self.playback_rate);
+        let mut samples = resample_speed(&stretched, self.speed);
+        self.apply_chain(&mut samples);
+        self.enqueue(samples);
+    }
+
+    /// Queue short UI sounds (earcons) as-is, without stretch or DSP (the
+    /// volume still applies)
+    pub fn enqueue_samples(&mut self, samples: &[f32]) {
+        self.enqueue(samples.to_vec());
     }
 
     /// Convert bytes to f32 samples (16-bit PCM)
@@ -62,15 +596,14 @@ impl AudioPlayer {
             })
             .collect()
     }
+}
 
-    /// Play text-to-speech (for testing without audio data)
-    pub async fn speak_text(&mut self, text: &str) -> Result<(), Box<dyn std::error::Error>> {
-        println!("🔊 EVA: {}", text);
-        
-        // TODO: In future, convert text to speech locally
-        // For now, just print the text
-        
-        Ok(())
+impl Drop for AudioPlayer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(drain) = self.drain.take() {
+            let _ = drain.join();
+        }
     }
 }
 
@@ -118,12 +651,170 @@ mod tests {
         assert!(samples.is_empty());
     }
 
-    #[tokio::test]
-    async fn test_speak_text() {
-        let device = AudioDevice::new().unwrap();
-        let mut player = AudioPlayer::new(device).unwrap();
-        
-        // Should not panic
-        player.speak_text("Hello, world!").await.unwrap();
+    #[test]
+    fn test_time_stretch_length_and_level() {
+        let tone: Vec<f32> = (0..24_000).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+
+        assert_eq!(time_stretch(&tone, 1.0), tone);
+        let slower = time_stretch(&tone, 0.8);
+        let faster = time_stretch(&tone, 1.25);
+        assert_eq!(slower.len(), 30_000);
+        assert_eq!(faster.len(), 19_200);
+
+        // Overlap-add is normalized: no clipping or level jump
+        let peak = |s: &[f32]| s.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+        assert!(peak(&faster) <= 0.55 && peak(&faster) > 0.3);
+    }
+
+    #[test]
+    fn test_gain_scales_and_never_clips() {
+        let mut samples = vec![0.5, -0.5, 1.0, -1.0, 0.0];
+        apply_gain(&mut samples, 0.5);
+        assert_eq!(samples, vec![0.25, -0.25, 0.5, -0.5, 0.0]);
+
+        // Gains are clamped: nothing is boosted past full scale
+        let mut samples = vec![0.8, -0.9];
+        apply_gain(&mut samples, 3.0);
+        assert_eq!(samples, vec![0.8, -0.9]);
+        apply_gain(&mut samples, -1.0);
+        assert_eq!(samples, vec![0.0, 0.0]);
+
+        // Out-of-range input comes out in range
+        let mut samples = vec![1.5, -1.2];
+        apply_gain(&mut samples, 1.0);
+        assert_eq!(samples, vec![1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_resample_speed_length_and_interpolation() {
+        let ramp: Vec<f32> = (0..1000).map(|i| i as f32 / 1000.0).collect();
+
+        assert_eq!(resample_speed(&ramp, 1.0), ramp);
+        let faster = resample_speed(&ramp, 1.5);
+        let slower = resample_speed(&ramp, 0.75);
+        assert_eq!(faster.len(), 666);
+        assert_eq!(slower.len(), 1333);
+
+        // A ramp stays a ramp, just steeper or shallower
+        assert!((faster[10] - 0.015).abs() < 1e-6);
+        assert!((slower[10] - 0.0075).abs() < 1e-6);
+        assert!(slower.windows(2).all(|w| w[1] >= w[0]));
+        assert!(resample_speed(&[0.5], 1.5) == vec![0.5]);
+    }
+
+    #[test]
+    fn test_volume_mute_and_speed_commands() {
+        let mut player = AudioPlayer::new(AudioDevice::new().unwrap()).unwrap();
+        let gain = |player: &AudioPlayer| f32::from_bits(player.gain.load(Ordering::Relaxed));
+
+        assert_eq!(player.apply(AudioOperation::VolumeDown, false), "Volume 90%.");
+        assert_eq!(player.apply(AudioOperation::VolumeDown, true), "Volume em 80%.");
+        assert_eq!(gain(&player), 0.8);
+        assert_eq!(player.apply(AudioOperation::SetVolume { percent: 100 }, false), "Volume 100%.");
+        assert_eq!(player.apply(AudioOperation::VolumeUp, false), "Volume 100%.");
+
+        player.apply(AudioOperation::Mute, false);
+        assert!(player.is_muted());
+        assert_eq!(gain(&player), 0.0);
+        assert_eq!(player.volume(), 1.0);
+        player.apply(AudioOperation::VolumeDown, false);
+        assert!(!player.is_muted());
+        assert_eq!(gain(&player), 0.9);
+
+        assert_eq!(player.apply(AudioOperation::SetSpeed { speed: 3.0 }, false), "Playing replies at 1.5x.");
+        player.set_speed(0.1);
+        assert_eq!(player.speed(), MIN_SPEED);
+    }
+
+    fn drain_all(queue: &mut PlaybackQueue) -> Vec<f32> {
+        let mut out: Vec<f32> = std::iter::from_fn(|| queue.pop(usize::MAX)).flatten().collect();
+        out.extend(queue.release_tail().unwrap_or_default());
+        out
+    }
+
+    #[test]
+    fn test_queue_keeps_order_and_joins_continuous_chunks() {
+        let ramp: Vec<f32> = (0..3000).map(|i| i as f32 / 10_000.0).collect();
+        let mut queue = PlaybackQueue::new();
+        for chunk in ramp.chunks(1000) {
+            queue.push(chunk.to_vec());
+        }
+        assert_eq!(queue.len(), 3000);
+
+        // Only the fade-in and fade-out touch the samples
+        let out = drain_all(&mut queue);
+        assert_eq!(out.len(), 3000);
+        assert_eq!(out[CROSSFADE..3000 - CROSSFADE], ramp[CROSSFADE..3000 - CROSSFADE]);
+        assert!(out[0].abs() < 1e-3 && out[2999].abs() < 0.01);
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn test_queue_crossfades_discontinuities() {
+        let mut queue = PlaybackQueue::new();
+        queue.push(vec![0.5; 1000]);
+        queue.push(vec![-0.5; 1000]);
+
+        // One crossfade's worth of overlap, and no sample-to-sample jump
+        let out = drain_all(&mut queue);
+        assert_eq!(out.len(), 2000 - CROSSFADE);
+        let max_step = out.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0f32, f32::max);
+        assert!(max_step < 0.01, "step {}", max_step);
+    }
+
+    #[test]
+    fn test_queue_pop_splits_and_clear_empties() {
+        let mut queue = PlaybackQueue::new();
+        queue.push(vec![0.1; 500]);
+        queue.push(vec![0.1; 500]);
+        assert_eq!(queue.pop(100).map(|c| c.len()), Some(100));
+        assert_eq!(queue.len(), 900);
+
+        queue.clear();
+        assert_eq!(queue.len(), 0);
+        assert!(queue.pop(usize::MAX).is_none());
+        assert!(queue.release_tail().is_none());
+    }
+
+    #[test]
+    fn test_flush_drops_pending_audio() {
+        let mut player = AudioPlayer::new(AudioDevice::new().unwrap()).unwrap();
+        player.enqueue_pcm(&[0x00, 0x40].repeat(PLAYBACK_SAMPLE_RATE as usize));
+        player.enqueue_samples(&[0.25; 480]);
+        player.flush();
+        assert_eq!(player.queue.lock().unwrap().len(), 0);
+
+        player.enqueue_samples(&[0.25; 480]);
+        player.stop();
+        assert!(!player.is_playing());
+        assert_eq!(player.queued_duration(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_reply_chunks_converted_to_queue_rate() {
+        let mut player = AudioPlayer::new(AudioDevice::new().unwrap()).unwrap();
+        // Nothing drains the queue while it is measured
+        player.running.store(false, Ordering::Relaxed);
+        player.drain.take().unwrap().join().unwrap();
+        let pcm = |samples: usize| BASE64.encode([0x00, 0x10].repeat(samples));
+
+        // 100 ms at 48 kHz in two chunks: ~100 ms at 24 kHz, one converter
+        player.enqueue_base64(&pcm(2400), Some(48_000)).unwrap();
+        player.enqueue_base64(&pcm(2400), Some(48_000)).unwrap();
+        assert!(player.reply_converter.as_ref().unwrap().converts(48_000, PLAYBACK_SAMPLE_RATE));
+        let queued = player.queue.lock().unwrap().len();
+        assert!((2350..=2400).contains(&queued), "{} samples", queued);
+
+        // No rate, or a nonsense one: taken as 24 kHz
+        player.flush();
+        player.enqueue_base64(&pcm(2400), None).unwrap();
+        assert!(player.rate_defaulted);
+        player.enqueue_base64(&pcm(2400), Some(7)).unwrap();
+        assert_eq!(player.queue.lock().unwrap().len(), 4800);
+        player.enqueue_base64(&pcm(2400), Some(PLAYBACK_SAMPLE_RATE)).unwrap();
+        assert!(!player.rate_defaulted);
+
+        assert_eq!(at_playback_rate(4800, 48_000), 2400);
+        assert_eq!(at_playback_rate(4410, 44_100), 2400);
     }
 }
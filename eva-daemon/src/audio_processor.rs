@@ -0,0 +1,579 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// A single DSP stage operating in place on a mono f32 frame
+pub trait DspStage: Send {
+    /// Process one frame in place
+    fn process(&mut self, frame: &mut [f32]);
+    /// Stage name (used for runtime toggles and metrics)
+    fn name(&self) -> &'static str;
+    /// Clear any internal state (e.g. between utterances)
+    fn reset(&mut self);
+}
+
+/// Stage configuration: which stage, its parameters, and whether it starts enabled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum StageConfig {
+    /// Remove DC offset with a one-pole high-pass filter
+    DcBlock { enabled: bool, pole: f32 },
+    /// Attenuate frames whose RMS is below the threshold
+    NoiseGate { enabled: bool, threshold: f32, attenuation: f32 },
+    /// Automatic gain control towards a target RMS
+    Agc { enabled: bool, target_rms: f32, max_gain: f32, smoothing: f32 },
+    /// Hard ceiling to prevent clipping
+    Limiter { enabled: bool, ceiling: f32 },
+}
+
+impl StageConfig {
+    fn build(&self) -> (Box<dyn DspStage>, bool) {
+        match *self {
+            StageConfig::DcBlock { enabled, pole } => (Box::new(DcBlock::new(pole)), enabled),
+            StageConfig::NoiseGate { enabled, threshold, attenuation } => {
+                (Box::new(NoiseGate::new(threshold, attenuation)), enabled)
+            }
+            StageConfig::Agc { enabled, target_rms, max_gain, smoothing } => {
+                (Box::new(Agc::new(target_rms, max_gain, smoothing)), enabled)
+            }
+            StageConfig::Limiter { enabled, ceiling } => (Box::new(Limiter::new(ceiling)), enabled),
+        }
+    }
+
+    /// Name of the stage this builds, as `DspChain::set_enabled` takes it
+    pub fn name(&self) -> &'static str {
+        match self {
+            StageConfig::DcBlock { .. } => "dc_block",
+            StageConfig::NoiseGate { .. } => "noise_gate",
+            StageConfig::Agc { .. } => "agc",
+            StageConfig::Limiter { .. } => "limiter",
+        }
+    }
+
+    /// Whether the stage starts enabled
+    pub fn enabled(&self) -> bool {
+        match *self {
+            StageConfig::DcBlock { enabled, .. }
+            | StageConfig::NoiseGate { enabled, .. }
+            | StageConfig::Agc { enabled, .. }
+            | StageConfig::Limiter { enabled, .. } => enabled,
+        }
+    }
+
+    fn enabled_mut(&mut self) -> &mut bool {
+        match self {
+            StageConfig::DcBlock { enabled, .. }
+            | StageConfig::NoiseGate { enabled, .. }
+            | StageConfig::Agc { enabled, .. }
+            | StageConfig::Limiter { enabled, .. } => enabled,
+        }
+    }
+}
+
+/// Which of the two chains a stage belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainKind {
+    Capture,
+    Playback,
+}
+
+impl ChainKind {
+    pub const ALL: [ChainKind; 2] = [ChainKind::Capture, ChainKind::Playback];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "capture" => Some(ChainKind::Capture),
+            "playback" => Some(ChainKind::Playback),
+            _ => None,
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            ChainKind::Capture => "capture",
+            ChainKind::Playback => "playback",
+        }
+    }
+}
+
+/// Capture and playback chain configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioProcessorConfig {
+    pub capture: Vec<StageConfig>,
+    pub playback: Vec<StageConfig>,
+}
+
+impl Default for AudioProcessorConfig {
+    fn default() -> Self {
+        Self {
+            capture: vec![
+                StageConfig::DcBlock { enabled: true, pole: 0.995 },
+                StageConfig::NoiseGate { enabled: true, threshold: 0.01, attenuation: 0.1 },
+                StageConfig::Agc { enabled: true, target_rms: 0.1, max_gain: 10.0, smoothing: 0.5 },
+                StageConfig::Limiter { enabled: true, ceiling: 0.99 },
+            ],
+            playback: vec![
+                StageConfig::Limiter { enabled: true, ceiling: 0.95 },
+            ],
+        }
+    }
+}
+
+impl AudioProcessorConfig {
+    /// Load chain configuration from audio_processor.json in the data directory (defaults if missing)
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::get_config_path()?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Save to audio_processor.json in the data directory
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.save_to(&Self::get_config_path()?)
+    }
+
+    /// Write the configuration to `path`
+    pub fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, json)?;
+        fs::rename(&partial, path)?;
+        Ok(())
+    }
+
+    pub fn chain(&self, kind: ChainKind) -> &[StageConfig] {
+        match kind {
+            ChainKind::Capture => &self.capture,
+            ChainKind::Playback => &self.playback,
+        }
+    }
+
+    /// Set whether `name` starts enabled in `kind`; false if the chain has no such stage
+    pub fn set_enabled(&mut self, kind: ChainKind, name: &str, enabled: bool) -> bool {
+        let chain = match kind {
+            ChainKind::Capture => &mut self.capture,
+            ChainKind::Playback => &mut self.playback,
+        };
+        let mut found = false;
+        for stage in chain.iter_mut().filter(|s| s.name() == name) {
+            *stage.enabled_mut() = enabled;
+            found = true;
+        }
+        found
+    }
+
+    /// Get config file path
+    fn get_config_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        crate::paths::data_file("audio_processor.json")
+    }
+}
+
+/// Per-stage timing metrics
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageMetrics {
+    pub name: &'static str,
+    pub enabled: bool,
+    pub frames: u64,
+    pub total_time: Duration,
+}
+
+impl StageMetrics {
+    /// Average processing time per frame in microseconds
+    pub fn avg_micros(&self) -> f64 {
+        if self.frames == 0 {
+            return 0.0;
+        }
+        self.total_time.as_secs_f64() * 1_000_000.0 / self.frames as f64
+    }
+}
+
+struct ChainEntry {
+    stage: Box<dyn DspStage>,
+    enabled: bool,
+    frames: u64,
+    total_time: Duration,
+}
+
+/// Ordered chain of DSP stages with per-stage bypass
+pub struct DspChain {
+    entries: Vec<ChainEntry>,
+}
+
+impl DspChain {
+    /// Build a chain from stage configs (order is preserved)
+    pub fn from_config(stages: &[StageConfig]) -> Self {
+        let entries = stages
+            .iter()
+            .map(|cfg| {
+                let (stage, enabled) = cfg.build();
+                ChainEntry { stage, enabled, frames: 0, total_time: Duration::ZERO }
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// Run a frame through every enabled stage
+    pub fn process(&mut self, frame: &mut [f32]) {
+        for entry in self.entries.iter_mut().filter(|e| e.enabled) {
+            let start = Instant::now();
+            entry.stage.process(frame);
+            entry.total_time += start.elapsed();
+            entry.frames += 1;
+        }
+    }
+
+    /// Enable or bypass a stage by name; returns false if no such stage
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for entry in self.entries.iter_mut().filter(|e| e.stage.name() == name) {
+            entry.enabled = enabled;
+            found = true;
+        }
+        found
+    }
+
+    /// Check whether a stage is enabled
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.entries.iter().any(|e| e.stage.name() == name && e.enabled)
+    }
+
+    /// Stage names in processing order
+    pub fn stage_names(&self) -> Vec<&'static str> {
+        self.entries.iter().map(|e| e.stage.name()).collect()
+    }
+
+    /// Reset all stages
+    pub fn reset(&mut self) {
+        for entry in &mut self.entries {
+            entry.stage.reset();
+        }
+    }
+
+    /// Per-stage timing metrics
+    pub fn metrics(&self) -> Vec<StageMetrics> {
+        self.entries
+            .iter()
+            .map(|e| StageMetrics {
+                name: e.stage.name(),
+                enabled: e.enabled,
+                frames: e.frames,
+                total_time: e.total_time,
+            })
+            .collect()
+    }
+}
+
+/// Audio processor with separate capture and playback chains
+pub struct AudioProcessor {
+    pub capture: DspChain,
+    pub playback: DspChain,
+}
+
+impl AudioProcessor {
+    /// Create a processor with the default chains
+    pub fn new() -> Self {
+        Self::from_config(&AudioProcessorConfig::default())
+    }
+
+    /// Create a processor from configuration
+    pub fn from_config(config: &AudioProcessorConfig) -> Self {
+        Self {
+            capture: DspChain::from_config(&config.capture),
+            playback: DspChain::from_config(&config.playback),
+        }
+    }
+}
+
+impl Default for AudioProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Root-mean-square of a frame
+pub fn rms(frame: &[f32]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt()
+}
+
+// ============================================================
+// Stages
+// ============================================================
+
+/// One-pole DC blocking filter: y[n] = x[n] - x[n-1] + pole * y[n-1]
+pub struct DcBlock {
+    pole: f32,
+    prev_in: f32,
+    prev_out: f32,
+}
+
+impl DcBlock {
+    pub fn new(pole: f32) -> Self {
+        Self { pole: pole.clamp(0.0, 0.9999), prev_in: 0.0, prev_out: 0.0 }
+    }
+}
+
+impl DspStage for DcBlock {
+    fn process(&mut self, frame: &mut [f32]) {
+        for s in frame.iter_mut() {
+            let out = *s - self.prev_in + self.pole * self.prev_out;
+            self.prev_in = *s;
+            self.prev_out = out;
+            *s = out;
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "dc_block"
+    }
+
+    fn reset(&mut self) {
+        self.prev_in = 0.0;
+        self.prev_out = 0.0;
+    }
+}
+
+/// Frame-level noise gate
+pub struct NoiseGate {
+    threshold: f32,
+    attenuation: f32,
+}
+
+impl NoiseGate {
+    pub fn new(threshold: f32, attenuation: f32) -> Self {
+        Self { threshold: threshold.max(0.0), attenuation: attenuation.clamp(0.0, 1.0) }
+    }
+}
+
+impl DspStage for NoiseGate {
+    fn process(&mut self, frame: &mut [f32]) {
+        if rms(frame) < self.threshold {
+            for s in frame.iter_mut() {
+                *s *= self.attenuation;
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "noise_gate"
+    }
+
+    fn reset(&mut self) {}
+}
+
+/// Automatic gain control with smoothed per-frame gain
+pub struct Agc {
+    target_rms: f32,
+    max_gain: f32,
+    smoothing: f32,
+    gain: f32,
+}
+
+impl Agc {
+    pub fn new(target_rms: f32, max_gain: f32, smoothing: f32) -> Self {
+        Self {
+            target_rms: target_rms.max(0.0),
+            max_gain: max_gain.max(1.0),
+            smoothing: smoothing.clamp(0.0, 1.0),
+            gain: 1.0,
+        }
+    }
+}
+
+impl DspStage for Agc {
+    fn process(&mut self, frame: &mut [f32]) {
+        let level = rms(frame);
+        if level > 1e-6 {
+            let wanted = (self.target_rms / level).min(self.max_gain);
+            self.gain = self.smoothing * self.gain + (1.0 - self.smoothing) * wanted;
+        }
+        for s in frame.iter_mut() {
+            *s *= self.gain;
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "agc"
+    }
+
+    fn reset(&mut self) {
+        self.gain = 1.0;
+    }
+}
+
+/// Hard limiter
+pub struct Limiter {
+    ceiling: f32,
+}
+
+impl Limiter {
+    pub fn new(ceiling: f32) -> Self {
+        Self { ceiling: ceiling.clamp(0.0, 1.0) }
+    }
+}
+
+impl DspStage for Limiter {
+    fn process(&mut self, frame: &mut [f32]) {
+        for s in frame.iter_mut() {
+            *s = s.clamp(-self.ceiling, self.ceiling);
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "limiter"
+    }
+
+    fn reset(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(amplitude: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16000.0).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_agc_reaches_target_rms() {
+        let mut chain = DspChain::from_config(&[StageConfig::Agc {
+            enabled: true,
+            target_rms: 0.1,
+            max_gain: 20.0,
+            smoothing: 0.5,
+        }]);
+
+        let mut frame = sine(0.02, 1600);
+        for _ in 0..20 {
+            frame = sine(0.02, 1600);
+            chain.process(&mut frame);
+        }
+
+        assert!((rms(&frame) - 0.1).abs() < 0.005, "rms after AGC = {}", rms(&frame));
+    }
+
+    #[test]
+    fn test_agc_respects_max_gain() {
+        let mut chain = DspChain::from_config(&[StageConfig::Agc {
+            enabled: true,
+            target_rms: 0.1,
+            max_gain: 2.0,
+            smoothing: 0.0,
+        }]);
+
+        let mut frame = sine(0.001, 1600);
+        chain.process(&mut frame);
+        let expected = rms(&sine(0.001, 1600)) * 2.0;
+        assert!((rms(&frame) - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_noise_gate_attenuation() {
+        let mut chain = DspChain::from_config(&[StageConfig::NoiseGate {
+            enabled: true,
+            threshold: 0.05,
+            attenuation: 0.1,
+        }]);
+
+        // Quiet frame gets attenuated by exactly the configured factor
+        let mut quiet = sine(0.01, 1600);
+        let before = rms(&quiet);
+        chain.process(&mut quiet);
+        assert!((rms(&quiet) - before * 0.1).abs() < 1e-5);
+
+        // Loud frame passes untouched
+        let mut loud = sine(0.5, 1600);
+        let before = rms(&loud);
+        chain.process(&mut loud);
+        assert!((rms(&loud) - before).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_dc_block_removes_offset() {
+        let mut chain = DspChain::from_config(&[StageConfig::DcBlock { enabled: true, pole: 0.995 }]);
+        let mut frame: Vec<f32> = sine(0.1, 16000).iter().map(|s| s + 0.3).collect();
+        chain.process(&mut frame);
+
+        let tail = &frame[8000..];
+        let mean: f32 = tail.iter().sum::<f32>() / tail.len() as f32;
+        assert!(mean.abs() < 0.01, "residual DC = {}", mean);
+    }
+
+    #[test]
+    fn test_bypass_and_ordering() {
+        let config = vec![
+            StageConfig::Agc { enabled: true, target_rms: 0.5, max_gain: 100.0, smoothing: 0.0 },
+            StageConfig::Limiter { enabled: true, ceiling: 0.3 },
+        ];
+        let mut chain = DspChain::from_config(&config);
+        assert_eq!(chain.stage_names(), vec!["agc", "limiter"]);
+
+        let mut frame = sine(0.05, 1600);
+        chain.process(&mut frame);
+        assert!(frame.iter().all(|s| s.abs() <= 0.3));
+
+        // Bypassing the limiter lets AGC output through unclipped
+        assert!(chain.set_enabled("limiter", false));
+        assert!(!chain.is_enabled("limiter"));
+        let mut frame = sine(0.05, 1600);
+        chain.process(&mut frame);
+        assert!(frame.iter().any(|s| s.abs() > 0.3));
+
+        assert!(!chain.set_enabled("echo_cancel", true));
+    }
+
+    #[test]
+    fn test_metrics_count_enabled_frames() {
+        let mut processor = AudioProcessor::new();
+        processor.capture.set_enabled("agc", false);
+
+        let mut frame = sine(0.1, 1600);
+        processor.capture.process(&mut frame);
+        processor.capture.process(&mut frame);
+
+        let metrics = processor.capture.metrics();
+        let agc = metrics.iter().find(|m| m.name == "agc").unwrap();
+        let gate = metrics.iter().find(|m| m.name == "noise_gate").unwrap();
+        assert_eq!(agc.frames, 0);
+        assert_eq!(gate.frames, 2);
+    }
+
+    #[test]
+    fn test_config_serialization() {
+        let config = AudioProcessorConfig::default();
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(json.contains(r#""stage":"noise_gate""#));
+        let parsed: AudioProcessorConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.capture, config.capture);
+    }
+
+    #[test]
+    fn test_toggle_is_saved_and_rebuilt_bypassed() {
+        let mut config = AudioProcessorConfig::default();
+        assert!(config.set_enabled(ChainKind::Capture, "noise_gate", false));
+        assert!(!config.set_enabled(ChainKind::Playback, "agc", false));
+        assert!(config.chain(ChainKind::Playback).iter().all(StageConfig::enabled));
+
+        let path = std::env::temp_dir().join(format!("eva_dsp_{}.json", std::process::id()));
+        config.save_to(&path).unwrap();
+        let loaded: AudioProcessorConfig = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_file(&path).ok();
+
+        let chain = DspChain::from_config(&loaded.capture);
+        assert!(!chain.is_enabled("noise_gate"));
+        assert!(chain.is_enabled("agc"));
+    }
+}
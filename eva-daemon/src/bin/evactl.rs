@@ -0,0 +1,154 @@
+//! evactl - query and command a running eva-daemon over its control socket
+//!
+//! ```text
+//! evactl status
+//! evactl say <text>
+//! evactl ask <text>
+//! evactl timemachine search <query> [--limit N]
+//! evactl get <path>
+//! evactl set <path> <value>
+//! evactl webhooks
+//! evactl export <file> [--audio]
+//! evactl import <file>
+//! evactl shutdown
+//! ```
+//!
+//! `get` and `set` take the paths of the `eva:` files on Redox
+//! (`config/wake_sensitivity`, `config/language`, `config/dsp`, `status`,
+//! `session/context`). `export` writes the session as JSON if the file
+//! name ends in `.json` (with turn audio if `--audio`), as Markdown
+//! otherwise; `import` reads such a JSON file back.
+//!
+//! The result is printed as JSON; a refused or failed request exits with 1.
+
+#[allow(dead_code)]
+#[path = "../control.rs"]
+mod control;
+#[allow(dead_code)]
+#[path = "../paths.rs"]
+mod paths;
+
+use control::Command;
+use std::process::ExitCode;
+
+const USAGE: &str =
+    "usage: evactl status | say <text> | ask <text> | timemachine search <query> [--limit N] | timemachine timeline [YYYY-MM-DD] | timemachine thumbnail <id> | get <path> | set <path> <value> | webhooks | export <file> [--audio] | import <file> | shutdown";
+
+/// The command `args` (program name excluded) spell out
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let rest = |from: usize| args.get(from..).unwrap_or_default().join(" ");
+    let command = match args.first().map(String::as_str) {
+        Some("status") if args.len() == 1 => Command::Status,
+        Some("shutdown") if args.len() == 1 => Command::Shutdown,
+        Some("webhooks") if args.len() == 1 => Command::Webhooks,
+        Some("say") => Command::Say { text: rest(1) },
+        Some("ask") => Command::Ask { text: rest(1) },
+        Some("get") if args.len() == 2 => Command::Get { path: args[1].clone() },
+        Some("set") if args.len() >= 3 => Command::Set { path: args[1].clone(), value: rest(2) },
+        Some("export") if args.len() == 2 || (args.len() == 3 && args[2] == "--audio") => {
+            let format = if args[1].to_ascii_lowercase().ends_with(".json") { "json" } else { "markdown" };
+            Command::Export { path: absolute(&args[1])?, format: format.to_string(), audio: args.len() == 3 }
+        }
+        Some("import") if args.len() == 2 => Command::Import { path: absolute(&args[1])? },
+        Some("timemachine") if args.get(1).map(String::as_str) == Some("search") => {
+            let mut words = args[2..].to_vec();
+            let mut limit = None;
+            if let Some(at) = words.iter().position(|w| w == "--limit") {
+                let value = words.get(at + 1).ok_or("--limit needs a number")?;
+                limit = Some(value.parse().map_err(|_| format!("--limit needs a number, not '{}'", value))?);
+                words.drain(at..at + 2);
+            }
+            Command::TimeMachineSearch { query: words.join(" "), limit }
+        }
+        Some("timemachine") if args.get(1).map(String::as_str) == Some("timeline") && args.len() <= 3 => {
+            Command::TimeMachineTimeline { date: args.get(2).cloned() }
+        }
+        Some("timemachine") if args.get(1).map(String::as_str) == Some("thumbnail") && args.len() == 3 => {
+            let id = args[2].parse().map_err(|_| format!("not a capture id: '{}'", args[2]))?;
+            Command::TimeMachineThumbnail { id }
+        }
+        _ => return Err(USAGE.to_string()),
+    };
+    match &command {
+        Command::Say { text } | Command::Ask { text } | Command::TimeMachineSearch { query: text, .. } if text.trim().is_empty() => {
+            Err(USAGE.to_string())
+        }
+        _ => Ok(command),
+    }
+}
+
+/// `path` as the daemon, which runs elsewhere, must be given it
+fn absolute(path: &str) -> Result<String, String> {
+    std::path::absolute(path).map(|p| p.display().to_string()).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match parse_args(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    match control::send(command).await {
+        Ok(response) if response.ok => {
+            if !response.result.is_null() {
+                println!("{}", serde_json::to_string_pretty(&response.result).unwrap_or_default());
+            }
+            ExitCode::SUCCESS
+        }
+        Ok(response) => {
+            eprintln!("evactl: {}", response.error.unwrap_or_else(|| "request failed".to_string()));
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("evactl: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Command, String> {
+        parse_args(&line.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse("status"), Ok(Command::Status));
+        assert_eq!(parse("say good morning"), Ok(Command::Say { text: "good morning".to_string() }));
+        assert_eq!(parse("ask what time is it"), Ok(Command::Ask { text: "what time is it".to_string() }));
+        assert_eq!(
+            parse("timemachine search quarterly --limit 3 budget"),
+            Ok(Command::TimeMachineSearch { query: "quarterly budget".to_string(), limit: Some(3) })
+        );
+        assert_eq!(parse("timemachine timeline"), Ok(Command::TimeMachineTimeline { date: None }));
+        assert_eq!(parse("timemachine timeline 2026-03-09"), Ok(Command::TimeMachineTimeline { date: Some("2026-03-09".to_string()) }));
+        assert_eq!(parse("timemachine thumbnail 42"), Ok(Command::TimeMachineThumbnail { id: 42 }));
+        assert_eq!(parse("shutdown"), Ok(Command::Shutdown));
+        assert_eq!(parse("webhooks"), Ok(Command::Webhooks));
+        assert_eq!(parse("get config/language"), Ok(Command::Get { path: "config/language".to_string() }));
+        assert_eq!(
+            parse("set session/context room=kitchen"),
+            Ok(Command::Set { path: "session/context".to_string(), value: "room=kitchen".to_string() })
+        );
+
+        assert!(matches!(parse("export Session.JSON --audio"), Ok(Command::Export { format, audio: true, .. }) if format == "json"));
+        assert!(matches!(parse("export notes.md"), Ok(Command::Export { format, audio: false, .. }) if format == "markdown"));
+        assert!(matches!(parse("import session.json"), Ok(Command::Import { path }) if std::path::Path::new(&path).is_absolute()));
+
+        assert!(parse("").is_err());
+        assert!(parse("export a.json --video").is_err());
+        assert!(parse("say").is_err());
+        assert!(parse("status now").is_err());
+        assert!(parse("set config/language").is_err());
+        assert!(parse("timemachine search budget --limit many").is_err());
+        assert!(parse("timemachine thumbnail latest").is_err());
+    }
+}
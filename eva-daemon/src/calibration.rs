@@ -0,0 +1,378 @@
+//! `eva-daemon --calibrate`: tune the wake word and VAD to one microphone
+//!
+//! The wizard listens to the room for `AMBIENT_SECS`, asks for the wake
+//! phrase `WAKE_TAKES` times and for a few ordinary sentences, scores every
+//! recording with the same detector the daemon uses, and writes the
+//! recommended sensitivity and `VadTuning` to the profile.
+//!
+//! Nothing is written until every step is done, and the profile is
+//! replaced in one rename, so Ctrl-C at any point leaves it as it was.
+//!
+//! `eva-daemon --enroll-wake-word` records the phrase `WAKE_TAKES` times
+//! and writes the template the daemon matches it against
+//! (`wake_word_template.json`), in place of the generic envelope match.
+
+use crate::audio::{AudioDevice, CaptureSource, CHUNK_SIZE};
+use crate::resample::Decimator;
+use crate::user_profile::UserProfile;
+use crate::vad::{VadConfig, VadTuning, VAD};
+use crate::wake_word::{WakeWordDetector, WakeWordTemplate};
+
+/// Background noise measured before anything is said
+pub const AMBIENT_SECS: u32 = 5;
+/// Times the wake phrase is recorded
+pub const WAKE_TAKES: usize = 5;
+/// Sentences that must not wake EVA
+pub const OTHER_SENTENCES: [&str; 3] =
+    ["What's the weather like tomorrow?", "Please remind me to call my sister.", "I'm going to make some coffee."];
+
+/// Recording window for one wake phrase and one sentence
+const TAKE_SECS: u32 = 3;
+const SENTENCE_SECS: u32 = 4;
+/// Attempts at a take before giving up on the microphone
+const TAKE_ATTEMPTS: usize = 3;
+
+/// A chunk counts as speech this far above the background
+const VOICED_FACTOR: f32 = 2.0;
+/// Bounds on the attack ratio, so an odd measurement can't make the VAD
+/// trigger on breathing or ignore normal speech
+const MIN_RATIO: f32 = 2.0;
+const MAX_RATIO: f32 = 8.0;
+/// Bounds on the recommended threshold (1.0 - sensitivity)
+const MIN_THRESHOLD: f32 = 0.05;
+const MAX_THRESHOLD: f32 = 0.95;
+/// Room left above the loudest other sentence when takes and sentences overlap
+const FALSE_ALARM_MARGIN: f32 = 0.02;
+
+/// RMS of each 100 ms chunk
+pub fn chunk_levels(samples: &[f32]) -> Vec<f32> {
+    samples.chunks(CHUNK_SIZE).map(|c| (c.iter().map(|s| s * s).sum::<f32>() / c.len() as f32).sqrt()).collect()
+}
+
+/// Value below which `fraction` of `values` fall; 0.0 when empty
+fn percentile(values: &[f32], fraction: f32) -> f32 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let index = ((sorted.len() as f32 - 1.0) * fraction).round().max(0.0) as usize;
+    sorted.get(index).copied().unwrap_or(0.0)
+}
+
+/// What the wizard heard, before any recommendation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Measurements {
+    /// Chunk levels of the quiet room
+    pub ambient: Vec<f32>,
+    /// Chunk levels of every wake take, for the speech level
+    pub speech: Vec<f32>,
+    /// Peak detector score of each wake take
+    pub wake_scores: Vec<f32>,
+    /// Peak detector score of each other sentence
+    pub other_scores: Vec<f32>,
+}
+
+impl Measurements {
+    /// Median background level, never below the VAD's own minimum
+    pub fn noise(&self) -> f32 {
+        percentile(&self.ambient, 0.5).max(VadConfig::default().min_floor)
+    }
+
+    /// Median level of the voiced chunks of the wake takes
+    pub fn speech_level(&self) -> f32 {
+        let noise = self.noise();
+        let voiced: Vec<f32> = self.speech.iter().copied().filter(|&l| l > noise * VOICED_FACTOR).collect();
+        percentile(&voiced, 0.5)
+    }
+}
+
+/// Settings to write, and how they do on the recordings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Recommendation {
+    pub sensitivity: f32,
+    pub vad: VadTuning,
+    /// Wake takes detected at the new sensitivity
+    pub hits: usize,
+    /// Other sentences detected at the new sensitivity
+    pub false_alarms: usize,
+}
+
+impl Recommendation {
+    pub fn from_measurements(measured: &Measurements) -> Self {
+        let threshold = recommend_threshold(&measured.wake_scores, &measured.other_scores);
+        Self {
+            sensitivity: 1.0 - threshold,
+            vad: recommend_vad(measured.noise(), measured.speech_level()),
+            hits: measured.wake_scores.iter().filter(|&&s| s > threshold).count(),
+            false_alarms: measured.other_scores.iter().filter(|&&s| s > threshold).count(),
+        }
+    }
+}
+
+/// Attack level halfway (geometrically) between the room and the voice
+pub fn recommend_vad(noise: f32, speech: f32) -> VadTuning {
+    let ratio = if speech > noise { (speech / noise).sqrt().clamp(MIN_RATIO, MAX_RATIO) } else { MIN_RATIO };
+    VadTuning { noise_floor: noise, ratio, energy_threshold: (noise * ratio).min(VAD::new().energy_threshold()) }
+}
+
+/// Threshold halfway between the weakest wake take and the strongest
+/// other sentence
+///
+/// When they overlap, the threshold just above the other sentences is
+/// used as long as it still catches most takes; past that, missing the
+/// wake word is worse than the odd false wake, and the median take decides.
+pub fn recommend_threshold(wake: &[f32], other: &[f32]) -> f32 {
+    let weakest_wake = wake.iter().copied().fold(f32::INFINITY, f32::min);
+    let loudest_other = other.iter().copied().fold(0.0, f32::max);
+    let threshold = if weakest_wake > loudest_other {
+        (weakest_wake + loudest_other) / 2.0
+    } else {
+        let above_others = loudest_other + FALSE_ALARM_MARGIN;
+        if wake.iter().filter(|&&s| s > above_others).count() * 2 > wake.len() {
+            above_others
+        } else {
+            percentile(wake, 0.5) - FALSE_ALARM_MARGIN
+        }
+    };
+    threshold.clamp(MIN_THRESHOLD, MAX_THRESHOLD)
+}
+
+/// Results as printed at the end of the wizard
+pub fn summary_table(phrase: &str, measured: &Measurements, current_sensitivity: f32, recommended: &Recommendation) -> String {
+    let threshold = 1.0 - recommended.sensitivity;
+    let mark = |score: f32, wanted: bool| if (score > threshold) == wanted { "ok" } else { "MISS" };
+    let mut rows = vec![
+        ("Background noise".to_string(), format!("{:.4} RMS", measured.noise())),
+        ("Speech level".to_string(), format!("{:.4} RMS", measured.speech_level())),
+        (
+            "VAD start level".to_string(),
+            format!("{:.4} RMS (ratio {:.1})", recommended.vad.noise_floor * recommended.vad.ratio, recommended.vad.ratio),
+        ),
+    ];
+    for (i, &score) in measured.wake_scores.iter().enumerate() {
+        rows.push((format!("\"{}\" take {}", phrase, i + 1), format!("{:.2}  {}", score, mark(score, true))));
+    }
+    for (i, &score) in measured.other_scores.iter().enumerate() {
+        rows.push((format!("Other sentence {}", i + 1), format!("{:.2}  {}", score, mark(score, false))));
+    }
+    rows.push(("Sensitivity".to_string(), format!("{:.2} -> {:.2}", current_sensitivity, recommended.sensitivity)));
+
+    let width = rows.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+    let mut table: String = rows.iter().map(|(label, value)| format!("  {:<width$}  {}\n", label, value, width = width)).collect();
+    table.push_str(&format!(
+        "  Detected {}/{} takes, {}/{} false wakes at the new setting\n",
+        recommended.hits,
+        measured.wake_scores.len(),
+        recommended.false_alarms,
+        measured.other_scores.len()
+    ));
+    table
+}
+
+/// Microphone audio at the pipeline rate
+struct Recorder {
+    source: CaptureSource,
+    downsampler: Decimator,
+}
+
+impl Recorder {
+    async fn record(&mut self, secs: u32) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        let mut samples = Vec::new();
+        for _ in 0..secs * 10 {
+            let chunk = self.source.capture_chunk().await.map_err(|e| e.to_string())?;
+            samples.extend(self.downsampler.process(&chunk));
+        }
+        Ok(samples)
+    }
+
+    /// Record until something louder than the room is heard
+    async fn record_speech(&mut self, secs: u32, noise: f32) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        for attempt in 1..=TAKE_ATTEMPTS {
+            let take = self.record(secs).await?;
+            if chunk_levels(&take).iter().any(|&l| l > noise * VOICED_FACTOR) {
+                return Ok(take);
+            }
+            if attempt < TAKE_ATTEMPTS {
+                println!("[Calibrate]   I didn't hear anything, please try again.");
+            }
+        }
+        Err("No speech was heard; check that the right microphone is selected".into())
+    }
+}
+
+/// The detector the daemon would build from this profile
+fn wake_word_detector(profile: &UserProfile) -> WakeWordDetector {
+    let mut detector = WakeWordDetector::new();
+    if let Some(ref phrase) = profile.custom_wake_word {
+        detector.set_phrase(phrase);
+    }
+    detector.set_sensitivity(profile.wake_word_sensitivity);
+    if let Ok(path) = crate::paths::data_file("wake_word_template.json") {
+        if let Ok(template) = WakeWordTemplate::load(&path) {
+            detector.set_template(template);
+        }
+    }
+    detector
+}
+
+async fn measure(recorder: &mut Recorder, detector: &WakeWordDetector) -> Result<Measurements, Box<dyn std::error::Error>> {
+    let mut measured = Measurements::default();
+
+    println!("[Calibrate] Stay quiet for {} seconds while I listen to the room...", AMBIENT_SECS);
+    measured.ambient = chunk_levels(&recorder.record(AMBIENT_SECS).await?);
+    let noise = measured.noise();
+
+    for take in 1..=WAKE_TAKES {
+        println!("[Calibrate] Say \"{}\" now ({}/{})", detector.phrase(), take, WAKE_TAKES);
+        let recording = recorder.record_speech(TAKE_SECS, noise).await?;
+        measured.speech.extend(chunk_levels(&recording));
+        measured.wake_scores.push(detector.peak_score(&recording));
+    }
+
+    for (i, sentence) in OTHER_SENTENCES.iter().enumerate() {
+        println!("[Calibrate] Now say: \"{}\" ({}/{})", sentence, i + 1, OTHER_SENTENCES.len());
+        let recording = recorder.record_speech(SENTENCE_SECS, noise).await?;
+        measured.other_scores.push(detector.peak_score(&recording));
+    }
+    Ok(measured)
+}
+
+/// Run the wizard against the real microphone and profile
+pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let profile = UserProfile::load()?;
+    let audio = AudioDevice::open(profile.microphone.as_deref())?;
+    if audio.is_mock() {
+        return Err("No microphone was found; calibration needs a real input device".into());
+    }
+    let detector = wake_word_detector(&profile);
+    let mut recorder = Recorder { source: audio.capture_source()?, downsampler: Decimator::capture_to_pipeline() };
+
+    println!("[Calibrate] Tuning wake word and voice detection (Ctrl-C to cancel)");
+    let measured = tokio::select! {
+        measured = measure(&mut recorder, &detector) => measured?,
+        _ = tokio::signal::ctrl_c() => {
+            println!("\n[Calibrate] Cancelled; the profile was not changed");
+            return Ok(());
+        }
+    };
+
+    let recommended = Recommendation::from_measurements(&measured);
+    println!("\n[Calibrate] Results\n{}", summary_table(detector.phrase(), &measured, profile.wake_word_sensitivity, &recommended));
+
+    // Re-read: the daemon or an editor may have changed it in the meantime
+    let path = UserProfile::get_profile_path()?;
+    let mut profile = UserProfile::load_from(&path)?;
+    profile.set_wake_word_sensitivity(recommended.sensitivity);
+    profile.vad = Some(recommended.vad);
+    profile.save_to(&path)?;
+    println!("[Calibrate] Saved to {}", path.display());
+    Ok(())
+}
+
+async fn record_takes(recorder: &mut Recorder, phrase: &str) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+    println!("[Enroll] Stay quiet for {} seconds while I listen to the room...", AMBIENT_SECS);
+    let noise = Measurements { ambient: chunk_levels(&recorder.record(AMBIENT_SECS).await?), ..Measurements::default() }.noise();
+
+    let mut takes = Vec::with_capacity(WAKE_TAKES);
+    for take in 1..=WAKE_TAKES {
+        println!("[Enroll] Say \"{}\" now ({}/{})", phrase, take, WAKE_TAKES);
+        takes.push(recorder.record_speech(TAKE_SECS, noise).await?);
+    }
+    Ok(takes)
+}
+
+/// Record the wake phrase and save the trained template
+pub async fn enroll() -> Result<(), Box<dyn std::error::Error>> {
+    let profile = UserProfile::load()?;
+    let audio = AudioDevice::open(profile.microphone.as_deref())?;
+    if audio.is_mock() {
+        return Err("No microphone was found; enrollment needs a real input device".into());
+    }
+    // Trained from scratch: an earlier template plays no part
+    let mut detector = WakeWordDetector::new();
+    if let Some(ref phrase) = profile.custom_wake_word {
+        detector.set_phrase(phrase);
+    }
+    let mut recorder = Recorder { source: audio.capture_source()?, downsampler: Decimator::capture_to_pipeline() };
+
+    println!("[Enroll] Recording the wake phrase (Ctrl-C to cancel)");
+    let takes = tokio::select! {
+        takes = record_takes(&mut recorder, detector.phrase()) => takes?,
+        _ = tokio::signal::ctrl_c() => {
+            println!("\n[Enroll] Cancelled; the template was not changed");
+            return Ok(());
+        }
+    };
+
+    detector.train_from_samples(&takes)?;
+    let path = crate::paths::data_file("wake_word_template.json")?;
+    if let Some(template) = detector.template() {
+        template.save(&path)?;
+    }
+    println!("[Enroll] Saved to {}; restart EVA to use it", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_between_separated_scores() {
+        let threshold = recommend_threshold(&[0.7, 0.8, 0.75, 0.9, 0.72], &[0.2, 0.3, 0.1]);
+        assert!((threshold - 0.5).abs() < 1e-6);
+
+        // A single loud sentence overlapping one weak take: stay above it
+        let threshold = recommend_threshold(&[0.5, 0.8, 0.75, 0.9, 0.72], &[0.2, 0.6, 0.1]);
+        assert!((threshold - 0.62).abs() < 1e-6);
+
+        // Sentences score like the phrase itself: don't lose most takes over it
+        let threshold = recommend_threshold(&[0.5, 0.55, 0.6, 0.65, 0.7], &[0.68, 0.3, 0.1]);
+        assert!((threshold - 0.58).abs() < 1e-6);
+
+        // Nothing detectable at all still gives a usable setting
+        assert_eq!(recommend_threshold(&[0.0; 5], &[0.0; 3]), MIN_THRESHOLD);
+    }
+
+    #[test]
+    fn test_vad_levels_from_room_and_voice() {
+        let measured = Measurements {
+            ambient: vec![0.004, 0.005, 0.003, 0.2, 0.004],
+            speech: vec![0.004, 0.1, 0.08, 0.12, 0.005],
+            ..Measurements::default()
+        };
+        // The cough in the room and the pauses between words don't count
+        assert_eq!(measured.noise(), 0.004);
+        assert_eq!(measured.speech_level(), 0.1);
+
+        let tuning = recommend_vad(measured.noise(), measured.speech_level());
+        assert_eq!(tuning.noise_floor, 0.004);
+        assert!((tuning.ratio - 5.0).abs() < 1e-4);
+        assert!((tuning.energy_threshold - 0.02).abs() < 1e-4);
+        // A quiet room and microphone lower the fixed 0.02 minimum
+        let quiet = recommend_vad(0.001, 0.016);
+        assert!((quiet.ratio - 4.0).abs() < 1e-4);
+        assert!((quiet.energy_threshold - 0.004).abs() < 1e-6);
+
+        // Speech barely above the room: ratio is held at its minimum
+        assert_eq!(recommend_vad(0.01, 0.012).ratio, MIN_RATIO);
+        assert_eq!(recommend_vad(0.0001, 0.5).ratio, MAX_RATIO);
+    }
+
+    #[test]
+    fn test_recommendation_counts_and_summary() {
+        let measured = Measurements {
+            ambient: vec![0.004; 50],
+            speech: vec![0.1; 30],
+            wake_scores: vec![0.7, 0.8, 0.75, 0.9, 0.72],
+            other_scores: vec![0.2, 0.3, 0.1],
+        };
+        let recommended = Recommendation::from_measurements(&measured);
+        assert!((recommended.sensitivity - 0.5).abs() < 1e-6);
+        assert_eq!((recommended.hits, recommended.false_alarms), (5, 0));
+
+        let table = summary_table("Hey EVA", &measured, 0.6, &recommended);
+        assert!(table.contains("\"Hey EVA\" take 5"), "{}", table);
+        assert!(table.contains("0.60 -> 0.50"), "{}", table);
+        assert!(table.contains("Detected 5/5 takes, 0/3 false wakes"), "{}", table);
+        assert!(!table.contains("MISS"), "{}", table);
+    }
+}
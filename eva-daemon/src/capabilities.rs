@@ -0,0 +1,302 @@
+use crate::command_parser::BUILTIN_COMMANDS;
+use crate::custom_commands::CustomCommandManager;
+use crate::macros::MacroManager;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Capability groups, in the order EVA reads them out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+    Files,
+    Apps,
+    System,
+    Network,
+    Timers,
+    Conversation,
+    CustomCommands,
+    Macros,
+    Integrations,
+}
+
+impl Category {
+    pub fn name(&self, portuguese: bool) -> &'static str {
+        match (self, portuguese) {
+            (Category::Files, false) => "files",
+            (Category::Files, true) => "arquivos",
+            (Category::Apps, false) => "apps",
+            (Category::Apps, true) => "aplicativos",
+            (Category::System, false) => "system info",
+            (Category::System, true) => "informações do sistema",
+            (Category::Network, false) => "network",
+            (Category::Network, true) => "rede",
+            (Category::Timers, false) => "timers",
+            (Category::Timers, true) => "timers",
+            (Category::Conversation, false) => "conversation",
+            (Category::Conversation, true) => "conversa",
+            (Category::CustomCommands, false) => "your custom commands",
+            (Category::CustomCommands, true) => "seus comandos personalizados",
+            (Category::Macros, false) => "your macros",
+            (Category::Macros, true) => "suas macros",
+            (Category::Integrations, false) => "integrations",
+            (Category::Integrations, true) => "integrações",
+        }
+    }
+}
+
+/// One thing this build can actually do
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capability {
+    pub category: Category,
+    pub name: String,
+    /// Example phrase or short description
+    pub example: String,
+}
+
+/// What is available at runtime besides the command registries
+#[derive(Debug, Clone, Default)]
+pub struct CapabilityContext {
+    /// Enabled Cargo feature toggles
+    pub features: Vec<&'static str>,
+    /// TimeMachine initialized and recording
+    pub timemachine: bool,
+    /// NPU device present
+    pub npu: bool,
+    /// Vosk model installed for offline speech recognition
+    pub offline_stt_model: bool,
+}
+
+impl CapabilityContext {
+    /// Context for this build and machine
+    pub fn detect(timemachine: bool) -> Self {
+        Self {
+            features: compiled_features(),
+            timemachine,
+            npu: npu_present(),
+            offline_stt_model: cfg!(feature = "offline-stt") && crate::stt::SttEngine::new().is_model_available(),
+        }
+    }
+
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.contains(&feature)
+    }
+}
+
+/// Cargo features compiled into this binary
+pub fn compiled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "timemachine") {
+        features.push("timemachine");
+    }
+    if cfg!(feature = "sysinfo") {
+        features.push("sysinfo");
+    }
+    if cfg!(feature = "offline-stt") {
+        features.push("offline-stt");
+    }
+    if cfg!(feature = "desktop-audio") {
+        features.push("desktop-audio");
+    }
+    if cfg!(all(feature = "desktop-input", not(target_os = "redox"))) {
+        features.push("desktop-input");
+    }
+    features
+}
+
+/// Intel NPU exposed by the driver (`npu:` scheme on Redox, accel node elsewhere)
+fn npu_present() -> bool {
+    Path::new("/scheme/npu").exists() || Path::new("/dev/accel/accel0").exists()
+}
+
+/// Structured list of everything EVA can do, built from the registries
+#[derive(Debug, Clone, Default)]
+pub struct CapabilityReport {
+    pub entries: Vec<Capability>,
+}
+
+impl CapabilityReport {
+    /// Aggregate built-in commands, custom commands, macros and integrations
+    pub fn build(
+        context: &CapabilityContext,
+        custom_commands: &CustomCommandManager,
+        macros: &MacroManager,
+    ) -> Self {
+        let custom = custom_commands
+            .list_commands()
+            .into_iter()
+            .map(|c| (c.trigger.clone(), c.description.clone()))
+            .collect();
+        let macro_names = macros.list_macros().into_iter().map(|m| m.name.clone()).collect();
+        Self::from_parts(context, custom, macro_names)
+    }
+
+    fn from_parts(context: &CapabilityContext, mut custom: Vec<(String, String)>, mut macro_names: Vec<String>) -> Self {
+        let mut entries: Vec<Capability> = BUILTIN_COMMANDS
+            .iter()
+            .filter(|spec| spec.requires.map_or(true, |f| context.has_feature(f)))
+            .map(|spec| Capability {
+                category: spec.category,
+                name: spec.name.to_string(),
+                example: spec.example.to_string(),
+            })
+            .collect();
+
+        custom.sort();
+        entries.extend(custom.into_iter().map(|(trigger, description)| Capability {
+            category: Category::CustomCommands,
+            name: trigger,
+            example: description,
+        }));
+
+        macro_names.sort();
+        entries.extend(macro_names.into_iter().map(|name| Capability {
+            category: Category::Macros,
+            example: format!("run macro {}", name),
+            name,
+        }));
+
+        let integrations = [
+            (context.timemachine, "Time Machine", "search what was on your screen"),
+            (context.npu, "NPU acceleration", "on-device AI models"),
+            (context.offline_stt_model, "offline speech recognition", "works without internet"),
+        ];
+        entries.extend(integrations.iter().filter(|(on, _, _)| *on).map(|(_, name, example)| Capability {
+            category: Category::Integrations,
+            name: name.to_string(),
+            example: example.to_string(),
+        }));
+
+        Self { entries }
+    }
+
+    /// Capability names grouped by category, in category order
+    pub fn grouped(&self) -> BTreeMap<Category, Vec<&Capability>> {
+        let mut groups: BTreeMap<Category, Vec<&Capability>> = BTreeMap::new();
+        for entry in &self.entries {
+            groups.entry(entry.category).or_default().push(entry);
+        }
+        groups
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|e| e.name == name)
+    }
+
+    /// Short answer for "what can you do", one clause per category
+    pub fn spoken(&self, portuguese: bool) -> String {
+        let clauses: Vec<String> = self
+            .grouped()
+            .iter()
+            .map(|(category, caps)| {
+                let names: Vec<&str> = caps.iter().map(|c| c.name.as_str()).collect();
+                format!("{}: {}", category.name(portuguese), names.join(", "))
+            })
+            .collect();
+
+        if portuguese {
+            format!("Eu posso ajudar com {}.", clauses.join("; "))
+        } else {
+            format!("I can help with {}.", clauses.join("; "))
+        }
+    }
+
+    /// Full listing with examples, for the TUI or a Markdown file
+    pub fn markdown(&self) -> String {
+        let mut out = String::from("# What EVA can do\n");
+        for (category, caps) in self.grouped() {
+            out.push_str(&format!("\n## {}\n\n", capitalize(category.name(false))));
+            for cap in caps {
+                out.push_str(&format!("- **{}** — \"{}\"\n", cap.name, cap.example));
+            }
+        }
+        out
+    }
+
+    /// One-paragraph version for the model's system instruction
+    pub fn condensed(&self) -> String {
+        format!(
+            "Local actions available on this device: {} Do not offer any other device actions.",
+            self.spoken(false).trim_start_matches("I can help with ")
+        )
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// "what can you do" / "o que você sabe fazer"
+pub fn is_capability_question(text: &str) -> bool {
+    let text = text.to_lowercase();
+    [
+        "what can you do",
+        "what are you able to do",
+        "what do you do",
+        "o que você sabe fazer",
+        "o que voce sabe fazer",
+        "o que você pode fazer",
+        "o que voce pode fazer",
+    ]
+    .iter()
+    .any(|q| text.contains(q))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(features: Vec<&'static str>) -> CapabilityContext {
+        CapabilityContext { features, timemachine: true, npu: false, offline_stt_model: false }
+    }
+
+    #[test]
+    fn test_disabled_feature_removed() {
+        let with = CapabilityReport::from_parts(&context(vec!["sysinfo"]), vec![], vec![]);
+        let without = CapabilityReport::from_parts(&context(vec![]), vec![], vec![]);
+
+        assert!(with.contains("memory usage"));
+        assert!(!without.contains("memory usage"));
+        assert!(!without.contains("list running processes"));
+        assert!(without.contains("create files"));
+        assert!(!without.spoken(false).contains("system info"));
+    }
+
+    #[test]
+    fn test_integrations_follow_context() {
+        let mut ctx = context(vec![]);
+        assert!(CapabilityReport::from_parts(&ctx, vec![], vec![]).contains("Time Machine"));
+        ctx.timemachine = false;
+        ctx.npu = true;
+        let report = CapabilityReport::from_parts(&ctx, vec![], vec![]);
+        assert!(!report.contains("Time Machine"));
+        assert!(report.contains("NPU acceleration"));
+    }
+
+    #[test]
+    fn test_user_registries_included() {
+        let report = CapabilityReport::from_parts(
+            &context(vec![]),
+            vec![("lights on".to_string(), "turn on the office lights".to_string())],
+            vec!["morning".to_string()],
+        );
+        let spoken = report.spoken(true);
+        assert!(spoken.starts_with("Eu posso ajudar com arquivos:"));
+        assert!(spoken.contains("seus comandos personalizados: lights on"));
+        assert!(spoken.contains("suas macros: morning"));
+
+        let md = report.markdown();
+        assert!(md.contains("## Your custom commands"));
+        assert!(md.contains("- **lights on** — \"turn on the office lights\""));
+        assert!(report.condensed().contains("Do not offer"));
+    }
+
+    #[test]
+    fn test_capability_question() {
+        assert!(is_capability_question("Hey EVA, what can you do?"));
+        assert!(is_capability_question("O que você sabe fazer"));
+        assert!(!is_capability_question("what time is it"));
+    }
+}
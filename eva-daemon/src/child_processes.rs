@@ -0,0 +1,306 @@
+//! Programs EVA started, followed until they exit
+//!
+//! Each child's stdout and stderr are piped and read on background threads
+//! into one buffer that keeps only the most recent `OUTPUT_LIMIT` bytes, so
+//! "run my build script" can be followed up with "is it done?" and "show
+//! its output" without a chatty program filling memory. Exited children
+//! are reaped (`reap`) so they don't linger as zombies; the last few stay
+//! listed with their exit status.
+
+use crate::timers::format_countdown;
+use std::collections::VecDeque;
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+/// Captured output kept per child (stdout and stderr together)
+pub const OUTPUT_LIMIT: usize = 16 * 1024;
+
+/// Exited children still listed
+const FINISHED_KEPT: usize = 16;
+
+/// The tail of what a child wrote
+#[derive(Default)]
+struct CapturedOutput {
+    tail: VecDeque<u8>,
+    /// Bytes that fell off the front
+    dropped: usize,
+}
+
+impl CapturedOutput {
+    fn push(&mut self, bytes: &[u8], limit: usize) {
+        self.tail.extend(bytes);
+        let excess = self.tail.len().saturating_sub(limit);
+        self.tail.drain(..excess);
+        self.dropped += excess;
+    }
+}
+
+struct Tracked {
+    pid: u32,
+    name: String,
+    started: Instant,
+    child: Child,
+    output: Arc<Mutex<CapturedOutput>>,
+    /// Exit status and when it was noticed
+    exited: Option<(ExitStatus, Instant)>,
+}
+
+impl Tracked {
+    fn describe(&self, now: Instant) -> String {
+        match self.exited {
+            None => format!(
+                "{} (pid {}) is still running, started {} ago.",
+                self.name,
+                self.pid,
+                format_countdown(now.duration_since(self.started))
+            ),
+            Some((status, at)) => format!(
+                "{} (pid {}) {} after {}.",
+                self.name,
+                self.pid,
+                describe_exit(status),
+                format_countdown(at.duration_since(self.started))
+            ),
+        }
+    }
+}
+
+/// A child that exited since the last `reap`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exited {
+    pub pid: u32,
+    pub name: String,
+    pub status: ExitStatus,
+}
+
+impl std::fmt::Display for Exited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (pid {}) {}", self.name, self.pid, describe_exit(self.status))
+    }
+}
+
+/// "exited with code 0", or how it was stopped
+fn describe_exit(status: ExitStatus) -> String {
+    match status.code() {
+        Some(code) => format!("exited with code {}", code),
+        None => "was stopped by a signal".to_string(),
+    }
+}
+
+/// Children started by the command executor
+pub struct ChildRegistry {
+    children: Vec<Tracked>,
+    output_limit: usize,
+}
+
+impl ChildRegistry {
+    pub fn new() -> Self {
+        Self::with_output_limit(OUTPUT_LIMIT)
+    }
+
+    /// Keep `limit` bytes of output per child instead of `OUTPUT_LIMIT`
+    pub fn with_output_limit(limit: usize) -> Self {
+        Self { children: Vec::new(), output_limit: limit }
+    }
+
+    /// Start `command` with its output captured; the new child's pid
+    pub fn spawn(&mut self, name: &str, mut command: Command) -> std::io::Result<u32> {
+        let mut child = command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+        let output = Arc::new(Mutex::new(CapturedOutput::default()));
+        let pid = child.id();
+        let pipes: [Option<Box<dyn Read + Send>>; 2] = [
+            child.stdout.take().map(|p| Box::new(p) as Box<dyn Read + Send>),
+            child.stderr.take().map(|p| Box::new(p) as Box<dyn Read + Send>),
+        ];
+        for pipe in pipes.into_iter().flatten() {
+            let output = output.clone();
+            let limit = self.output_limit;
+            thread::Builder::new().name(format!("child-{}-output", pid)).spawn(move || capture(pipe, output, limit))?;
+        }
+        self.children.push(Tracked { pid, name: name.to_string(), started: Instant::now(), child, output, exited: None });
+        Ok(pid)
+    }
+
+    /// Collect the exit status of children that finished, so none is left
+    /// a zombie; the ones that exited since the last call
+    pub fn reap(&mut self) -> Vec<Exited> {
+        let now = Instant::now();
+        let mut exited = Vec::new();
+        for tracked in self.children.iter_mut().filter(|t| t.exited.is_none()) {
+            if let Ok(Some(status)) = tracked.child.try_wait() {
+                tracked.exited = Some((status, now));
+                exited.push(Exited { pid: tracked.pid, name: tracked.name.clone(), status });
+            }
+        }
+        // Forget the oldest finished ones
+        let finished = self.children.iter().filter(|t| t.exited.is_some()).count();
+        let mut forget = finished.saturating_sub(FINISHED_KEPT);
+        self.children.retain(|t| {
+            let drop = forget > 0 && t.exited.is_some();
+            forget -= drop as usize;
+            !drop
+        });
+        exited
+    }
+
+    /// Children still running
+    pub fn running(&self) -> usize {
+        self.children.iter().filter(|t| t.exited.is_none()).count()
+    }
+
+    /// Whether a child is running or how it ended, by pid or by name (the
+    /// most recent one started under that name)
+    pub fn status(&mut self, name_or_pid: &str) -> Result<String, String> {
+        self.reap();
+        let tracked = self.find(name_or_pid).ok_or_else(|| format!("I haven't started anything called {}.", name_or_pid))?;
+        Ok(tracked.describe(Instant::now()))
+    }
+
+    /// The captured output of a child, with a note when the start of it
+    /// was cut off
+    pub fn output(&mut self, pid: u32) -> Result<String, String> {
+        self.reap();
+        let tracked = self.children.iter().find(|t| t.pid == pid).ok_or_else(|| format!("I haven't started a process {}.", pid))?;
+        let output = tracked.output.lock().unwrap();
+        let text = String::from_utf8_lossy(&output.tail.iter().copied().collect::<Vec<u8>>()).into_owned();
+        let mut reply = tracked.describe(Instant::now());
+        if output.dropped > 0 {
+            reply.push_str(&format!(" Output truncated: {} KB before this not shown.", output.dropped.div_ceil(1024)));
+        }
+        if text.trim().is_empty() {
+            reply.push_str(" No output so far.");
+        } else {
+            reply.push('\n');
+            reply.push_str(text.trim_end());
+        }
+        Ok(reply)
+    }
+
+    fn find(&self, name_or_pid: &str) -> Option<&Tracked> {
+        let name_or_pid = name_or_pid.trim();
+        match name_or_pid.parse::<u32>() {
+            Ok(pid) => self.children.iter().find(|t| t.pid == pid),
+            Err(_) => self.children.iter().rev().find(|t| t.name.eq_ignore_ascii_case(name_or_pid)),
+        }
+    }
+}
+
+impl Default for ChildRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reader thread: copy a pipe into the shared buffer until the child
+/// closes it
+fn capture(mut pipe: Box<dyn Read + Send>, output: Arc<Mutex<CapturedOutput>>, limit: usize) {
+    let mut buf = [0u8; 4096];
+    loop {
+        match pipe.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => output.lock().unwrap().push(&buf[..n], limit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// `script` run by the platform's shell
+    fn shell(script: &str) -> Command {
+        #[cfg(windows)]
+        let mut command = {
+            let mut command = Command::new("cmd");
+            command.arg("/C");
+            command
+        };
+        #[cfg(not(windows))]
+        let mut command = {
+            let mut command = Command::new("sh");
+            command.arg("-c");
+            command
+        };
+        command.arg(script);
+        command
+    }
+
+    /// Reap until `pid` has exited (at most a few seconds)
+    fn wait_for_exit(registry: &mut ChildRegistry, pid: u32) -> Vec<Exited> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut exited = Vec::new();
+        while !exited.iter().any(|e: &Exited| e.pid == pid) {
+            assert!(Instant::now() < deadline, "process {} did not exit", pid);
+            exited.extend(registry.reap());
+            thread::sleep(Duration::from_millis(10));
+        }
+        exited
+    }
+
+    /// The output once the reader threads caught up with `expected`
+    fn output_containing(registry: &mut ChildRegistry, pid: u32, expected: &str) -> String {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let output = registry.output(pid).unwrap();
+            if output.contains(expected) || Instant::now() > deadline {
+                return output;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_output_is_captured_and_children_reaped() {
+        let mut registry = ChildRegistry::new();
+        let pid = registry.spawn("greeter", shell("echo hello from stdout&& echo oops 1>&2&& exit 3")).unwrap();
+
+        let exited = wait_for_exit(&mut registry, pid);
+        assert_eq!(exited.len(), 1);
+        assert_eq!(exited[0].status.code(), Some(3));
+        assert_eq!(exited[0].to_string(), format!("greeter (pid {}) exited with code 3", pid));
+        assert_eq!(registry.running(), 0);
+        // Reported once
+        assert!(registry.reap().is_empty());
+
+        let output = output_containing(&mut registry, pid, "oops");
+        assert!(output.contains("hello from stdout"), "{}", output);
+        assert!(output.contains("oops"), "{}", output);
+        assert!(registry.status("greeter").unwrap().contains("exited with code 3"));
+        assert!(registry.status(&pid.to_string()).unwrap().starts_with("greeter"));
+        assert!(registry.status("unknown").is_err());
+        assert!(registry.output(pid + 1).is_err());
+    }
+
+    #[test]
+    fn test_running_child_and_long_output() {
+        let mut registry = ChildRegistry::with_output_limit(1024);
+
+        #[cfg(windows)]
+        let sleeper = shell("ping -n 30 127.0.0.1 >NUL");
+        #[cfg(not(windows))]
+        let sleeper = shell("sleep 30");
+        let sleeping = registry.spawn("sleeper", sleeper).unwrap();
+        assert!(registry.status("sleeper").unwrap().contains("is still running"));
+        assert_eq!(registry.running(), 1);
+
+        #[cfg(windows)]
+        let chatty = shell("for /L %i in (1,1,500) do @echo line %i");
+        #[cfg(not(windows))]
+        let chatty = shell("i=1; while [ $i -le 500 ]; do echo line $i; i=$((i+1)); done");
+        let pid = registry.spawn("chatty", chatty).unwrap();
+        wait_for_exit(&mut registry, pid);
+        let output = output_containing(&mut registry, pid, "line 500");
+        assert!(output.contains("line 500"), "{}", output);
+        assert!(!output.contains("line 1\n"), "{}", output);
+        assert!(output.contains("Output truncated"), "{}", output);
+        assert!(output.len() < 1024 + 200);
+
+        registry.children.iter_mut().find(|t| t.pid == sleeping).unwrap().child.kill().unwrap();
+        wait_for_exit(&mut registry, sleeping);
+        assert_eq!(registry.running(), 0);
+    }
+}
@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Source of wall-clock and monotonic time
+///
+/// Code that schedules or rate-limits takes its time from here instead of
+/// calling `Utc::now()` / `Instant::now()` directly, so scenario tests can
+/// run a whole day in milliseconds.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+    fn instant(&self) -> Instant;
+}
+
+/// The real clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock that only moves when told to; clones share the same time
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    start: DateTime<Utc>,
+    base: Instant,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl VirtualClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self { start, base: Instant::now(), elapsed: Arc::new(Mutex::new(Duration::ZERO)) }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.start + chrono::Duration::from_std(self.elapsed()).unwrap_or_else(|_| chrono::Duration::zero())
+    }
+
+    fn instant(&self) -> Instant {
+        self.base + self.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_virtual_clock_moves_only_when_advanced() {
+        let start = Utc.with_ymd_and_hms(2026, 1, 5, 8, 0, 0).unwrap();
+        let clock = VirtualClock::new(start);
+        let shared = clock.clone();
+        let before = clock.instant();
+
+        assert_eq!(clock.now(), start);
+        shared.advance(Duration::from_secs(90 * 60));
+        assert_eq!(clock.now(), Utc.with_ymd_and_hms(2026, 1, 5, 9, 30, 0).unwrap());
+        assert_eq!(clock.instant() - before, Duration::from_secs(90 * 60));
+    }
+}
@@ -1,119 +1,505 @@
+use crate::child_processes::{ChildRegistry, Exited};
+use crate::command_history::describe_intent;
 use crate::command_parser::{CommandIntent, FileOperation, ProcessOperation, SystemOperation, NetworkOperation, TextOperation};
+use crate::desktop_input::{self, InputBackend, TextInput};
+use crate::macros::{MacroStep, VoiceMacro};
+use crate::network;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long a held destructive command waits for "yes"
+pub const CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a macro recording waits for the next command before it stops
+/// by itself
+pub const MACRO_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// What `execute` does with a command the parser produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionPolicy {
+    /// Run everything straight away
+    AutoApprove,
+    /// Hold Delete/Move/Kill and synthesized keystrokes until the user
+    /// confirms
+    #[default]
+    ConfirmDestructive,
+    /// Describe what would happen without touching anything
+    DryRun,
+}
+
+impl ExecutionPolicy {
+    /// Profile preference value: "auto", "confirm" or "dry_run"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().replace('-', "_").as_str() {
+            "auto" | "auto_approve" => Some(ExecutionPolicy::AutoApprove),
+            "confirm" | "confirm_destructive" => Some(ExecutionPolicy::ConfirmDestructive),
+            "dry_run" => Some(ExecutionPolicy::DryRun),
+            _ => None,
+        }
+    }
+}
+
+/// Result of `execute`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionOutcome {
+    /// The command ran
+    Done(String),
+    /// Held until `confirm_pending`; the question to put to the user
+    NeedsConfirmation(String),
+    /// Dry run: what would have happened
+    WouldRun(String),
+}
+
+impl ExecutionOutcome {
+    pub fn into_message(self) -> String {
+        match self {
+            ExecutionOutcome::Done(msg) | ExecutionOutcome::NeedsConfirmation(msg) | ExecutionOutcome::WouldRun(msg) => msg,
+        }
+    }
+}
+
+/// What commands may do under an allowed folder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathAccess {
+    #[default]
+    ReadOnly,
+    ReadWrite,
+}
+
+/// Folder outside the sandbox that commands may use (stored in the profile)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllowedPath {
+    /// May start with `~`
+    pub path: String,
+    #[serde(default)]
+    pub access: PathAccess,
+}
+
+/// A destructive command waiting for the user's answer
+struct PendingCommand {
+    intent: CommandIntent,
+    expires_at: Instant,
+}
+
+/// A macro being recorded from the commands that run
+struct MacroRecording {
+    recorded: VoiceMacro,
+    last_activity: Instant,
+}
+
+/// "yes" / "não": the answer to a held command, if the text is one
+pub fn parse_confirmation(text: &str) -> Option<bool> {
+    let text = text.to_lowercase();
+    let words: Vec<&str> = text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+    let has_word = |list: &[&str]| list.iter().any(|w| words.contains(w));
+    if has_word(&["no", "nope", "cancel", "stop", "não", "nao", "cancelar", "cancela"]) {
+        Some(false)
+    } else if has_word(&["yes", "yeah", "yep", "confirm", "sure", "sim", "confirmo", "pode"]) {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Why a compound request stopped before its last command
+#[derive(Debug, Clone, PartialEq)]
+pub enum SequenceStop {
+    /// The step is held for a "yes"; the question to put to the user
+    Held(String),
+    Failed(String),
+}
+
+/// One reply for a compound request: what each step that ran said, then
+/// why the rest didn't run
+pub fn sequence_summary(outputs: &[String], total: usize, stop: Option<SequenceStop>) -> Result<String, String> {
+    let step = outputs.len() + 1;
+    match stop {
+        None => Ok(format!("Ran {} commands:\n{}", total, outputs.join("\n"))),
+        Some(SequenceStop::Held(prompt)) => {
+            let done = if outputs.is_empty() { String::new() } else { format!("{}\n", outputs.join("\n")) };
+            Ok(format!("{}Step {} of {} is waiting for confirmation; the rest will not run. {}", done, step, total, prompt))
+        }
+        Some(SequenceStop::Failed(e)) => {
+            let done = if outputs.is_empty() { String::new() } else { format!(" Done before it:\n{}", outputs.join("\n")) };
+            Err(format!("Step {} of {} failed: {}.{}", step, total, e, done))
+        }
+    }
+}
 
 /// Command executor with sandboxing
 pub struct CommandExecutor {
     sandbox_dir: PathBuf,
+    policy: ExecutionPolicy,
+    pending: Option<PendingCommand>,
+    /// Canonical roots outside the sandbox
+    allowed: Vec<(PathBuf, PathAccess)>,
+    recording: Option<MacroRecording>,
+    macro_idle_timeout: Duration,
+    input: TextInput,
+    /// Programs started by `ProcessOperation::Start`
+    children: ChildRegistry,
 }
 
 impl CommandExecutor {
     /// Create a new command executor
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        // Create sandbox directory
-        let sandbox_dir = Self::get_sandbox_dir()?;
-        
+        Self::with_sandbox(Self::get_sandbox_dir()?)
+    }
+
+    /// Create an executor confined to a specific sandbox directory
+    pub fn with_sandbox(sandbox_dir: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
         if !sandbox_dir.exists() {
             fs::create_dir_all(&sandbox_dir)?;
         }
         
-        Ok(Self { sandbox_dir })
+        Ok(Self {
+            sandbox_dir,
+            policy: ExecutionPolicy::default(),
+            pending: None,
+            allowed: Vec::new(),
+            recording: None,
+            macro_idle_timeout: MACRO_IDLE_TIMEOUT,
+            input: TextInput::new(desktop_input::system_backend()),
+            children: ChildRegistry::new(),
+        })
+    }
+
+    /// Send clipboard access and keystrokes somewhere other than the desktop
+    pub fn set_input_backend(&mut self, backend: Box<dyn InputBackend>) {
+        self.input = TextInput::new(backend);
+    }
+
+    /// Sandbox directory this executor is confined to
+    pub fn sandbox_dir(&self) -> &Path {
+        &self.sandbox_dir
+    }
+
+    /// Let commands read and write under `path` (which must exist)
+    pub fn allow_path(&mut self, path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        self.allow_path_with(path, PathAccess::ReadWrite)
+    }
+
+    /// Let commands only read under `path`
+    pub fn allow_read_only(&mut self, path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        self.allow_path_with(path, PathAccess::ReadOnly)
+    }
+
+    fn allow_path_with(&mut self, path: PathBuf, access: PathAccess) -> Result<(), Box<dyn std::error::Error>> {
+        let root = path.canonicalize().map_err(|e| format!("Cannot allow {}: {}", path.display(), e))?;
+        if !root.is_dir() {
+            return Err(format!("Cannot allow {}: not a folder", path.display()).into());
+        }
+        self.allowed.retain(|(existing, _)| *existing != root);
+        self.allowed.push((root, access));
+        Ok(())
+    }
+
+    /// Apply the profile's allowlist; returns the entries that were skipped
+    pub fn allow_paths(&mut self, paths: &[AllowedPath]) -> Vec<String> {
+        paths
+            .iter()
+            .filter_map(|entry| {
+                let path = PathBuf::from(crate::paths::expand_home(&entry.path));
+                self.allow_path_with(path, entry.access).err().map(|e| e.to_string())
+            })
+            .collect()
     }
 
     /// Get sandbox directory path
     fn get_sandbox_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
-        #[cfg(target_os = "windows")]
-        {
-            let home = std::env::var("USERPROFILE")?;
-            Ok(PathBuf::from(home).join(".eva").join("sandbox"))
+        crate::paths::sandbox_dir()
+    }
+
+    /// Collect started programs that exited; the ones that did since the
+    /// last call
+    pub fn reap_children(&mut self) -> Vec<Exited> {
+        self.children.reap()
+    }
+
+    pub fn policy(&self) -> ExecutionPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: ExecutionPolicy) {
+        self.policy = policy;
+        if policy != ExecutionPolicy::ConfirmDestructive {
+            self.pending = None;
         }
-        
-        #[cfg(not(target_os = "windows"))]
-        {
-            let home = std::env::var("HOME")?;
-            Ok(PathBuf::from(home).join(".eva").join("sandbox"))
+    }
+
+    /// Execute a command, subject to the execution policy
+    pub async fn execute(&mut self, intent: CommandIntent) -> Result<ExecutionOutcome, Box<dyn std::error::Error>> {
+        self.execute_at(intent, Instant::now()).await
+    }
+
+    /// Same, at a given time (see `Clock::instant`)
+    pub async fn execute_at(&mut self, intent: CommandIntent, now: Instant) -> Result<ExecutionOutcome, Box<dyn std::error::Error>> {
+        match self.policy {
+            ExecutionPolicy::DryRun => Ok(ExecutionOutcome::WouldRun(self.dry_run(&intent))),
+            ExecutionPolicy::ConfirmDestructive if intent.is_risky() => {
+                let prompt = format!("About to {}. Say yes to confirm or no to cancel.", describe_intent(&intent));
+                // A newer destructive command replaces the one still waiting
+                self.pending = Some(PendingCommand { intent, expires_at: now + CONFIRM_TIMEOUT });
+                Ok(ExecutionOutcome::NeedsConfirmation(prompt))
+            }
+            _ => {
+                let step = self.recording.is_some().then(|| intent.clone());
+                let output = self.run(intent).await?;
+                if let Some(step) = step {
+                    self.record_step(step, now);
+                }
+                Ok(ExecutionOutcome::Done(output))
+            }
+        }
+    }
+
+    /// The command waiting for confirmation, if it hasn't expired
+    pub fn pending(&self) -> Option<&CommandIntent> {
+        self.pending_at(Instant::now())
+    }
+
+    pub fn pending_at(&self, now: Instant) -> Option<&CommandIntent> {
+        self.pending.as_ref().filter(|p| now < p.expires_at).map(|p| &p.intent)
+    }
+
+    /// Run the held command
+    pub async fn confirm_pending(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        self.confirm_pending_at(Instant::now()).await
+    }
+
+    pub async fn confirm_pending_at(&mut self, now: Instant) -> Result<String, Box<dyn std::error::Error>> {
+        let pending = self.pending.take().ok_or("Nothing is waiting for confirmation")?;
+        if now >= pending.expires_at {
+            return Err(format!("Confirmation timed out, did not {}", describe_intent(&pending.intent)).into());
+        }
+        let step = self.recording.is_some().then(|| pending.intent.clone());
+        let output = self.run(pending.intent).await?;
+        if let Some(step) = step {
+            self.record_step(step, now);
+        }
+        Ok(output)
+    }
+
+    /// Drop the held command; returns what was cancelled
+    pub fn cancel_pending(&mut self) -> Option<String> {
+        self.pending.take().map(|p| format!("Cancelled: {}", describe_intent(&p.intent)))
+    }
+
+    /// Auto-cancel a held command whose time ran out; returns the notice
+    pub fn expire_pending(&mut self, now: Instant) -> Option<String> {
+        if self.pending.as_ref().is_some_and(|p| now >= p.expires_at) {
+            let pending = self.pending.take()?;
+            return Some(format!("No confirmation, did not {}", describe_intent(&pending.intent)));
+        }
+        None
+    }
+
+    /// Start appending every command that runs to a new macro
+    pub fn start_macro(&mut self, name: String) -> Result<(), Box<dyn std::error::Error>> {
+        self.start_macro_at(name, Instant::now())
+    }
+
+    pub fn start_macro_at(&mut self, name: String, now: Instant) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(ref current) = self.recording {
+            return Err(format!("Already recording macro '{}'; stop it before starting another", current.recorded.name).into());
         }
+        let recorded = VoiceMacro { name, steps: Vec::new(), created_at: SystemTime::now() };
+        self.recording = Some(MacroRecording { recorded, last_activity: now });
+        Ok(())
+    }
+
+    /// Stop recording; the macro is for `MacroManager` to save
+    pub fn stop_macro(&mut self) -> Result<VoiceMacro, Box<dyn std::error::Error>> {
+        self.recording.take().map(|r| r.recorded).ok_or_else(|| "No macro is being recorded".into())
+    }
+
+    /// Name of the macro being recorded
+    pub fn recording_macro(&self) -> Option<&str> {
+        self.recording.as_ref().map(|r| r.recorded.name.as_str())
+    }
+
+    pub fn set_macro_idle_timeout(&mut self, timeout: Duration) {
+        self.macro_idle_timeout = timeout;
     }
 
-    /// Execute a command
-    pub async fn execute(&mut self, intent: CommandIntent) -> Result<String, Box<dyn std::error::Error>> {
+    /// Auto-stop a recording that has had no command for the idle timeout;
+    /// returns the macro so far
+    pub fn expire_macro(&mut self, now: Instant) -> Option<VoiceMacro> {
+        let idle = self.recording.as_ref()?.last_activity + self.macro_idle_timeout;
+        if now >= idle {
+            return self.stop_macro().ok();
+        }
+        None
+    }
+
+    fn record_step(&mut self, intent: CommandIntent, now: Instant) {
+        if intent == CommandIntent::Unknown {
+            return;
+        }
+        if let Some(ref mut recording) = self.recording {
+            recording.recorded.steps.push(MacroStep { command: describe_intent(&intent), delay_ms: 0, intent: Some(intent) });
+            recording.last_activity = now;
+        }
+    }
+
+    /// Replay a macro's commands in order through `execute`, stopping at
+    /// the first one that fails or needs confirmation
+    pub async fn run_macro(&mut self, recorded: &VoiceMacro) -> Result<String, Box<dyn std::error::Error>> {
+        let total = recorded.steps.len();
+        if total == 0 {
+            return Err(format!("Macro '{}' has no steps", recorded.name).into());
+        }
+
+        let mut outputs = Vec::new();
+        for (i, step) in recorded.steps.iter().enumerate() {
+            let completed = || match i {
+                0 => "No steps completed.".to_string(),
+                _ => {
+                    let done: Vec<&str> = recorded.steps[..i].iter().map(|s| s.command.as_str()).collect();
+                    format!("Completed: {}.", done.join(", "))
+                }
+            };
+            let Some(intent) = step.intent.clone() else {
+                return Err(format!("Macro '{}' stopped at step {} of {} ({}): not a recorded command. {}", recorded.name, i + 1, total, step.command, completed()).into());
+            };
+            match self.execute(intent).await {
+                Ok(ExecutionOutcome::Done(output)) | Ok(ExecutionOutcome::WouldRun(output)) => outputs.push(output),
+                Ok(ExecutionOutcome::NeedsConfirmation(prompt)) => {
+                    return Ok(format!("Macro '{}' paused at step {} of {}; the rest will not run. {} {}", recorded.name, i + 1, total, completed(), prompt));
+                }
+                Err(e) => {
+                    return Err(format!("Macro '{}' stopped at step {} of {} ({}): {}. {}", recorded.name, i + 1, total, step.command, e, completed()).into());
+                }
+            }
+        }
+        Ok(format!("Ran macro '{}' ({} steps):\n{}", recorded.name, total, outputs.join("\n")))
+    }
+
+    /// Human-readable account of what `run` would do
+    fn dry_run(&self, intent: &CommandIntent) -> String {
+        let exists = |path: &str| self.resolve(path).is_ok_and(|p| p.exists());
+        let state = |path: &str| match self.validate_read(path).map(fs::metadata) {
+            Ok(Ok(meta)) if meta.is_dir() => " (a folder)".to_string(),
+            Ok(Ok(meta)) => format!(" ({} bytes)", meta.len()),
+            Ok(Err(_)) => " (which does not exist)".to_string(),
+            Err(_) => " (outside the allowed folders, so it would fail)".to_string(),
+        };
+        let detail = match intent {
+            CommandIntent::File(FileOperation::Create { path, .. }) if exists(path) => " (overwriting it)".to_string(),
+            CommandIntent::File(FileOperation::Delete { path })
+            | CommandIntent::File(FileOperation::Read { path }) => state(path),
+            CommandIntent::File(FileOperation::Copy { from, to })
+            | CommandIntent::File(FileOperation::Move { from, to }) => {
+                let overwrite = if exists(to) { format!(", replacing {}", to) } else { String::new() };
+                format!("{}{}", state(from), overwrite)
+            }
+            _ => String::new(),
+        };
+        format!("Dry run: would {}{}. Nothing was changed.", describe_intent(intent), detail)
+    }
+
+    async fn run(&mut self, intent: CommandIntent) -> Result<String, Box<dyn std::error::Error>> {
         match intent {
             CommandIntent::File(op) => self.execute_file_op(op).await,
             CommandIntent::Process(op) => self.execute_process_op(op).await,
             CommandIntent::System(op) => self.execute_system_op(op).await,
             CommandIntent::Network(op) => self.execute_network_op(op).await,
             CommandIntent::Text(op) => self.execute_text_op(op).await,
+            CommandIntent::Session(_) => Err("Session operations are handled by the conversation loop".into()),
+            // Repeats are resolved against the command history first: this one named nothing there
+            CommandIntent::Repeat(_) => Err("There's no such command in the history to repeat".into()),
+            CommandIntent::Timer(_) => Err("Timer operations are handled by the timer manager".into()),
+            CommandIntent::TimeMachine(_) => Err("Time Machine operations are handled by the Time Machine".into()),
+            CommandIntent::Macro(_) => Err("Macro operations are handled by the macro manager".into()),
+            CommandIntent::Profile(_) => Err("Profile changes are handled by the conversation loop".into()),
+            CommandIntent::Audio(_) => Err("Audio settings are handled by the audio player".into()),
             CommandIntent::Unknown => Ok("I didn't understand that command.".to_string()),
         }
     }
 
-    /// Validate and resolve path within sandbox
-    ///
-    /// # Security
-    /// This function prevents path traversal attacks by:
-    /// 1. Removing dangerous characters and sequences
-    /// 2. Resolving to canonical path (follows symlinks)
-    /// 3. Verifying final path is within sandbox
+    /// Validate a path the command will write to (creating its parent
+    /// folders); see `check_access`
     fn validate_path(&self, path: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
-        // 1. Remove dangerous characters and sequences
-        let mut clean_path = path.to_string();
-
-        // Remove path traversal attempts (multiple passes for nested attacks)
-        for _ in 0..5 {
-            let before = clean_path.clone();
-            clean_path = clean_path
-                .replace("..", "")
-                .replace("~", "")
-                .replace("//", "/")
-                .replace("\\\\", "\\")
-                .replace("\0", ""); // Null byte injection
-
-            if clean_path == before {
-                break;
-            }
-        }
-
-        // Remove leading slashes/backslashes (absolute path attempts)
-        let clean_path = clean_path.trim_start_matches(['/', '\\']);
-
-        // 2. Build full path within sandbox
-        let full_path = self.sandbox_dir.join(clean_path);
-
-        // 3. Get canonical sandbox path
-        let sandbox_canonical = self.sandbox_dir.canonicalize()
-            .unwrap_or_else(|_| self.sandbox_dir.clone());
-
-        // 4. Try to canonicalize the target path
-        // If file doesn't exist yet, check parent directory
-        let target_canonical = if full_path.exists() {
-            full_path.canonicalize()?
-        } else {
-            // For new files, verify parent is in sandbox
-            let parent = full_path.parent()
-                .ok_or("Invalid path: no parent directory")?;
-
+        let resolved = self.check_access(path, PathAccess::ReadWrite)?;
+        if let Some(parent) = resolved.parent() {
             if !parent.exists() {
-                // Create parent directories within sandbox
                 fs::create_dir_all(parent)?;
             }
+        }
+        Ok(resolved)
+    }
 
-            let parent_canonical = parent.canonicalize()?;
+    /// Validate a path the command only reads
+    fn validate_read(&self, path: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        self.check_access(path, PathAccess::ReadOnly)
+    }
 
-            // Verify parent is in sandbox
-            if !parent_canonical.starts_with(&sandbox_canonical) {
-                return Err("Path traversal detected: parent outside sandbox".into());
+    /// Resolve `path` and check it lies under the sandbox or an allowed root
+    /// granting `needed`
+    ///
+    /// # Security
+    /// Containment is decided on the canonical path, after every symlink
+    /// and `..` in the existing part has been resolved by the filesystem,
+    /// so a link inside the sandbox can't point the command elsewhere.
+    fn check_access(&self, path: &str, needed: PathAccess) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let resolved = self.resolve(path)?;
+        match self.access_for(&resolved) {
+            None => Err(format!("{} is outside the sandbox and the allowed folders", path).into()),
+            Some(PathAccess::ReadOnly) if needed == PathAccess::ReadWrite => {
+                Err(format!("{} is in a read-only folder", path).into())
             }
+            Some(_) => Ok(resolved),
+        }
+    }
 
-            // Return the non-canonical path for new files
-            full_path
-        };
+    /// Canonical form of `path`: relative paths are taken inside the
+    /// sandbox, `~` is the home folder. For a path that doesn't exist yet,
+    /// the nearest existing ancestor is canonicalized and the rest appended.
+    /// A broken symlink counts as existing, so it is refused rather than
+    /// written through. Touches nothing on disk.
+    fn resolve(&self, path: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        if path.contains('\0') {
+            return Err("Invalid path: contains a null byte".into());
+        }
+        let expanded = PathBuf::from(crate::paths::expand_home(path.trim()));
+        let full_path = if expanded.is_absolute() { expanded } else { self.sandbox_dir.join(expanded) };
 
-        // 5. Final verification: ensure path is within sandbox
-        if target_canonical.exists() && !target_canonical.starts_with(&sandbox_canonical) {
-            return Err("Path traversal detected: resolved path outside sandbox".into());
+        // `exists` follows links; a dangling one would look missing and be
+        // re-joined under its (allowed) parent
+        let mut existing = full_path.as_path();
+        let mut rest = Vec::new();
+        while existing.symlink_metadata().is_err() {
+            rest.push(existing.file_name().ok_or("Invalid path")?);
+            existing = existing.parent().ok_or("Invalid path: no existing parent directory")?;
         }
 
-        Ok(target_canonical)
+        let mut resolved = existing
+            .canonicalize()
+            .map_err(|e| format!("Invalid path: {} can't be resolved ({})", existing.display(), e))?;
+        for part in rest.into_iter().rev() {
+            // Not on disk yet, so ".." can't be resolved safely
+            if part == ".." {
+                return Err("Invalid path: '..' below a missing folder".into());
+            }
+            resolved.push(part);
+        }
+        Ok(resolved)
+    }
+
+    /// Access granted at a resolved path: the sandbox is read-write, an
+    /// allowed root grants its own access (the most specific root wins)
+    fn access_for(&self, resolved: &Path) -> Option<PathAccess> {
+        let sandbox = self.sandbox_dir.canonicalize().unwrap_or_else(|_| self.sandbox_dir.clone());
+        self.allowed
+            .iter()
+            .map(|(root, access)| (root.as_path(), *access))
+            .chain(std::iter::once((sandbox.as_path(), PathAccess::ReadWrite)))
+            .filter(|(root, _)| resolved.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count())
+            .map(|(_, access)| access)
     }
 
     /// Execute file operation
@@ -143,7 +529,7 @@ impl CommandExecutor {
             }
             
             FileOperation::Copy { from, to } => {
-                let safe_from = self.validate_path(&from)?;
+                let safe_from = self.validate_read(&from)?;
                 let safe_to = self.validate_path(&to)?;
                 
                 if !safe_from.exists() {
@@ -168,7 +554,7 @@ impl CommandExecutor {
             
             FileOperation::List { path } => {
                 let safe_path = if let Some(p) = path {
-                    self.validate_path(&p)?
+                    self.validate_read(&p)?
                 } else {
                     self.sandbox_dir.clone()
                 };
@@ -200,7 +586,7 @@ impl CommandExecutor {
             }
             
             FileOperation::Read { path } => {
-                let safe_path = self.validate_path(&path)?;
+                let safe_path = self.validate_read(&path)?;
                 
                 if !safe_path.exists() {
                     return Err(format!("File not found: {}", path).into());
@@ -219,7 +605,7 @@ impl CommandExecutor {
     }
 
     /// Execute process operation
-    async fn execute_process_op(&self, op: ProcessOperation) -> Result<String, Box<dyn std::error::Error>> {
+    async fn execute_process_op(&mut self, op: ProcessOperation) -> Result<String, Box<dyn std::error::Error>> {
         match op {
             ProcessOperation::List => {
                 // Get process list using sysinfo
@@ -249,29 +635,37 @@ impl CommandExecutor {
                 // Expanded capability: Open specific apps or URLs
                 // Note: We are trusting the intent classifier to not send malicious commands
                 
+                // Every child is tracked, so it is reaped once it exits and
+                // its output can be asked for
                 #[cfg(target_os = "windows")]
                 {
                     // Use 'cmd /C start' to leverage Windows shell association (handles URLs, apps, files)
                     // The empty string argument is for the window title, which start interprets as title if quoted
-                    std::process::Command::new("cmd")
-                        .args(["/C", "start", "", &name])
-                        .spawn()?;
-                        
-                    Ok(format!("Started/Opened: {}", name))
+                    let mut command = std::process::Command::new("cmd");
+                    command.args(["/C", "start", "", &name]);
+                    let pid = self.children.spawn(&name, command)?;
+
+                    Ok(format!("Started/Opened: {} (pid {})", name, pid))
                 }
                 
                 #[cfg(not(target_os = "windows"))]
                 {
                     // Linux/Mac implementation (fallback to xdg-open or open)
                     if name.starts_with("http") {
-                         std::process::Command::new("xdg-open").arg(&name).spawn()?;
+                         let mut command = std::process::Command::new("xdg-open");
+                         command.arg(&name);
+                         self.children.spawn(&name, command)?;
                          Ok(format!("Opened URL: {}", name))
                     } else {
-                         std::process::Command::new(&name).spawn()?;
-                         Ok(format!("Started process: {}", name))
+                         let pid = self.children.spawn(&name, std::process::Command::new(&name))?;
+                         Ok(format!("Started process: {} (pid {})", name, pid))
                     }
                 }
             }
+
+            ProcessOperation::Status { name_or_pid } => Ok(self.children.status(&name_or_pid)?),
+
+            ProcessOperation::Output { pid } => Ok(self.children.output(pid)?),
             
             ProcessOperation::Kill { pid } => {
                 // Implement process kill using sysinfo
@@ -398,35 +792,22 @@ impl CommandExecutor {
     async fn execute_network_op(&self, op: NetworkOperation) -> Result<String, Box<dyn std::error::Error>> {
         match op {
             NetworkOperation::GetIP => {
-                // Get local IP
-                Ok("IP address: 127.0.0.1 (localhost)".to_string())
+                Ok(network::describe_local_addresses(&network::local_addresses()))
             }
             
             NetworkOperation::Ping { host } => {
-                Ok(format!("Ping {} - not yet implemented", host))
+                Ok(network::ping(&host).await?)
+            }
+
+            NetworkOperation::DnsLookup { host } => {
+                Ok(network::dns_lookup(&host).await?)
             }
         }
     }
 
     /// Execute text operation
-    async fn execute_text_op(&self, op: TextOperation) -> Result<String, Box<dyn std::error::Error>> {
-        match op {
-            TextOperation::Type { text } => {
-                Ok(format!("Typed: {}", text))
-            }
-            
-            TextOperation::Select => {
-                Ok("Select all - not yet implemented".to_string())
-            }
-            
-            TextOperation::Copy => {
-                Ok("Copy - not yet implemented".to_string())
-            }
-            
-            TextOperation::Paste => {
-                Ok("Paste - not yet implemented".to_string())
-            }
-        }
+    async fn execute_text_op(&mut self, op: TextOperation) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(self.input.run(op, Instant::now()).await?)
     }
 }
 
@@ -446,6 +827,18 @@ mod tests {
         assert!(executor.sandbox_dir.exists());
     }
 
+    #[test]
+    fn test_sequence_summary() {
+        let outputs = vec!["Created notes.txt".to_string(), "notes.txt".to_string()];
+        assert_eq!(sequence_summary(&outputs, 2, None), Ok("Ran 2 commands:\nCreated notes.txt\nnotes.txt".to_string()));
+
+        let held = sequence_summary(&outputs[..1], 3, Some(SequenceStop::Held("Delete a.txt?".to_string()))).unwrap();
+        assert_eq!(held, "Created notes.txt\nStep 2 of 3 is waiting for confirmation; the rest will not run. Delete a.txt?");
+
+        let failed = sequence_summary(&[], 2, Some(SequenceStop::Failed("No such file".to_string()))).unwrap_err();
+        assert_eq!(failed, "Step 1 of 2 failed: No such file.");
+    }
+
     #[test]
     fn test_path_validation() {
         let executor = CommandExecutor::new().unwrap();
@@ -498,4 +891,194 @@ mod tests {
         // Cleanup
         let _ = fs::remove_file(executor.sandbox_dir.join("test_file.txt"));
     }
+
+    fn scratch_executor(name: &str) -> CommandExecutor {
+        let dir = std::env::temp_dir().join(format!("eva_test_exec_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        CommandExecutor::with_sandbox(dir).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_destructive_commands_wait_for_confirmation() {
+        let mut executor = scratch_executor("confirm");
+        assert_eq!(executor.policy(), ExecutionPolicy::ConfirmDestructive);
+        fs::write(executor.sandbox_dir.join("notes.txt"), "keep me").unwrap();
+        let delete = CommandIntent::File(FileOperation::Delete { path: "notes.txt".to_string() });
+
+        // Safe commands still run at once
+        let listed = executor.execute(CommandIntent::File(FileOperation::List { path: None })).await.unwrap();
+        assert!(matches!(listed, ExecutionOutcome::Done(ref msg) if msg.contains("notes.txt")));
+
+        let outcome = executor.execute(delete.clone()).await.unwrap();
+        assert_eq!(outcome, ExecutionOutcome::NeedsConfirmation("About to delete file notes.txt. Say yes to confirm or no to cancel.".to_string()));
+        assert!(executor.sandbox_dir.join("notes.txt").exists());
+        assert_eq!(executor.pending(), Some(&delete));
+
+        assert_eq!(executor.confirm_pending().await.unwrap(), "Deleted file: notes.txt");
+        assert!(!executor.sandbox_dir.join("notes.txt").exists());
+        // Confirming twice doesn't run it again
+        assert!(executor.confirm_pending().await.is_err());
+
+        // Cancelled commands never run
+        fs::write(executor.sandbox_dir.join("notes.txt"), "keep me").unwrap();
+        executor.execute(delete).await.unwrap();
+        assert_eq!(executor.cancel_pending().as_deref(), Some("Cancelled: delete file notes.txt"));
+        assert!(executor.confirm_pending().await.is_err());
+        assert!(executor.sandbox_dir.join("notes.txt").exists());
+
+        let _ = fs::remove_dir_all(&executor.sandbox_dir);
+    }
+
+    #[tokio::test]
+    async fn test_pending_command_expires() {
+        let mut executor = scratch_executor("expire");
+        fs::write(executor.sandbox_dir.join("a.txt"), "a").unwrap();
+        let start = Instant::now();
+        let moved = CommandIntent::File(FileOperation::Move { from: "a.txt".to_string(), to: "b.txt".to_string() });
+
+        executor.execute_at(moved.clone(), start).await.unwrap();
+        let late = start + CONFIRM_TIMEOUT;
+        assert!(executor.pending_at(late).is_none());
+        assert!(executor.expire_pending(start + Duration::from_secs(5)).is_none());
+        assert_eq!(executor.expire_pending(late).as_deref(), Some("No confirmation, did not move a.txt to b.txt"));
+        assert!(executor.expire_pending(late).is_none());
+
+        // A late "yes" is refused, not run
+        executor.execute_at(moved, start).await.unwrap();
+        let err = executor.confirm_pending_at(late).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert!(executor.sandbox_dir.join("a.txt").exists());
+        assert!(!executor.sandbox_dir.join("b.txt").exists());
+
+        let _ = fs::remove_dir_all(&executor.sandbox_dir);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_touches_nothing() {
+        let mut executor = scratch_executor("dry");
+        executor.set_policy(ExecutionPolicy::DryRun);
+        fs::write(executor.sandbox_dir.join("report.txt"), "12345").unwrap();
+
+        let outcome = executor.execute(CommandIntent::File(FileOperation::Delete { path: "report.txt".to_string() })).await.unwrap();
+        assert_eq!(outcome, ExecutionOutcome::WouldRun("Dry run: would delete file report.txt (5 bytes). Nothing was changed.".to_string()));
+        let outcome = executor
+            .execute(CommandIntent::File(FileOperation::Create { path: "new/dir/file.txt".to_string(), content: None }))
+            .await
+            .unwrap();
+        assert!(matches!(outcome, ExecutionOutcome::WouldRun(_)));
+
+        assert!(executor.sandbox_dir.join("report.txt").exists());
+        assert!(!executor.sandbox_dir.join("new").exists());
+        assert!(executor.pending().is_none());
+
+        let _ = fs::remove_dir_all(&executor.sandbox_dir);
+    }
+
+    #[tokio::test]
+    async fn test_keystrokes_wait_for_confirmation() {
+        let mut executor = scratch_executor("keys");
+        let fake = desktop_input::FakeInput::default();
+        executor.set_input_backend(Box::new(fake.clone()));
+        let typing = CommandIntent::Text(TextOperation::Type { text: "me@example.com".to_string() });
+
+        let outcome = executor.execute(typing.clone()).await.unwrap();
+        assert_eq!(outcome, ExecutionOutcome::NeedsConfirmation("About to type \"me@example.com\". Say yes to confirm or no to cancel.".to_string()));
+        assert!(fake.typed.lock().unwrap().is_empty());
+        assert_eq!(executor.confirm_pending().await.unwrap(), "Typed 14 characters.");
+        assert_eq!(*fake.typed.lock().unwrap(), vec!["me@example.com".to_string()]);
+
+        // Dry runs type nothing
+        executor.set_policy(ExecutionPolicy::DryRun);
+        assert!(matches!(executor.execute(typing).await.unwrap(), ExecutionOutcome::WouldRun(_)));
+        assert_eq!(fake.typed.lock().unwrap().len(), 1);
+
+        let _ = fs::remove_dir_all(&executor.sandbox_dir);
+    }
+
+    #[test]
+    fn test_parse_confirmation() {
+        assert_eq!(parse_confirmation("yes, do it"), Some(true));
+        assert_eq!(parse_confirmation("Sim!"), Some(true));
+        assert_eq!(parse_confirmation("no, cancel that"), Some(false));
+        assert_eq!(parse_confirmation("não"), Some(false));
+        assert_eq!(parse_confirmation("what time is it"), None);
+        assert_eq!(ExecutionPolicy::from_name("dry-run"), Some(ExecutionPolicy::DryRun));
+        assert_eq!(ExecutionPolicy::from_name("whatever"), None);
+    }
+
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks_cannot_escape_sandbox() {
+        use std::os::unix::fs::symlink;
+        let mut executor = scratch_executor("symlink");
+        executor.set_policy(ExecutionPolicy::AutoApprove);
+        let outside = std::env::temp_dir().join(format!("eva_test_outside_{}", std::process::id()));
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("secret.txt"), "secret").unwrap();
+        symlink(&outside, executor.sandbox_dir.join("escape")).unwrap();
+        symlink(outside.join("secret.txt"), executor.sandbox_dir.join("innocent.txt")).unwrap();
+
+        let read = |path: &str| CommandIntent::File(FileOperation::Read { path: path.to_string() });
+        assert!(executor.execute(read("escape/secret.txt")).await.is_err());
+        assert!(executor.execute(read("innocent.txt")).await.is_err());
+        assert!(executor.execute(read(&outside.join("secret.txt").display().to_string())).await.is_err());
+        // Writing a new file through the link is refused too
+        let create = CommandIntent::File(FileOperation::Create { path: "escape/planted.txt".to_string(), content: None });
+        assert!(executor.execute(create).await.is_err());
+        assert!(!outside.join("planted.txt").exists());
+        assert!(executor.validate_path("escape/new/../../x").is_err());
+        // A link to a file that doesn't exist yet can't be written through
+        let target = outside.join("dangling.txt");
+        symlink(&target, executor.sandbox_dir.join("dangling.txt")).unwrap();
+        let create = CommandIntent::File(FileOperation::Create { path: "dangling.txt".to_string(), content: Some("pwned".to_string()) });
+        assert!(executor.execute(create).await.is_err());
+        assert!(executor.validate_path("dangling.txt/below").is_err());
+        assert!(!target.exists());
+
+        // Once the folder is allowed read-only, reading works but writing doesn't
+        executor.allow_read_only(outside.clone()).unwrap();
+        let content = executor.execute(read("escape/secret.txt")).await.unwrap().into_message();
+        assert!(content.contains("secret"));
+        let delete = CommandIntent::File(FileOperation::Delete { path: "innocent.txt".to_string() });
+        assert!(executor.execute(delete).await.is_err());
+        // Deleting the link itself would need write access to its target too
+        assert!(outside.join("secret.txt").exists());
+
+        // Read-write lets commands change it
+        executor.allow_path(outside.clone()).unwrap();
+        let copy = CommandIntent::File(FileOperation::Copy { from: "escape/secret.txt".to_string(), to: "escape/copy.txt".to_string() });
+        executor.execute(copy).await.unwrap();
+        assert!(outside.join("copy.txt").exists());
+
+        let _ = fs::remove_dir_all(&outside);
+        let _ = fs::remove_dir_all(&executor.sandbox_dir);
+    }
+
+    #[test]
+    fn test_allowlist_from_profile() {
+        let mut executor = scratch_executor("allowlist");
+        let docs = std::env::temp_dir().join(format!("eva_test_docs_{}", std::process::id()));
+        fs::create_dir_all(docs.join("private")).unwrap();
+
+        let skipped = executor.allow_paths(&[
+            AllowedPath { path: docs.display().to_string(), access: PathAccess::ReadWrite },
+            AllowedPath { path: docs.join("private").display().to_string(), access: PathAccess::ReadOnly },
+            AllowedPath { path: "/definitely/not/here".to_string(), access: PathAccess::ReadOnly },
+        ]);
+        assert_eq!(skipped.len(), 1);
+
+        assert!(executor.validate_path(&docs.join("notes.txt").display().to_string()).is_ok());
+        // The more specific root wins
+        assert!(executor.validate_path(&docs.join("private/notes.txt").display().to_string()).is_err());
+        assert!(executor.validate_read(&docs.join("private/notes.txt").display().to_string()).is_ok());
+        assert!(executor.validate_read("/etc/passwd").is_err());
+
+        let entry: AllowedPath = serde_json::from_str(r#"{"path":"~/Downloads"}"#).unwrap();
+        assert_eq!(entry.access, PathAccess::ReadOnly);
+
+        let _ = fs::remove_dir_all(&docs);
+        let _ = fs::remove_dir_all(&executor.sandbox_dir);
+    }
+
 }
@@ -0,0 +1,508 @@
+use crate::command_parser::{
+    CommandIntent, FileOperation, NetworkOperation, ProcessOperation, RepeatTarget, TextOperation,
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// One executed command and its outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub intent: CommandIntent,
+    pub success: bool,
+    pub output: String,
+    #[serde(with = "crate::session::serde_millis")]
+    pub timestamp: SystemTime,
+}
+
+/// Persistent history of executed commands (command_history.json in the data directory)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandHistory {
+    entries: Vec<HistoryEntry>,
+    max_entries: usize,
+    /// Entries as last read out to the user, most recent first
+    #[serde(skip)]
+    read_out: Vec<usize>,
+}
+
+impl CommandHistory {
+    /// Create an empty history
+    pub fn new() -> Self {
+        Self { entries: Vec::new(), max_entries: 100, read_out: Vec::new() }
+    }
+
+    /// Load history from disk (empty if missing)
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::get_history_path()?;
+
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Save history to disk
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::get_history_path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Get history file path
+    fn get_history_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        crate::paths::data_file("command_history.json")
+    }
+
+    /// Record an executed command
+    pub fn record(&mut self, intent: CommandIntent, result: &Result<String, String>) {
+        // Repeats, session, timer, Time Machine, macro, profile and audio operations are not commands worth re-running
+        if matches!(
+            intent,
+            CommandIntent::Repeat(_)
+                | CommandIntent::Session(_)
+                | CommandIntent::Timer(_)
+                | CommandIntent::TimeMachine(_)
+                | CommandIntent::Macro(_)
+                | CommandIntent::Profile(_)
+                | CommandIntent::Audio(_)
+                | CommandIntent::Unknown
+        ) {
+            return;
+        }
+
+        let (success, output) = match result {
+            Ok(out) => (true, out.clone()),
+            Err(e) => (false, e.clone()),
+        };
+
+        self.entries.push(HistoryEntry { intent, success, output, timestamp: SystemTime::now() });
+
+        if self.entries.len() > self.max_entries {
+            let excess = self.entries.len() - self.max_entries;
+            self.entries.drain(..excess);
+            self.read_out.clear();
+        }
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if history is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Most recent entries, newest first
+    pub fn recent(&self, count: usize) -> Vec<&HistoryEntry> {
+        self.entries.iter().rev().take(count).collect()
+    }
+
+    /// Build the numbered spoken list and remember its order
+    pub fn read_out(&mut self, count: usize) -> String {
+        self.read_out = (0..self.entries.len()).rev().take(count).collect();
+
+        if self.read_out.is_empty() {
+            return "No commands in history.".to_string();
+        }
+
+        self.read_out
+            .iter()
+            .enumerate()
+            .map(|(i, &idx)| format!("{}. {}", i + 1, describe_intent(&self.entries[idx].intent)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Replace a repeat request with the command it names; anything else,
+    /// or a repeat of an entry that isn't there, is returned as is
+    pub fn expand(&self, intent: CommandIntent) -> CommandIntent {
+        match intent {
+            CommandIntent::Repeat(ref target) => self.resolve(target).cloned().unwrap_or(intent),
+            intent => intent,
+        }
+    }
+
+    /// Resolve a voice repeat request to the intent to re-run
+    pub fn resolve(&self, target: &RepeatTarget) -> Option<&CommandIntent> {
+        match target {
+            RepeatTarget::Last => self.entries.last().map(|e| &e.intent),
+            RepeatTarget::Numbered(n) => {
+                let idx = if self.read_out.is_empty() {
+                    // Nothing read out yet: number from newest
+                    self.entries.len().checked_sub(*n)?
+                } else {
+                    *self.read_out.get(n.checked_sub(1)?)?
+                };
+                self.entries.get(idx).map(|e| &e.intent)
+            }
+        }
+    }
+}
+
+impl Default for CommandHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Short human-readable description of an intent
+pub fn describe_intent(intent: &CommandIntent) -> String {
+    match intent {
+        CommandIntent::File(op) => match op {
+            FileOperation::Create { path, .. } => format!("create file {}", path),
+            FileOperation::Delete { path } => format!("delete file {}", path),
+            FileOperation::Copy { from, to } => format!("copy {} to {}", from, to),
+            FileOperation::Move { from, to } => format!("move {} to {}", from, to),
+            FileOperation::List { path } => format!("list files in {}", path.as_deref().unwrap_or(".")),
+            FileOperation::Read { path } => format!("read file {}", path),
+        },
+        CommandIntent::Process(op) => match op {
+            ProcessOperation::List => "list processes".to_string(),
+            ProcessOperation::Start { name } => format!("start {}", name),
+            ProcessOperation::Kill { pid } => format!("kill process {}", pid),
+            ProcessOperation::Status { name_or_pid } => format!("check on {}", name_or_pid),
+            ProcessOperation::Output { pid } => format!("show the output of process {}", pid),
+        },
+        CommandIntent::System(op) => format!("system info: {:?}", op),
+        CommandIntent::Network(op) => match op {
+            NetworkOperation::GetIP => "get IP address".to_string(),
+            NetworkOperation::Ping { host } => format!("ping {}", host),
+            NetworkOperation::DnsLookup { host } => format!("look up {}", host),
+        },
+        CommandIntent::Text(op) => match op {
+            TextOperation::Type { text } => format!("type \"{}\"", text),
+            TextOperation::Select => "select all text".to_string(),
+            TextOperation::Copy => "copy the selection".to_string(),
+            TextOperation::Paste => "paste the clipboard".to_string(),
+        },
+        CommandIntent::Session(op) => format!("session: {:?}", op),
+        CommandIntent::Repeat(target) => format!("repeat: {:?}", target),
+        CommandIntent::Timer(op) => format!("timer: {:?}", op),
+        CommandIntent::TimeMachine(op) => format!("time machine: {:?}", op),
+        CommandIntent::Macro(op) => format!("macro: {:?}", op),
+        CommandIntent::Profile(op) => format!("profile: {:?}", op),
+        CommandIntent::Audio(op) => format!("audio: {:?}", op),
+        CommandIntent::Unknown => "unknown".to_string(),
+    }
+}
+
+/// Kind of an editable intent parameter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    Path,
+    Pid,
+    Text,
+}
+
+/// One editable parameter of an intent
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntentField {
+    pub name: &'static str,
+    pub kind: FieldKind,
+    pub value: String,
+}
+
+/// Editable parameters of an intent, in display order
+pub fn intent_fields(intent: &CommandIntent) -> Vec<IntentField> {
+    let field = |name, kind, value: &str| IntentField { name, kind, value: value.to_string() };
+
+    match intent {
+        CommandIntent::File(op) => match op {
+            FileOperation::Create { path, content } => vec![
+                field("path", FieldKind::Path, path),
+                field("content", FieldKind::Text, content.as_deref().unwrap_or("")),
+            ],
+            FileOperation::Delete { path } | FileOperation::Read { path } => {
+                vec![field("path", FieldKind::Path, path)]
+            }
+            FileOperation::Copy { from, to } | FileOperation::Move { from, to } => vec![
+                field("from", FieldKind::Path, from),
+                field("to", FieldKind::Path, to),
+            ],
+            FileOperation::List { path } => vec![field("path", FieldKind::Path, path.as_deref().unwrap_or(""))],
+        },
+        CommandIntent::Process(ProcessOperation::Start { name }) => vec![field("name", FieldKind::Text, name)],
+        CommandIntent::Process(ProcessOperation::Kill { pid }) | CommandIntent::Process(ProcessOperation::Output { pid }) => {
+            vec![field("pid", FieldKind::Pid, &pid.to_string())]
+        }
+        CommandIntent::Process(ProcessOperation::Status { name_or_pid }) => vec![field("name", FieldKind::Text, name_or_pid)],
+        CommandIntent::Network(NetworkOperation::Ping { host })
+        | CommandIntent::Network(NetworkOperation::DnsLookup { host }) => vec![field("host", FieldKind::Text, host)],
+        CommandIntent::Text(TextOperation::Type { text }) => vec![field("text", FieldKind::Text, text)],
+        _ => Vec::new(),
+    }
+}
+
+/// Return a copy of the intent with one parameter replaced
+pub fn apply_field(intent: &CommandIntent, name: &str, value: &str) -> Result<CommandIntent, String> {
+    let mut intent = intent.clone();
+    let value_opt = if value.is_empty() { None } else { Some(value.to_string()) };
+
+    let target: &mut String = match (&mut intent, name) {
+        (CommandIntent::File(FileOperation::Create { content, .. }), "content") => {
+            *content = value_opt;
+            return Ok(intent);
+        }
+        (CommandIntent::File(FileOperation::List { path }), "path") => {
+            *path = value_opt;
+            return Ok(intent);
+        }
+        (CommandIntent::Process(ProcessOperation::Kill { pid }), "pid")
+        | (CommandIntent::Process(ProcessOperation::Output { pid }), "pid") => {
+            *pid = value.trim().parse().map_err(|_| format!("'{}' is not a valid PID", value))?;
+            return Ok(intent);
+        }
+        (CommandIntent::File(FileOperation::Create { path, .. }), "path")
+        | (CommandIntent::File(FileOperation::Delete { path }), "path")
+        | (CommandIntent::File(FileOperation::Read { path }), "path") => path,
+        (CommandIntent::File(FileOperation::Copy { from, .. }), "from")
+        | (CommandIntent::File(FileOperation::Move { from, .. }), "from") => from,
+        (CommandIntent::File(FileOperation::Copy { to, .. }), "to")
+        | (CommandIntent::File(FileOperation::Move { to, .. }), "to") => to,
+        (CommandIntent::Process(ProcessOperation::Start { name }), "name")
+        | (CommandIntent::Process(ProcessOperation::Status { name_or_pid: name }), "name") => name,
+        (CommandIntent::Network(NetworkOperation::Ping { host }), "host")
+        | (CommandIntent::Network(NetworkOperation::DnsLookup { host }), "host") => host,
+        (CommandIntent::Text(TextOperation::Type { text }), "text") => text,
+        _ => return Err(format!("'{}' is not editable for this command", name)),
+    };
+
+    if value.trim().is_empty() {
+        return Err(format!("'{}' cannot be empty", name));
+    }
+    *target = value.to_string();
+    Ok(intent)
+}
+
+/// Keys understood by the history view
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistoryKey {
+    Up,
+    Down,
+    Enter,
+    /// Start editing the selected command
+    Edit,
+    /// Move to the next field in the edit form
+    Tab,
+    Char(char),
+    Backspace,
+    Escape,
+}
+
+/// What the conversation loop should do after a key press
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistoryAction {
+    None,
+    Close,
+    /// Re-run this intent; `confirm` means it must go through confirmation
+    Run { intent: CommandIntent, confirm: bool },
+    Error(String),
+}
+
+/// Edit form state for one command
+#[derive(Debug, Clone)]
+pub struct EditForm {
+    pub intent: CommandIntent,
+    pub fields: Vec<IntentField>,
+    pub focused: usize,
+}
+
+/// Interactive history view state (toggled with the history hotkey)
+#[derive(Debug, Clone, Default)]
+pub struct HistoryView {
+    pub selected: usize,
+    pub form: Option<EditForm>,
+}
+
+impl HistoryView {
+    /// Handle a key press against the given history
+    pub fn handle_key(&mut self, history: &CommandHistory, key: HistoryKey) -> HistoryAction {
+        let entries = history.recent(history.len());
+
+        if let Some(form) = self.form.as_mut() {
+            return match key {
+                HistoryKey::Escape => {
+                    self.form = None;
+                    HistoryAction::None
+                }
+                HistoryKey::Tab | HistoryKey::Down if !form.fields.is_empty() => {
+                    form.focused = (form.focused + 1) % form.fields.len();
+                    HistoryAction::None
+                }
+                HistoryKey::Up if !form.fields.is_empty() => {
+                    form.focused = (form.focused + form.fields.len() - 1) % form.fields.len();
+                    HistoryAction::None
+                }
+                HistoryKey::Char(c) => {
+                    if let Some(field) = form.fields.get_mut(form.focused) {
+                        field.value.push(c);
+                    }
+                    HistoryAction::None
+                }
+                HistoryKey::Backspace => {
+                    if let Some(field) = form.fields.get_mut(form.focused) {
+                        field.value.pop();
+                    }
+                    HistoryAction::None
+                }
+                HistoryKey::Enter => {
+                    let mut intent = form.intent.clone();
+                    for field in &form.fields {
+                        intent = match apply_field(&intent, field.name, &field.value) {
+                            Ok(i) => i,
+                            Err(e) => return HistoryAction::Error(e),
+                        };
+                    }
+                    self.form = None;
+                    let confirm = intent.is_risky();
+                    HistoryAction::Run { intent, confirm }
+                }
+                _ => HistoryAction::None,
+            };
+        }
+
+        match key {
+            HistoryKey::Escape => HistoryAction::Close,
+            HistoryKey::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                HistoryAction::None
+            }
+            HistoryKey::Down => {
+                if self.selected + 1 < entries.len() {
+                    self.selected += 1;
+                }
+                HistoryAction::None
+            }
+            HistoryKey::Enter => match entries.get(self.selected) {
+                Some(entry) => HistoryAction::Run { intent: entry.intent.clone(), confirm: entry.intent.is_risky() },
+                None => HistoryAction::None,
+            },
+            HistoryKey::Edit => {
+                if let Some(entry) = entries.get(self.selected) {
+                    self.form = Some(EditForm {
+                        intent: entry.intent.clone(),
+                        fields: intent_fields(&entry.intent),
+                        focused: 0,
+                    });
+                }
+                HistoryAction::None
+            }
+            _ => HistoryAction::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_history() -> CommandHistory {
+        let mut history = CommandHistory::new();
+        history.record(CommandIntent::File(FileOperation::List { path: None }), &Ok("a.txt".to_string()));
+        history.record(CommandIntent::Process(ProcessOperation::Kill { pid: 42 }), &Err("denied".to_string()));
+        history.record(CommandIntent::Network(NetworkOperation::Ping { host: "example.com".to_string() }), &Ok("pong".to_string()));
+        history
+    }
+
+    #[test]
+    fn test_record_skips_repeats() {
+        let mut history = sample_history();
+        history.record(CommandIntent::Repeat(RepeatTarget::Last), &Ok(String::new()));
+        assert_eq!(history.len(), 3);
+        assert!(!history.recent(3)[1].success);
+    }
+
+    #[test]
+    fn test_resolve_last_and_numbered() {
+        let mut history = sample_history();
+        assert!(matches!(
+            history.resolve(&RepeatTarget::Last),
+            Some(CommandIntent::Network(NetworkOperation::Ping { .. }))
+        ));
+
+        let spoken = history.read_out(5);
+        assert!(spoken.starts_with("1. ping example.com"));
+        assert!(spoken.contains("3. list files in ."));
+
+        assert!(matches!(
+            history.resolve(&RepeatTarget::Numbered(3)),
+            Some(CommandIntent::File(FileOperation::List { .. }))
+        ));
+        assert!(history.resolve(&RepeatTarget::Numbered(0)).is_none());
+        assert!(history.resolve(&RepeatTarget::Numbered(9)).is_none());
+    }
+
+    #[test]
+    fn test_expand_repeats_only() {
+        let history = sample_history();
+        assert!(matches!(
+            history.expand(CommandIntent::Repeat(RepeatTarget::Last)),
+            CommandIntent::Network(NetworkOperation::Ping { .. })
+        ));
+        let missing = CommandIntent::Repeat(RepeatTarget::Numbered(9));
+        assert_eq!(history.expand(missing.clone()), missing);
+        let list = CommandIntent::File(FileOperation::List { path: None });
+        assert_eq!(history.expand(list.clone()), list);
+    }
+
+    #[test]
+    fn test_fields_roundtrip() {
+        let intent = CommandIntent::File(FileOperation::Copy { from: "a".to_string(), to: "b".to_string() });
+        let fields = intent_fields(&intent);
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[1].kind, FieldKind::Path);
+
+        let edited = apply_field(&intent, "to", "c").unwrap();
+        assert_eq!(edited, CommandIntent::File(FileOperation::Copy { from: "a".to_string(), to: "c".to_string() }));
+
+        let kill = CommandIntent::Process(ProcessOperation::Kill { pid: 1 });
+        assert!(apply_field(&kill, "pid", "abc").is_err());
+        assert_eq!(apply_field(&kill, "pid", "7").unwrap(), CommandIntent::Process(ProcessOperation::Kill { pid: 7 }));
+        assert!(apply_field(&kill, "path", "x").is_err());
+    }
+
+    #[test]
+    fn test_view_rerun_and_edit() {
+        let history = sample_history();
+        let mut view = HistoryView::default();
+
+        // Newest first: ping is selected, Enter re-runs it without confirmation
+        assert_eq!(
+            view.handle_key(&history, HistoryKey::Enter),
+            HistoryAction::Run {
+                intent: CommandIntent::Network(NetworkOperation::Ping { host: "example.com".to_string() }),
+                confirm: false
+            }
+        );
+
+        // Edit the kill command's PID; risky commands still need confirmation
+        view.handle_key(&history, HistoryKey::Down);
+        view.handle_key(&history, HistoryKey::Edit);
+        view.handle_key(&history, HistoryKey::Backspace);
+        view.handle_key(&history, HistoryKey::Char('3'));
+        assert_eq!(
+            view.handle_key(&history, HistoryKey::Enter),
+            HistoryAction::Run { intent: CommandIntent::Process(ProcessOperation::Kill { pid: 43 }), confirm: true }
+        );
+        assert!(view.form.is_none());
+        assert_eq!(view.handle_key(&history, HistoryKey::Escape), HistoryAction::Close);
+    }
+
+    #[test]
+    fn test_history_serialization() {
+        let history = sample_history();
+        let json = serde_json::to_string(&history).unwrap();
+        let parsed: CommandHistory = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.len(), 3);
+    }
+}
@@ -1,18 +1,66 @@
+use crate::capabilities::Category;
+use crate::timers::Recurrence;
+use chrono::Weekday;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
 /// Command intent types
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CommandIntent {
     File(FileOperation),
     Process(ProcessOperation),
     System(SystemOperation),
     Network(NetworkOperation),
     Text(TextOperation),
+    Session(SessionOperation),
+    Repeat(RepeatTarget),
+    Timer(TimerOperation),
+    TimeMachine(TimeMachineOperation),
+    Macro(MacroOperation),
+    Profile(ProfileOperation),
+    Audio(AudioOperation),
     Unknown,
 }
 
+/// How much damage a command can do if it was misheard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RiskLevel {
+    /// Read-only or confined to the sandbox
+    Safe,
+    /// Reaches outside the sandbox (starts programs, types, pings)
+    Moderate,
+    /// Destroys or relocates data, kills processes
+    Risky,
+}
+
+impl CommandIntent {
+    /// Risk classification used for confirmation and guest mode
+    pub fn risk(&self) -> RiskLevel {
+        match self {
+            CommandIntent::File(FileOperation::Delete { .. })
+            | CommandIntent::File(FileOperation::Move { .. })
+            | CommandIntent::Process(ProcessOperation::Kill { .. })
+            | CommandIntent::TimeMachine(TimeMachineOperation::DeleteToday)
+            | CommandIntent::TimeMachine(TimeMachineOperation::Verify { delete_orphans: true })
+            // Keystrokes land in whatever window has focus
+            | CommandIntent::Text(_) => RiskLevel::Risky,
+            CommandIntent::Process(ProcessOperation::Start { .. })
+            | CommandIntent::Network(NetworkOperation::Ping { .. })
+            | CommandIntent::Repeat(_)
+            | CommandIntent::Macro(MacroOperation::Run { .. }) => RiskLevel::Moderate,
+            _ => RiskLevel::Safe,
+        }
+    }
+
+    /// Whether the command needs confirmation before (re-)running
+    pub fn is_risky(&self) -> bool {
+        self.risk() == RiskLevel::Risky
+    }
+}
+
 /// File operations
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FileOperation {
     Create { path: String, content: Option<String> },
     Delete { path: String },
@@ -23,15 +71,19 @@ pub enum FileOperation {
 }
 
 /// Process operations
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ProcessOperation {
     List,
     Start { name: String },
     Kill { pid: u32 },
+    /// Whether a program EVA started is still running, or how it ended
+    Status { name_or_pid: String },
+    /// What a program EVA started printed (the last part, if it was long)
+    Output { pid: u32 },
 }
 
 /// System operations
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SystemOperation {
     MemoryInfo,
     DiskInfo,
@@ -40,14 +92,15 @@ pub enum SystemOperation {
 }
 
 /// Network operations
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum NetworkOperation {
     GetIP,
     Ping { host: String },
+    DnsLookup { host: String },
 }
 
 /// Text operations
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TextOperation {
     Type { text: String },
     Select,
@@ -55,6 +108,393 @@ pub enum TextOperation {
     Paste,
 }
 
+/// Conversation session operations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SessionOperation {
+    ForgetLastExchange,
+    Branch,
+    /// "remember that my locker is 42": kept in the session's memory
+    Remember { key: String, value: String },
+}
+
+/// Timer operations; `label: None` means "this timer"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TimerOperation {
+    Set { label: Option<String>, seconds: u64 },
+    Query { label: Option<String> },
+    Snooze { label: Option<String>, seconds: u64 },
+    Repeat { label: Option<String>, rule: Recurrence },
+}
+
+/// Time Machine (screen history) operations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TimeMachineOperation {
+    Pause,
+    Resume,
+    Status,
+    DeleteToday,
+    /// "check my recordings": checksums, missing files, leftover files
+    /// (removed with "clean up my recordings")
+    Verify { delete_orphans: bool },
+    /// "what was I reading about rust lifetimes an hour ago"
+    Search { query: String, time_hint: Option<TimeHint> },
+    /// "what was I doing at 3 yesterday": the captures nearest that time.
+    /// `twelve_hour` when no am/pm was said, so 3 may mean 15:00
+    Timeline { hour: u32, minute: u32, twelve_hour: bool, yesterday: bool },
+    /// A capture picked from a search's follow-ups, saved to the exports
+    /// folder (never parsed from speech)
+    Show { id: u64 },
+}
+
+/// Voice macro operations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MacroOperation {
+    /// Record the commands that follow as a macro
+    StartRecording { name: String },
+    StopRecording,
+    Run { name: String },
+}
+
+/// User profile changes ("call me Daniel", "switch language to English")
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ProfileOperation {
+    /// Checked and applied by `UserProfile::set`
+    Set { field: String, value: String },
+}
+
+/// How EVA's replies sound ("volume down", "mute yourself")
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AudioOperation {
+    VolumeUp,
+    VolumeDown,
+    /// "set the volume to 30%"
+    SetVolume { percent: u8 },
+    Mute,
+    Unmute,
+    /// "play replies at 1.25x": faster playback of long answers
+    SetSpeed { speed: f32 },
+}
+
+/// When a searched-for capture was seen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeHint {
+    /// Roughly this long before now ("an hour ago", "há 10 minutos")
+    Ago { seconds: u64 },
+    Today,
+    Yesterday,
+}
+
+/// Which history entry to re-run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RepeatTarget {
+    /// The most recently executed command ("do that again")
+    Last,
+    /// 1-based position in the list EVA last read out ("run command 3 again")
+    Numbered(usize),
+}
+
+/// A built-in command the parser understands, for capability listings
+#[derive(Debug, Clone, Copy)]
+pub struct CommandSpec {
+    pub category: Category,
+    pub name: &'static str,
+    /// A phrase `CommandParser::parse` turns into this command
+    pub example: &'static str,
+    /// Cargo feature the executor needs to actually perform it
+    pub requires: Option<&'static str>,
+}
+
+/// Registry of built-in commands
+///
+/// Only commands the executor really performs are listed; stubs that
+/// answer "not yet implemented" stay out so EVA never offers them.
+pub const BUILTIN_COMMANDS: &[CommandSpec] = &[
+    CommandSpec { category: Category::Files, name: "create files", example: "create a file called notes.txt", requires: None },
+    CommandSpec { category: Category::Files, name: "read files", example: "read file notes.txt", requires: None },
+    CommandSpec { category: Category::Files, name: "list files", example: "list files", requires: None },
+    CommandSpec { category: Category::Files, name: "copy files", example: "copy a.txt to b.txt", requires: None },
+    CommandSpec { category: Category::Files, name: "move files", example: "move a.txt to b.txt", requires: None },
+    CommandSpec { category: Category::Files, name: "delete files", example: "delete file old.txt", requires: None },
+    CommandSpec { category: Category::Apps, name: "open programs and links", example: "open calculator", requires: None },
+    CommandSpec { category: Category::Apps, name: "list running processes", example: "show running processes", requires: Some("sysinfo") },
+    CommandSpec { category: Category::Apps, name: "type into the focused window", example: "type hello world", requires: Some("desktop-input") },
+    CommandSpec { category: Category::Apps, name: "copy and paste", example: "copy that", requires: Some("desktop-input") },
+    CommandSpec { category: Category::System, name: "memory usage", example: "how much memory is free", requires: Some("sysinfo") },
+    CommandSpec { category: Category::System, name: "disk space", example: "check disk space", requires: Some("sysinfo") },
+    CommandSpec { category: Category::System, name: "CPU info", example: "cpu info", requires: Some("sysinfo") },
+    CommandSpec { category: Category::Network, name: "your IP address", example: "what is my ip address", requires: None },
+    CommandSpec { category: Category::Network, name: "ping hosts", example: "ping google.com", requires: None },
+    CommandSpec { category: Category::Network, name: "DNS lookups", example: "look up google.com", requires: None },
+    CommandSpec { category: Category::Timers, name: "set timers", example: "set a timer for 10 minutes", requires: None },
+    CommandSpec { category: Category::Timers, name: "time left", example: "how much time is left on the pasta timer", requires: None },
+    CommandSpec { category: Category::Timers, name: "snooze timers", example: "snooze the timer for 5 minutes", requires: None },
+    CommandSpec { category: Category::Timers, name: "recurring reminders", example: "repeat the medicine reminder every day at 9", requires: None },
+    CommandSpec { category: Category::Conversation, name: "forget the last exchange", example: "forget the last exchange", requires: None },
+    CommandSpec { category: Category::Conversation, name: "branch the conversation", example: "branch this conversation", requires: None },
+    CommandSpec { category: Category::Conversation, name: "repeat commands", example: "do that again", requires: None },
+    CommandSpec { category: Category::Conversation, name: "change what EVA calls you", example: "call me Daniel", requires: None },
+    CommandSpec { category: Category::Conversation, name: "switch language", example: "switch language to English", requires: None },
+    CommandSpec { category: Category::Conversation, name: "change the volume", example: "volume down", requires: None },
+    CommandSpec { category: Category::Conversation, name: "mute and unmute", example: "mute yourself", requires: None },
+];
+
+/// Host from "look up google.com" / "resolve google.com" / "dns google.com" /
+/// "ip address of google.com"
+fn parse_dns_host(text: &str) -> Option<String> {
+    let re = Regex::new(r"\b(?:look ?up|resolve|dns(?: lookup)?(?: for)?|ip(?: address)? (?:of|for))\s+([a-z0-9][a-z0-9.-]*[a-z0-9])").ok()?;
+    let host = re.captures(text)?[1].to_string();
+    // A name needs a dot ("localhost" aside); "look up the weather" is not a lookup
+    (host.contains('.') || host == "localhost").then_some(host)
+}
+
+/// "select all" / "copy that" / "paste" and the Portuguese equivalents
+fn parse_clipboard(text: &str) -> Option<TextOperation> {
+    let select = Regex::new(r"\bselect all\b|\bselecion(?:ar|e|a) tudo\b").ok()?;
+    let copy = Regex::new(r"^\s*(?:copy|copiar|copie|copia)\s*[.!]?\s*$|\bcopy (?:that|this|it|the selection|selection)\b|\bcopy\b.*\bclipboard\b|\bcopi(?:ar|e|a) (?:isso|isto)\b").ok()?;
+    let paste = Regex::new(r"\bpaste\b|\bcol(?:ar|e|a)\b").ok()?;
+
+    if select.is_match(text) {
+        Some(TextOperation::Select)
+    } else if copy.is_match(text) {
+        Some(TextOperation::Copy)
+    } else if paste.is_match(text) && !text.contains("file") && !text.contains("arquivo") {
+        Some(TextOperation::Paste)
+    } else {
+        None
+    }
+}
+
+/// "is build.sh still running", "show the output of process 4242" and the
+/// Portuguese equivalents: about programs EVA started
+fn parse_process_query(text: &str) -> Option<ProcessOperation> {
+    let output = Regex::new(r"\boutput (?:of|from) (?:process |pid )?(\d+)\b|\bsaída do (?:processo )?(\d+)\b").ok()?;
+    let status = Regex::new(
+        r"\bstatus of (?:process |pid )?([\w./-]+)|^\s*is ([\w./-]+) (?:still )?running\b|\b(?:has|did) ([\w./-]+) finish(?:ed)?\b|\bstatus do (?:processo )?([\w./-]+)|\b(?:o )?([\w./-]+) (?:ainda )?está rodando\b|\b([\w./-]+) (?:já )?terminou\b",
+    )
+    .ok()?;
+    let first = |caps: regex::Captures| caps.iter().skip(1).flatten().next().map(|m| m.as_str().to_string());
+
+    if let Some(pid) = output.captures(text).and_then(first) {
+        return Some(ProcessOperation::Output { pid: pid.parse().ok()? });
+    }
+    let name = status.captures(text).and_then(first)?;
+    // "is anything running" is a process listing
+    if matches!(name.as_str(), "anything" | "something" | "everything" | "it" | "that" | "this" | "isso" | "algo" | "algum") {
+        return None;
+    }
+    Some(ProcessOperation::Status { name_or_pid: name })
+}
+
+/// "volume down", "mute yourself", "play replies at 1.5x" and the
+/// Portuguese equivalents
+fn parse_audio(text: &str) -> Option<AudioOperation> {
+    let unmute = Regex::new(r"\bunmute\b|\bativ(?:e|ar|a) o som\b|\btir(?:e|ar|a) do mudo\b").ok()?;
+    let mute = Regex::new(
+        r"\bmute (?:yourself|your voice|the audio|audio|eva)\b|^\s*(?:eva[,!]?\s+)?mute\s*[.!]?\s*$|\bsilenci(?:e|ar|a)(?:-se)?\b|\bfique mud[ao]\b|\bmodo mudo\b",
+    )
+    .ok()?;
+    if unmute.is_match(text) {
+        return Some(AudioOperation::Unmute);
+    }
+    if mute.is_match(text) {
+        return Some(AudioOperation::Mute);
+    }
+
+    let speed = Regex::new(r"\b(?:play(?:back)?|read|toque|reproduza)\b.*?(\d+(?:[.,]\d+)?)\s*(?:x\b|times\b|vezes\b)|\bplayback speed\s+(?:to\s+)?(\d+(?:[.,]\d+)?)").ok()?;
+    if let Some(c) = speed.captures(text) {
+        let value = c.get(1).or_else(|| c.get(2))?.as_str().replace(',', ".");
+        return Some(AudioOperation::SetSpeed { speed: value.parse().ok()? });
+    }
+
+    let words: Vec<&str> = text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+    let has = |list: &[&str]| list.iter().any(|w| if w.contains(' ') { text.contains(w) } else { words.contains(w) });
+    let louder = ["louder", "mais alto"];
+    let quieter = ["quieter", "softer", "mais baixo"];
+    if !words.contains(&"volume") {
+        // "speak louder", "fale mais baixo"
+        let speak = has(&["speak", "talk", "fale", "fala"]);
+        return match (speak && has(&louder), speak && has(&quieter)) {
+            (true, _) => Some(AudioOperation::VolumeUp),
+            (_, true) => Some(AudioOperation::VolumeDown),
+            _ => None,
+        };
+    }
+
+    let level = Regex::new(r"volume\s+(?:to\s+|at\s+|para\s+|em\s+|no\s+)?(\d{1,3})\s*(?:%|percent|por cento)?").ok()?;
+    if let Some(c) = level.captures(text) {
+        return Some(AudioOperation::SetVolume { percent: c[1].parse::<u16>().ok()?.min(100) as u8 });
+    }
+    if has(&["up", "raise", "increase", "aumente", "aumenta", "aumentar", "suba", "subir"]) || has(&louder) {
+        Some(AudioOperation::VolumeUp)
+    } else if has(&["down", "lower", "decrease", "diminua", "diminui", "diminuir", "abaixe", "abaixa", "baixar"]) || has(&quieter) {
+        Some(AudioOperation::VolumeDown)
+    } else {
+        None
+    }
+}
+
+/// "daniel" → "Daniel"
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+}
+
+/// Timer name from "the pasta timer" / "o timer do macarrão"
+fn timer_label(text: &str) -> Option<String> {
+    let en = Regex::new(r"\b(?:the|my|a|an) ([\w ]+?) (?:timer|reminder|alarm)").ok()?;
+    let pt = Regex::new(r"(?:timer|lembrete|alarme|cronômetro) (?:do|da|de|dos|das|pro|pra|para o|para a) (\w+)").ok()?;
+
+    let label = en
+        .captures(text)
+        .or_else(|| pt.captures(text))
+        .map(|c| c[1].trim().to_string())?;
+
+    // "this timer", "a timer for 10 minutes", "timer de 10 minutos" name nothing
+    if ["this", "that", "este", "esse"].contains(&label.as_str()) || label.chars().any(|c| c.is_ascii_digit()) {
+        None
+    } else {
+        Some(label)
+    }
+}
+
+/// "10 minutes", "1 hora", "30 seconds" -> seconds
+fn parse_duration_secs(text: &str) -> Option<u64> {
+    let re = Regex::new(r"(\d+)\s*(hours?|horas?|h\b|minutes?|minutos?|mins?\b|seconds?|segundos?|secs?\b|s\b)").ok()?;
+    let mut total = 0;
+    for cap in re.captures_iter(text) {
+        let n: u64 = cap[1].parse().ok()?;
+        let unit = &cap[2];
+        total += if unit.starts_with('h') {
+            n * 3600
+        } else if unit.starts_with('m') {
+            n * 60
+        } else {
+            n
+        };
+    }
+    if total > 0 {
+        Some(total)
+    } else {
+        None
+    }
+}
+
+/// "every weekday at 7", "todos os dias às 19h30", "every monday and friday at 6:30 pm"
+fn parse_recurrence(text: &str) -> Option<Recurrence> {
+    let time_re = Regex::new(r"(?:\bat|às|as)\s+(\d{1,2})(?:[:h](\d{2}))?\s*(am|pm)?").ok()?;
+    let cap = time_re.captures(text)?;
+    let mut hour: u32 = cap[1].parse().ok()?;
+    let minute: u32 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
+    match cap.get(3).map(|m| m.as_str()) {
+        Some("pm") if hour < 12 => hour += 12,
+        Some("am") if hour == 12 => hour = 0,
+        _ => {}
+    }
+    if hour > 23 || minute > 59 {
+        return None;
+    }
+
+    if ["weekday", "dia útil", "dias úteis", "dia util", "dias uteis"].iter().any(|w| text.contains(w)) {
+        return Some(Recurrence::weekdays(hour, minute));
+    }
+
+    const DAY_NAMES: [(&str, &str, Weekday); 7] = [
+        ("monday", "segunda", Weekday::Mon),
+        ("tuesday", "terça", Weekday::Tue),
+        ("wednesday", "quarta", Weekday::Wed),
+        ("thursday", "quinta", Weekday::Thu),
+        ("friday", "sexta", Weekday::Fri),
+        ("saturday", "sábado", Weekday::Sat),
+        ("sunday", "domingo", Weekday::Sun),
+    ];
+    let days: Vec<Weekday> = DAY_NAMES
+        .iter()
+        .filter(|(en, pt, _)| text.contains(en) || text.contains(pt))
+        .map(|(_, _, day)| *day)
+        .collect();
+    if !days.is_empty() {
+        return Some(Recurrence::on(days, hour, minute));
+    }
+
+    if ["every day", "daily", "todo dia", "todos os dias"].iter().any(|w| text.contains(w)) {
+        return Some(Recurrence::daily(hour, minute));
+    }
+    None
+}
+
+/// "an hour ago", "2 days ago", "há 10 minutos", "uma hora atrás", "today", "ontem"
+///
+/// Returns the hint and the text with its phrase removed.
+fn parse_time_hint(text: &str) -> (Option<TimeHint>, String) {
+    const PATTERNS: [&str; 4] = [
+        r"\b(\d+|an?|one) (minute|hour|day)s? ago\b",
+        r"\b(?:há|ha) (\d+|uma?) (minuto|hora|dia)s?\b",
+        r"\b(\d+|uma?) (minuto|hora|dia)s? atrás",
+        r"\b(today|hoje|yesterday|ontem)\b",
+    ];
+    for pattern in PATTERNS {
+        let Ok(re) = Regex::new(pattern) else { continue };
+        let Some(cap) = re.captures(text) else { continue };
+
+        let hint = match cap.get(2) {
+            Some(unit) => {
+                let n: u64 = cap[1].parse().unwrap_or(1);
+                let unit_secs = match unit.as_str() {
+                    "minute" | "minuto" => 60,
+                    "hour" | "hora" => 3600,
+                    _ => 86_400,
+                };
+                TimeHint::Ago { seconds: n * unit_secs }
+            }
+            None if matches!(&cap[1], "today" | "hoje") => TimeHint::Today,
+            None => TimeHint::Yesterday,
+        };
+        let phrase = cap.get(0).map_or(0..0, |m| m.range());
+        let rest = format!("{}{}", &text[..phrase.start], &text[phrase.end..]);
+        return (Some(hint), rest);
+    }
+    (None, text.to_string())
+}
+
+/// "what was I doing at 3 yesterday", "o que eu estava fazendo às 15h ontem"
+///
+/// Only a clock time and a day may follow the question; anything else
+/// makes it a search.
+fn parse_timeline(text: &str) -> Option<TimeMachineOperation> {
+    let question = Regex::new(r"(?:what was i doing|what was i up to|o que eu (?:estava|tava) fazendo)\s+(.+)").ok()?;
+    let rest = question.captures(text)?.get(1)?.as_str();
+    let time_re = Regex::new(r"(?:\b(?:at|around)|às|as|por volta das?)\s+(\d{1,2})(?:(?::|h)(\d{2})|h)?\s*(am|pm)?").ok()?;
+    let cap = time_re.captures(rest)?;
+    let mut hour: u32 = cap[1].parse().ok()?;
+    let minute: u32 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
+    let meridiem = cap.get(3).map(|m| m.as_str());
+    match meridiem {
+        Some("pm") if hour < 12 => hour += 12,
+        Some("am") if hour == 12 => hour = 0,
+        _ => {}
+    }
+    if hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let time = cap.get(0)?.range();
+    let leftover = format!("{} {}", &rest[..time.start], &rest[time.end..]);
+    let day_re = Regex::new(r"\b(?:today|yesterday|hoje|ontem)\b").ok()?;
+    let yesterday = leftover.contains("yesterday") || leftover.contains("ontem");
+    if !day_re.replace_all(&leftover, "").trim_matches(|c: char| c.is_whitespace() || c.is_ascii_punctuation()).is_empty() {
+        return None;
+    }
+    Some(TimeMachineOperation::Timeline { hour, minute, twelve_hour: meridiem.is_none() && (1..12).contains(&hour), yesterday })
+}
+
+/// Most commands taken from one utterance; anything past that stays in
+/// the last clause
+pub const MAX_INTENTS: usize = 5;
+
+/// Sequence markers a compound request is split on, longest first
+/// ("create a file called a.txt and then list files")
+const CLAUSE_SEPARATOR: &str = r"(?i)\s*[,;]?\s+(?:and then|and also|after that|then|and|e depois|e também|e tambem|e então|e entao|em seguida|depois disso|depois|então|entao)\s+|\s*;\s*";
+
 /// Command parser
 pub struct CommandParser {
     whitelist: HashSet<String>,
@@ -86,17 +526,109 @@ impl CommandParser {
         // Network operations
         whitelist.insert("ip".to_string());
         whitelist.insert("ping".to_string());
+        whitelist.insert("dns".to_string());
         
         // Text operations
         whitelist.insert("type".to_string());
+        whitelist.insert("select".to_string());
+        whitelist.insert("paste".to_string());
         
         Self { whitelist }
     }
 
+    /// Parse a possibly compound request into its commands, in order
+    ///
+    /// A clause that isn't a command on its own stays with the clause
+    /// before it ("type hello and goodbye"), or the one after if it comes
+    /// first, so only real commands are split apart. Never empty: a
+    /// request with no command gives `[Unknown]`.
+    pub fn parse_all(&self, text: &str) -> Vec<CommandIntent> {
+        let parse = |clause: &str| self.parse(clause).unwrap_or(CommandIntent::Unknown);
+        let Ok(separator) = Regex::new(CLAUSE_SEPARATOR) else { return vec![parse(text)] };
+
+        // Clause boundaries as byte ranges, so merged clauses keep their joining words
+        let mut bounds = Vec::new();
+        let mut start = 0;
+        for found in separator.find_iter(text).take(MAX_INTENTS - 1) {
+            bounds.push(start..found.start());
+            start = found.end();
+        }
+        bounds.push(start..text.len());
+
+        let mut groups: Vec<(std::ops::Range<usize>, bool)> = Vec::new();
+        for range in bounds {
+            let is_command = parse(&text[range.clone()]) != CommandIntent::Unknown;
+            match groups.last_mut() {
+                // Joins the clause before, or a leading non-command clause joins this one
+                Some((last, has_command)) if !is_command || !*has_command => {
+                    last.end = range.end;
+                    *has_command |= is_command;
+                }
+                _ => groups.push((range, is_command)),
+            }
+        }
+        groups.into_iter().map(|(range, _)| parse(&text[range])).collect()
+    }
+
     /// Parse text into command intent
     pub fn parse(&self, text: &str) -> Result<CommandIntent, Box<dyn std::error::Error>> {
         let text_lower = text.to_lowercase();
         
+        // Session operations (the original text: facts keep their capitals)
+        if let Some(op) = self.parse_remember(text) {
+            return Ok(CommandIntent::Session(op));
+        }
+
+        if (text_lower.contains("forget") || text_lower.contains("esqueça") || text_lower.contains("esquece"))
+            && (text_lower.contains("exchange") || text_lower.contains("last") || text_lower.contains("última") || text_lower.contains("ultima"))
+        {
+            return Ok(CommandIntent::Session(SessionOperation::ForgetLastExchange));
+        }
+
+        if text_lower.contains("branch") && (text_lower.contains("conversation") || text_lower.contains("session")) {
+            return Ok(CommandIntent::Session(SessionOperation::Branch));
+        }
+
+        // Profile (the original text: names keep their capitals)
+        if let Some(op) = self.parse_profile(text) {
+            return Ok(CommandIntent::Profile(op));
+        }
+
+        // Macros (before Time Machine: "stop recording the macro" pauses nothing)
+        if let Some(op) = self.parse_macro(&text_lower) {
+            return Ok(CommandIntent::Macro(op));
+        }
+
+        // Time Machine (before processes: "start recording" launches nothing)
+        if let Some(op) = self.parse_timemachine(&text_lower) {
+            return Ok(CommandIntent::TimeMachine(op));
+        }
+
+        // Timers (before history: "repeat this timer" is not a command re-run)
+        if let Some(op) = self.parse_timer(&text_lower) {
+            return Ok(CommandIntent::Timer(op));
+        }
+
+        // Volume and mute (after macros: "play the morning macro 2 times" runs it)
+        if let Some(op) = parse_audio(&text_lower) {
+            return Ok(CommandIntent::Audio(op));
+        }
+
+        // Command history
+        if let Some(target) = self.parse_repeat(&text_lower) {
+            return Ok(CommandIntent::Repeat(target));
+        }
+        
+        // Clipboard (before files: "copy that" names no file)
+        if let Some(op) = parse_clipboard(&text_lower) {
+            return Ok(CommandIntent::Text(op));
+        }
+
+        // Programs EVA started (before files: "show the output of 42" lists nothing)
+        if let Some(op) = parse_process_query(&text_lower) {
+            return Ok(CommandIntent::Process(op));
+        }
+
         // File operations
         if text_lower.contains("create") && text_lower.contains("file") {
             return self.parse_file_create(&text_lower);
@@ -144,7 +676,11 @@ impl CommandParser {
             return Ok(CommandIntent::System(SystemOperation::CpuInfo));
         }
         
-        // Network operations
+        // Network operations ("ip address of google.com" is a lookup, not ours)
+        if let Some(host) = parse_dns_host(&text_lower) {
+            return Ok(CommandIntent::Network(NetworkOperation::DnsLookup { host }));
+        }
+
         if text_lower.contains("ip") && text_lower.contains("address") {
             return Ok(CommandIntent::Network(NetworkOperation::GetIP));
         }
@@ -155,12 +691,209 @@ impl CommandParser {
         
         // Text operations
         if text_lower.contains("type") {
-            return self.parse_text_type(&text_lower);
+            // The original text: what gets typed keeps its capitals
+            return self.parse_text_type(text);
         }
         
         Ok(CommandIntent::Unknown)
     }
 
+    // History parsers
+    fn parse_repeat(&self, text: &str) -> Option<RepeatTarget> {
+        let again = text.contains("again") || text.contains("de novo") || text.contains("novamente");
+
+        if again && (text.contains("run command") || text.contains("comando")) {
+            // "run command 3 again"
+            let number = text
+                .split(|c: char| !c.is_ascii_digit())
+                .find(|s| !s.is_empty())
+                .and_then(|s| s.parse().ok());
+            if let Some(n) = number {
+                return Some(RepeatTarget::Numbered(n));
+            }
+        }
+
+        if (again && (text.contains("do that") || text.contains("faça isso") || text.contains("faz isso")))
+            || text.contains("repeat that")
+            || text.contains("repeat the last command")
+        {
+            return Some(RepeatTarget::Last);
+        }
+
+        None
+    }
+
+    /// "remember that X is Y" / "lembre que X é Y": subject and value;
+    /// a fact with no "is" is kept whole under itself
+    fn parse_remember(&self, text: &str) -> Option<SessionOperation> {
+        let remember = Regex::new(
+            r"(?i)^(?:eva[,!]?\s+)?(?:please\s+|por favor,?\s+)?(?:remember|lembre(?:-se)?|lembra(?:-te)?)\s+(?:that|de que|que)\s+(.+?)[.!]?$",
+        )
+        .ok()?;
+        let fact = remember.captures(text.trim())?.get(1)?.as_str().trim();
+
+        let copula = Regex::new(r"(?i)\s(?:is|are|é|são|fica)\s").ok()?;
+        let Some(found) = copula.find(fact) else {
+            return Some(SessionOperation::Remember { key: fact.to_lowercase(), value: fact.to_string() });
+        };
+        let subject = fact[..found.start()].trim();
+        let subject = Regex::new(r"(?i)^(?:my|the|meu|minha|o|a)\s+").ok()?.replace(subject, "");
+        let value = fact[found.end()..].trim();
+        if subject.is_empty() || value.is_empty() {
+            return None;
+        }
+        Some(SessionOperation::Remember { key: subject.to_lowercase(), value: value.to_string() })
+    }
+
+    fn parse_profile(&self, text: &str) -> Option<ProfileOperation> {
+        let set = |field: &str, value: &str| Some(ProfileOperation::Set { field: field.to_string(), value: value.trim().to_string() });
+
+        let name = Regex::new(
+            r"(?i)\b(?:call me|set my name to|change my name to|me chame de|me chama de|mude meu nome para|muda meu nome para)\s+([\p{L}][\p{L}' -]*?)[.!]?$",
+        )
+        .ok()?;
+        if let Some(c) = name.captures(text.trim()) {
+            let words: Vec<&str> = c[1].split_whitespace().collect();
+            // "call me back later", "call me a taxi": not a name
+            let not_a_name = ["a", "an", "the", "back", "later", "tomorrow", "when", "if", "um", "uma", "depois", "amanhã", "quando"];
+            if words.len() <= 3 && !not_a_name.contains(&words[0].to_lowercase().as_str()) {
+                // Transcripts come in lower case
+                let typed = c[1].chars().any(char::is_uppercase);
+                let name: Vec<String> = words.iter().map(|w| if typed { w.to_string() } else { capitalize(w) }).collect();
+                return set("name", &name.join(" "));
+            }
+        }
+
+        let language = Regex::new(
+            r"(?i)\b(?:(?:switch|change|set)\s+(?:the\s+|your\s+|my\s+)?language\s+to|(?:mude|muda|troque|troca|altere|altera)\s+(?:o\s+)?idioma\s+para(?:\s+o)?)\s+(\p{L}+)",
+        )
+        .ok()?;
+        if let Some(c) = language.captures(text) {
+            return set("language", &c[1]);
+        }
+
+        // "set voice speed to 1.2", "set my wake word sensitivity to 0.8"
+        let field = Regex::new(r"(?i)\bset\s+(?:my\s+|your\s+|the\s+)?(voice speed|voice pitch|personality|wake word sensitivity)\s+to\s+(\S+)").ok()?;
+        let c = field.captures(text)?;
+        set(&c[1].to_lowercase().replace(' ', "_"), c[2].trim_end_matches(['.', '!']))
+    }
+
+    fn parse_macro(&self, text: &str) -> Option<MacroOperation> {
+        let words: Vec<&str> = text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+        let at = words.iter().position(|w| *w == "macro")?;
+        let has = |list: &[&str]| list.iter().any(|w| words.contains(w));
+
+        let stop = ["stop", "finish", "end", "done", "pare", "parar", "termine", "terminar", "encerre", "encerrar"];
+        let record = ["record", "recording", "create", "grave", "gravar", "grava", "gravando", "crie", "criar"];
+        let run = ["run", "play", "execute", "rode", "rodar", "roda", "executar", "executa"];
+        if has(&stop) {
+            return Some(MacroOperation::StopRecording);
+        }
+
+        // "macro called morning", "macro chamada manhã", "run macro morning",
+        // or the word before: "run the morning macro"
+        let after = &words[at + 1..];
+        let after = match after.iter().position(|w| ["called", "named", "chamada", "chamado"].contains(w)) {
+            Some(i) => &after[i + 1..],
+            None => after,
+        };
+        let name = if !after.is_empty() {
+            Some(after.join(" "))
+        } else {
+            let skip = ["a", "the", "my", "this", "o", "uma", "minha", "essa", "esta"];
+            words[..at]
+                .last()
+                .filter(|w| !skip.contains(w) && !record.contains(w) && !run.contains(w))
+                .map(|w| w.to_string())
+        }?;
+
+        if has(&record) {
+            Some(MacroOperation::StartRecording { name })
+        } else if has(&run) {
+            Some(MacroOperation::Run { name })
+        } else {
+            None
+        }
+    }
+
+    fn parse_timemachine(&self, text: &str) -> Option<TimeMachineOperation> {
+        if let Some(op) = parse_timeline(text) {
+            return Some(op);
+        }
+        let search_en = Regex::new(
+            r"(?:what was i|what did i see) (?:reading|looking at|watching|working on|doing|seeing)?\s*(?:about |on )?(.+)|(?:search|find) (?:in |on )?(?:my )?(?:screen|history|time machine|recordings?) (?:for )?(.+)",
+        )
+        .ok()?;
+        let search_pt = Regex::new(
+            r"o que eu (?:estava|tava) (?:lendo|vendo|olhando|fazendo)\s*(?:sobre )?(.+)|(?:procure|procura|busque|busca|pesquise|pesquisa) (?:no |na )?(?:minha |meu )?(?:tela|histórico|time machine|gravação) (?:por |sobre )?(.+)",
+        )
+        .ok()?;
+        if let Some(cap) = search_en.captures(text).or_else(|| search_pt.captures(text)) {
+            let phrase = cap.get(1).or_else(|| cap.get(2))?.as_str();
+            let (time_hint, rest) = parse_time_hint(phrase);
+            let query = rest
+                .trim_matches(|c: char| c.is_whitespace() || c.is_ascii_punctuation() || c == '¿')
+                .to_string();
+            if !query.is_empty() {
+                return Some(TimeMachineOperation::Search { query, time_hint });
+            }
+        }
+
+        let about_recording = ["recording", "time machine", "gravação", "gravações", "gravando", "gravar"]
+            .iter()
+            .any(|w| text.contains(w));
+        if !about_recording {
+            return None;
+        }
+
+        let has = |words: &[&str]| words.iter().any(|w| text.contains(w));
+        if has(&["delete", "erase", "apagar", "apaga", "deletar", "excluir", "exclua"]) && has(&["today", "hoje"]) {
+            return Some(TimeMachineOperation::DeleteToday);
+        }
+        let repair = ["clean", "repair", "fix", "limpe", "limpar", "limpa", "repare", "reparar", "conserte", "consertar"];
+        if has(&["verify", "check", "scan", "verifique", "verificar", "verifica", "cheque", "checar"]) || has(&repair) {
+            return Some(TimeMachineOperation::Verify { delete_orphans: has(&repair) });
+        }
+        if has(&["resume", "continue", "unpause", "start", "retomar", "retome", "retoma", "continuar", "voltar a", "volte a"]) {
+            return Some(TimeMachineOperation::Resume);
+        }
+        if has(&["pause", "stop", "pausa", "parar", "pare de"]) {
+            return Some(TimeMachineOperation::Pause);
+        }
+        if has(&["status", "are you", "is it", "estado", "está gravando", "esta gravando", "você está", "voce esta"]) {
+            return Some(TimeMachineOperation::Status);
+        }
+        None
+    }
+
+    fn parse_timer(&self, text: &str) -> Option<TimerOperation> {
+        let is_timer = ["timer", "reminder", "alarm", "lembrete", "alarme", "cronômetro"]
+            .iter()
+            .any(|w| text.contains(w));
+        if !is_timer {
+            return None;
+        }
+        let label = timer_label(text);
+
+        if ["how long", "time left", "how much time", "quanto tempo", "quanto falta"].iter().any(|w| text.contains(w)) {
+            return Some(TimerOperation::Query { label });
+        }
+
+        if ["snooze", "adiar", "adia ", "soneca"].iter().any(|w| text.contains(w)) {
+            let seconds = parse_duration_secs(text).unwrap_or(10 * 60);
+            return Some(TimerOperation::Snooze { label, seconds });
+        }
+
+        let repeat_words = ["repeat", "every", "repetir", "repita", "repete", "todo dia", "todos os", "toda "];
+        if repeat_words.iter().any(|w| text.contains(w)) {
+            if let Some(rule) = parse_recurrence(text) {
+                return Some(TimerOperation::Repeat { label, rule });
+            }
+        }
+
+        parse_duration_secs(text).map(|seconds| TimerOperation::Set { label, seconds })
+    }
+
     // File operation parsers
     fn parse_file_create(&self, text: &str) -> Result<CommandIntent, Box<dyn std::error::Error>> {
         // Extract filename: "create a file called test.txt"
@@ -265,14 +998,11 @@ impl CommandParser {
     // Text operation parsers
     fn parse_text_type(&self, text: &str) -> Result<CommandIntent, Box<dyn std::error::Error>> {
         // "type hello world" or "type 'hello world'"
-        let text_to_type = if let Some(idx) = text.find("type ") {
-            text[idx + 5..].trim()
-                .trim_matches('\'')
-                .trim_matches('"')
-                .to_string()
-        } else {
-            "".to_string()
-        };
+        let re = Regex::new(r"(?i)\btype\s+(.+)")?;
+        let text_to_type = re
+            .captures(text)
+            .map(|caps| caps[1].trim().trim_matches('\'').trim_matches('"').to_string())
+            .unwrap_or_default();
         
         if text_to_type.is_empty() {
             return Err("No text specified".into());
@@ -324,6 +1054,50 @@ mod tests {
         assert!(matches!(result, CommandIntent::Process(ProcessOperation::List)));
     }
 
+    #[test]
+    fn test_parse_process_queries() {
+        let parser = CommandParser::new();
+        let status = |name: &str| CommandIntent::Process(ProcessOperation::Status { name_or_pid: name.to_string() });
+
+        assert_eq!(parser.parse("is build.sh still running?").unwrap(), status("build.sh"));
+        assert_eq!(parser.parse("what's the status of process 4242").unwrap(), status("4242"));
+        assert_eq!(parser.parse("has make finished").unwrap(), status("make"));
+        assert_eq!(parser.parse("o backup.sh ainda está rodando?").unwrap(), status("backup.sh"));
+        assert_eq!(
+            parser.parse("show me the output of process 4242").unwrap(),
+            CommandIntent::Process(ProcessOperation::Output { pid: 4242 })
+        );
+        assert_eq!(parser.parse("is anything running").unwrap(), CommandIntent::Process(ProcessOperation::List));
+    }
+
+    #[test]
+    fn test_parse_network() {
+        let parser = CommandParser::new();
+        let lookup = |host: &str| CommandIntent::Network(NetworkOperation::DnsLookup { host: host.to_string() });
+
+        assert_eq!(parser.parse("what is my ip address").unwrap(), CommandIntent::Network(NetworkOperation::GetIP));
+        assert_eq!(parser.parse("ping google.com").unwrap(), CommandIntent::Network(NetworkOperation::Ping { host: "google.com".to_string() }));
+        assert_eq!(parser.parse("look up google.com").unwrap(), lookup("google.com"));
+        assert_eq!(parser.parse("resolve example.org please").unwrap(), lookup("example.org"));
+        assert_eq!(parser.parse("what's the ip address of github.com").unwrap(), lookup("github.com"));
+        assert_ne!(parser.parse("look up the weather").unwrap(), lookup("the"));
+    }
+
+    #[test]
+    fn test_parse_text_operations() {
+        let parser = CommandParser::new();
+        let text = |op: TextOperation| CommandIntent::Text(op);
+
+        assert_eq!(parser.parse("type Hello World").unwrap(), text(TextOperation::Type { text: "Hello World".to_string() }));
+        assert_eq!(parser.parse("select all").unwrap(), text(TextOperation::Select));
+        assert_eq!(parser.parse("copy that").unwrap(), text(TextOperation::Copy));
+        assert_eq!(parser.parse("copy it to the clipboard").unwrap(), text(TextOperation::Copy));
+        assert_eq!(parser.parse("paste").unwrap(), text(TextOperation::Paste));
+        assert_eq!(parser.parse("cole aqui").unwrap(), text(TextOperation::Paste));
+        // File copies still go to the file parser
+        assert!(matches!(parser.parse("copy a.txt to b.txt").unwrap(), CommandIntent::File(FileOperation::Copy { .. })));
+    }
+
     #[test]
     fn test_parse_unknown() {
         let parser = CommandParser::new();
@@ -331,4 +1105,308 @@ mod tests {
         
         assert!(matches!(result, CommandIntent::Unknown));
     }
+
+    #[test]
+    fn test_parse_session_operations() {
+        let parser = CommandParser::new();
+
+        let result = parser.parse("EVA, forget the last exchange").unwrap();
+        assert_eq!(result, CommandIntent::Session(SessionOperation::ForgetLastExchange));
+
+        let result = parser.parse("esqueça a última conversa").unwrap();
+        assert_eq!(result, CommandIntent::Session(SessionOperation::ForgetLastExchange));
+
+        let result = parser.parse("branch this conversation").unwrap();
+        assert_eq!(result, CommandIntent::Session(SessionOperation::Branch));
+    }
+
+    #[test]
+    fn test_parse_remember() {
+        let parser = CommandParser::new();
+        let remember = |key: &str, value: &str| CommandIntent::Session(SessionOperation::Remember { key: key.to_string(), value: value.to_string() });
+
+        assert_eq!(parser.parse("EVA, remember that my locker number is 42.").unwrap(), remember("locker number", "42"));
+        assert_eq!(parser.parse("lembre que o aniversário da Ana é 12 de maio").unwrap(), remember("aniversário da ana", "12 de maio"));
+        assert_eq!(parser.parse("remember that I parked on level 3").unwrap(), remember("i parked on level 3", "I parked on level 3"));
+        // Not about the last exchange, even with "last" in it
+        assert_eq!(parser.parse("remember that my last car was blue").unwrap(), remember("my last car was blue", "my last car was blue"));
+
+        // Reminders and questions aren't facts
+        assert!(!matches!(parser.parse("remember to buy milk").unwrap(), CommandIntent::Session(_)));
+        assert!(!matches!(parser.parse("do you remember what I said?").unwrap(), CommandIntent::Session(_)));
+    }
+
+    #[test]
+    fn test_parse_profile() {
+        let parser = CommandParser::new();
+        let set = |field: &str, value: &str| CommandIntent::Profile(ProfileOperation::Set { field: field.to_string(), value: value.to_string() });
+
+        assert_eq!(parser.parse("call me daniel").unwrap(), set("name", "Daniel"));
+        assert_eq!(parser.parse("EVA, call me Mary Jane.").unwrap(), set("name", "Mary Jane"));
+        assert_eq!(parser.parse("me chame de joão").unwrap(), set("name", "João"));
+        assert_eq!(parser.parse("switch language to English").unwrap(), set("language", "English"));
+        assert_eq!(parser.parse("mude o idioma para o português").unwrap(), set("language", "português"));
+        assert_eq!(parser.parse("set voice speed to 1.2").unwrap(), set("voice_speed", "1.2"));
+
+        // Not names
+        assert_eq!(parser.parse("call me back later").unwrap(), CommandIntent::Unknown);
+        assert_eq!(parser.parse("call me a taxi").unwrap(), CommandIntent::Unknown);
+    }
+
+    #[test]
+    fn test_parse_audio() {
+        let parser = CommandParser::new();
+        let audio = |op: AudioOperation| CommandIntent::Audio(op);
+
+        assert_eq!(parser.parse("volume down").unwrap(), audio(AudioOperation::VolumeDown));
+        assert_eq!(parser.parse("EVA, turn the volume up").unwrap(), audio(AudioOperation::VolumeUp));
+        assert_eq!(parser.parse("speak quieter please").unwrap(), audio(AudioOperation::VolumeDown));
+        assert_eq!(parser.parse("abaixe o volume").unwrap(), audio(AudioOperation::VolumeDown));
+        assert_eq!(parser.parse("fale mais alto").unwrap(), audio(AudioOperation::VolumeUp));
+        assert_eq!(parser.parse("set the volume to 30%").unwrap(), audio(AudioOperation::SetVolume { percent: 30 }));
+        assert_eq!(parser.parse("volume em 250").unwrap(), audio(AudioOperation::SetVolume { percent: 100 }));
+        assert_eq!(parser.parse("mute yourself").unwrap(), audio(AudioOperation::Mute));
+        assert_eq!(parser.parse("Mute.").unwrap(), audio(AudioOperation::Mute));
+        assert_eq!(parser.parse("silencie-se").unwrap(), audio(AudioOperation::Mute));
+        assert_eq!(parser.parse("unmute yourself").unwrap(), audio(AudioOperation::Unmute));
+        assert_eq!(parser.parse("tire do mudo").unwrap(), audio(AudioOperation::Unmute));
+        assert_eq!(parser.parse("play replies at 1.25x").unwrap(), audio(AudioOperation::SetSpeed { speed: 1.25 }));
+        assert_eq!(parser.parse("set playback speed to 0,75").unwrap(), audio(AudioOperation::SetSpeed { speed: 0.75 }));
+
+        // Not about EVA's voice
+        assert_eq!(parser.parse("how loud is a jet engine").unwrap(), CommandIntent::Unknown);
+        assert_eq!(parser.parse("set voice speed to 1.2").unwrap(), CommandIntent::Profile(ProfileOperation::Set {
+            field: "voice_speed".to_string(),
+            value: "1.2".to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_parse_repeat() {
+        let parser = CommandParser::new();
+
+        assert_eq!(parser.parse("do that again").unwrap(), CommandIntent::Repeat(RepeatTarget::Last));
+        assert_eq!(parser.parse("faça isso de novo").unwrap(), CommandIntent::Repeat(RepeatTarget::Last));
+        assert_eq!(parser.parse("run command 3 again").unwrap(), CommandIntent::Repeat(RepeatTarget::Numbered(3)));
+    }
+
+    #[test]
+    fn test_parse_macro() {
+        let parser = CommandParser::new();
+        let start = |name: &str| CommandIntent::Macro(MacroOperation::StartRecording { name: name.to_string() });
+        let run = |name: &str| CommandIntent::Macro(MacroOperation::Run { name: name.to_string() });
+
+        assert_eq!(parser.parse("EVA, record a macro called morning").unwrap(), start("morning"));
+        assert_eq!(parser.parse("grave uma macro chamada bom dia").unwrap(), start("bom dia"));
+        assert_eq!(parser.parse("stop recording the macro").unwrap(), CommandIntent::Macro(MacroOperation::StopRecording));
+        assert_eq!(parser.parse("pare de gravar a macro").unwrap(), CommandIntent::Macro(MacroOperation::StopRecording));
+        assert_eq!(parser.parse("run macro morning").unwrap(), run("morning"));
+        assert_eq!(parser.parse("run the morning macro").unwrap(), run("morning"));
+        assert_eq!(parser.parse("execute a macro manhã").unwrap(), run("manhã"));
+
+        // No name, nothing to record; "recording" alone is still Time Machine
+        assert_eq!(parser.parse("record a macro").unwrap(), CommandIntent::Unknown);
+        assert_eq!(parser.parse("stop recording").unwrap(), CommandIntent::TimeMachine(TimeMachineOperation::Pause));
+        assert!(CommandIntent::Macro(MacroOperation::Run { name: "x".to_string() }).risk() == RiskLevel::Moderate);
+    }
+
+    #[test]
+    fn test_parse_all_splits_compound_requests() {
+        let parser = CommandParser::new();
+        let create = |path: &str| CommandIntent::File(FileOperation::Create { path: path.to_string(), content: None });
+        let list = CommandIntent::File(FileOperation::List { path: None });
+
+        assert_eq!(
+            parser.parse_all("create a file called notes.txt and then list the directory"),
+            vec![create("notes.txt"), list.clone()]
+        );
+        assert_eq!(
+            parser.parse_all("show memory usage, then show disk storage and list files"),
+            vec![
+                CommandIntent::System(SystemOperation::MemoryInfo),
+                CommandIntent::System(SystemOperation::DiskInfo),
+                list.clone()
+            ]
+        );
+        assert_eq!(
+            parser.parse_all("pausar gravação e depois timer de 10 minutos"),
+            vec![
+                CommandIntent::TimeMachine(TimeMachineOperation::Pause),
+                CommandIntent::Timer(TimerOperation::Set { label: None, seconds: 600 })
+            ]
+        );
+
+        // Single commands and chit-chat are unchanged
+        assert_eq!(parser.parse_all("list files"), vec![list.clone()]);
+        assert_eq!(parser.parse_all("hello world and good morning"), vec![CommandIntent::Unknown]);
+    }
+
+    #[test]
+    fn test_parse_all_keeps_non_command_clauses_together() {
+        let parser = CommandParser::new();
+
+        // The second clause is not a command: nothing is split off
+        assert_eq!(
+            parser.parse_all("create a file called notes.txt and tell me a joke"),
+            vec![CommandIntent::File(FileOperation::Create { path: "notes.txt".to_string(), content: None })]
+        );
+        assert_eq!(
+            parser.parse_all("type hello and goodbye"),
+            vec![parser.parse("type hello and goodbye").unwrap()]
+        );
+
+        // Runaway splitting is capped; the rest stays in the last clause
+        let many = vec!["list files"; 8].join(" and ");
+        assert_eq!(parser.parse_all(&many).len(), MAX_INTENTS);
+    }
+
+    #[test]
+    fn test_intent_serialization() {
+        let intent = CommandIntent::File(FileOperation::List { path: Some("docs".to_string()) });
+        let json = serde_json::to_string(&intent).unwrap();
+        let parsed: CommandIntent = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, intent);
+
+        assert!(CommandIntent::Process(ProcessOperation::Kill { pid: 1 }).is_risky());
+        assert!(!intent.is_risky());
+    }
+
+    #[test]
+    fn test_risk_levels() {
+        assert_eq!(CommandIntent::System(SystemOperation::Uptime).risk(), RiskLevel::Safe);
+        assert_eq!(CommandIntent::File(FileOperation::Read { path: "a".into() }).risk(), RiskLevel::Safe);
+        assert_eq!(CommandIntent::Process(ProcessOperation::Start { name: "x".into() }).risk(), RiskLevel::Moderate);
+        assert_eq!(CommandIntent::Repeat(RepeatTarget::Last).risk(), RiskLevel::Moderate);
+        assert_eq!(CommandIntent::Process(ProcessOperation::Kill { pid: 1 }).risk(), RiskLevel::Risky);
+        assert_eq!(CommandIntent::Text(TextOperation::Type { text: "hi".into() }).risk(), RiskLevel::Risky);
+        assert!(CommandIntent::Text(TextOperation::Paste).is_risky());
+        assert!(CommandIntent::File(FileOperation::Delete { path: "a".into() }).is_risky());
+    }
+
+    #[test]
+    fn test_parse_timer_query() {
+        let parser = CommandParser::new();
+        assert_eq!(
+            parser.parse("how long left on the pasta timer").unwrap(),
+            CommandIntent::Timer(TimerOperation::Query { label: Some("pasta".to_string()) })
+        );
+        assert_eq!(
+            parser.parse("quanto tempo falta no timer do macarrão").unwrap(),
+            CommandIntent::Timer(TimerOperation::Query { label: Some("macarrão".to_string()) })
+        );
+    }
+
+    #[test]
+    fn test_parse_timer_snooze() {
+        let parser = CommandParser::new();
+        assert_eq!(
+            parser.parse("snooze the laundry reminder for 10 minutes").unwrap(),
+            CommandIntent::Timer(TimerOperation::Snooze { label: Some("laundry".to_string()), seconds: 600 })
+        );
+        assert_eq!(
+            parser.parse("adiar o lembrete da roupa por 1 hora").unwrap(),
+            CommandIntent::Timer(TimerOperation::Snooze { label: Some("roupa".to_string()), seconds: 3600 })
+        );
+    }
+
+    #[test]
+    fn test_parse_timer_repeat() {
+        let parser = CommandParser::new();
+        assert_eq!(
+            parser.parse("repeat this timer every weekday at 7").unwrap(),
+            CommandIntent::Timer(TimerOperation::Repeat { label: None, rule: Recurrence::weekdays(7, 0) })
+        );
+        assert_eq!(
+            parser.parse("repetir este alarme toda segunda e sexta às 18h30").unwrap(),
+            CommandIntent::Timer(TimerOperation::Repeat {
+                label: None,
+                rule: Recurrence::on(vec![Weekday::Mon, Weekday::Fri], 18, 30),
+            })
+        );
+        assert_eq!(
+            parser.parse("repeat the standup reminder every day at 9:15 am").unwrap(),
+            CommandIntent::Timer(TimerOperation::Repeat { label: Some("standup".to_string()), rule: Recurrence::daily(9, 15) })
+        );
+    }
+
+    #[test]
+    fn test_parse_timer_set() {
+        let parser = CommandParser::new();
+        assert_eq!(
+            parser.parse("set a pasta timer for 10 minutes").unwrap(),
+            CommandIntent::Timer(TimerOperation::Set { label: Some("pasta".to_string()), seconds: 600 })
+        );
+        assert_eq!(
+            parser.parse("timer de 5 minutos").unwrap(),
+            CommandIntent::Timer(TimerOperation::Set { label: None, seconds: 300 })
+        );
+    }
+
+    #[test]
+    fn test_parse_timemachine_controls() {
+        let parser = CommandParser::new();
+        let tm = |text: &str| parser.parse(text).unwrap();
+
+        assert_eq!(tm("EVA, pause recording"), CommandIntent::TimeMachine(TimeMachineOperation::Pause));
+        assert_eq!(tm("pausar gravação"), CommandIntent::TimeMachine(TimeMachineOperation::Pause));
+        assert_eq!(tm("resume recording"), CommandIntent::TimeMachine(TimeMachineOperation::Resume));
+        assert_eq!(tm("start recording"), CommandIntent::TimeMachine(TimeMachineOperation::Resume));
+        assert_eq!(tm("retomar a gravação"), CommandIntent::TimeMachine(TimeMachineOperation::Resume));
+        assert_eq!(tm("time machine status"), CommandIntent::TimeMachine(TimeMachineOperation::Status));
+        assert_eq!(tm("você está gravando?"), CommandIntent::TimeMachine(TimeMachineOperation::Status));
+        assert_eq!(tm("delete today's recordings"), CommandIntent::TimeMachine(TimeMachineOperation::DeleteToday));
+        assert_eq!(tm("apagar a gravação de hoje"), CommandIntent::TimeMachine(TimeMachineOperation::DeleteToday));
+        assert!(CommandIntent::TimeMachine(TimeMachineOperation::DeleteToday).is_risky());
+
+        let verify = |delete_orphans| CommandIntent::TimeMachine(TimeMachineOperation::Verify { delete_orphans });
+        assert_eq!(tm("check my recordings"), verify(false));
+        assert_eq!(tm("verifique as gravações"), verify(false));
+        assert_eq!(tm("clean up the time machine recordings"), verify(true));
+        assert!(verify(true).is_risky() && !verify(false).is_risky());
+    }
+
+    #[test]
+    fn test_parse_timemachine_search() {
+        let parser = CommandParser::new();
+        let search = |query: &str, time_hint| CommandIntent::TimeMachine(TimeMachineOperation::Search { query: query.to_string(), time_hint });
+
+        assert_eq!(
+            parser.parse("what was I reading about rust lifetimes an hour ago").unwrap(),
+            search("rust lifetimes", Some(TimeHint::Ago { seconds: 3600 }))
+        );
+        assert_eq!(
+            parser.parse("o que eu estava lendo sobre impostos há 20 minutos?").unwrap(),
+            search("impostos", Some(TimeHint::Ago { seconds: 1200 }))
+        );
+        assert_eq!(parser.parse("search my screen for invoice yesterday").unwrap(), search("invoice", Some(TimeHint::Yesterday)));
+        assert_eq!(parser.parse("procure na tela por receita de bolo").unwrap(), search("receita de bolo", None));
+    }
+
+    #[test]
+    fn test_parse_timemachine_timeline() {
+        let parser = CommandParser::new();
+        let timeline = |hour, minute, twelve_hour, yesterday| {
+            CommandIntent::TimeMachine(TimeMachineOperation::Timeline { hour, minute, twelve_hour, yesterday })
+        };
+
+        assert_eq!(parser.parse("what was I doing at 3 yesterday?").unwrap(), timeline(3, 0, true, true));
+        assert_eq!(parser.parse("EVA, what was I up to yesterday around 9:30 pm").unwrap(), timeline(21, 30, false, true));
+        assert_eq!(parser.parse("what was I doing at 14:15").unwrap(), timeline(14, 15, false, false));
+        assert_eq!(parser.parse("o que eu estava fazendo às 15h ontem").unwrap(), timeline(15, 0, false, true));
+        assert_eq!(parser.parse("o que eu tava fazendo hoje às 10h30").unwrap(), timeline(10, 30, true, false));
+        // Anything else asked about is still a search
+        assert_eq!(
+            parser.parse("what was I doing on the budget at 3").unwrap(),
+            CommandIntent::TimeMachine(TimeMachineOperation::Search { query: "the budget at 3".to_string(), time_hint: None })
+        );
+    }
+
+    #[test]
+    fn test_builtin_registry_examples_parse() {
+        let parser = CommandParser::new();
+        for spec in BUILTIN_COMMANDS {
+            let intent = parser.parse(spec.example).unwrap();
+            assert_ne!(intent, CommandIntent::Unknown, "{:?}", spec);
+        }
+    }
 }
@@ -0,0 +1,543 @@
+//! Control socket: other programs query and command the running daemon
+//!
+//! One JSON object per line over a Unix domain socket (`eva-control.sock`
+//! in the runtime directory, next to the status file) or, on Windows, the
+//! named pipe `\\.\pipe\eva-control`. Redox serves Unix sockets through its
+//! `chan:` scheme, so it takes the Unix path.
+//!
+//! ```json
+//! {"version": 1, "token": "…", "command": "status"}
+//! {"version": 1, "token": "…", "command": "ask", "text": "what's on my calendar?"}
+//! {"version": 1, "ok": true, "result": {"reply": "…"}}
+//! ```
+//!
+//! `get` and `set` read and write the runtime settings that Redox exposes as
+//! `eva:` files (see `eva_scheme`); there the scheme sends the same requests.
+//!
+//! The token is rewritten to `control.token` in the data directory at every
+//! start, readable by the owner only. Requests are handed to the main loop,
+//! which owns the session, the player and the Time Machine, and answers them
+//! on its next pass; `ask` answers once its turn is over.
+//!
+//! `evactl` includes this file as well, for the client half.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot};
+
+/// Envelope version; requests of another version are refused
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Token file, in the data directory
+const TOKEN_FILE: &str = "control.token";
+
+#[cfg(unix)]
+const SOCKET_NAME: &str = "eva-control.sock";
+
+#[cfg(windows)]
+const PIPE_NAME: &str = r"\\.\pipe\eva-control";
+
+/// Longest request line accepted
+const MAX_REQUEST_BYTES: u64 = 64 * 1024;
+
+/// Requests waiting for the main loop
+const QUEUE_DEPTH: usize = 16;
+
+/// Longest wait for the main loop's answer (an `ask` runs a whole turn)
+pub const ANSWER_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Search results returned when the request doesn't say
+pub const DEFAULT_SEARCH_LIMIT: usize = 5;
+
+/// What a client can ask of the daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    /// EVA's state, session and counters
+    Status,
+    /// Speak `text` as EVA
+    Say { text: String },
+    /// Run `text` as a typed turn and return the reply
+    Ask { text: String },
+    /// Semantic search of the Time Machine
+    #[serde(rename = "timemachine_search")]
+    TimeMachineSearch {
+        query: String,
+        #[serde(default)]
+        limit: Option<usize>,
+    },
+    /// Every capture of a local day (`YYYY-MM-DD`, today if absent),
+    /// oldest first
+    #[serde(rename = "timemachine_timeline")]
+    TimeMachineTimeline {
+        #[serde(default)]
+        date: Option<String>,
+    },
+    /// A capture's thumbnail, base64-encoded
+    #[serde(rename = "timemachine_thumbnail")]
+    TimeMachineThumbnail { id: u64 },
+    /// Delivery stats and the last error of each webhook endpoint
+    Webhooks,
+    /// Stop the daemon as Ctrl-C would
+    Shutdown,
+    /// Read an `eva:` file (`config/language`, `status`, ...)
+    Get { path: String },
+    /// Write an `eva:` file; the value is checked before it is applied
+    Set { path: String, value: String },
+    /// Write the session to a file, unencrypted: `format` is `markdown` or
+    /// `json`, which embeds turn audio if `audio`
+    Export {
+        path: String,
+        format: String,
+        #[serde(default)]
+        audio: bool,
+    },
+    /// Replace the session with one exported as JSON
+    Import { path: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub version: u32,
+    pub token: String,
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub version: u32,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub result: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Response {
+    pub fn ok(result: Value) -> Self {
+        Self { version: PROTOCOL_VERSION, ok: true, result, error: None }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self { version: PROTOCOL_VERSION, ok: false, result: Value::Null, error: Some(message.into()) }
+    }
+}
+
+/// A request for the main loop to answer
+#[derive(Debug)]
+pub struct Pending {
+    pub command: Command,
+    reply: oneshot::Sender<Result<Value, String>>,
+}
+
+impl Pending {
+    /// A request made inside the daemon (the `eva:` scheme) and where its
+    /// answer will arrive
+    #[cfg(target_os = "redox")]
+    pub fn new(command: Command) -> (Self, oneshot::Receiver<Result<Value, String>>) {
+        let (reply, answer) = oneshot::channel();
+        (Self { command, reply }, answer)
+    }
+
+    /// Send the answer back; a client that hung up is not an error
+    pub fn answer(self, result: Result<Value, String>) {
+        let _ = self.reply.send(result);
+    }
+}
+
+/// The listening socket and its token; dropping it stops listening and
+/// removes both files
+pub struct ControlServer {
+    #[cfg(unix)]
+    socket: PathBuf,
+    /// For requests made inside the daemon
+    #[cfg(target_os = "redox")]
+    requests: mpsc::Sender<Pending>,
+    token_file: PathBuf,
+    accept: tokio::task::JoinHandle<()>,
+}
+
+impl ControlServer {
+    /// Listen at the usual place; requests come out of the returned channel
+    pub fn start() -> Result<(Self, mpsc::Receiver<Pending>), Box<dyn Error>> {
+        let token_file = crate::paths::data_file(TOKEN_FILE)?;
+        #[cfg(unix)]
+        let started = Self::bind(&socket_path(), &token_file);
+        #[cfg(windows)]
+        let started = Self::bind_pipe(PIPE_NAME, &token_file);
+        started
+    }
+
+    /// Listen on the Unix socket `socket`, with a fresh token in `token_file`
+    #[cfg(unix)]
+    pub fn bind(socket: &Path, token_file: &Path) -> Result<(Self, mpsc::Receiver<Pending>), Box<dyn Error>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        if socket.exists() {
+            // A socket nobody answers on is left over from a crash
+            if std::os::unix::net::UnixStream::connect(socket).is_ok() {
+                return Err(format!("another EVA daemon is listening on {}", socket.display()).into());
+            }
+            std::fs::remove_file(socket)?;
+        }
+        let listener = tokio::net::UnixListener::bind(socket)?;
+        std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600))?;
+        let token = Arc::new(write_token(token_file)?);
+
+        let (requests, pending) = mpsc::channel(QUEUE_DEPTH);
+        #[cfg(target_os = "redox")]
+        let own = requests.clone();
+        let accept = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(serve(stream, token.clone(), requests.clone()));
+                    }
+                    Err(e) => {
+                        eprintln!("[Control] Accept failed, control socket closed: {}", e);
+                        return;
+                    }
+                }
+            }
+        });
+        Ok((
+            Self {
+                socket: socket.to_path_buf(),
+                #[cfg(target_os = "redox")]
+                requests: own,
+                token_file: token_file.to_path_buf(),
+                accept,
+            },
+            pending,
+        ))
+    }
+
+    /// Queue requests next to the socket's, answered the same way
+    #[cfg(target_os = "redox")]
+    pub fn requests(&self) -> mpsc::Sender<Pending> {
+        self.requests.clone()
+    }
+
+    /// Listen on the named pipe `name`, with a fresh token in `token_file`
+    #[cfg(windows)]
+    pub fn bind_pipe(name: &str, token_file: &Path) -> Result<(Self, mpsc::Receiver<Pending>), Box<dyn Error>> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        let mut server = ServerOptions::new().first_pipe_instance(true).create(name)?;
+        let token = Arc::new(write_token(token_file)?);
+        let name = name.to_string();
+
+        let (requests, pending) = mpsc::channel(QUEUE_DEPTH);
+        let accept = tokio::spawn(async move {
+            loop {
+                let connected = server.connect().await;
+                // The next client needs an instance of its own
+                let client = match ServerOptions::new().create(&name) {
+                    Ok(next) => std::mem::replace(&mut server, next),
+                    Err(e) => {
+                        eprintln!("[Control] Cannot reopen {}, control pipe closed: {}", name, e);
+                        return;
+                    }
+                };
+                match connected {
+                    Ok(()) => {
+                        tokio::spawn(serve(client, token.clone(), requests.clone()));
+                    }
+                    Err(e) => eprintln!("[Control] Pipe connection failed: {}", e),
+                }
+            }
+        });
+        Ok((Self { token_file: token_file.to_path_buf(), accept }, pending))
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.accept.abort();
+        #[cfg(unix)]
+        let _ = std::fs::remove_file(&self.socket);
+        let _ = std::fs::remove_file(&self.token_file);
+    }
+}
+
+/// Where the daemon listens: the runtime directory, like the status file
+#[cfg(unix)]
+pub fn socket_path() -> PathBuf {
+    std::env::var("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| std::env::temp_dir())
+        .join(SOCKET_NAME)
+}
+
+/// A new random token in `path`, owner-only
+fn write_token(path: &Path) -> Result<String, Box<dyn Error>> {
+    use aes_gcm::aead::rand_core::RngCore;
+    use aes_gcm::aead::OsRng;
+    use std::io::Write;
+
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    // Replaced rather than truncated, so a reader never sees half a token
+    let _ = std::fs::remove_file(path);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(token.as_bytes())?;
+    Ok(token)
+}
+
+/// Same length and bytes, without stopping at the first difference
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len() && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Answer requests on one connection until the client hangs up
+async fn serve<S>(stream: S, token: Arc<String>, requests: mpsc::Sender<Pending>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (read, mut write) = tokio::io::split(stream);
+    let mut reader = BufReader::new(read);
+    loop {
+        let mut line = String::new();
+        match (&mut reader).take(MAX_REQUEST_BYTES + 1).read_line(&mut line).await {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        let too_long = !line.ends_with('\n') && line.len() as u64 > MAX_REQUEST_BYTES;
+        let response = if too_long {
+            Response::error(format!("request longer than {} bytes", MAX_REQUEST_BYTES))
+        } else if line.trim().is_empty() {
+            continue;
+        } else {
+            answer(&line, &token, &requests).await
+        };
+
+        let mut encoded = serde_json::to_string(&response).unwrap_or_default();
+        encoded.push('\n');
+        if write.write_all(encoded.as_bytes()).await.is_err() || too_long {
+            return;
+        }
+    }
+}
+
+/// Check one request line and have the main loop answer it
+async fn answer(line: &str, token: &str, requests: &mpsc::Sender<Pending>) -> Response {
+    // The version first, so a newer client gets a clear refusal rather
+    // than a parse error about a command it expects to exist
+    let raw: Value = match serde_json::from_str(line) {
+        Ok(raw) => raw,
+        Err(e) => return Response::error(format!("invalid JSON: {}", e)),
+    };
+    match raw.get("version").and_then(Value::as_u64) {
+        Some(version) if version == PROTOCOL_VERSION as u64 => {}
+        Some(version) => return Response::error(format!("unsupported protocol version {} (expected {})", version, PROTOCOL_VERSION)),
+        None => return Response::error("missing protocol version"),
+    }
+    let request: Request = match serde_json::from_value(raw) {
+        Ok(request) => request,
+        Err(e) => return Response::error(format!("invalid request: {}", e)),
+    };
+    if !tokens_match(&request.token, token) {
+        return Response::error("invalid token");
+    }
+
+    let (reply, answered) = oneshot::channel();
+    if requests.send(Pending { command: request.command, reply }).await.is_err() {
+        return Response::error("the daemon is shutting down");
+    }
+    match tokio::time::timeout(ANSWER_TIMEOUT, answered).await {
+        Ok(Ok(Ok(result))) => Response::ok(result),
+        Ok(Ok(Err(e))) => Response::error(e),
+        Ok(Err(_)) => Response::error("the daemon is shutting down"),
+        Err(_) => Response::error(format!("no answer after {}s", ANSWER_TIMEOUT.as_secs())),
+    }
+}
+
+/// Send one request over `stream` and read the response
+pub async fn exchange<S>(stream: S, request: &Request) -> Result<Response, Box<dyn Error>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (read, mut write) = tokio::io::split(stream);
+    let mut encoded = serde_json::to_string(request)?;
+    encoded.push('\n');
+    write.write_all(encoded.as_bytes()).await?;
+
+    let mut line = String::new();
+    BufReader::new(read).read_line(&mut line).await?;
+    if line.is_empty() {
+        return Err("the daemon closed the connection".into());
+    }
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// Token the running daemon wrote to `path`
+pub fn read_token(path: &Path) -> Result<String, Box<dyn Error>> {
+    std::fs::read_to_string(path)
+        .map(|token| token.trim().to_string())
+        .map_err(|e| format!("cannot read {} (is eva-daemon running?): {}", path.display(), e).into())
+}
+
+/// Send `command` to the daemon at the usual place (the daemon itself
+/// never does; `evactl` does)
+#[allow(dead_code)]
+pub async fn send(command: Command) -> Result<Response, Box<dyn Error>> {
+    let token = read_token(&crate::paths::data_file(TOKEN_FILE)?)?;
+    let request = Request { version: PROTOCOL_VERSION, token, command };
+
+    #[cfg(unix)]
+    let stream = tokio::net::UnixStream::connect(socket_path())
+        .await
+        .map_err(|e| format!("cannot connect to {} (is eva-daemon running?): {}", socket_path().display(), e))?;
+    #[cfg(windows)]
+    let stream = tokio::net::windows::named_pipe::ClientOptions::new()
+        .open(PIPE_NAME)
+        .map_err(|e| format!("cannot open {} (is eva-daemon running?): {}", PIPE_NAME, e))?;
+
+    exchange(stream, &request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Stands in for the main loop: answers what it is asked, like main.rs
+    /// does, and records the commands it saw
+    fn fake_daemon(mut pending: mpsc::Receiver<Pending>) -> tokio::task::JoinHandle<Vec<Command>> {
+        tokio::spawn(async move {
+            let mut seen = Vec::new();
+            while let Some(request) = pending.recv().await {
+                seen.push(request.command.clone());
+                let result = match &request.command {
+                    Command::Status => Ok(json!({ "status": "Idle", "session_id": "s-1", "turns": 4 })),
+                    Command::Say { .. } => Ok(json!({})),
+                    Command::Ask { text } => Ok(json!({ "reply": format!("You asked: {}", text) })),
+                    Command::TimeMachineSearch { query, limit } => {
+                        Ok(json!({ "results": [{ "id": 7, "text": query }], "limit": limit.unwrap_or(DEFAULT_SEARCH_LIMIT) }))
+                    }
+                    Command::TimeMachineTimeline { date } => Ok(json!([{ "id": 7, "date": date }])),
+                    Command::TimeMachineThumbnail { id } => Ok(json!({ "id": id, "mime_type": "image/png", "data": "" })),
+                    Command::Webhooks => Ok(json!({ "webhooks": [{ "name": "home", "delivered": 3 }] })),
+                    Command::Shutdown => Err("not now".to_string()),
+                    Command::Get { path } => Ok(json!({ "path": path })),
+                    Command::Set { value, .. } if value.trim() == "1.5" => Err("out of range".to_string()),
+                    Command::Set { .. } => Ok(json!({})),
+                    Command::Export { path, .. } => Ok(json!({ "path": path })),
+                    Command::Import { .. } => Ok(json!({ "session_id": "s-2", "turns": 2 })),
+                };
+                request.answer(result);
+            }
+            seen
+        })
+    }
+
+    fn request(token: &str, command: Command) -> Request {
+        Request { version: PROTOCOL_VERSION, token: token.to_string(), command }
+    }
+
+    #[test]
+    fn test_envelope_wire_format() {
+        let encoded = serde_json::to_value(request("t", Command::TimeMachineSearch { query: "budget".to_string(), limit: None })).unwrap();
+        assert_eq!(encoded, json!({ "version": 1, "token": "t", "command": "timemachine_search", "query": "budget", "limit": null }));
+
+        let decoded: Request = serde_json::from_str(r#"{"version":1,"token":"t","command":"say","text":"hi"}"#).unwrap();
+        assert_eq!(decoded.command, Command::Say { text: "hi".to_string() });
+        let decoded: Request = serde_json::from_str(r#"{"version":1,"token":"t","command":"set","path":"status","value":"x"}"#).unwrap();
+        assert_eq!(decoded.command, Command::Set { path: "status".to_string(), value: "x".to_string() });
+        let decoded: Request = serde_json::from_str(r#"{"version":1,"token":"t","command":"timemachine_timeline"}"#).unwrap();
+        assert_eq!(decoded.command, Command::TimeMachineTimeline { date: None });
+        let decoded: Request = serde_json::from_str(r#"{"version":1,"token":"t","command":"export","path":"/tmp/s.md","format":"markdown"}"#).unwrap();
+        assert_eq!(decoded.command, Command::Export { path: "/tmp/s.md".to_string(), format: "markdown".to_string(), audio: false });
+
+        assert_eq!(serde_json::to_value(Response::ok(json!({ "reply": "hi" }))).unwrap(), json!({ "version": 1, "ok": true, "result": { "reply": "hi" } }));
+        assert_eq!(serde_json::to_value(Response::error("nope")).unwrap(), json!({ "version": 1, "ok": false, "error": "nope" }));
+    }
+
+    #[tokio::test]
+    async fn test_bad_requests_never_reach_the_daemon() {
+        let (requests, pending) = mpsc::channel(QUEUE_DEPTH);
+        let daemon = fake_daemon(pending);
+        let token = "a".repeat(64);
+
+        let reply = answer(&serde_json::to_string(&request("wrong", Command::Shutdown)).unwrap(), &token, &requests).await;
+        assert_eq!(reply.error.as_deref(), Some("invalid token"));
+        let reply = answer(r#"{"version":2,"command":"status"}"#, &token, &requests).await;
+        assert_eq!(reply.error.as_deref(), Some("unsupported protocol version 2 (expected 1)"));
+        let reply = answer(&format!(r#"{{"version":1,"token":"{}","command":"reboot"}}"#, token), &token, &requests).await;
+        assert!(reply.error.unwrap().starts_with("invalid request"));
+        assert!(answer("status", &token, &requests).await.error.unwrap().starts_with("invalid JSON"));
+
+        drop(requests);
+        assert!(daemon.await.unwrap().is_empty());
+        assert!(!tokens_match(&"a".repeat(63), &token));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_every_command_over_the_socket() {
+        let dir = std::env::temp_dir().join(format!("eva_test_control_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join(SOCKET_NAME);
+        let token_file = dir.join(TOKEN_FILE);
+
+        let (server, pending) = ControlServer::bind(&socket, &token_file).unwrap();
+        let daemon = fake_daemon(pending);
+        let token = read_token(&token_file).unwrap();
+        assert_eq!(token.len(), 64);
+        // Only one daemon at a time
+        assert!(ControlServer::bind(&socket, &dir.join("other.token")).is_err());
+
+        let commands = [
+            Command::Status,
+            Command::Say { text: "hello".to_string() },
+            Command::Ask { text: "what time is it?".to_string() },
+            Command::TimeMachineSearch { query: "budget".to_string(), limit: Some(2) },
+            Command::Shutdown,
+            Command::Get { path: "config/language".to_string() },
+            Command::Set { path: "config/wake_sensitivity".to_string(), value: "1.5".to_string() },
+            Command::Webhooks,
+        ];
+        let mut responses = Vec::new();
+        for command in &commands {
+            let stream = tokio::net::UnixStream::connect(&socket).await.unwrap();
+            responses.push(exchange(stream, &request(&token, command.clone())).await.unwrap());
+        }
+        assert_eq!(responses[0].result["session_id"], "s-1");
+        assert!(responses[1].ok);
+        assert_eq!(responses[2].result["reply"], "You asked: what time is it?");
+        assert_eq!(responses[3].result, json!({ "results": [{ "id": 7, "text": "budget" }], "limit": 2 }));
+        assert_eq!(responses[4], Response::error("not now"));
+        assert_eq!(responses[5].result["path"], "config/language");
+        assert_eq!(responses[6], Response::error("out of range"));
+        assert_eq!(responses[7].result["webhooks"][0]["delivered"], 3);
+
+        // Several requests on one connection
+        let mut stream = tokio::net::UnixStream::connect(&socket).await.unwrap();
+        for _ in 0..2 {
+            assert!(exchange(&mut stream, &request(&token, Command::Status)).await.unwrap().ok);
+        }
+        drop(stream);
+
+        drop(server);
+        assert!(!socket.exists() && !token_file.exists());
+        let mut seen = daemon.await.unwrap();
+        seen.truncate(commands.len());
+        assert_eq!(seen, commands);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,9 +1,20 @@
+use crate::command_parser::CommandIntent;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
+/// Words an utterance may add around a trigger without hurting the match
+const FILLER_WORDS: &[&str] = &[
+    "eva", "hey", "ok", "okay", "please", "now", "um", "uh", "the", "a", "an", "can", "could", "you",
+    "por", "favor", "agora", "o", "os", "as", "pode", "ei",
+];
+
 /// Custom command definition
+///
+/// The trigger may contain `{name}` placeholders ("deploy {branch} to
+/// {env}"); the words the user says in their place are substituted into
+/// the same placeholders in the action.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CustomCommand {
     pub trigger: String,
@@ -14,27 +25,100 @@ pub struct CustomCommand {
 /// Command action types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum CommandAction {
+    /// Arguments are substituted quoted for the shell it runs in (`sh`, or
+    /// `cmd` on Windows), so don't quote the placeholders yourself
     ExecuteShell(String),
     RunMacro(String),
     SendText(String),
     Custom(String),
+    /// A built-in command; arguments go into its text fields
+    Intent(CommandIntent),
+}
+
+impl CommandAction {
+    /// The action with `{name}` placeholders replaced by `args`
+    ///
+    /// One pass over the template: braces inside an argument are never
+    /// expanded again. Unknown placeholders are left as they are.
+    pub fn substitute(&self, args: &HashMap<String, String>) -> CommandAction {
+        match self {
+            CommandAction::ExecuteShell(cmd) => CommandAction::ExecuteShell(fill_template(cmd, args, shell_quote)),
+            CommandAction::RunMacro(name) => CommandAction::RunMacro(fill_template(name, args, str::to_string)),
+            CommandAction::SendText(text) => CommandAction::SendText(fill_template(text, args, str::to_string)),
+            CommandAction::Custom(text) => CommandAction::Custom(fill_template(text, args, str::to_string)),
+            CommandAction::Intent(intent) => {
+                let mut value = serde_json::to_value(intent).unwrap_or(serde_json::Value::Null);
+                substitute_strings(&mut value, args);
+                CommandAction::Intent(serde_json::from_value(value).unwrap_or_else(|_| intent.clone()))
+            }
+        }
+    }
+}
+
+/// A piece of a trigger
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Word(String),
+    Param(String),
+}
+
+impl CustomCommand {
+    /// Placeholder names in the trigger, in order
+    pub fn parameters(&self) -> Vec<String> {
+        segments(&self.trigger)
+            .into_iter()
+            .filter_map(|seg| match seg {
+                Segment::Param(name) => Some(name),
+                Segment::Word(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// A trigger matched against an utterance
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateMatch {
+    pub trigger: String,
+    pub args: HashMap<String, String>,
+    /// Placeholders the utterance left empty, in trigger order
+    pub missing: Vec<String>,
+    /// Higher is better: trigger words and filled parameters count for,
+    /// unexplained extra words and missing parameters against
+    pub score: f32,
+}
+
+/// What a custom command turned an utterance into
+#[derive(Debug, Clone)]
+pub enum CustomOutcome {
+    /// All parameters known; the action with its arguments substituted.
+    /// `alternatives` are the other triggers that also matched.
+    Run { action: CommandAction, alternatives: Vec<String> },
+    /// A parameter is missing; ask this, the answer goes to `take_input`
+    Ask(String),
 }
 
 /// Custom command manager
 pub struct CustomCommandManager {
     commands: HashMap<String, CustomCommand>,
     config_path: PathBuf,
+    /// Template waiting for the user to supply a missing parameter
+    pending: Option<TemplateMatch>,
 }
 
 impl CustomCommandManager {
     /// Create a new custom command manager
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let config_path = Self::get_config_path()?;
+        Self::load_from(Self::get_config_path()?)
+    }
+
+    /// Load commands from a specific file (missing file = no commands)
+    pub fn load_from(config_path: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
         let commands = Self::load_commands(&config_path)?;
 
         Ok(Self {
             commands,
             config_path,
+            pending: None,
         })
     }
 
@@ -59,14 +143,50 @@ impl CustomCommandManager {
             return Some(cmd);
         }
 
-        // Check for partial match
-        for (trigger, cmd) in &self.commands {
-            if text_lower.contains(trigger) {
-                return Some(cmd);
+        self.match_all(text).first().and_then(|m| self.commands.get(&m.trigger))
+    }
+
+    /// Every trigger the utterance matches, best first
+    pub fn match_all(&self, text: &str) -> Vec<TemplateMatch> {
+        let words = words(text);
+        let mut matches: Vec<TemplateMatch> =
+            self.commands.keys().filter_map(|trigger| match_template(trigger, &words)).collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.trigger.cmp(&b.trigger)));
+        matches
+    }
+
+    /// Handle a line: the answer to a pending parameter question, or a new
+    /// utterance matched against the triggers. `None` if no trigger matches.
+    pub fn take_input(&mut self, text: &str) -> Option<CustomOutcome> {
+        if let Some(mut pending) = self.pending.take() {
+            let value = clean_value(&words(text));
+            if !value.is_empty() {
+                let name = pending.missing.remove(0);
+                pending.args.insert(name, value);
             }
+            return Some(self.outcome(pending, Vec::new()));
         }
 
-        None
+        let mut matches = self.match_all(text).into_iter();
+        let best = matches.next()?;
+        let alternatives = matches.map(|m| m.trigger).collect();
+        Some(self.outcome(best, alternatives))
+    }
+
+    /// Whether the last outcome was a question still waiting for its answer
+    pub fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    fn outcome(&mut self, matched: TemplateMatch, alternatives: Vec<String>) -> CustomOutcome {
+        if let Some(name) = matched.missing.first() {
+            let shown = fill_template(&matched.trigger, &matched.args, str::to_string);
+            let question = format!("Which {}? ({})", name.replace('_', " "), shown);
+            self.pending = Some(matched);
+            return CustomOutcome::Ask(question);
+        }
+        let action = self.commands[&matched.trigger].action.substitute(&matched.args);
+        CustomOutcome::Run { action, alternatives }
     }
 
     /// Get command count
@@ -111,22 +231,149 @@ impl CustomCommandManager {
 
     /// Get config file path
     fn get_config_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
-        #[cfg(target_os = "windows")]
-        {
-            let home = std::env::var("USERPROFILE")?;
-            Ok(PathBuf::from(home)
-                .join(".eva")
-                .join("custom_commands.json"))
+        crate::paths::data_file("custom_commands.json")
+    }
+}
+
+/// Split a trigger into words and `{name}` placeholders
+fn segments(trigger: &str) -> Vec<Segment> {
+    trigger
+        .split_whitespace()
+        .map(trim_punctuation)
+        .filter(|w| !w.is_empty())
+        .map(|w| match w.strip_prefix('{').and_then(|w| w.strip_suffix('}')) {
+            Some(name) => Segment::Param(name.trim().to_string()),
+            None => Segment::Word(w.to_lowercase()),
+        })
+        .collect()
+}
+
+/// Utterance words as (lowercase, as spoken)
+fn words(text: &str) -> Vec<(String, &str)> {
+    text.split_whitespace()
+        .map(trim_punctuation)
+        .filter(|w| !w.is_empty())
+        .map(|w| (w.to_lowercase(), w))
+        .collect()
+}
+
+fn trim_punctuation(word: &str) -> &str {
+    word.trim_matches(|c: char| matches!(c, ',' | '.' | '!' | '?' | ';' | ':' | '"' | '¿' | '¡'))
+}
+
+fn is_filler(word: &str) -> bool {
+    FILLER_WORDS.contains(&word)
+}
+
+/// A parameter value: the words as spoken, without filler at either end
+fn clean_value(words: &[(String, &str)]) -> String {
+    let start = words.iter().position(|(w, _)| !is_filler(w)).unwrap_or(words.len());
+    let end = words.iter().rposition(|(w, _)| !is_filler(w)).map_or(start, |i| i + 1);
+    words[start..end.max(start)].iter().map(|(_, w)| *w).collect::<Vec<_>>().join(" ")
+}
+
+/// Match the trigger's words in order; the words between them fill the
+/// placeholders. A trigger without any fixed word never matches.
+fn match_template(trigger: &str, words: &[(String, &str)]) -> Option<TemplateMatch> {
+    let segments = segments(trigger);
+    let literals = segments.iter().filter(|s| matches!(s, Segment::Word(_))).count();
+    if literals == 0 {
+        return None;
+    }
+
+    let mut args = HashMap::new();
+    let mut missing = Vec::new();
+    let mut fill = |name: String, span: &[(String, &str)]| {
+        let value = clean_value(span);
+        if value.is_empty() {
+            missing.push(name);
+        } else {
+            args.insert(name, value);
+        }
+    };
+    let extra = |span: &[(String, &str)]| span.iter().filter(|(w, _)| !is_filler(w)).count();
+
+    let mut pos = 0;
+    let mut extra_words = 0;
+    let mut open: Option<String> = None;
+    for segment in segments {
+        match segment {
+            Segment::Word(word) => {
+                let at = (pos..words.len()).find(|&i| words[i].0 == word)?;
+                match open.take() {
+                    Some(name) => fill(name, &words[pos..at]),
+                    None => extra_words += extra(&words[pos..at]),
+                }
+                pos = at + 1;
+            }
+            // Two placeholders in a row: the first one takes a single word
+            Segment::Param(name) => {
+                if let Some(previous) = open.replace(name) {
+                    let end = (pos + 1).min(words.len());
+                    fill(previous, &words[pos..end]);
+                    pos = end;
+                }
+            }
         }
+    }
+    match open {
+        Some(name) => fill(name, &words[pos..]),
+        None => extra_words += extra(&words[pos..]),
+    }
+
+    let score = literals as f32 + 0.5 * args.len() as f32 - 0.25 * extra_words as f32 - 0.5 * missing.len() as f32;
+    Some(TemplateMatch { trigger: trigger.to_string(), args, missing, score })
+}
 
-        #[cfg(not(target_os = "windows"))]
-        {
-            let home = std::env::var("HOME")?;
-            Ok(PathBuf::from(home)
-                .join(".eva")
-                .join("custom_commands.json"))
+/// Replace `{name}` placeholders in one pass, passing values through `escape`
+fn fill_template(template: &str, args: &HashMap<String, String>, escape: impl Fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        match after.find('}').map(|close| (&after[..close], close)) {
+            Some((name, close)) if args.contains_key(name.trim()) => {
+                out.push_str(&escape(&args[name.trim()]));
+                rest = &after[close + 1..];
+            }
+            _ => {
+                out.push('{');
+                rest = after;
+            }
         }
     }
+    out.push_str(rest);
+    out
+}
+
+/// Quote a value as one argument for the shell `ExecuteShell` runs in
+fn shell_quote(value: &str) -> String {
+    if cfg!(target_os = "windows") {
+        cmd_quote(value)
+    } else {
+        sh_quote(value)
+    }
+}
+
+/// Single-quote a value for `sh`
+fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Double-quote a value for `cmd /S /C`: quotes are doubled, and `%` is
+/// left outside the quotes behind `^` so no variable is expanded
+fn cmd_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\"").replace('%', "\"^%\""))
+}
+
+fn substitute_strings(value: &mut serde_json::Value, args: &HashMap<String, String>) {
+    match value {
+        serde_json::Value::String(s) => *s = fill_template(s, args, str::to_string),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|v| substitute_strings(v, args)),
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(|v| substitute_strings(v, args)),
+        _ => {}
+    }
 }
 
 impl Default for CustomCommandManager {
@@ -191,4 +438,127 @@ mod tests {
         mgr.remove_command("remove_me").unwrap();
         assert_eq!(mgr.count(), 0);
     }
+
+    fn scratch(name: &str, commands: &[(&str, CommandAction)]) -> CustomCommandManager {
+        let path = std::env::temp_dir().join(format!("eva_test_custom_{}_{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut mgr = CustomCommandManager::load_from(path).unwrap();
+        for (trigger, action) in commands {
+            let cmd = CustomCommand { trigger: trigger.to_string(), action: action.clone(), description: String::new() };
+            mgr.add_command(cmd).unwrap();
+        }
+        mgr
+    }
+
+    fn shell(cmd: &str) -> CommandAction {
+        CommandAction::ExecuteShell(cmd.to_string())
+    }
+
+    #[test]
+    fn test_template_extracts_parameters() {
+        let mgr = scratch("extract", &[("deploy {branch} to {env}", shell("./deploy.sh {branch} {env}"))]);
+        assert_eq!(mgr.list_commands()[0].parameters(), vec!["branch", "env"]);
+
+        let matched = &mgr.match_all("Hey EVA, please deploy Feature/Login to Staging now!")[0];
+        assert_eq!(matched.args["branch"], "Feature/Login");
+        assert_eq!(matched.args["env"], "Staging");
+        assert!(matched.missing.is_empty());
+
+        // Trigger words must all be there, in order
+        assert!(mgr.match_all("deploy main").is_empty());
+        assert!(mgr.match_all("to staging deploy main").is_empty());
+
+        let _ = fs::remove_file(&mgr.config_path);
+    }
+
+    #[test]
+    fn test_best_template_wins() {
+        let mut mgr = scratch(
+            "ambiguous",
+            &[
+                ("deploy {app}", shell("deploy-app {app}")),
+                ("deploy {branch} to {env}", shell("deploy {branch} {env}")),
+            ],
+        );
+
+        match mgr.take_input("deploy main to staging") {
+            Some(CustomOutcome::Run { action: CommandAction::ExecuteShell(cmd), alternatives }) => {
+                assert_eq!(cmd, "deploy 'main' 'staging'");
+                assert_eq!(alternatives, vec!["deploy {app}"]);
+            }
+            other => panic!("{:?}", other),
+        }
+        match mgr.take_input("deploy the website") {
+            Some(CustomOutcome::Run { action: CommandAction::ExecuteShell(cmd), .. }) => assert_eq!(cmd, "deploy-app 'website'"),
+            other => panic!("{:?}", other),
+        }
+        assert!(mgr.take_input("what's the weather").is_none());
+
+        let _ = fs::remove_file(&mgr.config_path);
+    }
+
+    #[test]
+    fn test_missing_parameter_is_asked_for() {
+        let mut mgr = scratch("missing", &[("deploy {branch} to {env}", shell("deploy {branch} {env}"))]);
+
+        match mgr.take_input("deploy to production") {
+            Some(CustomOutcome::Ask(question)) => assert_eq!(question, "Which branch? (deploy {branch} to production)"),
+            other => panic!("{:?}", other),
+        }
+        assert!(mgr.has_pending());
+        match mgr.take_input("the hotfix branch") {
+            Some(CustomOutcome::Run { action: CommandAction::ExecuteShell(cmd), .. }) => {
+                assert_eq!(cmd, "deploy 'hotfix branch' 'production'")
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(!mgr.has_pending());
+
+        let _ = fs::remove_file(&mgr.config_path);
+    }
+
+    #[test]
+    fn test_substitution_escaping() {
+        let args: HashMap<String, String> = [
+            ("msg".to_string(), "it's $(rm -rf ~); `x`".to_string()),
+            ("name".to_string(), "{msg}".to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        // Shell arguments are one quoted word, whatever they contain
+        let CommandAction::ExecuteShell(cmd) = shell("echo {msg} {other}").substitute(&args) else { unreachable!() };
+        assert_eq!(cmd, format!("echo {} {{other}}", shell_quote("it's $(rm -rf ~); `x`")));
+        assert_eq!(sh_quote("it's $(rm -rf ~); `x`"), "'it'\\''s $(rm -rf ~); `x`'");
+        assert_eq!(cmd_quote(r#"50% & "x" | del"#), r#""50"^%" & ""x"" | del""#);
+
+        // Values are never expanded a second time
+        let CommandAction::SendText(text) = CommandAction::SendText("hi {name}".to_string()).substitute(&args) else { unreachable!() };
+        assert_eq!(text, "hi {msg}");
+
+        // Intents get the raw value in their text fields
+        let intent = CommandIntent::File(crate::command_parser::FileOperation::Create {
+            path: "notes/{name}.txt".to_string(),
+            content: Some("{msg}".to_string()),
+        });
+        let CommandAction::Intent(filled) = CommandAction::Intent(intent).substitute(&args) else { unreachable!() };
+        assert_eq!(
+            filled,
+            CommandIntent::File(crate::command_parser::FileOperation::Create {
+                path: "notes/{msg}.txt".to_string(),
+                content: Some("it's $(rm -rf ~); `x`".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_templates_persist() {
+        let mgr = scratch("persist", &[("open ticket {id}", CommandAction::SendText("Opening {id}".to_string()))]);
+        let mut reloaded = CustomCommandManager::load_from(mgr.config_path.clone()).unwrap();
+        match reloaded.take_input("open ticket 42") {
+            Some(CustomOutcome::Run { action: CommandAction::SendText(text), .. }) => assert_eq!(text, "Opening 42"),
+            other => panic!("{:?}", other),
+        }
+        let _ = fs::remove_file(&mgr.config_path);
+    }
 }
@@ -0,0 +1,316 @@
+//! Clipboard and synthesized input for the text commands
+//!
+//! "Type ...", "select all", "copy that" and "paste" act on whatever
+//! window has focus. With the `desktop-input` feature the clipboard is
+//! arboard and keystrokes come from enigo; without it, and on Redox, every
+//! text command fails with a sentence saying why.
+//!
+//! Synthesized keystrokes are rate limited: one command types at most
+//! `MAX_TYPED_CHARS`, and no more than `KEYSTROKE_BUDGET` keys go out per
+//! `KEYSTROKE_WINDOW`, so a runaway macro can't flood the focused window.
+
+use crate::command_parser::TextOperation;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Longest text one "type ..." may enter
+pub const MAX_TYPED_CHARS: usize = 500;
+
+/// Keystrokes allowed per `KEYSTROKE_WINDOW`
+pub const KEYSTROKE_BUDGET: usize = 2000;
+
+pub const KEYSTROKE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Time for the focused app to fill the clipboard after the copy shortcut
+const COPY_SETTLE: Duration = Duration::from_millis(150);
+
+/// Where clipboard access and keystrokes go
+pub trait InputBackend: Send {
+    fn clipboard_text(&mut self) -> Result<String, String>;
+    fn set_clipboard_text(&mut self, text: &str) -> Result<(), String>;
+    /// Type `text` into the focused window
+    fn type_text(&mut self, text: &str) -> Result<(), String>;
+    /// Press Ctrl+`key` (Cmd+`key` on macOS)
+    fn shortcut(&mut self, key: char) -> Result<(), String>;
+}
+
+/// The backend for this build and platform
+pub fn system_backend() -> Box<dyn InputBackend> {
+    #[cfg(all(feature = "desktop-input", not(target_os = "redox")))]
+    {
+        Box::new(SystemInput)
+    }
+    #[cfg(not(all(feature = "desktop-input", not(target_os = "redox"))))]
+    {
+        Box::new(Unavailable)
+    }
+}
+
+/// arboard + enigo; both are opened per call, which keeps this `Send` and
+/// picks up a display that appeared after startup
+#[cfg(all(feature = "desktop-input", not(target_os = "redox")))]
+struct SystemInput;
+
+#[cfg(all(feature = "desktop-input", not(target_os = "redox")))]
+impl SystemInput {
+    fn clipboard() -> Result<arboard::Clipboard, String> {
+        arboard::Clipboard::new().map_err(|e| format!("I can't reach the clipboard: {}", e))
+    }
+
+    fn keyboard() -> Result<enigo::Enigo, String> {
+        enigo::Enigo::new(&enigo::Settings::default()).map_err(|e| format!("I can't send keystrokes here: {}", e))
+    }
+}
+
+#[cfg(all(feature = "desktop-input", not(target_os = "redox")))]
+impl InputBackend for SystemInput {
+    fn clipboard_text(&mut self) -> Result<String, String> {
+        match Self::clipboard()?.get_text() {
+            Ok(text) => Ok(text),
+            Err(arboard::Error::ContentNotAvailable) => Ok(String::new()),
+            Err(e) => Err(format!("I couldn't read the clipboard: {}", e)),
+        }
+    }
+
+    fn set_clipboard_text(&mut self, text: &str) -> Result<(), String> {
+        Self::clipboard()?.set_text(text).map_err(|e| format!("I couldn't write to the clipboard: {}", e))
+    }
+
+    fn type_text(&mut self, text: &str) -> Result<(), String> {
+        use enigo::Keyboard;
+        Self::keyboard()?.text(text).map_err(|e| format!("Typing failed: {}", e))
+    }
+
+    fn shortcut(&mut self, key: char) -> Result<(), String> {
+        use enigo::{Direction, Key, Keyboard};
+        let modifier = if cfg!(target_os = "macos") { Key::Meta } else { Key::Control };
+        let mut keyboard = Self::keyboard()?;
+        keyboard.key(modifier, Direction::Press).map_err(|e| e.to_string())?;
+        let pressed = keyboard.key(Key::Unicode(key), Direction::Click);
+        // Release the modifier even if the key failed, or it stays held
+        let released = keyboard.key(modifier, Direction::Release);
+        pressed.and(released).map_err(|e| format!("The shortcut failed: {}", e))
+    }
+}
+
+/// Redox, or a build without `desktop-input`
+#[cfg(not(all(feature = "desktop-input", not(target_os = "redox"))))]
+struct Unavailable;
+
+#[cfg(not(all(feature = "desktop-input", not(target_os = "redox"))))]
+impl Unavailable {
+    fn reason() -> String {
+        if cfg!(target_os = "redox") {
+            "Typing and the clipboard aren't supported on Redox yet.".to_string()
+        } else {
+            "Typing and the clipboard need EVA built with the desktop-input feature.".to_string()
+        }
+    }
+}
+
+#[cfg(not(all(feature = "desktop-input", not(target_os = "redox"))))]
+impl InputBackend for Unavailable {
+    fn clipboard_text(&mut self) -> Result<String, String> {
+        Err(Self::reason())
+    }
+
+    fn set_clipboard_text(&mut self, _text: &str) -> Result<(), String> {
+        Err(Self::reason())
+    }
+
+    fn type_text(&mut self, _text: &str) -> Result<(), String> {
+        Err(Self::reason())
+    }
+
+    fn shortcut(&mut self, _key: char) -> Result<(), String> {
+        Err(Self::reason())
+    }
+}
+
+/// Sliding-window budget of synthesized keystrokes
+pub struct KeystrokeLimiter {
+    budget: usize,
+    window: Duration,
+    sent: VecDeque<(Instant, usize)>,
+}
+
+impl KeystrokeLimiter {
+    pub fn new(budget: usize, window: Duration) -> Self {
+        Self { budget, window, sent: VecDeque::new() }
+    }
+
+    /// Spend `count` keystrokes at `now`, or say when there'll be room
+    pub fn take(&mut self, count: usize, now: Instant) -> Result<(), String> {
+        while self.sent.front().is_some_and(|(at, _)| now.duration_since(*at) >= self.window) {
+            self.sent.pop_front();
+        }
+        let used: usize = self.sent.iter().map(|(_, n)| n).sum();
+        if used + count > self.budget {
+            let wait = self.sent.front().map_or(self.window, |(at, _)| self.window.saturating_sub(now.duration_since(*at)));
+            return Err(format!(
+                "That's a lot of typing in a short time. Try again in {} seconds.",
+                wait.as_secs().max(1)
+            ));
+        }
+        self.sent.push_back((now, count));
+        Ok(())
+    }
+}
+
+/// Runs text commands against an `InputBackend`
+pub struct TextInput {
+    backend: Box<dyn InputBackend>,
+    limiter: KeystrokeLimiter,
+}
+
+impl TextInput {
+    pub fn new(backend: Box<dyn InputBackend>) -> Self {
+        Self { backend, limiter: KeystrokeLimiter::new(KEYSTROKE_BUDGET, KEYSTROKE_WINDOW) }
+    }
+
+    pub async fn run(&mut self, op: TextOperation, now: Instant) -> Result<String, String> {
+        match op {
+            TextOperation::Type { text } => {
+                let count = text.chars().count();
+                if count > MAX_TYPED_CHARS {
+                    return Err(format!("That's {} characters; I type at most {} at a time.", count, MAX_TYPED_CHARS));
+                }
+                self.limiter.take(count, now)?;
+                self.backend.type_text(&text)?;
+                Ok(format!("Typed {}.", characters(count)))
+            }
+            TextOperation::Select => {
+                self.limiter.take(1, now)?;
+                self.backend.shortcut('a')?;
+                Ok("Selected all text.".to_string())
+            }
+            TextOperation::Copy => {
+                self.limiter.take(1, now)?;
+                // Clear first, so a copy that didn't happen isn't reported
+                // as the old clipboard
+                self.backend.set_clipboard_text("")?;
+                self.backend.shortcut('c')?;
+                tokio::time::sleep(COPY_SETTLE).await;
+                match self.backend.clipboard_text()?.chars().count() {
+                    0 => Err("Nothing was copied. Select some text first.".to_string()),
+                    n => Ok(format!("Copied {}.", characters(n))),
+                }
+            }
+            TextOperation::Paste => {
+                let count = self.backend.clipboard_text()?.chars().count();
+                if count == 0 {
+                    return Err("The clipboard is empty.".to_string());
+                }
+                self.limiter.take(1, now)?;
+                self.backend.shortcut('v')?;
+                Ok(format!("Pasted {}.", characters(count)))
+            }
+        }
+    }
+}
+
+/// "1 character", "12 characters"
+fn characters(count: usize) -> String {
+    if count == 1 {
+        "1 character".to_string()
+    } else {
+        format!("{} characters", count)
+    }
+}
+
+/// In-memory backend recording what it was asked to do
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct FakeInput {
+    pub clipboard: std::sync::Arc<std::sync::Mutex<String>>,
+    pub typed: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    /// Text the focused window has selected, copied on Ctrl+C
+    pub selection: String,
+}
+
+#[cfg(test)]
+impl InputBackend for FakeInput {
+    fn clipboard_text(&mut self) -> Result<String, String> {
+        Ok(self.clipboard.lock().unwrap().clone())
+    }
+
+    fn set_clipboard_text(&mut self, text: &str) -> Result<(), String> {
+        *self.clipboard.lock().unwrap() = text.to_string();
+        Ok(())
+    }
+
+    fn type_text(&mut self, text: &str) -> Result<(), String> {
+        self.typed.lock().unwrap().push(text.to_string());
+        Ok(())
+    }
+
+    fn shortcut(&mut self, key: char) -> Result<(), String> {
+        match key {
+            'c' => *self.clipboard.lock().unwrap() = self.selection.clone(),
+            'v' => {
+                let pasted = self.clipboard.lock().unwrap().clone();
+                self.typed.lock().unwrap().push(pasted);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_text_operations_on_fake_backend() {
+        let fake = FakeInput { selection: "hello".to_string(), ..FakeInput::default() };
+        let mut input = TextInput::new(Box::new(fake.clone()));
+        let now = Instant::now();
+
+        assert_eq!(input.run(TextOperation::Paste, now).await.unwrap_err(), "The clipboard is empty.");
+        assert_eq!(input.run(TextOperation::Copy, now).await.unwrap(), "Copied 5 characters.");
+        assert_eq!(input.run(TextOperation::Paste, now).await.unwrap(), "Pasted 5 characters.");
+        assert_eq!(input.run(TextOperation::Type { text: "a".to_string() }, now).await.unwrap(), "Typed 1 character.");
+        assert_eq!(*fake.typed.lock().unwrap(), vec!["hello".to_string(), "a".to_string()]);
+
+        let long = "x".repeat(MAX_TYPED_CHARS + 1);
+        assert!(input.run(TextOperation::Type { text: long }, now).await.unwrap_err().contains("at most"));
+    }
+
+    #[test]
+    fn test_keystroke_rate_limit() {
+        let mut limiter = KeystrokeLimiter::new(10, Duration::from_secs(60));
+        let start = Instant::now();
+        limiter.take(6, start).unwrap();
+        limiter.take(4, start + Duration::from_secs(10)).unwrap();
+
+        let err = limiter.take(1, start + Duration::from_secs(20)).unwrap_err();
+        assert!(err.contains("40 seconds"), "{}", err);
+
+        // The first burst ages out of the window
+        limiter.take(6, start + Duration::from_secs(60)).unwrap();
+        assert!(limiter.take(1, start + Duration::from_secs(61)).is_err());
+    }
+
+    #[cfg(not(all(feature = "desktop-input", not(target_os = "redox"))))]
+    #[tokio::test]
+    async fn test_unavailable_backend_explains_why() {
+        let mut input = TextInput::new(system_backend());
+        let err = input.run(TextOperation::Select, Instant::now()).await.unwrap_err();
+        assert!(err.contains("desktop-input") || err.contains("Redox"), "{}", err);
+    }
+
+    /// Needs a real desktop session: skipped on headless Linux
+    #[cfg(all(feature = "desktop-input", not(target_os = "redox")))]
+    #[test]
+    fn test_system_clipboard_round_trip() {
+        if cfg!(target_os = "linux") && std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+            return;
+        }
+        let mut backend = system_backend();
+        let previous = backend.clipboard_text().unwrap_or_default();
+        backend.set_clipboard_text("eva clipboard test").unwrap();
+        assert_eq!(backend.clipboard_text().unwrap(), "eva clipboard test");
+        let _ = backend.set_clipboard_text(&previous);
+    }
+}
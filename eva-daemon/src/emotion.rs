@@ -1,3 +1,4 @@
+use crate::stt::Language;
 use std::collections::HashMap;
 use std::fmt;
 
@@ -29,9 +30,166 @@ impl fmt::Display for Emotion {
     }
 }
 
+/// Default share of the voice in a blended result (the rest is the text)
+pub const DEFAULT_VOICE_WEIGHT: f32 = 0.5;
+
+/// Analysis frame for loudness and pitch (40 ms, long enough for two
+/// periods at the lowest pitch searched)
+const FRAME_SECS: f32 = 0.04;
+/// Envelope frame for counting syllable onsets
+const ENVELOPE_SECS: f32 = 0.01;
+/// Frames quieter than this (RMS) are treated as silence
+const SILENCE_RMS: f32 = 0.01;
+/// Pitch search range for speaking voices
+const MIN_PITCH_HZ: f32 = 60.0;
+const MAX_PITCH_HZ: f32 = 400.0;
+/// Normalized autocorrelation a frame needs to count as voiced
+const VOICING_THRESHOLD: f32 = 0.5;
+/// Utterances with less voiced audio than this are not judged
+const MIN_VOICED_SECS: f32 = 0.3;
+
+/// Prosody of a captured utterance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProsodyFeatures {
+    /// Mean RMS of the non-silent frames
+    pub energy: f32,
+    /// Mean pitch of the voiced frames, if any were voiced
+    pub pitch_hz: Option<f32>,
+    /// Pitch standard deviation relative to the mean (0 = monotone)
+    pub pitch_variation: f32,
+    /// Syllable-like energy onsets per second
+    pub speaking_rate: f32,
+    /// Seconds of non-silent audio
+    pub voiced_secs: f32,
+}
+
+impl ProsodyFeatures {
+    /// Extract pitch (autocorrelation), energy and speaking rate
+    pub fn analyze(samples: &[f32], sample_rate: u32) -> Self {
+        let rate = sample_rate.max(1) as f32;
+        let frame = ((rate * FRAME_SECS) as usize).max(1);
+
+        let mut energies = Vec::new();
+        let mut pitches = Vec::new();
+        for chunk in samples.chunks_exact(frame) {
+            let rms = rms(chunk);
+            if rms < SILENCE_RMS {
+                continue;
+            }
+            energies.push(rms);
+            if let Some(pitch) = frame_pitch(chunk, rate) {
+                pitches.push(pitch);
+            }
+        }
+
+        let energy = mean(&energies);
+        let pitch_hz = (!pitches.is_empty()).then(|| mean(&pitches));
+        let pitch_variation = pitch_hz
+            .map(|m| (pitches.iter().map(|p| (p - m).powi(2)).sum::<f32>() / pitches.len() as f32).sqrt() / m)
+            .unwrap_or(0.0);
+
+        Self {
+            energy,
+            pitch_hz,
+            pitch_variation,
+            speaking_rate: onset_rate(samples, rate),
+            voiced_secs: energies.len() as f32 * frame as f32 / rate,
+        }
+    }
+
+    /// Map the features to an emotion with a confidence (0.0 to 1.0)
+    ///
+    /// Loudness, pace and pitch movement together give the arousal: lively
+    /// and animated reads as excited, loud but flat as frustrated, and a
+    /// quiet, even voice as calm (neutral).
+    pub fn classify(&self) -> (Emotion, f32) {
+        if self.voiced_secs < MIN_VOICED_SECS {
+            return (Emotion::Neutral, 0.0);
+        }
+
+        let loud = ((self.energy - 0.02) / 0.15).clamp(0.0, 1.0);
+        let fast = ((self.speaking_rate - 2.0) / 4.0).clamp(0.0, 1.0);
+        let lively = (self.pitch_variation / 0.25).clamp(0.0, 1.0);
+        let arousal = (loud + fast + lively) / 3.0;
+
+        if arousal > 0.6 && lively > 0.5 {
+            (Emotion::Excited, arousal)
+        } else if loud > 0.6 && lively < 0.3 {
+            (Emotion::Frustrated, (loud + fast) / 2.0)
+        } else {
+            (Emotion::Neutral, 1.0 - arousal)
+        }
+    }
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+fn mean(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f32>() / values.len() as f32
+}
+
+/// Pitch of one frame from its normalized autocorrelation, `None` if unvoiced
+fn frame_pitch(frame: &[f32], rate: f32) -> Option<f32> {
+    let min_lag = (rate / MAX_PITCH_HZ) as usize;
+    let max_lag = ((rate / MIN_PITCH_HZ) as usize).min(frame.len() / 2);
+    if min_lag == 0 || min_lag >= max_lag {
+        return None;
+    }
+
+    let correlation = |lag: usize| {
+        let (a, b) = (&frame[..frame.len() - lag], &frame[lag..]);
+        let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        let norm = (a.iter().map(|x| x * x).sum::<f32>() * b.iter().map(|y| y * y).sum::<f32>()).sqrt();
+        if norm > 0.0 { dot / norm } else { 0.0 }
+    };
+    let scores: Vec<(usize, f32)> = (min_lag..=max_lag).map(|lag| (lag, correlation(lag))).collect();
+    let best = scores.iter().map(|&(_, r)| r).fold(f32::MIN, f32::max);
+    if best < VOICING_THRESHOLD {
+        return None;
+    }
+    // The first peak close to the best one, so multiples of the period
+    // don't halve the pitch
+    let peak = scores.windows(3).find(|w| w[1].1 >= best * 0.9 && w[1].1 >= w[0].1 && w[1].1 >= w[2].1);
+    let (lag, _) = peak.map(|w| w[1]).or_else(|| scores.iter().copied().max_by(|a, b| a.1.total_cmp(&b.1)))?;
+    Some(rate / lag as f32)
+}
+
+/// Energy onsets per second, with hysteresis so one syllable counts once
+fn onset_rate(samples: &[f32], rate: f32) -> f32 {
+    let hop = ((rate * ENVELOPE_SECS) as usize).max(1);
+    let envelope: Vec<f32> = samples.chunks(hop).map(rms).collect();
+    let peak = envelope.iter().cloned().fold(0.0, f32::max);
+    let on = (peak * 0.3).max(SILENCE_RMS);
+    let off = on * 0.5;
+
+    let mut onsets = 0;
+    let mut sounding = false;
+    for &level in &envelope {
+        if !sounding && level >= on {
+            onsets += 1;
+            sounding = true;
+        } else if sounding && level < off {
+            sounding = false;
+        }
+    }
+
+    let secs = samples.len() as f32 / rate;
+    if secs > 0.0 { onsets as f32 / secs } else { 0.0 }
+}
+
 /// Emotion detector
 pub struct EmotionDetector {
     keywords: HashMap<Emotion, Vec<String>>,
+    /// Share of the voice when blending with text (0.0 to 1.0)
+    voice_weight: f32,
 }
 
 impl EmotionDetector {
@@ -120,7 +278,45 @@ impl EmotionDetector {
             ],
         );
 
-        Self { keywords }
+        Self { keywords, voice_weight: DEFAULT_VOICE_WEIGHT }
+    }
+
+    /// Create an emotion detector using the lexicon for a language
+    ///
+    /// Only English and Portuguese lexicons exist; other languages fall
+    /// back to English.
+    pub fn for_language(language: Language) -> Self {
+        match language {
+            Language::PortugueseBR => Self::portuguese(),
+            _ => Self::new(),
+        }
+    }
+
+    /// Portuguese (Brazil) lexicon
+    fn portuguese() -> Self {
+        let lexicon: &[(Emotion, &[&str])] = &[
+            (Emotion::Happy, &["feliz", "ótimo", "otimo", "legal", "excelente", "maravilhoso", "bom", "adoro", "perfeito"]),
+            (Emotion::Sad, &["triste", "decepcionado", "ruim", "terrível", "terrivel", "péssimo", "pessimo", "deprimido"]),
+            (Emotion::Angry, &["bravo", "irritado", "raiva", "furioso", "chateado"]),
+            (Emotion::Excited, &["animado", "incrível", "incrivel", "uau", "fantástico", "fantastico"]),
+            (Emotion::Confused, &["confuso", "não entendi", "nao entendi", "não entendo", "como assim"]),
+            (Emotion::Grateful, &["obrigado", "obrigada", "valeu", "agradeço", "agradeco"]),
+            (Emotion::Frustrated, &["frustrado", "travado", "não consigo", "nao consigo", "não funciona"]),
+        ];
+
+        let keywords = lexicon
+            .iter()
+            .map(|(emotion, words)| (*emotion, words.iter().map(|w| w.to_string()).collect()))
+            .collect();
+
+        Self { keywords, voice_weight: DEFAULT_VOICE_WEIGHT }
+    }
+
+    /// How much the voice counts against the words in `blend` (0.0 = text
+    /// only, 1.0 = voice only)
+    pub fn with_voice_weight(mut self, weight: f32) -> Self {
+        self.voice_weight = weight.clamp(0.0, 1.0);
+        self
     }
 
     /// Detect emotion from text
@@ -180,6 +376,35 @@ impl EmotionDetector {
             (Emotion::Neutral, 1.0)
         }
     }
+
+    /// Detect emotion from how a captured utterance sounds
+    pub fn detect_from_audio(&self, samples: &[f32], sample_rate: u32) -> Emotion {
+        self.detect_from_audio_with_confidence(samples, sample_rate).0
+    }
+
+    /// Emotion from the utterance's prosody, with confidence (0.0 to 1.0)
+    pub fn detect_from_audio_with_confidence(&self, samples: &[f32], sample_rate: u32) -> (Emotion, f32) {
+        ProsodyFeatures::analyze(samples, sample_rate).classify()
+    }
+
+    /// Combine a text result with a voice result, weighted by the voice
+    /// weight. Neutral only wins when neither side found anything else.
+    pub fn blend(&self, text: (Emotion, f32), voice: Option<(Emotion, f32)>) -> Emotion {
+        let Some(voice) = voice else {
+            return text.0;
+        };
+
+        let mut scores: HashMap<Emotion, f32> = HashMap::new();
+        *scores.entry(text.0).or_default() += (1.0 - self.voice_weight) * text.1;
+        *scores.entry(voice.0).or_default() += self.voice_weight * voice.1;
+
+        scores
+            .into_iter()
+            .filter(|&(emotion, score)| emotion != Emotion::Neutral && score > 0.0)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(emotion, _)| emotion)
+            .unwrap_or(Emotion::Neutral)
+    }
 }
 
 impl Default for EmotionDetector {
@@ -237,6 +462,17 @@ mod tests {
         assert_eq!(detector.detect("The sky is blue"), Emotion::Neutral);
     }
 
+    #[test]
+    fn test_portuguese_lexicon() {
+        let detector = EmotionDetector::for_language(Language::PortugueseBR);
+        assert_eq!(detector.detect("Muito obrigado pela ajuda"), Emotion::Grateful);
+        assert_eq!(detector.detect("Estou tão feliz hoje"), Emotion::Happy);
+
+        // The English lexicon doesn't know these words
+        let english = EmotionDetector::for_language(Language::EnglishUS);
+        assert_eq!(english.detect("Muito obrigado pela ajuda"), Emotion::Neutral);
+    }
+
     #[test]
     fn test_detect_with_confidence() {
         let detector = EmotionDetector::new();
@@ -245,4 +481,81 @@ mod tests {
         assert!(emotion == Emotion::Happy || emotion == Emotion::Excited);
         assert!(confidence > 0.0 && confidence <= 1.0);
     }
+
+    const RATE: u32 = 16000;
+
+    /// A voice at `pitch(t)` Hz, gated by `gain(t)`
+    fn voice(secs: f32, pitch: impl Fn(f32) -> f32, gain: impl Fn(f32) -> f32) -> Vec<f32> {
+        let mut phase = 0.0f32;
+        (0..(secs * RATE as f32) as usize)
+            .map(|i| {
+                let t = i as f32 / RATE as f32;
+                phase += 2.0 * std::f32::consts::PI * pitch(t) / RATE as f32;
+                phase.sin() * gain(t)
+            })
+            .collect()
+    }
+
+    /// Loud syllables, five a second
+    fn syllables(t: f32) -> f32 {
+        if (t * 5.0).fract() < 0.5 { 0.5 } else { 0.0 }
+    }
+
+    #[test]
+    fn test_prosody_features() {
+        let steady = ProsodyFeatures::analyze(&voice(2.0, |_| 120.0, |_| 0.05), RATE);
+        let pitch = steady.pitch_hz.unwrap();
+        assert!((pitch - 120.0).abs() < 3.0, "pitch {}", pitch);
+        assert!(steady.pitch_variation < 0.02);
+        assert!(steady.speaking_rate < 1.0);
+        assert!((steady.voiced_secs - 2.0).abs() < 0.05);
+
+        let lively = ProsodyFeatures::analyze(
+            &voice(2.0, |t| 225.0 + 75.0 * (2.0 * std::f32::consts::PI * 3.0 * t).sin(), syllables),
+            RATE,
+        );
+        assert!(lively.energy > steady.energy * 5.0);
+        assert!(lively.pitch_variation > 0.15, "variation {}", lively.pitch_variation);
+        assert!((4.0..=6.0).contains(&lively.speaking_rate), "rate {}", lively.speaking_rate);
+
+        let silence = ProsodyFeatures::analyze(&[0.0; 16000], RATE);
+        assert_eq!(silence.pitch_hz, None);
+        assert_eq!(silence.voiced_secs, 0.0);
+    }
+
+    #[test]
+    fn test_detect_from_audio() {
+        let detector = EmotionDetector::new();
+
+        // Quiet monotone: calm
+        let calm = voice(2.0, |_| 120.0, |_| 0.02);
+        assert_eq!(detector.detect_from_audio(&calm, RATE), Emotion::Neutral);
+
+        // Loud, quick and with a moving pitch: excited
+        let excited = voice(2.0, |t| 225.0 + 75.0 * (2.0 * std::f32::consts::PI * 3.0 * t).sin(), syllables);
+        assert_eq!(detector.detect_from_audio(&excited, RATE), Emotion::Excited);
+
+        // Just as loud and quick but flat: frustrated
+        let flat = voice(2.0, |_| 180.0, syllables);
+        assert_eq!(detector.detect_from_audio(&flat, RATE), Emotion::Frustrated);
+
+        // Too short to judge
+        let (emotion, confidence) = detector.detect_from_audio_with_confidence(&excited[..1600], RATE);
+        assert_eq!((emotion, confidence), (Emotion::Neutral, 0.0));
+    }
+
+    #[test]
+    fn test_blend_weights_voice_against_text() {
+        let text = (Emotion::Happy, 0.4);
+        let voice = Some((Emotion::Frustrated, 0.8));
+
+        assert_eq!(EmotionDetector::new().blend(text, voice), Emotion::Frustrated);
+        assert_eq!(EmotionDetector::new().with_voice_weight(0.2).blend(text, voice), Emotion::Happy);
+        assert_eq!(EmotionDetector::new().with_voice_weight(0.0).blend(text, voice), Emotion::Happy);
+
+        // A neutral side never hides the other one
+        assert_eq!(EmotionDetector::new().blend((Emotion::Neutral, 1.0), voice), Emotion::Frustrated);
+        assert_eq!(EmotionDetector::new().blend(text, Some((Emotion::Neutral, 1.0))), Emotion::Happy);
+        assert_eq!(EmotionDetector::new().blend((Emotion::Neutral, 1.0), None), Emotion::Neutral);
+    }
 }
@@ -0,0 +1,241 @@
+use crate::errors::{ErrorKind, EvaError};
+use crate::tts::SpeechFallback;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Identical failures are spoken at most once per this window
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(5 * 60);
+
+/// Short spoken explanation with a next step, (en, pt)
+pub fn explanation(kind: ErrorKind) -> (&'static str, &'static str) {
+    match kind {
+        ErrorKind::ConnectFailed => (
+            "I can't reach the cloud right now. Local commands and timers still work.",
+            "Não consigo falar com a nuvem agora. Comandos locais e timers continuam funcionando.",
+        ),
+        ErrorKind::StreamFailed => (
+            "I lost my connection to the cloud in the middle of that. Please say it again in a moment.",
+            "Perdi a conexão com a nuvem no meio disso. Repita daqui a pouco, por favor.",
+        ),
+        ErrorKind::ReceiveFailed => (
+            "My connection dropped while I was answering. I'll keep trying.",
+            "A conexão caiu enquanto eu respondia. Vou continuar tentando.",
+        ),
+        ErrorKind::NoResponse => (
+            "I didn't get an answer back. Could you ask again?",
+            "Não recebi resposta. Pode perguntar de novo?",
+        ),
+        ErrorKind::AudioCapture => (
+            "I can't hear the microphone. Check that it's plugged in and not muted.",
+            "Não estou ouvindo o microfone. Veja se ele está conectado e sem mudo.",
+        ),
+        ErrorKind::AudioPlayback => (
+            "I'm having trouble with the speakers. The answer is on the screen.",
+            "Estou com problema nos alto-falantes. A resposta está na tela.",
+        ),
+        ErrorKind::CommandFailed => (
+            "That command didn't work. The details are on the screen.",
+            "Esse comando não funcionou. Os detalhes estão na tela.",
+        ),
+        ErrorKind::CommandBlocked => (
+            "I'm not allowed to do that here.",
+            "Não tenho permissão para fazer isso aqui.",
+        ),
+        ErrorKind::SaveFailed => (
+            "I couldn't save that to disk. Check that there's free space.",
+            "Não consegui salvar no disco. Veja se há espaço livre.",
+        ),
+        ErrorKind::TimeMachineUnavailable => (
+            "Time Machine isn't running, so I can't search your screen history.",
+            "O Time Machine não está ativo, então não consigo buscar no histórico da tela.",
+        ),
+    }
+}
+
+/// Localized explanation for a profile language tag ("pt-BR", "en", ...)
+pub fn explain(error: &EvaError, language: &str) -> &'static str {
+    let (en, pt) = explanation(error.kind());
+    if language.to_lowercase().starts_with("pt") {
+        pt
+    } else {
+        en
+    }
+}
+
+/// What to do with one failure
+#[derive(Debug, Clone, PartialEq)]
+pub struct Announcement {
+    /// Said out loud; `None` when the same failure was spoken recently
+    pub spoken: Option<&'static str>,
+    /// Raw detail, always shown in the TUI
+    pub detail: String,
+}
+
+/// An announcement with its speech
+pub struct Report {
+    pub announcement: Announcement,
+    /// `announcement.spoken` synthesized; `None` when nothing is said
+    pub speech: Option<Vec<f32>>,
+}
+
+/// Rate-limits spoken error explanations
+pub struct ErrorAnnouncer {
+    language: String,
+    cooldown: Duration,
+    last_spoken: HashMap<ErrorKind, Instant>,
+}
+
+impl ErrorAnnouncer {
+    pub fn new(language: &str) -> Self {
+        Self::with_cooldown(language, DEFAULT_COOLDOWN)
+    }
+
+    pub fn with_cooldown(language: &str, cooldown: Duration) -> Self {
+        Self { language: language.to_string(), cooldown, last_spoken: HashMap::new() }
+    }
+
+    pub fn announce(&mut self, error: &EvaError) -> Announcement {
+        self.announce_at(error, Instant::now())
+    }
+
+    /// Same, at a given time (see `Clock::instant`)
+    pub fn announce_at(&mut self, error: &EvaError, now: Instant) -> Announcement {
+        let kind = error.kind();
+        let recently = self
+            .last_spoken
+            .get(&kind)
+            .is_some_and(|t| now.duration_since(*t) < self.cooldown);
+
+        let spoken = if recently {
+            None
+        } else {
+            self.last_spoken.insert(kind, now);
+            Some(explain(error, &self.language))
+        };
+        Announcement { spoken, detail: error.to_string() }
+    }
+
+    /// Announce `error` and synthesize what is said with `speech`, the
+    /// fallback text-only replies are read out through
+    pub fn report(&mut self, error: &EvaError, speech: &mut SpeechFallback) -> Report {
+        self.report_at(error, Instant::now(), speech)
+    }
+
+    /// Same, at a given time (see `Clock::instant`)
+    pub fn report_at(&mut self, error: &EvaError, now: Instant, speech: &mut SpeechFallback) -> Report {
+        let announcement = self.announce_at(error, now);
+        let speech = announcement.spoken.and_then(|spoken| speech.speech_for(spoken, false));
+        Report { announcement, speech }
+    }
+
+    /// The failure went away; speak it again if it comes back
+    pub fn resolved(&mut self, kind: ErrorKind) {
+        self.last_spoken.remove(&kind);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tts::TextToSpeech;
+    use std::sync::{Arc, Mutex};
+
+    /// Records what it was asked to say
+    struct MockTts(Arc<Mutex<Vec<String>>>);
+
+    impl TextToSpeech for MockTts {
+        fn synth(&mut self, text: &str) -> Vec<f32> {
+            self.0.lock().unwrap().push(text.to_string());
+            vec![0.1; 240]
+        }
+    }
+
+    /// Exhaustive on purpose: a new ErrorKind fails to compile here until
+    /// it is added to ErrorKind::ALL as well
+    fn ordinal(kind: ErrorKind) -> usize {
+        match kind {
+            ErrorKind::ConnectFailed => 0,
+            ErrorKind::StreamFailed => 1,
+            ErrorKind::ReceiveFailed => 2,
+            ErrorKind::NoResponse => 3,
+            ErrorKind::AudioCapture => 4,
+            ErrorKind::AudioPlayback => 5,
+            ErrorKind::CommandFailed => 6,
+            ErrorKind::CommandBlocked => 7,
+            ErrorKind::SaveFailed => 8,
+            ErrorKind::TimeMachineUnavailable => 9,
+        }
+    }
+
+    #[test]
+    fn test_every_kind_has_messages() {
+        for (i, kind) in ErrorKind::ALL.iter().enumerate() {
+            assert_eq!(ordinal(*kind), i, "ErrorKind::ALL out of sync at {:?}", kind);
+            let (en, pt) = explanation(*kind);
+            assert!(!en.trim().is_empty(), "{:?} has no English message", kind);
+            assert!(!pt.trim().is_empty(), "{:?} has no Portuguese message", kind);
+            assert_ne!(en, pt, "{:?} is not translated", kind);
+        }
+    }
+
+    #[test]
+    fn test_localized_by_profile_language() {
+        let err = EvaError::NoResponse;
+        assert!(explain(&err, "pt-BR").starts_with("Não recebi"));
+        assert!(explain(&err, "en").starts_with("I didn't"));
+    }
+
+    #[test]
+    fn test_repeated_failure_spoken_once() {
+        let mut announcer = ErrorAnnouncer::with_cooldown("en", Duration::from_secs(60));
+        let t0 = Instant::now();
+        let err = EvaError::StreamFailed("connection reset by peer".to_string());
+
+        let first = announcer.announce_at(&err, t0);
+        assert!(first.spoken.is_some());
+        assert_eq!(first.detail, "Stream error: connection reset by peer");
+
+        let again = announcer.announce_at(&err, t0 + Duration::from_secs(5));
+        assert_eq!(again.spoken, None);
+        assert_eq!(again.detail, first.detail);
+
+        // A different failure is still spoken
+        assert!(announcer.announce_at(&EvaError::NoResponse, t0).spoken.is_some());
+
+        // After the cooldown, or once resolved, it is spoken again
+        assert!(announcer.announce_at(&err, t0 + Duration::from_secs(61)).spoken.is_some());
+        announcer.resolved(ErrorKind::StreamFailed);
+        assert!(announcer.announce_at(&err, t0 + Duration::from_secs(62)).spoken.is_some());
+    }
+
+    #[test]
+    fn test_spoken_error_reaches_the_tts_backend() {
+        let said = Arc::new(Mutex::new(Vec::new()));
+        let mut speech = SpeechFallback::with_backend(Some(Box::new(MockTts(said.clone()))));
+        let mut announcer = ErrorAnnouncer::new("pt-BR");
+
+        let report = announcer.report(&EvaError::AudioCapture("no device".to_string()), &mut speech);
+        assert_eq!(report.announcement.detail, "Audio error: no device");
+        assert_eq!(report.speech.map(|s| s.len()), Some(240));
+        assert_eq!(*said.lock().unwrap(), vec![explain(&EvaError::AudioCapture(String::new()), "pt-BR").to_string()]);
+    }
+
+    #[test]
+    fn test_recovery_hint_is_synthesized_once_per_cooldown() {
+        let said = Arc::new(Mutex::new(Vec::new()));
+        let mut speech = SpeechFallback::with_backend(Some(Box::new(MockTts(said.clone()))));
+        let mut announcer = ErrorAnnouncer::with_cooldown("en", Duration::from_secs(60));
+        let t0 = Instant::now();
+        let err = EvaError::ConnectFailed("timed out".to_string());
+
+        assert!(announcer.report_at(&err, t0, &mut speech).speech.is_some());
+        let again = announcer.report_at(&err, t0 + Duration::from_secs(5), &mut speech);
+        assert_eq!(again.announcement.detail, "Could not connect to EVA-Mind: timed out");
+        assert!(again.speech.is_none());
+        assert_eq!(*said.lock().unwrap(), vec!["I can't reach the cloud right now. Local commands and timers still work.".to_string()]);
+
+        announcer.resolved(ErrorKind::ConnectFailed);
+        assert!(announcer.report_at(&err, t0 + Duration::from_secs(6), &mut speech).speech.is_some());
+        assert_eq!(said.lock().unwrap().len(), 2);
+    }
+}
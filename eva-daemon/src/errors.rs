@@ -0,0 +1,95 @@
+use std::fmt;
+
+/// Failures EVA can run into while handling a conversation
+///
+/// Each variant carries the raw detail for the TUI/logs; what gets said out
+/// loud comes from `error_speech`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvaError {
+    /// Could not reach EVA-Mind at startup
+    ConnectFailed(String),
+    /// Streaming microphone audio to EVA-Mind failed mid-turn
+    StreamFailed(String),
+    /// Reading EVA-Mind's reply failed
+    ReceiveFailed(String),
+    /// EVA-Mind accepted the turn but sent no audio back
+    NoResponse,
+    /// Microphone capture failed
+    AudioCapture(String),
+    /// Speaker playback failed
+    AudioPlayback(String),
+    /// A local command ran and failed
+    CommandFailed(String),
+    /// A local command was refused (guest mode, sandbox)
+    CommandBlocked(String),
+    /// Saving local state (session, timers, profile) failed
+    SaveFailed(String),
+    /// Time Machine could not start
+    TimeMachineUnavailable(String),
+}
+
+/// Variant without payload, used as a key for messages and rate limiting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    ConnectFailed,
+    StreamFailed,
+    ReceiveFailed,
+    NoResponse,
+    AudioCapture,
+    AudioPlayback,
+    CommandFailed,
+    CommandBlocked,
+    SaveFailed,
+    TimeMachineUnavailable,
+}
+
+impl ErrorKind {
+    pub const ALL: [ErrorKind; 10] = [
+        ErrorKind::ConnectFailed,
+        ErrorKind::StreamFailed,
+        ErrorKind::ReceiveFailed,
+        ErrorKind::NoResponse,
+        ErrorKind::AudioCapture,
+        ErrorKind::AudioPlayback,
+        ErrorKind::CommandFailed,
+        ErrorKind::CommandBlocked,
+        ErrorKind::SaveFailed,
+        ErrorKind::TimeMachineUnavailable,
+    ];
+}
+
+impl EvaError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            EvaError::ConnectFailed(_) => ErrorKind::ConnectFailed,
+            EvaError::StreamFailed(_) => ErrorKind::StreamFailed,
+            EvaError::ReceiveFailed(_) => ErrorKind::ReceiveFailed,
+            EvaError::NoResponse => ErrorKind::NoResponse,
+            EvaError::AudioCapture(_) => ErrorKind::AudioCapture,
+            EvaError::AudioPlayback(_) => ErrorKind::AudioPlayback,
+            EvaError::CommandFailed(_) => ErrorKind::CommandFailed,
+            EvaError::CommandBlocked(_) => ErrorKind::CommandBlocked,
+            EvaError::SaveFailed(_) => ErrorKind::SaveFailed,
+            EvaError::TimeMachineUnavailable(_) => ErrorKind::TimeMachineUnavailable,
+        }
+    }
+}
+
+impl fmt::Display for EvaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvaError::ConnectFailed(e) => write!(f, "Could not connect to EVA-Mind: {}", e),
+            EvaError::StreamFailed(e) => write!(f, "Stream error: {}", e),
+            EvaError::ReceiveFailed(e) => write!(f, "Receive error: {}", e),
+            EvaError::NoResponse => write!(f, "No audio response received"),
+            EvaError::AudioCapture(e) => write!(f, "Audio error: {}", e),
+            EvaError::AudioPlayback(e) => write!(f, "Audio playback error: {}", e),
+            EvaError::CommandFailed(e) => write!(f, "Command failed: {}", e),
+            EvaError::CommandBlocked(e) => write!(f, "Command blocked: {}", e),
+            EvaError::SaveFailed(e) => write!(f, "Could not save: {}", e),
+            EvaError::TimeMachineUnavailable(e) => write!(f, "Time Machine disabled: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EvaError {}
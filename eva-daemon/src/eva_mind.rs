@@ -19,6 +19,10 @@ fn log_debug(msg: &str) {
 pub struct EvaMindConfig {
     pub ws_url: String,
     pub cpf: String,
+    /// Condensed capability list sent with the call so the model only
+    /// offers actions this build can perform
+    #[serde(default)]
+    pub capabilities: Option<String>,
 }
 
 impl Default for EvaMindConfig {
@@ -26,6 +30,7 @@ impl Default for EvaMindConfig {
         Self {
             ws_url: "wss://eva-ia.org:8090/ws/pcm".to_string(),
             cpf: "64525430249".to_string(), // Creator CPF
+            capabilities: None,
         }
     }
 }
@@ -77,11 +82,14 @@ impl EvaMindClient {
 
     /// Start call session
     pub async fn start_call(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let start_msg = json!({
+        let mut start_msg = json!({
             "type": "start_call",
             "cpf": self.config.cpf,
             "session_id": self.session_id
         });
+        if let Some(ref capabilities) = self.config.capabilities {
+            start_msg["capabilities"] = json!(capabilities);
+        }
 
         log_debug(&format!("📤 Start call: {}", start_msg));
         self.ws.send_text(&start_msg.to_string()).await?;
@@ -156,6 +164,11 @@ impl EvaMindClient {
         Ok(())
     }
 
+    /// Messages (mostly audio) waiting to be written to the socket
+    pub fn queue_depth(&self) -> usize {
+        self.ws.queue_depth()
+    }
+
     /// Receive audio data (PCM bytes or control messages)
     pub async fn receive(&mut self) -> Result<Option<EvaMindResponse>, Box<dyn std::error::Error>> {
         let receive_timeout = tokio::time::timeout(
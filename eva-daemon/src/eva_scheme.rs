@@ -0,0 +1,441 @@
+//! `eva:` scheme: runtime settings as files
+//!
+//! On Redox nothing else changes a running daemon (no TUI keys, no config
+//! file editing), so its settings are files in the `eva:` scheme:
+//!
+//! ```text
+//! eva:config/wake_sensitivity   rw  0.0 to 1.0
+//! eva:config/language           rw  a language tag (en-US, pt-BR, ...)
+//! eva:config/dsp                rw  chain.stage=on|off lines (capture.noise_gate=off)
+//! eva:status                    r   status, session and counters (JSON)
+//! eva:session/context           rw  key=value lines; a write sets the keys given
+//! ```
+//!
+//! Reading `eva:` lists them. Writes are checked before anything changes:
+//! a bad value fails the write with `EINVAL`, a read-only file with
+//! `EACCES`. Config writes are saved (the profile, or audio_processor.json
+//! for DSP stages) and applied live.
+//!
+//! Everywhere else the same files are `get`/`set` requests on the control
+//! socket (`evactl get config/language`). On Redox the scheme thread turns
+//! file operations into those requests too, so the main loop answers both
+//! through `read` and `write` below.
+
+use crate::audio_processor::ChainKind;
+use serde_json::{json, Value};
+
+/// Stages a DSP chain can hold, as `config/dsp` names them
+const DSP_STAGES: [&str; 4] = ["dc_block", "noise_gate", "agc", "limiter"];
+
+/// One virtual file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvaFile {
+    /// `eva:` itself: the list of files
+    Root,
+    WakeSensitivity,
+    Language,
+    Dsp,
+    Status,
+    SessionContext,
+}
+
+impl EvaFile {
+    pub const ALL: [EvaFile; 5] = [EvaFile::WakeSensitivity, EvaFile::Language, EvaFile::Dsp, EvaFile::Status, EvaFile::SessionContext];
+
+    /// The file at `path`, with or without the `eva:` prefix
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let path = path.trim();
+        let path = path.strip_prefix("eva:").unwrap_or(path).trim_matches('/');
+        match path {
+            "" => Ok(EvaFile::Root),
+            "config/wake_sensitivity" => Ok(EvaFile::WakeSensitivity),
+            "config/language" => Ok(EvaFile::Language),
+            "config/dsp" => Ok(EvaFile::Dsp),
+            "status" => Ok(EvaFile::Status),
+            "session/context" => Ok(EvaFile::SessionContext),
+            _ => Err(format!("no such file eva:{}", path)),
+        }
+    }
+
+    pub fn path(&self) -> &'static str {
+        match self {
+            EvaFile::Root => "",
+            EvaFile::WakeSensitivity => "config/wake_sensitivity",
+            EvaFile::Language => "config/language",
+            EvaFile::Dsp => "config/dsp",
+            EvaFile::Status => "status",
+            EvaFile::SessionContext => "session/context",
+        }
+    }
+}
+
+/// A write that passed validation
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    WakeSensitivity(f32),
+    /// A tag `Language::from_tag` knows, in its canonical form
+    Language(String),
+    /// DSP stages to turn on or off, in the order given
+    Dsp(Vec<(ChainKind, String, bool)>),
+    /// Context keys to set, in the order given
+    Context(Vec<(String, String)>),
+}
+
+/// Why a read or write was refused
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileError {
+    NotFound(String),
+    ReadOnly(&'static str),
+    Invalid(String),
+    /// The value was fine but applying it failed
+    Failed(String),
+}
+
+impl std::fmt::Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileError::NotFound(e) | FileError::Invalid(e) | FileError::Failed(e) => write!(f, "{}", e),
+            FileError::ReadOnly(path) => write!(f, "eva:{} is read-only", path),
+        }
+    }
+}
+
+/// Check `value` for `path` without applying it
+pub fn parse_write(path: &str, value: &str) -> Result<Change, FileError> {
+    let file = EvaFile::parse(path).map_err(FileError::NotFound)?;
+    let value = value.trim();
+    match file {
+        EvaFile::WakeSensitivity => value
+            .replace(',', ".")
+            .parse::<f32>()
+            .ok()
+            .filter(|n| (0.0..=1.0).contains(n))
+            .map(Change::WakeSensitivity)
+            .ok_or_else(|| FileError::Invalid(format!("wake_sensitivity must be a number from 0 to 1, not '{}'", value))),
+        EvaFile::Language => crate::stt::Language::from_tag(value)
+            .map(|language| Change::Language(language.locale().to_string()))
+            .ok_or_else(|| FileError::Invalid(format!("unsupported language '{}'", value))),
+        EvaFile::Dsp => {
+            let mut stages = Vec::new();
+            for line in value.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                let invalid = || FileError::Invalid(format!("expected chain.stage=on|off, not '{}'", line));
+                let (key, state) = line.split_once('=').ok_or_else(invalid)?;
+                let (chain, stage) = key.trim().split_once('.').ok_or_else(invalid)?;
+                let chain = ChainKind::parse(chain).ok_or_else(invalid)?;
+                if !DSP_STAGES.contains(&stage) {
+                    return Err(FileError::Invalid(format!("unknown DSP stage '{}'", stage)));
+                }
+                let enabled = match state.trim() {
+                    "on" | "true" => true,
+                    "off" | "false" => false,
+                    _ => return Err(invalid()),
+                };
+                stages.push((chain, stage.to_string(), enabled));
+            }
+            if stages.is_empty() {
+                return Err(FileError::Invalid("expected chain.stage=on|off lines".to_string()));
+            }
+            Ok(Change::Dsp(stages))
+        }
+        EvaFile::SessionContext => {
+            let mut entries = Vec::new();
+            for line in value.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                let (key, val) = line
+                    .split_once('=')
+                    .map(|(k, v)| (k.trim(), v.trim()))
+                    .filter(|(k, _)| !k.is_empty() && !k.contains(char::is_whitespace))
+                    .ok_or_else(|| FileError::Invalid(format!("expected key=value, not '{}'", line)))?;
+                entries.push((key.to_string(), val.to_string()));
+            }
+            if entries.is_empty() {
+                return Err(FileError::Invalid("expected key=value lines".to_string()));
+            }
+            Ok(Change::Context(entries))
+        }
+        EvaFile::Root | EvaFile::Status => Err(FileError::ReadOnly(file.path())),
+    }
+}
+
+/// What the files read from and write to; the main loop's state
+pub trait Settings {
+    fn wake_sensitivity(&self) -> f32;
+    fn language(&self) -> String;
+    /// Each configured DSP stage as `chain.stage` and whether it is on
+    fn dsp(&self) -> Vec<(String, bool)>;
+    fn status(&self) -> Value;
+    /// Session context, sorted by key
+    fn context(&self) -> Vec<(String, String)>;
+    fn apply(&mut self, change: Change) -> Result<(), String>;
+}
+
+/// Current contents of `path`
+pub fn read(settings: &impl Settings, path: &str) -> Result<Value, FileError> {
+    Ok(match EvaFile::parse(path).map_err(FileError::NotFound)? {
+        EvaFile::Root => json!(EvaFile::ALL.iter().map(EvaFile::path).collect::<Vec<_>>()),
+        EvaFile::WakeSensitivity => json!(settings.wake_sensitivity()),
+        EvaFile::Language => json!(settings.language()),
+        EvaFile::Dsp => Value::Object(settings.dsp().into_iter().map(|(k, on)| (k, json!(on))).collect()),
+        EvaFile::Status => settings.status(),
+        EvaFile::SessionContext => Value::Object(settings.context().into_iter().map(|(k, v)| (k, json!(v))).collect()),
+    })
+}
+
+/// Validate `value` and apply it
+pub fn write(settings: &mut impl Settings, path: &str, value: &str) -> Result<(), FileError> {
+    let change = parse_write(path, value)?;
+    settings.apply(change).map_err(FileError::Failed)
+}
+
+/// `value` as file contents: text as is, context and DSP stages as they
+/// are written, the rest as JSON
+pub fn render(file: EvaFile, value: &Value) -> String {
+    let mut text = match (file, value) {
+        (EvaFile::Root, Value::Array(paths)) => paths.iter().filter_map(Value::as_str).collect::<Vec<_>>().join("\n"),
+        (EvaFile::SessionContext, Value::Object(entries)) => entries
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value.as_str().unwrap_or_default()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        (EvaFile::Dsp, Value::Object(stages)) => stages
+            .iter()
+            .map(|(key, on)| format!("{}={}", key, if on.as_bool().unwrap_or_default() { "on" } else { "off" }))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        (_, Value::String(text)) => text.clone(),
+        (_, other) => serde_json::to_string_pretty(other).unwrap_or_default(),
+    };
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text
+}
+
+/// The `eva:` scheme served on its own thread; reads and writes become
+/// control requests answered by the main loop
+#[cfg(target_os = "redox")]
+mod redox {
+    use super::{parse_write, render, EvaFile, FileError};
+    use crate::control::{Command, Pending};
+    use serde_json::Value;
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+    use syscall::{EACCES, EBADF, EINVAL, EIO, ENOENT};
+    use tokio::sync::mpsc;
+
+    struct Handle {
+        file: EvaFile,
+        /// Contents, fetched on the first read
+        text: Option<Vec<u8>>,
+        pos: usize,
+    }
+
+    pub struct EvaScheme {
+        requests: mpsc::Sender<Pending>,
+        handles: RefCell<HashMap<usize, Handle>>,
+        next_id: Cell<usize>,
+    }
+
+    impl EvaScheme {
+        fn ask(&self, command: Command) -> Result<Value, i32> {
+            let (pending, answer) = Pending::new(command);
+            self.requests.blocking_send(pending).map_err(|_| EIO)?;
+            answer.blocking_recv().map_err(|_| EIO)?.map_err(|e| {
+                eprintln!("[Scheme] {}", e);
+                EIO
+            })
+        }
+    }
+
+    impl syscall::Scheme for EvaScheme {
+        fn open(&self, path: &str, _flags: usize, _uid: u32, _gid: u32) -> syscall::Result<usize> {
+            let file = EvaFile::parse(path).map_err(|_| syscall::Error::new(ENOENT))?;
+            let id = self.next_id.get();
+            self.next_id.set(id + 1);
+            self.handles.borrow_mut().insert(id, Handle { file, text: None, pos: 0 });
+            Ok(id)
+        }
+
+        fn read(&self, id: usize, buf: &mut [u8]) -> syscall::Result<usize> {
+            let mut handles = self.handles.borrow_mut();
+            let handle = handles.get_mut(&id).ok_or(syscall::Error::new(EBADF))?;
+            if handle.text.is_none() {
+                let value = self.ask(Command::Get { path: handle.file.path().to_string() }).map_err(syscall::Error::new)?;
+                handle.text = Some(render(handle.file, &value).into_bytes());
+            }
+            let text = handle.text.as_deref().unwrap_or_default();
+            let len = buf.len().min(text.len() - handle.pos);
+            buf[..len].copy_from_slice(&text[handle.pos..handle.pos + len]);
+            handle.pos += len;
+            Ok(len)
+        }
+
+        fn write(&self, id: usize, buf: &[u8]) -> syscall::Result<usize> {
+            let file = self.handles.borrow().get(&id).ok_or(syscall::Error::new(EBADF))?.file;
+            let value = std::str::from_utf8(buf).map_err(|_| syscall::Error::new(EINVAL))?;
+            // Refused here already, so a bad value gets its own errno
+            parse_write(file.path(), value).map_err(|e| {
+                syscall::Error::new(match e {
+                    FileError::ReadOnly(_) => EACCES,
+                    FileError::NotFound(_) => ENOENT,
+                    FileError::Invalid(_) | FileError::Failed(_) => EINVAL,
+                })
+            })?;
+            self.ask(Command::Set { path: file.path().to_string(), value: value.to_string() }).map_err(syscall::Error::new)?;
+            Ok(buf.len())
+        }
+
+        fn close(&self, id: usize) -> syscall::Result<usize> {
+            self.handles.borrow_mut().remove(&id).ok_or(syscall::Error::new(EBADF))?;
+            Ok(0)
+        }
+    }
+
+    /// Register `eva:` and serve it until the daemon exits
+    pub fn spawn(requests: mpsc::Sender<Pending>) -> std::io::Result<()> {
+        std::thread::Builder::new().name("eva-scheme".to_string()).spawn(move || {
+            if let Err(e) = serve(requests) {
+                eprintln!("[Scheme] eva: stopped: {}", e);
+            }
+        })?;
+        Ok(())
+    }
+
+    fn serve(requests: mpsc::Sender<Pending>) -> Result<(), String> {
+        use syscall::Scheme;
+
+        let scheme = EvaScheme { requests, handles: RefCell::new(HashMap::new()), next_id: Cell::new(0) };
+        let socket = syscall::open(":eva", syscall::O_CREAT | syscall::O_RDWR | syscall::O_CLOEXEC)
+            .map_err(|e| format!("cannot create the eva: scheme: {:?}", e))?;
+        loop {
+            let mut packet = syscall::Packet::default();
+            if syscall::read(socket, &mut packet).map_err(|e| format!("reading a scheme packet: {:?}", e))? == 0 {
+                return Ok(());
+            }
+            scheme.handle(&mut packet);
+            syscall::write(socket, &packet).map_err(|e| format!("writing a scheme packet: {:?}", e))?;
+        }
+    }
+}
+
+#[cfg(target_os = "redox")]
+pub use redox::spawn;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct FakeSettings {
+        sensitivity: f32,
+        language: String,
+        dsp: Vec<(String, bool)>,
+        context: Vec<(String, String)>,
+        applied: Vec<Change>,
+    }
+
+    impl Settings for FakeSettings {
+        fn wake_sensitivity(&self) -> f32 {
+            self.sensitivity
+        }
+
+        fn language(&self) -> String {
+            self.language.clone()
+        }
+
+        fn dsp(&self) -> Vec<(String, bool)> {
+            self.dsp.clone()
+        }
+
+        fn status(&self) -> Value {
+            json!({ "status": "Idle" })
+        }
+
+        fn context(&self) -> Vec<(String, String)> {
+            let mut context = self.context.clone();
+            context.sort();
+            context
+        }
+
+        fn apply(&mut self, change: Change) -> Result<(), String> {
+            match &change {
+                Change::WakeSensitivity(s) => self.sensitivity = *s,
+                Change::Language(tag) => self.language = tag.clone(),
+                Change::Dsp(stages) => {
+                    for (chain, stage, on) in stages {
+                        let key = format!("{}.{}", chain.key(), stage);
+                        match self.dsp.iter_mut().find(|(k, _)| *k == key) {
+                            Some(entry) => entry.1 = *on,
+                            None => return Err(format!("no {} stage in the {} chain", stage, chain.key())),
+                        }
+                    }
+                }
+                Change::Context(entries) => self.context.extend(entries.iter().cloned()),
+            }
+            self.applied.push(change);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(EvaFile::parse("eva:config/language"), Ok(EvaFile::Language));
+        assert_eq!(EvaFile::parse("/session/context"), Ok(EvaFile::SessionContext));
+        assert_eq!(EvaFile::parse("eva:"), Ok(EvaFile::Root));
+        assert!(EvaFile::parse("config/volume").is_err());
+        for file in EvaFile::ALL {
+            assert_eq!(EvaFile::parse(file.path()), Ok(file));
+        }
+    }
+
+    #[test]
+    fn test_bad_writes_change_nothing() {
+        let mut settings = FakeSettings { sensitivity: 0.6, language: "en-US".to_string(), ..FakeSettings::default() };
+
+        for bad in ["1.5", "-0.1", "loud", ""] {
+            let err = write(&mut settings, "config/wake_sensitivity", bad).unwrap_err();
+            assert!(matches!(err, FileError::Invalid(_)), "{:?}", err);
+        }
+        assert!(matches!(write(&mut settings, "config/language", "klingon"), Err(FileError::Invalid(_))));
+        assert!(matches!(write(&mut settings, "session/context", "no equals sign"), Err(FileError::Invalid(_))));
+        assert!(matches!(write(&mut settings, "session/context", "two words=x"), Err(FileError::Invalid(_))));
+        assert!(matches!(write(&mut settings, "session/context", "\n"), Err(FileError::Invalid(_))));
+        for bad in ["noise_gate=off", "mic.agc=off", "capture.echo=on", "capture.agc=maybe", ""] {
+            assert!(matches!(write(&mut settings, "config/dsp", bad), Err(FileError::Invalid(_))), "{}", bad);
+        }
+        assert_eq!(write(&mut settings, "status", "{}").unwrap_err().to_string(), "eva:status is read-only");
+        assert!(matches!(write(&mut settings, "config/volume", "1"), Err(FileError::NotFound(_))));
+        assert!(settings.applied.is_empty());
+    }
+
+    #[test]
+    fn test_writes_apply_and_read_back() {
+        let dsp = vec![("capture.noise_gate".to_string(), true), ("playback.limiter".to_string(), true)];
+        let mut settings = FakeSettings { dsp, ..FakeSettings::default() };
+        write(&mut settings, "eva:config/wake_sensitivity", "0,75\n").unwrap();
+        write(&mut settings, "config/language", "pt").unwrap();
+        write(&mut settings, "session/context", "room=kitchen\n\nmood = calm\n").unwrap();
+        write(&mut settings, "config/dsp", "capture.noise_gate = off\n").unwrap();
+        assert_eq!(write(&mut settings, "config/dsp", "playback.agc=off"), Err(FileError::Failed("no agc stage in the playback chain".to_string())));
+        assert_eq!(
+            settings.applied,
+            vec![
+                Change::WakeSensitivity(0.75),
+                Change::Language("pt-BR".to_string()),
+                Change::Context(vec![("room".to_string(), "kitchen".to_string()), ("mood".to_string(), "calm".to_string())]),
+                Change::Dsp(vec![(ChainKind::Capture, "noise_gate".to_string(), false)]),
+            ]
+        );
+
+        assert_eq!(read(&settings, "config/wake_sensitivity").unwrap(), json!(0.75));
+        assert_eq!(render(EvaFile::Language, &read(&settings, "config/language").unwrap()), "pt-BR\n");
+        let context = read(&settings, "session/context").unwrap();
+        assert_eq!(render(EvaFile::SessionContext, &context), "mood=calm\nroom=kitchen\n");
+        let dsp = read(&settings, "config/dsp").unwrap();
+        assert_eq!(render(EvaFile::Dsp, &dsp), "capture.noise_gate=off\nplayback.limiter=on\n");
+        assert_eq!(read(&settings, "status").unwrap()["status"], "Idle");
+        assert_eq!(
+            render(EvaFile::Root, &read(&settings, "").unwrap()),
+            "config/wake_sensitivity\nconfig/language\nconfig/dsp\nstatus\nsession/context\n"
+        );
+    }
+}
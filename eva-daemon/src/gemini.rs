@@ -1,105 +1,1045 @@
-use crate::websocket::WebSocketClient;
+use crate::session::{Role, Turn};
+use crate::stt::Language;
+use crate::user_profile::{ResponseLanguageMode, UserProfile, DEFAULT_NAME};
+use crate::websocket::{Connector, Transport, WsConnector};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-use std::fs::OpenOptions;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::watch;
 
-fn log_debug(msg: &str) {
-    if let Ok(mut file) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("eva_debug.log")
-    {
-        let timestamp = chrono::Local::now().format("%H:%M:%S%.3f");
-        writeln!(file, "[{}] {}", timestamp, msg).ok();
+/// Longest gap between two parts of a streamed reply
+const STREAM_IDLE_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(30);
+
+/// Persona used when the profile doesn't set its own
+pub const DEFAULT_SYSTEM_INSTRUCTION: &str = "You are EVA, a friendly voice assistant. Answer naturally and concisely.";
+
+/// Live models tried in order when the config lists none of its own;
+/// previews get retired, so the later ones keep EVA reachable
+pub const DEFAULT_MODELS: &[&str] = &[
+    "gemini-2.5-flash-native-audio-preview-12-2025",
+    "gemini-2.5-flash-native-audio-preview-09-2025",
+    "gemini-live-2.5-flash-preview",
+];
+
+/// How long to wait for a dropped socket to acknowledge the close
+const CLOSE_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(1);
+
+/// Set to `1` to include message text in the audit log (never audio)
+pub const DEBUG_CONTENT_ENV: &str = "EVA_DEBUG_CONTENT";
+
+/// Audit log size before it is rotated
+const AUDIT_MAX_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Rotated audit logs kept (`gemini_audit.log.1` is the newest)
+const AUDIT_KEEP: usize = 3;
+
+/// Which way an audited message went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// Structured record of the traffic with Gemini, one JSON object per line
+///
+/// Entries hold the message type, sizes and reply latency. Audio is only
+/// ever counted; text is counted too, and only written out when
+/// `EVA_DEBUG_CONTENT=1`. The file is rotated at `max_bytes`, keeping
+/// `keep` older files.
+pub struct AuditLog {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    content: bool,
+    enabled: AtomicBool,
+    state: Mutex<AuditState>,
+}
+
+#[derive(Default)]
+struct AuditState {
+    /// Opened on first write
+    file: Option<File>,
+    size: u64,
+    /// Latency of a received message is measured from here
+    last_sent: Option<std::time::Instant>,
+}
+
+/// The log every `GeminiClient` writes to; off under `cargo test`
+pub fn audit_log() -> &'static AuditLog {
+    static AUDIT: OnceLock<AuditLog> = OnceLock::new();
+    AUDIT.get_or_init(|| {
+        let path = crate::paths::data_file("gemini_audit.log").unwrap_or_else(|_| PathBuf::from("gemini_audit.log"));
+        let content = std::env::var(DEBUG_CONTENT_ENV).is_ok_and(|v| v == "1");
+        let log = AuditLog::new(path, AUDIT_MAX_BYTES, AUDIT_KEEP, content);
+        log.set_enabled(!cfg!(test));
+        log
+    })
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf, max_bytes: u64, keep: usize, content: bool) -> Self {
+        Self { path, max_bytes, keep, content, enabled: AtomicBool::new(true), state: Mutex::new(AuditState::default()) }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Flip logging on or off (the TUI's `d` key); returns the new state
+    pub fn toggle(&self) -> bool {
+        !self.enabled.fetch_xor(true, Ordering::Relaxed)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A raw protocol message, summarised
+    pub fn message(&self, direction: Direction, raw: &str) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut entry = summarize(raw, self.content);
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = std::time::Instant::now();
+        match direction {
+            Direction::Sent => {
+                entry["dir"] = json!("sent");
+                state.last_sent = Some(now);
+            }
+            Direction::Received => {
+                entry["dir"] = json!("received");
+                if let Some(sent) = state.last_sent {
+                    entry["latency_ms"] = json!(now.duration_since(sent).as_millis() as u64);
+                }
+            }
+        }
+        self.write(&mut state, entry);
+    }
+
+    /// A connection event ("connected", "reconnecting", ...)
+    pub fn event(&self, event: &str, detail: Option<String>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut entry = json!({ "event": event });
+        if let Some(detail) = detail {
+            entry["detail"] = json!(detail);
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        self.write(&mut state, entry);
+    }
+
+    fn write(&self, state: &mut AuditState, mut entry: Value) {
+        entry["ts"] = json!(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+        let line = format!("{}\n", entry);
+
+        if state.size > 0 && state.size + line.len() as u64 > self.max_bytes {
+            state.file = None;
+            self.rotate();
+            state.size = 0;
+        }
+        if state.file.is_none() {
+            let opened = OpenOptions::new().create(true).append(true).open(&self.path);
+            state.size = opened.as_ref().ok().and_then(|f| f.metadata().ok()).map_or(0, |m| m.len());
+            state.file = opened.ok();
+        }
+        if let Some(ref mut file) = state.file {
+            if file.write_all(line.as_bytes()).is_ok() {
+                state.size += line.len() as u64;
+            }
+        }
+    }
+
+    /// `log` → `log.1` → ... → `log.<keep>`, dropping the oldest
+    fn rotate(&self) {
+        let numbered = |n: usize| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{}", n));
+            PathBuf::from(name)
+        };
+        if self.keep == 0 {
+            let _ = fs::remove_file(&self.path);
+            return;
+        }
+        let _ = fs::remove_file(numbered(self.keep));
+        for n in (1..self.keep).rev() {
+            let _ = fs::rename(numbered(n), numbered(n + 1));
+        }
+        let _ = fs::rename(&self.path, numbered(1));
+    }
+}
+
+/// Type, sizes and (only if `content`) text of a message; the raw
+/// message itself is never copied
+fn summarize(raw: &str, content: bool) -> Value {
+    let mut entry = json!({ "bytes": raw.len() });
+    let Ok(message) = serde_json::from_str::<Value>(raw) else {
+        entry["type"] = json!("unparsed");
+        return entry;
+    };
+
+    const KNOWN: &[&str] = &[
+        "setup", "realtime_input", "client_content", "tool_response",
+        "setupComplete", "serverContent", "toolCall", "toolCallCancellation", "goAway", "error",
+    ];
+    let kind = KNOWN
+        .iter()
+        .find(|k| message.get(**k).is_some())
+        .map(|k| k.to_string())
+        .or_else(|| message.as_object().and_then(|o| o.keys().next().cloned()))
+        .unwrap_or_else(|| "unknown".to_string());
+    entry["type"] = json!(kind);
+
+    let mut audio_bytes = 0;
+    let mut texts = Vec::new();
+    collect_payloads(&message, &mut audio_bytes, &mut texts);
+    entry["audio_bytes"] = json!(audio_bytes);
+    entry["text_chars"] = json!(texts.iter().map(|t| t.chars().count()).sum::<usize>());
+    if content && !texts.is_empty() {
+        entry["text"] = json!(texts);
+    }
+    if let Some(done) = message.pointer("/serverContent/turnComplete") {
+        entry["turn_complete"] = done.clone();
+    }
+    entry
+}
+
+/// Decoded size of every base64 `data` blob, and every `text` field
+fn collect_payloads<'a>(value: &'a Value, audio_bytes: &mut usize, texts: &mut Vec<&'a str>) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map {
+                match (key.as_str(), field) {
+                    ("data", Value::String(data)) => *audio_bytes += data.len() / 4 * 3,
+                    ("text", Value::String(text)) => texts.push(text),
+                    _ => collect_payloads(field, audio_bytes, texts),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_payloads(item, audio_bytes, texts)),
+        _ => {}
+    }
+}
+
+/// Voice parameters sent in the setup's `speech_config`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeechSettings {
+    pub voice_name: String,
+    /// BCP-47 code, e.g. "pt-BR"
+    pub language_code: Option<String>,
+    /// 1.0 = natural speed
+    pub speaking_rate: f32,
+    /// Semitones relative to the voice's natural pitch
+    pub pitch: f32,
+}
+
+impl Default for SpeechSettings {
+    fn default() -> Self {
+        Self { voice_name: "Aoede".to_string(), language_code: None, speaking_rate: 1.0, pitch: 0.0 }
+    }
+}
+
+impl SpeechSettings {
+    /// Voice from the personality preset, delivery from the profile
+    pub fn from_profile(profile: &UserProfile) -> Self {
+        let preset = profile.personality;
+        Self {
+            voice_name: preset.voice_name().to_string(),
+            language_code: Some(profile.language.clone()),
+            speaking_rate: (profile.voice_speed * preset.rate_factor()).clamp(0.25, 4.0),
+            pitch: (profile.voice_pitch + preset.pitch_offset()).clamp(-20.0, 20.0),
+        }
+    }
+
+    /// Rate or pitch differ from the voice's defaults
+    pub fn has_prosody(&self) -> bool {
+        (self.speaking_rate - 1.0).abs() > f32::EPSILON || self.pitch.abs() > f32::EPSILON
+    }
+}
+
+/// Where speaking rate and pitch are applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProsodyMode {
+    /// Sent in `speech_config`, the server synthesizes at that rate/pitch
+    Server,
+    /// The server rejected them; playback is time-stretched locally
+    /// (rate only, pitch unchanged)
+    LocalStretch,
+}
+
+impl ProsodyMode {
+    /// Tell the user which mechanism is in effect
+    pub fn describe(&self, portuguese: bool) -> &'static str {
+        match (self, portuguese) {
+            (ProsodyMode::Server, false) => "Speaking rate and pitch are set by the voice service.",
+            (ProsodyMode::Server, true) => "A velocidade e o tom da voz são ajustados pelo serviço de voz.",
+            (ProsodyMode::LocalStretch, false) => {
+                "The voice service doesn't support rate or pitch, so I'm speeding up or slowing down playback locally. Pitch stays the same."
+            }
+            (ProsodyMode::LocalStretch, true) => {
+                "O serviço de voz não aceita velocidade nem tom, então estou ajustando a velocidade localmente. O tom continua o mesmo."
+            }
+        }
+    }
+}
+
+/// Setup error caused by the rate/pitch fields (older API versions reject
+/// unknown `speech_config` fields)
+fn is_prosody_rejection(error: &str) -> bool {
+    let error = error.to_lowercase();
+    ["speaking_rate", "speakingrate", "pitch"].iter().any(|field| error.contains(field))
+}
+
+/// Why the server refused a session at setup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetupFailure {
+    /// The API key is missing, invalid or not allowed to use the API:
+    /// another model won't help
+    Auth,
+    /// The model doesn't exist (any more) or can't do live sessions
+    Model,
+    Other,
+}
+
+/// A refused setup, from the server's `error` payload or the reason of the
+/// close frame it sends instead
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetupError {
+    /// HTTP-style code (`404`) or WebSocket close code
+    pub code: Option<i64>,
+    /// `NOT_FOUND`, `PERMISSION_DENIED`, ...
+    pub status: Option<String>,
+    pub message: String,
+}
+
+impl SetupError {
+    /// `{"code": 404, "status": "NOT_FOUND", "message": "..."}`; anything
+    /// else is kept whole as the message
+    pub fn from_payload(error: &Value) -> Self {
+        match error.get("message").and_then(Value::as_str) {
+            Some(message) => Self {
+                code: error.get("code").and_then(Value::as_i64),
+                status: error.get("status").and_then(Value::as_str).map(str::to_string),
+                message: message.to_string(),
+            },
+            None => Self { code: None, status: None, message: error.to_string() },
+        }
+    }
+
+    pub fn from_close(code: u16, reason: &str) -> Self {
+        Self { code: Some(code as i64), status: None, message: reason.to_string() }
+    }
+
+    pub fn failure(&self) -> SetupFailure {
+        let message = self.message.to_lowercase();
+        let status = self.status.as_deref().unwrap_or("");
+        if matches!(self.code, Some(401 | 403))
+            || matches!(status, "UNAUTHENTICATED" | "PERMISSION_DENIED")
+            || message.contains("api key")
+        {
+            SetupFailure::Auth
+        } else if self.code == Some(404)
+            || status == "NOT_FOUND"
+            || (message.contains("model") && (message.contains("not found") || message.contains("not supported")))
+        {
+            SetupFailure::Model
+        } else {
+            SetupFailure::Other
+        }
+    }
+}
+
+impl std::fmt::Display for SetupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.status {
+            Some(ref status) => write!(f, "Gemini error ({}): {}", status, self.message),
+            None => write!(f, "Gemini error: {}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for SetupError {}
+
+/// `model` is a priority list; configs from before it was one name a
+/// single model
+fn one_or_more_models<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Models {
+        One(String),
+        Several(Vec<String>),
+    }
+    Ok(match Models::deserialize(deserializer)? {
+        Models::One(model) => vec![model],
+        Models::Several(models) => models,
+    })
+}
+
+/// Retry schedule after the connection drops
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    pub max_attempts: u32,
+    /// Wait before the first attempt; doubles on each one after
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 5, initial_backoff_ms: 500, max_backoff_ms: 8000 }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Wait before attempt `attempt` (1-based)
+    pub fn delay(&self, attempt: u32) -> tokio::time::Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(20);
+        tokio::time::Duration::from_millis(self.initial_backoff_ms.saturating_mul(factor).min(self.max_backoff_ms))
+    }
+}
+
+/// Share of a quota past which the status indicator warns
+const QUOTA_WARN_AT: f64 = 0.9;
+
+/// Shortest gap between two writes of the quota counters (also written
+/// on close)
+const QUOTA_SAVE_INTERVAL: u64 = 10;
+
+const MINUTE: u64 = 60;
+const DAY: u64 = 24 * 60 * 60;
+
+/// Soft limits on Gemini API use, enforced locally so a turn is refused
+/// with a clear message before the API rejects it mid-conversation
+///
+/// Minutes and days are fixed UTC windows. `None` is no limit.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuotaLimits {
+    /// Typed turns (`send_text`); audio is metered in seconds instead
+    pub requests_per_minute: Option<u64>,
+    pub requests_per_day: Option<u64>,
+    /// Microphone audio sent with `send_audio`
+    pub audio_seconds_per_minute: Option<u64>,
+    pub audio_seconds_per_day: Option<u64>,
+    /// Response tokens, from the server's `usageMetadata`
+    pub tokens_per_minute: Option<u64>,
+    pub tokens_per_day: Option<u64>,
+}
+
+/// What was sent or received in one window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub requests: u64,
+    pub audio_ms: u64,
+    pub tokens: u64,
+}
+
+/// How close the counters are to the limits
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuotaState {
+    Ok,
+    /// Within 10% of a limit; holds the warning
+    Near(String),
+    /// A limit is used up; holds the error
+    Exceeded(String),
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct QuotaWindow {
+    /// Unix time the window opened
+    start: u64,
+    usage: QuotaUsage,
+}
+
+impl QuotaWindow {
+    /// Start from zero if `now` is past this window of `length` seconds
+    fn roll(&mut self, now: u64, length: u64) {
+        let start = now - now % length;
+        if self.start != start {
+            *self = QuotaWindow { start, usage: QuotaUsage::default() };
+        }
+    }
+
+    fn add(&mut self, usage: QuotaUsage) {
+        self.usage.requests += usage.requests;
+        self.usage.audio_ms += usage.audio_ms;
+        self.usage.tokens += usage.tokens;
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct QuotaCounters {
+    minute: QuotaWindow,
+    day: QuotaWindow,
+}
+
+/// Gemini API use per minute and per day, kept across restarts
+///
+/// Times are Unix seconds, passed in so windows can be tested.
+pub struct QuotaTracker {
+    path: Option<PathBuf>,
+    counters: QuotaCounters,
+    saved_at: u64,
+}
+
+/// The tracker every `GeminiClient` counts against (they share one API
+/// key); kept in memory only under `cargo test`
+pub fn quota_tracker() -> &'static Mutex<QuotaTracker> {
+    static QUOTA: OnceLock<Mutex<QuotaTracker>> = OnceLock::new();
+    QUOTA.get_or_init(|| {
+        let path = if cfg!(test) { None } else { crate::paths::data_file("gemini_quota.json").ok() };
+        Mutex::new(QuotaTracker::load(path))
+    })
+}
+
+/// Now, in the tracker's Unix seconds
+pub fn quota_now() -> u64 {
+    chrono::Utc::now().timestamp().max(0) as u64
+}
+
+impl QuotaTracker {
+    /// Counters saved at `path`, or zero if there are none (or they can't
+    /// be read); `None` keeps them in memory
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let counters = path
+            .as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        Self { path, counters, saved_at: 0 }
+    }
+
+    fn roll(&mut self, now: u64) {
+        self.counters.minute.roll(now, MINUTE);
+        self.counters.day.roll(now, DAY);
+    }
+
+    /// Count `usage` at `now`; written out at most every `QUOTA_SAVE_INTERVAL`
+    pub fn record(&mut self, usage: QuotaUsage, now: u64) {
+        self.roll(now);
+        self.counters.minute.add(usage);
+        self.counters.day.add(usage);
+        if now.saturating_sub(self.saved_at) >= QUOTA_SAVE_INTERVAL {
+            if let Err(e) = self.save(now) {
+                audit_log().event("quota_save_failed", Some(e.to_string()));
+            }
+        }
+    }
+
+    /// Use in the current minute and day
+    pub fn usage(&mut self, now: u64) -> (QuotaUsage, QuotaUsage) {
+        self.roll(now);
+        (self.counters.minute.usage, self.counters.day.usage)
+    }
+
+    /// Compare the counters at `now` with `limits`; the first limit used up
+    /// wins over any warning
+    pub fn check(&mut self, limits: &QuotaLimits, now: u64) -> QuotaState {
+        let (minute, day) = self.usage(now);
+        // (what, window, used, limit, unit of `used` per unit of `limit`)
+        let meters = [
+            ("requests", MINUTE, minute.requests, limits.requests_per_minute, 1),
+            ("requests", DAY, day.requests, limits.requests_per_day, 1),
+            ("audio seconds", MINUTE, minute.audio_ms, limits.audio_seconds_per_minute, 1000),
+            ("audio seconds", DAY, day.audio_ms, limits.audio_seconds_per_day, 1000),
+            ("response tokens", MINUTE, minute.tokens, limits.tokens_per_minute, 1),
+            ("response tokens", DAY, day.tokens, limits.tokens_per_day, 1),
+        ];
+
+        let mut near = None;
+        for (what, length, used, limit, unit) in meters {
+            let Some(limit) = limit else { continue };
+            let per = if length == MINUTE { "minute" } else { "day" };
+            if used >= limit.saturating_mul(unit) {
+                let minutes = (now - now % length + length - now).div_ceil(60);
+                return QuotaState::Exceeded(format!(
+                    "Gemini quota exceeded ({} of {} {} per {} used), try again in {} minute{}",
+                    used / unit,
+                    limit,
+                    what,
+                    per,
+                    minutes,
+                    if minutes == 1 { "" } else { "s" }
+                ));
+            }
+            if near.is_none() && used as f64 >= (limit * unit) as f64 * QUOTA_WARN_AT {
+                near = Some(format!("Gemini quota almost used up: {} of {} {} per {}", used / unit, limit, what, per));
+            }
+        }
+        near.map_or(QuotaState::Ok, QuotaState::Near)
+    }
+
+    /// Write the counters now
+    pub fn save(&mut self, now: u64) -> std::io::Result<()> {
+        self.saved_at = now;
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string(&self.counters).map_err(std::io::Error::other)?;
+        fs::write(path, json)
     }
 }
 
+/// Session health, for the status indicator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connected,
+    /// The socket dropped; `attempt` of `ReconnectPolicy::max_attempts`
+    Reconnecting { attempt: u32 },
+    /// Every reconnection attempt failed
+    Disconnected,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GeminiConfig {
     pub api_key: String,
-    pub model: String,
+    /// Models to try, most preferred first; the next one is used when the
+    /// server doesn't know (or no longer serves) a model
+    #[serde(deserialize_with = "one_or_more_models")]
+    pub model: Vec<String>,
     pub ws_url: String,
+    /// Condensed capability list appended to the system instruction
+    #[serde(default)]
+    pub capabilities: Option<String>,
+    /// Voice, language and prosody for replies
+    #[serde(default)]
+    pub speech: SpeechSettings,
+    #[serde(default)]
+    pub reconnect: ReconnectPolicy,
+    #[serde(default = "default_system_instruction")]
+    pub system_instruction: String,
+    /// What to call the user; told to the model after the persona
+    #[serde(default)]
+    pub user_name: Option<String>,
+    #[serde(default = "default_temperature")]
+    pub temperature: f32,
+    /// BCP-47 code of the language EVA answers in unless a turn asks otherwise
+    #[serde(default = "default_response_language")]
+    pub response_language: String,
+    /// Declare EVA's commands as tools (see `tools`); when off, commands
+    /// only come from the local parser
+    #[serde(default = "default_tools")]
+    pub tools: bool,
+    /// Most recent session turns replayed when a session is (re)opened
+    #[serde(default = "default_context_turns")]
+    pub context_turns: usize,
+    /// Leave command output out of the replayed turns
+    #[serde(default)]
+    pub skip_command_results: bool,
+    /// Ask for written replies instead of speech (used by `summary`)
+    #[serde(default)]
+    pub text_replies: bool,
+    #[serde(default)]
+    pub quota: QuotaLimits,
+}
+
+fn default_system_instruction() -> String {
+    DEFAULT_SYSTEM_INSTRUCTION.to_string()
+}
+
+fn default_temperature() -> f32 {
+    0.6
+}
+
+fn default_response_language() -> String {
+    "pt-BR".to_string()
+}
+
+fn default_tools() -> bool {
+    true
+}
+
+fn default_context_turns() -> usize {
+    10
 }
 
 impl Default for GeminiConfig {
     fn default() -> Self {
         Self {
             api_key: std::env::var("GOOGLE_API_KEY").unwrap_or_default(),
-            model: "gemini-2.5-flash-native-audio-preview-12-2025".to_string(),
+            model: DEFAULT_MODELS.iter().map(|m| m.to_string()).collect(),
             ws_url: "wss://generativelanguage.googleapis.com/ws/google.ai.generativelanguage.v1beta.GenerativeService.BidiGenerateContent".to_string(),
+            capabilities: None,
+            speech: SpeechSettings::default(),
+            reconnect: ReconnectPolicy::default(),
+            system_instruction: default_system_instruction(),
+            user_name: None,
+            temperature: default_temperature(),
+            response_language: default_response_language(),
+            tools: default_tools(),
+            context_turns: default_context_turns(),
+            skip_command_results: false,
+            text_replies: false,
+            quota: QuotaLimits::default(),
+        }
+    }
+}
+
+impl GeminiConfig {
+    /// Persona, voice, reply language and models from the user's profile
+    pub fn from_profile(profile: &UserProfile) -> Self {
+        let response_language = match profile.response_language {
+            ResponseLanguageMode::Always(ref tag) => tag.clone(),
+            ResponseLanguageMode::Mirror => profile.language.clone(),
+        };
+        let mut config = Self {
+            speech: SpeechSettings::from_profile(profile),
+            system_instruction: profile.system_instruction.clone().unwrap_or_else(default_system_instruction),
+            user_name: (profile.name != DEFAULT_NAME && !profile.name.is_empty()).then(|| profile.name.clone()),
+            response_language,
+            quota: profile.gemini_quota.clone(),
+            ..Self::default()
+        };
+        // "gemini_models": comma-separated, most preferred first
+        if let Some(models) = profile.get_preference("gemini_models") {
+            let models: Vec<String> = models.split(',').map(str::trim).filter(|m| !m.is_empty()).map(str::to_string).collect();
+            if !models.is_empty() {
+                config.model = models;
+            }
+        }
+        config
+    }
+
+    /// The `setup` message for the first model
+    pub fn setup_message(&self, prosody: ProsodyMode) -> Value {
+        self.setup_for(self.model.first().map_or("", String::as_str), prosody)
+    }
+
+    /// The `setup` message for `model`; rate/pitch are only included in
+    /// server mode
+    pub fn setup_for(&self, model: &str, prosody: ProsodyMode) -> Value {
+        let mut instruction = self.system_instruction.clone();
+        if let Some(ref name) = self.user_name {
+            instruction.push_str(&format!("\n\nThe user's name is {}.", name));
+        }
+        if !self.response_language.is_empty() {
+            let name = Language::from_tag(&self.response_language).map_or(self.response_language.as_str(), |l| l.name());
+            instruction.push_str(&format!(
+                "\n\nAnswer in {} ({}) unless a turn asks for another language.",
+                name, self.response_language
+            ));
+        }
+        if let Some(ref capabilities) = self.capabilities {
+            instruction.push_str("\n\n");
+            instruction.push_str(capabilities);
+        }
+
+        let mut speech_config = json!({
+            "voice_config": {
+                "prebuilt_voice_config": {
+                    "voice_name": self.speech.voice_name
+                }
+            }
+        });
+        if let Some(ref code) = self.speech.language_code {
+            speech_config["language_code"] = json!(code);
+        }
+        if prosody == ProsodyMode::Server && self.speech.has_prosody() {
+            speech_config["speaking_rate"] = json!(self.speech.speaking_rate);
+            speech_config["pitch"] = json!(self.speech.pitch);
+        }
+
+        let generation_config = if self.text_replies {
+            json!({ "response_modalities": ["TEXT"], "temperature": self.temperature })
+        } else {
+            json!({ "response_modalities": ["AUDIO"], "speech_config": speech_config, "temperature": self.temperature })
+        };
+
+        let mut setup = json!({
+            "setup": {
+                "model": format!("models/{}", model),
+                "generation_config": generation_config,
+                "system_instruction": {
+                    "parts": [{
+                        "text": instruction
+                    }]
+                }
+            }
+        });
+        if self.tools {
+            setup["setup"]["tools"] = crate::tools::declarations();
         }
+        setup
     }
 }
 
 pub struct GeminiClient {
-    ws: WebSocketClient,
+    ws: Box<dyn Transport>,
+    connector: Box<dyn Connector>,
     config: GeminiConfig,
+    /// The model of `config.model` the server accepted
+    active_model: String,
     setup_complete: bool,
+    prosody: ProsodyMode,
+    /// Parts of an abandoned turn are still in flight; drop them
+    discarding: bool,
+    state: watch::Sender<ConnectionState>,
+    /// Conversation so far, replayed after a reconnect
+    resume_context: Vec<Turn>,
+    /// Response tokens the server has reported for this session; its
+    /// `usageMetadata` is a running total
+    reported_tokens: u64,
 }
 
 impl GeminiClient {
     /// Connect to Gemini API via WebSocket
     pub async fn connect(config: GeminiConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::connect_with(config, Box::new(WsConnector)).await
+    }
+
+    /// Connect through `connector` (tests use a scripted one)
+    ///
+    /// The models of `config.model` are tried in order: one the server
+    /// doesn't know or can't stream with moves on to the next, while a
+    /// rejected API key fails at once.
+    pub async fn connect_with(config: GeminiConfig, connector: Box<dyn Connector>) -> Result<Self, Box<dyn std::error::Error>> {
         if config.api_key.is_empty() {
             return Err("GOOGLE_API_KEY não configurada".into());
         }
+        let Some(first) = config.model.first().cloned() else {
+            return Err("No Gemini model configured".into());
+        };
 
-        let url = format!("{}?key={}", config.ws_url, config.api_key);
+        audit_log().event("connecting", None);
+        let ws = connector.connect(&Self::url(&config)).await?;
+        audit_log().event("connected", None);
 
-        log_debug("🤖 Conectando ao Gemini...");
-        let ws = WebSocketClient::connect(&url).await?;
-        log_debug("✅ WebSocket conectado");
+        let mut client = Self {
+            ws,
+            connector,
+            config,
+            active_model: first,
+            setup_complete: false,
+            prosody: ProsodyMode::Server,
+            discarding: false,
+            state: watch::Sender::new(ConnectionState::Connected),
+            resume_context: Vec::new(),
+            reported_tokens: 0,
+        };
 
-        let mut client = Self { ws, config, setup_complete: false };
+        // Send setup and wait for setupComplete (CRITICAL!)
+        let mut refused = Vec::new();
+        for (i, model) in client.config.model.clone().into_iter().enumerate() {
+            client.active_model = model.clone();
+            // The server closes the socket after refusing a setup
+            let opened = if i == 0 {
+                match client.send_setup().await {
+                    Ok(()) => client.wait_for_setup_complete().await,
+                    Err(e) => Err(e),
+                }
+            } else {
+                client.reopen().await
+            };
+            let opened = match opened {
+                Err(e) if client.config.speech.has_prosody() && is_prosody_rejection(&e.to_string()) => {
+                    audit_log().event("prosody_unsupported", None);
+                    client.prosody = ProsodyMode::LocalStretch;
+                    client.reopen().await
+                }
+                other => other,
+            };
+            match opened {
+                Ok(()) => {
+                    if !refused.is_empty() {
+                        audit_log().event("model_fallback", Some(model));
+                    }
+                    return Ok(client);
+                }
+                Err(e) => match e.downcast_ref::<SetupError>() {
+                    Some(setup) if setup.failure() == SetupFailure::Model => {
+                        audit_log().event("model_unavailable", Some(format!("{}: {}", model, setup.message)));
+                        refused.push(format!("{} ({})", model, setup.message));
+                    }
+                    _ => return Err(e),
+                },
+            }
+        }
 
-        // Send setup
-        client.send_setup().await?;
+        Err(format!("None of the configured Gemini models is available: {}", refused.join("; ")).into())
+    }
 
-        // Wait for setupComplete (CRITICAL!)
-        client.wait_for_setup_complete().await?;
+    /// The model this session runs on (the first of `GeminiConfig::model`
+    /// the server accepted)
+    pub fn active_model(&self) -> &str {
+        &self.active_model
+    }
 
-        Ok(client)
+    fn url(config: &GeminiConfig) -> String {
+        format!("{}?key={}", config.ws_url, config.api_key)
     }
 
-    /// Send setup message
-    async fn send_setup(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let model_full = format!("models/{}", self.config.model);
+    /// Where rate and pitch are currently applied
+    pub fn prosody_mode(&self) -> ProsodyMode {
+        self.prosody
+    }
 
-        let setup = json!({
-            "setup": {
-                "model": model_full,
-                "generation_config": {
-                    "response_modalities": ["AUDIO"],
-                    "speech_config": {
-                        "voice_config": {
-                            "prebuilt_voice_config": {
-                                "voice_name": "Aoede"
-                            }
-                        }
-                    },
-                    "temperature": 0.6
-                },
-                "system_instruction": {
-                    "parts": [{
-                        "text": "Você é EVA, uma assistente de voz amigável. Responda em português brasileiro de forma natural e concisa."
-                    }]
+    /// Messages waiting to be written to the socket
+    pub fn queue_depth(&self) -> usize {
+        self.ws.queue_depth()
+    }
+
+    pub fn connection_state(&self) -> ConnectionState {
+        *self.state.borrow()
+    }
+
+    /// End the session cleanly: queued messages are written and the close
+    /// handshake sent, waiting at most `CLOSE_TIMEOUT` for a dead socket
+    pub async fn close(self) -> Result<(), Box<dyn std::error::Error>> {
+        audit_log().event("closing", None);
+        let _ = quota_tracker().lock().unwrap_or_else(|e| e.into_inner()).save(quota_now());
+        self.state.send_replace(ConnectionState::Disconnected);
+        match tokio::time::timeout(CLOSE_TIMEOUT, self.ws.close()).await {
+            Ok(closed) => closed,
+            Err(_) => Err("Gemini didn't answer the close".into()),
+        }
+    }
+
+    /// Follow connection state changes while a call is in progress
+    pub fn watch_state(&self) -> watch::Receiver<ConnectionState> {
+        self.state.subscribe()
+    }
+
+    /// Conversation to replay if the session has to be re-established
+    /// (`ConversationSession::turns()`)
+    pub fn set_resume_context(&mut self, turns: Vec<Turn>) {
+        self.resume_context = turns;
+    }
+
+    /// Change voice settings mid-session ("speak slower")
+    ///
+    /// The Live API only reads `speech_config` at setup, so the session is
+    /// re-established and the conversation replayed. Server-side prosody is
+    /// tried again each time; if the API rejects it, rate and pitch are left
+    /// to local time-stretch.
+    pub async fn update_speech(&mut self, speech: SpeechSettings) -> Result<ProsodyMode, Box<dyn std::error::Error>> {
+        self.config.speech = speech;
+        self.prosody = ProsodyMode::Server;
+        if let Err(e) = self.reopen_with_context().await {
+            if !(self.config.speech.has_prosody() && is_prosody_rejection(&e.to_string())) {
+                return Err(e);
+            }
+            audit_log().event("prosody_unsupported", None);
+            self.prosody = ProsodyMode::LocalStretch;
+            self.reopen_with_context().await?;
+        }
+        Ok(self.prosody)
+    }
+
+    /// Pick up a changed profile (name, language, voice, persona, quotas)
+    ///
+    /// Capabilities, tools and connection settings stay as they are; the
+    /// session is re-established through `update_speech`, so a rate or pitch
+    /// the API won't take falls back to local time-stretch.
+    pub async fn apply_profile(&mut self, profile: &UserProfile) -> Result<ProsodyMode, Box<dyn std::error::Error>> {
+        let fresh = GeminiConfig::from_profile(profile);
+        self.config.quota = fresh.quota;
+        self.config.system_instruction = fresh.system_instruction;
+        self.config.user_name = fresh.user_name;
+        self.config.response_language = fresh.response_language;
+        self.update_speech(fresh.speech).await
+    }
+
+    /// `reopen`, then replay the conversation so far
+    async fn reopen_with_context(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.reopen().await?;
+        let context = std::mem::take(&mut self.resume_context);
+        let sent = self.send_context(&context).await;
+        self.resume_context = context;
+        sent
+    }
+
+    /// Replace the WebSocket and run setup again
+    async fn reopen(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let new_ws = self.connector.connect(&Self::url(&self.config)).await?;
+        let old_ws = std::mem::replace(&mut self.ws, new_ws);
+        // A dead socket may never answer the close
+        let _ = tokio::time::timeout(CLOSE_TIMEOUT, old_ws.close()).await;
+        self.setup_complete = false;
+        self.discarding = false;
+        self.reported_tokens = 0;
+
+        self.send_setup().await?;
+        self.wait_for_setup_complete().await
+    }
+
+    /// Re-establish a dropped session, backing off between attempts, and
+    /// replay the conversation so the model keeps the thread
+    async fn reconnect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let policy = self.config.reconnect.clone();
+        let mut last_error = "no attempts allowed".to_string();
+
+        for attempt in 1..=policy.max_attempts {
+            self.state.send_replace(ConnectionState::Reconnecting { attempt });
+            tokio::time::sleep(policy.delay(attempt)).await;
+            audit_log().event("reconnecting", Some(format!("attempt {}/{}", attempt, policy.max_attempts)));
+
+            let resumed = match self.reopen().await {
+                Ok(()) => {
+                    let context = std::mem::take(&mut self.resume_context);
+                    let sent = self.send_context(&context).await;
+                    self.resume_context = context;
+                    sent
+                }
+                Err(e) => Err(e),
+            };
+            match resumed {
+                Ok(()) => {
+                    audit_log().event("reconnected", None);
+                    self.state.send_replace(ConnectionState::Connected);
+                    return Ok(());
+                }
+                Err(e) => {
+                    audit_log().event("reconnect_failed", Some(e.to_string()));
+                    last_error = e.to_string();
                 }
             }
-        });
+        }
 
-        log_debug(&format!("📤 Setup: {}", setup.to_string()));
-        self.ws.send_text(&setup.to_string()).await?;
-        log_debug("✅ Setup enviado");
+        self.state.send_replace(ConnectionState::Disconnected);
+        Err(format!("Reconnection failed after {} attempts: {}", policy.max_attempts, last_error).into())
+    }
 
+    /// Send a message, reconnecting once if the socket turns out to be dead
+    ///
+    /// Sends are queued, so a write that fails after queuing shows up as
+    /// the next send or receive failing.
+    async fn send_message(&mut self, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if let Err(e) = self.send_raw(message).await {
+            audit_log().event("send_failed", Some(e.to_string()));
+            self.reconnect().await?;
+            self.send_raw(message).await?;
+        }
+        Ok(())
+    }
+
+    /// Every message to the server goes through here, to be audited
+    async fn send_raw(&mut self, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        audit_log().message(Direction::Sent, message);
+        self.ws.send_text(message).await
+    }
+
+    /// Send setup message
+    async fn send_setup(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let setup = self.config.setup_for(&self.active_model, self.prosody);
+
+        self.send_raw(&setup.to_string()).await?;
         Ok(())
     }
 
     /// Wait for setupComplete from Gemini (with timeout)
     async fn wait_for_setup_complete(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        log_debug("⏳ Aguardando setupComplete...");
-
         let timeout = tokio::time::Duration::from_secs(10);
         let start = tokio::time::Instant::now();
 
@@ -115,33 +1055,37 @@ impl GeminiClient {
             ).await;
 
             match receive_timeout {
-                Ok(Ok(Some(msg))) => {
-                    if let tokio_tungstenite::tungstenite::Message::Text(text) = msg {
-                        log_debug(&format!("📥 Setup resp: {}", &text[..text.len().min(200)]));
+                Ok(Ok(Some(tokio_tungstenite::tungstenite::Message::Text(text)))) => {
+                    audit_log().message(Direction::Received, &text);
 
-                        let json: Value = serde_json::from_str(&text)?;
+                    let json: Value = serde_json::from_str(&text)?;
 
-                        // Check for setupComplete
-                        if json.get("setupComplete").is_some() {
-                            log_debug("✅ setupComplete recebido - Pronto!");
-                            self.setup_complete = true;
-                            return Ok(());
-                        }
+                    // Check for setupComplete
+                    if json.get("setupComplete").is_some() {
+                        self.setup_complete = true;
+                        return Ok(());
+                    }
 
-                        // Check for error
-                        if let Some(error) = json.get("error") {
-                            let err_msg = format!("Gemini error: {:?}", error);
-                            log_debug(&format!("❌ {}", err_msg));
-                            return Err(err_msg.into());
-                        }
+                    // Check for error
+                    if let Some(error) = json.get("error") {
+                        let error = SetupError::from_payload(error);
+                        audit_log().event("error", Some(error.to_string()));
+                        return Err(error.into());
                     }
                 }
+                // The Live API usually refuses a setup by closing with a reason
+                Ok(Ok(Some(tokio_tungstenite::tungstenite::Message::Close(Some(frame))))) => {
+                    let error = SetupError::from_close(frame.code.into(), &frame.reason);
+                    audit_log().event("error", Some(error.to_string()));
+                    return Err(error.into());
+                }
+                Ok(Ok(Some(_))) => {}
                 Ok(Ok(None)) => {
                     // No message, continue waiting
                     tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
                 }
                 Ok(Err(e)) => {
-                    log_debug(&format!("❌ Erro ao receber: {}", e));
+                    audit_log().event("receive_failed", Some(e.to_string()));
                     return Err(e);
                 }
                 Err(_) => {
@@ -152,10 +1096,50 @@ impl GeminiClient {
         }
     }
 
+    /// Refuse to send once a quota is used up, rather than have the API
+    /// reject the session
+    fn check_quota(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let state = quota_tracker().lock().unwrap_or_else(|e| e.into_inner()).check(&self.config.quota, quota_now());
+        if let QuotaState::Exceeded(error) = state {
+            audit_log().event("quota_exceeded", Some(error.clone()));
+            return Err(error.into());
+        }
+        Ok(())
+    }
+
+    fn record_usage(&self, usage: QuotaUsage) {
+        quota_tracker().lock().unwrap_or_else(|e| e.into_inner()).record(usage, quota_now());
+    }
+
+    /// A warning for the status indicator when a quota is nearly (or
+    /// completely) used up
+    pub fn quota_warning(&self) -> Option<String> {
+        match quota_tracker().lock().unwrap_or_else(|e| e.into_inner()).check(&self.config.quota, quota_now()) {
+            QuotaState::Ok => None,
+            QuotaState::Near(warning) | QuotaState::Exceeded(warning) => Some(warning),
+        }
+    }
+
+    /// Count the response tokens a received message reports
+    fn count_tokens(&mut self, text: &str) {
+        if !text.contains("usageMetadata") {
+            return;
+        }
+        let Some(usage) = serde_json::from_str::<Value>(text)
+            .ok()
+            .and_then(|json| serde_json::from_value::<UsageMetadata>(json.get("usageMetadata")?.clone()).ok())
+        else {
+            return;
+        };
+        // A smaller total means the server started counting afresh
+        let tokens = usage.response_token_count.checked_sub(self.reported_tokens).unwrap_or(usage.response_token_count);
+        self.reported_tokens = usage.response_token_count;
+        self.record_usage(QuotaUsage { tokens, ..QuotaUsage::default() });
+    }
+
     /// Send audio data (PCM 16kHz)
     pub async fn send_audio(&mut self, pcm_data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
-        log_debug(&format!("🎤 Enviando áudio: {} bytes", pcm_data.len()));
-
+        self.check_quota()?;
         let base64_audio = BASE64.encode(pcm_data);
 
         // ✅ FIX: Usar mime_type com rate como EVA-Mind
@@ -168,15 +1152,15 @@ impl GeminiClient {
             }
         });
 
-        self.ws.send_text(&message.to_string()).await?;
-        log_debug("✅ Áudio enviado");
+        self.send_message(&message.to_string()).await?;
+        // 16-bit mono at 16 kHz: 32 bytes a millisecond
+        self.record_usage(QuotaUsage { audio_ms: pcm_data.len() as u64 / 32, ..QuotaUsage::default() });
         Ok(())
     }
 
     /// Send text message
     pub async fn send_text(&mut self, text: &str) -> Result<(), Box<dyn std::error::Error>> {
-        log_debug(&format!("📤 Enviando texto: {}", text));
-
+        self.check_quota()?;
         let message = json!({
             "client_content": {
                 "turn_complete": true,
@@ -189,13 +1173,52 @@ impl GeminiClient {
             }
         });
 
-        self.ws.send_text(&message.to_string()).await?;
-        log_debug("✅ Texto enviado");
+        self.send_message(&message.to_string()).await?;
+        self.record_usage(QuotaUsage { requests: 1, ..QuotaUsage::default() });
+        Ok(())
+    }
+
+    /// Answer a tool call so the model can carry on and narrate the result
+    pub async fn send_tool_response(&mut self, call: &FunctionCall, result: &Result<String, String>) -> Result<(), Box<dyn std::error::Error>> {
+        self.send_message(&tool_response_message(call, result).to_string()).await
+    }
+
+    /// Add a per-turn directive (e.g. reply language) to the context
+    ///
+    /// Sent without `turn_complete` so it applies to the user turn that
+    /// follows instead of triggering a reply of its own.
+    pub async fn send_directive(&mut self, directive: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let message = json!({
+            "client_content": {
+                "turn_complete": false,
+                "turns": [{
+                    "role": "user",
+                    "parts": [{
+                        "text": directive
+                    }]
+                }]
+            }
+        });
+
+        self.send_message(&message.to_string()).await?;
         Ok(())
     }
 
     /// Receive a single message from WebSocket (non-blocking with short timeout)
+    ///
+    /// A dropped connection is re-established, but whatever reply was in
+    /// flight is gone, so that is still reported as an error.
     async fn receive_message(&mut self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let lost = match self.receive_raw().await {
+            Err(e) => e,
+            result => return result,
+        };
+        audit_log().event("connection_lost", Some(lost.to_string()));
+        self.reconnect().await?;
+        Err(format!("Connection lost mid-reply ({}); reconnected", lost).into())
+    }
+
+    async fn receive_raw(&mut self) -> Result<Option<String>, Box<dyn std::error::Error>> {
         let receive_result = tokio::time::timeout(
             tokio::time::Duration::from_millis(100),
             self.ws.receive()
@@ -204,6 +1227,8 @@ impl GeminiClient {
         match receive_result {
             Ok(Ok(Some(msg))) => {
                 if let tokio_tungstenite::tungstenite::Message::Text(text) = msg {
+                    audit_log().message(Direction::Received, &text);
+                    self.count_tokens(&text);
                     return Ok(Some(text));
                 }
                 Ok(None)
@@ -217,78 +1242,65 @@ impl GeminiClient {
     /// Try to receive one response, returns immediately if no message available
     pub async fn try_receive(&mut self) -> Result<Option<GeminiResponse>, Box<dyn std::error::Error>> {
         if let Some(text) = self.receive_message().await? {
-            let preview = &text[..text.len().min(200)];
-            log_debug(&format!("📥 Msg: {}", preview));
-
             // Parse JSON
             let json: Value = serde_json::from_str(&text)?;
 
             // Check for error
             if let Some(error) = json.get("error") {
                 let err_msg = format!("Gemini error: {:?}", error);
-                log_debug(&format!("❌ {}", err_msg));
+                audit_log().event("error", Some(err_msg.clone()));
                 return Err(err_msg.into());
             }
 
-            // Check for serverContent with modelTurn (audio response)
-            if json.get("serverContent").is_some() {
-                match serde_json::from_str::<GeminiResponse>(&text) {
+            // Content, turn events and token usage (tool calls go through
+            // `receive_stream`)
+            if json.get("serverContent").is_some() || json.get("usageMetadata").is_some() {
+                match serde_json::from_value::<GeminiResponse>(json) {
                     Ok(response) => {
-                        if let Some(ref content) = response.server_content {
-                            if let Some(ref turn) = content.model_turn {
-                                for part in &turn.parts {
-                                    if let Some(ref txt) = part.text {
-                                        log_debug(&format!("💬 Texto: {}", txt));
-                                    }
-                                    if let Some(ref data) = part.inline_data {
-                                        log_debug(&format!("🔊 Áudio: {} ({} bytes)",
-                                            data.mime_type, data.data.len()));
-                                    }
-                                }
-                                // Return when we have parts with content
-                                if !turn.parts.is_empty() {
-                                    return Ok(Some(response));
-                                }
-                            }
-                            if content.turn_complete.unwrap_or(false) {
-                                log_debug("✅ Turn complete");
-                            }
+                        if let Some(ref usage) = response.usage_metadata {
+                            audit_log().event("usage", Some(usage.to_string()));
                         }
                         return Ok(Some(response));
                     }
                     Err(e) => {
-                        log_debug(&format!("⚠️ Parse error: {}", e));
+                        audit_log().event("parse_error", Some(e.to_string()));
                     }
                 }
-            } else {
-                log_debug("📥 (non-content msg)");
             }
         }
         Ok(None)
     }
 
-    /// Receive response - keeps trying until content or timeout
-    pub async fn receive(&mut self) -> Result<Option<GeminiResponse>, Box<dyn std::error::Error>> {
+    /// Receive response - keeps trying until content, the end of the turn
+    /// or timeout
+    ///
+    /// A cut-off or finished turn returns right away rather than waiting
+    /// out the timeout for content that won't come.
+    pub async fn receive(&mut self) -> Result<ReceiveOutcome, Box<dyn std::error::Error>> {
         let timeout = tokio::time::Duration::from_secs(30);
         let start = tokio::time::Instant::now();
 
-        log_debug("👂 Aguardando resposta...");
-
         while start.elapsed() < timeout {
             match self.try_receive().await {
                 Ok(Some(response)) => {
-                    // Check if has actual audio/text content
                     if let Some(ref content) = response.server_content {
-                        if let Some(ref turn) = content.model_turn {
-                            let has_content = turn.parts.iter().any(|p| {
-                                p.text.is_some() || p.inline_data.is_some()
-                            });
-                            if has_content {
-                                return Ok(Some(response));
-                            }
+                        // Check if has actual audio/text content
+                        let has_content = content
+                            .model_turn
+                            .as_ref()
+                            .is_some_and(|turn| turn.parts.iter().any(|p| p.text.is_some() || p.inline_data.is_some()));
+                        if has_content {
+                            return Ok(ReceiveOutcome::Content(response));
+                        }
+                        if content.interrupted.unwrap_or(false) {
+                            audit_log().event("interrupted", None);
+                            return Ok(ReceiveOutcome::Interrupted);
+                        }
+                        if content.turn_complete.unwrap_or(false) {
+                            return Ok(ReceiveOutcome::TurnComplete);
                         }
                     }
-                    // Got message but no content yet, continue
+                    // generationComplete or usage only: the turn goes on
                 }
                 Ok(None) => {
                     // No message, small sleep and continue
@@ -300,20 +1312,253 @@ impl GeminiClient {
             }
         }
 
-        log_debug("⏱️ Timeout");
-        Ok(None)
+        audit_log().event("timeout", None);
+        Ok(ReceiveOutcome::TimedOut)
+    }
+
+    /// Stream the reply part by part as it arrives
+    ///
+    /// Unlike `receive()`, audio can be played as soon as the first chunk
+    /// lands instead of after the model has finished the turn.
+    pub fn receive_stream(&mut self) -> ResponseStream<'_> {
+        ResponseStream { client: self, pending: VecDeque::new(), done: false }
+    }
+
+    /// Give up on the reply being received (the user barged in)
+    ///
+    /// The server stops generating on its own once it hears new user audio
+    /// and answers with `interrupted`; until that or the end of the turn
+    /// arrives, whatever is still in flight is dropped instead of streamed.
+    pub fn abandon_turn(&mut self) {
+        audit_log().event("turn_abandoned", None);
+        self.discarding = true;
+    }
+
+    /// Re-establish the session so the server forgets everything it holds,
+    /// then replay the given (already trimmed) conversation.
+    ///
+    /// The Live API keeps the conversation server-side, so removing turns
+    /// locally is not enough for the model to actually forget them.
+    pub async fn reset_with_context(&mut self, turns: &[Turn]) -> Result<(), Box<dyn std::error::Error>> {
+        audit_log().event("session_reset", None);
+        self.reopen().await?;
+
+        self.send_context(turns).await
+    }
+
+    /// Replay earlier turns into a fresh session so the model keeps the
+    /// thread (after connecting, or after a reconnect)
+    ///
+    /// Sent as `user`/`model` turns without `turn_complete`, so nothing is
+    /// answered until the next real user turn. Only the last
+    /// `context_turns` are sent, without command output if
+    /// `skip_command_results` is set.
+    pub async fn send_context(&mut self, turns: &[Turn]) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(message) = context_message(turns, self.config.context_turns, self.config.skip_command_results) else {
+            return Ok(());
+        };
+        self.send_raw(&message.to_string()).await?;
+        Ok(())
     }
 
-    /// Keep connection alive
+    /// Ping now; an idle connection is also pinged on its own
     pub async fn ping(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.ws.ping().await
+        if let Err(e) = self.ws.ping().await {
+            audit_log().event("ping_failed", Some(e.to_string()));
+            self.reconnect().await?;
+        }
+        Ok(())
+    }
+}
+
+/// `client_content` replaying the last `limit` of `turns` (oldest first),
+/// or `None` if there is nothing to send
+fn context_message(turns: &[Turn], limit: usize, skip_command_results: bool) -> Option<Value> {
+    let kept: Vec<&Turn> = turns.iter().filter(|t| !(skip_command_results && t.command_result)).collect();
+    let recent = &kept[kept.len().saturating_sub(limit)..];
+    if recent.is_empty() {
+        return None;
+    }
+    let turns: Vec<Value> = recent
+        .iter()
+        .map(|turn| {
+            let role = match turn.role {
+                Role::User => "user",
+                Role::Assistant => "model",
+            };
+            json!({ "role": role, "parts": [{ "text": turn.content }] })
+        })
+        .collect();
+    Some(json!({ "client_content": { "turn_complete": false, "turns": turns } }))
+}
+
+/// One piece of a streamed reply, in arrival order
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    Text(String),
+    /// Base64 PCM, ready for `AudioPlayer::play_response`
+    Audio(InlineData),
+    /// Last event of the turn
+    TurnComplete,
+    /// The server stopped generating because the user spoke; ends the turn
+    Interrupted,
+    /// The model wants a tool run; the turn goes on once it is answered
+    /// with `ResponseStream::respond`
+    ToolCall(FunctionCall),
+}
+
+/// `toolResponse` for a finished call
+fn tool_response_message(call: &FunctionCall, result: &Result<String, String>) -> Value {
+    let response = match result {
+        Ok(output) => json!({ "result": output }),
+        Err(error) => json!({ "error": error }),
+    };
+    let mut function_response = json!({ "name": call.name, "response": response });
+    if let Some(ref id) = call.id {
+        function_response["id"] = json!(id);
+    }
+    json!({ "tool_response": { "function_responses": [function_response] } })
+}
+
+/// Events carried by one server message; errors, top-level or inside
+/// `serverContent`, are returned as `Err`
+fn parse_stream_message(text: &str) -> Result<Vec<StreamEvent>, Box<dyn std::error::Error>> {
+    let json: Value = serde_json::from_str(text)?;
+
+    if let Some(error) = json.get("error").or_else(|| json.pointer("/serverContent/error")) {
+        let err_msg = format!("Gemini error: {:?}", error);
+        audit_log().event("error", Some(err_msg.clone()));
+        return Err(err_msg.into());
+    }
+
+    // Token usage rides along with other messages or comes on its own
+    if let Some(usage) = json.get("usageMetadata").and_then(|u| serde_json::from_value::<UsageMetadata>(u.clone()).ok()) {
+        audit_log().event("usage", Some(usage.to_string()));
+    }
+
+    // Tool calls arrive on their own, outside serverContent
+    if let Some(calls) = json.pointer("/toolCall/functionCalls") {
+        let calls: Vec<FunctionCall> = serde_json::from_value(calls.clone())?;
+        return Ok(calls.into_iter().map(StreamEvent::ToolCall).collect());
+    }
+
+    let Some(content) = json.get("serverContent") else {
+        return Ok(Vec::new());
+    };
+    let content: ServerContent = serde_json::from_value(content.clone())?;
+
+    let mut events = Vec::new();
+    for part in content.model_turn.map(|turn| turn.parts).unwrap_or_default() {
+        if let Some(text) = part.text {
+            events.push(StreamEvent::Text(text));
+        }
+        if let Some(data) = part.inline_data {
+            events.push(StreamEvent::Audio(data));
+        }
+        if let Some(call) = part.function_call {
+            events.push(StreamEvent::ToolCall(call));
+        }
+    }
+    if content.interrupted.unwrap_or(false) {
+        events.push(StreamEvent::Interrupted);
+    }
+    if content.turn_complete.unwrap_or(false) {
+        events.push(StreamEvent::TurnComplete);
+    }
+    Ok(events)
+}
+
+/// Drop the rest of an abandoned turn, up to and including its end marker
+fn drop_abandoned(discarding: &mut bool, events: Vec<StreamEvent>) -> Vec<StreamEvent> {
+    if !*discarding {
+        return events;
+    }
+    match events.iter().position(|e| matches!(e, StreamEvent::TurnComplete | StreamEvent::Interrupted)) {
+        Some(end) => {
+            *discarding = false;
+            events.into_iter().skip(end + 1).collect()
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Reply being received, see `GeminiClient::receive_stream`
+pub struct ResponseStream<'a> {
+    client: &'a mut GeminiClient,
+    /// Parts of the last message not handed out yet
+    pending: VecDeque<StreamEvent>,
+    done: bool,
+}
+
+impl ResponseStream<'_> {
+    /// Answer a `ToolCall` event; the reply continues on this stream
+    pub async fn respond(&mut self, call: &FunctionCall, result: &Result<String, String>) -> Result<(), Box<dyn std::error::Error>> {
+        self.client.send_tool_response(call, result).await
+    }
+
+    /// Next part of the reply; `None` after `TurnComplete` or `Interrupted`
+    ///
+    /// An error ends the stream. Going `STREAM_IDLE_TIMEOUT` without any
+    /// part is reported as an error too.
+    pub async fn next(&mut self) -> Option<Result<StreamEvent, Box<dyn std::error::Error>>> {
+        let start = tokio::time::Instant::now();
+
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                if matches!(event, StreamEvent::TurnComplete | StreamEvent::Interrupted) {
+                    self.done = true;
+                }
+                return Some(Ok(event));
+            }
+            if self.done {
+                return None;
+            }
+            if start.elapsed() > STREAM_IDLE_TIMEOUT {
+                audit_log().event("timeout", None);
+                self.done = true;
+                return Some(Err("Timeout aguardando resposta".into()));
+            }
+
+            let parsed = match self.client.receive_message().await {
+                Ok(Some(text)) => parse_stream_message(&text),
+                Ok(None) => {
+                    tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+                    continue;
+                }
+                Err(e) => Err(e),
+            };
+            match parsed {
+                Ok(events) => self.pending.extend(drop_abandoned(&mut self.client.discarding, events)),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
     }
 }
 
+/// What `GeminiClient::receive` got
+#[derive(Debug)]
+pub enum ReceiveOutcome {
+    /// A message with audio or text
+    Content(GeminiResponse),
+    /// The model was cut off (the user spoke); nothing more is coming for
+    /// this turn
+    Interrupted,
+    /// The turn ended without (more) content
+    TurnComplete,
+    /// Nothing arrived in time
+    TimedOut,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct GeminiResponse {
     #[serde(rename = "serverContent")]
     pub server_content: Option<ServerContent>,
+    /// Sent at the top level of a message, not inside `serverContent`
+    #[serde(rename = "usageMetadata")]
+    pub usage_metadata: Option<UsageMetadata>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -322,6 +1567,30 @@ pub struct ServerContent {
     pub model_turn: Option<ModelTurn>,
     #[serde(rename = "turnComplete")]
     pub turn_complete: Option<bool>,
+    pub interrupted: Option<bool>,
+    /// The model has finished generating; audio may still be streaming and
+    /// `turnComplete` follows
+    #[serde(rename = "generationComplete")]
+    pub generation_complete: Option<bool>,
+}
+
+/// Tokens the session has used so far
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UsageMetadata {
+    pub prompt_token_count: u64,
+    pub response_token_count: u64,
+    pub total_token_count: u64,
+}
+
+impl std::fmt::Display for UsageMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} prompt + {} response = {} tokens",
+            self.prompt_token_count, self.response_token_count, self.total_token_count
+        )
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -334,11 +1603,764 @@ pub struct Part {
     pub text: Option<String>,
     #[serde(rename = "inlineData")]
     pub inline_data: Option<InlineData>,
+    #[serde(rename = "functionCall")]
+    pub function_call: Option<FunctionCall>,
 }
 
-#[derive(Debug, Deserialize)]
+/// A tool the model asked EVA to run
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FunctionCall {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub args: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct InlineData {
     #[serde(rename = "mimeType")]
     pub mime_type: String,
     pub data: String,
 }
+
+impl InlineData {
+    /// The `rate` parameter of the MIME type (`audio/pcm;rate=24000`), if
+    /// there is one
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.mime_type.split(';').skip(1).find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            key.trim().eq_ignore_ascii_case("rate").then(|| value.trim().parse().ok())?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::ConversationSession;
+    use crate::user_profile::Personality;
+    use crate::websocket::WsFuture;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tokio_tungstenite::tungstenite::Message;
+
+    fn config(speech: SpeechSettings) -> GeminiConfig {
+        GeminiConfig { api_key: "test".to_string(), speech, ..Default::default() }
+    }
+
+    #[test]
+    fn test_default_setup_has_voice_only() {
+        let setup = config(SpeechSettings::default()).setup_message(ProsodyMode::Server);
+        let speech = &setup["setup"]["generation_config"]["speech_config"];
+        assert_eq!(speech["voice_config"]["prebuilt_voice_config"]["voice_name"], "Aoede");
+        assert!(speech.get("language_code").is_none());
+        assert!(speech.get("speaking_rate").is_none());
+        assert!(speech.get("pitch").is_none());
+    }
+
+    #[test]
+    fn test_profile_rate_pitch_and_language() {
+        let mut profile = UserProfile::default();
+        profile.set_language("pt-BR");
+        profile.set_voice_speed(0.8);
+        profile.set_voice_pitch(-1.0);
+
+        let setup = config(SpeechSettings::from_profile(&profile)).setup_message(ProsodyMode::Server);
+        let speech = &setup["setup"]["generation_config"]["speech_config"];
+        assert_eq!(speech["language_code"], "pt-BR");
+        assert!((speech["speaking_rate"].as_f64().unwrap() - 0.8).abs() < 1e-6);
+        assert_eq!(speech["pitch"], -1.0);
+    }
+
+    #[test]
+    fn test_personality_preset_combines_with_profile() {
+        let mut profile = UserProfile { personality: Personality::Calm, ..UserProfile::default() };
+        profile.set_voice_speed(1.0);
+
+        let speech = SpeechSettings::from_profile(&profile);
+        assert_eq!(speech.voice_name, "Charon");
+        assert!((speech.speaking_rate - 0.9).abs() < 1e-6);
+        assert_eq!(speech.pitch, -2.0);
+
+        let setup = config(speech).setup_message(ProsodyMode::Server);
+        assert_eq!(
+            setup["setup"]["generation_config"]["speech_config"]["voice_config"]["prebuilt_voice_config"]["voice_name"],
+            "Charon"
+        );
+    }
+
+    #[test]
+    fn test_local_stretch_omits_prosody_fields() {
+        let speech = SpeechSettings { speaking_rate: 1.3, pitch: 2.0, language_code: Some("en-US".to_string()), ..Default::default() };
+        let setup = config(speech).setup_message(ProsodyMode::LocalStretch);
+        let speech = &setup["setup"]["generation_config"]["speech_config"];
+        assert!(speech.get("speaking_rate").is_none());
+        assert!(speech.get("pitch").is_none());
+        // Language is still sent: it is supported regardless
+        assert_eq!(speech["language_code"], "en-US");
+    }
+
+    #[test]
+    fn test_capabilities_and_round_trip() {
+        let mut cfg = config(SpeechSettings::default());
+        cfg.capabilities = Some("Local actions available on this device: timers.".to_string());
+        let setup = cfg.setup_message(ProsodyMode::Server);
+        let text = setup["setup"]["system_instruction"]["parts"][0]["text"].as_str().unwrap();
+        assert!(text.ends_with("timers."));
+        assert_eq!(setup["setup"]["model"], format!("models/{}", cfg.model[0]));
+
+        // Configs saved before speech settings existed still load, their
+        // single model as a list of one
+        let old = r#"{"api_key":"k","model":"m","ws_url":"wss://x"}"#;
+        let loaded: GeminiConfig = serde_json::from_str(old).unwrap();
+        assert_eq!(loaded.model, vec!["m".to_string()]);
+        assert_eq!(loaded.speech, SpeechSettings::default());
+        assert_eq!(loaded.system_instruction, DEFAULT_SYSTEM_INSTRUCTION);
+        assert_eq!(loaded.response_language, "pt-BR");
+    }
+
+    #[test]
+    fn test_stream_keeps_interleaved_parts_in_order() {
+        let first = r#"{"serverContent":{"modelTurn":{"parts":[
+            {"inlineData":{"mimeType":"audio/pcm;rate=24000","data":"AAA="}},
+            {"text":"Olá"},
+            {"inlineData":{"mimeType":"audio/pcm;rate=24000","data":"AQE="}}]}}}"#;
+        let events = parse_stream_message(first).unwrap();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(&events[0], StreamEvent::Audio(d) if d.data == "AAA="));
+        assert_eq!(events[1], StreamEvent::Text("Olá".to_string()));
+        assert!(matches!(&events[2], StreamEvent::Audio(d) if d.data == "AQE="));
+
+        let last = r#"{"serverContent":{"modelTurn":{"parts":[{"text":"!"}]},"turnComplete":true}}"#;
+        assert_eq!(
+            parse_stream_message(last).unwrap(),
+            vec![StreamEvent::Text("!".to_string()), StreamEvent::TurnComplete]
+        );
+        assert!(parse_stream_message(r#"{"usageMetadata":{}}"#).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stream_surfaces_errors_mid_turn() {
+        let err = parse_stream_message(r#"{"serverContent":{"error":{"code":500,"message":"internal"}}}"#).unwrap_err();
+        assert!(err.to_string().contains("internal"));
+        assert!(parse_stream_message(r#"{"error":{"message":"quota"}}"#).is_err());
+    }
+
+    #[test]
+    fn test_inline_data_sample_rate() {
+        let rate = |mime_type: &str| InlineData { mime_type: mime_type.to_string(), data: String::new() }.sample_rate();
+        assert_eq!(rate("audio/pcm;rate=24000"), Some(24000));
+        assert_eq!(rate("audio/L16; codec=pcm; Rate=16000"), Some(16000));
+        assert_eq!(rate("audio/pcm"), None);
+        assert_eq!(rate("audio/pcm;rate=fast"), None);
+    }
+
+    #[test]
+    fn test_abandoned_turn_is_dropped_until_it_ends() {
+        let audio = |data: &str| StreamEvent::Audio(InlineData { mime_type: "audio/pcm".to_string(), data: data.to_string() });
+        let mut discarding = true;
+
+        assert!(drop_abandoned(&mut discarding, vec![audio("old1"), audio("old2")]).is_empty());
+        let interrupted = parse_stream_message(r#"{"serverContent":{"interrupted":true}}"#).unwrap();
+        assert_eq!(interrupted, vec![StreamEvent::Interrupted]);
+        assert!(drop_abandoned(&mut discarding, interrupted).is_empty());
+        assert!(!discarding);
+
+        // The next turn comes through untouched
+        assert_eq!(drop_abandoned(&mut discarding, vec![audio("new")]), vec![audio("new")]);
+
+        // A message that ends the old turn and starts the next keeps the tail
+        discarding = true;
+        let events = vec![audio("old"), StreamEvent::TurnComplete, StreamEvent::Text("next".to_string())];
+        assert_eq!(drop_abandoned(&mut discarding, events), vec![StreamEvent::Text("next".to_string())]);
+    }
+
+    /// Server messages captured from a Live API session
+    const MODEL_TURN: &str = include_str!("../tests/fixtures/gemini/model_turn.json");
+    const GENERATION_COMPLETE: &str = include_str!("../tests/fixtures/gemini/generation_complete.json");
+    const TURN_COMPLETE: &str = include_str!("../tests/fixtures/gemini/turn_complete.json");
+    const INTERRUPTED: &str = include_str!("../tests/fixtures/gemini/interrupted.json");
+    const USAGE_METADATA: &str = include_str!("../tests/fixtures/gemini/usage_metadata.json");
+    const TOOL_CALL: &str = include_str!("../tests/fixtures/gemini/tool_call.json");
+
+    #[test]
+    fn test_server_events_parse_from_fixtures() {
+        let response: GeminiResponse = serde_json::from_str(MODEL_TURN).unwrap();
+        let parts = &response.server_content.unwrap().model_turn.unwrap().parts;
+        assert_eq!(parts[0].inline_data.as_ref().unwrap().data, "AAABAAIAAwA=");
+        assert_eq!(parts[0].inline_data.as_ref().unwrap().sample_rate(), Some(24000));
+        assert_eq!(parts[1].text.as_deref(), Some("Claro, "));
+        let events = parse_stream_message(MODEL_TURN).unwrap();
+        assert!(matches!(&events[..], [StreamEvent::Audio(_), StreamEvent::Text(t)] if t == "Claro, "));
+
+        let content = serde_json::from_str::<GeminiResponse>(GENERATION_COMPLETE).unwrap().server_content.unwrap();
+        assert_eq!(content.generation_complete, Some(true));
+        assert_eq!(content.turn_complete, None);
+        // Audio may still be on its way: not the end of the turn
+        assert!(parse_stream_message(GENERATION_COMPLETE).unwrap().is_empty());
+
+        let content = serde_json::from_str::<GeminiResponse>(TURN_COMPLETE).unwrap().server_content.unwrap();
+        assert_eq!(content.turn_complete, Some(true));
+        assert_eq!(parse_stream_message(TURN_COMPLETE).unwrap(), vec![StreamEvent::TurnComplete]);
+
+        let content = serde_json::from_str::<GeminiResponse>(INTERRUPTED).unwrap().server_content.unwrap();
+        assert_eq!(content.interrupted, Some(true));
+        assert_eq!(parse_stream_message(INTERRUPTED).unwrap(), vec![StreamEvent::Interrupted]);
+
+        let response: GeminiResponse = serde_json::from_str(USAGE_METADATA).unwrap();
+        assert!(response.server_content.is_none());
+        let usage = response.usage_metadata.unwrap();
+        assert_eq!(usage, UsageMetadata { prompt_token_count: 412, response_token_count: 96, total_token_count: 508 });
+        assert_eq!(usage.to_string(), "412 prompt + 96 response = 508 tokens");
+        assert!(parse_stream_message(USAGE_METADATA).unwrap().is_empty());
+
+        let events = parse_stream_message(TOOL_CALL).unwrap();
+        assert!(matches!(&events[..], [StreamEvent::ToolCall(c)]
+            if c.id.as_deref() == Some("function-call-7342") && c.name == "list_files" && c.args["path"] == "."));
+    }
+
+    #[test]
+    fn test_tool_calls_are_parsed_and_answered() {
+        let setup = config(SpeechSettings::default()).setup_message(ProsodyMode::Server);
+        assert!(setup["setup"]["tools"][0]["function_declarations"].is_array());
+        let off = GeminiConfig { tools: false, ..config(SpeechSettings::default()) };
+        assert!(off.setup_message(ProsodyMode::Server)["setup"].get("tools").is_none());
+        assert_eq!(setup["setup"]["generation_config"]["response_modalities"], json!(["AUDIO"]));
+        let text = GeminiConfig { text_replies: true, ..off }.setup_message(ProsodyMode::Server);
+        assert_eq!(text["setup"]["generation_config"]["response_modalities"], json!(["TEXT"]));
+        assert!(text["setup"]["generation_config"].get("speech_config").is_none());
+
+        let delete = FunctionCall { id: Some("c1".to_string()), name: "delete_file".to_string(), args: json!({ "path": "foo.txt" }) };
+        let events = parse_stream_message(
+            r#"{"toolCall":{"functionCalls":[{"id":"c1","name":"delete_file","args":{"path":"foo.txt"}}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(events, vec![StreamEvent::ToolCall(delete.clone())]);
+
+        // Also accepted as a part of the model's turn
+        let events = parse_stream_message(
+            r#"{"serverContent":{"modelTurn":{"parts":[{"functionCall":{"name":"list_processes"}}]}}}"#,
+        )
+        .unwrap();
+        assert!(matches!(&events[..], [StreamEvent::ToolCall(c)] if c.name == "list_processes" && c.id.is_none()));
+
+        let answer = tool_response_message(&delete, &Ok("Deleted foo.txt".to_string()));
+        let response = &answer["tool_response"]["function_responses"][0];
+        assert_eq!(response["id"], "c1");
+        assert_eq!(response["name"], "delete_file");
+        assert_eq!(response["response"]["result"], "Deleted foo.txt");
+        let failed = tool_response_message(&delete, &Err("No such file".to_string()));
+        assert_eq!(failed["tool_response"]["function_responses"][0]["response"]["error"], "No such file");
+    }
+
+    /// Scripted connection: answers setup, records what was sent and
+    /// fails once its `alive` flag is cleared
+    struct MockTransport {
+        sent: Arc<Mutex<Vec<String>>>,
+        alive: Arc<AtomicBool>,
+        incoming: VecDeque<Message>,
+        /// Server messages that follow `setupComplete`
+        script: Vec<String>,
+        /// Sent instead of `setupComplete`: the setup is refused
+        refusal: Option<Message>,
+    }
+
+    impl Transport for MockTransport {
+        fn send_text<'a>(&'a mut self, text: &'a str) -> WsFuture<'a, ()> {
+            Box::pin(async move {
+                if !self.alive.load(Ordering::SeqCst) {
+                    return Err("broken pipe".into());
+                }
+                self.sent.lock().unwrap().push(text.to_string());
+                if text.contains("\"setup\"") {
+                    if let Some(refusal) = self.refusal.take() {
+                        self.incoming.push_back(refusal);
+                        return Ok(());
+                    }
+                    self.incoming.push_back(Message::Text(r#"{"setupComplete":{}}"#.to_string()));
+                    self.incoming.extend(self.script.drain(..).map(Message::Text));
+                }
+                Ok(())
+            })
+        }
+
+        fn receive(&mut self) -> WsFuture<'_, Option<Message>> {
+            Box::pin(async move {
+                if !self.alive.load(Ordering::SeqCst) {
+                    return Ok(None);
+                }
+                match self.incoming.pop_front() {
+                    Some(msg) => Ok(Some(msg)),
+                    None => std::future::pending().await,
+                }
+            })
+        }
+
+        fn ping(&mut self) -> WsFuture<'_, ()> {
+            let alive = self.alive.load(Ordering::SeqCst);
+            Box::pin(async move { if alive { Ok(()) } else { Err("broken pipe".into()) } })
+        }
+
+        fn close(self: Box<Self>) -> WsFuture<'static, ()> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    /// Hands out `MockTransport`s, refusing `refuse` connections first
+    #[derive(Clone, Default)]
+    struct MockConnector {
+        opened: Arc<Mutex<Vec<(Arc<Mutex<Vec<String>>>, Arc<AtomicBool>)>>>,
+        refuse: Arc<Mutex<u32>>,
+        /// What the server says after setup, on every connection
+        script: Vec<String>,
+        /// Setup refusals for the next connections, one each
+        refusals: Arc<Mutex<VecDeque<Message>>>,
+    }
+
+    impl MockConnector {
+        fn sent(&self, connection: usize) -> Vec<String> {
+            self.opened.lock().unwrap()[connection].0.lock().unwrap().clone()
+        }
+
+        fn drop_connection(&self, connection: usize) {
+            self.opened.lock().unwrap()[connection].1.store(false, Ordering::SeqCst);
+        }
+
+        fn count(&self) -> usize {
+            self.opened.lock().unwrap().len()
+        }
+    }
+
+    impl Connector for MockConnector {
+        fn connect<'a>(&'a self, _url: &'a str) -> WsFuture<'a, Box<dyn Transport>> {
+            Box::pin(async move {
+                let mut refuse = self.refuse.lock().unwrap();
+                if *refuse > 0 {
+                    *refuse -= 1;
+                    return Err("connection refused".into());
+                }
+                let transport = MockTransport {
+                    sent: Arc::new(Mutex::new(Vec::new())),
+                    alive: Arc::new(AtomicBool::new(true)),
+                    incoming: VecDeque::new(),
+                    script: self.script.clone(),
+                    refusal: self.refusals.lock().unwrap().pop_front(),
+                };
+                self.opened.lock().unwrap().push((Arc::clone(&transport.sent), Arc::clone(&transport.alive)));
+                Ok(Box::new(transport) as Box<dyn Transport>)
+            })
+        }
+    }
+
+    fn fast_reconnect() -> GeminiConfig {
+        GeminiConfig {
+            reconnect: ReconnectPolicy { max_attempts: 3, initial_backoff_ms: 1, max_backoff_ms: 4 },
+            ..config(SpeechSettings::default())
+        }
+    }
+
+    #[test]
+    fn test_profile_language_and_persona_reach_setup() {
+        let instruction_of = |cfg: &GeminiConfig| {
+            cfg.setup_message(ProsodyMode::Server)["setup"]["system_instruction"]["parts"][0]["text"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+
+        let mut profile = UserProfile::default();
+        let english = GeminiConfig::from_profile(&profile);
+        assert_eq!(english.speech.language_code.as_deref(), Some("en-US"));
+        let text = instruction_of(&english);
+        assert!(text.starts_with(DEFAULT_SYSTEM_INSTRUCTION));
+        assert!(text.contains("Answer in English (en-US)"), "{}", text);
+
+        profile.set_language("pt-BR");
+        profile.system_instruction = Some("Você é EVA, a assistente da casa.".to_string());
+        let portuguese = GeminiConfig::from_profile(&profile);
+        let text = instruction_of(&portuguese);
+        assert!(text.starts_with("Você é EVA, a assistente da casa."));
+        assert!(text.contains("Brazilian Portuguese (pt-BR)"), "{}", text);
+
+        // A fixed reply language wins over the profile language
+        profile.response_language = ResponseLanguageMode::Always("es-ES".to_string());
+        assert!(instruction_of(&GeminiConfig::from_profile(&profile)).contains("Spanish (es-ES)"));
+
+        assert_eq!(GeminiConfig::from_profile(&profile).model[0], DEFAULT_MODELS[0]);
+        profile.set_preference("gemini_models", "gemini-next-live, gemini-live-2.5-flash-preview");
+        assert_eq!(GeminiConfig::from_profile(&profile).model, vec!["gemini-next-live", "gemini-live-2.5-flash-preview"]);
+
+        let cfg = GeminiConfig { temperature: 0.2, ..config(SpeechSettings::default()) };
+        let temperature = cfg.setup_message(ProsodyMode::Server)["setup"]["generation_config"]["temperature"].as_f64().unwrap();
+        assert!((temperature - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let policy = ReconnectPolicy::default();
+        let delays: Vec<u64> = (1..=6).map(|n| policy.delay(n).as_millis() as u64).collect();
+        assert_eq!(delays, vec![500, 1000, 2000, 4000, 8000, 8000]);
+        assert_eq!(policy.delay(u32::MAX).as_millis(), 8000);
+    }
+
+    #[test]
+    fn test_quota_windows_roll_over() {
+        let mut tracker = QuotaTracker::load(None);
+        let limits = QuotaLimits { requests_per_minute: Some(10), tokens_per_day: Some(1000), ..QuotaLimits::default() };
+        // 23:59:00 UTC
+        let start = 20 * DAY - MINUTE;
+
+        tracker.record(QuotaUsage { requests: 8, ..QuotaUsage::default() }, start);
+        assert_eq!(tracker.check(&limits, start + 5), QuotaState::Ok);
+        tracker.record(QuotaUsage { requests: 1, tokens: 950, ..QuotaUsage::default() }, start + 10);
+        assert!(matches!(tracker.check(&limits, start + 10), QuotaState::Near(w) if w.contains("9 of 10 requests per minute")));
+        tracker.record(QuotaUsage { requests: 1, ..QuotaUsage::default() }, start + 15);
+        let QuotaState::Exceeded(error) = tracker.check(&limits, start + 15) else { panic!("not exceeded") };
+        assert_eq!(error, "Gemini quota exceeded (10 of 10 requests per minute used), try again in 1 minute");
+
+        // A new minute and a new day: both windows start from zero
+        assert_eq!(tracker.usage(start + MINUTE), (QuotaUsage::default(), QuotaUsage::default()));
+        assert_eq!(tracker.check(&limits, start + MINUTE), QuotaState::Ok);
+
+        // Only the minute rolls within a day
+        tracker.record(QuotaUsage { tokens: 1000, ..QuotaUsage::default() }, start + 2 * MINUTE);
+        let QuotaState::Exceeded(error) = tracker.check(&limits, start + 3 * MINUTE + 30) else { panic!("not exceeded") };
+        assert!(error.contains("response tokens per day") && error.ends_with("try again in 1438 minutes"), "{}", error);
+
+        let limits = QuotaLimits { audio_seconds_per_minute: Some(60), ..QuotaLimits::default() };
+        tracker.record(QuotaUsage { audio_ms: 59_999, ..QuotaUsage::default() }, start + 4 * MINUTE);
+        assert!(matches!(tracker.check(&limits, start + 4 * MINUTE), QuotaState::Near(_)));
+        tracker.record(QuotaUsage { audio_ms: 1, ..QuotaUsage::default() }, start + 4 * MINUTE);
+        assert!(matches!(tracker.check(&limits, start + 4 * MINUTE), QuotaState::Exceeded(e) if e.contains("60 of 60 audio seconds")));
+    }
+
+    #[test]
+    fn test_quota_counters_survive_a_restart() {
+        let path = std::env::temp_dir().join(format!("eva_quota_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let now = 20 * DAY + 12 * 3600;
+
+        let mut tracker = QuotaTracker::load(Some(path.clone()));
+        tracker.record(QuotaUsage { requests: 1, audio_ms: 500, tokens: 40 }, now);
+        // Within the save interval: only written when asked
+        tracker.record(QuotaUsage { requests: 1, ..QuotaUsage::default() }, now + 1);
+        assert_eq!(QuotaTracker::load(Some(path.clone())).usage(now + 1).1.requests, 1);
+        tracker.save(now + 2).unwrap();
+        drop(tracker);
+
+        let mut reloaded = QuotaTracker::load(Some(path.clone()));
+        let expected = QuotaUsage { requests: 2, audio_ms: 500, tokens: 40 };
+        assert_eq!(reloaded.usage(now + 30), (expected, expected));
+        // Saved yesterday: nothing carries over
+        assert_eq!(reloaded.usage(now + DAY).1, QuotaUsage::default());
+
+        // Garbage starts from zero
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(QuotaTracker::load(Some(path.clone())).usage(now).1, QuotaUsage::default());
+        let _ = fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_exceeded_quota_refuses_to_send() {
+        let connector = MockConnector::default();
+        // A limit of zero is used up whatever other tests have counted
+        let cfg = GeminiConfig { quota: QuotaLimits { requests_per_day: Some(0), ..QuotaLimits::default() }, ..fast_reconnect() };
+        let mut client = GeminiClient::connect_with(cfg, Box::new(connector.clone())).await.unwrap();
+
+        let err = client.send_text("hello").await.unwrap_err();
+        assert!(err.to_string().starts_with("Gemini quota exceeded"), "{}", err);
+        assert!(client.send_audio(&[0; 320]).await.is_err());
+        assert!(client.quota_warning().is_some());
+        // Only the setup went out
+        assert_eq!(connector.sent(0).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_socket_reconnects_and_replays_context() {
+        let connector = MockConnector::default();
+        let mut client = GeminiClient::connect_with(fast_reconnect(), Box::new(connector.clone())).await.unwrap();
+        let mut session = ConversationSession::new();
+        session.add_turn(Role::User, "my name is Ana".to_string());
+        session.add_turn(Role::Assistant, "Hi Ana!".to_string());
+        client.set_resume_context(session.turns().to_vec());
+
+        connector.drop_connection(0);
+        client.send_text("what is my name?").await.unwrap();
+
+        assert_eq!(connector.count(), 2);
+        let sent = connector.sent(1);
+        assert_eq!(sent.len(), 3);
+        assert!(sent[0].contains("\"setup\""));
+        assert!(sent[1].contains("my name is Ana") && sent[1].contains("\"turn_complete\":false"));
+        assert!(sent[2].contains("what is my name?"));
+        assert_eq!(client.connection_state(), ConnectionState::Connected);
+
+        // Mid-reply drops lose the reply but keep the session usable
+        connector.drop_connection(1);
+        let err = client.receive_message().await.unwrap_err();
+        assert!(err.to_string().contains("reconnected"), "{}", err);
+        assert_eq!(connector.count(), 3);
+        assert_eq!(client.connection_state(), ConnectionState::Connected);
+    }
+
+    #[tokio::test]
+    async fn test_context_sent_as_role_turns() {
+        let mut session = ConversationSession::new();
+        for i in 0..4 {
+            session.add_turn(Role::User, format!("question {}", i));
+            session.add_turn(Role::Assistant, format!("answer {}", i));
+        }
+        session.add_turn(Role::User, "list files".to_string());
+        session.add_command_result("notes.txt".to_string());
+
+        let connector = MockConnector::default();
+        let cfg = GeminiConfig { context_turns: 4, ..fast_reconnect() };
+        let mut client = GeminiClient::connect_with(cfg, Box::new(connector.clone())).await.unwrap();
+        client.send_context(session.turns()).await.unwrap();
+        client.config.skip_command_results = true;
+        client.send_context(session.turns()).await.unwrap();
+        // Nothing to replay: nothing sent
+        client.send_context(&[]).await.unwrap();
+
+        let sent = connector.sent(0);
+        assert_eq!(sent.len(), 3);
+        let turns_of = |raw: &str| {
+            let message: Value = serde_json::from_str(raw).unwrap();
+            assert_eq!(message["client_content"]["turn_complete"], false);
+            message["client_content"]["turns"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|t| (t["role"].as_str().unwrap().to_string(), t["parts"][0]["text"].as_str().unwrap().to_string()))
+                .collect::<Vec<_>>()
+        };
+        let pair = |role: &str, text: &str| (role.to_string(), text.to_string());
+
+        // Last four turns, oldest first
+        assert_eq!(
+            turns_of(&sent[1]),
+            vec![pair("user", "question 3"), pair("model", "answer 3"), pair("user", "list files"), pair("model", "notes.txt")]
+        );
+        // Command output left out, the window reaches further back instead
+        assert_eq!(
+            turns_of(&sent[2]),
+            vec![pair("model", "answer 2"), pair("user", "question 3"), pair("model", "answer 3"), pair("user", "list files")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_profile_change_reopens_with_name_and_language() {
+        let connector = MockConnector::default();
+        let mut client = GeminiClient::connect_with(fast_reconnect(), Box::new(connector.clone())).await.unwrap();
+        let mut session = ConversationSession::new();
+        session.add_turn(Role::User, "hello".to_string());
+        client.set_resume_context(session.turns().to_vec());
+        assert!(!connector.sent(0)[0].contains("The user's name is"));
+
+        let mut profile = UserProfile::default();
+        profile.set("name", "Daniel").unwrap();
+        profile.set("language", "Portuguese").unwrap();
+        assert_eq!(client.apply_profile(&profile).await.unwrap(), ProsodyMode::Server);
+
+        assert_eq!(connector.count(), 2);
+        let sent = connector.sent(1);
+        assert!(sent[0].contains("The user's name is Daniel."), "{}", sent[0]);
+        assert!(sent[0].contains("Brazilian Portuguese (pt-BR)"));
+        assert!(sent[1].contains("hello"));
+
+        // A rate the API rejects falls back to local time-stretch
+        profile.set("voice_speed", "0.8").unwrap();
+        connector
+            .refusals
+            .lock()
+            .unwrap()
+            .push_back(setup_error(400, "INVALID_ARGUMENT", "Invalid JSON payload received. Unknown name \"speaking_rate\""));
+        assert_eq!(client.apply_profile(&profile).await.unwrap(), ProsodyMode::LocalStretch);
+        assert_eq!(connector.count(), 4);
+        let sent = connector.sent(3);
+        assert!(!sent[0].contains("speaking_rate"), "{}", sent[0]);
+        assert!(sent[1].contains("hello"));
+    }
+
+    fn audit_file(name: &str, max_bytes: u64, content: bool) -> (AuditLog, PathBuf) {
+        let path = std::env::temp_dir().join(format!("eva_audit_{}_{}.log", name, std::process::id()));
+        for n in 0..=3 {
+            let _ = fs::remove_file(if n == 0 { path.clone() } else { PathBuf::from(format!("{}.{}", path.display(), n)) });
+        }
+        (AuditLog::new(path.clone(), max_bytes, 2, content), path)
+    }
+
+    fn audit_entries(path: &Path) -> Vec<Value> {
+        fs::read_to_string(path).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_audit_log_never_writes_audio_and_redacts_text() {
+        let audio = BASE64.encode([0x5Au8; 3000]);
+        let sent = json!({ "realtime_input": { "media_chunks": [{ "mime_type": "audio/pcm;rate=16000", "data": audio }] } });
+        let reply = json!({ "serverContent": { "modelTurn": { "parts": [
+            { "text": "your password is hunter2" },
+            { "inlineData": { "mimeType": "audio/pcm;rate=24000", "data": audio } }
+        ] }, "turnComplete": true } });
+
+        // Redacted: sizes only
+        let (log, path) = audit_file("redacted", 1 << 20, false);
+        log.message(Direction::Sent, &sent.to_string());
+        log.message(Direction::Received, &reply.to_string());
+        log.event("reconnecting", Some("attempt 1/3".to_string()));
+        let raw = fs::read_to_string(&path).unwrap();
+        assert!(!raw.contains(&audio[..64]));
+        assert!(!raw.contains("hunter2"));
+        let entries = audit_entries(&path);
+        assert_eq!(entries[0]["dir"], "sent");
+        assert_eq!(entries[0]["type"], "realtime_input");
+        assert_eq!(entries[0]["audio_bytes"], 3000);
+        assert_eq!(entries[1]["type"], "serverContent");
+        assert_eq!(entries[1]["text_chars"], 24);
+        assert_eq!(entries[1]["turn_complete"], true);
+        assert!(entries[1]["latency_ms"].is_u64());
+        assert!(entries[1].get("text").is_none());
+        assert_eq!(entries[2]["event"], "reconnecting");
+        assert!(entries.iter().all(|e| e["ts"].is_string()));
+        let _ = fs::remove_file(&path);
+
+        // With EVA_DEBUG_CONTENT=1 text is kept, audio still isn't
+        let (log, path) = audit_file("content", 1 << 20, true);
+        log.message(Direction::Received, &reply.to_string());
+        let raw = fs::read_to_string(&path).unwrap();
+        assert!(raw.contains("hunter2"));
+        assert!(!raw.contains(&audio[..64]));
+
+        // Switched off (the TUI toggle): nothing written
+        assert!(!log.toggle());
+        assert!(!log.is_enabled());
+        log.message(Direction::Sent, &sent.to_string());
+        assert_eq!(audit_entries(&path).len(), 1);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_audit_log_rotates_at_threshold() {
+        let (log, path) = audit_file("rotate", 400, false);
+        let rotated = |n: usize| PathBuf::from(format!("{}.{}", path.display(), n));
+        let message = json!({ "client_content": { "turns": [{ "role": "user", "parts": [{ "text": "hi" }] }] } }).to_string();
+
+        // Each entry is ~130 bytes: three fit, the fourth starts a new file
+        for _ in 0..3 {
+            log.message(Direction::Sent, &message);
+        }
+        assert!(!rotated(1).exists());
+        log.message(Direction::Sent, &message);
+        assert!(rotated(1).exists());
+        assert_eq!(audit_entries(&path).len(), 1);
+        assert_eq!(audit_entries(&rotated(1)).len(), 3);
+
+        // Only `keep` (2) old files are kept
+        for _ in 0..9 {
+            log.message(Direction::Sent, &message);
+        }
+        assert!(rotated(2).exists());
+        assert!(!rotated(3).exists());
+        assert!(fs::metadata(&path).unwrap().len() <= 400);
+        for file in [rotated(1), rotated(2), path] {
+            let _ = fs::remove_file(file);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let connector = MockConnector::default();
+        let mut client = GeminiClient::connect_with(fast_reconnect(), Box::new(connector.clone())).await.unwrap();
+        let mut states = client.watch_state();
+
+        *connector.refuse.lock().unwrap() = 10;
+        connector.drop_connection(0);
+        let err = client.send_audio(&[0, 0]).await.unwrap_err();
+        assert!(err.to_string().contains("after 3 attempts"), "{}", err);
+        assert_eq!(*connector.refuse.lock().unwrap(), 7);
+        assert!(states.has_changed().unwrap());
+        assert_eq!(*states.borrow_and_update(), ConnectionState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn test_receive_returns_as_soon_as_the_turn_ends() {
+        let connector = MockConnector {
+            script: [USAGE_METADATA, GENERATION_COMPLETE, INTERRUPTED, MODEL_TURN, TURN_COMPLETE]
+                .iter()
+                .map(|m| m.to_string())
+                .collect(),
+            ..MockConnector::default()
+        };
+        let mut client = GeminiClient::connect_with(fast_reconnect(), Box::new(connector)).await.unwrap();
+
+        let start = std::time::Instant::now();
+        assert!(matches!(client.receive().await.unwrap(), ReceiveOutcome::Interrupted));
+        assert!(start.elapsed() < std::time::Duration::from_secs(5), "took {:?}", start.elapsed());
+        assert!(matches!(client.receive().await.unwrap(), ReceiveOutcome::Content(r) if r.server_content.is_some()));
+        assert!(matches!(client.receive().await.unwrap(), ReceiveOutcome::TurnComplete));
+    }
+
+    fn models(names: &[&str]) -> GeminiConfig {
+        GeminiConfig { model: names.iter().map(|m| m.to_string()).collect(), ..fast_reconnect() }
+    }
+
+    fn setup_error(code: u16, status: &str, message: &str) -> Message {
+        Message::Text(json!({ "error": { "code": code, "status": status, "message": message } }).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_models_fall_back_to_the_next() {
+        use tokio_tungstenite::tungstenite::protocol::frame::{coding::CloseCode, CloseFrame};
+
+        let connector = MockConnector::default();
+        connector.refusals.lock().unwrap().extend([
+            Message::Close(Some(CloseFrame {
+                code: CloseCode::Policy,
+                reason: "models/retired is not found for API version v1beta, or is not supported for bidiGenerateContent.".into(),
+            })),
+            setup_error(404, "NOT_FOUND", "models/renamed is not found"),
+        ]);
+        let client = GeminiClient::connect_with(models(&["retired", "renamed", "current"]), Box::new(connector.clone()))
+            .await
+            .unwrap();
+
+        assert_eq!(client.active_model(), "current");
+        assert_eq!(connector.count(), 3);
+        let setup: Value = serde_json::from_str(&connector.sent(2)[0]).unwrap();
+        assert_eq!(setup["setup"]["model"], "models/current");
+
+        // None left: the error names every model and why it was refused
+        connector.refusals.lock().unwrap().extend([
+            setup_error(404, "NOT_FOUND", "models/a is not found"),
+            setup_error(400, "INVALID_ARGUMENT", "models/b is not supported for bidiGenerateContent"),
+        ]);
+        let err = GeminiClient::connect_with(models(&["a", "b"]), Box::new(connector.clone())).await.err().unwrap();
+        let err = err.to_string();
+        assert!(err.starts_with("None of the configured Gemini models is available"), "{}", err);
+        assert!(err.contains("a (models/a is not found)") && err.contains("b (models/b"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_key_does_not_try_other_models() {
+        let connector = MockConnector::default();
+        connector.refusals.lock().unwrap().push_back(setup_error(403, "PERMISSION_DENIED", "API key not valid. Please pass a valid API key."));
+        let err = GeminiClient::connect_with(models(&["first", "second"]), Box::new(connector.clone())).await.err().unwrap();
+
+        assert_eq!(err.to_string(), "Gemini error (PERMISSION_DENIED): API key not valid. Please pass a valid API key.");
+        assert_eq!(err.downcast_ref::<SetupError>().map(SetupError::failure), Some(SetupFailure::Auth));
+        assert_eq!(connector.count(), 1);
+
+        // Anything else isn't a reason to switch models either
+        let other = SetupError::from_payload(&json!({ "code": 400, "status": "INVALID_ARGUMENT", "message": "Unknown name \"foo\"" }));
+        assert_eq!(other.failure(), SetupFailure::Other);
+        assert_eq!(SetupError::from_close(1007, "API key expired").failure(), SetupFailure::Auth);
+    }
+
+    #[test]
+    fn test_prosody_rejection_detection() {
+        assert!(is_prosody_rejection(r#"Gemini error: Object {"message": String("Invalid JSON payload received. Unknown name \"speaking_rate\"")}"#));
+        assert!(!is_prosody_rejection("Gemini error: API key not valid"));
+        assert!(ProsodyMode::LocalStretch.describe(false).contains("locally"));
+    }
+}
@@ -0,0 +1,438 @@
+//! Guest mode
+//!
+//! "EVA, guest mode" swaps the conversation for an ephemeral in-memory
+//! session, pauses TimeMachine capture, confines commands to Safe-risk
+//! intents in a throwaway sandbox and switches to a neutral persona.
+//! Leaving guest mode (spoken passphrase, or the TUI after confirmation)
+//! restores the previous session and discards everything the guest said.
+
+use crate::command_executor::{CommandExecutor, ExecutionOutcome};
+use crate::command_parser::{CommandIntent, RiskLevel, SessionOperation};
+use crate::offline::TurnRoute;
+use crate::session::ConversationSession;
+use crate::timemachine::TimeMachine;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Persona used while a guest is talking to EVA
+pub const NEUTRAL_PERSONA: &str = "You are a polite, neutral voice assistant talking to a guest. \
+Do not mention the owner's name, preferences or past conversations. Keep answers short.";
+
+/// Phrases that turn guest mode on
+const ENTER_PHRASES: &[&str] = &["guest mode", "modo visitante", "modo convidado"];
+
+/// Phrases that turn guest mode off; the passphrase follows them
+const EXIT_PHRASES: &[&str] = &[
+    "exit guest mode",
+    "leave guest mode",
+    "end guest mode",
+    "sair do modo visitante",
+    "sair do modo convidado",
+];
+
+/// Spoken guest mode commands
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuestCommand {
+    Enter,
+    /// Leave guest mode, authenticated by the words after the trigger phrase
+    Exit { passphrase: String },
+}
+
+/// Recognise "EVA, guest mode" / "exit guest mode <passphrase>"
+pub fn parse_guest_command(text: &str) -> Option<GuestCommand> {
+    let text = normalize(text);
+
+    for phrase in EXIT_PHRASES {
+        if let Some(idx) = text.find(phrase) {
+            let passphrase = text[idx + phrase.len()..].trim().to_string();
+            return Some(GuestCommand::Exit { passphrase });
+        }
+    }
+
+    if ENTER_PHRASES.iter().any(|p| text.contains(p)) {
+        Some(GuestCommand::Enter)
+    } else {
+        None
+    }
+}
+
+/// Lowercase, strip punctuation and collapse whitespace
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c.is_whitespace() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Intents the conversation loop applies to the owner's macros, captures,
+/// profile, timers or memory instead of handing them to the executor
+fn reaches_owner_data(intent: &CommandIntent) -> bool {
+    matches!(
+        intent,
+        CommandIntent::Macro(_)
+            | CommandIntent::TimeMachine(_)
+            | CommandIntent::Profile(_)
+            | CommandIntent::Timer(_)
+            | CommandIntent::Session(SessionOperation::Remember { .. })
+    )
+}
+
+/// What EVA says on entering guest mode
+pub fn entered_reply(portuguese: bool) -> &'static str {
+    if portuguese {
+        "Modo visitante ativado. Nada será salvo até ele terminar."
+    } else {
+        "Guest mode is on. Nothing is saved until it ends."
+    }
+}
+
+/// What EVA says on leaving guest mode
+pub fn exited_reply(summary: &ExitSummary, portuguese: bool) -> String {
+    if portuguese {
+        format!("Modo visitante encerrado; {} falas descartadas.", summary.discarded_turns)
+    } else {
+        format!("Guest mode is off; {} guest turns discarded.", summary.discarded_turns)
+    }
+}
+
+/// What leaving guest mode threw away
+#[derive(Debug, Clone)]
+pub struct ExitSummary {
+    pub discarded_turns: usize,
+    pub duration: Duration,
+}
+
+/// Everything needed to put the owner's state back
+struct GuestState {
+    saved_session: ConversationSession,
+    executor: CommandExecutor,
+    resume_capture: bool,
+    started_at: SystemTime,
+}
+
+/// Guest mode controller
+pub struct GuestMode {
+    passphrase: Option<String>,
+    state: Option<GuestState>,
+    exit_pending: bool,
+}
+
+impl GuestMode {
+    /// Create the controller; without a passphrase guest mode can only be
+    /// left from the TUI
+    pub fn new(passphrase: Option<String>) -> Self {
+        Self {
+            passphrase: passphrase.map(|p| normalize(&p)).filter(|p| !p.is_empty()),
+            state: None,
+            exit_pending: false,
+        }
+    }
+
+    /// Whether a guest session is running
+    pub fn is_active(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Whether sessions, command history and learned memories may be saved
+    pub fn allows_persistence(&self) -> bool {
+        !self.is_active()
+    }
+
+    /// Persona override while a guest is talking
+    pub fn persona(&self) -> Option<&'static str> {
+        if self.is_active() {
+            Some(NEUTRAL_PERSONA)
+        } else {
+            None
+        }
+    }
+
+    /// Sandbox the guest's commands run in
+    pub fn sandbox_dir(&self) -> Option<&Path> {
+        self.state.as_ref().map(|s| s.executor.sandbox_dir())
+    }
+
+    /// Switch to an ephemeral session and pause screen capture
+    pub fn enter(
+        &mut self,
+        session: &mut ConversationSession,
+        capture: Option<&TimeMachine>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.is_active() {
+            return Err("Already in guest mode".into());
+        }
+
+        let executor = CommandExecutor::with_sandbox(Self::scratch_sandbox())?;
+
+        let resume_capture = match capture {
+            Some(tm) if !tm.is_paused() => {
+                tm.pause();
+                true
+            }
+            _ => false,
+        };
+
+        let saved_session = std::mem::replace(session, ConversationSession::new());
+        self.state = Some(GuestState {
+            saved_session,
+            executor,
+            resume_capture,
+            started_at: SystemTime::now(),
+        });
+        self.exit_pending = false;
+
+        println!("[GuestMode] Entered guest mode");
+        Ok(())
+    }
+
+    /// Reject anything above Safe risk
+    pub fn check(&self, intent: &CommandIntent) -> Result<(), String> {
+        if self.is_active() && intent.risk() != RiskLevel::Safe {
+            return Err("That command is not available in guest mode".to_string());
+        }
+        Ok(())
+    }
+
+    /// Reject turns that reach the owner's data: macros, the Time Machine,
+    /// the profile, timers and remembered facts (commands are checked by
+    /// `execute`)
+    pub fn admit(&self, route: &TurnRoute) -> Result<(), String> {
+        let owner_data = match route {
+            TurnRoute::Macro(_) | TurnRoute::TimeMachine(_) | TurnRoute::Profile(_) | TurnRoute::Timer(_) | TurnRoute::Remember { .. } => true,
+            TurnRoute::Command(intent) => reaches_owner_data(intent),
+            TurnRoute::Sequence(intents) => intents.iter().any(reaches_owner_data),
+            TurnRoute::Audio(_) | TurnRoute::Capabilities | TurnRoute::Model => false,
+        };
+        if self.is_active() && owner_data {
+            return Err("That isn't available in guest mode".to_string());
+        }
+        Ok(())
+    }
+
+    /// Execute a command in the guest sandbox
+    pub async fn execute(&mut self, intent: CommandIntent) -> Result<String, Box<dyn std::error::Error>> {
+        self.check(&intent)?;
+        match self.state.as_mut() {
+            Some(state) => state.executor.execute(intent).await.map(ExecutionOutcome::into_message),
+            None => Err("Not in guest mode".into()),
+        }
+    }
+
+    /// Leave guest mode with the spoken passphrase
+    pub fn exit_with_passphrase(
+        &mut self,
+        spoken: &str,
+        session: &mut ConversationSession,
+        capture: Option<&TimeMachine>,
+    ) -> Result<ExitSummary, Box<dyn std::error::Error>> {
+        if !self.is_active() {
+            return Err("Not in guest mode".into());
+        }
+
+        match &self.passphrase {
+            None => Err("No guest passphrase configured; exit guest mode from the terminal".into()),
+            Some(expected) if *expected != normalize(spoken) => Err("Wrong passphrase".into()),
+            Some(_) => self.finish(session, capture),
+        }
+    }
+
+    /// Start a TUI exit; returns the confirmation prompt to show
+    pub fn request_exit(&mut self) -> Result<&'static str, Box<dyn std::error::Error>> {
+        if !self.is_active() {
+            return Err("Not in guest mode".into());
+        }
+        self.exit_pending = true;
+        Ok("Exit guest mode and discard the guest conversation? (y/n)")
+    }
+
+    /// Whether a TUI exit is waiting for confirmation
+    pub fn exit_pending(&self) -> bool {
+        self.exit_pending
+    }
+
+    /// Answer the TUI confirmation; `None` if the user declined
+    pub fn confirm_exit(
+        &mut self,
+        confirmed: bool,
+        session: &mut ConversationSession,
+        capture: Option<&TimeMachine>,
+    ) -> Result<Option<ExitSummary>, Box<dyn std::error::Error>> {
+        if !self.exit_pending {
+            return Err("Exit was not requested".into());
+        }
+        self.exit_pending = false;
+
+        if !confirmed {
+            return Ok(None);
+        }
+        self.finish(session, capture).map(Some)
+    }
+
+    /// Restore the owner's session and throw the guest's away
+    fn finish(
+        &mut self,
+        session: &mut ConversationSession,
+        capture: Option<&TimeMachine>,
+    ) -> Result<ExitSummary, Box<dyn std::error::Error>> {
+        let state = self.state.take().ok_or("Not in guest mode")?;
+        self.exit_pending = false;
+
+        let guest_session = std::mem::replace(session, state.saved_session);
+        let summary = ExitSummary {
+            discarded_turns: guest_session.turn_count(),
+            duration: state.started_at.elapsed().unwrap_or_default(),
+        };
+
+        let _ = fs::remove_dir_all(state.executor.sandbox_dir());
+
+        if state.resume_capture {
+            if let Some(tm) = capture {
+                tm.resume();
+            }
+        }
+
+        println!("[GuestMode] Left guest mode ({} turns discarded)", summary.discarded_turns);
+        Ok(summary)
+    }
+
+    /// Fresh sandbox outside the data directory so guest files never mix with the owner's
+    fn scratch_sandbox() -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        std::env::temp_dir().join(format!("eva-guest-{}-{}", std::process::id(), nanos))
+    }
+}
+
+impl Drop for GuestMode {
+    fn drop(&mut self) {
+        if let Some(state) = &self.state {
+            let _ = fs::remove_dir_all(state.executor.sandbox_dir());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_parser::CommandParser;
+    use crate::offline;
+    use crate::session::Role;
+
+    #[test]
+    fn test_parse_guest_command() {
+        assert_eq!(parse_guest_command("EVA, guest mode"), Some(GuestCommand::Enter));
+        assert_eq!(parse_guest_command("ativar modo visitante"), Some(GuestCommand::Enter));
+        assert_eq!(
+            parse_guest_command("Exit guest mode, blue harbor!"),
+            Some(GuestCommand::Exit { passphrase: "blue harbor".to_string() })
+        );
+        assert_eq!(parse_guest_command("what's the weather"), None);
+    }
+
+    #[test]
+    fn test_enter_and_exit_restore_session() {
+        let mut guest = GuestMode::new(Some("Blue Harbor".to_string()));
+        let mut session = ConversationSession::new();
+        session.add_turn(Role::User, "owner question".to_string());
+        let owner_id = session.session_id().to_string();
+
+        guest.enter(&mut session, None).unwrap();
+        assert!(guest.is_active());
+        assert!(!guest.allows_persistence());
+        assert_eq!(guest.persona(), Some(NEUTRAL_PERSONA));
+        assert_eq!(session.turn_count(), 0);
+        assert!(guest.enter(&mut session, None).is_err());
+
+        session.add_turn(Role::User, "guest question".to_string());
+        session.add_turn(Role::Assistant, "guest answer".to_string());
+
+        assert!(guest.exit_with_passphrase("red harbor", &mut session, None).is_err());
+        assert!(guest.is_active());
+
+        let summary = guest.exit_with_passphrase("blue harbor", &mut session, None).unwrap();
+        assert_eq!(summary.discarded_turns, 2);
+        assert!(!guest.is_active());
+        assert_eq!(session.session_id(), owner_id);
+        assert_eq!(session.turn_count(), 1);
+        assert!(!session.get_context().contains("guest question"));
+    }
+
+    #[test]
+    fn test_tui_exit_needs_confirmation() {
+        let mut guest = GuestMode::new(None);
+        let mut session = ConversationSession::new();
+        guest.enter(&mut session, None).unwrap();
+
+        // No passphrase configured: voice exit is refused
+        assert!(guest.exit_with_passphrase("anything", &mut session, None).is_err());
+
+        guest.request_exit().unwrap();
+        assert!(guest.exit_pending());
+        assert!(guest.confirm_exit(false, &mut session, None).unwrap().is_none());
+        assert!(guest.is_active());
+
+        guest.request_exit().unwrap();
+        assert!(guest.confirm_exit(true, &mut session, None).unwrap().is_some());
+        assert!(!guest.is_active());
+        assert!(guest.confirm_exit(true, &mut session, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_only_safe_intents_in_guest_sandbox() {
+        let parser = CommandParser::new();
+        let mut guest = GuestMode::new(None);
+        let mut session = ConversationSession::new();
+        guest.enter(&mut session, None).unwrap();
+
+        let sandbox = guest.sandbox_dir().unwrap().to_path_buf();
+        guest.execute(parser.parse("create a file called notes.txt").unwrap()).await.unwrap();
+        assert!(sandbox.join("notes.txt").exists());
+
+        assert!(guest.execute(parser.parse("delete file notes.txt").unwrap()).await.is_err());
+        assert!(guest.execute(parser.parse("start firefox").unwrap()).await.is_err());
+        assert!(sandbox.join("notes.txt").exists());
+
+        guest.request_exit().unwrap();
+        guest.confirm_exit(true, &mut session, None).unwrap();
+        assert!(!sandbox.exists());
+    }
+
+    #[test]
+    fn test_guest_turns_to_owner_data_are_refused() {
+        let parser = CommandParser::new();
+        let mut guest = GuestMode::new(None);
+        let mut session = ConversationSession::new();
+
+        let owner_data = [
+            "run the morning macro",
+            "search my screen for invoice",
+            "resume recording",
+            "call me Mallory",
+            "remember that my pin is 1234",
+            "set a timer for 5 minutes",
+            "list files and then run the morning macro",
+        ];
+        let allowed = ["list files", "volume down", "what can you do", "tell me a joke"];
+
+        // The owner may do all of it
+        for utterance in owner_data.iter().chain(&allowed) {
+            assert_eq!(guest.admit(&offline::route(&parser, utterance)), Ok(()), "{}", utterance);
+        }
+
+        guest.enter(&mut session, None).unwrap();
+        for utterance in owner_data {
+            let route = offline::route(&parser, utterance);
+            assert!(!matches!(route, TurnRoute::Model | TurnRoute::Command(_)), "{} routed to {:?}", utterance, route);
+            assert!(guest.admit(&route).is_err(), "{} was let through", utterance);
+        }
+        for utterance in allowed {
+            assert_eq!(guest.admit(&offline::route(&parser, utterance)), Ok(()), "{}", utterance);
+        }
+    }
+}
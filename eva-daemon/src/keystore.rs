@@ -0,0 +1,199 @@
+//! Encryption keys for what EVA stores on disk
+//!
+//! Each purpose (conversation sessions, Time Machine captures) gets its own
+//! random 256-bit key the first time it is needed. With the `os-keyring`
+//! feature the key is kept in the OS keyring (Secret Service, Keychain,
+//! Credential Manager); without it, or when the keyring won't take the key,
+//! it is a base64 file under `keys/` in the data directory, readable only by
+//! the owner. A keyring that can't be read (locked, not running) is an
+//! error: making a new key then would orphan everything encrypted with the
+//! one it holds.
+
+use aes_gcm::aead::{KeyInit, OsRng};
+use aes_gcm::Aes256Gcm;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+/// Key for saved conversation sessions
+pub const SESSION: &str = "session";
+/// Key for Time Machine screenshots
+pub const TIMEMACHINE: &str = "timemachine";
+
+/// Service name of EVA's entries in the OS keyring
+#[cfg(feature = "os-keyring")]
+const KEYRING_SERVICE: &str = "eva-daemon";
+
+pub type Key = [u8; 32];
+type KeyResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Where keys are kept
+pub struct KeyStore {
+    dir: PathBuf,
+    keyring: bool,
+}
+
+impl KeyStore {
+    /// Keys in the OS keyring (if built in) and under `keys/` in the data directory
+    pub fn open() -> KeyResult<Self> {
+        let dir = crate::paths::keys_dir().map_err(|e| e.to_string())?;
+        Ok(Self { dir, keyring: cfg!(feature = "os-keyring") })
+    }
+
+    /// Keys only as files in `dir`
+    pub fn in_dir(dir: PathBuf) -> Self {
+        Self { dir, keyring: false }
+    }
+
+    /// The key for `purpose`, generated and stored the first time
+    pub fn get_or_create(&self, purpose: &str) -> KeyResult<Key> {
+        if let Some(key) = self.read(purpose)? {
+            return Ok(key);
+        }
+        let key: Key = Aes256Gcm::generate_key(&mut OsRng).into();
+        self.write(purpose, &key)?;
+        Ok(key)
+    }
+
+    fn read(&self, purpose: &str) -> KeyResult<Option<Key>> {
+        if self.keyring {
+            if let Some(encoded) = keyring_get(purpose)? {
+                return decode(&encoded).map(Some);
+            }
+        }
+        let path = self.file(purpose);
+        if !path.exists() {
+            return Ok(None);
+        }
+        // A damaged key file is an error, never a reason to make a new key
+        decode(&fs::read_to_string(&path)?)
+            .map(Some)
+            .map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    fn write(&self, purpose: &str, key: &Key) -> KeyResult<()> {
+        let encoded = BASE64.encode(key);
+        if self.keyring && keyring_set(purpose, &encoded) {
+            return Ok(());
+        }
+
+        crate::paths::ensure_private_dir(&self.dir)?;
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(self.file(purpose))?.write_all(encoded.as_bytes())?;
+        Ok(())
+    }
+
+    fn file(&self, purpose: &str) -> PathBuf {
+        self.dir.join(format!("{}.key", purpose))
+    }
+}
+
+fn decode(encoded: &str) -> KeyResult<Key> {
+    let bytes = BASE64.decode(encoded.trim())?;
+    Key::try_from(bytes.as_slice()).map_err(|_| format!("key is {} bytes, expected 32", bytes.len()).into())
+}
+
+/// Stored key, `None` only when the keyring has no entry for it
+#[cfg(feature = "os-keyring")]
+fn keyring_get(purpose: &str) -> KeyResult<Option<String>> {
+    match keyring::Entry::new(KEYRING_SERVICE, purpose).and_then(|entry| entry.get_password()) {
+        Ok(encoded) => Ok(Some(encoded)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("OS keyring unavailable, not creating a new {} key: {}", purpose, e).into()),
+    }
+}
+
+#[cfg(not(feature = "os-keyring"))]
+fn keyring_get(_purpose: &str) -> KeyResult<Option<String>> {
+    Ok(None)
+}
+
+/// Whether the keyring took the key
+#[cfg(feature = "os-keyring")]
+fn keyring_set(purpose: &str, encoded: &str) -> bool {
+    match keyring::Entry::new(KEYRING_SERVICE, purpose).and_then(|entry| entry.set_password(encoded)) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("[Keystore] OS keyring unavailable ({}), storing the {} key in a file", e, purpose);
+            false
+        }
+    }
+}
+
+#[cfg(not(feature = "os-keyring"))]
+fn keyring_set(_purpose: &str, _encoded: &str) -> bool {
+    false
+}
+
+/// The key for `purpose` from the user's key store, cached for the process
+pub fn get_or_create_key(purpose: &str) -> KeyResult<Key> {
+    static KEYS: OnceLock<Mutex<HashMap<String, Key>>> = OnceLock::new();
+    let mut keys = KEYS.get_or_init(Default::default).lock().map_err(|_| "key cache poisoned")?;
+    if let Some(key) = keys.get(purpose) {
+        return Ok(*key);
+    }
+    let key = default_store()?.get_or_create(purpose)?;
+    keys.insert(purpose.to_string(), key);
+    Ok(key)
+}
+
+fn default_store() -> KeyResult<KeyStore> {
+    // Tests never touch the user's keys
+    if cfg!(test) {
+        return Ok(KeyStore::in_dir(std::env::temp_dir().join(format!("eva_test_keys_{}", std::process::id()))));
+    }
+    KeyStore::open()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_are_random_stable_and_per_purpose() {
+        let dir = std::env::temp_dir().join(format!("eva_keystore_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = KeyStore::in_dir(dir.clone());
+
+        let session = store.get_or_create(SESSION).unwrap();
+        assert_ne!(session, [0u8; 32]);
+        assert_eq!(store.get_or_create(SESSION).unwrap(), session);
+        assert_ne!(store.get_or_create(TIMEMACHINE).unwrap(), session);
+
+        // Survives a restart
+        assert_eq!(KeyStore::in_dir(dir.clone()).get_or_create(SESSION).unwrap(), session);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.join("session.key")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_damaged_key_file_is_not_replaced() {
+        let dir = std::env::temp_dir().join(format!("eva_keystore_damaged_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("session.key"), "c2hvcnQ=").unwrap();
+
+        let err = KeyStore::in_dir(dir.clone()).get_or_create(SESSION).unwrap_err();
+        assert!(err.to_string().contains("expected 32"), "{}", err);
+        assert_eq!(fs::read_to_string(dir.join("session.key")).unwrap(), "c2hvcnQ=");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,205 @@
+//! Per-turn language detection and response language switching
+//!
+//! Detects the language of each user transcript with a cheap stopword and
+//! character n-gram classifier, and decides whether the reply should
+//! switch away from the profile language for that turn.
+
+use crate::stt::Language;
+use crate::user_profile::ResponseLanguageMode;
+use std::time::{Duration, Instant};
+
+/// How long the offline STT stays on a switched language after a turn
+const FOLLOW_UP_WINDOW: Duration = Duration::from_secs(30);
+
+/// Minimum score margin before trusting a detection
+const MIN_MARGIN: f32 = 1.0;
+
+/// Frequent function words per language
+const EN_STOPWORDS: &[&str] = &[
+    "the", "is", "are", "what", "how", "you", "it", "and", "to", "of", "in", "my",
+    "please", "can", "i", "me", "this", "that", "with", "for", "time", "do", "does",
+];
+
+const PT_STOPWORDS: &[&str] = &[
+    "o", "a", "os", "as", "é", "que", "de", "do", "da", "em", "um", "uma", "você",
+    "eu", "meu", "minha", "para", "por", "com", "não", "como", "qual", "quando",
+    "isso", "está", "são", "me", "favor", "hoje",
+];
+
+/// Character trigrams that are strongly language specific
+const EN_NGRAMS: &[&str] = &["th", "wh", "ing", "ght", "ee", "oo", "w"];
+const PT_NGRAMS: &[&str] = &["ção", "ões", "nh", "lh", "ão", "ã", "ç", "ê", "õ"];
+
+/// Detect the language of a transcript (English or Portuguese)
+///
+/// Returns `None` when the text is too short or the scores are too close
+/// to call, so callers fall back to the profile language.
+pub fn detect_language(text: &str) -> Option<Language> {
+    let lower = text.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    if words.is_empty() {
+        return None;
+    }
+
+    let mut en = 0.0f32;
+    let mut pt = 0.0f32;
+
+    for word in &words {
+        if EN_STOPWORDS.contains(word) {
+            en += 1.0;
+        }
+        if PT_STOPWORDS.contains(word) {
+            pt += 1.0;
+        }
+    }
+
+    for ngram in EN_NGRAMS {
+        en += lower.matches(ngram).count() as f32 * 0.5;
+    }
+    for ngram in PT_NGRAMS {
+        pt += lower.matches(ngram).count() as f32 * 0.5;
+    }
+
+    if en - pt >= MIN_MARGIN {
+        Some(Language::EnglishUS)
+    } else if pt - en >= MIN_MARGIN {
+        Some(Language::PortugueseBR)
+    } else {
+        None
+    }
+}
+
+/// Build the per-turn directive asking Gemini to answer in a given language
+pub fn build_directive(language: Language) -> String {
+    format!(
+        "For this turn only, answer in {} ({}), regardless of the language of previous turns.",
+        language.name(),
+        language.locale()
+    )
+}
+
+/// Language decision for one user turn
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurnLanguage {
+    /// Language detected in the transcript, if confident
+    pub detected: Option<Language>,
+    /// Language EVA should answer in
+    pub response: Language,
+    /// Directive to add to the Gemini context (only when switching)
+    pub directive: Option<String>,
+}
+
+/// Tracks the profile language and per-turn switches
+pub struct LanguageSwitcher {
+    profile_language: Language,
+    mode: ResponseLanguageMode,
+    follow_up: Option<(Language, Instant)>,
+}
+
+impl LanguageSwitcher {
+    /// Create a switcher from the profile language tag and reply mode
+    pub fn new(profile_language: &str, mode: ResponseLanguageMode) -> Self {
+        Self {
+            profile_language: Language::from_tag(profile_language).unwrap_or(Language::EnglishUS),
+            mode,
+            follow_up: None,
+        }
+    }
+
+    /// Analyze a user transcript and decide the reply language
+    pub fn observe(&mut self, transcript: &str) -> TurnLanguage {
+        let detected = detect_language(transcript);
+
+        let response = match &self.mode {
+            ResponseLanguageMode::Mirror => detected.unwrap_or(self.profile_language),
+            ResponseLanguageMode::Always(tag) => Language::from_tag(tag).unwrap_or(self.profile_language),
+        };
+
+        let directive = if response != self.profile_language {
+            Some(build_directive(response))
+        } else {
+            None
+        };
+
+        // Keep listening in the user's language for the follow-up window
+        self.follow_up = match detected {
+            Some(lang) if lang != self.profile_language => Some((lang, Instant::now())),
+            _ => None,
+        };
+
+        TurnLanguage { detected, response, directive }
+    }
+
+    /// Language the offline STT model should use right now
+    pub fn stt_language(&self) -> Language {
+        match self.follow_up {
+            Some((lang, since)) if since.elapsed() < FOLLOW_UP_WINDOW => lang,
+            _ => self.profile_language,
+        }
+    }
+
+    /// Language whose lexicon emotion detection should use for this turn
+    pub fn emotion_language(&self, turn: &TurnLanguage) -> Language {
+        turn.detected.unwrap_or(self.profile_language)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_english() {
+        assert_eq!(detect_language("What is the weather like today?"), Some(Language::EnglishUS));
+        assert_eq!(detect_language("Can you list my files please"), Some(Language::EnglishUS));
+    }
+
+    #[test]
+    fn test_detect_portuguese() {
+        assert_eq!(detect_language("Qual é a previsão do tempo para hoje?"), Some(Language::PortugueseBR));
+        assert_eq!(detect_language("Você pode abrir o meu arquivo, por favor?"), Some(Language::PortugueseBR));
+    }
+
+    #[test]
+    fn test_detect_too_short() {
+        assert_eq!(detect_language(""), None);
+        assert_eq!(detect_language("ok"), None);
+    }
+
+    #[test]
+    fn test_mirror_mode_mixed_transcripts() {
+        let mut switcher = LanguageSwitcher::new("pt-BR", ResponseLanguageMode::Mirror);
+
+        let turn = switcher.observe("What time is it in Tokyo?");
+        assert_eq!(turn.detected, Some(Language::EnglishUS));
+        assert_eq!(turn.response, Language::EnglishUS);
+        assert!(turn.directive.as_deref().unwrap().contains("English"));
+        assert_eq!(switcher.stt_language(), Language::EnglishUS);
+        assert_eq!(switcher.emotion_language(&turn), Language::EnglishUS);
+
+        let turn = switcher.observe("E agora, que horas são em Lisboa?");
+        assert_eq!(turn.response, Language::PortugueseBR);
+        assert_eq!(turn.directive, None);
+        assert_eq!(switcher.stt_language(), Language::PortugueseBR);
+    }
+
+    #[test]
+    fn test_always_mode() {
+        let mut switcher = LanguageSwitcher::new("pt-BR", ResponseLanguageMode::Always("en-US".to_string()));
+        let turn = switcher.observe("Qual é o meu endereço IP?");
+        assert_eq!(turn.detected, Some(Language::PortugueseBR));
+        assert_eq!(turn.response, Language::EnglishUS);
+        assert!(turn.directive.is_some());
+    }
+
+    #[test]
+    fn test_directive_construction() {
+        let directive = build_directive(Language::PortugueseBR);
+        assert!(directive.contains("Brazilian Portuguese"));
+        assert!(directive.contains("pt-BR"));
+    }
+}
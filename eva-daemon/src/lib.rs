@@ -0,0 +1,59 @@
+//! EVA daemon library
+//!
+//! Everything the `eva-daemon` binary is built from. The binary owns the
+//! main loop; the modules live here so the integration tests under `tests/`
+//! can drive the same routing the loop does.
+
+pub mod tls;
+pub mod websocket;
+pub mod gemini;
+pub mod eva_mind;
+pub mod audio;
+pub mod listener;
+pub mod wake_word;
+pub mod vad;
+pub mod audio_player;
+pub mod audio_processor;
+pub mod audio_frontend;
+pub mod resample;
+pub mod session;
+pub mod command_parser;
+pub mod command_executor;
+pub mod command_history;
+pub mod user_profile;
+pub mod custom_commands;
+pub mod macros;
+pub mod emotion;
+pub mod status_indicator;
+pub mod statistics;
+pub mod animations;
+pub mod terminal_ui;
+pub mod timemachine;
+pub mod logging;
+pub mod stt;
+pub mod offline;
+pub mod tools;
+pub mod language;
+pub mod webhooks;
+pub mod guest_mode;
+pub mod timers;
+pub mod capabilities;
+pub mod errors;
+pub mod error_speech;
+pub mod paths;
+pub mod keystore;
+pub mod accessibility;
+pub mod suggestions;
+pub mod clock;
+pub mod network;
+pub mod desktop_input;
+pub mod calibration;
+pub mod shutdown;
+pub mod summary;
+pub mod control;
+pub mod eva_scheme;
+pub mod answers;
+pub mod child_processes;
+pub mod metrics;
+pub mod tts;
+pub mod turn;
@@ -0,0 +1,505 @@
+//! Background microphone listener
+//!
+//! One task owns the microphone. It runs the wake word detector on every
+//! chunk, captures the utterance that follows and ends it when VAD hears
+//! the user stop. While waiting for the wake word, the detector and VAD
+//! share one `AudioFrontend` analysis of each chunk. The main loop only
+//! reads `ListenerEvent`s, so "Hey EVA" is still heard while a turn is
+//! being processed.
+//!
+//! While EVA speaks, detection pauses (her own voice must not wake her) and
+//! the processed microphone audio is forwarded instead, for barge-in
+//! detection.
+
+use crate::audio::{self, CaptureSource, RingBuffer};
+use crate::audio_frontend::AudioFrontend;
+use crate::audio_processor::{DspChain, StageMetrics};
+use crate::resample::Decimator;
+use crate::vad::{VadEnergy, VAD};
+use crate::wake_word::WakeWordDetector;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+/// Events kept for a consumer that falls behind: ~6s of audio chunks
+pub const QUEUE_CAPACITY: usize = 64;
+/// Longest utterance before it is cut off
+pub const MAX_UTTERANCE_SECS: u32 = 30;
+
+/// How often a waiting `recv` checks that the task is still running
+const RECV_POLL: Duration = Duration::from_millis(100);
+/// Pause before retrying a microphone that failed
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListenerEvent {
+    /// The wake word was heard; `resumed` turns were started by
+    /// `listen_now` instead
+    Wake { resumed: bool },
+    /// Processed 16 kHz audio: the utterance being captured, or the room
+    /// while EVA speaks
+    Audio(Vec<f32>),
+    /// The utterance is over; `timed_out` if it hit `MAX_UTTERANCE_SECS`
+    /// before the user stopped
+    EndOfSpeech { energy: VadEnergy, timed_out: bool },
+    /// The microphone failed; capture retries
+    Error(String),
+}
+
+/// Bounded event queue that drops the oldest audio when full
+///
+/// Wake and end-of-speech markers are never dropped while audio is
+/// waiting: losing one would leave the consumer waiting on a turn that
+/// already ended.
+struct Queue {
+    events: Mutex<VecDeque<ListenerEvent>>,
+    capacity: usize,
+    ready: Notify,
+    dropped: AtomicU64,
+}
+
+impl Queue {
+    fn new(capacity: usize) -> Self {
+        Self { events: Mutex::new(VecDeque::new()), capacity: capacity.max(1), ready: Notify::new(), dropped: AtomicU64::new(0) }
+    }
+
+    fn push(&self, event: ListenerEvent) {
+        if let Ok(mut events) = self.events.lock() {
+            if events.len() >= self.capacity {
+                let oldest = events.iter().position(|e| matches!(e, ListenerEvent::Audio(_))).unwrap_or(0);
+                events.remove(oldest);
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            events.push_back(event);
+        }
+        self.ready.notify_one();
+    }
+
+    fn pop(&self) -> Option<ListenerEvent> {
+        self.events.lock().ok()?.pop_front()
+    }
+}
+
+/// Where capture stands between chunks
+#[derive(Debug, Clone, PartialEq)]
+enum Mode {
+    Idle,
+    /// Capturing a turn; `lead_in` goes out ahead of the first chunk
+    Utterance { lead_in: Vec<f32>, samples: usize },
+    Speaking,
+}
+
+/// Wake word, VAD and capture DSP, fed one 16 kHz chunk at a time
+struct Pipeline {
+    wake_word: WakeWordDetector,
+    vad: VAD,
+    /// Features of the raw chunks, for the wake word and VAD alike
+    frontend: AudioFrontend,
+    capture_chain: DspChain,
+    /// The last moments before the wake word fired
+    pre_roll: RingBuffer,
+    mode: Mode,
+    telemetry: Arc<Mutex<Telemetry>>,
+}
+
+/// Levels of the turn being captured, for the statistics panel
+#[derive(Debug, Clone, Default)]
+struct Telemetry {
+    dsp: Vec<StageMetrics>,
+    vad: VadEnergy,
+}
+
+impl Pipeline {
+    fn feed(&mut self, mut chunk: Vec<f32>, speaking: bool, listen_now: bool, events: &mut Vec<ListenerEvent>) {
+        if matches!(self.mode, Mode::Utterance { .. }) {
+            self.capture(chunk, events);
+            return;
+        }
+
+        if !speaking && self.mode == Mode::Speaking {
+            // Her voice is in the detector's history and the pre-roll
+            self.wake_word.reset();
+            self.frontend.reset();
+            self.pre_roll.clear();
+            self.capture_chain.reset();
+            self.mode = Mode::Idle;
+        }
+
+        if listen_now {
+            // The user is already talking: this chunk is part of the turn
+            self.start(true, events);
+            self.capture(chunk, events);
+        } else if speaking {
+            self.capture_chain.process(&mut chunk);
+            events.push(ListenerEvent::Audio(chunk));
+            self.mode = Mode::Speaking;
+        } else {
+            self.pre_roll.write(&chunk);
+            let features = self.frontend.analyze(&chunk);
+            if self.wake_word.detect_frame(&features) {
+                self.start(false, events);
+            } else {
+                // Nobody is talking to EVA: learn the room's background noise
+                self.vad.observe_background_frame(&features);
+            }
+        }
+    }
+
+    fn start(&mut self, resumed: bool, events: &mut Vec<ListenerEvent>) {
+        self.wake_word.reset();
+        self.frontend.reset();
+        self.vad.reset();
+        self.capture_chain.reset();
+        // The request may have started before detection caught up; after
+        // a barge-in the buffer predates the reply and is stale
+        let mut lead_in = self.pre_roll.drain();
+        if resumed {
+            lead_in.clear();
+        }
+        self.capture_chain.process(&mut lead_in);
+        events.push(ListenerEvent::Wake { resumed });
+        self.mode = Mode::Utterance { lead_in, samples: 0 };
+    }
+
+    fn capture(&mut self, mut chunk: Vec<f32>, events: &mut Vec<ListenerEvent>) {
+        let Mode::Utterance { lead_in, samples } = &mut self.mode else {
+            return;
+        };
+        self.capture_chain.process(&mut chunk);
+        if !lead_in.is_empty() {
+            lead_in.append(&mut chunk);
+            chunk = std::mem::take(lead_in);
+        }
+        *samples += chunk.len();
+        let timed_out = *samples > (audio::SAMPLE_RATE * MAX_UTTERANCE_SECS) as usize;
+
+        // End the turn once the user has finished speaking
+        self.vad.is_speech(&chunk);
+        let ended = self.vad.is_end_of_utterance();
+        let energy = self.vad.energy_snapshot();
+        if let Ok(mut telemetry) = self.telemetry.lock() {
+            *telemetry = Telemetry { dsp: self.capture_chain.metrics(), vad: energy };
+        }
+        events.push(ListenerEvent::Audio(chunk));
+
+        if ended || timed_out {
+            events.push(ListenerEvent::EndOfSpeech { energy, timed_out: !ended });
+            self.vad.reset();
+            self.capture_chain.reset();
+            self.mode = Mode::Idle;
+        }
+    }
+}
+
+/// State shared between the task and its handle
+struct Shared {
+    queue: Queue,
+    speaking: AtomicBool,
+    listen_now: AtomicBool,
+    /// New wake word sensitivity, picked up before the next chunk
+    sensitivity: Mutex<Option<f32>>,
+    /// Capture stage toggles, applied before the next chunk
+    stages: Mutex<Vec<(String, bool)>>,
+}
+
+/// Handle to the listening task; dropping it stops the task
+pub struct Listener {
+    shared: Arc<Shared>,
+    telemetry: Arc<Mutex<Telemetry>>,
+    task: JoinHandle<()>,
+}
+
+impl Listener {
+    /// Start listening on `source` (must be called inside a tokio runtime)
+    pub fn spawn(
+        source: CaptureSource,
+        downsampler: Decimator,
+        wake_word: WakeWordDetector,
+        vad: VAD,
+        capture_chain: DspChain,
+        pre_roll_ms: u32,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Queue::new(QUEUE_CAPACITY),
+            speaking: AtomicBool::new(false),
+            listen_now: AtomicBool::new(false),
+            sensitivity: Mutex::new(None),
+            stages: Mutex::new(Vec::new()),
+        });
+        let telemetry = Arc::new(Mutex::new(Telemetry::default()));
+        let pipeline = Pipeline {
+            wake_word,
+            vad,
+            frontend: AudioFrontend::new(audio::SAMPLE_RATE),
+            capture_chain,
+            pre_roll: RingBuffer::new((audio::SAMPLE_RATE as u64 * pre_roll_ms as u64 / 1000) as usize),
+            mode: Mode::Idle,
+            telemetry: Arc::clone(&telemetry),
+        };
+        let task = tokio::spawn(listen_loop(source, downsampler, pipeline, Arc::clone(&shared)));
+        Self { shared, telemetry, task }
+    }
+
+    /// Next event, waiting for one; `None` once the task has stopped
+    pub async fn recv(&self) -> Option<ListenerEvent> {
+        loop {
+            if let Some(event) = self.shared.queue.pop() {
+                return Some(event);
+            }
+            if self.task.is_finished() {
+                return None;
+            }
+            let _ = tokio::time::timeout(RECV_POLL, self.shared.queue.ready.notified()).await;
+        }
+    }
+
+    /// Next event, or `None` if there is none within `limit`
+    pub async fn recv_timeout(&self, limit: Duration) -> Option<ListenerEvent> {
+        tokio::time::timeout(limit, self.recv()).await.ok().flatten()
+    }
+
+    /// Pause wake word detection while EVA speaks, forwarding the
+    /// microphone for barge-in instead
+    pub fn set_speaking(&self, speaking: bool) {
+        self.shared.speaking.store(speaking, Ordering::Relaxed);
+    }
+
+    /// Start a turn on the next chunk without waiting for the wake word
+    /// (the user talked over EVA)
+    pub fn listen_now(&self) {
+        self.shared.speaking.store(false, Ordering::Relaxed);
+        self.shared.listen_now.store(true, Ordering::Relaxed);
+    }
+
+    /// Change the wake word sensitivity (0.0 to 1.0) without restarting
+    pub fn set_wake_sensitivity(&self, sensitivity: f32) {
+        *self.shared.sensitivity.lock().unwrap_or_else(|e| e.into_inner()) = Some(sensitivity);
+    }
+
+    /// Turn a capture DSP stage on or off without restarting
+    pub fn set_dsp_stage(&self, name: &str, enabled: bool) {
+        self.shared.stages.lock().unwrap_or_else(|e| e.into_inner()).push((name.to_string(), enabled));
+    }
+
+    /// Events dropped because the consumer fell behind
+    pub fn dropped(&self) -> u64 {
+        self.shared.queue.dropped.load(Ordering::Relaxed)
+    }
+
+    /// DSP stage metrics of the turn being captured
+    pub fn dsp_metrics(&self) -> Vec<StageMetrics> {
+        self.telemetry.lock().map(|t| t.dsp.clone()).unwrap_or_default()
+    }
+
+    /// VAD levels of the turn being captured
+    pub fn vad_energy(&self) -> VadEnergy {
+        self.telemetry.lock().map(|t| t.vad).unwrap_or_default()
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn listen_loop(mut source: CaptureSource, mut downsampler: Decimator, mut pipeline: Pipeline, shared: Arc<Shared>) {
+    let mut events = Vec::new();
+    loop {
+        let chunk = match source.capture_chunk().await {
+            Ok(c) => downsampler.process(&c),
+            Err(e) => {
+                shared.queue.push(ListenerEvent::Error(e.to_string()));
+                tokio::time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+        };
+        if let Some(sensitivity) = shared.sensitivity.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            pipeline.wake_word.set_sensitivity(sensitivity);
+        }
+        for (name, enabled) in shared.stages.lock().unwrap_or_else(|e| e.into_inner()).drain(..) {
+            pipeline.capture_chain.set_enabled(&name, enabled);
+        }
+        let listen_now = shared.listen_now.swap(false, Ordering::Relaxed);
+        pipeline.feed(chunk, shared.speaking.load(Ordering::Relaxed), listen_now, &mut events);
+        for event in events.drain(..) {
+            shared.queue.push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_processor::{AudioProcessor, AudioProcessorConfig};
+    use crate::vad::VadConfig;
+    use crate::wake_word::WakeWordConfig;
+
+    /// Syllable-like tone bursts at the given frequencies (cycles/sample)
+    fn syllables(tones: &[f32]) -> Vec<f32> {
+        let mut out = vec![0.0; 1600];
+        for &freq in tones {
+            out.extend((0..3200).map(|i| {
+                let envelope = (i.min(3200 - i) as f32 / 400.0).min(1.0);
+                0.5 * envelope * (2.0 * std::f32::consts::PI * freq * i as f32).sin()
+            }));
+            out.extend(vec![0.0; 800]);
+        }
+        out.extend(vec![0.0; 1600]);
+        out
+    }
+
+    const PHRASE: [f32; 4] = [0.05, 0.2, 0.12, 0.3];
+
+    /// A steady tone at `freq` (cycles/sample)
+    fn tone(freq: f32, secs: f32) -> Vec<f32> {
+        let len = (audio::SAMPLE_RATE as f32 * secs) as usize;
+        (0..len).map(|i| 0.3 * (2.0 * std::f32::consts::PI * freq * i as f32).sin()).collect()
+    }
+
+    fn pipeline() -> Pipeline {
+        let mut wake_word =
+            WakeWordDetector::with_config(WakeWordConfig { phrase: "Ok Computer".to_string(), cooldown_ms: 0, ..WakeWordConfig::default() });
+        wake_word.set_sensitivity(0.6);
+        wake_word.train_from_samples(&[syllables(&PHRASE), syllables(&PHRASE), syllables(&PHRASE)]).unwrap();
+        Pipeline {
+            wake_word,
+            vad: VAD::with_config(VadConfig::default()),
+            frontend: AudioFrontend::new(audio::SAMPLE_RATE),
+            capture_chain: AudioProcessor::from_config(&AudioProcessorConfig::default()).capture,
+            pre_roll: RingBuffer::new(audio::SAMPLE_RATE as usize * audio::PRE_ROLL_MS as usize / 1000),
+            mode: Mode::Idle,
+            telemetry: Arc::default(),
+        }
+    }
+
+    /// Feed `samples` in 100ms chunks, collecting the events
+    fn feed(pipeline: &mut Pipeline, samples: &[f32], speaking: bool) -> Vec<ListenerEvent> {
+        let mut events = Vec::new();
+        for chunk in samples.chunks(audio::CHUNK_SIZE) {
+            pipeline.feed(chunk.to_vec(), speaking, false, &mut events);
+        }
+        events
+    }
+
+    fn audio_samples(events: &[ListenerEvent]) -> usize {
+        events.iter().map(|e| if let ListenerEvent::Audio(a) = e { a.len() } else { 0 }).sum()
+    }
+
+    #[test]
+    fn test_wake_then_utterance_then_end_of_speech() {
+        let mut pipeline = pipeline();
+        assert!(feed(&mut pipeline, &vec![0.0; 16_000], false).is_empty());
+
+        let events = feed(&mut pipeline, &syllables(&PHRASE), false);
+        assert_eq!(events.first(), Some(&ListenerEvent::Wake { resumed: false }), "{:?}", events.first());
+
+        // A pause, the request, then the user falls quiet
+        let mut request = vec![0.0; 8_000];
+        request.extend(tone(0.15, 1.0));
+        request.extend(vec![0.0; 32_000]);
+        let mut events = feed(&mut pipeline, &request, false);
+        let Some(ListenerEvent::EndOfSpeech { timed_out: false, .. }) = events.pop() else {
+            panic!("no end of speech: {:?}", events.last());
+        };
+        assert!(events.iter().all(|e| matches!(e, ListenerEvent::Audio(_))));
+        // Every chunk until the end of speech, plus the pre-roll
+        assert!(audio_samples(&events) > 16_000, "{}", audio_samples(&events));
+
+        // Back to waiting for the wake word
+        assert!(feed(&mut pipeline, &vec![0.0; 16_000], false).is_empty());
+    }
+
+    #[test]
+    fn test_no_detection_while_speaking() {
+        let mut pipeline = pipeline();
+
+        // EVA saying her own wake word only goes out for barge-in detection
+        let phrase = syllables(&PHRASE);
+        let events = feed(&mut pipeline, &phrase, true);
+        assert!(events.iter().all(|e| matches!(e, ListenerEvent::Audio(_))));
+        assert_eq!(audio_samples(&events), phrase.len());
+
+        // Detection picks up again once she is done
+        assert!(feed(&mut pipeline, &vec![0.0; 16_000], false).is_empty());
+        assert!(feed(&mut pipeline, &phrase, false).contains(&ListenerEvent::Wake { resumed: false }));
+    }
+
+    #[test]
+    fn test_listen_now_starts_a_turn() {
+        let mut pipeline = pipeline();
+        feed(&mut pipeline, &vec![0.0; 8_000], true);
+
+        let mut events = Vec::new();
+        pipeline.feed(vec![0.1; audio::CHUNK_SIZE], true, true, &mut events);
+        assert_eq!(events[0], ListenerEvent::Wake { resumed: true });
+        // No stale pre-roll: just the chunk that was heard
+        assert_eq!(audio_samples(&events), audio::CHUNK_SIZE);
+
+        // A silent turn still ends
+        let events = feed(&mut pipeline, &vec![0.0; audio::SAMPLE_RATE as usize * 6], false);
+        assert!(matches!(events.last(), Some(ListenerEvent::EndOfSpeech { .. })), "{:?}", events.last());
+    }
+
+    #[test]
+    fn test_utterance_capped() {
+        let mut pipeline = pipeline();
+        let mut events = Vec::new();
+        pipeline.feed(vec![0.0; audio::CHUNK_SIZE], false, true, &mut events);
+
+        // Steady speech never ends on its own
+        let events = feed(&mut pipeline, &tone(0.15, 31.0), false);
+        assert!(matches!(events.last(), Some(ListenerEvent::EndOfSpeech { timed_out: true, .. })), "{:?}", events.last());
+        assert_eq!(events.iter().filter(|e| matches!(e, ListenerEvent::EndOfSpeech { .. })).count(), 1);
+    }
+
+    #[test]
+    fn test_queue_drops_oldest_audio() {
+        let queue = Queue::new(3);
+        queue.push(ListenerEvent::Wake { resumed: false });
+        for i in 0..4 {
+            queue.push(ListenerEvent::Audio(vec![i as f32]));
+        }
+        queue.push(ListenerEvent::EndOfSpeech { energy: VadEnergy::default(), timed_out: false });
+        assert_eq!(queue.dropped.load(Ordering::Relaxed), 3);
+
+        // The markers survive; the newest audio between them does too
+        assert_eq!(queue.pop(), Some(ListenerEvent::Wake { resumed: false }));
+        assert_eq!(queue.pop(), Some(ListenerEvent::Audio(vec![3.0])));
+        assert!(matches!(queue.pop(), Some(ListenerEvent::EndOfSpeech { .. })));
+        assert_eq!(queue.pop(), None);
+
+        // With nothing but markers, the oldest goes
+        let queue = Queue::new(2);
+        for resumed in [false, true, false] {
+            queue.push(ListenerEvent::Wake { resumed });
+        }
+        assert_eq!(queue.pop(), Some(ListenerEvent::Wake { resumed: true }));
+    }
+
+    #[tokio::test]
+    async fn test_mock_microphone_stays_quiet() {
+        let device = audio::AudioDevice::new().unwrap();
+        if !device.is_mock() {
+            return;
+        }
+        let pipeline = pipeline();
+        let listener = Listener::spawn(
+            device.capture_source().unwrap(),
+            Decimator::capture_to_pipeline(),
+            pipeline.wake_word,
+            pipeline.vad,
+            pipeline.capture_chain,
+            audio::PRE_ROLL_MS,
+        );
+        assert_eq!(listener.recv_timeout(Duration::from_millis(350)).await, None);
+
+        // Forwarded for barge-in while she speaks
+        listener.set_speaking(true);
+        let event = listener.recv_timeout(Duration::from_millis(500)).await;
+        assert!(matches!(event, Some(ListenerEvent::Audio(ref a)) if a.len() == audio::CHUNK_SIZE), "{:?}", event);
+    }
+}
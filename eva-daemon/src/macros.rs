@@ -1,3 +1,5 @@
+use crate::command_executor::CommandExecutor;
+use crate::command_parser::{CommandIntent, MacroOperation};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -17,6 +19,9 @@ pub struct VoiceMacro {
 pub struct MacroStep {
     pub command: String,
     pub delay_ms: u64,
+    /// The command to replay; `None` for steps saved as text only
+    #[serde(default)]
+    pub intent: Option<CommandIntent>,
 }
 
 /// Macro manager
@@ -29,7 +34,11 @@ pub struct MacroManager {
 impl MacroManager {
     /// Create a new macro manager
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let config_path = Self::get_config_path()?;
+        Self::load_from(Self::get_config_path()?)
+    }
+
+    /// Load macros from a specific file (missing file = no macros)
+    pub fn load_from(config_path: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
         let macros = Self::load_macros(&config_path)?;
 
         Ok(Self {
@@ -51,7 +60,7 @@ impl MacroManager {
     /// Add a step to the current recording
     pub fn add_step(&mut self, command: String, delay_ms: u64) {
         if let Some(ref mut macro_rec) = self.recording {
-            macro_rec.steps.push(MacroStep { command, delay_ms });
+            macro_rec.steps.push(MacroStep { command, delay_ms, intent: None });
         }
     }
 
@@ -96,6 +105,37 @@ impl MacroManager {
         Ok(results)
     }
 
+    /// Start or stop recording from live commands, or run a saved macro
+    ///
+    /// While recording, the executor appends every command it runs; the
+    /// finished macro is saved here.
+    pub async fn apply(&mut self, op: MacroOperation, executor: &mut CommandExecutor) -> Result<String, String> {
+        match op {
+            MacroOperation::StartRecording { name } => {
+                executor.start_macro(name.clone()).map_err(|e| e.to_string())?;
+                Ok(format!("Recording macro '{}'. Say \"stop recording the macro\" when you're done.", name))
+            }
+            MacroOperation::StopRecording => {
+                let recorded = executor.stop_macro().map_err(|e| e.to_string())?;
+                self.finish_recording(recorded)
+            }
+            MacroOperation::Run { name } => {
+                let recorded = self.macros.get(&name).cloned().ok_or_else(|| format!("Macro '{}' not found", name))?;
+                executor.run_macro(&recorded).await.map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Save a recording the executor handed back (stopped or timed out)
+    pub fn finish_recording(&mut self, recorded: VoiceMacro) -> Result<String, String> {
+        if recorded.steps.is_empty() {
+            return Err(format!("Macro '{}' has no commands, nothing was saved", recorded.name));
+        }
+        let message = format!("Saved macro '{}' with {} steps", recorded.name, recorded.steps.len());
+        self.save_macro(recorded).map_err(|e| e.to_string())?;
+        Ok(message)
+    }
+
     /// Get macro count
     pub fn count(&self) -> usize {
         self.macros.len()
@@ -143,17 +183,7 @@ impl MacroManager {
 
     /// Get config file path
     fn get_config_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
-        #[cfg(target_os = "windows")]
-        {
-            let home = std::env::var("USERPROFILE")?;
-            Ok(PathBuf::from(home).join(".eva").join("macros.json"))
-        }
-
-        #[cfg(not(target_os = "windows"))]
-        {
-            let home = std::env::var("HOME")?;
-            Ok(PathBuf::from(home).join(".eva").join("macros.json"))
-        }
+        crate::paths::data_file("macros.json")
     }
 }
 
@@ -166,6 +196,8 @@ impl Default for MacroManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::command_history::describe_intent;
+    use crate::command_parser::FileOperation;
 
     #[test]
     fn test_start_stop_recording() {
@@ -192,6 +224,7 @@ mod tests {
             steps: vec![MacroStep {
                 command: "test command".to_string(),
                 delay_ms: 100,
+                intent: None,
             }],
             created_at: SystemTime::now(),
         };
@@ -227,10 +260,12 @@ mod tests {
                 MacroStep {
                     command: "cmd1".to_string(),
                     delay_ms: 10,
+                    intent: None,
                 },
                 MacroStep {
                     command: "cmd2".to_string(),
                     delay_ms: 10,
+                    intent: None,
                 },
             ],
             created_at: SystemTime::now(),
@@ -243,4 +278,81 @@ mod tests {
         assert_eq!(commands[0], "cmd1");
         assert_eq!(commands[1], "cmd2");
     }
+
+    fn scratch(name: &str) -> (MacroManager, CommandExecutor) {
+        let dir = std::env::temp_dir().join(format!("eva_test_macros_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let executor = CommandExecutor::with_sandbox(dir.join("sandbox")).unwrap();
+        (MacroManager::load_from(dir.join("macros.json")).unwrap(), executor)
+    }
+
+    fn create(path: &str) -> CommandIntent {
+        CommandIntent::File(FileOperation::Create { path: path.to_string(), content: None })
+    }
+
+    #[tokio::test]
+    async fn test_record_and_run_from_live_commands() {
+        let (mut mgr, mut executor) = scratch("record");
+        let start = |name: &str| MacroOperation::StartRecording { name: name.to_string() };
+
+        mgr.apply(start("morning"), &mut executor).await.unwrap();
+        assert_eq!(executor.recording_macro(), Some("morning"));
+        // Nested recording is refused and leaves the first one running
+        assert!(mgr.apply(start("evening"), &mut executor).await.unwrap_err().contains("Already recording macro 'morning'"));
+
+        executor.execute(create("a.txt")).await.unwrap();
+        executor.execute(CommandIntent::Unknown).await.unwrap();
+        // Failed commands aren't recorded
+        assert!(executor.execute(CommandIntent::File(FileOperation::Read { path: "missing.txt".to_string() })).await.is_err());
+        executor.execute(create("b.txt")).await.unwrap();
+
+        assert_eq!(mgr.apply(MacroOperation::StopRecording, &mut executor).await.unwrap(), "Saved macro 'morning' with 2 steps");
+        assert!(mgr.apply(MacroOperation::StopRecording, &mut executor).await.is_err());
+
+        // Persisted with the intents, so a reload can replay it
+        let mut reloaded = MacroManager::load_from(mgr.config_path.clone()).unwrap();
+        let steps = &reloaded.get_macro("morning").unwrap().steps;
+        assert_eq!(steps[1].intent, Some(create("b.txt")));
+
+        fs::remove_file(executor.sandbox_dir().join("a.txt")).unwrap();
+        fs::remove_file(executor.sandbox_dir().join("b.txt")).unwrap();
+        let ran = reloaded.apply(MacroOperation::Run { name: "morning".to_string() }, &mut executor).await.unwrap();
+        assert_eq!(ran, "Ran macro 'morning' (2 steps):\nCreated file: a.txt\nCreated file: b.txt");
+        assert!(executor.sandbox_dir().join("b.txt").exists());
+
+        assert!(reloaded.apply(MacroOperation::Run { name: "nope".to_string() }, &mut executor).await.is_err());
+        let _ = fs::remove_dir_all(mgr.config_path.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_at_first_failure() {
+        let (mut mgr, mut executor) = scratch("failure");
+        let read = CommandIntent::File(FileOperation::Read { path: "missing.txt".to_string() });
+        let steps = [create("a.txt"), read, create("c.txt")]
+            .into_iter()
+            .map(|intent| MacroStep { command: describe_intent(&intent), delay_ms: 0, intent: Some(intent) })
+            .collect();
+        mgr.save_macro(VoiceMacro { name: "broken".to_string(), steps, created_at: SystemTime::now() }).unwrap();
+
+        let err = mgr.apply(MacroOperation::Run { name: "broken".to_string() }, &mut executor).await.unwrap_err();
+        assert_eq!(err, "Macro 'broken' stopped at step 2 of 3 (read file missing.txt): File not found: missing.txt. Completed: create file a.txt.");
+        assert!(!executor.sandbox_dir().join("c.txt").exists());
+        let _ = fs::remove_dir_all(mgr.config_path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_recording_stops_when_idle() {
+        let (mut mgr, mut executor) = scratch("idle");
+        executor.set_macro_idle_timeout(Duration::from_secs(60));
+        let t0 = std::time::Instant::now();
+
+        executor.start_macro_at("empty".to_string(), t0).unwrap();
+        assert!(executor.expire_macro(t0 + Duration::from_secs(59)).is_none());
+        let recorded = executor.expire_macro(t0 + Duration::from_secs(60)).unwrap();
+        assert_eq!(executor.recording_macro(), None);
+        // Nothing was recorded, so nothing is saved
+        assert!(mgr.finish_recording(recorded).is_err());
+        assert_eq!(mgr.count(), 0);
+        let _ = fs::remove_dir_all(mgr.config_path.parent().unwrap());
+    }
 }
@@ -1,59 +1,103 @@
-mod tls;
-mod websocket;
-mod gemini;
-mod eva_mind;
-mod audio;
-mod wake_word;
-mod vad;
-mod audio_player;
-mod session;
-mod command_parser;
-mod command_executor;
-mod user_profile;
-mod custom_commands;
-mod macros;
-mod emotion;
-mod status_indicator;
-mod statistics;
-mod animations;
-mod terminal_ui;
-mod timemachine;
-mod logging;
-mod stt;
+use eva_daemon::{
+    gemini, eva_mind, audio, listener, wake_word, vad, audio_player, audio_processor, resample, session, command_parser, command_executor, command_history, user_profile, custom_commands, macros, emotion, status_indicator, statistics, animations, terminal_ui, timemachine, offline, tools, language, webhooks, guest_mode, timers, capabilities, errors, error_speech, paths, accessibility, suggestions, clock, calibration, shutdown, summary, control, eva_scheme, answers, metrics, tts, turn,
+};
 
 use audio::AudioDevice;
-use wake_word::WakeWordDetector;
-use vad::VAD;
+use wake_word::{WakeWordDetector, WakeWordTemplate};
+use vad::{BargeInDetector, VadConfig, VAD};
+use listener::{Listener, ListenerEvent};
 use eva_mind::{EvaMindClient, EvaMindConfig, EvaMindResponse};
+use gemini::{ConnectionState, GeminiClient, GeminiConfig, ProsodyMode, SpeechSettings, StreamEvent};
 use audio_player::AudioPlayer;
-use session::{ConversationSession, Role};
-use command_parser::CommandParser;
-use command_executor::CommandExecutor;
-use user_profile::UserProfile;
-use custom_commands::CustomCommandManager;
+use audio_processor::{AudioProcessor, AudioProcessorConfig, ChainKind};
+use session::{ConversationSession, ExportFormat, Role, SessionStore, Turn};
+use command_parser::{AudioOperation, CommandIntent, CommandParser, MacroOperation, SessionOperation, TimeMachineOperation};
+use command_executor::{sequence_summary, CommandExecutor, ExecutionOutcome, ExecutionPolicy, SequenceStop};
+use command_history::{CommandHistory, HistoryAction};
+use user_profile::{ProfileChange, ProfileWatcher, SharedProfile, UserProfile};
+use eva_scheme::Change;
+use answers::AnswerRouter;
+use custom_commands::{CommandAction, CustomCommandManager, CustomOutcome};
 use macros::MacroManager;
-use emotion::EmotionDetector;
+use emotion::{Emotion, EmotionDetector};
+use language::LanguageSwitcher;
 use status_indicator::{StatusIndicator, EvaStatus};
-use statistics::Statistics;
-use terminal_ui::TerminalUI;
-use animations::Animation;
+use statistics::{AnswerSource, LatencyStage, SharedStatistics, Statistics};
+use metrics::{AudioProbe, GeminiLink, Sampler};
+use tts::SpeechFallback;
+use terminal_ui::{InputLine, TerminalUI, TextInput};
+use animations::AnimationEngine;
+use webhooks::{DailyDigest, NpuWatch, WebhookDispatcher, WebhookEvent, WebhookEventKind, WebhookSecrets, WebhooksConfig};
+use guest_mode::{GuestCommand, GuestMode};
+use timers::TimerManager;
+use capabilities::{CapabilityContext, CapabilityReport};
+use errors::{ErrorKind, EvaError};
+use error_speech::ErrorAnnouncer;
+use suggestions::{FollowUpSource, FollowUps};
+use clock::{Clock, SystemClock};
+use offline::{OfflineRecognizer, TurnRoute};
+use tools::ToolContext;
+use shutdown::{ShutdownSignal, StepFuture};
+use turn::{Intercept, Plan};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+
+/// Longest wait for the listener in one pass, so keys and timers stay responsive
+const LISTEN_POLL: std::time::Duration = std::time::Duration::from_millis(100);
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Offer to copy ~/.eva into a relocated data root (asked once, before the TUI takes over)
+    if let Err(e) = paths::offer_legacy_migration() {
+        eprintln!("[Paths] Legacy data migration failed: {}", e);
+    }
+
+    // `eva-daemon --calibrate` tunes the wake word and VAD to the microphone,
+    // `--enroll-wake-word` records the phrase for template matching; both exit
+    if std::env::args().skip(1).any(|arg| arg == "--calibrate") {
+        return calibration::run().await;
+    }
+    if std::env::args().skip(1).any(|arg| arg == "--enroll-wake-word") {
+        return calibration::enroll().await;
+    }
+
     // Initialize UI components first
     let mut status_indicator = StatusIndicator::new();
-    let mut statistics = Statistics::new();
+    // Totals from earlier runs; a broken stats file starts over
+    let (statistics, stats_error) = match Statistics::load() {
+        Ok(stats) => (stats, None),
+        Err(e) => (Statistics::new(), Some(e.to_string())),
+    };
+    let statistics: SharedStatistics = std::sync::Arc::new(std::sync::RwLock::new(statistics));
     let mut terminal_ui = TerminalUI::new()?;
+    // Colours and the cursor come back even if EVA panics
+    let _terminal_guard = scopeguard::guard_on_unwind((), |()| terminal_ui::reset_terminal());
 
     // Initial draw
     terminal_ui.add_system_message("EVA OS Starting...");
+    if let Some(e) = stats_error {
+        terminal_ui.add_system_message(&format!("⚠️  Could not load statistics, starting fresh: {}", e));
+    }
     terminal_ui.draw(&status_indicator, &statistics);
 
     // Initialize components
     terminal_ui.add_system_message("[1/13] Initializing audio device...");
     terminal_ui.draw(&status_indicator, &statistics);
-    let mut audio = AudioDevice::new()?;
-    terminal_ui.add_system_message("✅ Audio device ready");
+    // The profile loads fully at step 8; only the pinned microphone and the
+    // calibrated VAD levels are needed before that
+    let early_profile = UserProfile::load().ok();
+    let microphone = early_profile.as_ref().and_then(|p| p.microphone.clone());
+    let audio = AudioDevice::open(microphone.as_deref())?;
+    if let Some(name) = microphone.as_ref() {
+        let available = AudioDevice::list_devices();
+        if !available.contains(name) {
+            terminal_ui.add_system_message(&format!("⚠️  Microphone '{}' not found (available: {})", name, available.join(", ")));
+        }
+    }
+    if audio.is_mock() {
+        terminal_ui.add_system_message("⚠️  No audio device: running with silent mock audio");
+    } else {
+        terminal_ui.add_system_message("✅ Audio device ready");
+    }
     terminal_ui.draw(&status_indicator, &statistics);
     
 
@@ -67,15 +111,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     terminal_ui.add_system_message("[3/13] Initializing Voice Activity Detection...");
     terminal_ui.draw(&status_indicator, &statistics);
-    let mut vad = VAD::new();
-    terminal_ui.add_system_message("✅ VAD ready");
+    let vad_config = VadConfig { sample_rate: audio::SAMPLE_RATE, ..VadConfig::default() };
+    let vad_tuning = early_profile.as_ref().and_then(|p| p.vad);
+    let vad = match vad_tuning {
+        Some(tuning) => VAD::with_tuning(vad_config, tuning),
+        None => VAD::with_config(vad_config),
+    };
+    let mut barge_in = BargeInDetector::new();
+    terminal_ui.add_system_message(if vad_tuning.is_some() { "✅ VAD ready (calibrated)" } else { "✅ VAD ready" });
     terminal_ui.draw(&status_indicator, &statistics);
 
     terminal_ui.add_system_message("[4/13] Initializing audio player...");
     terminal_ui.draw(&status_indicator, &statistics);
-    let audio_device_clone = AudioDevice::new()?;
+    let audio_device_clone = AudioDevice::open(microphone.as_deref())?;
     let mut audio_player = AudioPlayer::new(audio_device_clone)?;
-    terminal_ui.add_system_message("✅ Audio player ready");
+    // CPU, memory, reply audio, Gemini and NPU state for the statistics
+    // panel, sampled in the background
+    let gemini_link = GeminiLink::default();
+    let _sampler = Sampler::for_this_system()
+        .with_probe(AudioProbe(audio_player.depth()))
+        .with_probe(gemini_link.clone())
+        .spawn(&statistics, metrics::SAMPLE_INTERVAL);
+    let mut dsp_config = AudioProcessorConfig::load().unwrap_or_else(|e| {
+        eprintln!("[AudioProcessor] Failed to load config, using defaults: {}", e);
+        AudioProcessorConfig::default()
+    });
+    let AudioProcessor { capture: capture_chain, playback: playback_chain } =
+        AudioProcessor::from_config(&dsp_config);
+    audio_player.set_playback_chain(playback_chain);
+    // The microphone runs at 48 kHz; everything downstream works at 16 kHz
+    let downsampler = resample::Decimator::capture_to_pipeline();
+    terminal_ui.add_system_message(&format!("✅ Audio player ready (DSP: {})", capture_chain.stage_names().join(" → ")));
     terminal_ui.draw(&status_indicator, &statistics);
 
     terminal_ui.add_system_message("[5/13] Initializing conversation session...");
@@ -85,25 +151,95 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         terminal_ui.add_system_message("No previous session found, starting new.");
         ConversationSession::new()
     });
+    // The session keeps the last turns; the day's transcript keeps all of them
+    match paths::transcripts_dir() {
+        Ok(dir) => session.set_transcript_dir(dir),
+        Err(e) => terminal_ui.add_system_message(&format!("⚠️  Transcripts disabled: {}", e)),
+    }
     terminal_ui.add_system_message(&format!("✅ Session ready (ID: {}, Turns: {})", session.session_id(), session.turn_count()));
+    terminal_ui.set_session(&session);
     terminal_ui.draw(&status_indicator, &statistics);
 
     terminal_ui.add_system_message("[6/13] Initializing command parser...");
     terminal_ui.draw(&status_indicator, &statistics);
     let command_parser = CommandParser::new();
+    let mut answer_router = AnswerRouter::new();
     terminal_ui.add_system_message("✅ Command parser ready");
     terminal_ui.draw(&status_indicator, &statistics);
 
     terminal_ui.add_system_message("[7/13] Initializing command executor...");
     terminal_ui.draw(&status_indicator, &statistics);
     let mut command_executor = CommandExecutor::new()?;
-    terminal_ui.add_system_message("✅ Command executor ready (sandbox enabled)");
+    let mut command_history = CommandHistory::load().unwrap_or_default();
+    terminal_ui.add_system_message(&format!("✅ Command executor ready (sandbox enabled, {} in history)", command_history.len()));
     terminal_ui.draw(&status_indicator, &statistics);
 
     terminal_ui.add_system_message("[8/13] Loading user profile...");
     terminal_ui.draw(&status_indicator, &statistics);
-    let _profile = UserProfile::load()?;
+    let mut _profile = UserProfile::load()?;
+    // Shared with the watcher; `_profile` is this loop's copy, refreshed on change
+    let profile = _profile.clone().shared();
+    let profile_path = UserProfile::get_profile_path()?;
     terminal_ui.add_system_message(&format!("✅ User profile loaded (User: {}, Language: {})", _profile.name, _profile.language));
+    // Wake phrase and sensitivity come from the profile; a template recorded
+    // for that phrase replaces the generic envelope match
+    if let Some(ref phrase) = _profile.custom_wake_word {
+        wake_word.set_phrase(phrase);
+    }
+    wake_word.set_sensitivity(_profile.wake_word_sensitivity);
+    if let Ok(path) = paths::data_file("wake_word_template.json") {
+        if path.exists() {
+            match WakeWordTemplate::load(&path).map(|t| wake_word.set_template(t)) {
+                Ok(true) => terminal_ui.add_system_message("✅ Trained wake word template loaded"),
+                Ok(false) => terminal_ui.add_system_message("⚠️  Wake word template is for another phrase, ignoring it"),
+                Err(e) => terminal_ui.add_system_message(&format!("⚠️  Invalid wake word template: {}", e)),
+            }
+        }
+    }
+    terminal_ui.add_system_message(&format!("✅ Wake word: \"{}\" (sensitivity: {})", wake_word.phrase(), _profile.wake_word_sensitivity));
+    // Accessibility mode: append-only tagged lines instead of redrawn boxes
+    terminal_ui.set_accessible(_profile.accessibility.enabled, _profile.accessibility.earcons);
+    // Destructive commands wait for a "yes" unless the profile says otherwise
+    if let Some(policy) = _profile.get_preference("command_policy").and_then(|p| ExecutionPolicy::from_name(p)) {
+        command_executor.set_policy(policy);
+    }
+    // A macro recording stops by itself after this long without a command
+    if let Some(secs) = _profile.get_preference("macro_idle_timeout_secs").and_then(|s| s.parse().ok()) {
+        command_executor.set_macro_idle_timeout(std::time::Duration::from_secs(secs));
+    }
+    // Gemini runs file, process and system commands as tool calls unless turned off
+    let use_tools = _profile.get_preference("gemini_tools").map_or(true, |v| !matches!(v.as_str(), "off" | "false"));
+    for skipped in command_executor.allow_paths(&_profile.allowed_paths) {
+        terminal_ui.add_system_message(&format!("⚠️  {}", skipped));
+    }
+    // Guest mode is left by speaking this passphrase (or from the TUI)
+    let mut guest_mode = GuestMode::new(_profile.get_preference("guest_passphrase").cloned());
+    // Failures are explained by voice in the profile language, once per cooldown
+    let mut error_announcer = ErrorAnnouncer::new(&_profile.language);
+    // EVA-Mind has no speech_config: the profile's speaking rate is applied locally
+    let speech = SpeechSettings::from_profile(&_profile);
+    if speech.has_prosody() {
+        audio_player.set_playback_rate(speech.speaking_rate);
+        terminal_ui.add_system_message(ProsodyMode::LocalStretch.describe(_profile.language.starts_with("pt")));
+    }
+    audio_player.set_volume(_profile.volume);
+    let webhook_config = WebhooksConfig::load().unwrap_or_else(|e| {
+        terminal_ui.add_system_message(&format!("⚠️  Invalid webhooks.toml: {}", e));
+        WebhooksConfig::default()
+    });
+    let webhook_secrets = WebhookSecrets::load().unwrap_or_else(|e| {
+        terminal_ui.add_system_message(&format!("⚠️  Invalid secrets.toml: {}", e));
+        WebhookSecrets::default()
+    });
+    let webhooks = WebhookDispatcher::start(webhook_config, webhook_secrets);
+    if webhooks.endpoint_count() > 0 {
+        terminal_ui.add_system_message(&format!("✅ Webhooks ready ({} endpoints)", webhooks.endpoint_count()));
+    }
+    let mut npu_watch = NpuWatch::default();
+    let mut digest = {
+        let stats = statistics.read().unwrap();
+        DailyDigest::new(chrono::Local::now().date_naive(), stats.turns, stats.commands_executed)
+    };
     terminal_ui.draw(&status_indicator, &statistics);
 
     terminal_ui.add_system_message("[9/13] Initializing custom commands...");
@@ -116,23 +252,73 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     terminal_ui.draw(&status_indicator, &statistics);
     let mut _macros = MacroManager::new()?;
     terminal_ui.add_system_message(&format!("✅ Macros ready ({} macros)", _macros.count()));
+    let mut timers = TimerManager::load().unwrap_or_else(|e| {
+        terminal_ui.add_system_message(&format!("⚠️  Could not load timers: {}", e));
+        TimerManager::new()
+    });
+    // Timers and other schedules read time through this (virtual in scenario tests)
+    let clock = SystemClock;
+    statistics.write().unwrap().update_timers(timers.active(clock.now()));
     terminal_ui.draw(&status_indicator, &statistics);
 
     terminal_ui.add_system_message("[11/13] Initializing emotion detection...");
     terminal_ui.draw(&status_indicator, &statistics);
-    let _emotion_detector = EmotionDetector::new();
+    // Profile preference "emotion_voice_weight": how much the voice counts against the words
+    let voice_weight = _profile
+        .get_preference("emotion_voice_weight")
+        .and_then(|w| w.parse().ok())
+        .unwrap_or(emotion::DEFAULT_VOICE_WEIGHT);
+    let emotion_detector = EmotionDetector::new().with_voice_weight(voice_weight);
+    // Each transcript's language picks the reply language and the emotion lexicon
+    let mut language_switcher = LanguageSwitcher::new(&_profile.language, _profile.response_language.clone());
     terminal_ui.add_system_message("✅ Emotion detection ready");
     terminal_ui.draw(&status_indicator, &statistics);
 
-    // Initialize animations
-    let mut anim_listening = Animation::listening();
-    let mut anim_processing = Animation::processing();
-    let mut anim_speaking = Animation::speaking();
+    let mut animations = AnimationEngine::new(&_profile.animations).unwrap_or_else(|e| {
+        terminal_ui.add_system_message(&format!("⚠️  Animations: {}, using the built-in ones", e));
+        AnimationEngine::default()
+    });
+    // Replies that come back as text only are read out locally
+    let mut reply_speech = SpeechFallback::new(&_profile.tts, &_profile.language);
+
+    // [12/13] Initialize Time Machine
+    terminal_ui.add_system_message("[12/13] Initializing Time Machine (NPU)...");
+    terminal_ui.draw(&status_indicator, &statistics);
+    
+    #[cfg(feature = "timemachine")]
+    let timemachine_res = timemachine::TimeMachine::new().await;
+    
+    #[cfg(not(feature = "timemachine"))]
+    let timemachine_res: Result<timemachine::TimeMachine, Box<dyn std::error::Error>> = Err("Feature disabled".into());
+
+    let (_timemachine, recorder) = match timemachine_res {
+        Ok(tm) => {
+            terminal_ui.add_system_message("✅ Time Machine ready (Encrypted & Local)");
+            let tm_arc = std::sync::Arc::new(tm);
+
+            // Start background recording; shutdown waits for it to save the index
+            let recorder = tm_arc.clone().start_recording_async();
+            (Some(tm_arc), Some(recorder))
+        },
+        Err(e) => {
+            terminal_ui.add_system_message(&format!("⚠️ Time Machine disabled: {}", e));
+            (None, None)
+        }
+    };
+    // What this build can actually do, now that Time Machine's real state
+    // is known: told to the models, and the answer to "what can you do"
+    let capabilities = CapabilityReport::build(
+        &CapabilityContext::detect(_timemachine.is_some()),
+        &_custom_commands,
+        &_macros,
+    );
+    terminal_ui.add_system_message(&format!("✅ Capabilities: {} actions", capabilities.entries.len()));
+    terminal_ui.draw(&status_indicator, &statistics);
 
-    terminal_ui.add_system_message("[12/13] Connecting to EVA-Mind...");
+    terminal_ui.add_system_message("[13/13] Connecting to EVA-Mind...");
     terminal_ui.draw(&status_indicator, &statistics);
 
-    let eva_config = EvaMindConfig::default();
+    let eva_config = EvaMindConfig { capabilities: Some(capabilities.condensed()), ..Default::default() };
     terminal_ui.add_system_message(&format!("   URL: {}", eva_config.ws_url));
     terminal_ui.draw(&status_indicator, &statistics);
 
@@ -149,110 +335,873 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     Some(client)
                 }
                 Err(e) => {
-                    terminal_ui.add_system_message(&format!("⚠️  Could not start session: {}", e));
-                    terminal_ui.add_system_message("   Running in demo mode");
+                    report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::ConnectFailed(e.to_string()));
+                    terminal_ui.add_system_message("   Running offline (local commands only)");
                     terminal_ui.draw(&status_indicator, &statistics);
                     None
                 }
             }
         }
         Err(e) => {
-            terminal_ui.add_system_message(&format!("⚠️  Could not connect to EVA-Mind: {}", e));
-            terminal_ui.add_system_message("   Running in demo mode");
+            report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::ConnectFailed(e.to_string()));
+            terminal_ui.add_system_message("   Running offline (local commands only)");
             terminal_ui.draw(&status_indicator, &statistics);
             None
         }
     };
 
-    // [13/13] Initialize Time Machine
-    terminal_ui.add_system_message("[13/13] Initializing Time Machine (NPU)...");
-    terminal_ui.draw(&status_indicator, &statistics);
-    
-    #[cfg(feature = "timemachine")]
-    let timemachine_res = crate::timemachine::TimeMachine::new().await;
-    
-    #[cfg(not(feature = "timemachine"))]
-    let timemachine_res: Result<crate::timemachine::TimeMachine, Box<dyn std::error::Error>> = Err("Feature disabled".into());
-
-    let _timemachine = match timemachine_res {
-        Ok(tm) => {
-            terminal_ui.add_system_message("✅ Time Machine ready (Encrypted & Local)");
-            let tm_arc = std::sync::Arc::new(tm);
-            let tm_clone = tm_arc.clone();
-            
-            // Start background recording
-            tokio::spawn(async move {
-                tm_clone.start_recording().await;
-            });
-            Some(tm_arc)
-        },
-        Err(e) => {
-            terminal_ui.add_system_message(&format!("⚠️ Time Machine disabled: {}", e));
-            None
-        }
-    };
-    terminal_ui.draw(&status_indicator, &statistics);
-
     // Start UI
     terminal_ui.add_system_message("EVA OS Started");
     terminal_ui.add_system_message(&format!("Session ID: {}", session.session_id()));
 
     if eva_mind.is_none() {
-        terminal_ui.add_system_message("Running in DEMO MODE (No Connection)");
+        terminal_ui.add_system_message("Running in OFFLINE MODE (local commands only)");
     }
 
     status_indicator.set_status(EvaStatus::Idle);
+    let _ = status_indicator.write_status_file(&status_indicator::status_file_path());
     terminal_ui.draw(&status_indicator, &statistics);
 
     // Pronto para receber áudio
-    if eva_mind.is_some() {
-        terminal_ui.add_system_message("🎤 Diga 'Hey EVA' para começar...");
-    }
+    terminal_ui.add_system_message(&format!("🎤 Diga '{}' para começar...", wake_word.phrase()));
     terminal_ui.draw(&status_indicator, &statistics);
 
+    // The microphone belongs to the listener from here on: it keeps hearing
+    // the wake word while a turn is processed
+    let pre_roll_ms = _profile.get_preference("pre_roll_ms").and_then(|ms| ms.parse().ok()).unwrap_or(audio::PRE_ROLL_MS);
+    let listener = Listener::spawn(audio.capture_source()?, downsampler, wake_word, vad, capture_chain, pre_roll_ms);
+    let mut dropped_events = 0;
+
+    // Numbered follow-ups offered after a command answer
+    let mut follow_ups = FollowUps::new();
+
+    // Typed input works with or without a microphone; text goes to Gemini,
+    // connected on first use
+    let mut text_input = TextInput::spawn();
+    let mut gemini: Option<GeminiClient> = None;
+    // Without EVA-Mind, spoken turns are transcribed locally (Vosk loads on
+    // first use) and handled like typed lines
+    let mut offline_stt = OfflineRecognizer::new(&_profile.language);
+    // Edits to profile.json take effect without a restart
+    let mut profile_updates = ProfileWatcher::new()?.spawn(profile.clone(), user_profile::WATCH_INTERVAL);
+    let mut profile_changed = false;
+    let mut offline_turn: Option<String> = None;
+    // How the utterance behind `offline_turn` sounded, blended with its words
+    let mut voice_emotion: Option<(Emotion, f32)> = None;
+
+    // Ctrl-C and SIGTERM stop the loop below from here on (before this
+    // they kill the process, which has nothing to save yet)
+    let shutdown = ShutdownSignal::new();
+    shutdown.trap_signals();
+
+    // Other programs (evactl) reach the daemon through the control socket;
+    // their requests are answered between turns
+    let (_control, mut control_requests) = match control::ControlServer::start() {
+        Ok((server, requests)) => (Some(server), Some(requests)),
+        Err(e) => {
+            terminal_ui.add_system_message(&format!("⚠️  Control socket disabled: {}", e));
+            (None, None)
+        }
+    };
+    // On Redox the runtime settings are `eva:` files as well, answered
+    // like `get`/`set` requests
+    #[cfg(target_os = "redox")]
+    if let Some(ref server) = _control {
+        if let Err(e) = eva_scheme::spawn(server.requests()) {
+            terminal_ui.add_system_message(&format!("⚠️  eva: scheme disabled: {}", e));
+        }
+    }
+    // An `ask` waiting for the reply to its turn
+    let mut pending_ask: Option<control::Pending> = None;
+
     // Main conversation loop
     let mut frame_count = 0u64;
-    loop {
-        // Reset animations
-        anim_listening.reset();
-        anim_processing.reset();
-        anim_speaking.reset();
-
-        // 1. Capture audio chunk
-        let chunk = match audio.capture_chunk().await {
-            Ok(c) => c,
-            Err(e) => {
-                terminal_ui.add_system_message(&format!("Audio Error: {}", e));
+    while !shutdown.is_requested() {
+        // Offline recognition stays on a switched language for a follow-up
+        if let Err(e) = offline_stt.set_language(language_switcher.stt_language().locale()) {
+            terminal_ui.add_system_message(&format!("⚠️  Offline recognition: {}", e));
+        }
+        // Follow-up chips vanish when their window closes
+        if follow_ups.expire(std::time::Instant::now()) {
+            terminal_ui.show_suggestions(&[]);
+        }
+        // So does an unanswered destructive command
+        if let Some(notice) = command_executor.expire_pending(std::time::Instant::now()) {
+            terminal_ui.add_system_message(&notice);
+        }
+        // And a macro recording nobody has added to in a while
+        if let Some(recorded) = command_executor.expire_macro(std::time::Instant::now()) {
+            match _macros.finish_recording(recorded) {
+                Ok(saved) => terminal_ui.add_system_message(&format!("Macro recording stopped (idle). {}", saved)),
+                Err(e) => terminal_ui.add_system_message(&e),
+            }
+        }
+
+        // The profile changed (edited on disk, or by voice): recognition,
+        // error speech and the Gemini session follow the name and language
+        while let Ok(update) = profile_updates.try_recv() {
+            match update {
+                Ok(()) => profile_changed = true,
+                Err(e) => terminal_ui.add_system_message(&format!("⚠️  Profile not reloaded: {}", e)),
+            }
+        }
+        if std::mem::take(&mut profile_changed) {
+            let fresh = profile.read().unwrap_or_else(|e| e.into_inner()).clone();
+            let change = ProfileChange::between(&_profile, &fresh);
+            if fresh.animations != _profile.animations {
+                match AnimationEngine::new(&fresh.animations) {
+                    Ok(engine) => animations = engine,
+                    Err(e) => terminal_ui.add_system_message(&format!("⚠️  Animations not changed: {}", e)),
+                }
+            }
+            if fresh.tts != _profile.tts || change.language {
+                reply_speech = SpeechFallback::new(&fresh.tts, &fresh.language);
+            }
+            if change.language || fresh.response_language != _profile.response_language {
+                language_switcher = LanguageSwitcher::new(&fresh.language, fresh.response_language.clone());
+            }
+            if fresh.accessibility != _profile.accessibility {
+                terminal_ui.set_accessible(fresh.accessibility.enabled, fresh.accessibility.earcons);
+            }
+            _profile = fresh;
+            audio_player.set_volume(_profile.volume);
+            listener.set_wake_sensitivity(_profile.wake_word_sensitivity);
+            if change.language {
+                if let Err(e) = offline_stt.set_language(&_profile.language) {
+                    terminal_ui.add_system_message(&format!("⚠️  Offline recognition: {}", e));
+                }
+                error_announcer = ErrorAnnouncer::new(&_profile.language);
+            }
+            if !change.is_empty() {
+                terminal_ui.add_system_message(&format!("✅ Profile updated (User: {}, Language: {})", _profile.name, _profile.language));
+                if let Some(client) = gemini.as_mut() {
+                    client.set_resume_context(session.turns().to_vec());
+                    match client.apply_profile(&_profile).await {
+                        // The API refused the new rate or pitch: stretched locally instead
+                        Ok(ProsodyMode::LocalStretch) => {
+                            audio_player.set_playback_rate(SpeechSettings::from_profile(&_profile).speaking_rate);
+                            terminal_ui.add_system_message(ProsodyMode::LocalStretch.describe(_profile.language.starts_with("pt")));
+                        }
+                        Ok(ProsodyMode::Server) => {}
+                        Err(e) => {
+                            report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::ConnectFailed(e.to_string()));
+                            // Reconnects with the new profile on next use
+                            terminal_ui.set_model(None);
+                            gemini_link.attach(None);
+                            gemini = None;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Keep the running totals on disk once a turn has changed them
+        let saved = {
+            let mut stats = statistics.write().unwrap();
+            (stats.is_dirty() && guest_mode.allows_persistence()).then(|| stats.save())
+        };
+        if let Some(Err(e)) = saved {
+            terminal_ui.add_system_message(&format!("⚠️  Failed to save statistics: {}", e));
+        }
+
+        // Control requests; an `ask` becomes the next typed turn and holds
+        // the rest back until it has been answered
+        while pending_ask.is_none() && offline_turn.is_none() {
+            let Some(request) = control_requests.as_mut().and_then(|r| r.try_recv().ok()) else { break };
+            match request.command.clone() {
+                control::Command::Status => {
+                    let status = control_status(&status_indicator, &session, &statistics.read().unwrap(), eva_mind.is_some(), gemini.is_some(), _timemachine.is_some());
+                    request.answer(Ok(status));
+                }
+                control::Command::Say { text } => {
+                    status_indicator.set_status(EvaStatus::Speaking);
+                    terminal_ui.add_eva_message(&text);
+                    terminal_ui.draw(&status_indicator, &statistics);
+                    if let Some(samples) = reply_speech.speech_for(&text, false) {
+                        audio_player.enqueue_samples(&samples);
+                    }
+                    status_indicator.set_status(EvaStatus::Idle);
+                    request.answer(Ok(serde_json::json!({})));
+                }
+                control::Command::Ask { text } => {
+                    offline_turn = Some(text);
+                    pending_ask = Some(request);
+                }
+                control::Command::TimeMachineSearch { query, limit } => {
+                    let found = match &_timemachine {
+                        Some(tm) => tm
+                            .search(&query, limit.unwrap_or(control::DEFAULT_SEARCH_LIMIT))
+                            .await
+                            .map(|results| search_results_json(&results))
+                            .map_err(|e| e.to_string()),
+                        None => Err("Time Machine is not running".to_string()),
+                    };
+                    request.answer(found);
+                }
+                control::Command::TimeMachineTimeline { date } => {
+                    let day = match date {
+                        Some(date) => chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| format!("invalid date '{}', expected YYYY-MM-DD", date)),
+                        None => Ok(clock.now().with_timezone(&chrono::Local).date_naive()),
+                    };
+                    let found = match (&_timemachine, day) {
+                        (Some(tm), Ok(day)) => tm.timeline(day, &chrono::Local).await.map(|entries| timeline_json(&entries)).map_err(|e| e.to_string()),
+                        (None, _) => Err("Time Machine is not running".to_string()),
+                        (_, Err(e)) => Err(e),
+                    };
+                    request.answer(found);
+                }
+                control::Command::TimeMachineThumbnail { id } => {
+                    let found = match &_timemachine {
+                        Some(tm) => tm
+                            .get_thumbnail(id)
+                            .await
+                            .map(|thumb| serde_json::json!({ "id": id, "mime_type": thumb.mime_type(), "data": BASE64.encode(&thumb.bytes) }))
+                            .map_err(|e| e.to_string()),
+                        None => Err("Time Machine is not running".to_string()),
+                    };
+                    request.answer(found);
+                }
+                control::Command::Webhooks => request.answer(Ok(webhooks.stats_json())),
+                control::Command::Shutdown => {
+                    shutdown.request();
+                    request.answer(Ok(serde_json::json!({})));
+                    break;
+                }
+                // Config writes are saved to the profile, which is reloaded
+                // at the top of the next pass; DSP toggles apply right away
+                control::Command::Get { path } => {
+                    let status = control_status(&status_indicator, &session, &statistics.read().unwrap(), eva_mind.is_some(), gemini.is_some(), _timemachine.is_some());
+                    let settings = LiveSettings {
+                        profile: &profile,
+                        profile_path: &profile_path,
+                        session: &mut session,
+                        status,
+                        profile_changed: &mut profile_changed,
+                        dsp: &mut dsp_config,
+                        listener: &listener,
+                        audio_player: &mut audio_player,
+                    };
+                    request.answer(eva_scheme::read(&settings, &path).map_err(|e| e.to_string()));
+                }
+                control::Command::Set { path, value } => {
+                    let mut settings = LiveSettings {
+                        profile: &profile,
+                        profile_path: &profile_path,
+                        session: &mut session,
+                        status: serde_json::Value::Null,
+                        profile_changed: &mut profile_changed,
+                        dsp: &mut dsp_config,
+                        listener: &listener,
+                        audio_player: &mut audio_player,
+                    };
+                    request.answer(eva_scheme::write(&mut settings, &path, &value).map(|()| serde_json::json!({})).map_err(|e| e.to_string()));
+                }
+                control::Command::Export { path, format, audio } => {
+                    let format = match format.as_str() {
+                        "markdown" => Ok(ExportFormat::Markdown),
+                        "json" => Ok(ExportFormat::Json { audio }),
+                        other => Err(format!("unknown export format '{}'", other)),
+                    };
+                    let exported = format.and_then(|format| session.export(&path, format).map_err(|e| e.to_string()));
+                    if exported.is_ok() {
+                        terminal_ui.add_system_message(&format!("📤 Session exported (unencrypted) to {}", path));
+                    }
+                    request.answer(exported.map(|()| serde_json::json!({ "path": path })));
+                }
+                control::Command::Import { path } => {
+                    let imported = ConversationSession::import(&path).map_err(|e| e.to_string()).map(|mut imported| {
+                        if let Ok(dir) = paths::transcripts_dir() {
+                            imported.set_transcript_dir(dir);
+                        }
+                        session = imported;
+                        if let Err(e) = session.save_to_file("session.json") {
+                            terminal_ui.add_system_message(&format!("⚠️  Failed to save session: {}", e));
+                        }
+                        terminal_ui.set_session(&session);
+                        terminal_ui.add_system_message(&format!("📥 Session {} imported ({} turns)", session.session_id(), session.turn_count()));
+                        serde_json::json!({ "session_id": session.session_id(), "turns": session.turn_count() })
+                    });
+                    request.answer(imported);
+                }
+            }
+        }
+
+        // 1. Wait for the listener (or take a typed line, or a turn transcribed offline).
+        // Keys are polled every pass: scrolling and export just redraw.
+        let (heard, line) = match offline_turn.take() {
+            Some(text) => (None, InputLine::Send(text)),
+            None => match terminal_ui.handle_input(&mut text_input) {
+                Some(line) => (None, line),
+                None => (Some(listener.recv_timeout(LISTEN_POLL).await), InputLine::Ignore),
+            },
+        };
+        let Some(heard) = heard else {
+            match line {
+                InputLine::Open => terminal_ui.show_input_prompt(),
+                InputLine::Send(text) => {
+                    status_indicator.set_status(EvaStatus::Processing);
+                    terminal_ui.add_user_message(&text);
+                    terminal_ui.draw(&status_indicator, &statistics);
+                    // Requests about EVA herself are handled before routing:
+                    // guest mode, accessibility mode, and forgetting or branching
+                    // the conversation (which rewrite the session, so they
+                    // aren't turns of it)
+                    let pt = _profile.language.to_lowercase().starts_with("pt");
+                    let handled = match turn::intercept(&command_parser, &text) {
+                        Some(Intercept::Guest(command)) => {
+                            let changed = match command {
+                                GuestCommand::Enter => guest_mode
+                                    .enter(&mut session, _timemachine.as_deref())
+                                    .map(|()| guest_mode::entered_reply(pt).to_string()),
+                                GuestCommand::Exit { passphrase } => guest_mode
+                                    .exit_with_passphrase(&passphrase, &mut session, _timemachine.as_deref())
+                                    .map(|summary| guest_mode::exited_reply(&summary, pt)),
+                            };
+                            if changed.is_ok() {
+                                // The Gemini session knows the other persona and conversation
+                                if let Some(client) = gemini.take() {
+                                    let _ = client.close().await;
+                                }
+                                terminal_ui.set_model(None);
+                                gemini_link.attach(None);
+                                status_indicator.set_guest(guest_mode.is_active());
+                                terminal_ui.set_session(&session);
+                            }
+                            Some(changed.map_err(|e| EvaError::CommandFailed(e.to_string())))
+                        }
+                        Some(Intercept::Accessibility(enabled)) => {
+                            // Kept in the profile, so it holds after a restart
+                            let mut shared = profile.write().unwrap_or_else(|e| e.into_inner());
+                            let mut updated = shared.clone();
+                            updated.set_accessibility(enabled);
+                            Some(match updated.save_to(&profile_path) {
+                                Ok(()) => {
+                                    *shared = updated;
+                                    _profile.set_accessibility(enabled);
+                                    terminal_ui.set_accessible(enabled, _profile.accessibility.earcons);
+                                    Ok(accessibility::toggle_reply(enabled, pt).to_string())
+                                }
+                                Err(e) => Err(EvaError::SaveFailed(format!("profile: {}", e))),
+                            })
+                        }
+                        Some(Intercept::Session(op)) => {
+                            statistics.write().unwrap().increment_commands();
+                            Some(match op {
+                                SessionOperation::ForgetLastExchange => {
+                                    let removed = session.forget_last_exchange().len();
+                                    // The Live API keeps the turns server-side: start over without them
+                                    if let (true, Some(client)) = (removed > 0, gemini.as_mut()) {
+                                        client.set_resume_context(session.turns().to_vec());
+                                        if client.reset_with_context(session.turns()).await.is_err() {
+                                            // Reconnects with the trimmed turns on next use
+                                            terminal_ui.set_model(None);
+                                            gemini_link.attach(None);
+                                            gemini = None;
+                                        }
+                                    }
+                                    terminal_ui.show_forgotten(&session, removed);
+                                    Ok(session::forgotten_reply(removed, pt).to_string())
+                                }
+                                // A guest's conversation is never stored
+                                _ if !guest_mode.allows_persistence() => Err(EvaError::CommandFailed("Branching is not available in guest mode".to_string())),
+                                _ => match SessionStore::open_default().and_then(|store| Ok(store.branch(&session)?)) {
+                                    Ok(branch) => {
+                                        let parent = std::mem::replace(&mut session, branch);
+                                        terminal_ui.show_branched(&session);
+                                        Ok(session::branched_reply(&session, parent.session_id(), pt))
+                                    }
+                                    Err(e) => Err(EvaError::SaveFailed(format!("session branch: {}", e))),
+                                },
+                            })
+                        }
+                        None => None,
+                    };
+                    if let Some(reply) = handled {
+                        if let Some(ask) = pending_ask.take() {
+                            ask.answer(reply.as_ref().map(|reply| serde_json::json!({ "reply": reply })).map_err(|e| e.to_string()));
+                        }
+                        match reply {
+                            Ok(reply) => {
+                                terminal_ui.add_eva_message(&reply);
+                                if let Some(samples) = reply_speech.speech_for(&reply, false) {
+                                    audio_player.enqueue_samples(&samples);
+                                }
+                            }
+                            Err(e) => report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, e),
+                        }
+                        if guest_mode.allows_persistence() {
+                            if let Err(e) = session.save_to_file("session.json") {
+                                report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::SaveFailed(format!("session: {}", e)));
+                            }
+                        }
+                        status_indicator.set_status(EvaStatus::Idle);
+                        let _ = status_indicator.write_status_file(&status_indicator::status_file_path());
+                        terminal_ui.draw(&status_indicator, &statistics);
+                        continue;
+                    }
+                    statistics.write().unwrap().increment_turns();
+                    let turn_language = language_switcher.observe(&text);
+                    session.add_turn_with_language(Role::User, text.clone(), turn_language.detected.map(|l| l.locale().to_string()));
+                    let words = EmotionDetector::for_language(language_switcher.emotion_language(&turn_language)).detect_with_confidence(&text);
+                    let emotion = emotion_detector.blend(words, voice_emotion.take());
+                    status_indicator.set_emotion(emotion);
+                    session.set_context("last_emotion".to_string(), emotion.to_string());
+
+                    // Guests can't let the model run tools
+                    let guest_persona = guest_mode.persona();
+                    let gemini_config = || {
+                        let mut config = GeminiConfig {
+                            capabilities: Some(capabilities.condensed()),
+                            tools: use_tools && guest_persona.is_none(),
+                            ..GeminiConfig::from_profile(&_profile)
+                        };
+                        if let Some(persona) = guest_persona {
+                            config.system_instruction = persona.to_string();
+                            config.user_name = None;
+                        }
+                        config
+                    };
+                    let mut plan = turn::Router {
+                        parser: &command_parser,
+                        answers: &mut answer_router,
+                        follow_ups: &mut follow_ups,
+                        history: &command_history,
+                        custom: Some(&mut _custom_commands),
+                        guest: &guest_mode,
+                        confirming: command_executor.pending().is_some(),
+                    }
+                    .plan(&text, clock.now(), &chrono::Local, clock.instant(), pt);
+                    // With tools the model decides whether a command was really
+                    // meant; the parser acts alone only when Gemini is out of reach
+                    if let Plan::Route { route, chose: false } = &mut plan {
+                        if use_tools && guest_persona.is_none() && offline::model_decides(route) && ensure_gemini(&mut gemini, gemini_config, earlier_turns(&session), session.memory_context()).await {
+                            *route = TurnRoute::Model;
+                        }
+                    }
+                    let from_model = matches!(plan, Plan::Served { source: AnswerSource::Cached, .. } | Plan::Route { route: TurnRoute::Model, .. });
+                    let mut source = AnswerSource::Local;
+                    // Gemini spoke the reply itself (or was cut off): nothing to synthesize
+                    let mut voiced = false;
+                    // What ran or was found this turn, for follow-ups
+                    let mut ran_command: Option<(CommandIntent, String)> = None;
+                    let mut found = Vec::new();
+                    let reply = match plan {
+                        // "yes" / "no" to a held destructive command
+                        Plan::Confirm(true) => {
+                            status_indicator.set_status(EvaStatus::Executing);
+                            terminal_ui.draw(&status_indicator, &statistics);
+                            let intent = command_executor.pending().cloned().unwrap_or(CommandIntent::Unknown);
+                            let result = command_executor.confirm_pending().await.map_err(|e| e.to_string());
+                            if let Ok(output) = &result {
+                                ran_command = Some((intent.clone(), output.clone()));
+                            }
+                            if guest_mode.allows_persistence() {
+                                webhooks.emit(command_event(&intent, &result, pending_ask.is_some()));
+                                command_history.record(intent, &result);
+                                let _ = command_history.save();
+                            }
+                            result.map_err(EvaError::CommandFailed)
+                        }
+                        Plan::Confirm(false) => Ok(command_executor.cancel_pending().unwrap_or_default()),
+                        Plan::Custom(CustomOutcome::Run { action, alternatives }) => {
+                            if !alternatives.is_empty() {
+                                terminal_ui.add_system_message(&format!("Also matched: {}", alternatives.join(", ")));
+                            }
+                            status_indicator.set_status(EvaStatus::Executing);
+                            terminal_ui.draw(&status_indicator, &statistics);
+                            statistics.write().unwrap().increment_commands();
+                            run_custom_action(action, &command_parser, &mut command_executor, &mut _macros)
+                                .await
+                                .map_err(EvaError::CommandFailed)
+                        }
+                        Plan::Custom(CustomOutcome::Ask(question)) => Ok(question),
+                        Plan::Served { reply, source: served } => {
+                            source = served;
+                            Ok(reply)
+                        }
+                        Plan::Refused(reason) => Err(EvaError::CommandFailed(reason)),
+                        Plan::Route { route, .. } => match route {
+                            TurnRoute::Timer(op) => {
+                                statistics.write().unwrap().increment_commands();
+                                let pt = _profile.language.to_lowercase().starts_with("pt");
+                                timers.apply(op, clock.now(), &chrono::Local, pt).map_err(EvaError::CommandFailed)
+                            }
+                            TurnRoute::TimeMachine(op) => {
+                                statistics.write().unwrap().increment_commands();
+                                let pt = _profile.language.to_lowercase().starts_with("pt");
+                                match &_timemachine {
+                                    Some(tm) => {
+                                        let search = matches!(op, TimeMachineOperation::Search { .. });
+                                        let applied = tm.apply(op, clock.now(), &chrono::Local, pt).await.map_err(EvaError::CommandFailed);
+                                        if search && applied.is_ok() {
+                                            found = tm.last_search();
+                                        }
+                                        applied
+                                    }
+                                    None => Err(EvaError::CommandFailed("Time Machine is not running".to_string())),
+                                }
+                            }
+                            TurnRoute::Macro(op) => {
+                                status_indicator.set_status(EvaStatus::Executing);
+                                terminal_ui.draw(&status_indicator, &statistics);
+                                statistics.write().unwrap().increment_commands();
+                                _macros.apply(op, &mut command_executor).await.map_err(EvaError::CommandFailed)
+                            }
+                            // "call me Daniel": takes effect from the next pass of the loop
+                            TurnRoute::Profile(op) => {
+                                statistics.write().unwrap().increment_commands();
+                                let pt = _profile.language.to_lowercase().starts_with("pt");
+                                let applied = user_profile::apply_operation(&profile, op, &profile_path, pt);
+                                profile_changed |= applied.is_ok();
+                                applied.map_err(EvaError::CommandFailed)
+                            }
+                            // "volume down", "mute yourself": right away, even mid-reply
+                            TurnRoute::Audio(op) => {
+                                statistics.write().unwrap().increment_commands();
+                                let pt = _profile.language.to_lowercase().starts_with("pt");
+                                apply_audio(op, &mut audio_player, guest_mode.allows_persistence().then_some((&profile, profile_path.as_path())), pt)
+                                    .map_err(EvaError::CommandFailed)
+                            }
+                            // "what can you do": the full listing on screen, a sentence per category spoken
+                            TurnRoute::Capabilities => {
+                                terminal_ui.add_system_message(&capabilities.markdown());
+                                Ok(capabilities.spoken(_profile.language.to_lowercase().starts_with("pt")))
+                            }
+                            // "remember that ...": kept with the session, however long it runs
+                            TurnRoute::Remember { key, value } => {
+                                session.remember(&key, &value);
+                                Ok(summary::remembered_reply(&key, &value, _profile.language.to_lowercase().starts_with("pt")))
+                            }
+                            TurnRoute::Command(intent) => {
+                                status_indicator.set_status(EvaStatus::Executing);
+                                terminal_ui.draw(&status_indicator, &statistics);
+                                statistics.write().unwrap().increment_commands();
+                                // A guest's commands run in the guest sandbox, Safe risk only
+                                let ran = if guest_mode.is_active() {
+                                    guest_mode.execute(intent.clone()).await.map(ExecutionOutcome::Done)
+                                } else {
+                                    command_executor.execute(intent.clone()).await
+                                };
+                                match ran {
+                                    // Held or dry run: nothing happened, so nothing to record
+                                    Ok(outcome @ (ExecutionOutcome::NeedsConfirmation(_) | ExecutionOutcome::WouldRun(_))) => Ok(outcome.into_message()),
+                                    ran => {
+                                        let result = ran.map(ExecutionOutcome::into_message).map_err(|e| e.to_string());
+                                        if let Ok(output) = &result {
+                                            ran_command = Some((intent.clone(), output.clone()));
+                                        }
+                                        if guest_mode.allows_persistence() {
+                                            webhooks.emit(command_event(&intent, &result, pending_ask.is_some()));
+                                            command_history.record(intent, &result);
+                                            let _ = command_history.save();
+                                        }
+                                        result.map_err(EvaError::CommandFailed)
+                                    }
+                                }
+                            }
+                            // "create a file called notes.txt and then list files": in
+                            // order, stopping at a failure or a command held for a "yes"
+                            TurnRoute::Sequence(intents) => {
+                                status_indicator.set_status(EvaStatus::Executing);
+                                terminal_ui.draw(&status_indicator, &statistics);
+                                let pt = _profile.language.to_lowercase().starts_with("pt");
+                                let total = intents.len();
+                                let mut outputs = Vec::new();
+                                let mut stop = None;
+                                for intent in intents {
+                                    statistics.write().unwrap().increment_commands();
+                                    let step = match intent {
+                                        CommandIntent::Timer(op) => timers.apply(op, clock.now(), &chrono::Local, pt).map(ExecutionOutcome::Done),
+                                        CommandIntent::TimeMachine(op) => match &_timemachine {
+                                            Some(tm) => tm.apply(op, clock.now(), &chrono::Local, pt).await.map(ExecutionOutcome::Done),
+                                            None => Err("Time Machine is not running".to_string()),
+                                        },
+                                        CommandIntent::Macro(op) => _macros.apply(op, &mut command_executor).await.map(ExecutionOutcome::Done),
+                                        CommandIntent::Profile(op) => {
+                                            let applied = user_profile::apply_operation(&profile, op, &profile_path, pt);
+                                            profile_changed |= applied.is_ok();
+                                            applied.map(ExecutionOutcome::Done)
+                                        }
+                                        CommandIntent::Audio(op) => {
+                                            apply_audio(op, &mut audio_player, guest_mode.allows_persistence().then_some((&profile, profile_path.as_path())), pt)
+                                                .map(ExecutionOutcome::Done)
+                                        }
+                                        CommandIntent::Session(SessionOperation::Remember { key, value }) => {
+                                            session.remember(&key, &value);
+                                            Ok(ExecutionOutcome::Done(summary::remembered_reply(&key, &value, pt)))
+                                        }
+                                        intent => {
+                                            let ran = if guest_mode.is_active() {
+                                                guest_mode.execute(intent.clone()).await.map(ExecutionOutcome::Done)
+                                            } else {
+                                                command_executor.execute(intent.clone()).await
+                                            };
+                                            let ran = ran.map_err(|e| e.to_string());
+                                            if guest_mode.allows_persistence() && !matches!(ran, Ok(ExecutionOutcome::NeedsConfirmation(_) | ExecutionOutcome::WouldRun(_))) {
+                                                let result = ran.clone().map(ExecutionOutcome::into_message);
+                                                webhooks.emit(command_event(&intent, &result, pending_ask.is_some()));
+                                                command_history.record(intent, &result);
+                                                let _ = command_history.save();
+                                            }
+                                            ran
+                                        }
+                                    };
+                                    match step {
+                                        Ok(ExecutionOutcome::NeedsConfirmation(prompt)) => stop = Some(SequenceStop::Held(prompt)),
+                                        Ok(outcome) => outputs.push(outcome.into_message()),
+                                        Err(e) => stop = Some(SequenceStop::Failed(e)),
+                                    }
+                                    if stop.is_some() {
+                                        break;
+                                    }
+                                }
+                                sequence_summary(&outputs, total, stop).map_err(EvaError::CommandFailed)
+                            }
+                            TurnRoute::Model => {
+                                ensure_gemini(&mut gemini, gemini_config, earlier_turns(&session), session.memory_context()).await;
+                                match gemini.as_mut() {
+                                    Some(client) => {
+                                        client.set_resume_context(session.turns().to_vec());
+                                        // Answer in the user's language for this turn; a dropped
+                                        // link fails the question right after and is reported there
+                                        if let Some(directive) = &turn_language.directive {
+                                            let _ = client.send_directive(directive).await;
+                                        }
+                                        let mut tools = ToolContext {
+                                            executor: &mut command_executor,
+                                            timemachine: _timemachine.as_deref(),
+                                            now: clock.now(),
+                                            portuguese: _profile.language.to_lowercase().starts_with("pt"),
+                                            ran: Vec::new(),
+                                        };
+                                        let reply = ask_gemini_showing_state(
+                                            client, &text, &mut audio_player, &mut tools, &mut status_indicator, &mut terminal_ui, &statistics,
+                                        )
+                                        .await
+                                        .map(|(reply, spoken)| {
+                                            voiced = spoken;
+                                            reply
+                                        });
+                                        source = AnswerSource::Remote;
+                                        // A plain answer to a question may be asked again
+                                        if let Ok(said) = &reply {
+                                            if tools.ran.is_empty() && said != SPOKEN_REPLY && said != INTERRUPTED_REPLY {
+                                                answer_router.remember(&text, said, clock.instant());
+                                            }
+                                        }
+                                        for (intent, result) in std::mem::take(&mut tools.ran) {
+                                            statistics.write().unwrap().increment_commands();
+                                            if let Ok(output) = &result {
+                                                ran_command = Some((intent.clone(), output.clone()));
+                                            }
+                                            if guest_mode.allows_persistence() {
+                                                webhooks.emit(command_event(&intent, &result, pending_ask.is_some()));
+                                                command_history.record(intent, &result);
+                                                let _ = command_history.save();
+                                            }
+                                        }
+                                        status_indicator.set_quota_warning(client.quota_warning());
+                                        terminal_ui.set_model(Some(client.active_model()));
+                                        gemini_link.attach(Some(client));
+                                        // Keep the session unless reconnecting gave up
+                                        if client.connection_state() == ConnectionState::Disconnected {
+                                            terminal_ui.set_model(None);
+                                            gemini_link.attach(None);
+                                            gemini = None;
+                                        }
+                                        reply.map_err(|e| EvaError::StreamFailed(e.to_string()))
+                                    }
+                                    None => {
+                                        report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::ConnectFailed("Gemini unavailable (is GOOGLE_API_KEY set?)".to_string()));
+                                        Ok(offline::offline_reply(_profile.language.to_lowercase().starts_with("pt")).to_string())
+                                    }
+                                }
+                            }
+                        },
+                    };
+                    statistics.write().unwrap().record_answer(source);
+
+                    if let Some(ask) = pending_ask.take() {
+                        ask.answer(reply.as_ref().map(|reply| serde_json::json!({ "reply": reply })).map_err(|e| e.to_string()));
+                    }
+                    match reply {
+                        Ok(reply) => {
+                            terminal_ui.add_eva_message(&reply);
+                            if let Some(samples) = reply_speech.speech_for(&reply, voiced) {
+                                audio_player.enqueue_samples(&samples);
+                            }
+                            if from_model {
+                                session.add_turn_with_language(Role::Assistant, reply, Some(turn_language.response.locale().to_string()));
+                            } else {
+                                session.add_command_result(reply);
+                            }
+                        }
+                        Err(e) => report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, e),
+                    }
+                    // Follow-ups for what was just run or found; any other answer clears them
+                    let offered = match (&ran_command, found.is_empty()) {
+                        (Some((intent, output)), _) => follow_ups.offer(&FollowUpSource::Command { intent, output }, &_profile.language, clock.instant()).to_vec(),
+                        (None, false) => follow_ups.offer(&FollowUpSource::TimeMachineSearch { results: &found }, &_profile.language, clock.instant()).to_vec(),
+                        (None, true) => {
+                            follow_ups.dismiss();
+                            Vec::new()
+                        }
+                    };
+                    terminal_ui.show_suggestions(&offered);
+                    // Let the model condense what fell out of the window; if it
+                    // can't, the local summary of those turns stands
+                    if gemini.is_some() && session.evicted_turns().len() >= summary::COMPRESS_AFTER {
+                        if let Ok(compressed) = summary::compress(gemini_config(), session.long_term_summary(), session.evicted_turns()).await {
+                            session.set_compressed_summary(compressed);
+                        }
+                    }
+                    if guest_mode.allows_persistence() {
+                        if let Err(e) = session.save_to_file("session.json") {
+                            report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::SaveFailed(format!("session: {}", e)));
+                        }
+                    }
+                    terminal_ui.set_session(&session);
+                    status_indicator.set_status(EvaStatus::Idle);
+                }
+                InputLine::History(keys) => {
+                    for key in keys {
+                        match terminal_ui.history_key(&command_history, key) {
+                            // The executor holds risky commands for confirmation itself
+                            HistoryAction::Run { intent, .. } if !guest_mode.is_active() => {
+                                statistics.write().unwrap().increment_commands();
+                                match command_executor.execute(intent.clone()).await {
+                                    Ok(outcome @ (ExecutionOutcome::NeedsConfirmation(_) | ExecutionOutcome::WouldRun(_))) => {
+                                        terminal_ui.add_eva_message(&outcome.into_message());
+                                    }
+                                    ran => {
+                                        let result = ran.map(ExecutionOutcome::into_message).map_err(|e| e.to_string());
+                                        webhooks.emit(command_event(&intent, &result, false));
+                                        command_history.record(intent, &result);
+                                        let _ = command_history.save();
+                                        match result {
+                                            Ok(output) => terminal_ui.add_eva_message(&output),
+                                            Err(e) => report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::CommandFailed(e)),
+                                        }
+                                    }
+                                }
+                            }
+                            HistoryAction::Run { .. } => terminal_ui.add_system_message("The command history isn't available in guest mode"),
+                            HistoryAction::Error(e) => terminal_ui.add_error_message(&e),
+                            HistoryAction::None | HistoryAction::Close => {}
+                        }
+                    }
+                    terminal_ui.refresh_history(&command_history);
+                }
+                InputLine::Ignore => {}
+                InputLine::Quit => {
+                    shutdown.request();
+                }
+            }
+            terminal_ui.draw(&status_indicator, &statistics);
+            continue;
+        };
+        // Audio left over from a turn or a reply that already ended is stale
+        let resumed = match heard {
+            Some(ListenerEvent::Wake { resumed }) => Some(resumed),
+            Some(ListenerEvent::Error(e)) => {
+                report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::AudioCapture(e));
                 continue;
             }
+            _ => None,
         };
 
         // Silently process audio
         frame_count += 1;
 
-        // 2. Check for wake word
-        if wake_word.detect(&chunk) {
+        // Fire due timers (fire times are absolute, so restarts and clock
+        // changes are handled by comparing against now)
+        let now = clock.now();
+        let fired = timers.due(now, &chrono::Local);
+        if !fired.is_empty() {
+            for timer in &fired {
+                terminal_ui.add_system_message(&format!("⏰ {}", timer.label));
+                digest.timer_fired();
+                webhooks.emit(WebhookEvent::new(
+                    WebhookEventKind::TimerFired,
+                    serde_json::json!({ "label": timer.label, "recurring": timer.recurrence.is_some() }),
+                ));
+            }
+            if let Err(e) = timers.save() {
+                report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::SaveFailed(format!("timers: {}", e)));
+            }
+        }
+        // The NPU back from a watchdog recovery, and the digest of a day once it is over
+        let (npu_state, turns, commands) = {
+            let stats = statistics.read().unwrap();
+            (stats.system.npu_state.clone(), stats.turns, stats.commands_executed)
+        };
+        if let Some(event) = npu_watch.observe(npu_state.as_deref()) {
+            webhooks.emit(event);
+        }
+        if let Some(event) = digest.roll(now.with_timezone(&chrono::Local).date_naive(), turns, commands) {
+            webhooks.emit(event);
+        }
+        // Programs EVA started: reaped as they exit, with a word on how
+        for exited in command_executor.reap_children() {
+            terminal_ui.add_system_message(&format!("⏹  {}", exited));
+        }
+        if !fired.is_empty() || frame_count % 100 == 0 {
+            statistics.write().unwrap().update_timers(timers.active(now));
+            terminal_ui.draw(&status_indicator, &statistics);
+        }
+
+        // 2. The wake word was heard (or the user talked over the last reply)
+        if let Some(resumed) = resumed {
             status_indicator.set_status(EvaStatus::Listening);
-            terminal_ui.add_system_message("Wake word detected! Listening...");
-            statistics.update_all();
+            terminal_ui.add_system_message(if resumed { "Listening..." } else { "Wake word detected! Listening..." });
             terminal_ui.draw(&status_indicator, &statistics);
+            if let Some(earcon) = terminal_ui.take_earcon() {
+                audio_player.enqueue_samples(&earcon.samples(audio::SAMPLE_RATE));
+            }
             
-            wake_word.reset();
+            let heard_at = std::time::Instant::now();
             
             // 3. Capture and STREAM audio in real-time (like EVA-Mobile)
-            let mut total_samples = 0usize;
-            let mut silence_count = 0;
             let mut chunk_count = 0u32;
             let mut response_chunks = 0u32;
+            // Kept for offline recognition if EVA-Mind is missing or drops
+            let mut utterance = Vec::new();
+            let mut streaming = eva_mind.is_some();
+            // When the reply started arriving, and whether it has been heard yet
+            let mut first_response: Option<std::time::Instant> = None;
+            let mut playback_started = false;
+            // Levels when the user stopped, for trimming the silence around the request
+            let mut speech_energy = listener.vad_energy();
 
             loop {
-                let audio_chunk = match audio.capture_chunk().await {
-                    Ok(c) => c,
-                    Err(_) => break,
+                // The listener ends the turn once the user has finished speaking
+                let audio_chunk = match listener.recv().await {
+                    Some(ListenerEvent::Audio(chunk)) => chunk,
+                    Some(ListenerEvent::EndOfSpeech { energy, timed_out }) => {
+                        if timed_out {
+                            terminal_ui.add_system_message("Max recording time reached");
+                        }
+                        speech_energy = energy;
+                        break;
+                    }
+                    Some(ListenerEvent::Error(e)) => {
+                        report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::AudioCapture(e));
+                        break;
+                    }
+                    Some(ListenerEvent::Wake { .. }) => continue,
+                    None => break,
                 };
+                utterance.extend_from_slice(&audio_chunk);
+
+                // Live caption of what has been understood so far (redraws rate-limited)
+                if let Some(partial) = offline_stt.caption(&audio_chunk) {
+                    if terminal_ui.set_caption(&partial, EvaStatus::Listening, std::time::Instant::now()) {
+                        terminal_ui.draw(&status_indicator, &statistics);
+                    }
+                }
 
                 // Stream audio to EVA-Mind in real-time (like EVA-Mobile)
-                if let Some(ref mut eva_client) = eva_mind {
+                if let Some(eva_client) = eva_mind.as_mut().filter(|_| streaming) {
                     // Convert f32 samples to PCM16 bytes
                     let audio_bytes: Vec<u8> = audio_chunk
                         .iter()
@@ -264,17 +1213,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                     // Send immediately (streaming)
                     if let Err(e) = eva_client.send_audio(&audio_bytes).await {
-                        terminal_ui.add_system_message(&format!("Stream error: {}", e));
-                        break;
+                        report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::StreamFailed(e.to_string()));
+                        // Keep recording and finish the turn offline
+                        streaming = false;
                     }
+                }
+                if let Some(eva_client) = eva_mind.as_mut().filter(|_| streaming) {
                     chunk_count += 1;
 
                     // Also check for incoming audio response (non-blocking)
                     match eva_client.receive().await {
                         Ok(Some(EvaMindResponse::Audio(audio_data))) => {
                             response_chunks += 1;
-                            if let Err(e) = audio_player.play_pcm(&audio_data).await {
-                                terminal_ui.add_system_message(&format!("Playback error: {}", e));
+                            first_response.get_or_insert_with(std::time::Instant::now);
+                            audio_player.enqueue_pcm(&audio_data);
+                            if let Some(e) = audio_player.take_error() {
+                                report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::AudioPlayback(e));
                             }
                         }
                         Ok(Some(EvaMindResponse::Control(msg))) => {
@@ -286,58 +1240,99 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                 }
 
-                total_samples += audio_chunk.len();
-
-                // Check for silence to end turn
-                if vad.is_speech(&audio_chunk) {
-                    silence_count = 0;
-                } else {
-                    silence_count += 1;
-                    if silence_count > 15 {
-                        // End of speech - stop streaming
-                        break;
-                    }
-                }
-
-                if total_samples > 48000 * 30 {
-                    terminal_ui.add_system_message("Max recording time reached");
-                    break;
-                }
-
                 // Animate listening
                 if chunk_count % 5 == 0 {
-                    statistics.update_all();
-                    status_indicator.set_symbol(anim_listening.next_frame());
+                    {
+                        let mut stats = statistics.write().unwrap();
+                        stats.update_dsp(listener.dsp_metrics(), audio_player.playback_metrics());
+                        stats.update_vad(listener.vad_energy());
+                        stats.update_send_queue(eva_mind.as_ref().filter(|_| streaming).map(|c| c.queue_depth()));
+                    }
+                    if let Some(symbol) = animations.current_symbol(status_indicator.get_status()) {
+                        status_indicator.set_symbol(&symbol);
+                    }
                     terminal_ui.draw(&status_indicator, &statistics);
                 }
             }
 
+            // The caption becomes the finished transcription
+            let transcript = offline_stt.finish_caption();
+            terminal_ui.clear_caption();
+            if let Some(text) = transcript.as_ref().filter(|_| streaming) {
+                // EVA-Mind answers the audio; the words are kept for the session
+                terminal_ui.add_user_message(text);
+                session.add_turn(Role::User, text.clone());
+                if guest_mode.allows_persistence() {
+                    if let Err(e) = session.save_to_file("session.json") {
+                        report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::SaveFailed(format!("session: {}", e)));
+                    }
+                }
+                terminal_ui.set_session(&session);
+            }
             terminal_ui.add_system_message(&format!("Streamed {} chunks, received {} responses", chunk_count, response_chunks));
-            statistics.turns += 1;
+            if listener.dropped() > dropped_events {
+                terminal_ui.add_system_message(&format!("⚠️  {} audio chunks dropped while EVA was busy", listener.dropped() - dropped_events));
+                dropped_events = listener.dropped();
+            }
+            statistics.write().unwrap().update_send_queue(None);
+            let speech_ended = std::time::Instant::now();
+            // After a barge-in there was no wake word to time from
+            if !resumed {
+                statistics.write().unwrap().record_latency(LatencyStage::WakeToEndOfSpeech, speech_ended - heard_at);
+            }
+            // Everything from the wake word on was kept, pauses included; only
+            // the quiet before and after the request goes
+            let utterance = vad::trim_silence(&utterance, audio::SAMPLE_RATE, speech_energy.release, vad::TRIM_PAD_MS).to_vec();
+            let prosody = emotion_detector.detect_from_audio_with_confidence(&utterance, audio::SAMPLE_RATE);
+            status_indicator.set_emotion(emotion_detector.blend((Emotion::Neutral, 0.0), Some(prosody)));
+            statistics.write().unwrap().increment_turns();
+            if streaming {
+                statistics.write().unwrap().record_answer(AnswerSource::Remote);
+            }
+            if webhooks.endpoint_count() > 0 {
+                terminal_ui.show_webhook_stats(&webhooks.stats());
+            }
             terminal_ui.draw(&status_indicator, &statistics);
 
             // 4. Wait for response audio
-            if let Some(ref mut eva_client) = eva_mind {
+            // The user talked over the reply: listen again without the wake word
+            let mut barged_in = false;
+            if let Some(eva_client) = eva_mind.as_mut().filter(|_| streaming) {
                 status_indicator.set_status(EvaStatus::Speaking);
+                listener.set_speaking(true);
                 terminal_ui.draw(&status_indicator, &statistics);
 
                 let timeout = tokio::time::Duration::from_secs(15);
                 let start = tokio::time::Instant::now();
                 let mut received_audio = false;
+                barge_in.reset();
 
                 while start.elapsed() < timeout {
-                    // Animate while waiting
-                    statistics.update_all();
-                    status_indicator.set_symbol(anim_speaking.next_frame());
+                    // Animate while reply audio is actually going out
+                    if let Some(symbol) = animations.current_symbol(status_indicator.get_status()).filter(|_| audio_player.is_playing()) {
+                        status_indicator.set_symbol(&symbol);
+                    }
+                    if let (Some(at), false) = (first_response, playback_started) {
+                        if audio_player.is_audible() {
+                            statistics.write().unwrap().record_latency(LatencyStage::ResponseToPlayback, at.elapsed());
+                            playback_started = true;
+                        }
+                    }
                     terminal_ui.draw(&status_indicator, &statistics);
 
                     // Try to receive audio
                     match eva_client.receive().await {
                         Ok(Some(EvaMindResponse::Audio(audio_data))) => {
                             received_audio = true;
+                            // Replies that began while the user was still talking aren't timed
+                            if first_response.is_none() {
+                                first_response = Some(std::time::Instant::now());
+                                statistics.write().unwrap().record_latency(LatencyStage::SendToFirstResponse, speech_ended.elapsed());
+                            }
                             // Play audio (raw PCM bytes from EVA-Mind)
-                            if let Err(e) = audio_player.play_pcm(&audio_data).await {
-                                terminal_ui.add_system_message(&format!("Audio Playback Error: {}", e));
+                            audio_player.enqueue_pcm(&audio_data);
+                            if let Some(e) = audio_player.take_error() {
+                                report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::AudioPlayback(e));
                             }
                         }
                         Ok(Some(EvaMindResponse::Control(msg))) => {
@@ -347,52 +1342,470 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                         Ok(None) => {
-                            // No message, continue
-                            if received_audio {
-                                // Got audio, small pause then continue
+                            // Nothing more in flight and the reply has finished playing
+                            if received_audio && !audio_player.is_playing() {
                                 break;
                             }
                         }
                         Err(e) => {
-                            terminal_ui.add_system_message(&format!("Receive Error: {}", e));
+                            report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::ReceiveFailed(e.to_string()));
                             break;
                         }
                     }
 
-                    tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+                    // Keep listening while EVA talks so she can be cut off
+                    // (paces the loop like the capture loop does)
+                    if let Some(ListenerEvent::Audio(mic)) = listener.recv_timeout(LISTEN_POLL).await {
+                        if barge_in.update(&mic, audio_player.playback_level()) {
+                            audio_player.stop();
+                            // Drop what the server already sent for this turn
+                            while let Ok(Some(_)) = eva_client.receive().await {}
+                            terminal_ui.add_system_message("Interrupted");
+                            listener.listen_now();
+                            barged_in = true;
+                            break;
+                        }
+                    }
                 }
+                listener.set_speaking(false);
 
-                if !received_audio {
-                    terminal_ui.add_system_message("No audio response received");
+                if received_audio || barged_in {
+                    error_announcer.resolved(ErrorKind::NoResponse);
+                } else {
+                    report_error(&mut terminal_ui, &mut error_announcer, &mut reply_speech, &mut audio_player, EvaError::NoResponse);
                 }
             } else {
-                // Demo mode logic
-                terminal_ui.add_system_message("Processing (Demo Mode)...");
-                tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-                
-                let demo_response = "I heard you! This is a demo response.";
-                terminal_ui.add_eva_message(demo_response);
-                
-                status_indicator.set_status(EvaStatus::Speaking);
-                
-                // Simulate speaking time with animation
-                let speak_duration = tokio::time::Duration::from_millis(2000);
-                let start_time = tokio::time::Instant::now();
-                
-                while start_time.elapsed() < speak_duration {
-                    statistics.update_all();
-                    status_indicator.set_symbol(anim_speaking.next_frame());
-                    terminal_ui.draw(&status_indicator, &statistics);
-                    tokio::time::sleep(anim_speaking.frame_duration()).await;
+                // No voice service for this turn: transcribe it locally and
+                // handle the text on the next pass, like a typed line
+                status_indicator.set_status(EvaStatus::Processing);
+                terminal_ui.draw(&status_indicator, &statistics);
+                // Already transcribed for the caption, unless captions weren't available
+                let transcribed = match transcript {
+                    Some(text) => Ok(Some(text)),
+                    None => offline_stt.transcribe(&utterance),
+                };
+                match transcribed {
+                    Ok(Some(text)) => {
+                        offline_turn = Some(text);
+                        voice_emotion = Some(prosody);
+                    }
+                    Ok(None) => terminal_ui.add_system_message("Didn't catch that"),
+                    Err(reason) => {
+                        terminal_ui.add_system_message(&format!("Offline speech recognition unavailable: {}", reason));
+                        // What still works, said like any other reply
+                        let hint = offline::offline_reply(_profile.language.to_lowercase().starts_with("pt"));
+                        terminal_ui.add_eva_message(hint);
+                        if let Some(samples) = reply_speech.speech_for(hint, false) {
+                            audio_player.enqueue_samples(&samples);
+                        }
+                    }
+                }
+            }
+
+            // Reset to idle (after a barge-in the next pass goes straight to listening)
+            if !barged_in {
+                status_indicator.set_status(EvaStatus::Idle);
+                status_indicator.set_guest(guest_mode.is_active());
+                let _ = status_indicator.write_status_file(&status_indicator::status_file_path());
+                terminal_ui.draw(&status_indicator, &statistics);
+                if let Some(earcon) = terminal_ui.take_earcon() {
+                    audio_player.enqueue_samples(&earcon.samples(audio::SAMPLE_RATE));
                 }
             }
-            
-            // Reset to idle
-            status_indicator.set_status(EvaStatus::Idle);
-            vad.reset();
-            terminal_ui.draw(&status_indicator, &statistics);
         }
 
         tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
     }
+
+    terminal_ui.add_system_message("Shutting down... (Ctrl-C again to force)");
+    terminal_ui.draw(&status_indicator, &statistics);
+    // The owner's session is the one saved; the guest's is discarded
+    if guest_mode.is_active() {
+        let _ = guest_mode.request_exit();
+        let _ = guest_mode.confirm_exit(true, &mut session, _timemachine.as_deref());
+    }
+    let mut parts = DaemonParts {
+        timemachine: _timemachine.as_deref(),
+        recorder,
+        session: &session,
+        statistics: &statistics,
+        gemini,
+        terminal_ui: &mut terminal_ui,
+    };
+    for (step, e) in shutdown::shut_down(&mut parts).await {
+        eprintln!("⚠️  Shutdown: {} failed: {}", step, e);
+    }
+    Ok(())
+}
+
+/// What the shutdown sequence stops, saves and closes
+struct DaemonParts<'a> {
+    timemachine: Option<&'a timemachine::TimeMachine>,
+    recorder: Option<tokio::task::JoinHandle<()>>,
+    session: &'a ConversationSession,
+    statistics: &'a std::sync::RwLock<Statistics>,
+    gemini: Option<GeminiClient>,
+    terminal_ui: &'a mut TerminalUI,
+}
+
+impl shutdown::Components for DaemonParts<'_> {
+    fn stop_capture(&mut self) {
+        if let Some(tm) = self.timemachine {
+            tm.stop_recording();
+        }
+    }
+
+    fn await_recorder(&mut self) -> StepFuture<'_> {
+        let recorder = self.recorder.take();
+        Box::pin(async move {
+            match recorder {
+                Some(task) => task.await.map_err(|e| e.to_string()),
+                None => Ok(()),
+            }
+        })
+    }
+
+    fn save_session(&mut self) -> Result<(), String> {
+        self.session.save_to_file("session.json").map_err(|e| e.to_string())
+    }
+
+    fn save_statistics(&mut self) -> Result<(), String> {
+        self.statistics.write().map_err(|e| e.to_string())?.save().map_err(|e| e.to_string())
+    }
+
+    fn close_gemini(&mut self) -> StepFuture<'_> {
+        let client = self.gemini.take();
+        Box::pin(async move {
+            match client {
+                Some(client) => client.close().await.map_err(|e| e.to_string()),
+                None => Ok(()),
+            }
+        })
+    }
+
+    fn restore_terminal(&mut self) {
+        self.terminal_ui.teardown();
+    }
+}
+
+/// What `evactl status` shows
+fn control_status(
+    status_indicator: &StatusIndicator,
+    session: &ConversationSession,
+    statistics: &Statistics,
+    eva_mind: bool,
+    gemini: bool,
+    timemachine: bool,
+) -> serde_json::Value {
+    serde_json::json!({
+        "status": status_indicator.to_status_json(),
+        "session_id": session.session_id(),
+        "session_turns": session.turn_count(),
+        "statistics": {
+            "turns": statistics.turns,
+            "commands_executed": statistics.commands_executed,
+            "uptime_seconds": statistics.uptime_seconds,
+            "memory_mb": statistics.system.rss_mb,
+            "free_memory_mb": statistics.system.free_mb,
+            "cpu_percent": statistics.system.cpu_percent,
+            "npu_state": statistics.system.npu_state,
+            "answers": AnswerSource::ALL.iter().map(|&source| (source.key().to_string(), statistics.answers(source).into())).collect::<serde_json::Map<_, _>>(),
+        },
+        "connected": { "eva_mind": eva_mind, "gemini": gemini },
+        "timemachine": timemachine,
+    })
+}
+
+/// The main loop's state behind the `eva:` files
+struct LiveSettings<'a> {
+    profile: &'a SharedProfile,
+    profile_path: &'a std::path::Path,
+    session: &'a mut ConversationSession,
+    /// `control_status` when the request came in
+    status: serde_json::Value,
+    /// Set once the profile was saved, so the loop reloads it
+    profile_changed: &'a mut bool,
+    /// Saved to audio_processor.json on a DSP toggle
+    dsp: &'a mut AudioProcessorConfig,
+    listener: &'a Listener,
+    audio_player: &'a mut AudioPlayer,
+}
+
+impl eva_scheme::Settings for LiveSettings<'_> {
+    fn wake_sensitivity(&self) -> f32 {
+        self.profile.read().unwrap_or_else(|e| e.into_inner()).wake_word_sensitivity
+    }
+
+    fn language(&self) -> String {
+        self.profile.read().unwrap_or_else(|e| e.into_inner()).language.clone()
+    }
+
+    fn dsp(&self) -> Vec<(String, bool)> {
+        ChainKind::ALL
+            .iter()
+            .flat_map(|&kind| self.dsp.chain(kind).iter().map(move |stage| (format!("{}.{}", kind.key(), stage.name()), stage.enabled())))
+            .collect()
+    }
+
+    fn status(&self) -> serde_json::Value {
+        self.status.clone()
+    }
+
+    fn context(&self) -> Vec<(String, String)> {
+        self.session.context_values()
+    }
+
+    fn apply(&mut self, change: Change) -> Result<(), String> {
+        let (field, value) = match change {
+            Change::WakeSensitivity(sensitivity) => ("wake_word_sensitivity", sensitivity.to_string()),
+            Change::Language(tag) => ("language", tag),
+            Change::Context(entries) => {
+                for (key, value) in entries {
+                    self.session.set_context(key, value);
+                }
+                return self.session.save_to_file("session.json").map_err(|e| format!("session: {}", e));
+            }
+            Change::Dsp(stages) => {
+                // Checked and saved as a whole before the chains change
+                let mut updated = self.dsp.clone();
+                for (kind, name, enabled) in &stages {
+                    if !updated.set_enabled(*kind, name, *enabled) {
+                        return Err(format!("no {} stage in the {} chain", name, kind.key()));
+                    }
+                }
+                updated.save().map_err(|e| format!("audio_processor.json: {}", e))?;
+                *self.dsp = updated;
+                for (kind, name, enabled) in stages {
+                    match kind {
+                        ChainKind::Capture => self.listener.set_dsp_stage(&name, enabled),
+                        ChainKind::Playback => {
+                            if let Some(chain) = self.audio_player.playback_chain_mut() {
+                                chain.set_enabled(&name, enabled);
+                            }
+                        }
+                    }
+                }
+                return Ok(());
+            }
+        };
+        let op = command_parser::ProfileOperation::Set { field: field.to_string(), value };
+        user_profile::apply_operation(self.profile, op, self.profile_path, false)?;
+        *self.profile_changed = true;
+        Ok(())
+    }
+}
+
+/// Time Machine search results for the control socket
+fn search_results_json(results: &[timemachine::SearchResult]) -> serde_json::Value {
+    results
+        .iter()
+        .map(|r| {
+            serde_json::json!({
+                "id": r.id,
+                "score": r.score,
+                "timestamp": r.timestamp.to_rfc3339(),
+                "text": r.text,
+                "screen": r.screen_number(),
+                "full_image": r.full_image,
+                "masked": r.masked,
+            })
+        })
+        .collect()
+}
+
+fn timeline_json(entries: &[timemachine::TimelineEntry]) -> serde_json::Value {
+    entries
+        .iter()
+        .map(|e| {
+            serde_json::json!({
+                "id": e.id,
+                "timestamp": e.timestamp.to_rfc3339(),
+                "app": e.app_name,
+                "snippet": e.snippet,
+                "thumbnail": e.thumbnail,
+                "screen": timemachine::screen_number(e.screen),
+                "full_image": e.full_image,
+            })
+        })
+        .collect()
+}
+
+/// Connect to Gemini on first use, replaying `history` and the session's
+/// long-term `memory` so the model remembers earlier conversations; `false`
+/// if it can't be reached
+async fn ensure_gemini(gemini: &mut Option<GeminiClient>, config: impl FnOnce() -> GeminiConfig, history: &[Turn], memory: Option<String>) -> bool {
+    if gemini.is_none() {
+        if let Ok(mut client) = GeminiClient::connect(config()).await {
+            let remembered = match memory {
+                Some(memory) => client.send_directive(&format!("What came before the turns that follow; don't reply to it.\n\n{}", memory)).await,
+                None => Ok(()),
+            };
+            if client.send_context(history).await.is_ok() && remembered.is_ok() {
+                *gemini = Some(client);
+            }
+        }
+    }
+    gemini.is_some()
+}
+
+/// Carry out a voice audio command; a new volume is kept in the profile
+/// when `saved_to` names it (not for guests)
+fn apply_audio(op: AudioOperation, audio_player: &mut AudioPlayer, saved_to: Option<(&SharedProfile, &std::path::Path)>, portuguese: bool) -> Result<String, String> {
+    let before = audio_player.volume();
+    let reply = audio_player.apply(op, portuguese);
+    if let (true, Some((profile, path))) = (audio_player.volume() != before, saved_to) {
+        user_profile::save_volume(profile, audio_player.volume(), path)?;
+    }
+    Ok(reply)
+}
+
+/// `command_executed` for the webhooks; a command run for a control API
+/// `ask`, which a webhook receiver may have sent, is marked as coming from
+/// one so it isn't pushed back out
+fn command_event(intent: &CommandIntent, result: &Result<String, String>, via_control: bool) -> WebhookEvent {
+    let event = WebhookEvent::command_executed(intent, result);
+    if via_control {
+        event.from_webhook()
+    } else {
+        event
+    }
+}
+
+/// Session turns before the one being answered (added just before)
+fn earlier_turns(session: &ConversationSession) -> &[Turn] {
+    session.turns().split_last().map_or(&[], |(_, earlier)| earlier)
+}
+
+/// Shown for a reply that was only spoken
+const SPOKEN_REPLY: &str = "🔊 (spoken reply)";
+
+/// Shown for a reply the user talked over before it said anything
+const INTERRUPTED_REPLY: &str = "(interrupted)";
+
+/// Send a typed message to Gemini and play the reply as it streams in,
+/// running the tools it calls on the way; returns the reply text (or a
+/// placeholder for audio-only and cut-off replies) and whether it needs no
+/// local speech: audio came with it, or the user talked over it
+///
+/// An interrupted reply ends the turn on the spot: what is still queued
+/// for the speaker is dropped rather than played out.
+async fn ask_gemini(
+    client: &mut GeminiClient,
+    text: &str,
+    audio_player: &mut AudioPlayer,
+    tools: &mut ToolContext<'_>,
+) -> Result<(String, bool), Box<dyn std::error::Error>> {
+    client.send_text(text).await?;
+
+    let mut reply = String::new();
+    let mut interrupted = false;
+    let mut audio = false;
+    let mut stream = client.receive_stream();
+    while let Some(event) = stream.next().await {
+        match event? {
+            StreamEvent::Text(part) => reply.push_str(&part),
+            StreamEvent::Audio(data) => {
+                audio = true;
+                audio_player.enqueue_base64(&data.data, data.sample_rate())?;
+            }
+            StreamEvent::ToolCall(call) => {
+                let result = tools.run(&call).await;
+                stream.respond(&call, &result).await?;
+            }
+            StreamEvent::Interrupted => {
+                audio_player.stop();
+                interrupted = true;
+            }
+            StreamEvent::TurnComplete => {}
+        }
+    }
+    if reply.trim().is_empty() {
+        reply = if interrupted { INTERRUPTED_REPLY } else { SPOKEN_REPLY }.to_string();
+    }
+    Ok((reply, audio || interrupted))
+}
+
+/// `ask_gemini`, showing "Reconnecting" while a dropped session is re-established
+async fn ask_gemini_showing_state(
+    client: &mut GeminiClient,
+    text: &str,
+    audio_player: &mut AudioPlayer,
+    tools: &mut ToolContext<'_>,
+    status_indicator: &mut StatusIndicator,
+    terminal_ui: &mut TerminalUI,
+    statistics: &std::sync::RwLock<Statistics>,
+) -> Result<(String, bool), Box<dyn std::error::Error>> {
+    let mut states = client.watch_state();
+    let ask = ask_gemini(client, text, audio_player, tools);
+    tokio::pin!(ask);
+    loop {
+        tokio::select! {
+            reply = &mut ask => return reply,
+            Ok(()) = states.changed() => {
+                let state = *states.borrow_and_update();
+                match state {
+                    ConnectionState::Reconnecting { attempt } => {
+                        status_indicator.set_status(EvaStatus::Reconnecting);
+                        terminal_ui.add_system_message(&format!("Connection to Gemini lost, reconnecting (attempt {})...", attempt));
+                    }
+                    ConnectionState::Connected => status_indicator.set_status(EvaStatus::Processing),
+                    ConnectionState::Disconnected => status_indicator.set_status(EvaStatus::Error),
+                }
+                terminal_ui.draw(status_indicator, statistics);
+            }
+        }
+    }
+}
+
+/// Carry out a custom command's action (arguments already substituted)
+async fn run_custom_action(
+    action: CommandAction,
+    parser: &CommandParser,
+    executor: &mut CommandExecutor,
+    macros: &mut MacroManager,
+) -> Result<String, String> {
+    match action {
+        CommandAction::SendText(text) => Ok(text),
+        CommandAction::RunMacro(name) => macros.apply(MacroOperation::Run { name }, executor).await,
+        CommandAction::Intent(intent) => executor.execute(intent).await.map(ExecutionOutcome::into_message).map_err(|e| e.to_string()),
+        // Free text: run it if it reads as a built-in command, otherwise say it
+        CommandAction::Custom(text) => match parser.parse(&text) {
+            Ok(intent) if intent != CommandIntent::Unknown => {
+                executor.execute(intent).await.map(ExecutionOutcome::into_message).map_err(|e| e.to_string())
+            }
+            _ => Ok(text),
+        },
+        CommandAction::ExecuteShell(command) => {
+            // Passed to cmd as written: its arguments are already quoted for
+            // cmd, and the usual argv escaping would mangle those quotes
+            #[cfg(target_os = "windows")]
+            let output = {
+                use std::os::windows::process::CommandExt;
+                std::process::Command::new("cmd").raw_arg(format!("/S /C \"{}\"", command)).output()
+            };
+            #[cfg(not(target_os = "windows"))]
+            let output = std::process::Command::new("sh").args(["-c", &command]).output();
+
+            let output = output.map_err(|e| format!("{}: {}", command, e))?;
+            let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if output.status.success() {
+                Ok(if stdout.is_empty() { format!("Ran: {}", command) } else { stdout })
+            } else {
+                Err(format!("{} failed ({}): {}", command, output.status, String::from_utf8_lossy(&output.stderr).trim()))
+            }
+        }
+    }
+}
+
+/// Show a failure in the TUI and, unless it was just explained, say what
+/// happened and what to do next
+fn report_error(terminal_ui: &mut TerminalUI, announcer: &mut ErrorAnnouncer, speech: &mut SpeechFallback, audio_player: &mut AudioPlayer, error: EvaError) {
+    let report = announcer.report(&error, speech);
+    terminal_ui.add_error_message(&report.announcement.detail);
+    if let Some(spoken) = report.announcement.spoken {
+        terminal_ui.add_eva_message(spoken);
+    }
+    if let Some(samples) = report.speech {
+        audio_player.enqueue_samples(&samples);
+    }
 }
@@ -0,0 +1,330 @@
+//! System metrics for the statistics panel, sampled in the background
+//!
+//! Every `SAMPLE_INTERVAL` the sampler asks each of its probes to fill in
+//! what it can measure (CPU and memory of this process, free memory, reply
+//! audio still queued, the Gemini session, the NPU driver's state), then
+//! publishes the result into the shared statistics the terminal UI draws
+//! from. Probing runs off the async runtime and without the statistics lock
+//! held, so a slow `/proc` read or a busy driver never stalls the main
+//! loop. A probe that can't measure something leaves it `None`; the panel
+//! just doesn't show it.
+
+use crate::audio_player::PlaybackDepth;
+use crate::gemini::{ConnectionState, GeminiClient};
+use crate::statistics::SharedStatistics;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// How often the sampler runs
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// NPU driver status file (`npu:status`)
+const NPU_STATUS: &str = "/scheme/npu/status";
+
+/// The latest sample; `None` where nothing could be measured
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SystemMetrics {
+    /// CPU used by EVA since the previous sample (100 = one full core)
+    pub cpu_percent: Option<f32>,
+    /// Resident memory of this process
+    pub rss_mb: Option<u64>,
+    /// Memory still available to the system
+    pub free_mb: Option<u64>,
+    /// Reply audio queued for the speaker
+    pub audio_buffered: Option<Duration>,
+    /// Gemini session, while a client exists
+    pub gemini: Option<ConnectionState>,
+    /// The NPU driver's state (`READY`, `BUSY`, ...), when it runs
+    pub npu_state: Option<String>,
+}
+
+/// One source of metrics
+pub trait Probe: Send {
+    /// Fill in whatever this probe measures; leave the rest alone
+    fn sample(&mut self, metrics: &mut SystemMetrics);
+}
+
+impl<F: FnMut(&mut SystemMetrics) + Send> Probe for F {
+    fn sample(&mut self, metrics: &mut SystemMetrics) {
+        self(metrics)
+    }
+}
+
+/// This process and the system's memory, through sysinfo
+pub struct SysinfoProbe {
+    system: sysinfo::System,
+    pid: sysinfo::Pid,
+    /// CPU use is measured between refreshes, the first one has none
+    primed: bool,
+}
+
+impl SysinfoProbe {
+    pub fn new() -> Self {
+        use sysinfo::{PidExt, SystemExt};
+        Self { system: sysinfo::System::new(), pid: sysinfo::Pid::from_u32(std::process::id()), primed: false }
+    }
+
+    /// Whether sysinfo can read this OS (it can't on Redox)
+    pub fn is_supported() -> bool {
+        use sysinfo::SystemExt;
+        sysinfo::System::IS_SUPPORTED
+    }
+}
+
+impl Default for SysinfoProbe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Probe for SysinfoProbe {
+    fn sample(&mut self, metrics: &mut SystemMetrics) {
+        use sysinfo::{ProcessExt, SystemExt};
+        self.system.refresh_memory();
+        metrics.free_mb = Some(self.system.available_memory() / 1024 / 1024).filter(|&mb| mb > 0);
+        if !self.system.refresh_process(self.pid) {
+            return;
+        }
+        if let Some(process) = self.system.process(self.pid) {
+            metrics.rss_mb = Some(process.memory() / 1024 / 1024);
+            metrics.cpu_percent = self.primed.then(|| process.cpu_usage());
+            self.primed = true;
+        }
+    }
+}
+
+/// Memory from procfs-style `status` and `meminfo` files, where sysinfo
+/// isn't supported
+pub struct ProcFsProbe {
+    root: PathBuf,
+}
+
+impl ProcFsProbe {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl Probe for ProcFsProbe {
+    fn sample(&mut self, metrics: &mut SystemMetrics) {
+        let read = |name: &str| std::fs::read_to_string(self.root.join(name)).ok();
+        if let Some(kb) = read("self/status").and_then(|s| kilobytes(&s, "VmRSS")) {
+            metrics.rss_mb = Some(kb / 1024);
+        }
+        if let Some(kb) = read("meminfo").and_then(|s| kilobytes(&s, "MemAvailable").or_else(|| kilobytes(&s, "MemFree"))) {
+            metrics.free_mb = Some(kb / 1024);
+        }
+    }
+}
+
+/// The value of a `Key:   1234 kB` line
+fn kilobytes(text: &str, key: &str) -> Option<u64> {
+    text.lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+        .and_then(|value| value.split_whitespace().next()?.parse().ok())
+}
+
+/// The NPU driver's `state:` line, while its scheme exists
+pub struct NpuProbe {
+    path: PathBuf,
+}
+
+impl NpuProbe {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Default for NpuProbe {
+    fn default() -> Self {
+        Self::new(NPU_STATUS)
+    }
+}
+
+impl Probe for NpuProbe {
+    fn sample(&mut self, metrics: &mut SystemMetrics) {
+        metrics.npu_state = std::fs::read_to_string(&self.path).ok().and_then(|status| {
+            status.lines().find_map(|line| line.strip_prefix("state:")).map(|state| state.trim().to_string())
+        });
+    }
+}
+
+/// Reply audio still queued in the player
+pub struct AudioProbe(pub PlaybackDepth);
+
+impl Probe for AudioProbe {
+    fn sample(&mut self, metrics: &mut SystemMetrics) {
+        metrics.audio_buffered = Some(self.0.queued_duration());
+    }
+}
+
+/// The Gemini client in use, for the sampler to follow; the main loop
+/// re-attaches it whenever the client is replaced or dropped
+#[derive(Clone, Default)]
+pub struct GeminiLink(Arc<Mutex<Option<watch::Receiver<ConnectionState>>>>);
+
+impl GeminiLink {
+    pub fn attach(&self, client: Option<&GeminiClient>) {
+        if let Ok(mut state) = self.0.lock() {
+            *state = client.map(|c| c.watch_state());
+        }
+    }
+}
+
+impl Probe for GeminiLink {
+    fn sample(&mut self, metrics: &mut SystemMetrics) {
+        metrics.gemini = self.0.lock().ok().and_then(|state| state.as_ref().map(|s| *s.borrow()));
+    }
+}
+
+/// Probes run together every `SAMPLE_INTERVAL`
+#[derive(Default)]
+pub struct Sampler {
+    probes: Vec<Box<dyn Probe>>,
+}
+
+impl Sampler {
+    /// No probes; add them with `with_probe`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Process and memory probes that work on this OS, and the NPU driver's
+    pub fn for_this_system() -> Self {
+        let sampler = if SysinfoProbe::is_supported() {
+            Self::new().with_probe(SysinfoProbe::new())
+        } else {
+            Self::new().with_probe(ProcFsProbe::new("/proc"))
+        };
+        sampler.with_probe(NpuProbe::default())
+    }
+
+    pub fn with_probe(mut self, probe: impl Probe + 'static) -> Self {
+        self.probes.push(Box::new(probe));
+        self
+    }
+
+    /// Run every probe
+    pub fn sample(&mut self) -> SystemMetrics {
+        let mut metrics = SystemMetrics::default();
+        for probe in &mut self.probes {
+            probe.sample(&mut metrics);
+        }
+        metrics
+    }
+
+    /// Sample, then publish into `stats`; the lock is only taken to store
+    /// the result
+    pub fn sample_into(&mut self, stats: &SharedStatistics) {
+        let metrics = self.sample();
+        if let Ok(mut stats) = stats.write() {
+            stats.update_uptime();
+            stats.update_system(metrics);
+        }
+    }
+
+    /// Sample into `stats` every `interval` until the statistics are
+    /// dropped; probes run on the blocking pool
+    pub fn spawn(self, stats: &SharedStatistics, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let stats = Arc::downgrade(stats);
+        tokio::spawn(async move {
+            let mut sampler = self;
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(shared) = stats.upgrade() else { return };
+                let sampled = tokio::task::spawn_blocking(move || {
+                    sampler.sample_into(&shared);
+                    sampler
+                });
+                match sampled.await {
+                    Ok(returned) => sampler = returned,
+                    Err(e) => {
+                        eprintln!("[Metrics] Sampler stopped: {}", e);
+                        return;
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statistics::Statistics;
+    use std::sync::mpsc;
+    use std::sync::RwLock;
+
+    #[test]
+    fn test_missing_backends_leave_metrics_unknown() {
+        let dir = std::env::temp_dir().join(format!("eva_test_metrics_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut sampler = Sampler::new()
+            .with_probe(ProcFsProbe::new(dir.join("proc")))
+            .with_probe(NpuProbe::new(dir.join("npu-status")))
+            .with_probe(GeminiLink::default());
+        assert_eq!(sampler.sample(), SystemMetrics::default());
+
+        std::fs::create_dir_all(dir.join("proc/self")).unwrap();
+        std::fs::write(dir.join("proc/self/status"), "Name:\teva-daemon\nVmRSS:\t   81920 kB\n").unwrap();
+        std::fs::write(dir.join("proc/meminfo"), "MemTotal:  8000000 kB\nMemFree:   2048000 kB\n").unwrap();
+        std::fs::write(dir.join("npu-status"), "state: READY\nfw_status: 0x00000001\npower: D0\n").unwrap();
+        let metrics = sampler.sample();
+        assert_eq!(metrics.rss_mb, Some(80));
+        assert_eq!(metrics.free_mb, Some(2000));
+        assert_eq!(metrics.npu_state.as_deref(), Some("READY"));
+        assert_eq!(metrics.cpu_percent, None);
+        assert_eq!(metrics.gemini, None);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_probes_run_without_holding_the_statistics_lock() {
+        let stats: SharedStatistics = Arc::new(RwLock::new(Statistics::new()));
+        let (entered_tx, entered) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+        let mut sampler = Sampler::new().with_probe(move |metrics: &mut SystemMetrics| {
+            entered_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+            metrics.cpu_percent = Some(12.5);
+        });
+
+        let shared = stats.clone();
+        let probing = std::thread::spawn(move || sampler.sample_into(&shared));
+        entered.recv().unwrap();
+        // The main loop can still update and draw while a probe is slow
+        stats.try_write().expect("statistics locked while probing").increment_turns();
+        assert_eq!(stats.read().unwrap().system.cpu_percent, None);
+        release.send(()).unwrap();
+        probing.join().unwrap();
+
+        let stats = stats.read().unwrap();
+        assert_eq!(stats.system.cpu_percent, Some(12.5));
+        assert_eq!(stats.turns, 1);
+    }
+
+    #[tokio::test]
+    async fn test_spawned_sampler_publishes_until_statistics_are_dropped() {
+        let stats: SharedStatistics = Arc::new(RwLock::new(Statistics::new()));
+        let sampler = Sampler::new().with_probe(|metrics: &mut SystemMetrics| metrics.npu_state = Some("READY".to_string()));
+        let task = sampler.spawn(&stats, Duration::from_millis(10));
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while stats.read().unwrap().system.npu_state.is_none() {
+            assert!(tokio::time::Instant::now() < deadline, "no sample published");
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        drop(stats);
+        tokio::time::timeout(Duration::from_secs(5), task).await.expect("sampler kept running").unwrap();
+    }
+
+    #[test]
+    fn test_kilobytes() {
+        assert_eq!(kilobytes("VmPeak:\t 100 kB\nVmRSS:\t 2048 kB\n", "VmRSS"), Some(2048));
+        assert_eq!(kilobytes("VmRSSx: 1 kB\n", "VmRSS"), None);
+        assert_eq!(kilobytes("", "MemAvailable"), None);
+    }
+}
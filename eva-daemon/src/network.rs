@@ -0,0 +1,368 @@
+//! Network probes behind the voice network commands
+//!
+//! Everything here answers in a sentence EVA can say out loud, and nothing
+//! takes longer than `PROBE_BUDGET`: a host that never answers is reported
+//! as unreachable instead of holding up the turn.
+//!
+//! - Local addresses come from the interface list (`getifaddrs` on Unix);
+//!   elsewhere, from the source address the OS would route an outbound
+//!   packet through.
+//! - Ping times TCP connects (443, then 80). A refused connection still
+//!   proves the host is up, and no raw socket, so no privileges, is needed.
+//!   A host that drops every connect gets one try with the system `ping`.
+//! - DNS lookups use the system resolver.
+
+use regex::Regex;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+/// Cap on the whole of one probe, lookup included
+pub const PROBE_BUDGET: Duration = Duration::from_secs(5);
+
+/// Connects timed per ping
+const PING_COUNT: usize = 3;
+
+/// Ports tried for TCP pings, in order
+const PING_PORTS: &[u16] = &[443, 80];
+
+/// Addresses read out before "and N more"
+const MAX_SPOKEN_ADDRESSES: usize = 3;
+
+/// A local address and the interface it belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalAddress {
+    /// Interface name; empty when the platform doesn't say
+    pub interface: String,
+    pub ip: IpAddr,
+}
+
+/// Non-loopback addresses of this machine, IPv4 first
+pub fn local_addresses() -> Vec<LocalAddress> {
+    let mut found: Vec<LocalAddress> = interface_addresses().into_iter().filter(|a| is_reportable(a.ip)).collect();
+    if found.is_empty() {
+        found.extend(routed_addresses().into_iter().filter(|a| is_reportable(a.ip)));
+    }
+    found.sort_by_key(|a| a.ip.is_ipv6());
+    found.dedup();
+    found
+}
+
+/// Worth telling the user about: not loopback, unspecified or IPv6 link-local
+fn is_reportable(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => !v4.is_loopback() && !v4.is_unspecified(),
+        IpAddr::V6(v6) => !v6.is_loopback() && !v6.is_unspecified() && (v6.segments()[0] & 0xffc0) != 0xfe80,
+    }
+}
+
+#[cfg(all(unix, not(target_os = "redox")))]
+fn interface_addresses() -> Vec<LocalAddress> {
+    use std::ffi::CStr;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    let mut found = Vec::new();
+    let mut head: *mut libc::ifaddrs = std::ptr::null_mut();
+    // SAFETY: on success `head` is a list owned by us until freeifaddrs
+    if unsafe { libc::getifaddrs(&mut head) } != 0 {
+        return found;
+    }
+
+    let mut cursor = head;
+    while !cursor.is_null() {
+        // SAFETY: non-null nodes of the list are valid until freeifaddrs
+        let ifa = unsafe { &*cursor };
+        cursor = ifa.ifa_next;
+        if ifa.ifa_addr.is_null() || ifa.ifa_name.is_null() {
+            continue;
+        }
+
+        // SAFETY: ifa_addr points at a sockaddr whose family says how
+        // large it is; the name is a NUL-terminated string
+        let ip = unsafe {
+            match i32::from((*ifa.ifa_addr).sa_family) {
+                libc::AF_INET => {
+                    let sin = &*(ifa.ifa_addr as *const libc::sockaddr_in);
+                    IpAddr::V4(Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr)))
+                }
+                libc::AF_INET6 => {
+                    let sin6 = &*(ifa.ifa_addr as *const libc::sockaddr_in6);
+                    IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr))
+                }
+                _ => continue,
+            }
+        };
+        let interface = unsafe { CStr::from_ptr(ifa.ifa_name) }.to_string_lossy().into_owned();
+        found.push(LocalAddress { interface, ip });
+    }
+
+    // SAFETY: `head` came from a successful getifaddrs
+    unsafe { libc::freeifaddrs(head) };
+    found
+}
+
+#[cfg(not(all(unix, not(target_os = "redox"))))]
+fn interface_addresses() -> Vec<LocalAddress> {
+    Vec::new()
+}
+
+/// Source addresses of the default IPv4 and IPv6 routes
+///
+/// Connecting a UDP socket sends nothing; it only picks the route.
+fn routed_addresses() -> Vec<LocalAddress> {
+    [("0.0.0.0:0", "8.8.8.8:53"), ("[::]:0", "[2001:4860:4860::8888]:53")]
+        .iter()
+        .filter_map(|(bind, target)| {
+            let socket = UdpSocket::bind(bind).ok()?;
+            socket.connect(target).ok()?;
+            Some(LocalAddress { interface: String::new(), ip: socket.local_addr().ok()?.ip() })
+        })
+        .collect()
+}
+
+/// "Your IP address is 192.168.1.20 on wlan0."
+pub fn describe_local_addresses(addresses: &[LocalAddress]) -> String {
+    if addresses.is_empty() {
+        return "I couldn't find a network address. This machine doesn't seem to be connected.".to_string();
+    }
+    let spoken: Vec<String> = addresses
+        .iter()
+        .take(MAX_SPOKEN_ADDRESSES)
+        .map(|a| if a.interface.is_empty() { a.ip.to_string() } else { format!("{} on {}", a.ip, a.interface) })
+        .collect();
+    let noun = if addresses.len() == 1 { "address is" } else { "addresses are" };
+    format!("Your IP {} {}.", noun, spoken_list(&spoken, addresses.len()))
+}
+
+/// "a", "a and b", "a, b and 4 more"
+fn spoken_list(items: &[String], total: usize) -> String {
+    let more = total.saturating_sub(items.len());
+    match (items, more) {
+        ([only], 0) => only.clone(),
+        ([init @ .., last], 0) => format!("{} and {}", init.join(", "), last),
+        _ => format!("{} and {} more", items.join(", "), more),
+    }
+}
+
+/// Resolve `host` within `budget`
+async fn resolve(host: &str, budget: Duration) -> Result<Vec<IpAddr>, String> {
+    let lookup = tokio::net::lookup_host((host, 0));
+    let addrs = match timeout(budget, lookup).await {
+        Err(_) => return Err(format!("Looking up {} took too long. The DNS server isn't answering.", host)),
+        Ok(Err(_)) => return Err(format!("I couldn't find a host called {}.", host)),
+        Ok(Ok(addrs)) => addrs,
+    };
+
+    let mut ips: Vec<IpAddr> = Vec::new();
+    for addr in addrs {
+        if !ips.contains(&addr.ip()) {
+            ips.push(addr.ip());
+        }
+    }
+    if ips.is_empty() {
+        return Err(format!("I couldn't find a host called {}.", host));
+    }
+    Ok(ips)
+}
+
+/// "google.com is at 142.250.78.14 and 2 more addresses."
+pub async fn dns_lookup(host: &str) -> Result<String, String> {
+    let host = clean_host(host)?;
+    let ips = resolve(host, PROBE_BUDGET).await?;
+    let spoken: Vec<String> = ips.iter().take(MAX_SPOKEN_ADDRESSES).map(IpAddr::to_string).collect();
+    let list = spoken_list(&spoken, ips.len());
+    let suffix = if ips.len() > MAX_SPOKEN_ADDRESSES { " addresses" } else { "" };
+    Ok(format!("{} is at {}{}.", host, list, suffix))
+}
+
+/// "google.com responded in 23 ms average."
+pub async fn ping(host: &str) -> Result<String, String> {
+    ping_with(host, PING_PORTS, PROBE_BUDGET, true).await
+}
+
+async fn ping_with(host: &str, ports: &[u16], budget: Duration, system_fallback: bool) -> Result<String, String> {
+    let host = clean_host(host)?;
+    let deadline = Instant::now() + budget;
+    let ip = resolve(host, budget / 2).await?[0];
+
+    let mut rtts = Vec::new();
+    let mut unreachable = None;
+    'probes: for i in 0..PING_COUNT {
+        for &port in ports {
+            // Split what's left between the connects still to come
+            let left = deadline.saturating_duration_since(Instant::now());
+            let slot = left / (PING_COUNT - i) as u32;
+            if slot.is_zero() {
+                break 'probes;
+            }
+            let start = Instant::now();
+            match timeout(slot, TcpStream::connect(SocketAddr::new(ip, port))).await {
+                // Refused means something answered: the host is up
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => {}
+                Ok(Err(e)) => {
+                    unreachable = Some(e);
+                    continue;
+                }
+                Err(_) => continue,
+            }
+            rtts.push(start.elapsed());
+            continue 'probes;
+        }
+    }
+
+    if !rtts.is_empty() {
+        let average = rtts.iter().sum::<Duration>() / rtts.len() as u32;
+        let mut summary = format!("{} responded in {} average", host, spoken_ms(average.as_secs_f64() * 1000.0));
+        if rtts.len() < PING_COUNT {
+            summary.push_str(&format!(", {} of {} probes answered", rtts.len(), PING_COUNT));
+        }
+        return Ok(summary + ".");
+    }
+
+    let left = deadline.saturating_duration_since(Instant::now());
+    if system_fallback && left >= Duration::from_secs(1) {
+        if let Some(average) = system_ping(ip, left).await {
+            return Ok(format!("{} responded in {} average.", host, spoken_ms(average)));
+        }
+    }
+
+    Err(match unreachable {
+        Some(e) if e.kind() != std::io::ErrorKind::TimedOut => {
+            format!("I can't reach {}. The network says: {}.", host, e)
+        }
+        _ => format!("{} didn't respond within {} seconds.", host, budget.as_secs().max(1)),
+    })
+}
+
+/// "23 ms", "under 1 ms"
+fn spoken_ms(ms: f64) -> String {
+    if ms < 1.0 {
+        "under 1 ms".to_string()
+    } else {
+        format!("{:.0} ms", ms)
+    }
+}
+
+/// Host name as said, minus trailing punctuation; rejects anything that
+/// could pass for a command-line option
+fn clean_host(host: &str) -> Result<&str, String> {
+    let host = host.trim().trim_end_matches(['.', '?', '!', ',']);
+    if host.is_empty() {
+        return Err("Which host? Say something like \"ping google.com\".".to_string());
+    }
+    if host.starts_with('-') || host.chars().any(char::is_whitespace) {
+        return Err(format!("\"{}\" doesn't look like a host name.", host));
+    }
+    Ok(host)
+}
+
+/// Average RTT in ms from the system `ping`, for hosts that drop TCP
+async fn system_ping(ip: IpAddr, budget: Duration) -> Option<f64> {
+    let secs = budget.as_secs().max(1).to_string();
+    let mut command = std::process::Command::new("ping");
+    if cfg!(windows) {
+        command.args(["-n", "3", "-w", &(budget.as_millis() / 3).to_string()]);
+    } else if cfg!(target_os = "macos") {
+        command.args(["-c", "3", "-t", &secs]);
+    } else {
+        command.args(["-c", "3", "-w", &secs]);
+    }
+    command.arg(ip.to_string());
+
+    // The deadline flag bounds the child; the timeout bounds us if it hangs
+    let run = tokio::task::spawn_blocking(move || command.output());
+    let output = timeout(budget + Duration::from_millis(500), run).await.ok()?.ok()?.ok()?;
+    parse_ping_average(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Average RTT from `ping` output (Linux, macOS/BSD or Windows)
+fn parse_ping_average(output: &str) -> Option<f64> {
+    let unix = Regex::new(r"min/avg/max\S*\s*=\s*[\d.]+/([\d.]+)/").ok()?;
+    let windows = Regex::new(r"Average = (\d+)ms").ok()?;
+    let caps = unix.captures(output).or_else(|| windows.captures(output))?;
+    caps[1].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn test_local_addresses_skip_loopback() {
+        for address in local_addresses() {
+            assert!(is_reportable(address.ip), "{:?}", address);
+        }
+        assert!(!is_reportable(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        assert!(!is_reportable("fe80::1".parse().unwrap()));
+        assert!(is_reportable(IpAddr::V6(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 1))));
+    }
+
+    #[test]
+    fn test_describe_local_addresses() {
+        let wlan = |ip: &str| LocalAddress { interface: "wlan0".to_string(), ip: ip.parse().unwrap() };
+        assert_eq!(describe_local_addresses(&[wlan("192.168.1.20")]), "Your IP address is 192.168.1.20 on wlan0.");
+
+        let many: Vec<_> = ["10.0.0.2", "10.0.0.3", "10.0.0.4", "fd00::1", "fd00::2"].iter().map(|ip| wlan(ip)).collect();
+        assert_eq!(
+            describe_local_addresses(&many),
+            "Your IP addresses are 10.0.0.2 on wlan0, 10.0.0.3 on wlan0, 10.0.0.4 on wlan0 and 2 more."
+        );
+        assert!(describe_local_addresses(&[]).contains("doesn't seem to be connected"));
+    }
+
+    #[test]
+    fn test_parse_ping_average() {
+        let linux = "rtt min/avg/max/mdev = 21.118/23.402/25.871/1.946 ms";
+        let macos = "round-trip min/avg/max/stddev = 9.411/10.250/11.032/0.662 ms";
+        let windows = "    Minimum = 20ms, Maximum = 27ms, Average = 23ms";
+        assert_eq!(parse_ping_average(linux), Some(23.402));
+        assert_eq!(parse_ping_average(macos), Some(10.25));
+        assert_eq!(parse_ping_average(windows), Some(23.0));
+        assert_eq!(parse_ping_average("100% packet loss"), None);
+    }
+
+    #[tokio::test]
+    async fn test_ping_counts_refused_as_answered() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let open = listener.local_addr().unwrap().port();
+        let closed = {
+            let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            probe.local_addr().unwrap().port()
+        };
+
+        for port in [open, closed] {
+            let summary = ping_with("127.0.0.1", &[port], PROBE_BUDGET, false).await.unwrap();
+            assert!(summary.starts_with("127.0.0.1 responded in "), "{}", summary);
+            assert!(summary.ends_with(" average."), "{}", summary);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_host_fails_within_budget() {
+        // TEST-NET-1 is never routed, so connects fail or time out (unless
+        // a transparent proxy answers for it); either way, within budget
+        let budget = Duration::from_millis(300);
+        let started = Instant::now();
+        let answer = ping_with("192.0.2.1", &[443], budget, false).await;
+        assert!(started.elapsed() < budget + Duration::from_millis(200));
+        if let Err(err) = answer {
+            assert!(err.contains("192.0.2.1"), "{}", err);
+        }
+
+        assert!(ping("-f").await.unwrap_err().contains("doesn't look like a host name"));
+        assert!(dns_lookup("").await.unwrap_err().starts_with("Which host?"));
+    }
+
+    #[tokio::test]
+    async fn test_dns_lookup_localhost() {
+        let answer = dns_lookup("localhost").await.unwrap();
+        assert!(answer.starts_with("localhost is at "), "{}", answer);
+        assert!(answer.contains("127.0.0.1") || answer.contains("::1"), "{}", answer);
+
+        let err = dns_lookup("no-such-host.invalid").await.unwrap_err();
+        assert!(err.contains("no-such-host.invalid"), "{}", err);
+    }
+}
@@ -0,0 +1,219 @@
+//! Turns without a voice service: speech is transcribed locally with Vosk
+//! and handled like a typed line, so commands still run when EVA-Mind and
+//! Gemini are unreachable
+
+use crate::command_parser::{AudioOperation, CommandIntent, CommandParser, MacroOperation, ProfileOperation, SessionOperation, TimeMachineOperation, TimerOperation};
+use crate::stt::{Language, StreamingSttSession, SttConfig, SttEngine};
+
+/// Where a typed line or offline transcript is handled
+#[derive(Debug, Clone, PartialEq)]
+pub enum TurnRoute {
+    Timer(TimerOperation),
+    TimeMachine(TimeMachineOperation),
+    Macro(MacroOperation),
+    Profile(ProfileOperation),
+    /// "volume down", "mute yourself": the audio player
+    Audio(AudioOperation),
+    /// "remember that ...": a fact for the session's memory
+    Remember { key: String, value: String },
+    /// Anything else the command executor runs
+    Command(CommandIntent),
+    /// A compound request: several commands, run in order
+    Sequence(Vec<CommandIntent>),
+    /// "what can you do": answered from the capability report
+    Capabilities,
+    /// Not a command: needs the language model
+    Model,
+}
+
+pub fn route(parser: &CommandParser, text: &str) -> TurnRoute {
+    if crate::capabilities::is_capability_question(text) {
+        return TurnRoute::Capabilities;
+    }
+    let mut intents = parser.parse_all(text);
+    if intents.len() > 1 {
+        return TurnRoute::Sequence(intents);
+    }
+    match intents.pop().unwrap_or(CommandIntent::Unknown) {
+        CommandIntent::Timer(op) => TurnRoute::Timer(op),
+        CommandIntent::TimeMachine(op) => TurnRoute::TimeMachine(op),
+        CommandIntent::Macro(op) => TurnRoute::Macro(op),
+        CommandIntent::Profile(op) => TurnRoute::Profile(op),
+        CommandIntent::Audio(op) => TurnRoute::Audio(op),
+        CommandIntent::Session(SessionOperation::Remember { key, value }) => TurnRoute::Remember { key, value },
+        CommandIntent::Unknown => TurnRoute::Model,
+        intent => TurnRoute::Command(intent),
+    }
+}
+
+/// Whether the model, through tool calls, should decide if a parsed
+/// command was really meant (see `tools`)
+pub fn model_decides(route: &TurnRoute) -> bool {
+    match route {
+        TurnRoute::Command(intent) => crate::tools::covers(intent),
+        TurnRoute::Sequence(intents) => intents.iter().all(crate::tools::covers),
+        _ => false,
+    }
+}
+
+/// Answer for a non-command turn when no model is reachable
+pub fn offline_reply(portuguese: bool) -> &'static str {
+    if portuguese {
+        "Estou offline no momento, então só consigo executar comandos locais, como timers e arquivos."
+    } else {
+        "I'm offline right now, so I can only run local commands like timers and files."
+    }
+}
+
+/// Vosk recognizer, loaded on first use
+///
+/// Also drives the live caption: audio fed with `caption` while the user
+/// speaks is recognized as it arrives, and `finish_caption` gives the
+/// final transcript without running the utterance through again.
+pub struct OfflineRecognizer {
+    session: Option<StreamingSttSession>,
+    config: SttConfig,
+    /// Why the model couldn't be loaded, once it has been tried
+    unavailable: Option<String>,
+}
+
+impl OfflineRecognizer {
+    pub fn new(profile_language: &str) -> Self {
+        let language = Language::from_tag(profile_language).unwrap_or(Language::EnglishUS);
+        Self::with_config(SttConfig { language, ..SttConfig::default() })
+    }
+
+    pub fn with_config(config: SttConfig) -> Self {
+        Self { session: None, config, unavailable: None }
+    }
+
+    /// Follow a language change; a loaded model is re-initialised for the
+    /// new language, and one that was missing is looked for again
+    pub fn set_language(&mut self, profile_language: &str) -> Result<(), String> {
+        let language = Language::from_tag(profile_language).unwrap_or(Language::EnglishUS);
+        if language == self.config.language {
+            return Ok(());
+        }
+        self.config.language = language;
+        self.unavailable = None;
+        let Some(session) = self.session.as_mut() else {
+            return Ok(());
+        };
+        if let Err(e) = session.set_language(language) {
+            // Loaded again, for the new language, on next use
+            self.session = None;
+            return Err(e.to_string());
+        }
+        Ok(())
+    }
+
+    /// Transcribe a finished utterance (16 kHz); `Ok(None)` when nothing
+    /// was understood, `Err` when offline recognition isn't available
+    pub fn transcribe(&mut self, audio: &[f32]) -> Result<Option<String>, String> {
+        let session = self.session()?;
+        session.reset();
+        session.add_audio_f32(audio);
+        let result = session.finalize().map_err(|e| e.to_string())?;
+        let text = result.text.trim();
+        Ok((!text.is_empty()).then(|| text.to_string()))
+    }
+
+    /// Feed a chunk of the utterance being spoken (16 kHz); the partial
+    /// transcript when it changed. Captions are best effort: `None` too
+    /// when recognition isn't available.
+    pub fn caption(&mut self, chunk: &[f32]) -> Option<String> {
+        let session = self.session().ok()?;
+        session.add_audio_f32(chunk);
+        let partial = session.process().ok()??;
+        Some(partial.text.trim().to_string())
+    }
+
+    /// Final transcript of what was fed to `caption`, ready for the next
+    /// utterance; `None` if nothing was understood
+    pub fn finish_caption(&mut self) -> Option<String> {
+        let session = self.session().ok()?;
+        let result = session.finalize();
+        session.reset();
+        let text = result.ok()?.text.trim().to_string();
+        (!text.is_empty()).then_some(text)
+    }
+
+    fn session(&mut self) -> Result<&mut StreamingSttSession, String> {
+        if let Some(ref reason) = self.unavailable {
+            return Err(reason.clone());
+        }
+        if self.session.is_none() {
+            let mut engine = SttEngine::with_config(self.config.clone());
+            let loaded = if !cfg!(feature = "offline-stt") {
+                Err("EVA was built without the offline-stt feature".to_string())
+            } else if !engine.is_model_available() {
+                Err(engine.get_download_instructions())
+            } else {
+                engine.init().map_err(|e| e.to_string())
+            };
+            let started = loaded.and_then(|()| StreamingSttSession::new(engine).map_err(|e| e.to_string()));
+            match started {
+                Ok(session) => self.session = Some(session),
+                Err(reason) => {
+                    self.unavailable = Some(reason.clone());
+                    return Err(reason);
+                }
+            }
+        }
+        Ok(self.session.as_mut().expect("session started above"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_parser::FileOperation;
+
+    #[test]
+    fn test_transcripts_reach_the_right_handler() {
+        let parser = CommandParser::new();
+
+        assert!(matches!(route(&parser, "set a timer for 5 minutes"), TurnRoute::Timer(_)));
+        assert_eq!(route(&parser, "pause recording"), TurnRoute::TimeMachine(TimeMachineOperation::Pause));
+        assert_eq!(
+            route(&parser, "list files"),
+            TurnRoute::Command(CommandIntent::File(FileOperation::List { path: None }))
+        );
+        assert!(matches!(route(&parser, "call me Daniel"), TurnRoute::Profile(_)));
+        assert_eq!(route(&parser, "mute yourself"), TurnRoute::Audio(AudioOperation::Mute));
+        assert!(matches!(route(&parser, "remember that my locker is 42"), TurnRoute::Remember { .. }));
+        assert!(matches!(route(&parser, "What can you do?"), TurnRoute::Capabilities));
+        assert_eq!(route(&parser, "tell me a story about dragons"), TurnRoute::Model);
+        assert!(matches!(
+            route(&parser, "set a timer for 5 minutes and then list files"),
+            TurnRoute::Sequence(intents) if intents.len() == 2
+        ));
+        assert_eq!(route(&parser, ""), TurnRoute::Model);
+    }
+
+    #[test]
+    fn test_tool_covered_commands_defer_to_the_model() {
+        let parser = CommandParser::new();
+
+        // Only run once the model calls delete_file
+        let delete = route(&parser, "delete file foo.txt");
+        assert_eq!(delete, TurnRoute::Command(CommandIntent::File(FileOperation::Delete { path: "foo.txt".to_string() })));
+        assert!(model_decides(&delete));
+
+        assert!(!model_decides(&route(&parser, "set a timer for 5 minutes")));
+        assert!(model_decides(&route(&parser, "show memory usage and then list files")));
+        assert!(!model_decides(&route(&parser, "set a timer for 5 minutes and then list files")));
+        assert!(!model_decides(&route(&parser, "pause recording")));
+        assert!(!model_decides(&TurnRoute::Model));
+    }
+
+    #[test]
+    fn test_missing_model_is_remembered() {
+        let models_path = std::env::temp_dir().join("eva_offline_no_models").display().to_string();
+        let mut recognizer = OfflineRecognizer::with_config(SttConfig { models_path, ..SttConfig::default() });
+        let first = recognizer.transcribe(&[0.0; 1600]).unwrap_err();
+        assert!(!first.is_empty());
+        assert_eq!(recognizer.transcribe(&[0.0; 1600]).unwrap_err(), first);
+        assert!(offline_reply(true).contains("offline"));
+    }
+}
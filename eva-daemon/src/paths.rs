@@ -0,0 +1,338 @@
+//! EVA data directory resolution
+//!
+//! Every file EVA persists lives under one data root, resolved in order:
+//! 1. `EVA_DATA_DIR`
+//! 2. `XDG_DATA_HOME/eva`, or the platform data directory if it already exists
+//!    (`~/.local/share/eva`, `%APPDATA%\eva`, `~/Library/Application Support/eva`)
+//! 3. the legacy `~/.eva`, if it exists
+//! 4. the platform data directory (fresh installs)
+//!
+//! Directories are created owner-only (0700 on Unix).
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Overrides every other location
+pub const DATA_DIR_ENV: &str = "EVA_DATA_DIR";
+
+/// Written in the data root once the legacy migration was offered
+const MIGRATION_MARKER: &str = ".legacy-migration";
+
+/// Where the data root came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootSource {
+    /// `EVA_DATA_DIR`
+    Env,
+    /// `XDG_DATA_HOME`
+    Xdg,
+    /// Platform data directory
+    Platform,
+    /// `~/.eva`
+    Legacy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataRoot {
+    pub path: PathBuf,
+    pub source: RootSource,
+}
+
+/// Resolve the data root from an environment lookup (pure apart from
+/// checking which directories exist)
+pub fn resolve_with(env: impl Fn(&str) -> Option<String>) -> Option<DataRoot> {
+    let var = |name: &str| env(name).filter(|v| !v.trim().is_empty());
+
+    if let Some(dir) = var(DATA_DIR_ENV) {
+        return Some(DataRoot { path: PathBuf::from(expand_home_with(&dir, &env)), source: RootSource::Env });
+    }
+    if let Some(xdg) = var("XDG_DATA_HOME") {
+        return Some(DataRoot { path: PathBuf::from(xdg).join("eva"), source: RootSource::Xdg });
+    }
+
+    let home = home_with(&env);
+    let platform = platform_dir_with(&env, home.as_deref());
+    if let Some(ref dir) = platform {
+        if dir.is_dir() {
+            return Some(DataRoot { path: dir.clone(), source: RootSource::Platform });
+        }
+    }
+    if let Some(legacy) = home.as_ref().map(|h| h.join(".eva")) {
+        if legacy.is_dir() {
+            return Some(DataRoot { path: legacy, source: RootSource::Legacy });
+        }
+    }
+    platform.map(|path| DataRoot { path, source: RootSource::Platform })
+}
+
+/// Data root for this process, resolved once
+pub fn resolve() -> Option<&'static DataRoot> {
+    static ROOT: OnceLock<Option<DataRoot>> = OnceLock::new();
+    ROOT.get_or_init(|| resolve_with(|name| std::env::var(name).ok())).as_ref()
+}
+
+fn home_with(env: &impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let home = if cfg!(target_os = "windows") {
+        env("USERPROFILE").or_else(|| env("HOME"))
+    } else {
+        env("HOME").or_else(|| env("USERPROFILE"))
+    };
+    home.filter(|h| !h.is_empty()).map(PathBuf::from)
+}
+
+fn platform_dir_with(env: &impl Fn(&str) -> Option<String>, home: Option<&Path>) -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        env("APPDATA").map(|d| PathBuf::from(d).join("eva"))
+    } else if cfg!(target_os = "macos") {
+        home.map(|h| h.join("Library").join("Application Support").join("eva"))
+    } else {
+        home.map(|h| h.join(".local").join("share").join("eva"))
+    }
+}
+
+fn expand_home_with(path: &str, env: &impl Fn(&str) -> Option<String>) -> String {
+    match (path.strip_prefix('~'), home_with(env)) {
+        (Some(rest), Some(home)) => format!("{}{}", home.display(), rest),
+        _ => path.to_string(),
+    }
+}
+
+/// Expand a leading `~` to the home directory
+pub fn expand_home(path: &str) -> String {
+    expand_home_with(path, &|name| std::env::var(name).ok())
+}
+
+/// The user's home directory
+pub fn home_dir() -> Option<PathBuf> {
+    home_with(&|name| std::env::var(name).ok())
+}
+
+/// Create `path` (and parents) readable only by the owner
+pub fn ensure_private_dir(path: &Path) -> io::Result<()> {
+    fs::create_dir_all(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+/// The data root, created if missing
+pub fn data_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let root = resolve().ok_or("Cannot locate a data directory: set EVA_DATA_DIR or HOME")?;
+    ensure_private_dir(&root.path)?;
+    Ok(root.path.clone())
+}
+
+/// A file directly under the data root (profile.json, timers.json, ...)
+pub fn data_file(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(data_dir()?.join(name))
+}
+
+fn subdir(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = data_dir()?.join(name);
+    ensure_private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Files the command executor may touch
+pub fn sandbox_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    subdir("sandbox")
+}
+
+/// Saved conversation sessions
+pub fn sessions_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    subdir("sessions")
+}
+
+/// Per-day conversation transcripts (`YYYY-MM-DD.jsonl`)
+pub fn transcripts_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    subdir("transcripts")
+}
+
+/// Conversations exported from the terminal UI
+pub fn exports_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    subdir("exports")
+}
+
+/// Downloaded speech and vision models
+pub fn models_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    subdir("models")
+}
+
+/// Encryption keys kept as files (when there is no OS keyring)
+pub fn keys_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    subdir("keys")
+}
+
+/// Time Machine database and screenshots
+pub fn timemachine_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    subdir("timemachine")
+}
+
+/// Legacy `~/.eva` that has not been offered for migration into `root` yet
+pub fn pending_migration(root: &DataRoot, legacy: &Path) -> Option<PathBuf> {
+    let offered = root.path.join(MIGRATION_MARKER).exists();
+    let same = root.path == legacy || root.source == RootSource::Legacy;
+    (!same && !offered && legacy.is_dir()).then(|| legacy.to_path_buf())
+}
+
+/// Copy everything under `from` into `to`, keeping files that already
+/// exist there. Returns the number of files copied.
+pub fn copy_legacy(from: &Path, to: &Path) -> io::Result<u64> {
+    ensure_private_dir(to)?;
+    let mut copied = 0;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copied += copy_legacy(&entry.path(), &target)?;
+        } else if file_type.is_file() && !target.exists() {
+            fs::copy(entry.path(), &target)?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+/// Remember that the migration was offered so it is only asked once
+pub fn record_migration(root: &Path, accepted: bool) -> io::Result<()> {
+    ensure_private_dir(root)?;
+    fs::write(root.join(MIGRATION_MARKER), if accepted { "copied\n" } else { "declined\n" })
+}
+
+/// Ask once on the terminal whether to copy `~/.eva` into a newly
+/// configured data root. The legacy directory is left in place.
+pub fn offer_legacy_migration() -> Result<(), Box<dyn std::error::Error>> {
+    let (Some(root), Some(home)) = (resolve(), home_dir()) else {
+        return Ok(());
+    };
+    let Some(legacy) = pending_migration(root, &home.join(".eva")) else {
+        return Ok(());
+    };
+
+    print!(
+        "[Paths] Found existing data in {}. Copy it to {}? [y/N] ",
+        legacy.display(),
+        root.path.display()
+    );
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    let accepted = matches!(answer.trim().to_lowercase().as_str(), "y" | "yes" | "s" | "sim");
+
+    if accepted {
+        let copied = copy_legacy(&legacy, &root.path)?;
+        println!("[Paths] Copied {} files; {} was left untouched", copied, legacy.display());
+    }
+    record_migration(&root.path, accepted)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("eva_paths_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn env(vars: &[(&str, &Path)]) -> impl Fn(&str) -> Option<String> {
+        let map: HashMap<String, String> =
+            vars.iter().map(|(k, v)| (k.to_string(), v.to_string_lossy().to_string())).collect();
+        move |name| map.get(name).cloned()
+    }
+
+    fn home_vars<'a>(home: &'a Path) -> Vec<(&'static str, &'a Path)> {
+        vec![("HOME", home), ("USERPROFILE", home), ("APPDATA", home)]
+    }
+
+    #[test]
+    fn test_precedence() {
+        let home = temp_dir("precedence");
+        let custom = home.join("custom");
+        let xdg = home.join("xdg");
+
+        // Fresh install: platform directory
+        let fresh = resolve_with(env(&home_vars(&home))).unwrap();
+        assert_eq!(fresh.source, RootSource::Platform);
+
+        // Existing ~/.eva is kept while the platform directory is missing
+        fs::create_dir_all(home.join(".eva")).unwrap();
+        let legacy = resolve_with(env(&home_vars(&home))).unwrap();
+        assert_eq!(legacy, DataRoot { path: home.join(".eva"), source: RootSource::Legacy });
+
+        // ...but a platform directory that exists wins
+        fs::create_dir_all(&fresh.path).unwrap();
+        assert_eq!(resolve_with(env(&home_vars(&home))).unwrap().source, RootSource::Platform);
+
+        let mut vars = home_vars(&home);
+        vars.push(("XDG_DATA_HOME", &xdg));
+        assert_eq!(
+            resolve_with(env(&vars)).unwrap(),
+            DataRoot { path: xdg.join("eva"), source: RootSource::Xdg }
+        );
+
+        vars.push((DATA_DIR_ENV, &custom));
+        assert_eq!(resolve_with(env(&vars)).unwrap(), DataRoot { path: custom, source: RootSource::Env });
+
+        assert_eq!(resolve_with(env(&[])), None);
+        fs::remove_dir_all(&home).ok();
+    }
+
+    #[test]
+    fn test_data_dir_override_expands_home() {
+        let home = temp_dir("expand");
+        let tilde = PathBuf::from("~/eva-data");
+        let mut vars = home_vars(&home);
+        vars.push((DATA_DIR_ENV, &tilde));
+        assert_eq!(resolve_with(env(&vars)).unwrap().path, home.join("eva-data"));
+        fs::remove_dir_all(&home).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_dir_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = temp_dir("perms").join("nested");
+        ensure_private_dir(&dir).unwrap();
+        assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        fs::remove_dir_all(dir.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_migration_copy_once() {
+        let base = temp_dir("migrate");
+        let legacy = base.join(".eva");
+        fs::create_dir_all(legacy.join("sessions")).unwrap();
+        fs::write(legacy.join("profile.json"), "{\"name\":\"old\"}").unwrap();
+        fs::write(legacy.join("sessions").join("a.json"), "{}").unwrap();
+
+        let root = DataRoot { path: base.join("new"), source: RootSource::Env };
+        fs::create_dir_all(&root.path).unwrap();
+        fs::write(root.path.join("profile.json"), "{\"name\":\"new\"}").unwrap();
+
+        assert_eq!(pending_migration(&root, &legacy), Some(legacy.clone()));
+        assert_eq!(copy_legacy(&legacy, &root.path).unwrap(), 1);
+        assert!(root.path.join("sessions").join("a.json").exists());
+        // Existing files in the new root are not overwritten
+        assert_eq!(fs::read_to_string(root.path.join("profile.json")).unwrap(), "{\"name\":\"new\"}");
+        // Legacy data is left in place
+        assert!(legacy.join("profile.json").exists());
+
+        record_migration(&root.path, true).unwrap();
+        assert_eq!(pending_migration(&root, &legacy), None);
+
+        // Nothing to offer when the root is the legacy directory itself
+        let legacy_root = DataRoot { path: legacy.clone(), source: RootSource::Legacy };
+        assert_eq!(pending_migration(&legacy_root, &legacy), None);
+        fs::remove_dir_all(&base).ok();
+    }
+}
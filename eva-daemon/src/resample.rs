@@ -0,0 +1,275 @@
+//! Sample rate conversion between the 48 kHz capture stream and the 16 kHz
+//! pipeline (wake word, VAD, Gemini/EVA-Mind and Vosk all expect 16 kHz),
+//! and from reply audio to whatever rate the speaker runs at
+//!
+//! Decimation is a windowed-sinc low-pass FIR evaluated only at the output
+//! positions, so nothing above the new Nyquist folds back into speech.
+//! `SincResampler` handles any ratio the same way, interpolating between
+//! input samples from a table of precomputed filter phases.
+
+use crate::audio::{CAPTURE_SAMPLE_RATE, SAMPLE_RATE};
+
+/// Filter length; odd so the filter has a whole-sample delay
+const TAPS: usize = 63;
+
+/// Passband edge as a fraction of the new Nyquist frequency (7.2 kHz at 16 kHz)
+const CUTOFF: f32 = 0.9;
+
+/// Streaming integer-factor decimator
+///
+/// Keeps the tail of the previous block, so a stream processed chunk by
+/// chunk gives the same samples as processing it in one go.
+pub struct Decimator {
+    factor: usize,
+    taps: Vec<f32>,
+    history: Vec<f32>,
+    /// Offset into `history + input` of the next output sample's window
+    next: usize,
+}
+
+impl Decimator {
+    pub fn new(factor: usize) -> Self {
+        let factor = factor.max(1);
+        let fc = CUTOFF / (2.0 * factor as f32);
+        let mid = (TAPS - 1) as f32 / 2.0;
+        let mut taps: Vec<f32> = (0..TAPS)
+            .map(|i| {
+                let x = i as f32 - mid;
+                let sinc = if x == 0.0 {
+                    2.0 * fc
+                } else {
+                    (2.0 * std::f32::consts::PI * fc * x).sin() / (std::f32::consts::PI * x)
+                };
+                let phase = 2.0 * std::f32::consts::PI * i as f32 / (TAPS - 1) as f32;
+                let blackman = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+                sinc * blackman
+            })
+            .collect();
+        // Unity gain at DC
+        let sum: f32 = taps.iter().sum();
+        taps.iter_mut().for_each(|t| *t /= sum);
+
+        Self { factor, taps, history: vec![0.0; TAPS - 1], next: 0 }
+    }
+
+    /// Decimator from the capture rate to the pipeline rate
+    pub fn capture_to_pipeline() -> Self {
+        Self::new((CAPTURE_SAMPLE_RATE / SAMPLE_RATE) as usize)
+    }
+
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        let mut buffer = std::mem::take(&mut self.history);
+        buffer.extend_from_slice(input);
+
+        let mut output = Vec::with_capacity(input.len() / self.factor + 1);
+        while self.next + TAPS <= buffer.len() {
+            let window = &buffer[self.next..self.next + TAPS];
+            output.push(window.iter().zip(&self.taps).map(|(s, t)| s * t).sum());
+            self.next += self.factor;
+        }
+
+        // Keep the last TAPS - 1 samples for the next block
+        let keep_from = buffer.len() - (TAPS - 1);
+        self.next -= keep_from;
+        self.history = buffer.split_off(keep_from);
+        output
+    }
+
+    /// Forget buffered audio (e.g. between turns)
+    pub fn reset(&mut self) {
+        self.history = vec![0.0; TAPS - 1];
+        self.next = 0;
+    }
+}
+
+/// Taps either side of an interpolated sample
+const SINC_HALF: usize = 16;
+
+/// Filter phases tabulated between two input samples
+const SINC_PHASES: usize = 256;
+
+/// Streaming sample rate converter for arbitrary rates
+///
+/// Like `Decimator`, keeps enough of the previous block that a stream
+/// converted chunk by chunk matches converting it in one go. Output lags
+/// the input by `SINC_HALF` samples until more input arrives.
+pub struct SincResampler {
+    from_rate: u32,
+    to_rate: u32,
+    /// Input samples advanced per output sample
+    step: f64,
+    /// `SINC_PHASES + 1` rows of `2 * SINC_HALF` taps
+    table: Vec<f32>,
+    history: Vec<f32>,
+    /// Position of the next output sample in `history + input`
+    pos: f64,
+}
+
+impl SincResampler {
+    pub fn new(from_rate: u32, to_rate: u32) -> Self {
+        let from_rate = from_rate.max(1);
+        let to_rate = to_rate.max(1);
+        // Low-pass at the lower of the two Nyquist frequencies
+        let cutoff = CUTOFF as f64 * (to_rate as f64 / from_rate as f64).min(1.0);
+        let width = 2 * SINC_HALF;
+        let mut table = Vec::with_capacity((SINC_PHASES + 1) * width);
+        for phase in 0..=SINC_PHASES {
+            let frac = phase as f64 / SINC_PHASES as f64;
+            let row: Vec<f64> = (0..width)
+                .map(|j| {
+                    // Distance from the output position to tap j
+                    let x = j as f64 - (SINC_HALF - 1) as f64 - frac;
+                    let sinc = if x == 0.0 { 1.0 } else { (std::f64::consts::PI * cutoff * x).sin() / (std::f64::consts::PI * cutoff * x) };
+                    let t = (x / SINC_HALF as f64).clamp(-1.0, 1.0);
+                    let blackman = 0.42 + 0.5 * (std::f64::consts::PI * t).cos() + 0.08 * (2.0 * std::f64::consts::PI * t).cos();
+                    sinc * blackman
+                })
+                .collect();
+            // Unity gain at DC for every phase
+            let sum: f64 = row.iter().sum();
+            table.extend(row.iter().map(|t| (t / sum) as f32));
+        }
+        Self {
+            from_rate,
+            to_rate,
+            step: from_rate as f64 / to_rate as f64,
+            table,
+            history: vec![0.0; width],
+            pos: width as f64,
+        }
+    }
+
+    /// Whether this converts `from_rate` to `to_rate`
+    pub fn converts(&self, from_rate: u32, to_rate: u32) -> bool {
+        self.from_rate == from_rate && self.to_rate == to_rate
+    }
+
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        if self.from_rate == self.to_rate {
+            return input.to_vec();
+        }
+        let width = 2 * SINC_HALF;
+        let mut buffer = std::mem::take(&mut self.history);
+        buffer.extend_from_slice(input);
+
+        let mut output = Vec::with_capacity((input.len() as f64 / self.step) as usize + 1);
+        while (self.pos as usize) + SINC_HALF < buffer.len() {
+            let base = self.pos as usize;
+            let phase = ((self.pos - base as f64) * SINC_PHASES as f64).round() as usize;
+            let taps = &self.table[phase * width..(phase + 1) * width];
+            let window = &buffer[base + 1 - SINC_HALF..=base + SINC_HALF];
+            output.push(window.iter().zip(taps).map(|(s, t)| s * t).sum());
+            self.pos += self.step;
+        }
+
+        // Keep the last 2 * SINC_HALF samples for the next block
+        let keep_from = buffer.len() - width;
+        self.pos -= keep_from as f64;
+        self.history = buffer.split_off(keep_from);
+        output
+    }
+
+    /// Forget buffered audio (e.g. between turns)
+    pub fn reset(&mut self) {
+        let width = 2 * SINC_HALF;
+        self.history = vec![0.0; width];
+        self.pos = width as f64;
+    }
+}
+
+/// One-shot 48 kHz -> 16 kHz conversion
+pub fn resample_48k_to_16k(input: &[f32]) -> Vec<f32> {
+    Decimator::new(3).process(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, rate: f32, len: usize) -> Vec<f32> {
+        (0..len).map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / rate).sin() * 0.5).collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_sine_keeps_frequency_and_length() {
+        let out = resample_48k_to_16k(&sine(1000.0, 48000.0, 48000));
+        assert_eq!(out.len(), 16000);
+
+        // Skip the filter's warm-up, then count rising zero crossings over 0.5 s
+        let settled = &out[100..8100];
+        let rising = settled.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        assert!((499..=501).contains(&rising), "{} cycles", rising);
+        assert!((rms(settled) - 0.5 / 2f32.sqrt()).abs() < 0.01, "rms {}", rms(settled));
+    }
+
+    #[test]
+    fn test_rejects_tones_above_new_nyquist() {
+        // 12 kHz would alias to 4 kHz without filtering
+        let out = resample_48k_to_16k(&sine(12000.0, 48000.0, 48000));
+        assert!(rms(&out[100..]) < 0.005, "rms {}", rms(&out[100..]));
+    }
+
+    /// Frequency (in 50 Hz steps) with the most energy
+    fn dominant_frequency(samples: &[f32], rate: f32) -> f32 {
+        let power = |freq: f32| {
+            let w = 2.0 * std::f32::consts::PI * freq / rate;
+            let (re, im) = samples.iter().enumerate().fold((0.0f32, 0.0f32), |(re, im), (i, s)| {
+                (re + s * (w * i as f32).cos(), im - s * (w * i as f32).sin())
+            });
+            re * re + im * im
+        };
+        (1..(rate / 100.0) as usize).map(|i| i as f32 * 50.0).max_by(|a, b| power(*a).total_cmp(&power(*b))).unwrap()
+    }
+
+    #[test]
+    fn test_reply_tone_keeps_its_pitch_at_device_rate() {
+        // 1 kHz reply audio at 24 kHz, converted in chunks as it streams in
+        let input = sine(1000.0, 24000.0, 12000);
+        let mut resampler = SincResampler::new(24000, 48000);
+        let out: Vec<f32> = input.chunks(960).flat_map(|c| resampler.process(c)).collect();
+        assert!((out.len() as i64 - 24000).abs() <= 2 * SINC_HALF as i64, "{} samples", out.len());
+
+        let settled = &out[200..4200];
+        assert_eq!(dominant_frequency(settled, 48000.0), 1000.0);
+        assert!((rms(settled) - 0.5 / 2f32.sqrt()).abs() < 0.01, "rms {}", rms(settled));
+
+        // Played at 48 kHz without conversion, the same tone would be 2 kHz
+        assert_eq!(dominant_frequency(&input[..2000], 48000.0), 2000.0);
+    }
+
+    #[test]
+    fn test_sinc_resampler_downsamples_and_resets() {
+        let input = sine(1000.0, 48000.0, 4800 * 3 + 17);
+        let mut resampler = SincResampler::new(48000, 44100);
+        let whole = resampler.process(&input);
+        let settled = &whole[200..4610];
+        assert_eq!(dominant_frequency(settled, 44100.0), 1000.0);
+
+        resampler.reset();
+        let chunked: Vec<f32> = input.chunks(1000).flat_map(|c| resampler.process(c)).collect();
+        assert_eq!(whole.len(), chunked.len());
+        assert!(whole.iter().zip(&chunked).all(|(a, b)| (a - b).abs() < 1e-6));
+
+        let mut same = SincResampler::new(24000, 24000);
+        assert_eq!(same.process(&input[..100]), &input[..100]);
+        assert!(same.converts(24000, 24000) && !same.converts(24000, 48000));
+    }
+
+    #[test]
+    fn test_chunked_matches_whole() {
+        let input = sine(440.0, 48000.0, 4800 * 3 + 17);
+        let whole = resample_48k_to_16k(&input);
+
+        let mut decimator = Decimator::capture_to_pipeline();
+        let chunked: Vec<f32> = input.chunks(1000).flat_map(|c| decimator.process(c)).collect();
+        assert_eq!(whole.len(), chunked.len());
+        assert!(whole.iter().zip(&chunked).all(|(a, b)| (a - b).abs() < 1e-6));
+
+        decimator.reset();
+        assert_eq!(decimator.process(&input), whole);
+    }
+}
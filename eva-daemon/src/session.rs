@@ -1,8 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::time::{SystemTime, UNIX_EPOCH};
 use serde::{Serialize, Deserialize};
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chrono::{DateTime, Local, NaiveDate};
 use aes_gcm::{
     aead::{Aead, AeadCore, KeyInit, OsRng},
     Aes256Gcm, Nonce,
@@ -34,10 +37,16 @@ pub struct Turn {
     pub audio: Option<Vec<u8>>,
     #[serde(with = "serde_millis")]
     pub timestamp: SystemTime,
+    /// Detected language of the turn (locale tag), for analytics
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Output of a command EVA ran rather than something the model said
+    #[serde(default)]
+    pub command_result: bool,
 }
 
 /// Helper module for SystemTime serialization
-mod serde_millis {
+pub(crate) mod serde_millis {
     use std::time::{SystemTime, UNIX_EPOCH};
     use serde::{Deserialize, Deserializer, Serializer};
 
@@ -60,8 +69,75 @@ mod serde_millis {
     }
 }
 
-/// Conversation session manager
+/// Audit record for operations that rewrite the conversation history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditNote {
+    pub action: String,
+    pub detail: String,
+    #[serde(with = "serde_millis")]
+    pub timestamp: SystemTime,
+}
+
+/// One line of a day's transcript
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TranscriptEntry {
+    session_id: String,
+    role: Role,
+    content: String,
+    #[serde(with = "serde_millis")]
+    timestamp: SystemTime,
+    #[serde(default)]
+    language: Option<String>,
+}
+
+/// Version of the JSON written by `ConversationSession::export`
+const EXPORT_VERSION: u32 = 1;
+
+/// Form of an exported session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Readable transcript with timestamps and roles
+    Markdown,
+    /// Everything `import` needs, turn audio embedded as base64 if `audio`
+    Json { audio: bool },
+}
+
+/// A session as exported to JSON: plain, self-contained, versioned
 #[derive(Serialize, Deserialize)]
+struct SessionExport {
+    version: u32,
+    session_id: String,
+    #[serde(default)]
+    parent_id: Option<String>,
+    #[serde(with = "serde_millis")]
+    started_at: SystemTime,
+    #[serde(default)]
+    context: BTreeMap<String, String>,
+    #[serde(default)]
+    long_term_summary: String,
+    #[serde(default)]
+    memories: BTreeMap<String, String>,
+    #[serde(default)]
+    audit: Vec<AuditNote>,
+    turns: Vec<ExportedTurn>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedTurn {
+    #[serde(flatten)]
+    turn: Turn,
+    /// Base64 of the turn's audio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    audio: Option<String>,
+}
+
+/// Conversation session manager
+///
+/// Only the last `max_history` turns are kept in memory (and in the saved
+/// session); older ones are folded into a rolling summary (see `summary`).
+/// With a transcript directory set, every turn is also appended to that
+/// day's transcript, which is never trimmed.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ConversationSession {
     session_id: String,
     history: Vec<Turn>,
@@ -69,6 +145,24 @@ pub struct ConversationSession {
     #[serde(with = "serde_millis")]
     started_at: SystemTime,
     max_history: usize,
+    /// Session this one was branched from, if any
+    #[serde(default)]
+    parent_id: Option<String>,
+    #[serde(default)]
+    audit: Vec<AuditNote>,
+    /// What the turns that fell out of the history were about
+    #[serde(default)]
+    long_term_summary: String,
+    /// Turns folded into the summary locally, waiting for the model to
+    /// compress them
+    #[serde(default)]
+    evicted: Vec<Turn>,
+    /// Facts the user asked EVA to remember, by subject
+    #[serde(default)]
+    memories: BTreeMap<String, String>,
+    /// Where every turn is appended; not saved with the session
+    #[serde(skip)]
+    transcript_dir: Option<PathBuf>,
 }
 
 impl ConversationSession {
@@ -80,9 +174,20 @@ impl ConversationSession {
             context: HashMap::new(),
             started_at: SystemTime::now(),
             max_history: 10, // Keep last 10 turns
+            parent_id: None,
+            audit: Vec::new(),
+            long_term_summary: String::new(),
+            evicted: Vec::new(),
+            memories: BTreeMap::new(),
+            transcript_dir: None,
         }
     }
 
+    /// Append every turn from now on to `<dir>/YYYY-MM-DD.jsonl`
+    pub fn set_transcript_dir<P: AsRef<Path>>(&mut self, dir: P) {
+        self.transcript_dir = Some(dir.as_ref().to_path_buf());
+    }
+
     /// Generate a unique session ID
     fn generate_session_id() -> String {
         let duration = SystemTime::now()
@@ -94,24 +199,32 @@ impl ConversationSession {
 
     /// Save session to file (encrypted)
     ///
-    /// Uses AES-256-GCM encryption with a key derived from machine-specific data.
+    /// Uses AES-256-GCM encryption with the session key from the keystore.
     /// Falls back to plaintext if encryption fails (with warning).
     pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
         let json = serde_json::to_string(self)?;
 
         // Try to encrypt
-        match Self::encrypt_data(json.as_bytes()) {
+        let data = match Self::encrypt_data(json.as_bytes()) {
             Ok(encrypted) => {
                 // Save with .enc extension marker (first 4 bytes)
                 let mut data = b"ENC1".to_vec(); // Magic bytes + version
                 data.extend(encrypted);
-                fs::write(path, data)?;
+                data
             }
             Err(e) => {
                 eprintln!("[Session] Warning: Encryption failed ({}), saving plaintext", e);
-                fs::write(path, json)?;
+                json.into_bytes()
             }
-        }
+        };
+
+        // Written aside and renamed over, so an interrupted save leaves the
+        // previous session intact
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        fs::write(&partial, data)?;
+        fs::rename(&partial, path)?;
 
         Ok(())
     }
@@ -148,8 +261,9 @@ impl ConversationSession {
         Ok(session)
     }
 
-    /// Derive encryption key from machine-specific data
-    fn get_session_key() -> Result<[u8; 32], Box<dyn std::error::Error + Send + Sync>> {
+    /// Key sessions were encrypted with before the keystore, derived from
+    /// the user and host names; only used to read old files
+    fn legacy_session_key() -> Result<[u8; 32], Box<dyn std::error::Error + Send + Sync>> {
         let username = std::env::var("USERNAME")
             .or_else(|_| std::env::var("USER"))
             .unwrap_or_else(|_| "eva_user".to_string());
@@ -180,8 +294,24 @@ impl ConversationSession {
 
     /// Encrypt data using AES-256-GCM
     fn encrypt_data(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
-        let key = Self::get_session_key()?;
-        let cipher = Aes256Gcm::new(&key.into());
+        Self::encrypt_with(&crate::keystore::get_or_create_key(crate::keystore::SESSION)?, data)
+    }
+
+    /// Decrypt data using AES-256-GCM
+    ///
+    /// Files from before the keystore are read with the legacy key; the
+    /// next save re-encrypts them with the new one.
+    fn decrypt_data(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let key = crate::keystore::get_or_create_key(crate::keystore::SESSION)?;
+        Self::decrypt_with(&key, data).or_else(|e| {
+            Self::legacy_session_key()
+                .and_then(|legacy| Self::decrypt_with(&legacy, data))
+                .map_err(|_| e)
+        })
+    }
+
+    fn encrypt_with(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let cipher = Aes256Gcm::new(key.into());
         let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
 
         let ciphertext = cipher
@@ -195,14 +325,12 @@ impl ConversationSession {
         Ok(result)
     }
 
-    /// Decrypt data using AES-256-GCM
-    fn decrypt_data(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    fn decrypt_with(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
         if data.len() < 12 {
             return Err("Data too short for decryption".into());
         }
 
-        let key = Self::get_session_key()?;
-        let cipher = Aes256Gcm::new(&key.into());
+        let cipher = Aes256Gcm::new(key.into());
 
         // Extract nonce (first 12 bytes)
         let nonce = Nonce::from_slice(&data[..12]);
@@ -217,44 +345,324 @@ impl ConversationSession {
 
     /// Add a turn to the conversation
     pub fn add_turn(&mut self, role: Role, content: String) {
-        self.history.push(Turn {
+        self.push_turn(Turn {
             role,
             content,
             audio: None,
             timestamp: SystemTime::now(),
+            language: None,
+            command_result: false,
         });
+    }
 
-        // Keep only last N turns
-        if self.history.len() > self.max_history {
-            self.history.remove(0);
-        }
+    /// Add EVA's reply that came from running a command
+    pub fn add_command_result(&mut self, content: String) {
+        self.push_turn(Turn {
+            role: Role::Assistant,
+            content,
+            audio: None,
+            timestamp: SystemTime::now(),
+            language: None,
+            command_result: true,
+        });
     }
 
     /// Add a turn with audio
     pub fn add_turn_with_audio(&mut self, role: Role, content: String, audio: Vec<u8>) {
-        self.history.push(Turn {
+        self.push_turn(Turn {
             role,
             content,
             audio: Some(audio),
             timestamp: SystemTime::now(),
+            language: None,
+            command_result: false,
         });
+    }
 
+    /// Add a turn tagged with its detected language
+    pub fn add_turn_with_language(&mut self, role: Role, content: String, language: Option<String>) {
+        self.push_turn(Turn {
+            role,
+            content,
+            audio: None,
+            timestamp: SystemTime::now(),
+            language,
+            command_result: false,
+        });
+    }
+
+    fn push_turn(&mut self, turn: Turn) {
+        if let Some(ref dir) = self.transcript_dir {
+            if let Err(e) = self.append_transcript(dir, &turn) {
+                eprintln!("[Session] Warning: Could not append to transcript: {}", e);
+            }
+        }
+
+        self.history.push(turn);
+
+        // Keep only last N turns; the rest live on in the summary
         if self.history.len() > self.max_history {
-            self.history.remove(0);
+            let evicted = self.history.remove(0);
+            self.long_term_summary = crate::summary::fold(&self.long_term_summary, &evicted);
+            self.evicted.push(evicted);
+            if self.evicted.len() > crate::summary::MAX_PENDING {
+                self.evicted.remove(0);
+            }
         }
     }
 
-    /// Get conversation context as string
-    pub fn get_context(&self) -> String {
-        if self.history.is_empty() {
-            return String::new();
+    /// Transcript file for a local date
+    fn transcript_path(dir: &Path, date: NaiveDate) -> PathBuf {
+        dir.join(format!("{}.jsonl", date.format("%Y-%m-%d")))
+    }
+
+    /// Append one turn as a line: "ENC1" + base64(nonce + ciphertext), or
+    /// plain JSON if encryption fails (like `save_to_file`)
+    fn append_transcript(&self, dir: &Path, turn: &Turn) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let entry = TranscriptEntry {
+            session_id: self.session_id.clone(),
+            role: turn.role.clone(),
+            content: turn.content.clone(),
+            timestamp: turn.timestamp,
+            language: turn.language.clone(),
+        };
+        let json = serde_json::to_string(&entry)?;
+        let line = match Self::encrypt_data(json.as_bytes()) {
+            Ok(encrypted) => format!("ENC1{}", BASE64.encode(encrypted)),
+            Err(e) => {
+                eprintln!("[Session] Warning: Encryption failed ({}), appending plaintext", e);
+                json
+            }
+        };
+
+        fs::create_dir_all(dir)?;
+        let date = DateTime::<Local>::from(turn.timestamp).date_naive();
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(Self::transcript_path(dir, date))?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+
+    fn read_transcript(dir: &Path, date: NaiveDate) -> std::io::Result<Vec<TranscriptEntry>> {
+        let content = match fs::read_to_string(Self::transcript_path(dir, date)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+        let mut entries = Vec::new();
+        for line in content.lines().filter(|l| !l.trim().is_empty()) {
+            let json = match line.strip_prefix("ENC1") {
+                Some(encoded) => {
+                    let data = BASE64.decode(encoded).map_err(|e| invalid(e.to_string()))?;
+                    let plain = Self::decrypt_data(&data).map_err(|e| invalid(format!("Decryption failed: {}", e)))?;
+                    String::from_utf8(plain).map_err(|e| invalid(e.to_string()))?
+                }
+                None => line.to_string(),
+            };
+            entries.push(serde_json::from_str(&json)?);
         }
+        Ok(entries)
+    }
 
-        self.history
+    /// Every turn recorded on a local date, across sessions, oldest first
+    pub fn load_transcript(date: NaiveDate) -> Result<Vec<Turn>, Box<dyn std::error::Error>> {
+        Ok(Self::load_transcript_from(crate::paths::transcripts_dir()?, date)?)
+    }
+
+    /// Same, from a given transcript directory
+    pub fn load_transcript_from<P: AsRef<Path>>(dir: P, date: NaiveDate) -> std::io::Result<Vec<Turn>> {
+        Ok(Self::read_transcript(dir.as_ref(), date)?
+            .into_iter()
+            .map(|e| Turn { role: e.role, content: e.content, audio: None, timestamp: e.timestamp, language: e.language, command_result: false })
+            .collect())
+    }
+
+    /// Transcripts from `from` to `to` (inclusive) as readable plain text
+    pub fn export_transcripts(from: NaiveDate, to: NaiveDate) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(Self::export_transcripts_from(crate::paths::transcripts_dir()?, from, to)?)
+    }
+
+    /// Same, from a given transcript directory
+    pub fn export_transcripts_from<P: AsRef<Path>>(dir: P, from: NaiveDate, to: NaiveDate) -> std::io::Result<String> {
+        let mut out = String::new();
+        for date in from.iter_days().take_while(|d| *d <= to) {
+            let entries = Self::read_transcript(dir.as_ref(), date)?;
+            if entries.is_empty() {
+                continue;
+            }
+
+            out.push_str(&format!("=== {} ===\n", date.format("%Y-%m-%d")));
+            let mut session = None;
+            for entry in entries {
+                if session.as_ref() != Some(&entry.session_id) {
+                    out.push_str(&format!("-- {} --\n", entry.session_id));
+                    session = Some(entry.session_id.clone());
+                }
+                let time = DateTime::<Local>::from(entry.timestamp).format("%H:%M:%S");
+                out.push_str(&format!("[{}] {}: {}\n", time, entry.role, entry.content));
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// Write the session to `path` for use outside EVA. Exports are never
+    /// encrypted, so this warns that the conversation leaves in plaintext.
+    pub fn export<P: AsRef<Path>>(&self, path: P, format: ExportFormat) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let data = match format {
+            ExportFormat::Markdown => self.to_markdown(),
+            ExportFormat::Json { audio } => serde_json::to_string_pretty(&self.to_export(audio))?,
+        };
+        eprintln!("[Session] Warning: Exporting session {} unencrypted to {}", self.session_id, path.display());
+        fs::write(path, data)
+    }
+
+    /// Session from a file written by `export` in JSON form. The turns are
+    /// added as new ones would be, so an export longer than the history is
+    /// folded into the summary.
+    pub fn import<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+        let export: SessionExport = serde_json::from_slice(&fs::read(path)?)?;
+        if export.version > EXPORT_VERSION {
+            return Err(invalid(format!("Export version {} is newer than this EVA ({})", export.version, EXPORT_VERSION)));
+        }
+
+        let mut session = Self {
+            session_id: export.session_id,
+            context: export.context.into_iter().collect(),
+            started_at: export.started_at,
+            parent_id: export.parent_id,
+            audit: export.audit,
+            long_term_summary: export.long_term_summary,
+            memories: export.memories,
+            ..Self::new()
+        };
+        for ExportedTurn { mut turn, audio } in export.turns {
+            turn.audio = match audio {
+                Some(encoded) => Some(BASE64.decode(encoded).map_err(|e| invalid(format!("Turn audio: {}", e)))?),
+                None => None,
+            };
+            session.push_turn(turn);
+        }
+        Ok(session)
+    }
+
+    fn to_export(&self, audio: bool) -> SessionExport {
+        SessionExport {
+            version: EXPORT_VERSION,
+            session_id: self.session_id.clone(),
+            parent_id: self.parent_id.clone(),
+            started_at: self.started_at,
+            context: self.context.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            long_term_summary: self.long_term_summary.clone(),
+            memories: self.memories.clone(),
+            audit: self.audit.clone(),
+            turns: self
+                .history
+                .iter()
+                .map(|turn| ExportedTurn {
+                    turn: turn.clone(),
+                    audio: turn.audio.as_ref().filter(|_| audio).map(|a| BASE64.encode(a)),
+                })
+                .collect(),
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let time = |t: SystemTime| DateTime::<Local>::from(t).format("%Y-%m-%d %H:%M:%S").to_string();
+        let mut out = format!("# EVA conversation {}\n\nStarted {}", self.session_id, time(self.started_at));
+        if let Some(parent) = &self.parent_id {
+            out.push_str(&format!(", branched from {}", parent));
+        }
+        out.push_str(".\n\n");
+
+        if !self.long_term_summary.is_empty() {
+            out.push_str(&format!("## Earlier\n\n{}\n\n", self.long_term_summary));
+        }
+        if !self.memories.is_empty() {
+            out.push_str("## Remembered\n\n");
+            for (subject, fact) in &self.memories {
+                out.push_str(&format!("- **{}**: {}\n", subject, fact));
+            }
+            out.push('\n');
+        }
+
+        out.push_str("## Transcript\n");
+        for turn in &self.history {
+            out.push_str(&format!("\n### {} · {}", turn.role, time(turn.timestamp)));
+            if turn.command_result {
+                out.push_str(" (command output)");
+            }
+            out.push_str(&format!("\n\n{}\n", turn.content.trim_end()));
+            if let Some(audio) = &turn.audio {
+                out.push_str(&format!("\n_Audio: {} bytes, not included_\n", audio.len()));
+            }
+        }
+        out
+    }
+
+    /// Get conversation context as string, long-term memory first
+    pub fn get_context(&self) -> String {
+        let turns = self.history
             .iter()
             .map(|turn| format!("{}: {}", turn.role, turn.content))
             .collect::<Vec<_>>()
-            .join("\n")
+            .join("\n");
+
+        match self.memory_context() {
+            Some(memory) if turns.is_empty() => memory,
+            Some(memory) => format!("{}\n\n{}", memory, turns),
+            None => turns,
+        }
+    }
+
+    /// The summary and remembered facts, for a model that only sees the
+    /// recent turns; `None` while there are neither
+    pub fn memory_context(&self) -> Option<String> {
+        let mut sections = Vec::new();
+        if !self.long_term_summary.is_empty() {
+            sections.push(format!("Earlier in this conversation:\n{}", self.long_term_summary));
+        }
+        if !self.memories.is_empty() {
+            let facts: Vec<String> = self.memories.iter().map(|(key, value)| crate::summary::fact(key, value)).collect();
+            sections.push(format!("The user asked you to remember:\n- {}", facts.join("\n- ")));
+        }
+        (!sections.is_empty()).then(|| sections.join("\n\n"))
+    }
+
+    /// Summary of the turns no longer in the history
+    pub fn long_term_summary(&self) -> &str {
+        &self.long_term_summary
+    }
+
+    /// Turns summarized locally since the model last compressed the summary
+    pub fn evicted_turns(&self) -> &[Turn] {
+        &self.evicted
+    }
+
+    /// Replace the summary with the model's compression of it and the
+    /// evicted turns
+    pub fn set_compressed_summary(&mut self, summary: String) {
+        self.long_term_summary = summary;
+        self.evicted.clear();
+    }
+
+    /// Keep a fact for the rest of the session ("remember that ..."); a
+    /// fact about the same subject replaces the old one
+    pub fn remember(&mut self, key: &str, value: &str) {
+        self.memories.insert(key.trim().to_lowercase(), value.trim().to_string());
+    }
+
+    /// Remembered facts, by subject
+    pub fn memories(&self) -> &BTreeMap<String, String> {
+        &self.memories
+    }
+
+    /// Turns kept in memory, oldest first
+    pub fn turns(&self) -> &[Turn] {
+        &self.history
     }
 
     /// Get last N turns
@@ -289,6 +697,9 @@ impl ConversationSession {
     pub fn clear(&mut self) {
         self.history.clear();
         self.context.clear();
+        self.long_term_summary.clear();
+        self.evicted.clear();
+        self.memories.clear();
     }
 
     /// Check if session should continue
@@ -301,6 +712,75 @@ impl ConversationSession {
             .unwrap_or(false)
     }
 
+    /// Remove the last user+assistant exchange from the history
+    ///
+    /// Trailing assistant turns are dropped together with the user turn that
+    /// prompted them. Returns the removed turns (oldest first), or an empty
+    /// vector when there is nothing to forget. An audit note is recorded so
+    /// the rewrite is visible later.
+    pub fn forget_last_exchange(&mut self) -> Vec<Turn> {
+        let mut removed = Vec::new();
+
+        while let Some(turn) = self.history.pop() {
+            let is_user = turn.role == Role::User;
+            removed.push(turn);
+            if is_user {
+                break;
+            }
+        }
+
+        if removed.is_empty() {
+            return removed;
+        }
+
+        removed.reverse();
+
+        let preview: String = removed[0].content.chars().take(40).collect();
+        self.record_audit("forget_last_exchange", &format!("{} turn(s) removed, started with \"{}\"", removed.len(), preview));
+
+        removed
+    }
+
+    /// Create a copy of the session at the current point under a new ID
+    ///
+    /// The branch keeps history and context but starts its own audit trail.
+    /// Use `SessionStore::branch` to get an ID that is unique on disk.
+    pub fn branch(&self) -> Self {
+        let mut branch = self.clone();
+        branch.session_id = format!("{}_b{}", self.session_id, Self::branch_suffix());
+        branch.parent_id = Some(self.session_id.clone());
+        branch.audit.clear();
+        branch.record_audit("branch", &format!("branched from {}", self.session_id));
+        branch
+    }
+
+    /// Millisecond suffix used to tell branches apart
+    fn branch_suffix() -> u128 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+    }
+
+    /// Get parent session ID (set for branches)
+    pub fn parent_id(&self) -> Option<&str> {
+        self.parent_id.as_deref()
+    }
+
+    /// Get audit notes
+    pub fn audit_notes(&self) -> &[AuditNote] {
+        &self.audit
+    }
+
+    /// Append an audit note
+    fn record_audit(&mut self, action: &str, detail: &str) {
+        self.audit.push(AuditNote {
+            action: action.to_string(),
+            detail: detail.to_string(),
+            timestamp: SystemTime::now(),
+        });
+    }
+
     /// Set context value
     pub fn set_context(&mut self, key: String, value: String) {
         self.context.insert(key, value);
@@ -310,6 +790,13 @@ impl ConversationSession {
     pub fn get_context_value(&self, key: &str) -> Option<&String> {
         self.context.get(key)
     }
+
+    /// Every context value, sorted by key
+    pub fn context_values(&self) -> Vec<(String, String)> {
+        let mut values: Vec<(String, String)> = self.context.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        values.sort();
+        values
+    }
 }
 
 impl Default for ConversationSession {
@@ -318,6 +805,95 @@ impl Default for ConversationSession {
     }
 }
 
+/// What EVA says after forgetting the last exchange (`removed` turns)
+pub fn forgotten_reply(removed: usize, portuguese: bool) -> &'static str {
+    match (removed, portuguese) {
+        (0, true) => "Não há nada para esquecer.",
+        (0, false) => "There's nothing to forget.",
+        (_, true) => "Pronto, esqueci nossa última troca.",
+        (_, false) => "Done, I've forgotten our last exchange.",
+    }
+}
+
+/// What EVA says after branching the conversation
+pub fn branched_reply(branch: &ConversationSession, parent: &str, portuguese: bool) -> String {
+    if portuguese {
+        format!("Conversa ramificada em {}; a original continua salva como {}.", branch.session_id(), parent)
+    } else {
+        format!("Branched the conversation as {}; the original is kept as {}.", branch.session_id(), parent)
+    }
+}
+
+/// Directory of saved sessions, one encrypted file per session ID
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    /// Open (and create) a session store in the given directory
+    pub fn new<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Open the default store under the data root's sessions directory
+    pub fn open_default() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::new(crate::paths::sessions_dir()?)?)
+    }
+
+    fn path_for(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    /// Save a session under its own ID
+    pub fn save(&self, session: &ConversationSession) -> std::io::Result<()> {
+        session.save_to_file(self.path_for(session.session_id()))
+    }
+
+    /// Load a session by ID
+    pub fn load(&self, id: &str) -> std::io::Result<ConversationSession> {
+        ConversationSession::load_from_file(self.path_for(id))
+    }
+
+    /// Check whether a session ID exists in the store
+    pub fn contains(&self, id: &str) -> bool {
+        self.path_for(id).exists()
+    }
+
+    /// List stored session IDs (sorted)
+    pub fn list(&self) -> std::io::Result<Vec<String>> {
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    ids.push(stem.to_string());
+                }
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Branch a session: saves the current state, then saves and returns a
+    /// copy under a fresh ID that does not collide with any stored session
+    pub fn branch(&self, session: &ConversationSession) -> std::io::Result<ConversationSession> {
+        self.save(session)?;
+
+        let mut branch = session.branch();
+        let base = branch.session_id.clone();
+        let mut n = 1;
+        while self.contains(&branch.session_id) {
+            branch.session_id = format!("{}_{}", base, n);
+            n += 1;
+        }
+
+        self.save(&branch)?;
+        Ok(branch)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -365,10 +941,62 @@ mod tests {
         // Should only keep last 3
         assert_eq!(session.turn_count(), 3);
         
+        let kept: Vec<&str> = session.turns().iter().map(|t| t.content.as_str()).collect();
+        assert_eq!(kept, ["Message 2", "Message 3", "Message 4"]);
+    }
+
+    #[test]
+    fn test_evicted_turns_are_summarized() {
+        let mut session = ConversationSession::new();
+        session.max_history = 2;
+
+        session.add_turn(Role::User, "My flight to Lisbon leaves at 7:40 on Friday. ok?".to_string());
+        session.add_turn(Role::Assistant, "Noted, have a good trip.".to_string());
+        session.add_turn(Role::User, "What's the weather like?".to_string());
+        session.add_turn(Role::Assistant, "Sunny.".to_string());
+
+        assert_eq!(session.turn_count(), 2);
+        assert_eq!(session.evicted_turns().len(), 2);
+        let summary = session.long_term_summary();
+        assert!(!summary.is_empty());
+        for token in ["Lisbon", "7:40", "Friday"] {
+            assert!(summary.contains(token), "{} missing from {:?}", token, summary);
+        }
+        assert!(!summary.contains("good trip"));
+
+        // The context leads with it, and it survives a save
+        let context = session.get_context();
+        assert!(context.starts_with("Earlier in this conversation:"));
+        assert!(context.contains("Lisbon") && context.ends_with("Assistant: Sunny."));
+
+        let path = std::env::temp_dir().join(format!("eva_test_summary_{}.json", std::process::id()));
+        session.save_to_file(&path).unwrap();
+        let loaded = ConversationSession::load_from_file(&path).unwrap();
+        assert_eq!(loaded.long_term_summary(), summary);
+        assert_eq!(loaded.evicted_turns().len(), 2);
+        let _ = std::fs::remove_file(&path);
+
+        session.set_compressed_summary("Flying to Lisbon on Friday at 7:40.".to_string());
+        assert!(session.evicted_turns().is_empty());
+        assert!(session.get_context().contains("Flying to Lisbon"));
+    }
+
+    #[test]
+    fn test_remember() {
+        let mut session = ConversationSession::new();
+        assert!(session.memory_context().is_none());
+
+        session.remember("Wifi password hint", "the dog's name");
+        session.remember("wifi password hint", "the cat's name");
+        session.remember("I parked on level 3", "I parked on level 3");
+        assert_eq!(session.memories().len(), 2);
+
         let context = session.get_context();
-        assert!(context.contains("Message 2"));
-        assert!(context.contains("Message 4"));
-        assert!(!context.contains("Message 0"));
+        assert!(context.contains("- wifi password hint: the cat's name"));
+        assert!(context.contains("- I parked on level 3"));
+
+        session.clear();
+        assert!(session.get_context().is_empty());
     }
 
     #[test]
@@ -445,6 +1073,78 @@ mod tests {
         // Note: audio is not persisted, but should exist in memory
     }
 
+    #[test]
+    fn test_export_import_round_trip_with_audio() {
+        let dir = std::env::temp_dir().join(format!("eva_test_export_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut session = ConversationSession::new();
+        session.add_turn_with_audio(Role::User, "Turn on the lights".to_string(), vec![7u8; 3200]);
+        session.add_command_result("Lights on.".to_string());
+        session.add_turn_with_language(Role::User, "Obrigado".to_string(), Some("pt-BR".to_string()));
+        session.set_context("room".to_string(), "kitchen".to_string());
+        session.remember("dog", "Rex");
+
+        let with_audio = dir.join("with_audio.json");
+        session.export(&with_audio, ExportFormat::Json { audio: true }).unwrap();
+        // Plain JSON, not an ENC1 file
+        assert!(fs::read_to_string(&with_audio).unwrap().trim_start().starts_with('{'));
+
+        let imported = ConversationSession::import(&with_audio).unwrap();
+        assert_eq!(imported.session_id(), session.session_id());
+        assert_eq!(imported.turn_count(), 3);
+        for (a, b) in imported.turns().iter().zip(session.turns()) {
+            assert_eq!(a.role, b.role);
+            assert_eq!(a.content, b.content);
+            assert_eq!(a.timestamp.duration_since(UNIX_EPOCH).unwrap().as_millis(), b.timestamp.duration_since(UNIX_EPOCH).unwrap().as_millis());
+            assert_eq!(a.language, b.language);
+            assert_eq!(a.command_result, b.command_result);
+            assert_eq!(a.audio.as_ref().map(Vec::len), b.audio.as_ref().map(Vec::len));
+        }
+        assert_eq!(imported.turns()[0].audio.as_deref(), Some(&[7u8; 3200][..]));
+        assert_eq!(imported.get_context_value("room"), Some(&"kitchen".to_string()));
+        assert_eq!(imported.memories().get("dog"), Some(&"Rex".to_string()));
+
+        // Audio only when asked for
+        let without_audio = dir.join("without_audio.json");
+        session.export(&without_audio, ExportFormat::Json { audio: false }).unwrap();
+        assert!(fs::metadata(&without_audio).unwrap().len() < 3200);
+        let imported = ConversationSession::import(&without_audio).unwrap();
+        assert!(imported.turns().iter().all(|t| t.audio.is_none()));
+        assert_eq!(imported.turns()[0].content, "Turn on the lights");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_export_markdown() {
+        let dir = std::env::temp_dir().join(format!("eva_test_export_md_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut session = ConversationSession::new();
+        session.add_turn_with_audio(Role::User, "What's the weather?".to_string(), vec![0u8; 640]);
+        session.add_turn(Role::Assistant, "Sunny, 24 degrees.".to_string());
+        session.remember("city", "Lisbon");
+
+        let path = dir.join("session.md");
+        session.export(&path, ExportFormat::Markdown).unwrap();
+        let markdown = fs::read_to_string(&path).unwrap();
+        assert!(markdown.starts_with(&format!("# EVA conversation {}", session.session_id())));
+        assert!(markdown.contains("- **city**: Lisbon"));
+        let user = markdown.find("### User · ").unwrap();
+        let assistant = markdown.find("### Assistant · ").unwrap();
+        assert!(user < assistant);
+        assert!(markdown.contains("What's the weather?"));
+        assert!(markdown.contains("_Audio: 640 bytes, not included_"));
+
+        // Markdown is for reading only
+        assert!(ConversationSession::import(&path).is_err());
+
+        let newer = dir.join("newer.json");
+        fs::write(&newer, r#"{"version": 99, "session_id": "s", "started_at": 0, "turns": []}"#).unwrap();
+        assert_eq!(ConversationSession::import(&newer).err().map(|e| e.kind()), Some(std::io::ErrorKind::InvalidData));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_session_duration() {
         let session = ConversationSession::new();
@@ -504,6 +1204,31 @@ mod tests {
         let _ = std::fs::remove_file(&temp_path);
     }
 
+    #[test]
+    fn test_legacy_encrypted_session_is_migrated() {
+        let temp_path = std::env::temp_dir().join(format!("eva_test_legacy_session_{}.json", std::process::id()));
+
+        // Written by a build that derived the key from user and host names
+        let mut session = ConversationSession::new();
+        session.add_turn(Role::User, "Remember the old key?".to_string());
+        let legacy = ConversationSession::legacy_session_key().unwrap();
+        let mut data = b"ENC1".to_vec();
+        data.extend(ConversationSession::encrypt_with(&legacy, serde_json::to_string(&session).unwrap().as_bytes()).unwrap());
+        std::fs::write(&temp_path, data).unwrap();
+
+        let loaded = ConversationSession::load_from_file(&temp_path).expect("legacy file should load");
+        assert_eq!(loaded.turn_count(), 1);
+
+        // Saving re-encrypts with the keystore key
+        loaded.save_to_file(&temp_path).unwrap();
+        let saved = std::fs::read(&temp_path).unwrap();
+        assert!(ConversationSession::decrypt_with(&legacy, &saved[4..]).is_err());
+        let key = crate::keystore::get_or_create_key(crate::keystore::SESSION).unwrap();
+        assert!(ConversationSession::decrypt_with(&key, &saved[4..]).is_ok());
+
+        let _ = std::fs::remove_file(&temp_path);
+    }
+
     #[test]
     fn test_default_trait() {
         let session: ConversationSession = Default::default();
@@ -521,4 +1246,132 @@ mod tests {
         let recent = session.get_recent_turns(10);
         assert_eq!(recent.len(), 2);
     }
+
+    #[test]
+    fn test_turn_language_persisted() {
+        let mut session = ConversationSession::new();
+        session.add_turn_with_language(Role::User, "What time is it?".to_string(), Some("en-US".to_string()));
+
+        let json = serde_json::to_string(&session).unwrap();
+        let loaded: ConversationSession = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.get_recent_turns(1)[0].language.as_deref(), Some("en-US"));
+    }
+
+    #[test]
+    fn test_forget_last_exchange() {
+        let mut session = ConversationSession::new();
+        session.add_turn(Role::User, "First question".to_string());
+        session.add_turn(Role::Assistant, "First answer".to_string());
+        session.add_turn(Role::User, "Off the rails".to_string());
+        session.add_turn(Role::Assistant, "Bad answer".to_string());
+
+        let removed = session.forget_last_exchange();
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[0].content, "Off the rails");
+        assert_eq!(session.turn_count(), 2);
+
+        let context = session.get_context();
+        assert!(context.contains("First answer"));
+        assert!(!context.contains("Bad answer"));
+        assert_eq!(session.audit_notes().len(), 1);
+        assert_eq!(session.audit_notes()[0].action, "forget_last_exchange");
+    }
+
+    #[test]
+    fn test_forget_last_exchange_empty() {
+        let mut session = ConversationSession::new();
+        assert!(session.forget_last_exchange().is_empty());
+        assert!(session.audit_notes().is_empty());
+    }
+
+    #[test]
+    fn test_branch_copies_history() {
+        let mut session = ConversationSession::new();
+        session.add_turn(Role::User, "Hello".to_string());
+        session.set_context("topic".to_string(), "rust".to_string());
+
+        let mut branch = session.branch();
+        assert_ne!(branch.session_id(), session.session_id());
+        assert_eq!(branch.parent_id(), Some(session.session_id()));
+        assert_eq!(branch.turn_count(), 1);
+        assert_eq!(branch.get_context_value("topic"), Some(&"rust".to_string()));
+
+        // Diverging the branch leaves the original untouched
+        branch.add_turn(Role::Assistant, "Alternative".to_string());
+        assert_eq!(session.turn_count(), 1);
+    }
+
+    #[test]
+    fn test_store_integrity_after_repeated_undo_and_branch() {
+        let dir = std::env::temp_dir().join(format!("eva_test_store_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = SessionStore::new(&dir).unwrap();
+
+        let mut session = ConversationSession::new();
+        for i in 0..4 {
+            session.add_turn(Role::User, format!("Q{}", i));
+            session.add_turn(Role::Assistant, format!("A{}", i));
+        }
+
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let branch = store.branch(&session).unwrap();
+            ids.push(branch.session_id().to_string());
+            session.forget_last_exchange();
+            store.save(&session).unwrap();
+        }
+
+        // Every branch got a distinct ID and the original is stored too
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+        assert_eq!(store.list().unwrap().len(), 4);
+
+        // Branches kept the history from the point they were taken
+        let first = store.load(&ids[0]).unwrap();
+        assert_eq!(first.parent_id(), Some(session.session_id()));
+
+        let reloaded = store.load(session.session_id()).unwrap();
+        assert_eq!(reloaded.turn_count(), 2);
+        assert_eq!(reloaded.audit_notes().len(), 3);
+        assert!(reloaded.get_context().contains("A0"));
+        assert!(!reloaded.get_context().contains("A1"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_transcript_keeps_everything_on_disk() {
+        let dir = std::env::temp_dir().join(format!("eva_test_transcripts_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut session = ConversationSession::new();
+        session.set_transcript_dir(&dir);
+        for i in 0..8 {
+            session.add_turn(Role::User, format!("Q{}", i));
+            session.add_turn_with_language(Role::Assistant, format!("A{}", i), Some("pt-BR".to_string()));
+        }
+        // Memory is still capped
+        assert_eq!(session.turn_count(), 10);
+
+        let today = Local::now().date_naive();
+        let turns = ConversationSession::load_transcript_from(&dir, today).unwrap();
+        assert_eq!(turns.len(), 16);
+        assert_eq!(turns[0].content, "Q0");
+        assert_eq!(turns[15].language.as_deref(), Some("pt-BR"));
+
+        // Not readable on disk
+        let raw = std::fs::read_to_string(dir.join(format!("{}.jsonl", today.format("%Y-%m-%d")))).unwrap();
+        assert!(raw.lines().all(|l| l.starts_with("ENC1")));
+        assert!(!raw.contains("\"content\"") && !raw.contains("Assistant"));
+
+        let text = ConversationSession::export_transcripts_from(&dir, today.pred_opt().unwrap(), today).unwrap();
+        assert!(text.starts_with(&format!("=== {} ===", today.format("%Y-%m-%d"))));
+        assert!(text.contains(session.session_id()));
+        assert!(text.contains("] User: Q0\n"));
+        assert!(text.contains("] Assistant: A7\n"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
 }
@@ -0,0 +1,236 @@
+//! Orderly exit for the daemon
+//!
+//! Ctrl-C, SIGTERM or `q` in the live view request a shutdown; the main
+//! loop notices on its next pass and runs `shut_down`, which stops the
+//! Time Machine before anything is saved and hands the terminal back last,
+//! so nothing is written after the screen is restored. A second Ctrl-C (or
+//! SIGTERM) while that runs exits on the spot.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Exit status of a forced exit, as if the signal had killed the process
+pub const FORCED_EXIT_CODE: i32 = 130;
+
+/// Longest wait for the capture in progress to finish and the index to be saved
+pub const RECORDER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest wait for Gemini's close handshake
+pub const GEMINI_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The parts of a shutdown, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    StopCapture,
+    AwaitRecorder,
+    SaveSession,
+    SaveStatistics,
+    CloseGemini,
+    RestoreTerminal,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Step::StopCapture => "stopping capture",
+            Step::AwaitRecorder => "waiting for the Time Machine",
+            Step::SaveSession => "saving the session",
+            Step::SaveStatistics => "saving statistics",
+            Step::CloseGemini => "closing the Gemini session",
+            Step::RestoreTerminal => "restoring the terminal",
+        })
+    }
+}
+
+pub type StepFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + 'a>>;
+
+/// What a shutdown tears down
+pub trait Components {
+    /// Ask the Time Machine's capture loop to stop after the current tick
+    fn stop_capture(&mut self);
+    /// Wait for the recording task, which saves the index on its way out
+    fn await_recorder(&mut self) -> StepFuture<'_>;
+    fn save_session(&mut self) -> Result<(), String>;
+    fn save_statistics(&mut self) -> Result<(), String>;
+    fn close_gemini(&mut self) -> StepFuture<'_>;
+    fn restore_terminal(&mut self);
+}
+
+/// Run every step in order, bounding the ones that wait on something else;
+/// a failed step doesn't stop the rest. Returns what went wrong, to report
+/// once the terminal is back.
+pub async fn shut_down(components: &mut dyn Components) -> Vec<(Step, String)> {
+    let mut failures = Vec::new();
+
+    components.stop_capture();
+    match tokio::time::timeout(RECORDER_TIMEOUT, components.await_recorder()).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => failures.push((Step::AwaitRecorder, e)),
+        Err(_) => failures.push((Step::AwaitRecorder, format!("still busy after {}s", RECORDER_TIMEOUT.as_secs()))),
+    }
+    if let Err(e) = components.save_session() {
+        failures.push((Step::SaveSession, e));
+    }
+    if let Err(e) = components.save_statistics() {
+        failures.push((Step::SaveStatistics, e));
+    }
+    match tokio::time::timeout(GEMINI_TIMEOUT, components.close_gemini()).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => failures.push((Step::CloseGemini, e)),
+        Err(_) => failures.push((Step::CloseGemini, format!("no answer after {}s", GEMINI_TIMEOUT.as_secs()))),
+    }
+    components.restore_terminal();
+
+    failures
+}
+
+/// Shared "please shut down" flag
+#[derive(Clone, Default)]
+pub struct ShutdownSignal {
+    requested: Arc<AtomicBool>,
+}
+
+impl ShutdownSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask for a shutdown; `false` if one was already requested
+    pub fn request(&self) -> bool {
+        !self.requested.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Trap Ctrl-C and SIGTERM: the first requests a shutdown, the next
+    /// one resets the terminal and exits without waiting
+    pub fn trap_signals(&self) {
+        let signal = self.clone();
+        tokio::spawn(async move {
+            #[cfg(unix)]
+            let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).ok();
+            loop {
+                #[cfg(unix)]
+                let terminated = async {
+                    match terminate.as_mut() {
+                        Some(stream) => {
+                            stream.recv().await;
+                        }
+                        None => std::future::pending().await,
+                    }
+                };
+                #[cfg(not(unix))]
+                let terminated = std::future::pending::<()>();
+
+                tokio::select! {
+                    caught = tokio::signal::ctrl_c() => {
+                        // Without a handler Ctrl-C would never arrive; leave
+                        // the default behaviour in place
+                        if caught.is_err() {
+                            return;
+                        }
+                    }
+                    _ = terminated => {}
+                }
+                if !signal.request() {
+                    crate::terminal_ui::reset_terminal();
+                    eprintln!("\nForced exit");
+                    std::process::exit(FORCED_EXIT_CODE);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records the steps it was asked to run
+    #[derive(Default)]
+    struct Recorded {
+        steps: Vec<Step>,
+        failing: Option<Step>,
+    }
+
+    impl Recorded {
+        fn run(&mut self, step: Step) -> Result<(), String> {
+            self.steps.push(step);
+            if self.failing == Some(step) {
+                return Err("disk full".to_string());
+            }
+            Ok(())
+        }
+    }
+
+    impl Components for Recorded {
+        fn stop_capture(&mut self) {
+            self.steps.push(Step::StopCapture);
+        }
+
+        fn await_recorder(&mut self) -> StepFuture<'_> {
+            let result = self.run(Step::AwaitRecorder);
+            Box::pin(async move { result })
+        }
+
+        fn save_session(&mut self) -> Result<(), String> {
+            self.run(Step::SaveSession)
+        }
+
+        fn save_statistics(&mut self) -> Result<(), String> {
+            self.run(Step::SaveStatistics)
+        }
+
+        fn close_gemini(&mut self) -> StepFuture<'_> {
+            let result = self.run(Step::CloseGemini);
+            Box::pin(async move { result })
+        }
+
+        fn restore_terminal(&mut self) {
+            self.steps.push(Step::RestoreTerminal);
+        }
+    }
+
+    const ALL_STEPS: [Step; 6] = [
+        Step::StopCapture,
+        Step::AwaitRecorder,
+        Step::SaveSession,
+        Step::SaveStatistics,
+        Step::CloseGemini,
+        Step::RestoreTerminal,
+    ];
+
+    #[tokio::test]
+    async fn test_steps_run_in_order() {
+        let mut components = Recorded::default();
+        assert!(shut_down(&mut components).await.is_empty());
+        assert_eq!(components.steps, ALL_STEPS);
+    }
+
+    #[tokio::test]
+    async fn test_failed_step_does_not_stop_the_rest() {
+        let mut components = Recorded { failing: Some(Step::SaveSession), ..Recorded::default() };
+        let failures = shut_down(&mut components).await;
+
+        assert_eq!(failures, vec![(Step::SaveSession, "disk full".to_string())]);
+        assert_eq!(components.steps, ALL_STEPS);
+    }
+
+    #[test]
+    fn test_second_request_is_reported() {
+        let signal = ShutdownSignal::new();
+        let seen_by_loop = signal.clone();
+        assert!(!seen_by_loop.is_requested());
+
+        assert!(signal.request());
+        assert!(seen_by_loop.is_requested());
+        // The signal handler force-exits on this one
+        assert!(!signal.request());
+    }
+}
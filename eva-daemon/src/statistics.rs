@@ -1,12 +1,180 @@
+use crate::audio_processor::StageMetrics;
+use crate::gemini::ConnectionState;
+use crate::metrics::SystemMetrics;
+use crate::vad::VadEnergy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, SystemTime};
 
+/// Layout of stats.json; bump when the meaning of a field changes
+const STATS_VERSION: u32 = 1;
+
+/// Upper edges of the latency buckets in milliseconds, roughly log-spaced;
+/// anything slower lands in a final overflow bucket
+const BUCKET_BOUNDS_MS: [u64; 15] = [25, 50, 100, 150, 250, 400, 600, 1000, 1500, 2500, 4000, 6000, 10000, 15000, 30000];
+
+/// A timed step of a voice turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LatencyStage {
+    /// Wake word heard until the user stops talking
+    WakeToEndOfSpeech,
+    /// Last audio sent until the first byte of the reply
+    SendToFirstResponse,
+    /// First reply byte until it comes out of the speaker
+    ResponseToPlayback,
+}
+
+impl LatencyStage {
+    pub const ALL: [LatencyStage; 3] =
+        [LatencyStage::WakeToEndOfSpeech, LatencyStage::SendToFirstResponse, LatencyStage::ResponseToPlayback];
+
+    /// Name in stats.json
+    fn key(self) -> &'static str {
+        match self {
+            LatencyStage::WakeToEndOfSpeech => "wake_to_end_of_speech",
+            LatencyStage::SendToFirstResponse => "send_to_first_response",
+            LatencyStage::ResponseToPlayback => "response_to_playback",
+        }
+    }
+
+    /// Short name for the stats panel
+    pub fn label(self) -> &'static str {
+        match self {
+            LatencyStage::WakeToEndOfSpeech => "speech",
+            LatencyStage::SendToFirstResponse => "reply",
+            LatencyStage::ResponseToPlayback => "playback",
+        }
+    }
+}
+
+/// Where a turn's answer came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AnswerSource {
+    /// Worked out on this machine: commands, local skills, offline replies
+    Local,
+    /// Gemini's earlier answer to the same question
+    Cached,
+    /// The model, over the network
+    Remote,
+}
+
+impl AnswerSource {
+    pub const ALL: [AnswerSource; 3] = [AnswerSource::Local, AnswerSource::Cached, AnswerSource::Remote];
+
+    /// Name in stats.json, the stats panel and the control status
+    pub fn key(self) -> &'static str {
+        match self {
+            AnswerSource::Local => "local",
+            AnswerSource::Cached => "cached",
+            AnswerSource::Remote => "remote",
+        }
+    }
+}
+
+/// Fixed-bucket latency histogram; percentiles are bucket upper edges
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+    /// Slowest sample, reported for the overflow bucket
+    max_ms: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self { counts: vec![0; BUCKET_BOUNDS_MS.len() + 1], max_ms: 0 }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let ms = latency.as_millis().min(u64::MAX as u128) as u64;
+        let bucket = BUCKET_BOUNDS_MS.iter().position(|&edge| ms <= edge).unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.counts[bucket] += 1;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Latency at or below which `fraction` of the samples fall, `None`
+    /// before anything was recorded
+    pub fn percentile(&self, fraction: f64) -> Option<Duration> {
+        let total = self.count();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((fraction.clamp(0.0, 1.0) * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let edge = BUCKET_BOUNDS_MS.get(bucket).map_or(self.max_ms, |&edge| edge.min(self.max_ms));
+                return Some(Duration::from_millis(edge));
+            }
+        }
+        None
+    }
+
+    /// Add another histogram's samples (ignored if its buckets differ)
+    fn merge(&mut self, other: &LatencyHistogram) {
+        if other.counts.len() == self.counts.len() {
+            self.counts.iter_mut().zip(&other.counts).for_each(|(a, b)| *a += b);
+            self.max_ms = self.max_ms.max(other.max_ms);
+        }
+    }
+}
+
+/// What survives a restart (stats.json in the data directory)
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedStats {
+    /// Missing in files written before versioning
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    turns: usize,
+    #[serde(default)]
+    commands_executed: usize,
+    /// Keyed by stage name and read leniently, so stages or histogram
+    /// layouts this build doesn't know are skipped
+    #[serde(default)]
+    latency: BTreeMap<String, serde_json::Value>,
+    /// Turns per answer source, keyed by source name
+    #[serde(default)]
+    answers: BTreeMap<String, u64>,
+}
+
+/// Statistics as updated by the main loop and the metrics sampler, and
+/// drawn by the terminal UI
+pub type SharedStatistics = Arc<RwLock<Statistics>>;
+
 /// Statistics tracker
+///
+/// Turns, commands and latencies accumulate across runs when loaded with
+/// `load`; uptime and the live panels are per run.
 pub struct Statistics {
     pub turns: usize,
     pub commands_executed: usize,
     pub uptime_seconds: u64,
-    pub memory_mb: usize,
+    /// Latest sample from `metrics::Sampler`
+    pub system: SystemMetrics,
+    /// Per-stage DSP timings (capture then playback)
+    pub dsp_stages: Vec<StageMetrics>,
+    /// Active timers and their time left, soonest first
+    pub timers: Vec<(String, Duration)>,
+    /// Mic level against the VAD's noise floor, while listening
+    pub vad: Option<VadEnergy>,
+    /// Messages waiting to go out over the WebSocket, while streaming
+    pub send_queue: Option<usize>,
+    latency: BTreeMap<LatencyStage, LatencyHistogram>,
+    answers: BTreeMap<AnswerSource, u64>,
     start_time: SystemTime,
+    path: Option<PathBuf>,
+    /// Something worth saving changed since the last save
+    dirty: bool,
 }
 
 impl Statistics {
@@ -16,19 +184,139 @@ impl Statistics {
             turns: 0,
             commands_executed: 0,
             uptime_seconds: 0,
-            memory_mb: 0,
+            system: SystemMetrics::default(),
+            dsp_stages: Vec::new(),
+            timers: Vec::new(),
+            vad: None,
+            send_queue: None,
+            latency: BTreeMap::new(),
+            answers: BTreeMap::new(),
             start_time: SystemTime::now(),
+            path: None,
+            dirty: false,
         }
     }
 
+    /// Load totals from the data directory (fresh if missing)
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load_from(crate::paths::data_file("stats.json")?)
+    }
+
+    /// Load totals from a specific file; saves go back to the same file
+    pub fn load_from(path: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut stats = Self::new();
+        if path.exists() {
+            let saved: SavedStats = serde_json::from_str(&fs::read_to_string(&path)?)?;
+            stats.merge(saved);
+        }
+        stats.path = Some(path);
+        Ok(stats)
+    }
+
+    /// Add saved totals to these. Counters mean the same in every version;
+    /// histograms from a newer build may use other buckets and are dropped.
+    fn merge(&mut self, saved: SavedStats) {
+        self.turns += saved.turns;
+        self.commands_executed += saved.commands_executed;
+        for source in AnswerSource::ALL {
+            *self.answers.entry(source).or_default() += saved.answers.get(source.key()).copied().unwrap_or(0);
+        }
+        if saved.version > STATS_VERSION {
+            return;
+        }
+        for stage in LatencyStage::ALL {
+            let saved = saved.latency.get(stage.key()).cloned().map(serde_json::from_value::<LatencyHistogram>);
+            if let Some(Ok(histogram)) = saved {
+                self.latency.entry(stage).or_default().merge(&histogram);
+            }
+        }
+    }
+
+    /// Persist totals (no-op for in-memory statistics)
+    pub fn save(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(path) = &self.path else { return Ok(()) };
+
+        let saved = SavedStats {
+            version: STATS_VERSION,
+            turns: self.turns,
+            commands_executed: self.commands_executed,
+            latency: self
+                .latency
+                .iter()
+                .filter_map(|(stage, h)| Some((stage.key().to_string(), serde_json::to_value(h).ok()?)))
+                .collect(),
+            answers: self.answers.iter().map(|(source, &count)| (source.key().to_string(), count)).collect(),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&saved)?)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Whether totals changed since they were last saved
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
     /// Increment conversation turns
     pub fn increment_turns(&mut self) {
         self.turns += 1;
+        self.dirty = true;
     }
 
     /// Increment commands executed
     pub fn increment_commands(&mut self) {
         self.commands_executed += 1;
+        self.dirty = true;
+    }
+
+    /// Add one turn's timing of a stage
+    pub fn record_latency(&mut self, stage: LatencyStage, latency: Duration) {
+        self.latency.entry(stage).or_default().record(latency);
+        self.dirty = true;
+    }
+
+    /// Count a turn served from `source`
+    pub fn record_answer(&mut self, source: AnswerSource) {
+        *self.answers.entry(source).or_default() += 1;
+        self.dirty = true;
+    }
+
+    /// Turns served from `source`
+    pub fn answers(&self, source: AnswerSource) -> u64 {
+        self.answers.get(&source).copied().unwrap_or(0)
+    }
+
+    /// Format turns per answer source, e.g. "12 local | 3 cached | 40 remote"
+    pub fn get_answers_string(&self) -> String {
+        if self.answers.values().all(|&count| count == 0) {
+            return String::new();
+        }
+        AnswerSource::ALL.iter().map(|&source| format!("{} {}", self.answers(source), source.key())).collect::<Vec<_>>().join(" | ")
+    }
+
+    /// Median latency of a stage, once it has been measured
+    pub fn latency_p50(&self, stage: LatencyStage) -> Option<Duration> {
+        self.latency.get(&stage).and_then(|h| h.percentile(0.5))
+    }
+
+    /// 95th percentile latency of a stage
+    pub fn latency_p95(&self, stage: LatencyStage) -> Option<Duration> {
+        self.latency.get(&stage).and_then(|h| h.percentile(0.95))
+    }
+
+    /// Format latency percentiles, e.g. "reply p50 400ms p95 1.5s"
+    pub fn get_latency_string(&self) -> String {
+        LatencyStage::ALL
+            .iter()
+            .filter_map(|&stage| {
+                let (p50, p95) = (self.latency_p50(stage)?, self.latency_p95(stage)?);
+                Some(format!("{} p50 {} p95 {}", stage.label(), format_latency(p50), format_latency(p95)))
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
     }
 
     /// Update uptime
@@ -38,24 +326,100 @@ impl Statistics {
         }
     }
 
-    /// Update memory usage
-    pub fn update_memory(&mut self) {
-        use sysinfo::{PidExt, ProcessExt, System, SystemExt};
-        let mut sys = System::new_all();
-        sys.refresh_all();
-        
-        if let Some(process) = sys.process(sysinfo::Pid::from_u32(std::process::id())) {
-            self.memory_mb = (process.memory() / 1024 / 1024) as usize;
-        } else {
-             // Fallback if process not found (rare)
-            self.memory_mb = 0;
+    /// Record the latest system metrics
+    pub fn update_system(&mut self, metrics: SystemMetrics) {
+        self.system = metrics;
+    }
+
+    /// "CPU 12% | RAM 85MB (3.2GB free) | Audio 240ms | Gemini connected |
+    /// NPU READY", leaving out what wasn't measured
+    pub fn get_system_string(&self) -> String {
+        let system = &self.system;
+        let mut parts = Vec::new();
+        if let Some(cpu) = system.cpu_percent {
+            parts.push(format!("CPU {:.0}%", cpu));
+        }
+        match (system.rss_mb, system.free_mb) {
+            (Some(rss), Some(free)) => parts.push(format!("RAM {}MB ({} free)", rss, format_megabytes(free))),
+            (Some(rss), None) => parts.push(format!("RAM {}MB", rss)),
+            (None, Some(free)) => parts.push(format!("RAM {} free", format_megabytes(free))),
+            (None, None) => {}
         }
+        if let Some(buffered) = system.audio_buffered {
+            parts.push(format!("Audio {}", format_latency(buffered)));
+        }
+        if let Some(gemini) = system.gemini {
+            parts.push(match gemini {
+                ConnectionState::Connected => "Gemini connected".to_string(),
+                ConnectionState::Reconnecting { attempt } => format!("Gemini reconnecting ({})", attempt),
+                ConnectionState::Disconnected => "Gemini disconnected".to_string(),
+            });
+        }
+        if let Some(npu) = &system.npu_state {
+            parts.push(format!("NPU {}", npu));
+        }
+        parts.join(" | ")
+    }
+
+    /// Record the latest DSP stage metrics
+    pub fn update_dsp(&mut self, capture: Vec<StageMetrics>, playback: Vec<StageMetrics>) {
+        self.dsp_stages = capture;
+        self.dsp_stages.extend(playback);
+    }
+
+    /// Format DSP stage timings, e.g. "agc 12.3µs, limiter 1.0µs"
+    pub fn get_dsp_string(&self) -> String {
+        self.dsp_stages
+            .iter()
+            .filter(|m| m.enabled)
+            .map(|m| format!("{} {:.1}µs", m.name, m.avg_micros()))
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 
-    /// Update all statistics
-    pub fn update_all(&mut self) {
-        self.update_uptime();
-        self.update_memory();
+    /// Record the active timers
+    pub fn update_timers(&mut self, timers: Vec<(String, Duration)>) {
+        self.timers = timers;
+    }
+
+    /// Format timer countdowns, e.g. "pasta 4:05 | laundry 12:00"
+    pub fn get_timers_string(&self) -> String {
+        self.timers
+            .iter()
+            .map(|(label, left)| format!("{} {}", label, crate::timers::format_countdown(*left)))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    /// Record the latest VAD levels
+    pub fn update_vad(&mut self, energy: VadEnergy) {
+        self.vad = Some(energy);
+    }
+
+    /// Format VAD levels, e.g. "level 0.120 | floor 0.015 | speech"
+    pub fn get_vad_string(&self) -> String {
+        match self.vad {
+            Some(v) => format!(
+                "level {:.3} | floor {:.3} | {}",
+                v.energy,
+                v.noise_floor,
+                if v.in_speech { "speech" } else { "quiet" }
+            ),
+            None => String::new(),
+        }
+    }
+
+    /// Record the WebSocket send queue depth (`None` when not streaming)
+    pub fn update_send_queue(&mut self, depth: Option<usize>) {
+        self.send_queue = depth;
+    }
+
+    /// Format the send queue, e.g. "3/32"
+    pub fn get_send_queue_string(&self) -> String {
+        match self.send_queue {
+            Some(depth) => format!("{}/{}", depth, crate::websocket::SEND_QUEUE_CAPACITY),
+            None => String::new(),
+        }
     }
 
     /// Get formatted uptime string
@@ -74,6 +438,24 @@ impl Statistics {
     }
 }
 
+/// "400ms" below a second, "1.5s" above
+fn format_latency(latency: Duration) -> String {
+    if latency < Duration::from_secs(1) {
+        format!("{}ms", latency.as_millis())
+    } else {
+        format!("{:.1}s", latency.as_secs_f64())
+    }
+}
+
+/// "512MB" below a gigabyte, "3.2GB" above
+fn format_megabytes(mb: u64) -> String {
+    if mb < 1024 {
+        format!("{}MB", mb)
+    } else {
+        format!("{:.1}GB", mb as f64 / 1024.0)
+    }
+}
+
 impl Default for Statistics {
     fn default() -> Self {
         Self::new()
@@ -120,4 +502,102 @@ mod tests {
         let uptime_str = stats.get_uptime_string();
         assert_eq!(uptime_str, "1h 1m 5s");
     }
+
+    #[test]
+    fn test_dsp_string() {
+        let mut stats = Statistics::new();
+        stats.update_dsp(
+            vec![StageMetrics { name: "agc", enabled: true, frames: 2, total_time: Duration::from_micros(20) }],
+            vec![StageMetrics { name: "limiter", enabled: false, frames: 0, total_time: Duration::ZERO }],
+        );
+
+        assert_eq!(stats.dsp_stages.len(), 2);
+        assert_eq!(stats.get_dsp_string(), "agc 10.0µs");
+    }
+
+    #[test]
+    fn test_system_string() {
+        let mut stats = Statistics::new();
+        assert_eq!(stats.get_system_string(), "");
+
+        stats.update_system(SystemMetrics {
+            cpu_percent: Some(12.4),
+            rss_mb: Some(85),
+            free_mb: Some(3277),
+            audio_buffered: Some(Duration::from_millis(240)),
+            gemini: Some(ConnectionState::Reconnecting { attempt: 2 }),
+            npu_state: Some("READY".to_string()),
+        });
+        assert_eq!(stats.get_system_string(), "CPU 12% | RAM 85MB (3.2GB free) | Audio 240ms | Gemini reconnecting (2) | NPU READY");
+
+        // Whatever a probe couldn't measure is left out
+        stats.update_system(SystemMetrics { free_mb: Some(512), ..Default::default() });
+        assert_eq!(stats.get_system_string(), "RAM 512MB free");
+    }
+
+    #[test]
+    fn test_vad_string() {
+        let mut stats = Statistics::new();
+        assert_eq!(stats.get_vad_string(), "");
+
+        stats.update_vad(VadEnergy { energy: 0.12, noise_floor: 0.015, attack: 0.045, release: 0.027, in_speech: true });
+        assert_eq!(stats.get_vad_string(), "level 0.120 | floor 0.015 | speech");
+    }
+
+    #[test]
+    fn test_latency_percentiles() {
+        let mut stats = Statistics::new();
+        assert_eq!(stats.latency_p50(LatencyStage::SendToFirstResponse), None);
+        assert_eq!(stats.get_latency_string(), "");
+
+        for ms in [80, 90, 300, 350, 380, 390, 395, 398, 399, 1200] {
+            stats.record_latency(LatencyStage::SendToFirstResponse, Duration::from_millis(ms));
+        }
+        stats.record_latency(LatencyStage::ResponseToPlayback, Duration::from_secs(45));
+
+        assert_eq!(stats.latency_p50(LatencyStage::SendToFirstResponse), Some(Duration::from_millis(400)));
+        assert_eq!(stats.latency_p95(LatencyStage::SendToFirstResponse), Some(Duration::from_millis(1200)));
+        // Past the last bucket the slowest sample is reported
+        assert_eq!(stats.latency_p95(LatencyStage::ResponseToPlayback), Some(Duration::from_secs(45)));
+        assert_eq!(stats.get_latency_string(), "reply p50 400ms p95 1.2s | playback p50 45.0s p95 45.0s");
+    }
+
+    #[test]
+    fn test_totals_accumulate_across_runs() {
+        let dir = std::env::temp_dir().join(format!("eva_stats_test_{}", std::process::id()));
+        let path = dir.join("stats.json");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut first = Statistics::load_from(path.clone()).unwrap();
+        assert!(!first.is_dirty());
+        first.increment_turns();
+        first.increment_commands();
+        first.record_latency(LatencyStage::WakeToEndOfSpeech, Duration::from_millis(1400));
+        first.record_answer(AnswerSource::Cached);
+        assert!(first.is_dirty());
+        first.save().unwrap();
+        assert!(!first.is_dirty());
+
+        let mut second = Statistics::load_from(path.clone()).unwrap();
+        second.increment_turns();
+        second.record_latency(LatencyStage::WakeToEndOfSpeech, Duration::from_millis(1400));
+        assert_eq!((second.turns, second.commands_executed), (2, 1));
+        assert_eq!(second.latency.get(&LatencyStage::WakeToEndOfSpeech).map(|h| h.count()), Some(2));
+        second.record_answer(AnswerSource::Remote);
+        assert_eq!(second.get_answers_string(), "0 local | 1 cached | 1 remote");
+
+        // Files from before versioning, and from newer builds, still load
+        fs::write(&path, r#"{"turns": 7, "commands_executed": 3}"#).unwrap();
+        let old = Statistics::load_from(path.clone()).unwrap();
+        assert_eq!((old.turns, old.commands_executed), (7, 3));
+        assert_eq!(old.get_answers_string(), "");
+
+        fs::write(&path, r#"{"version": 9, "turns": 4, "latency": {"wake_to_end_of_speech": {"buckets": "log2"}}, "extra": true}"#).unwrap();
+        let newer = Statistics::load_from(path).unwrap();
+        assert_eq!(newer.turns, 4);
+        assert_eq!(newer.latency_p50(LatencyStage::WakeToEndOfSpeech), None);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
 }
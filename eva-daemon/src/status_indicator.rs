@@ -1,4 +1,5 @@
 use std::fmt;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 /// EVA status states
@@ -10,6 +11,7 @@ pub enum EvaStatus {
     Processing,     // Sending to Gemini
     Speaking,       // Playing response
     Executing,      // Running command
+    Reconnecting,   // Connection dropped, re-establishing
     Error,          // Error state
 }
 
@@ -22,6 +24,7 @@ impl fmt::Display for EvaStatus {
             EvaStatus::Processing => write!(f, "🧠 Processing"),
             EvaStatus::Speaking => write!(f, "🗣️  Speaking"),
             EvaStatus::Executing => write!(f, "⚙️  Executing"),
+            EvaStatus::Reconnecting => write!(f, "🔄 Reconnecting"),
             EvaStatus::Error => write!(f, "❌ Error"),
         }
     }
@@ -36,6 +39,10 @@ pub struct StatusIndicator {
     status_history: Vec<(EvaStatus, SystemTime)>,
     max_history: usize,
     override_symbol: Option<String>,
+    /// Guest mode badge, shown until guest mode is exited
+    guest: bool,
+    /// A Gemini quota is (nearly) used up
+    quota_warning: Option<String>,
 }
 
 impl StatusIndicator {
@@ -47,6 +54,8 @@ impl StatusIndicator {
             status_history: Vec::new(),
             max_history: 100,
             override_symbol: None,
+            guest: false,
+            quota_warning: None,
         }
     }
 
@@ -102,6 +111,7 @@ impl StatusIndicator {
                 EvaStatus::Processing => "Processing",
                 EvaStatus::Speaking => "Speaking",
                 EvaStatus::Executing => "Executing",
+                EvaStatus::Reconnecting => "Reconnecting",
                 EvaStatus::Error => "Error",
             };
             format!("{} {}{}", symbol, status_name, emotion_str)
@@ -119,6 +129,7 @@ impl StatusIndicator {
             EvaStatus::Processing => "blue",
             EvaStatus::Speaking => "green",
             EvaStatus::Executing => "cyan",
+            EvaStatus::Reconnecting => "magenta",
             EvaStatus::Error => "red",
         }
     }
@@ -127,6 +138,52 @@ impl StatusIndicator {
     pub fn get_history(&self) -> &[(EvaStatus, SystemTime)] {
         &self.status_history
     }
+
+    /// Show or clear the GUEST badge
+    pub fn set_guest(&mut self, guest: bool) {
+        self.guest = guest;
+    }
+
+    /// Whether the GUEST badge is shown
+    pub fn is_guest(&self) -> bool {
+        self.guest
+    }
+
+    /// Show or clear the quota warning
+    pub fn set_quota_warning(&mut self, warning: Option<String>) {
+        self.quota_warning = warning;
+    }
+
+    pub fn quota_warning(&self) -> Option<&str> {
+        self.quota_warning.as_deref()
+    }
+
+    /// Status as JSON, for the status file read by shell widgets
+    pub fn to_status_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "status": self.get_status_string(),
+            "emotion": self.current_emotion.to_string(),
+            "guest": self.guest,
+            "badge": if self.guest { "GUEST" } else { "" },
+            "quota_warning": self.quota_warning,
+        })
+    }
+
+    /// Write the status file
+    pub fn write_status_file(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_status_json().to_string())
+    }
+}
+
+/// Location of the status file
+///
+/// Lives in the runtime directory rather than the data directory: it changes constantly
+/// and must not count as persisted user data (guest mode writes it too).
+pub fn status_file_path() -> PathBuf {
+    std::env::var("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| std::env::temp_dir())
+        .join("eva-status.json")
 }
 
 impl Default for StatusIndicator {
@@ -165,6 +222,21 @@ mod tests {
         assert_eq!(indicator.get_status_string(), "💤 Idle");
     }
 
+    #[test]
+    fn test_guest_badge_in_status_json() {
+        let mut indicator = StatusIndicator::new();
+        assert_eq!(indicator.to_status_json()["guest"], false);
+
+        indicator.set_guest(true);
+        let json = indicator.to_status_json();
+        assert_eq!(json["guest"], true);
+        assert_eq!(json["badge"], "GUEST");
+        assert!(json["quota_warning"].is_null());
+
+        indicator.set_quota_warning(Some("Gemini quota almost used up".to_string()));
+        assert_eq!(indicator.to_status_json()["quota_warning"], "Gemini quota almost used up");
+    }
+
     #[test]
     fn test_color_names() {
         let mut indicator = StatusIndicator::new();
@@ -58,6 +58,79 @@ impl Language {
         )
     }
 
+    /// Get the BCP-47 locale tag used in profiles and Gemini config
+    pub fn locale(&self) -> &'static str {
+        match self {
+            Language::EnglishUS => "en-US",
+            Language::PortugueseBR => "pt-BR",
+            Language::Spanish => "es-ES",
+            Language::French => "fr-FR",
+            Language::German => "de-DE",
+            Language::Italian => "it-IT",
+            Language::Russian => "ru-RU",
+            Language::Chinese => "zh-CN",
+            Language::Japanese => "ja-JP",
+            Language::Korean => "ko-KR",
+        }
+    }
+
+    /// Get the English name of the language (used in model directives)
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::EnglishUS => "English",
+            Language::PortugueseBR => "Brazilian Portuguese",
+            Language::Spanish => "Spanish",
+            Language::French => "French",
+            Language::German => "German",
+            Language::Italian => "Italian",
+            Language::Russian => "Russian",
+            Language::Chinese => "Chinese",
+            Language::Japanese => "Japanese",
+            Language::Korean => "Korean",
+        }
+    }
+
+    /// Parse a locale tag or ISO 639-1 code ("pt-BR", "pt", "en_US")
+    pub fn from_tag(tag: &str) -> Option<Language> {
+        let code = tag
+            .split(|c| c == '-' || c == '_')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+
+        match code.as_str() {
+            "en" => Some(Language::EnglishUS),
+            "pt" => Some(Language::PortugueseBR),
+            "es" => Some(Language::Spanish),
+            "fr" => Some(Language::French),
+            "de" => Some(Language::German),
+            "it" => Some(Language::Italian),
+            "ru" => Some(Language::Russian),
+            "zh" => Some(Language::Chinese),
+            "ja" => Some(Language::Japanese),
+            "ko" => Some(Language::Korean),
+            _ => None,
+        }
+    }
+
+    /// Parse a spoken language name, in English or Portuguese
+    /// ("English", "português")
+    pub fn from_name(name: &str) -> Option<Language> {
+        match name.trim().to_lowercase().as_str() {
+            "english" | "inglês" | "ingles" => Some(Language::EnglishUS),
+            "portuguese" | "português" | "portugues" => Some(Language::PortugueseBR),
+            "spanish" | "espanhol" => Some(Language::Spanish),
+            "french" | "francês" | "frances" => Some(Language::French),
+            "german" | "alemão" | "alemao" => Some(Language::German),
+            "italian" | "italiano" => Some(Language::Italian),
+            "russian" | "russo" => Some(Language::Russian),
+            "chinese" | "chinês" | "chines" => Some(Language::Chinese),
+            "japanese" | "japonês" | "japones" => Some(Language::Japanese),
+            "korean" | "coreano" => Some(Language::Korean),
+            _ => None,
+        }
+    }
+
     /// Get language code (ISO 639-1)
     pub fn code(&self) -> &'static str {
         match self {
@@ -95,9 +168,11 @@ pub struct SttConfig {
 impl Default for SttConfig {
     fn default() -> Self {
         Self {
-            models_path: "~/.eva/models".to_string(),
+            models_path: crate::paths::models_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_else(|_| "~/.eva/models".to_string()),
             language: Language::EnglishUS,
-            sample_rate: 16000,
+            sample_rate: crate::audio::SAMPLE_RATE,
             partial_results: true,
             word_timestamps: false,
             max_alternatives: 3,
@@ -258,7 +333,29 @@ impl SttEngine {
         })
     }
 
-    /// Recognize speech from f32 samples (normalized -1.0 to 1.0)
+    /// Feed audio without ending the utterance, so `get_partial` keeps
+    /// growing (`recognize` finalizes what it was given)
+    pub fn accept(&mut self, audio: &[i16]) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.is_ready {
+            return Err("STT engine not initialized".into());
+        }
+
+        #[cfg(feature = "offline-stt")]
+        {
+            if let Some(ref mut recognizer) = self.recognizer {
+                let bytes: Vec<u8> = audio.iter().flat_map(|&sample| sample.to_le_bytes()).collect();
+                recognizer.accept_waveform(&bytes);
+            }
+        }
+        #[cfg(not(feature = "offline-stt"))]
+        let _ = audio;
+
+        Ok(())
+    }
+
+    /// Recognize speech from f32 samples (normalized -1.0 to 1.0) at
+    /// `config.sample_rate`; raw capture audio goes through
+    /// `resample::Decimator` first
     pub fn recognize_f32(&mut self, audio: &[f32]) -> Result<RecognitionResult, Box<dyn std::error::Error>> {
         // Convert f32 to i16
         let i16_samples: Vec<i16> = audio
@@ -361,14 +458,7 @@ impl SttEngine {
 
     /// Expand home directory in path
     fn expand_path(&self, path: &str) -> String {
-        if path.starts_with("~") {
-            let home = std::env::var("USERPROFILE")
-                .or_else(|_| std::env::var("HOME"))
-                .unwrap_or_else(|_| ".".to_string());
-            path.replace("~", &home)
-        } else {
-            path.to_string()
-        }
+        crate::paths::expand_home(path)
     }
 
     /// Check if model is downloaded
@@ -451,7 +541,7 @@ impl StreamingSttSession {
         // Process available chunks
         while self.audio_buffer.len() >= self.chunk_size {
             let chunk: Vec<i16> = self.audio_buffer.drain(..self.chunk_size).collect();
-            let _ = self.engine.recognize(&chunk)?;
+            self.engine.accept(&chunk)?;
         }
 
         // Get partial result
@@ -471,7 +561,7 @@ impl StreamingSttSession {
         // Process any remaining audio
         if !self.audio_buffer.is_empty() {
             let remaining: Vec<i16> = self.audio_buffer.drain(..).collect();
-            let _ = self.engine.recognize(&remaining)?;
+            self.engine.accept(&remaining)?;
         }
 
         // Get final result
@@ -490,6 +580,12 @@ impl StreamingSttSession {
         self.last_partial.clear();
         self.engine.reset();
     }
+
+    /// Switch the engine to another language (re-initialises it)
+    pub fn set_language(&mut self, language: Language) -> Result<(), Box<dyn std::error::Error>> {
+        self.reset();
+        self.engine.set_language(language)
+    }
 }
 
 #[cfg(test)]
@@ -503,6 +599,23 @@ mod tests {
         assert_eq!(Language::Spanish.code(), "es");
     }
 
+    #[test]
+    fn test_language_from_tag() {
+        assert_eq!(Language::from_tag("pt-BR"), Some(Language::PortugueseBR));
+        assert_eq!(Language::from_tag("en_US"), Some(Language::EnglishUS));
+        assert_eq!(Language::from_tag("EN"), Some(Language::EnglishUS));
+        assert_eq!(Language::from_tag("xx"), None);
+        assert_eq!(Language::PortugueseBR.locale(), "pt-BR");
+    }
+
+    #[test]
+    fn test_language_from_name() {
+        assert_eq!(Language::from_name("English"), Some(Language::EnglishUS));
+        assert_eq!(Language::from_name(" Português "), Some(Language::PortugueseBR));
+        assert_eq!(Language::from_name("alemão"), Some(Language::German));
+        assert_eq!(Language::from_name("en"), None);
+    }
+
     #[test]
     fn test_model_names() {
         assert!(Language::EnglishUS.model_name().contains("en-us"));
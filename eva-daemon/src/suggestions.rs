@@ -0,0 +1,409 @@
+use crate::command_parser::{CommandIntent, FileOperation, ProcessOperation, SystemOperation, TimerOperation};
+use crate::timemachine::SearchResult;
+use std::time::{Duration, Instant};
+
+/// How long numbered follow-ups stay selectable after an answer
+const DEFAULT_WINDOW: Duration = Duration::from_secs(15);
+
+/// At most this many chips are shown
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// What selecting a suggestion does
+#[derive(Debug, Clone, PartialEq)]
+pub enum FollowUpAction {
+    /// Run this intent directly, without re-parsing the label
+    Run(CommandIntent),
+    /// Open a Time Machine screenshot
+    ShowScreenshot(u64),
+}
+
+/// One numbered follow-up chip
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub label: String,
+    /// The varying part of the label ("notes.txt"), matched against speech
+    pub subject: String,
+    pub action: FollowUpAction,
+}
+
+impl Suggestion {
+    /// Risky intents still go through the normal confirmation
+    pub fn needs_confirmation(&self) -> bool {
+        matches!(&self.action, FollowUpAction::Run(intent) if intent.is_risky())
+    }
+}
+
+/// What EVA just answered
+pub enum FollowUpSource<'a> {
+    Command { intent: &'a CommandIntent, output: &'a str },
+    TimeMachineSearch { results: &'a [SearchResult] },
+}
+
+/// Rule table key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowUpCategory {
+    FileList,
+    FileRead,
+    FileCreate,
+    ProcessList,
+    ProcessStart,
+    SystemInfo,
+    TimerSet,
+    TimeMachineSearch,
+}
+
+impl FollowUpCategory {
+    pub fn of(source: &FollowUpSource) -> Option<Self> {
+        let intent = match source {
+            FollowUpSource::TimeMachineSearch { .. } => return Some(FollowUpCategory::TimeMachineSearch),
+            FollowUpSource::Command { intent, .. } => intent,
+        };
+        match intent {
+            CommandIntent::File(FileOperation::List { .. }) => Some(FollowUpCategory::FileList),
+            CommandIntent::File(FileOperation::Read { .. }) => Some(FollowUpCategory::FileRead),
+            CommandIntent::File(FileOperation::Create { .. }) => Some(FollowUpCategory::FileCreate),
+            CommandIntent::Process(ProcessOperation::List) => Some(FollowUpCategory::ProcessList),
+            CommandIntent::Process(ProcessOperation::Start { .. }) => Some(FollowUpCategory::ProcessStart),
+            CommandIntent::System(_) => Some(FollowUpCategory::SystemInfo),
+            CommandIntent::Timer(TimerOperation::Set { .. }) => Some(FollowUpCategory::TimerSet),
+            _ => None,
+        }
+    }
+}
+
+/// One follow-up template: `{}` in the label is replaced by each subject
+/// the expander finds
+struct Rule {
+    category: FollowUpCategory,
+    en: &'static str,
+    pt: &'static str,
+    expand: fn(&FollowUpSource) -> Vec<(String, FollowUpAction)>,
+}
+
+const RULES: &[Rule] = &[
+    Rule { category: FollowUpCategory::FileList, en: "Read {}?", pt: "Ler {}?", expand: listed_files },
+    Rule { category: FollowUpCategory::FileList, en: "Open folder {}?", pt: "Abrir a pasta {}?", expand: listed_dirs },
+    Rule { category: FollowUpCategory::FileCreate, en: "Read {} back?", pt: "Ler {} de volta?", expand: read_back },
+    Rule { category: FollowUpCategory::FileRead, en: "Set a reminder about {}?", pt: "Criar um lembrete sobre {}?", expand: remind_about_file },
+    Rule { category: FollowUpCategory::FileCreate, en: "Set a reminder about {}?", pt: "Criar um lembrete sobre {}?", expand: remind_about_file },
+    Rule { category: FollowUpCategory::ProcessList, en: "Kill {}?", pt: "Encerrar {}?", expand: listed_processes },
+    Rule { category: FollowUpCategory::ProcessStart, en: "Show running processes?", pt: "Mostrar os processos?", expand: list_processes },
+    Rule { category: FollowUpCategory::SystemInfo, en: "Show running processes?", pt: "Mostrar os processos?", expand: list_processes },
+    Rule { category: FollowUpCategory::SystemInfo, en: "Show disk usage?", pt: "Mostrar o uso de disco?", expand: disk_info },
+    Rule { category: FollowUpCategory::TimerSet, en: "How much time is left?", pt: "Quanto tempo falta?", expand: query_timer },
+    Rule { category: FollowUpCategory::TimeMachineSearch, en: "Show the screenshot of {}?", pt: "Mostrar a captura de {}?", expand: screenshots },
+];
+
+/// Reminder follow-ups fire after this long
+const REMINDER_SECS: u64 = 10 * 60;
+
+fn command<'a>(source: &FollowUpSource<'a>) -> Option<(&'a CommandIntent, &'a str)> {
+    match source {
+        FollowUpSource::Command { intent, output } => Some((intent, output)),
+        FollowUpSource::TimeMachineSearch { .. } => None,
+    }
+}
+
+fn join(dir: Option<&str>, name: &str) -> String {
+    match dir {
+        Some(dir) if !dir.is_empty() && dir != "." => format!("{}/{}", dir.trim_end_matches('/'), name),
+        _ => name.to_string(),
+    }
+}
+
+/// Entries of a `FileOperation::List` output ("📄 a.txt (12 bytes)", "📁 docs")
+fn listed_entries<'a>(source: &FollowUpSource<'a>, marker: &str) -> Vec<(Option<&'a str>, String)> {
+    let Some((CommandIntent::File(FileOperation::List { path }), output)) = command(source) else {
+        return Vec::new();
+    };
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix(marker))
+        .map(|rest| {
+            let name = match rest.rfind(" (") {
+                Some(i) if rest.ends_with(" bytes)") => &rest[..i],
+                _ => rest,
+            };
+            (path.as_deref(), name.trim().to_string())
+        })
+        .collect()
+}
+
+fn listed_files(source: &FollowUpSource) -> Vec<(String, FollowUpAction)> {
+    listed_entries(source, "📄 ")
+        .into_iter()
+        .map(|(dir, name)| {
+            let path = join(dir, &name);
+            (name, FollowUpAction::Run(CommandIntent::File(FileOperation::Read { path })))
+        })
+        .collect()
+}
+
+fn listed_dirs(source: &FollowUpSource) -> Vec<(String, FollowUpAction)> {
+    listed_entries(source, "📁 ")
+        .into_iter()
+        .map(|(dir, name)| {
+            let path = join(dir, &name);
+            (name, FollowUpAction::Run(CommandIntent::File(FileOperation::List { path: Some(path) })))
+        })
+        .collect()
+}
+
+fn file_path<'a>(source: &FollowUpSource<'a>) -> Option<&'a str> {
+    match command(source)?.0 {
+        CommandIntent::File(FileOperation::Read { path }) | CommandIntent::File(FileOperation::Create { path, .. }) => Some(path),
+        _ => None,
+    }
+}
+
+fn read_back(source: &FollowUpSource) -> Vec<(String, FollowUpAction)> {
+    file_path(source)
+        .map(|path| (path.to_string(), FollowUpAction::Run(CommandIntent::File(FileOperation::Read { path: path.to_string() }))))
+        .into_iter()
+        .collect()
+}
+
+fn remind_about_file(source: &FollowUpSource) -> Vec<(String, FollowUpAction)> {
+    file_path(source)
+        .map(|path| {
+            let name = path.rsplit('/').next().unwrap_or(path).to_string();
+            let timer = TimerOperation::Set { label: Some(name.clone()), seconds: REMINDER_SECS };
+            (name, FollowUpAction::Run(CommandIntent::Timer(timer)))
+        })
+        .into_iter()
+        .collect()
+}
+
+/// "PID 1234: firefox" lines of a process listing
+fn listed_processes(source: &FollowUpSource) -> Vec<(String, FollowUpAction)> {
+    let Some((_, output)) = command(source) else {
+        return Vec::new();
+    };
+    output
+        .lines()
+        .filter_map(|line| {
+            let (pid, name) = line.strip_prefix("PID ")?.split_once(':')?;
+            let pid = pid.trim().parse().ok()?;
+            Some((name.trim().to_string(), FollowUpAction::Run(CommandIntent::Process(ProcessOperation::Kill { pid }))))
+        })
+        .collect()
+}
+
+fn list_processes(_: &FollowUpSource) -> Vec<(String, FollowUpAction)> {
+    vec![(String::new(), FollowUpAction::Run(CommandIntent::Process(ProcessOperation::List)))]
+}
+
+fn disk_info(source: &FollowUpSource) -> Vec<(String, FollowUpAction)> {
+    match command(source) {
+        Some((CommandIntent::System(SystemOperation::DiskInfo), _)) => Vec::new(),
+        _ => vec![(String::new(), FollowUpAction::Run(CommandIntent::System(SystemOperation::DiskInfo)))],
+    }
+}
+
+fn query_timer(source: &FollowUpSource) -> Vec<(String, FollowUpAction)> {
+    match command(source) {
+        Some((CommandIntent::Timer(TimerOperation::Set { label, .. }), _)) => {
+            let query = TimerOperation::Query { label: label.clone() };
+            vec![(String::new(), FollowUpAction::Run(CommandIntent::Timer(query)))]
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn screenshots(source: &FollowUpSource) -> Vec<(String, FollowUpAction)> {
+    let FollowUpSource::TimeMachineSearch { results } = source else {
+        return Vec::new();
+    };
+    results
+        .iter()
+        .map(|r| {
+            let snippet: String = r.text.split_whitespace().take(4).collect::<Vec<_>>().join(" ");
+            let subject = if snippet.is_empty() { format!("#{}", r.id) } else { format!("\"{}\"", snippet) };
+            (subject, FollowUpAction::ShowScreenshot(r.id))
+        })
+        .collect()
+}
+
+/// Up to `MAX_SUGGESTIONS` follow-ups for an answer, in rule-table order
+pub fn suggest(source: &FollowUpSource, language: &str) -> Vec<Suggestion> {
+    let Some(category) = FollowUpCategory::of(source) else {
+        return Vec::new();
+    };
+    let pt = language.to_lowercase().starts_with("pt");
+
+    RULES
+        .iter()
+        .filter(|rule| rule.category == category)
+        .flat_map(|rule| {
+            let template = if pt { rule.pt } else { rule.en };
+            (rule.expand)(source).into_iter().map(move |(subject, action)| Suggestion {
+                label: template.replace("{}", &subject),
+                subject,
+                action,
+            })
+        })
+        .take(MAX_SUGGESTIONS)
+        .collect()
+}
+
+/// "2", "two", "the second one", "número dois", "a segunda"
+pub fn parse_choice(text: &str) -> Option<usize> {
+    const WORDS: &[(&str, usize)] = &[
+        ("1", 1), ("one", 1), ("first", 1), ("um", 1), ("uma", 1), ("primeiro", 1), ("primeira", 1),
+        ("2", 2), ("two", 2), ("second", 2), ("dois", 2), ("duas", 2), ("segundo", 2), ("segunda", 2),
+        ("3", 3), ("three", 3), ("third", 3), ("três", 3), ("tres", 3), ("terceiro", 3), ("terceira", 3),
+    ];
+    let text = text.to_lowercase();
+    let words: Vec<&str> = text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+    // A short reply only: "read number two please" yes, a whole new request no
+    if words.is_empty() || words.len() > 4 {
+        return None;
+    }
+    words.iter().find_map(|w| WORDS.iter().find(|(word, _)| word == w).map(|(_, n)| *n))
+}
+
+/// Suggestions on screen and the window in which they can be picked
+pub struct FollowUps {
+    suggestions: Vec<Suggestion>,
+    expires: Option<Instant>,
+    window: Duration,
+}
+
+impl FollowUps {
+    pub fn new() -> Self {
+        Self::with_window(DEFAULT_WINDOW)
+    }
+
+    pub fn with_window(window: Duration) -> Self {
+        Self { suggestions: Vec::new(), expires: None, window }
+    }
+
+    /// Replace the suggestions after an answer and open the window
+    pub fn offer(&mut self, source: &FollowUpSource, language: &str, now: Instant) -> &[Suggestion] {
+        self.suggestions = suggest(source, language);
+        self.expires = if self.suggestions.is_empty() { None } else { Some(now + self.window) };
+        &self.suggestions
+    }
+
+    /// Suggestions that can still be picked (empty once the window closed)
+    pub fn active(&self, now: Instant) -> &[Suggestion] {
+        match self.expires {
+            Some(expires) if now < expires => &self.suggestions,
+            _ => &[],
+        }
+    }
+
+    /// Drop suggestions whose window has closed; true if any were dropped
+    pub fn expire(&mut self, now: Instant) -> bool {
+        if self.expires.is_some() && self.active(now).is_empty() {
+            self.dismiss();
+            return true;
+        }
+        false
+    }
+
+    pub fn dismiss(&mut self) {
+        self.suggestions.clear();
+        self.expires = None;
+    }
+
+    /// Pick chip `number` (1-based) by key press or click; closes the window
+    pub fn choose(&mut self, number: usize, now: Instant) -> Option<Suggestion> {
+        let chosen = self.active(now).get(number.checked_sub(1)?).cloned()?;
+        self.dismiss();
+        Some(chosen)
+    }
+
+    /// Pick by a short spoken reply: a number, or the chip's subject
+    pub fn accept_phrase(&mut self, text: &str, now: Instant) -> Option<Suggestion> {
+        if let Some(number) = parse_choice(text) {
+            return self.choose(number, now);
+        }
+        let lower = text.to_lowercase();
+        let position = self
+            .active(now)
+            .iter()
+            .position(|s| !s.subject.is_empty() && lower.contains(&s.subject.to_lowercase()))?;
+        self.choose(position + 1, now)
+    }
+}
+
+impl Default for FollowUps {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_parser::TextOperation;
+
+    fn list_source(output: &str) -> (CommandIntent, String) {
+        (CommandIntent::File(FileOperation::List { path: Some("docs".to_string()) }), output.to_string())
+    }
+
+    #[test]
+    fn test_rule_table_mapping() {
+        let (intent, output) = list_source("Found 3 items:\n📁 old\n📄 a.txt (12 bytes)\n📄 b (1).md (3 bytes)");
+        let chips = suggest(&FollowUpSource::Command { intent: &intent, output: &output }, "en-US");
+        let labels: Vec<&str> = chips.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, ["Read a.txt?", "Read b (1).md?", "Open folder old?"]);
+        assert_eq!(chips[0].action, FollowUpAction::Run(CommandIntent::File(FileOperation::Read { path: "docs/a.txt".to_string() })));
+
+        let intent = CommandIntent::Process(ProcessOperation::List);
+        let output = "Running processes (2):\nPID 42: firefox\nPID 7: vim";
+        let chips = suggest(&FollowUpSource::Command { intent: &intent, output }, "pt-BR");
+        assert_eq!(chips[0].label, "Encerrar firefox?");
+        assert_eq!(chips[0].action, FollowUpAction::Run(CommandIntent::Process(ProcessOperation::Kill { pid: 42 })));
+        assert!(chips[0].needs_confirmation());
+
+        let results = vec![SearchResult { id: 9, score: 0.8, text: "Quarterly report draft final v2".to_string(), timestamp: chrono::Utc::now(), full_image: true, screen: None, masked: false }];
+        let chips = suggest(&FollowUpSource::TimeMachineSearch { results: &results }, "en");
+        assert_eq!(chips[0].label, "Show the screenshot of \"Quarterly report draft final\"?");
+        assert_eq!(chips[0].action, FollowUpAction::ShowScreenshot(9));
+
+        let intent = CommandIntent::Timer(TimerOperation::Set { label: Some("tea".to_string()), seconds: 60 });
+        let chips = suggest(&FollowUpSource::Command { intent: &intent, output: "" }, "en");
+        assert_eq!(chips[0].action, FollowUpAction::Run(CommandIntent::Timer(TimerOperation::Query { label: Some("tea".to_string()) })));
+
+        let intent = CommandIntent::Text(TextOperation::Paste);
+        assert!(suggest(&FollowUpSource::Command { intent: &intent, output: "" }, "en").is_empty());
+    }
+
+    #[test]
+    fn test_choice_by_number_or_phrase() {
+        assert_eq!(parse_choice("2"), Some(2));
+        assert_eq!(parse_choice("the third one"), Some(3));
+        assert_eq!(parse_choice("a segunda"), Some(2));
+        assert_eq!(parse_choice("open the first file in my documents folder please"), None);
+
+        let (intent, output) = list_source("📄 a.txt (1 bytes)\n📄 b.txt (1 bytes)");
+        let now = Instant::now();
+        let mut follow_ups = FollowUps::new();
+        follow_ups.offer(&FollowUpSource::Command { intent: &intent, output: &output }, "en", now);
+        let chosen = follow_ups.accept_phrase("read b.txt", now).unwrap();
+        assert_eq!(chosen.subject, "b.txt");
+        // Picking closes the window
+        assert!(follow_ups.active(now).is_empty());
+    }
+
+    #[test]
+    fn test_suggestions_disappear_when_window_closes() {
+        let (intent, output) = list_source("📄 a.txt (1 bytes)");
+        let now = Instant::now();
+        let mut follow_ups = FollowUps::with_window(Duration::from_secs(10));
+        assert_eq!(follow_ups.offer(&FollowUpSource::Command { intent: &intent, output: &output }, "en", now).len(), 1);
+
+        let later = now + Duration::from_secs(5);
+        assert_eq!(follow_ups.active(later).len(), 1);
+        assert!(!follow_ups.expire(later));
+
+        let closed = now + Duration::from_secs(10);
+        assert!(follow_ups.active(closed).is_empty());
+        assert_eq!(follow_ups.choose(1, closed), None);
+        assert_eq!(follow_ups.accept_phrase("one", closed), None);
+        assert!(follow_ups.expire(closed));
+        assert!(!follow_ups.expire(closed));
+    }
+}
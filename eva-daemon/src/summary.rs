@@ -0,0 +1,219 @@
+//! Long-term memory beyond the session window
+//!
+//! `ConversationSession` only keeps its last few turns. Each turn it drops
+//! is folded into a rolling summary right away with a local heuristic:
+//! sentences that name something or carry a number are kept, small talk
+//! goes. When Gemini is reachable the turns summarized that way are
+//! compressed by the model in a separate, text-only session, so the
+//! summary stays short however long the conversation runs.
+
+use std::time::Duration;
+
+use crate::gemini::{GeminiClient, GeminiConfig, StreamEvent};
+use crate::session::{Role, Turn};
+
+/// Longest summary kept; the oldest lines go first
+pub const MAX_SUMMARY_CHARS: usize = 1500;
+
+/// Evicted turns kept for the model to compress; older ones only live in
+/// the local summary
+pub const MAX_PENDING: usize = 20;
+
+/// Evicted turns worth a compression round trip
+pub const COMPRESS_AFTER: usize = 6;
+
+/// Longest wait for the model's summary, connection included
+pub const COMPRESS_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SUMMARIZER_INSTRUCTION: &str = "You maintain the long-term memory of a voice assistant called EVA. \
+Merge the summary and the conversation turns you are given into one updated summary of at most 150 words. \
+Keep names, places, dates, times, numbers, preferences and anything the user said they will do or want remembered; \
+drop greetings and small talk. Reply with the summary only, as plain sentences.";
+
+/// `summary` with the notable sentences of an evicted turn appended
+///
+/// Command output is left out: it describes the machine, not the
+/// conversation.
+pub fn fold(summary: &str, turn: &Turn) -> String {
+    let mut lines: Vec<String> = summary.lines().map(str::to_string).collect();
+    if !turn.command_result {
+        for sentence in sentences(&turn.content).into_iter().filter(|s| is_notable(s)) {
+            let line = format!("{}: {}", speaker(&turn.role), sentence);
+            if !lines.contains(&line) {
+                lines.push(line);
+            }
+        }
+    }
+    cap(&lines.join("\n"))
+}
+
+fn speaker(role: &Role) -> &'static str {
+    match role {
+        Role::User => "User",
+        Role::Assistant => "EVA",
+    }
+}
+
+/// Sentences of `text`, trimmed; a `.` inside a number ("3.5") doesn't end one
+fn sentences(text: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let at_break = chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+        if c == '\n' || (matches!(c, '.' | '!' | '?') && at_break) {
+            found.push(text[start..i + c.len_utf8()].trim());
+            start = i + c.len_utf8();
+        }
+    }
+    found.push(text[start..].trim());
+    found.retain(|s| !s.is_empty());
+    found
+}
+
+/// A number, or a capitalized word past the first (a name, a place, a
+/// month); "I" and EVA's own name don't count
+fn is_notable(sentence: &str) -> bool {
+    if sentence.chars().any(|c| c.is_ascii_digit()) {
+        return true;
+    }
+    sentence.split_whitespace().skip(1).any(|word| {
+        let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+        word.chars().next().is_some_and(char::is_uppercase) && !["I", "EVA"].contains(&word)
+    })
+}
+
+/// Drop the oldest lines until `summary` fits; a single line that is still
+/// too long keeps its end
+fn cap(summary: &str) -> String {
+    let mut lines: Vec<&str> = summary.lines().collect();
+    while lines.len() > 1 && lines.iter().map(|l| l.len() + 1).sum::<usize>() > MAX_SUMMARY_CHARS {
+        lines.remove(0);
+    }
+    let joined = lines.join("\n");
+    let excess = joined.chars().count().saturating_sub(MAX_SUMMARY_CHARS);
+    joined.chars().skip(excess).collect()
+}
+
+/// One remembered fact, as shown to the model; facts that weren't split
+/// into subject and value are stored under themselves
+pub fn fact(key: &str, value: &str) -> String {
+    if key == value.to_lowercase() {
+        value.to_string()
+    } else {
+        format!("{}: {}", key, value)
+    }
+}
+
+/// Reply to "remember that ..."
+pub fn remembered_reply(key: &str, value: &str, portuguese: bool) -> String {
+    match (key == value.to_lowercase(), portuguese) {
+        (true, true) => format!("Certo, vou lembrar que {}", value),
+        (true, false) => format!("Got it, I'll remember that {}", value),
+        (false, true) => format!("Certo, vou lembrar: {} é {}", key, value),
+        (false, false) => format!("Got it, I'll remember: {} is {}", key, value),
+    }
+}
+
+/// What the summarizer is asked
+pub fn compression_prompt(summary: &str, evicted: &[Turn]) -> String {
+    let turns: Vec<String> = evicted
+        .iter()
+        .filter(|turn| !turn.command_result)
+        .map(|turn| format!("{}: {}", speaker(&turn.role), turn.content))
+        .collect();
+    format!(
+        "Current summary:\n{}\n\nTurns to merge into it:\n{}",
+        if summary.is_empty() { "(empty)" } else { summary },
+        turns.join("\n")
+    )
+}
+
+/// Have the model merge `evicted` into `summary`, over a connection of
+/// its own so the conversation's session doesn't see the exchange
+pub async fn compress(config: GeminiConfig, summary: &str, evicted: &[Turn]) -> Result<String, Box<dyn std::error::Error>> {
+    let config = GeminiConfig {
+        system_instruction: SUMMARIZER_INSTRUCTION.to_string(),
+        text_replies: true,
+        tools: false,
+        capabilities: None,
+        ..config
+    };
+    let prompt = compression_prompt(summary, evicted);
+
+    let ask = async {
+        let mut client = GeminiClient::connect(config).await?;
+        client.send_text(&prompt).await?;
+        let mut reply = String::new();
+        let mut stream = client.receive_stream();
+        while let Some(event) = stream.next().await {
+            if let StreamEvent::Text(part) = event? {
+                reply.push_str(&part);
+            }
+        }
+        let _ = client.close().await;
+        Ok::<_, Box<dyn std::error::Error>>(reply)
+    };
+    let reply = tokio::time::timeout(COMPRESS_TIMEOUT, ask)
+        .await
+        .map_err(|_| format!("no summary after {}s", COMPRESS_TIMEOUT.as_secs()))??;
+
+    let reply = reply.trim();
+    if reply.is_empty() {
+        return Err("the model sent back an empty summary".into());
+    }
+    Ok(cap(reply))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn turn(role: Role, content: &str) -> Turn {
+        Turn { role, content: content.to_string(), audio: None, timestamp: SystemTime::now(), language: None, command_result: false }
+    }
+
+    #[test]
+    fn test_fold_keeps_names_and_numbers() {
+        let summary = fold("", &turn(Role::User, "Hi there. My sister Ana arrives on the 12th! Can you help me plan? Version 3.5 is out."));
+        assert_eq!(summary, "User: My sister Ana arrives on the 12th!\nUser: Version 3.5 is out.");
+
+        // Nothing notable, already there, or command output: unchanged
+        assert_eq!(fold(&summary, &turn(Role::Assistant, "Of course, happy to help.")), summary);
+        assert_eq!(fold(&summary, &turn(Role::User, "My sister Ana arrives on the 12th!")), summary);
+        let output = Turn { command_result: true, ..turn(Role::Assistant, "Deleted 4 files") };
+        assert_eq!(fold(&summary, &output), summary);
+
+        let summary = fold(&summary, &turn(Role::Assistant, "I booked a table at Tasca for 8."));
+        assert!(summary.ends_with("EVA: I booked a table at Tasca for 8."));
+    }
+
+    #[test]
+    fn test_summary_is_capped_oldest_first() {
+        let mut summary = String::new();
+        for i in 0..200 {
+            summary = fold(&summary, &turn(Role::User, &format!("Reminder number {} for later.", i)));
+        }
+        assert!(summary.len() <= MAX_SUMMARY_CHARS);
+        assert!(summary.ends_with("Reminder number 199 for later."));
+        assert!(!summary.contains("Reminder number 0 "));
+
+        assert_eq!(cap(&"x".repeat(MAX_SUMMARY_CHARS + 10)).len(), MAX_SUMMARY_CHARS);
+    }
+
+    #[test]
+    fn test_compression_prompt() {
+        let output = Turn { command_result: true, ..turn(Role::Assistant, "3 processes") };
+        let prompt = compression_prompt("", &[turn(Role::User, "I live in Porto."), output, turn(Role::Assistant, "Nice city.")]);
+        assert_eq!(prompt, "Current summary:\n(empty)\n\nTurns to merge into it:\nUser: I live in Porto.\nEVA: Nice city.");
+    }
+
+    #[test]
+    fn test_facts() {
+        assert_eq!(fact("wifi password hint", "the dog's name"), "wifi password hint: the dog's name");
+        assert_eq!(fact("i parked on level 3", "I parked on level 3"), "I parked on level 3");
+        assert_eq!(remembered_reply("aniversário", "12 de maio", true), "Certo, vou lembrar: aniversário é 12 de maio");
+        assert_eq!(remembered_reply("i parked on level 3", "I parked on level 3", false), "Got it, I'll remember that I parked on level 3");
+    }
+}
@@ -1,20 +1,194 @@
+use crate::accessibility::{plain_text, status_line, Earcon, MessageKind};
 use crate::status_indicator::{EvaStatus, StatusIndicator};
 use crate::statistics::Statistics;
+use crate::session::ConversationSession;
+use crate::webhooks::EndpointStats;
+use crate::command_history::{describe_intent, CommandHistory, HistoryAction, HistoryKey, HistoryView};
+use crate::suggestions::Suggestion;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Conversation lines on screen at once
+const VIEWPORT_LINES: usize = 10;
+
+/// Messages kept for scrolling back and exporting
+const SCROLLBACK_LIMIT: usize = 2000;
+
+/// The live caption redraws the screen at most this often (5 times a second)
+const CAPTION_REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+
+/// What PageUp / PageDown put on the (line-buffered) input line
+const KEY_PAGE_UP: &str = "\x1b[5~";
+const KEY_PAGE_DOWN: &str = "\x1b[6~";
+
+/// Arrow keys and Escape, for the command history view
+const KEY_UP: &str = "\x1b[A";
+const KEY_DOWN: &str = "\x1b[B";
+const KEY_ESCAPE: &str = "\x1b";
+
+/// Where the UI writes its output
+pub trait UiSink {
+    /// Replace the whole screen (standard mode)
+    fn frame(&mut self, text: &str);
+    /// Append one line, never redrawing what came before (accessibility mode)
+    fn line(&mut self, text: &str);
+}
+
+/// Terminal output
+pub struct StdoutSink;
+
+impl UiSink for StdoutSink {
+    fn frame(&mut self, text: &str) {
+        print!("\x1B[2J\x1B[1;1H{}", text);
+        io::stdout().flush().ok();
+    }
+
+    fn line(&mut self, text: &str) {
+        println!("{}", text);
+    }
+}
+
+/// Records output instead of printing (tests and headless runs)
+#[derive(Clone, Default)]
+pub struct HeadlessSink {
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl HeadlessSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything written so far; a frame is recorded line by line
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().clone()
+    }
+}
+
+impl UiSink for HeadlessSink {
+    fn frame(&mut self, text: &str) {
+        self.lines.lock().unwrap().extend(text.lines().map(str::to_string));
+    }
+
+    fn line(&mut self, text: &str) {
+        self.lines.lock().unwrap().push(text.to_string());
+    }
+}
+
+/// Interim transcription of what the user is saying
+struct Caption {
+    text: String,
+    /// Cleared once the status moves on from this one
+    status: EvaStatus,
+    /// Last time setting it asked for a redraw
+    drawn_at: Option<Instant>,
+}
+
+/// A logged message with the time it was added
+struct LogEntry {
+    at: chrono::DateTime<chrono::Local>,
+    text: String,
+}
 
 /// Simple terminal UI (without heavy TUI dependencies)
+///
+/// In accessibility mode the screen is never cleared or redrawn: every
+/// message and status change is appended as one plain-text line starting
+/// with a tag like `[EVA]` or `[ERROR]`, with no emoji, box drawing or
+/// color, so screen readers read each change exactly once and the
+/// terminal's own high-contrast theme applies.
 pub struct TerminalUI {
     conversation_log: Vec<String>,
     max_log_size: usize,
+    session_label: String,
+    sink: Box<dyn UiSink + Send>,
+    accessible: bool,
+    earcons: bool,
+    /// Accessible lines not yet written to the sink
+    pending: Vec<String>,
+    last_status: Option<(EvaStatus, bool)>,
+    pending_earcon: Option<Earcon>,
+    /// (selected command, focused form field) last announced
+    last_focus: Option<(usize, Option<usize>)>,
+    /// Numbered follow-up chips under the conversation
+    suggestions: Vec<String>,
+    /// Every message, for scrolling back and exporting
+    scrollback: VecDeque<LogEntry>,
+    /// Lines hidden below the viewport while scrolled up; `None` follows
+    /// the newest message
+    scroll: Option<usize>,
+    /// Messages that arrived while scrolled up
+    unseen: usize,
+    /// Highlighted search text and the line it was last found on
+    search: Option<(String, usize)>,
+    /// The next typed line is a search query
+    awaiting_search: bool,
+    /// One-line feedback under the conversation (search results)
+    notice: Option<String>,
+    /// Live caption row under the status
+    caption: Option<Caption>,
+    /// Gemini model of the open session, under the header
+    model: Option<String>,
+    /// Command history view, while open (the `h` key)
+    history: Option<HistoryView>,
+    /// Last drawn history view, under the conversation
+    history_panel: Option<String>,
 }
 
 impl TerminalUI {
     /// Create new terminal UI
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Self {
+        Ok(Self::with_sink(Box::new(StdoutSink)))
+    }
+
+    /// Create a terminal UI writing to the given sink
+    pub fn with_sink(sink: Box<dyn UiSink + Send>) -> Self {
+        Self {
             conversation_log: Vec::new(),
             max_log_size: 50,
-        })
+            session_label: String::new(),
+            sink,
+            accessible: false,
+            earcons: true,
+            pending: Vec::new(),
+            last_status: None,
+            pending_earcon: None,
+            last_focus: None,
+            suggestions: Vec::new(),
+            scrollback: VecDeque::new(),
+            scroll: None,
+            unseen: 0,
+            search: None,
+            awaiting_search: false,
+            notice: None,
+            caption: None,
+            model: None,
+            history: None,
+            history_panel: None,
+        }
+    }
+
+    /// Switch accessibility mode; the next draw announces the full state
+    pub fn set_accessible(&mut self, accessible: bool, earcons: bool) {
+        if accessible && !self.accessible {
+            self.pending.push(format!("{} Accessibility mode on", MessageKind::System.tag()));
+        }
+        if !accessible {
+            self.pending.clear();
+        }
+        self.accessible = accessible;
+        self.earcons = earcons;
+        self.last_status = None;
+        self.last_focus = None;
+    }
+
+    /// Earcon for the last announced status change, if one should play
+    pub fn take_earcon(&mut self) -> Option<Earcon> {
+        self.pending_earcon.take()
     }
 
     /// Clear screen
@@ -23,71 +197,536 @@ impl TerminalUI {
         io::stdout().flush().ok();
     }
 
-    /// Draw header
-    pub fn draw_header(&self) {
-        println!("╔════════════════════════════════════════════════════════════╗");
-        println!("║          🧠 EVA OS v0.8.0 - Visual Feedback              ║");
-        println!("╚════════════════════════════════════════════════════════════╝");
-        println!();
+    /// Render header
+    fn render_header(&self, out: &mut String) {
+        writeln!(out, "╔════════════════════════════════════════════════════════════╗").ok();
+        writeln!(out, "║          🧠 EVA OS v0.8.0 - Visual Feedback              ║").ok();
+        writeln!(out, "╚════════════════════════════════════════════════════════════╝").ok();
+        if let Some(ref model) = self.model {
+            writeln!(out, "  Gemini: {}", model).ok();
+        }
+        writeln!(out).ok();
     }
 
-    /// Draw status bar
-    pub fn draw_status(&self, status: &StatusIndicator) {
+    /// Render status bar
+    fn render_status(&self, status: &StatusIndicator, out: &mut String) {
         let status_str = status.get_status_string();
         let color = self.get_ansi_color(status.get_color_name());
-        
-        println!("┌─ Status ────────────────────────────────────────────────┐");
-        println!("│ {}{}\x1B[0m", color, status_str);
-        println!("└─────────────────────────────────────────────────────────┘");
-        println!();
+
+        writeln!(out, "┌─ Status ────────────────────────────────────────────────┐").ok();
+        writeln!(out, "│ {}{}\x1B[0m", color, status_str).ok();
+        if let Some(ref caption) = self.caption {
+            writeln!(out, "│ 🎙️  \x1B[3m{}\x1B[0m", caption.text).ok();
+        }
+        if status.is_guest() {
+            writeln!(out, "│ \x1B[45;97m GUEST \x1B[0m nothing is saved or learned until guest mode ends").ok();
+        }
+        if let Some(warning) = status.quota_warning() {
+            writeln!(out, "│ \x1B[33m⚠️  {}\x1B[0m", warning).ok();
+        }
+        if !self.session_label.is_empty() {
+            writeln!(out, "│ {}", self.session_label).ok();
+        }
+        writeln!(out, "└─────────────────────────────────────────────────────────┘").ok();
+        writeln!(out).ok();
     }
 
-    /// Draw statistics
-    pub fn draw_statistics(&self, stats: &Statistics) {
-        println!("┌─ Statistics ────────────────────────────────────────────┐");
-        println!("│ Turns: {} | Commands: {} | Uptime: {} | Memory: {}MB",
+    /// Render statistics
+    fn render_statistics(&self, stats: &Statistics, out: &mut String) {
+        writeln!(out, "┌─ Statistics ────────────────────────────────────────────┐").ok();
+        writeln!(out, "│ Turns: {} | Commands: {} | Uptime: {}",
             stats.turns,
             stats.commands_executed,
-            stats.get_uptime_string(),
-            stats.memory_mb
-        );
-        println!("└─────────────────────────────────────────────────────────┘");
-        println!();
+            stats.get_uptime_string()
+        ).ok();
+        let system = stats.get_system_string();
+        if !system.is_empty() {
+            writeln!(out, "│ System: {}", system).ok();
+        }
+        let dsp = stats.get_dsp_string();
+        if !dsp.is_empty() {
+            writeln!(out, "│ DSP: {}", dsp).ok();
+        }
+        let vad = stats.get_vad_string();
+        if !vad.is_empty() {
+            writeln!(out, "│ VAD: {}", vad).ok();
+        }
+        let send_queue = stats.get_send_queue_string();
+        if !send_queue.is_empty() {
+            writeln!(out, "│ Send queue: {}", send_queue).ok();
+        }
+        let latency = stats.get_latency_string();
+        if !latency.is_empty() {
+            writeln!(out, "│ Latency: {}", latency).ok();
+        }
+        let answers = stats.get_answers_string();
+        if !answers.is_empty() {
+            writeln!(out, "│ Answers: {}", answers).ok();
+        }
+        let timers = stats.get_timers_string();
+        if !timers.is_empty() {
+            writeln!(out, "│ ⏲  {}", timers).ok();
+        }
+        writeln!(out, "└─────────────────────────────────────────────────────────┘").ok();
+        writeln!(out).ok();
     }
 
-    /// Draw conversation log
-    pub fn draw_conversation(&self) {
-        println!("┌─ Conversation ──────────────────────────────────────────┐");
-        
-        let start = if self.conversation_log.len() > 10 {
-            self.conversation_log.len() - 10
+    /// Render the conversation viewport
+    fn render_conversation(&self, out: &mut String) {
+        let end = self.scrollback.len() - self.scroll.unwrap_or(0);
+        let start = end.saturating_sub(VIEWPORT_LINES);
+        match self.scroll {
+            Some(_) => writeln!(out, "┌─ Conversation ({}-{} of {}) ─────────────────────────────┐", start + 1, end, self.scrollback.len()).ok(),
+            None => writeln!(out, "┌─ Conversation ──────────────────────────────────────────┐").ok(),
+        };
+
+        let query = self.search.as_ref().map(|(q, _)| q.as_str());
+        for entry in self.scrollback.range(start..end) {
+            writeln!(out, "│ {}", highlight(&entry.text, query)).ok();
+        }
+
+        if self.scrollback.is_empty() {
+            writeln!(out, "│ (No messages yet)").ok();
+        }
+        if self.unseen > 0 {
+            let plural = if self.unseen == 1 { "" } else { "s" };
+            writeln!(out, "│ ↓ {} new message{} below (PgDn)", self.unseen, plural).ok();
+        }
+        if let Some(ref notice) = self.notice {
+            writeln!(out, "│ {}", notice).ok();
+        }
+
+        if !self.suggestions.is_empty() {
+            let chips: Vec<String> = self.suggestions.iter().enumerate().map(|(i, s)| format!("[{}] {}", i + 1, s)).collect();
+            writeln!(out, "│ 💡 {}", chips.join("  ")).ok();
+        }
+
+        writeln!(out, "└─────────────────────────────────────────────────────────┘").ok();
+        writeln!(out, "  Press i or / then Enter to type instead of speaking").ok();
+        if self.scroll.is_some() {
+            writeln!(out, "  PgUp/PgDn scroll · / search · n next match · q back to live · e export · d request log").ok();
         } else {
-            0
+            writeln!(out, "  PgUp scroll back · e export conversation · d request log · q quit").ok();
+        }
+        writeln!(out).ok();
+    }
+
+    /// Scroll the conversation one page back
+    pub fn scroll_up(&mut self) {
+        let top = self.scrollback.len().saturating_sub(VIEWPORT_LINES);
+        self.scroll = Some((self.scroll.unwrap_or(0) + VIEWPORT_LINES).min(top));
+    }
+
+    /// Scroll one page forward; past the newest message the view follows
+    /// new messages again
+    pub fn scroll_down(&mut self) {
+        match self.scroll {
+            Some(hidden) if hidden > VIEWPORT_LINES => self.scroll = Some(hidden - VIEWPORT_LINES),
+            _ => self.follow_live(),
+        }
+    }
+
+    /// Back to the newest messages, dropping search state
+    pub fn follow_live(&mut self) {
+        self.scroll = None;
+        self.unseen = 0;
+        self.search = None;
+        self.notice = None;
+    }
+
+    /// Highlight `query` and scroll to its newest match at or above the
+    /// bottom of the view; returns whether it was found
+    pub fn search(&mut self, query: &str) -> bool {
+        let below = self.scrollback.len() - self.scroll.unwrap_or(0);
+        self.search = Some((query.to_string(), below));
+        self.search_next()
+    }
+
+    /// Scroll to the next older match of the current search
+    pub fn search_next(&mut self) -> bool {
+        let Some((query, from)) = self.search.clone() else { return false };
+        let needle = query.to_lowercase();
+        let found = (0..from).rev().find(|&i| self.scrollback[i].text.to_lowercase().contains(&needle));
+        match found {
+            Some(line) => {
+                self.search = Some((query.clone(), line));
+                // Bring the match into view, as the bottom line if it was below it
+                let top = self.scrollback.len().saturating_sub(VIEWPORT_LINES);
+                let hidden = self.scroll.unwrap_or(0);
+                let end = self.scrollback.len() - hidden;
+                if line >= end || line + VIEWPORT_LINES < end {
+                    self.scroll = Some((self.scrollback.len() - 1 - line).min(top));
+                } else {
+                    self.scroll = Some(hidden);
+                }
+                self.notice = Some(format!("🔍 \"{}\" (n for the next match)", query));
+                true
+            }
+            None => {
+                self.notice = Some(format!("🔍 No more matches for \"{}\"", query));
+                false
+            }
+        }
+    }
+
+    /// Write every logged message, with its time, to a new text file in `dir`
+    pub fn export_conversation(&self, dir: &Path) -> io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let now = chrono::Local::now();
+        let path = dir.join(format!("conversation-{}.txt", now.format("%Y%m%d-%H%M%S")));
+        let mut text = String::new();
+        for entry in &self.scrollback {
+            writeln!(text, "[{}] {}", entry.at.format("%Y-%m-%d %H:%M:%S"), plain_text(&entry.text)).ok();
+        }
+        std::fs::write(&path, text)?;
+        Ok(path)
+    }
+
+    /// Handle waiting keyboard input without blocking: scrolling, search
+    /// and export are handled here, the first line meant for the
+    /// conversation is returned. `None` if nothing was typed.
+    pub fn handle_input(&mut self, input: &mut TextInput) -> Option<InputLine> {
+        let mut handled = None;
+        while let Some(line) = input.try_line() {
+            match self.handle_line(&line, input) {
+                InputLine::Ignore => handled = Some(InputLine::Ignore),
+                typed => return Some(typed),
+            }
+        }
+        handled
+    }
+
+    /// One typed line: a view key, a search query, or input for `TextInput`
+    pub fn handle_line(&mut self, line: &str, input: &mut TextInput) -> InputLine {
+        let key = line.trim();
+        if std::mem::take(&mut self.awaiting_search) {
+            if !key.is_empty() {
+                self.search(key);
+            }
+            return InputLine::Ignore;
+        }
+        if let Some(view) = &self.history {
+            return InputLine::History(history_keys(line, view));
+        }
+        if input.is_open() {
+            return input.accept(line);
+        }
+
+        let scrolled = self.scroll.is_some();
+        match key {
+            KEY_PAGE_UP => self.scroll_up(),
+            KEY_PAGE_DOWN => self.scroll_down(),
+            "e" => {
+                let exported = crate::paths::exports_dir()
+                    .map_err(|e| e.to_string())
+                    .and_then(|dir| self.export_conversation(&dir).map_err(|e| e.to_string()));
+                match exported {
+                    Ok(path) => self.add_system_message(&format!("💾 Conversation exported to {}", path.display())),
+                    Err(e) => self.add_error_message(&format!("Export failed: {}", e)),
+                }
+            }
+            "d" => {
+                let audit = crate::gemini::audit_log();
+                if audit.toggle() {
+                    self.add_system_message(&format!("📝 Gemini request log on ({})", audit.path().display()));
+                } else {
+                    self.add_system_message("📝 Gemini request log off");
+                }
+            }
+            "/" if scrolled => {
+                self.awaiting_search = true;
+                self.notice = Some("🔍 Type text to search for and press Enter".to_string());
+            }
+            "n" if scrolled && self.search.is_some() => {
+                self.search_next();
+            }
+            "h" => {
+                self.history = Some(HistoryView::default());
+                return InputLine::History(Vec::new());
+            }
+            // A chip's number picks it, like saying it
+            _ if !self.suggestions.is_empty() && key.parse::<usize>().is_ok_and(|n| (1..=self.suggestions.len()).contains(&n)) => {
+                return InputLine::Send(key.to_string());
+            }
+            "q" if scrolled => self.follow_live(),
+            "q" => return InputLine::Quit,
+            _ => return input.accept(line),
+        }
+        InputLine::Ignore
+    }
+
+    /// Full standard-mode screen
+    pub fn render(&self, status: &StatusIndicator, stats: &Statistics) -> String {
+        let mut out = String::new();
+        self.render_header(&mut out);
+        self.render_status(status, &mut out);
+        self.render_statistics(stats, &mut out);
+        self.render_conversation(&mut out);
+        if let Some(panel) = &self.history_panel {
+            out.push_str(panel);
+        }
+        out
+    }
+
+    /// Accessible overview: one short fact per line
+    pub fn render_accessible(&self, status: &StatusIndicator, stats: &Statistics) -> Vec<String> {
+        let mut lines = vec![status_line(status.get_status())];
+        if status.is_guest() {
+            lines.push("[STATUS] Guest mode, nothing is saved or learned".to_string());
+        }
+        if let Some(warning) = status.quota_warning() {
+            lines.push(format!("[STATUS] {}", warning));
+        }
+        if !self.session_label.is_empty() {
+            lines.push(format!("[SESSION] {}", self.session_label));
+        }
+        let mut overview = format!("[STATS] Turns {}. Commands {}. Uptime {}.",
+            stats.turns, stats.commands_executed, stats.get_uptime_string());
+        if let Some(rss) = stats.system.rss_mb {
+            write!(overview, " Memory {} MB.", rss).ok();
+        }
+        lines.push(overview);
+        let timers = stats.get_timers_string();
+        if !timers.is_empty() {
+            lines.push(format!("[TIMERS] {}", plain_text(&timers)));
+        }
+        lines
+    }
+
+    /// Show the latest partial transcription while in `status`. Returns
+    /// whether to redraw now: at most every `CAPTION_REDRAW_INTERVAL`, the
+    /// text in between shows up with the next redraw.
+    ///
+    /// Accessibility mode doesn't announce partials; the final
+    /// transcription arrives as the user's message.
+    pub fn set_caption(&mut self, text: &str, status: EvaStatus, now: Instant) -> bool {
+        let caption = self.caption.get_or_insert_with(|| Caption { text: String::new(), status, drawn_at: None });
+        caption.text = text.to_string();
+        caption.status = status;
+        let due = !self.accessible && !caption.drawn_at.is_some_and(|at| now.duration_since(at) < CAPTION_REDRAW_INTERVAL);
+        if due {
+            caption.drawn_at = Some(now);
+        }
+        due
+    }
+
+    pub fn clear_caption(&mut self) {
+        self.caption = None;
+    }
+
+    /// Caption text currently shown
+    pub fn caption(&self) -> Option<&str> {
+        self.caption.as_ref().map(|c| c.text.as_str())
+    }
+
+    /// Draw complete UI from the shared statistics
+    pub fn draw(&mut self, status: &StatusIndicator, stats: &RwLock<Statistics>) {
+        // A poisoned lock still holds the last statistics worth showing
+        let stats = stats.read().unwrap_or_else(|e| e.into_inner());
+        if self.caption.as_ref().is_some_and(|c| c.status != status.get_status()) {
+            self.caption = None;
+        }
+        if !self.accessible {
+            let frame = self.render(status, &stats);
+            self.sink.frame(&frame);
+            return;
+        }
+
+        let current = (status.get_status(), status.is_guest());
+        match self.last_status {
+            None => {
+                let overview = self.render_accessible(status, &stats);
+                self.pending.extend(overview);
+            }
+            Some(last) if last != current => {
+                if last.0 != current.0 {
+                    self.pending.push(status_line(current.0));
+                    if self.earcons {
+                        self.pending_earcon = Earcon::for_status(current.0);
+                    }
+                }
+                if last.1 != current.1 {
+                    let state = if current.1 { "on, nothing is saved or learned" } else { "off" };
+                    self.pending.push(format!("[STATUS] Guest mode {}", state));
+                }
+            }
+            Some(_) => {}
+        }
+        self.last_status = Some(current);
+
+        for line in self.pending.drain(..) {
+            self.sink.line(&line);
+        }
+    }
+
+    /// Update the session line shown under the status
+    pub fn set_session(&mut self, session: &ConversationSession) {
+        self.session_label = match session.parent_id() {
+            Some(parent) => format!("Session: {} (branch of {}) | Turns: {}", session.session_id(), parent, session.turn_count()),
+            None => format!("Session: {} | Turns: {}", session.session_id(), session.turn_count()),
         };
-        
-        for msg in &self.conversation_log[start..] {
-            println!("│ {}", msg);
+    }
+
+    /// Show the model Gemini is running on (`None` when not connected)
+    pub fn set_model(&mut self, model: Option<&str>) {
+        self.model = model.map(str::to_string);
+    }
+
+    /// Report that the last exchange was forgotten
+    pub fn show_forgotten(&mut self, session: &ConversationSession, removed: usize) {
+        if removed == 0 {
+            self.add_system_message("Nothing to forget");
+        } else {
+            self.add_system_message(&format!("↩️  Forgot last exchange ({} turns removed)", removed));
         }
-        
-        if self.conversation_log.is_empty() {
-            println!("│ (No messages yet)");
+        self.set_session(session);
+    }
+
+    /// Report that the conversation was branched
+    pub fn show_branched(&mut self, branch: &ConversationSession) {
+        self.add_system_message(&format!("🌿 Branched conversation: {}", branch.session_id()));
+        self.set_session(branch);
+    }
+
+    /// Draw the command history view (Enter re-runs, 'e' edits, Esc closes)
+    pub fn draw_history(&mut self, history: &CommandHistory, view: &HistoryView) {
+        if self.accessible {
+            self.announce_focus(history, view);
+            return;
         }
-        
-        println!("└─────────────────────────────────────────────────────────┘");
-        println!();
+
+        let mut out = String::new();
+        writeln!(out, "┌─ Command History ───────────────────────────────────────┐").ok();
+
+        if history.is_empty() {
+            writeln!(out, "│ (no commands yet)").ok();
+        }
+
+        for (i, entry) in history.recent(10).iter().enumerate() {
+            let cursor = if i == view.selected { "▶" } else { " " };
+            let mark = if entry.success { "✅" } else { "❌" };
+            let output: String = entry.output.lines().next().unwrap_or("").chars().take(30).collect();
+            writeln!(out, "│ {} {}. {} {} → {}", cursor, i + 1, mark, describe_intent(&entry.intent), output).ok();
+        }
+
+        if let Some(ref form) = view.form {
+            writeln!(out, "├─ Edit ──────────────────────────────────────────────────┤").ok();
+            for (i, field) in form.fields.iter().enumerate() {
+                let cursor = if i == form.focused { "▶" } else { " " };
+                writeln!(out, "│ {} {}: {}", cursor, field.name, field.value).ok();
+            }
+        }
+
+        writeln!(out, "│ [↑/↓ or j/k] select  [Enter] run  [e] edit  [Esc or q] close").ok();
+        writeln!(out, "└─────────────────────────────────────────────────────────┘").ok();
+        self.history_panel = Some(out);
+    }
+
+    /// Whether the command history view is open
+    pub fn history_open(&self) -> bool {
+        self.history.is_some()
+    }
+
+    /// Apply a key to the open history view; it closes on Escape and when
+    /// a command is picked to run
+    pub fn history_key(&mut self, history: &CommandHistory, key: HistoryKey) -> HistoryAction {
+        let Some(view) = self.history.as_mut() else {
+            return HistoryAction::Close;
+        };
+        let action = view.handle_key(history, key);
+        if matches!(action, HistoryAction::Close | HistoryAction::Run { .. }) {
+            self.history = None;
+            self.history_panel = None;
+            self.last_focus = None;
+        } else if self.accessible {
+            // Said as the key moves it, not on the next redraw
+            let view = view.clone();
+            self.announce_focus(history, &view);
+        }
+        action
+    }
+
+    /// Draw the open history view again, after keys or a new command
+    pub fn refresh_history(&mut self, history: &CommandHistory) {
+        if let Some(view) = self.history.clone() {
+            self.draw_history(history, &view);
+        }
+    }
+
+    /// Accessible history: say what has focus and which keys act on it,
+    /// only when the focus moves
+    fn announce_focus(&mut self, history: &CommandHistory, view: &HistoryView) {
+        let focus = (view.selected, view.form.as_ref().map(|f| f.focused));
+        if self.last_focus == Some(focus) {
+            return;
+        }
+        self.last_focus = Some(focus);
+
+        let line = match view.form {
+            Some(ref form) => match form.fields.get(form.focused) {
+                Some(field) => format!("[FOCUS] Field {} of {}: {}, value {}. Tab moves, Enter runs, Escape cancels.",
+                    form.focused + 1, form.fields.len(), field.name,
+                    if field.value.is_empty() { "empty" } else { &field.value }),
+                None => "[FOCUS] Nothing to edit. Enter runs, Escape cancels.".to_string(),
+            },
+            None => {
+                let entries = history.recent(10);
+                match entries.get(view.selected) {
+                    Some(entry) => format!("[FOCUS] Command {} of {}: {}, {}. Up and Down move, Enter runs, E edits, Escape closes.",
+                        view.selected + 1, entries.len(), describe_intent(&entry.intent),
+                        if entry.success { "succeeded" } else { "failed" }),
+                    None => "[FOCUS] No commands yet. Escape closes.".to_string(),
+                }
+            }
+        };
+        self.sink.line(&line);
     }
 
-    /// Draw complete UI
-    pub fn draw(&self, status: &StatusIndicator, stats: &Statistics) {
-        self.clear_screen();
-        self.draw_header();
-        self.draw_status(status);
-        self.draw_statistics(stats);
-        self.draw_conversation();
+    /// Show follow-up chips (pick by number, or say it); empty clears them
+    pub fn show_suggestions(&mut self, suggestions: &[Suggestion]) {
+        self.suggestions = suggestions.iter().map(|s| s.label.clone()).collect();
+        if self.accessible && !self.suggestions.is_empty() {
+            let chips: Vec<String> = self.suggestions.iter().enumerate().map(|(i, s)| format!("{}: {}", i + 1, s)).collect();
+            self.pending.push(format!("[SUGGEST] {}", chips.join(" ")));
+        }
+    }
+
+    /// Show webhook delivery stats (one line per endpoint)
+    pub fn show_webhook_stats(&mut self, stats: &[(String, EndpointStats)]) {
+        for (name, s) in stats {
+            let state = if s.circuit_open() { " [circuit open]" } else { "" };
+            let mut line = format!("🔗 Webhook {}: {} delivered, {} failed, {} dropped{}",
+                name, s.delivered, s.failed, s.dropped, state);
+            if let Some(ref err) = s.last_error {
+                line.push_str(&format!(" (last error: {})", err));
+            }
+            self.add_system_message(&line);
+        }
     }
 
     /// Add message to conversation log
     pub fn add_message(&mut self, message: String) {
+        if self.accessible {
+            self.pending.push(plain_text(&message));
+        }
+        self.push_log(message);
+    }
+
+    fn push_log(&mut self, message: String) {
+        self.scrollback.push_back(LogEntry { at: chrono::Local::now(), text: message.clone() });
+        if self.scrollback.len() > SCROLLBACK_LIMIT {
+            self.scrollback.pop_front();
+            if let Some((_, ref mut line)) = self.search {
+                *line = line.saturating_sub(1);
+            }
+        }
+        // While scrolled up the view stays where it is
+        if let Some(hidden) = self.scroll {
+            let top = self.scrollback.len().saturating_sub(VIEWPORT_LINES);
+            self.scroll = Some((hidden + 1).min(top));
+            self.unseen += 1;
+        }
+
         self.conversation_log.push(message);
         
         // Keep log size limited
@@ -96,19 +735,43 @@ impl TerminalUI {
         }
     }
 
+    /// Log a message under its label; accessible mode also queues its tagged line
+    fn add_tagged(&mut self, kind: MessageKind, message: &str) {
+        let label = match kind {
+            MessageKind::User => "👤 User:",
+            MessageKind::Eva => "🤖 EVA:",
+            MessageKind::System => "ℹ️  System:",
+            MessageKind::Error => "❌ Error:",
+        };
+        if self.accessible {
+            self.pending.push(format!("{} {}", kind.tag(), plain_text(message)));
+        }
+        self.push_log(format!("{} {}", label, message));
+    }
+
     /// Add user message
     pub fn add_user_message(&mut self, message: &str) {
-        self.add_message(format!("👤 User: {}", message));
+        self.add_tagged(MessageKind::User, message);
     }
 
     /// Add EVA message
     pub fn add_eva_message(&mut self, message: &str) {
-        self.add_message(format!("🤖 EVA: {}", message));
+        self.add_tagged(MessageKind::Eva, message);
     }
 
     /// Add system message
     pub fn add_system_message(&mut self, message: &str) {
-        self.add_message(format!("ℹ️  System: {}", message));
+        self.add_tagged(MessageKind::System, message);
+    }
+
+    /// Add error message
+    pub fn add_error_message(&mut self, message: &str) {
+        self.add_tagged(MessageKind::Error, message);
+    }
+
+    /// Typed input was opened with `i` or `/`
+    pub fn show_input_prompt(&mut self) {
+        self.add_system_message("⌨️  Type a message and press Enter (empty line cancels)");
     }
 
     /// Get ANSI color code
@@ -120,23 +783,158 @@ impl TerminalUI {
             "green" => "\x1B[32m",
             "cyan" => "\x1B[36m",
             "red" => "\x1B[31m",
+            "magenta" => "\x1B[35m",
             _ => "\x1B[0m",
         }
     }
 
-    /// Cleanup (placeholder for compatibility)
-    pub fn cleanup(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.clear_screen();
-        Ok(())
+    /// Hand the terminal back on exit: the dashboard is cleared (the
+    /// accessible transcript stays on screen) and attributes are reset
+    pub fn teardown(&mut self) {
+        if !self.accessible {
+            self.clear_screen();
+        }
+        reset_terminal();
     }
 }
 
+/// Reset colours and show the cursor; needs no `TerminalUI`, so a panic or
+/// a forced exit can call it too
+pub fn reset_terminal() {
+    print!("\x1B[0m\x1B[?25h");
+    io::stdout().flush().ok();
+}
+
+/// Mark case-insensitive matches of `query` in reverse video
+fn highlight(text: &str, query: Option<&str>) -> String {
+    let Some(query) = query.filter(|q| !q.is_empty()) else { return text.to_string() };
+    // ASCII lowercasing keeps byte offsets, so they index `text` too
+    let (haystack, needle) = (text.to_ascii_lowercase(), query.to_ascii_lowercase());
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (at, _) in haystack.match_indices(&needle) {
+        out.push_str(&text[last..at]);
+        write!(out, "\x1B[7m{}\x1B[0m", &text[at..at + needle.len()]).ok();
+        last = at + needle.len();
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
 impl Default for TerminalUI {
     fn default() -> Self {
         Self::new().expect("Failed to create TerminalUI")
     }
 }
 
+/// What one typed line means
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputLine {
+    /// `i` or `/` alone: show the prompt, the next line is the message
+    Open,
+    /// A complete message to handle like an utterance
+    Send(String),
+    /// Stray keystrokes, or an empty line closing the prompt
+    Ignore,
+    /// `q` in the live view: shut EVA down
+    Quit,
+    /// `h` in the live view opens the command history; while it is open
+    /// every line is keys for it (none when it just opened)
+    History(Vec<HistoryKey>),
+}
+
+/// Keys for the history view from one typed line
+///
+/// The terminal is line-buffered: arrows arrive as escape sequences and
+/// Enter ends the line. In the edit form a typed line replaces the
+/// focused field and moves to the next one; an empty line runs.
+fn history_keys(line: &str, view: &HistoryView) -> Vec<HistoryKey> {
+    let key = match line {
+        "\t" => Some(HistoryKey::Tab),
+        KEY_UP => Some(HistoryKey::Up),
+        KEY_DOWN => Some(HistoryKey::Down),
+        KEY_ESCAPE => Some(HistoryKey::Escape),
+        _ if line.trim().is_empty() => Some(HistoryKey::Enter),
+        _ => None,
+    };
+    if let Some(key) = key {
+        return vec![key];
+    }
+    match &view.form {
+        Some(form) => {
+            let typed = form.fields.get(form.focused).map_or(0, |f| f.value.chars().count());
+            std::iter::repeat_n(HistoryKey::Backspace, typed)
+                .chain(line.trim().chars().map(HistoryKey::Char))
+                .chain(std::iter::once(HistoryKey::Tab))
+                .collect()
+        }
+        None => match line.trim() {
+            "k" => vec![HistoryKey::Up],
+            "j" => vec![HistoryKey::Down],
+            "e" => vec![HistoryKey::Edit],
+            "q" => vec![HistoryKey::Escape],
+            _ => Vec::new(),
+        },
+    }
+}
+
+/// Typed input, so EVA can be driven without a microphone
+///
+/// The terminal stays line-buffered: `i` or `/` on its own opens the input
+/// line, and `/list files` sends a message in one go.
+pub struct TextInput {
+    lines: tokio::sync::mpsc::UnboundedReceiver<String>,
+    open: bool,
+}
+
+impl TextInput {
+    /// Read stdin on a background thread
+    pub fn spawn() -> Self {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            for line in io::stdin().lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Self::new(rx)
+    }
+
+    fn new(lines: tokio::sync::mpsc::UnboundedReceiver<String>) -> Self {
+        Self { lines, open: false }
+    }
+
+    /// Next raw line if one has been typed
+    pub fn try_line(&mut self) -> Option<String> {
+        self.lines.try_recv().ok()
+    }
+
+    /// Whether the input prompt is open, so the next line is a message
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Interpret a line typed by the user
+    pub fn accept(&mut self, line: &str) -> InputLine {
+        let line = line.trim();
+        if std::mem::take(&mut self.open) {
+            return if line.is_empty() { InputLine::Ignore } else { InputLine::Send(line.to_string()) };
+        }
+        match line {
+            "i" | "/" => {
+                self.open = true;
+                InputLine::Open
+            }
+            _ => match line.strip_prefix('/').map(str::trim) {
+                Some(text) if !text.is_empty() => InputLine::Send(text.to_string()),
+                _ => InputLine::Ignore,
+            },
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,4 +973,336 @@ mod tests {
         
         assert!(ui.conversation_log[0].contains("EVA:"));
     }
+
+    #[test]
+    fn test_session_affordances() {
+        let mut ui = TerminalUI::new().unwrap();
+        let mut session = ConversationSession::new();
+        session.add_turn(crate::session::Role::User, "Hi".to_string());
+
+        ui.set_session(&session);
+        assert!(ui.session_label.contains(session.session_id()));
+
+        let branch = session.branch();
+        ui.show_branched(&branch);
+        assert!(ui.session_label.contains("branch of"));
+        assert!(ui.conversation_log[0].contains(branch.session_id()));
+    }
+
+    /// Scripted conversation as heard through a screen reader
+    #[test]
+    fn test_accessible_transcript_snapshot() {
+        let sink = HeadlessSink::new();
+        let mut ui = TerminalUI::with_sink(Box::new(sink.clone()));
+        let mut status = StatusIndicator::new();
+        let stats = RwLock::new(Statistics::new());
+        ui.set_accessible(true, true);
+
+        status.set_status(EvaStatus::Idle);
+        ui.draw(&status, &stats);
+        assert_eq!(ui.take_earcon(), None);
+
+        status.set_status(EvaStatus::Listening);
+        ui.add_system_message("🎤 Wake word detected!");
+        ui.draw(&status, &stats);
+        assert_eq!(ui.take_earcon(), Some(Earcon::Listening));
+
+        // Animation frames redraw without changing status: nothing is repeated
+        status.set_symbol("🔵");
+        ui.draw(&status, &stats);
+
+        ui.add_user_message("what time is it");
+        status.set_status(EvaStatus::Speaking);
+        ui.add_eva_message("It is ten o'clock.");
+        ui.draw(&status, &stats);
+
+        ui.add_error_message("Stream error: connection reset");
+        status.set_status(EvaStatus::Idle);
+        status.set_guest(true);
+        ui.draw(&status, &stats);
+
+        assert_eq!(sink.lines().join("\n"), "\
+[INFO] Accessibility mode on
+[STATUS] Idle, say Hey EVA to start
+[STATS] Turns 0. Commands 0. Uptime 0s.
+[INFO] Wake word detected!
+[STATUS] Listening
+[YOU] what time is it
+[EVA] It is ten o'clock.
+[STATUS] Speaking
+[ERROR] Stream error: connection reset
+[STATUS] Idle, say Hey EVA to start
+[STATUS] Guest mode on, nothing is saved or learned");
+        // The standard log keeps its own labels
+        assert!(ui.conversation_log[1].contains("User:"));
+    }
+
+    #[test]
+    fn test_standard_mode_redraws_frames() {
+        let sink = HeadlessSink::new();
+        let mut ui = TerminalUI::with_sink(Box::new(sink.clone()));
+        let status = StatusIndicator::new();
+        let stats = RwLock::new(Statistics::new());
+
+        ui.add_eva_message("Hello");
+        ui.draw(&status, &stats);
+        ui.draw(&status, &stats);
+
+        let lines = sink.lines();
+        assert_eq!(lines.iter().filter(|l| l.contains("🤖 EVA: Hello")).count(), 2);
+        assert!(lines.iter().any(|l| l.starts_with("┌─ Status")));
+        assert!(!lines.iter().any(|l| l.starts_with("[EVA]")));
+    }
+
+    #[test]
+    fn test_live_caption_rate_limited_and_cleared() {
+        let sink = HeadlessSink::new();
+        let mut ui = TerminalUI::with_sink(Box::new(sink.clone()));
+        let mut status = StatusIndicator::new();
+        let stats = RwLock::new(Statistics::new());
+        status.set_status(EvaStatus::Listening);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // First partial draws; the next within 200 ms only updates the text
+        assert!(ui.set_caption("turn on", EvaStatus::Listening, at(0)));
+        assert!(!ui.set_caption("turn on the", EvaStatus::Listening, at(100)));
+        assert!(ui.set_caption("turn on the lights", EvaStatus::Listening, at(250)));
+        ui.draw(&status, &stats);
+        assert!(sink.lines().iter().any(|l| l.contains("🎙️") && l.contains("turn on the lights")));
+
+        // Gone once the status moves on
+        status.set_status(EvaStatus::Processing);
+        ui.draw(&status, &stats);
+        assert_eq!(ui.caption(), None);
+
+        // Accessibility mode never redraws for partials
+        ui.set_accessible(true, false);
+        assert!(!ui.set_caption("hello", EvaStatus::Listening, at(1000)));
+    }
+
+    #[test]
+    fn test_history_hotkey_drives_the_view() {
+        use crate::command_parser::{CommandIntent, NetworkOperation, ProcessOperation};
+
+        let mut history = CommandHistory::new();
+        history.record(CommandIntent::Process(ProcessOperation::Kill { pid: 42 }), &Err("denied".to_string()));
+        history.record(CommandIntent::Network(NetworkOperation::Ping { host: "example.com".to_string() }), &Ok("pong".to_string()));
+
+        let mut ui = TerminalUI::with_sink(Box::new(HeadlessSink::new()));
+        let mut input = TextInput::new(tokio::sync::mpsc::unbounded_channel().1);
+        assert_eq!(ui.handle_line("h", &mut input), InputLine::History(Vec::new()));
+        assert!(ui.history_open());
+
+        // Lines are keys while the view is open
+        assert_eq!(ui.handle_line("j", &mut input), InputLine::History(vec![HistoryKey::Down]));
+        assert_eq!(ui.handle_line("q", &mut input), InputLine::History(vec![HistoryKey::Escape]));
+        ui.history_key(&history, HistoryKey::Down);
+        ui.history_key(&history, HistoryKey::Edit);
+
+        // In the edit form a typed line replaces the field
+        let InputLine::History(keys) = ui.handle_line("7", &mut input) else { panic!("not history keys") };
+        assert_eq!(keys, vec![HistoryKey::Backspace, HistoryKey::Backspace, HistoryKey::Char('7'), HistoryKey::Tab]);
+        for key in keys {
+            ui.history_key(&history, key);
+        }
+        assert_eq!(
+            ui.history_key(&history, HistoryKey::Enter),
+            HistoryAction::Run { intent: CommandIntent::Process(ProcessOperation::Kill { pid: 7 }), confirm: true }
+        );
+        assert!(!ui.history_open());
+        assert_eq!(ui.handle_line("q", &mut input), InputLine::Quit);
+    }
+
+    #[test]
+    fn test_accessible_history_announces_focus() {
+        use crate::command_history::{HistoryKey, HistoryView};
+        use crate::command_parser::{CommandIntent, NetworkOperation, ProcessOperation};
+
+        let mut history = CommandHistory::new();
+        history.record(CommandIntent::Process(ProcessOperation::Kill { pid: 42 }), &Err("denied".to_string()));
+        history.record(CommandIntent::Network(NetworkOperation::Ping { host: "example.com".to_string() }), &Ok("pong".to_string()));
+
+        let sink = HeadlessSink::new();
+        let mut ui = TerminalUI::with_sink(Box::new(sink.clone()));
+        ui.set_accessible(true, false);
+        let mut view = HistoryView::default();
+
+        ui.draw_history(&history, &view);
+        ui.draw_history(&history, &view);
+        view.handle_key(&history, HistoryKey::Down);
+        ui.draw_history(&history, &view);
+        view.handle_key(&history, HistoryKey::Edit);
+        ui.draw_history(&history, &view);
+
+        assert_eq!(sink.lines(), vec![
+            "[FOCUS] Command 1 of 2: ping example.com, succeeded. Up and Down move, Enter runs, E edits, Escape closes.",
+            "[FOCUS] Command 2 of 2: kill process 42, failed. Up and Down move, Enter runs, E edits, Escape closes.",
+            "[FOCUS] Field 1 of 1: pid, value 42. Tab moves, Enter runs, Escape cancels.",
+        ]);
+
+        // Keys on the open view announce the focus they move to
+        let sink = HeadlessSink::new();
+        let mut ui = TerminalUI::with_sink(Box::new(sink.clone()));
+        ui.set_accessible(true, false);
+        let mut input = TextInput::new(tokio::sync::mpsc::unbounded_channel().1);
+        ui.handle_line("h", &mut input);
+        ui.history_key(&history, HistoryKey::Down);
+        ui.refresh_history(&history);
+        assert_eq!(sink.lines(), vec![
+            "[FOCUS] Command 2 of 2: kill process 42, failed. Up and Down move, Enter runs, E edits, Escape closes.",
+        ]);
+    }
+
+    #[test]
+    fn test_suggestion_chips() {
+        use crate::command_parser::{CommandIntent, FileOperation};
+        use crate::suggestions::{FollowUpSource, FollowUps};
+        use std::time::{Duration, Instant};
+
+        let sink = HeadlessSink::new();
+        let mut ui = TerminalUI::with_sink(Box::new(sink.clone()));
+        let status = StatusIndicator::new();
+        let stats = RwLock::new(Statistics::new());
+
+        let intent = CommandIntent::File(FileOperation::List { path: None });
+        let now = Instant::now();
+        let mut follow_ups = FollowUps::with_window(Duration::from_secs(10));
+        ui.show_suggestions(follow_ups.offer(&FollowUpSource::Command { intent: &intent, output: "📄 a.txt (1 bytes)" }, "en", now));
+        assert!(ui.render(&status, &stats.read().unwrap()).contains("💡 [1] Read a.txt?"));
+        let mut input = TextInput::new(tokio::sync::mpsc::unbounded_channel().1);
+        assert_eq!(ui.handle_line("1", &mut input), InputLine::Send("1".to_string()));
+        assert_eq!(ui.handle_line("7", &mut input), InputLine::Ignore);
+
+        let closed = now + Duration::from_secs(10);
+        if follow_ups.expire(closed) {
+            ui.show_suggestions(follow_ups.active(closed));
+        }
+        assert!(!ui.render(&status, &stats.read().unwrap()).contains("💡"));
+
+        ui.set_accessible(true, false);
+        ui.show_suggestions(follow_ups.offer(&FollowUpSource::Command { intent: &intent, output: "📄 a.txt (1 bytes)" }, "en", closed));
+        ui.draw(&status, &stats);
+        assert!(sink.lines().contains(&"[SUGGEST] 1: Read a.txt?".to_string()));
+    }
+
+    #[test]
+    fn test_typed_input_lines() {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        drop(tx);
+        let mut input = TextInput::new(rx);
+
+        assert_eq!(input.accept("/list files"), InputLine::Send("list files".to_string()));
+        assert_eq!(input.accept("list files"), InputLine::Ignore);
+        assert_eq!(input.accept("i"), InputLine::Open);
+        assert_eq!(input.accept("  what time is it "), InputLine::Send("what time is it".to_string()));
+        assert_eq!(input.accept("/"), InputLine::Open);
+        assert_eq!(input.accept(""), InputLine::Ignore);
+        assert_eq!(input.accept("i"), InputLine::Open);
+        assert_eq!(input.accept("i"), InputLine::Send("i".to_string()));
+    }
+
+    fn ui_with_messages(count: usize) -> TerminalUI {
+        let mut ui = TerminalUI::with_sink(Box::new(HeadlessSink::new()));
+        for i in 0..count {
+            ui.add_user_message(&format!("message {}", i));
+        }
+        ui
+    }
+
+    fn visible(ui: &TerminalUI) -> Vec<String> {
+        let mut out = String::new();
+        ui.render_conversation(&mut out);
+        out.lines().filter(|l| l.starts_with("│ ")).map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_scrollback_viewport() {
+        let mut ui = ui_with_messages(60);
+        // More than the recent log keeps
+        assert_eq!(ui.scrollback.len(), 60);
+        assert!(visible(&ui).last().unwrap().ends_with("message 59"));
+
+        ui.scroll_up();
+        let lines = visible(&ui);
+        assert!(lines[0].ends_with("message 40") && lines[9].ends_with("message 49"), "{:?}", lines);
+
+        // New messages don't move the view, they're counted
+        ui.add_eva_message("late reply");
+        ui.add_eva_message("another");
+        let lines = visible(&ui);
+        assert!(lines[9].ends_with("message 49"));
+        assert_eq!(lines[10], "│ ↓ 2 new messages below (PgDn)");
+
+        ui.scroll_down();
+        assert!(visible(&ui)[9].ends_with("message 59"));
+        ui.scroll_down();
+        assert_eq!(ui.scroll, None);
+        assert!(visible(&ui)[9].ends_with("another"));
+
+        // The top stops at the first message
+        for _ in 0..10 {
+            ui.scroll_up();
+        }
+        assert!(visible(&ui)[0].ends_with("message 0"));
+    }
+
+    #[test]
+    fn test_search_highlights_and_scrolls() {
+        let mut ui = ui_with_messages(30);
+        ui.add_eva_message("Deploying MAIN to staging");
+        for i in 0..20 {
+            ui.add_system_message(&format!("noise {}", i));
+        }
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let mut input = TextInput::new(rx);
+        for line in [KEY_PAGE_UP, "/", "main", "hello"] {
+            tx.send(line.to_string()).unwrap();
+        }
+        // The view keys and the query are handled; the stray word is not
+        assert_eq!(ui.handle_input(&mut input), Some(InputLine::Ignore));
+        assert_eq!(ui.handle_input(&mut input), None);
+
+        let lines = visible(&ui);
+        assert!(lines.iter().any(|l| l.contains("Deploying \x1B[7mMAIN\x1B[0m to staging")), "{:?}", lines);
+        assert!(!ui.search_next());
+        assert!(visible(&ui).iter().any(|l| l.contains("No more matches")));
+
+        assert!(ui.search("message 1"));
+        assert!(visible(&ui).iter().any(|l| l.contains("\x1B[7mmessage 1\x1B[0m9")));
+
+        // "/" in the live view still opens typed input
+        assert_eq!(ui.handle_line("q", &mut input), InputLine::Ignore);
+        assert_eq!(ui.handle_line("/", &mut input), InputLine::Open);
+        assert_eq!(ui.handle_line(KEY_PAGE_UP, &mut input), InputLine::Send(KEY_PAGE_UP.to_string()));
+
+        // Scrolled back, "q" returns to live; from there it quits, unless
+        // the input prompt is open
+        ui.scroll_up();
+        assert_eq!(ui.handle_line("q", &mut input), InputLine::Ignore);
+        assert_eq!(ui.handle_line("q", &mut input), InputLine::Quit);
+        assert_eq!(ui.handle_line("i", &mut input), InputLine::Open);
+        assert_eq!(ui.handle_line("q", &mut input), InputLine::Send("q".to_string()));
+    }
+
+    #[test]
+    fn test_export_conversation() {
+        let mut ui = ui_with_messages(0);
+        ui.add_system_message("EVA OS Started");
+        ui.add_user_message("what time is it");
+        ui.add_eva_message("It is ten o'clock.");
+
+        let dir = std::env::temp_dir().join(format!("eva_test_export_{}", std::process::id()));
+        let path = ui.export_conversation(&dir).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        // "[YYYY-MM-DD HH:MM:SS] ..."
+        assert_eq!(&lines[1][0..1], "[");
+        assert_eq!(&lines[1][20..], "] User: what time is it");
+        assert!(lines[2].ends_with("EVA: It is ten o'clock."));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
 }
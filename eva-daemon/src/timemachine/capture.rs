@@ -1,4 +1,4 @@
-use image::DynamicImage;
+use image::{imageops, DynamicImage, GenericImageView, Rgba};
 use screenshots::Screen;
 use std::error::Error;
 
@@ -64,11 +64,231 @@ const BLOCKED_APP_NAMES: &[&str] = &[
     "authy",
 ];
 
+/// Side of the blocks a pixelated window is reduced to, in pixels
+const PIXELATE_BLOCK: u32 = 16;
+
+/// How a masked window is hidden
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaskStyle {
+    /// Solid black
+    BlackOut,
+    /// Coarse blocks: the layout stays recognizable, text doesn't
+    Pixelate,
+}
+
+/// What happens to a capture showing a window a privacy pattern matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrivacyAction {
+    /// Skip every screen the window may be on
+    #[default]
+    Block,
+    /// Hide just the window's rectangle and keep the rest of the screen;
+    /// blocks like `Block` when the window's position is unknown
+    Mask(MaskStyle),
+}
+
+/// A user-defined privacy pattern and what it does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrivacyPattern {
+    /// Lowercase text looked for in the window title and app name
+    pub pattern: String,
+    pub action: PrivacyAction,
+}
+
+impl PrivacyPattern {
+    pub fn new(pattern: &str, action: PrivacyAction) -> Self {
+        Self { pattern: pattern.to_lowercase(), action }
+    }
+}
+
+/// Why a window is kept out of captures, and how
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrivacyMatch {
+    pub reason: String,
+    pub action: PrivacyAction,
+}
+
+/// Which screens a capture tick records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptureMode {
+    /// The primary screen only
+    #[default]
+    Primary,
+    /// Every screen, each stored as its own capture with a shared group id
+    All,
+    /// Whichever screen shows the focused window
+    ActiveWindowScreen,
+}
+
+impl CaptureMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CaptureMode::Primary => "primary",
+            CaptureMode::All => "all",
+            CaptureMode::ActiveWindowScreen => "active_window_screen",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().replace(['-', ' '], "_").as_str() {
+            "primary" => Some(CaptureMode::Primary),
+            "all" => Some(CaptureMode::All),
+            "active_window_screen" | "active" => Some(CaptureMode::ActiveWindowScreen),
+            _ => None,
+        }
+    }
+}
+
+/// A rectangle in desktop coordinates (screens left or above the primary
+/// one have negative positions)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Bounds {
+    fn right(&self) -> i64 {
+        self.x as i64 + self.width as i64
+    }
+
+    fn bottom(&self) -> i64 {
+        self.y as i64 + self.height as i64
+    }
+
+    pub fn contains(&self, x: i64, y: i64) -> bool {
+        x >= self.x as i64 && x < self.right() && y >= self.y as i64 && y < self.bottom()
+    }
+
+    pub fn intersects(&self, other: &Bounds) -> bool {
+        (self.x as i64) < other.right() && (other.x as i64) < self.right() && (self.y as i64) < other.bottom() && (other.y as i64) < self.bottom()
+    }
+
+    fn center(&self) -> (i64, i64) {
+        (self.x as i64 + self.width as i64 / 2, self.y as i64 + self.height as i64 / 2)
+    }
+}
+
+/// Where a screen sits on the desktop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenInfo {
+    pub bounds: Bounds,
+    pub primary: bool,
+}
+
+/// The focused window
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ActiveWindow {
+    pub title: String,
+    pub app_name: String,
+    /// `None` where the platform doesn't report window positions
+    pub bounds: Option<Bounds>,
+}
+
+/// One screen's image from a capture tick
+pub struct ScreenImage {
+    /// Position in the system's screen list
+    pub screen_index: usize,
+    pub image: DynamicImage,
+    /// The focused window may be on this screen, so its title and app
+    /// describe the capture
+    pub shows_window: bool,
+    /// Part of the image was hidden by a masking privacy pattern
+    pub masked: bool,
+}
+
+/// What one capture tick produced
+pub struct CaptureSet {
+    pub images: Vec<ScreenImage>,
+    /// Screens left out because a blocked window was on them
+    pub blocked: usize,
+}
+
+/// Index of the primary screen, or the first one
+fn primary_screen(screens: &[ScreenInfo]) -> usize {
+    screens.iter().position(|s| s.primary).unwrap_or(0)
+}
+
+/// Screens `mode` records, by index into `screens`
+///
+/// `ActiveWindowScreen` picks the screen under the window's center, and the
+/// primary screen when the window's position is unknown or off every screen.
+pub fn select_screens(mode: CaptureMode, screens: &[ScreenInfo], window: Option<Bounds>) -> Vec<usize> {
+    if screens.is_empty() {
+        return Vec::new();
+    }
+    match mode {
+        CaptureMode::Primary => vec![primary_screen(screens)],
+        CaptureMode::All => (0..screens.len()).collect(),
+        CaptureMode::ActiveWindowScreen => {
+            let hosting = window.and_then(|w| {
+                let (x, y) = w.center();
+                screens.iter().position(|s| s.bounds.contains(x, y))
+            });
+            vec![hosting.unwrap_or_else(|| primary_screen(screens))]
+        }
+    }
+}
+
+/// Whether a window at `window` may show on `screen`: any overlap counts,
+/// and so does not knowing where the window is
+///
+/// A blocked window keeps every screen it may show on out of the capture.
+pub fn window_on_screen(screen: &Bounds, window: Option<Bounds>) -> bool {
+    window.is_none_or(|w| w.intersects(screen))
+}
+
+/// Hide the part of `image` (a capture of the screen at `screen`) that
+/// `window` covers; false if the window isn't on it
+///
+/// Desktop coordinates are scaled to the image, which is larger than the
+/// screen's bounds on HiDPI displays.
+pub fn mask_window(image: &mut DynamicImage, screen: &Bounds, window: &Bounds, style: MaskStyle) -> bool {
+    if !window.intersects(screen) || screen.width == 0 || screen.height == 0 {
+        return false;
+    }
+    let (width, height) = image.dimensions();
+    let to_image = |offset: i64, extent: u32, size: u32| (offset.clamp(0, extent as i64) as u64 * size as u64).div_ceil(extent as u64) as u32;
+    let left = to_image(window.x as i64 - screen.x as i64, screen.width, width);
+    let top = to_image(window.y as i64 - screen.y as i64, screen.height, height);
+    let right = to_image(window.right() - screen.x as i64, screen.width, width);
+    let bottom = to_image(window.bottom() - screen.y as i64, screen.height, height);
+    if left >= right || top >= bottom {
+        return false;
+    }
+
+    let mut rgba = image.to_rgba8();
+    match style {
+        MaskStyle::BlackOut => {
+            for y in top..bottom {
+                for x in left..right {
+                    rgba.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+                }
+            }
+        }
+        MaskStyle::Pixelate => {
+            let patch = imageops::crop_imm(&rgba, left, top, right - left, bottom - top).to_image();
+            let small = imageops::resize(
+                &patch,
+                patch.width().div_ceil(PIXELATE_BLOCK),
+                patch.height().div_ceil(PIXELATE_BLOCK),
+                imageops::FilterType::Triangle,
+            );
+            let blocky = imageops::resize(&small, patch.width(), patch.height(), imageops::FilterType::Nearest);
+            imageops::replace(&mut rgba, &blocky, left as i64, top as i64);
+        }
+    }
+    *image = DynamicImage::ImageRgba8(rgba);
+    true
+}
+
 /// Screen capture with privacy filtering
 pub struct ScreenCapture {
     screens: Vec<Screen>,
-    /// User-defined blocked window patterns
-    user_blocked_patterns: Vec<String>,
+    /// User-defined privacy patterns, checked before the built-in lists
+    user_patterns: Vec<PrivacyPattern>,
     /// Whether privacy filter is enabled
     privacy_enabled: bool,
 }
@@ -80,14 +300,19 @@ impl ScreenCapture {
 
         Self {
             screens,
-            user_blocked_patterns: Vec::new(),
+            user_patterns: Vec::new(),
             privacy_enabled: true,
         }
     }
 
     /// Add a user-defined pattern to block
     pub fn add_blocked_pattern(&mut self, pattern: String) {
-        self.user_blocked_patterns.push(pattern.to_lowercase());
+        self.add_pattern(PrivacyPattern::new(&pattern, PrivacyAction::Block));
+    }
+
+    /// Add a user-defined pattern with its own action
+    pub fn add_pattern(&mut self, pattern: PrivacyPattern) {
+        self.user_patterns.push(pattern);
     }
 
     /// Enable or disable privacy filter
@@ -95,19 +320,70 @@ impl ScreenCapture {
         self.privacy_enabled = enabled;
     }
 
-    /// Take a screenshot with privacy filtering
-    pub fn take_screenshot(&self) -> Result<DynamicImage, Box<dyn Error>> {
+    /// Where each screen sits, in the system's order
+    pub fn screen_infos(&self) -> Vec<ScreenInfo> {
+        self.screens
+            .iter()
+            .map(|screen| {
+                let info = screen.display_info;
+                ScreenInfo {
+                    bounds: Bounds { x: info.x, y: info.y, width: info.width, height: info.height },
+                    primary: info.is_primary,
+                }
+            })
+            .collect()
+    }
+
+    /// Capture the screens `mode` selects, with privacy filtering
+    ///
+    /// A blocked `window` only keeps the screens it overlaps out of the
+    /// set, so in `All` mode the other screens are still recorded. A masked
+    /// one is hidden on the screens it overlaps, which are then kept.
+    pub fn take_screenshots(&self, mode: CaptureMode, window: Option<&ActiveWindow>) -> Result<CaptureSet, Box<dyn Error>> {
         if self.screens.is_empty() {
             return Err("No screens found".into());
         }
 
-        // Check privacy before capturing
-        if self.privacy_enabled && self.should_block()? {
-            return Err("Screenshot blocked by privacy filter".into());
+        let infos = self.screen_infos();
+        let matched = window
+            .filter(|_| self.privacy_enabled)
+            .and_then(|w| privacy_match(&w.title, &w.app_name, &self.user_patterns).map(|m| (m, w.bounds)));
+
+        let mut set = CaptureSet { images: Vec::new(), blocked: 0 };
+        for index in select_screens(mode, &infos, window.and_then(|w| w.bounds)) {
+            let screen = infos[index].bounds;
+            let mut mask = None;
+            if let Some((privacy, bounds)) = matched.as_ref() {
+                if window_on_screen(&screen, *bounds) {
+                    match (privacy.action, bounds) {
+                        (PrivacyAction::Mask(style), Some(bounds)) => mask = Some((style, *bounds)),
+                        _ => {
+                            println!("[Privacy] Blocked screen {}: {}", index + 1, privacy.reason);
+                            set.blocked += 1;
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            let mut image = Self::capture_screen(&self.screens[index])?;
+            let masked = mask.is_some_and(|(style, bounds)| mask_window(&mut image, &screen, &bounds, style));
+            if masked {
+                if let Some((privacy, _)) = matched.as_ref() {
+                    println!("[Privacy] Masked window on screen {}: {}", index + 1, privacy.reason);
+                }
+            }
+            set.images.push(ScreenImage {
+                screen_index: index,
+                image,
+                shows_window: window.is_some_and(|w| window_on_screen(&screen, w.bounds)),
+                masked,
+            });
         }
+        Ok(set)
+    }
 
-        // Capture primary screen
-        let screen = self.screens[0];
+    fn capture_screen(screen: &Screen) -> Result<DynamicImage, Box<dyn Error>> {
         let image = screen.capture().ok_or("Failed to capture screen")?;
 
         let buffer = image.buffer();
@@ -118,86 +394,60 @@ impl ScreenCapture {
         Ok(dynamic_image)
     }
 
-    /// Check if current screen should be blocked
-    fn should_block(&self) -> Result<bool, Box<dyn Error>> {
-        // Get active window information
-        let active_window = self.get_active_window_info();
-
-        if let Some((title, app_name)) = active_window {
-            let title_lower = title.to_lowercase();
-            let app_lower = app_name.to_lowercase();
-
-            // Check against built-in blocked titles
-            for blocked in BLOCKED_WINDOW_TITLES {
-                if title_lower.contains(blocked) {
-                    println!("[Privacy] Blocked: title contains '{}'", blocked);
-                    return Ok(true);
-                }
-            }
-
-            // Check against built-in blocked apps
-            for blocked in BLOCKED_APP_NAMES {
-                if app_lower.contains(blocked) {
-                    println!("[Privacy] Blocked: app '{}' is in blocklist", app_lower);
-                    return Ok(true);
-                }
-            }
-
-            // Check against user-defined patterns
-            for pattern in &self.user_blocked_patterns {
-                if title_lower.contains(pattern) || app_lower.contains(pattern) {
-                    println!("[Privacy] Blocked: matches user pattern '{}'", pattern);
-                    return Ok(true);
-                }
-            }
-        }
-
-        Ok(false)
+    /// Title, application and position of the active window, if known
+    pub fn active_window(&self) -> Option<ActiveWindow> {
+        self.get_active_window_info()
     }
 
     /// Get information about the currently active window
-    #[cfg(target_os = "windows")]
-    fn get_active_window_info(&self) -> Option<(String, String)> {
-        // Try using active-win-pos-rs
-        match active_win_pos_rs::get_active_window() {
-            Ok(window) => {
-                let title = window.title;
-                let app_name = window.app_name;
-                Some((title, app_name))
-            }
-            Err(_) => None,
-        }
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    fn get_active_window_info(&self) -> Option<ActiveWindow> {
+        let window = active_win_pos_rs::get_active_window().ok()?;
+        let position = window.position;
+        // Minimized or off-screen windows report an empty rectangle
+        let bounds = (position.width >= 1.0 && position.height >= 1.0).then(|| Bounds {
+            x: position.x.round() as i32,
+            y: position.y.round() as i32,
+            width: position.width.round() as u32,
+            height: position.height.round() as u32,
+        });
+        Some(ActiveWindow { title: window.title, app_name: window.app_name, bounds })
     }
 
-    #[cfg(target_os = "linux")]
-    fn get_active_window_info(&self) -> Option<(String, String)> {
-        match active_win_pos_rs::get_active_window() {
-            Ok(window) => {
-                let title = window.title;
-                let app_name = window.app_name;
-                Some((title, app_name))
-            }
-            Err(_) => None,
-        }
+    #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+    fn get_active_window_info(&self) -> Option<ActiveWindow> {
+        // Redox OS or other - no window detection available
+        None
     }
+}
 
-    #[cfg(target_os = "macos")]
-    fn get_active_window_info(&self) -> Option<(String, String)> {
-        match active_win_pos_rs::get_active_window() {
-            Ok(window) => {
-                let title = window.title;
-                let app_name = window.app_name;
-                Some((title, app_name))
-            }
-            Err(_) => None,
-        }
+/// Why a window must not be captured as is, or `None` if it may be
+///
+/// User patterns come first, so one can turn a built-in block into a mask
+/// (e.g. "bank" masked instead of blocked).
+pub fn privacy_match(title: &str, app_name: &str, user_patterns: &[PrivacyPattern]) -> Option<PrivacyMatch> {
+    let title_lower = title.to_lowercase();
+    let app_lower = app_name.to_lowercase();
+
+    // Check against user-defined patterns
+    if let Some(p) = user_patterns
+        .iter()
+        .find(|p| title_lower.contains(p.pattern.as_str()) || app_lower.contains(p.pattern.as_str()))
+    {
+        return Some(PrivacyMatch { reason: format!("matches user pattern '{}'", p.pattern), action: p.action });
     }
 
-    #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
-    fn get_active_window_info(&self) -> Option<(String, String)> {
-        // Redox OS or other - no window detection available
-        None
+    // Check against built-in blocked titles
+    if let Some(blocked) = BLOCKED_WINDOW_TITLES.iter().find(|b| title_lower.contains(*b)) {
+        return Some(PrivacyMatch { reason: format!("title contains '{}'", blocked), action: PrivacyAction::Block });
+    }
+
+    // Check against built-in blocked apps
+    if BLOCKED_APP_NAMES.iter().any(|b| app_lower.contains(b)) {
+        return Some(PrivacyMatch { reason: format!("app '{}' is in blocklist", app_lower), action: PrivacyAction::Block });
     }
+
+    None
 }
 
 impl Default for ScreenCapture {
@@ -250,34 +500,36 @@ mod tests {
 
     #[test]
     fn test_user_patterns() {
-        let mut capture = ScreenCapture {
+        let capture = ScreenCapture {
             screens: vec![],
-            user_blocked_patterns: vec!["secret project".to_string()],
+            user_patterns: vec![PrivacyPattern::new("secret project", PrivacyAction::Block)],
             privacy_enabled: true,
         };
 
         // Test that user patterns work
-        assert!(capture.user_blocked_patterns.contains(&"secret project".to_string()));
+        let matched = privacy_match("Secret Project - Notes", "editor", &capture.user_patterns).unwrap();
+        assert_eq!(matched.action, PrivacyAction::Block);
+        assert!(privacy_match("Notes", "editor", &capture.user_patterns).is_none());
     }
 
     #[test]
     fn test_add_blocked_pattern() {
         let mut capture = ScreenCapture {
             screens: vec![],
-            user_blocked_patterns: vec![],
+            user_patterns: vec![],
             privacy_enabled: true,
         };
 
         capture.add_blocked_pattern("MY_SECRET".to_string());
         // Should be lowercased
-        assert!(capture.user_blocked_patterns.contains(&"my_secret".to_string()));
+        assert_eq!(capture.user_patterns, vec![PrivacyPattern::new("my_secret", PrivacyAction::Block)]);
     }
 
     #[test]
     fn test_privacy_toggle() {
         let mut capture = ScreenCapture {
             screens: vec![],
-            user_blocked_patterns: vec![],
+            user_patterns: vec![],
             privacy_enabled: true,
         };
 
@@ -355,12 +607,135 @@ mod tests {
     fn test_no_screens_error() {
         let capture = ScreenCapture {
             screens: vec![],
-            user_blocked_patterns: vec![],
+            user_patterns: vec![],
             privacy_enabled: false,
         };
 
-        let result = capture.take_screenshot();
+        let result = capture.take_screenshots(CaptureMode::All, None);
         assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("No screens found"));
+        assert!(result.err().unwrap().to_string().contains("No screens found"));
+    }
+
+    /// Laptop panel on the left, primary 2560x1440 monitor on the right
+    fn dual_screens() -> Vec<ScreenInfo> {
+        vec![
+            ScreenInfo { bounds: Bounds { x: -1920, y: 0, width: 1920, height: 1080 }, primary: false },
+            ScreenInfo { bounds: Bounds { x: 0, y: 0, width: 2560, height: 1440 }, primary: true },
+        ]
+    }
+
+    #[test]
+    fn test_select_screens_by_mode() {
+        let screens = dual_screens();
+        let on_laptop = Bounds { x: -1500, y: 100, width: 800, height: 600 };
+
+        assert_eq!(select_screens(CaptureMode::Primary, &screens, Some(on_laptop)), vec![1]);
+        assert_eq!(select_screens(CaptureMode::All, &screens, None), vec![0, 1]);
+        assert_eq!(select_screens(CaptureMode::ActiveWindowScreen, &screens, Some(on_laptop)), vec![0]);
+        // Mostly on the right monitor: its center decides
+        let straddling = Bounds { x: -300, y: 0, width: 1200, height: 800 };
+        assert_eq!(select_screens(CaptureMode::ActiveWindowScreen, &screens, Some(straddling)), vec![1]);
+        // Unknown position falls back to the primary screen
+        assert_eq!(select_screens(CaptureMode::ActiveWindowScreen, &screens, None), vec![1]);
+        assert!(select_screens(CaptureMode::All, &[], None).is_empty());
+
+        assert_eq!(CaptureMode::from_name("Active window screen"), Some(CaptureMode::ActiveWindowScreen));
+        assert_eq!(CaptureMode::from_name(CaptureMode::All.name()), Some(CaptureMode::All));
+        assert_eq!(CaptureMode::from_name("second"), None);
+    }
+
+    #[test]
+    fn test_blocked_window_only_blocks_its_screens() {
+        let screens = dual_screens();
+        let on_laptop = Bounds { x: -1500, y: 100, width: 800, height: 600 };
+        assert!(window_on_screen(&screens[0].bounds, Some(on_laptop)));
+        assert!(!window_on_screen(&screens[1].bounds, Some(on_laptop)));
+
+        // Spanning the seam blocks both; touching the edge blocks neither
+        let straddling = Bounds { x: -300, y: 0, width: 1200, height: 800 };
+        assert!(screens.iter().all(|s| window_on_screen(&s.bounds, Some(straddling))));
+        let flush = Bounds { x: 0, y: 0, width: 500, height: 500 };
+        assert!(!window_on_screen(&screens[0].bounds, Some(flush)));
+
+        // Nowhere to tell: block everything
+        assert!(screens.iter().all(|s| window_on_screen(&s.bounds, None)));
+    }
+
+    /// Every pixel a different color, so anything copied or left behind shows
+    fn gradient(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(image::ImageBuffer::from_fn(width, height, |x, y| Rgba([x as u8, y as u8, (x + y) as u8, 255])))
+    }
+
+    /// Pixels outside `left..right` x `top..bottom` are as they were
+    fn unchanged_outside(before: &DynamicImage, after: &DynamicImage, (left, top, right, bottom): (u32, u32, u32, u32)) -> bool {
+        before
+            .pixels()
+            .filter(|(x, y, _)| !(left..right).contains(x) || !(top..bottom).contains(y))
+            .all(|(x, y, p)| after.get_pixel(x, y) == p)
+    }
+
+    #[test]
+    fn test_mask_window_blacks_out_only_its_rectangle() {
+        // A 100x50 screen at x=-100 captured at twice its size (HiDPI)
+        let screen = Bounds { x: -100, y: 0, width: 100, height: 50 };
+        let window = Bounds { x: -80, y: 10, width: 30, height: 20 };
+        let before = gradient(200, 100);
+        let mut after = before.clone();
+
+        assert!(mask_window(&mut after, &screen, &window, MaskStyle::BlackOut));
+        let region = (40, 20, 100, 60);
+        for y in region.1..region.3 {
+            for x in region.0..region.2 {
+                assert_eq!(after.get_pixel(x, y), Rgba([0, 0, 0, 255]), "({}, {}) survived", x, y);
+            }
+        }
+        assert!(unchanged_outside(&before, &after, region));
+
+        // Hanging off the screen's right edge: only the visible part
+        let mut after = before.clone();
+        let overhanging = Bounds { x: -10, y: 0, width: 500, height: 10 };
+        assert!(mask_window(&mut after, &screen, &overhanging, MaskStyle::BlackOut));
+        assert!(unchanged_outside(&before, &after, (180, 0, 200, 20)));
+        assert_eq!(after.get_pixel(199, 19), Rgba([0, 0, 0, 255]));
+
+        // On another screen: untouched
+        let mut after = before.clone();
+        assert!(!mask_window(&mut after, &screen, &Bounds { x: 0, y: 0, width: 50, height: 50 }, MaskStyle::BlackOut));
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn test_mask_window_pixelates_only_its_rectangle() {
+        let screen = Bounds { x: 0, y: 0, width: 160, height: 96 };
+        let window = Bounds { x: 32, y: 16, width: 64, height: 48 };
+        let before = gradient(160, 96);
+        let mut after = before.clone();
+
+        assert!(mask_window(&mut after, &screen, &window, MaskStyle::Pixelate));
+        assert!(unchanged_outside(&before, &after, (32, 16, 96, 64)));
+
+        // 3072 distinct pixels reduced to one color per block
+        let colors: std::collections::HashSet<Rgba<u8>> =
+            after.pixels().filter(|(x, y, _)| (32..96).contains(x) && (16..64).contains(y)).map(|(_, _, p)| p).collect();
+        assert!(colors.len() <= (64 / PIXELATE_BLOCK * 48 / PIXELATE_BLOCK) as usize, "{} colors left", colors.len());
+        let kept = (32..96).flat_map(|x| (16..64).map(move |y| (x, y))).filter(|&(x, y)| after.get_pixel(x, y) == before.get_pixel(x, y)).count();
+        assert!(kept < 64, "{} original pixels kept", kept);
+    }
+
+    #[test]
+    fn test_user_pattern_can_mask_instead_of_block() {
+        let patterns = vec![
+            PrivacyPattern::new("Slack", PrivacyAction::Mask(MaskStyle::Pixelate)),
+            PrivacyPattern::new("bank", PrivacyAction::Mask(MaskStyle::BlackOut)),
+        ];
+        let slack = privacy_match("general", "Slack", &patterns).unwrap();
+        assert_eq!(slack.action, PrivacyAction::Mask(MaskStyle::Pixelate));
+        assert_eq!(slack.reason, "matches user pattern 'slack'");
+
+        // Overrides the built-in block
+        assert_eq!(privacy_match("Bank of America", "firefox", &patterns).unwrap().action, PrivacyAction::Mask(MaskStyle::BlackOut));
+        // Built-ins still block by default
+        assert_eq!(privacy_match("Bitwarden", "bitwarden", &patterns).unwrap().action, PrivacyAction::Block);
+        assert_eq!(privacy_match("Bank of America", "firefox", &[]).unwrap().action, PrivacyAction::default());
     }
 }
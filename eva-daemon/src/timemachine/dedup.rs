@@ -0,0 +1,131 @@
+//! Duplicate capture detection - skips screenshots that look like the last one
+//!
+//! Each capture gets a 256-bit difference hash (dHash): the screen is shrunk
+//! to a 17x16 grayscale thumbnail and every bit records whether a cell is
+//! brighter than its right-hand neighbour. Shrinking first means small
+//! changes (a ticking clock, a blinking cursor) barely move the hash, while
+//! a new window or scrolled page flips many bits.
+
+use image::{imageops::FilterType, DynamicImage};
+use std::collections::VecDeque;
+
+/// Hash grid: HASH_SIZE rows of HASH_SIZE comparisons
+const HASH_SIZE: u32 = 16;
+
+/// Brightness difference (0-255) below which neighbours count as equal, so
+/// flat areas don't flip bits on noise
+const EDGE_MARGIN: i16 = 2;
+
+/// Perceptual hash of a screenshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageHash([u64; 4]);
+
+impl ImageHash {
+    pub fn of(image: &DynamicImage) -> Self {
+        let small = image.resize_exact(HASH_SIZE + 1, HASH_SIZE, FilterType::Triangle).to_luma8();
+        let mut bits = [0u64; 4];
+        for y in 0..HASH_SIZE {
+            for x in 0..HASH_SIZE {
+                let left = small.get_pixel(x, y)[0] as i16;
+                let right = small.get_pixel(x + 1, y)[0] as i16;
+                if left - right > EDGE_MARGIN {
+                    let bit = (y * HASH_SIZE + x) as usize;
+                    bits[bit / 64] |= 1 << (bit % 64);
+                }
+            }
+        }
+        Self(bits)
+    }
+
+    /// 1.0 for identical hashes, down to 0.0 when every bit differs
+    pub fn similarity(&self, other: &ImageHash) -> f32 {
+        let differing: u32 = self.0.iter().zip(&other.0).map(|(a, b)| (a ^ b).count_ones()).sum();
+        1.0 - differing as f32 / (HASH_SIZE * HASH_SIZE) as f32
+    }
+}
+
+/// Remembers the hashes of the last few stored captures
+pub struct DuplicateFilter {
+    recent: VecDeque<ImageHash>,
+    history: usize,
+    threshold: f32,
+}
+
+impl DuplicateFilter {
+    /// `threshold` is the similarity at which a capture counts as a
+    /// duplicate (above 1.0 disables the filter); `history` is how many
+    /// stored captures to compare against.
+    pub fn new(threshold: f32, history: usize) -> Self {
+        Self { recent: VecDeque::new(), history: history.max(1), threshold }
+    }
+
+    /// Whether `hash` matches one of the recently stored captures
+    pub fn is_duplicate(&self, hash: &ImageHash) -> bool {
+        self.recent.iter().any(|h| h.similarity(hash) >= self.threshold)
+    }
+
+    /// Note a capture that was stored
+    pub fn record(&mut self, hash: ImageHash) {
+        if self.recent.len() == self.history {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(hash);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    /// Desktop-like screen: flat background with a "window" of text stripes
+    fn desktop(window_x: u32) -> RgbaImage {
+        let mut img = RgbaImage::from_pixel(640, 360, Rgba([40, 60, 90, 255]));
+        for y in 60..300 {
+            for x in window_x..window_x + 300 {
+                let ink = y % 12 < 4 && x % 7 < 5;
+                img.put_pixel(x, y, if ink { Rgba([20, 20, 20, 255]) } else { Rgba([240, 240, 240, 255]) });
+            }
+        }
+        img
+    }
+
+    #[test]
+    fn test_clock_change_stays_duplicate() {
+        let before = desktop(40);
+        let mut after = before.clone();
+        // Clock digits in the bottom-right corner change
+        for y in 340..355 {
+            for x in 580..630 {
+                if (x + y) % 3 == 0 {
+                    after.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+                }
+            }
+        }
+
+        let a = ImageHash::of(&DynamicImage::ImageRgba8(before));
+        let b = ImageHash::of(&DynamicImage::ImageRgba8(after));
+        assert!(a.similarity(&b) >= 0.95, "similarity {}", a.similarity(&b));
+
+        let moved = ImageHash::of(&DynamicImage::ImageRgba8(desktop(300)));
+        assert!(a.similarity(&moved) < 0.9, "similarity {}", a.similarity(&moved));
+    }
+
+    #[test]
+    fn test_filter_compares_recent_history() {
+        let hash = |x| ImageHash::of(&DynamicImage::ImageRgba8(desktop(x)));
+        let mut filter = DuplicateFilter::new(0.95, 2);
+        assert!(!filter.is_duplicate(&hash(40)));
+
+        filter.record(hash(40));
+        filter.record(hash(300));
+        // Switching back to the earlier screen is still within history
+        assert!(filter.is_duplicate(&hash(40)));
+        filter.record(hash(170));
+        assert!(!filter.is_duplicate(&hash(40)));
+
+        let mut off = DuplicateFilter::new(1.1, 1);
+        off.record(hash(40));
+        assert!(!off.is_duplicate(&hash(40)));
+    }
+}
@@ -5,9 +5,13 @@ use unicode_segmentation::UnicodeSegmentation;
 #[cfg(feature = "timemachine")]
 use ort::{Session, Value};
 
+use super::npu_delegate::NpuModel;
+
 /// Embedding dimension (matches MiniLM-L6-v2)
 const EMBEDDING_DIM: usize = 384;
 
+const MODEL_PATH: &str = "models/embeddings.onnx";
+
 /// Embedding Engine for semantic text representation
 ///
 /// Uses ONNX model (MiniLM) when available, falls back to
@@ -15,6 +19,8 @@ const EMBEDDING_DIM: usize = 384;
 pub struct EmbeddingEngine {
     #[cfg(feature = "timemachine")]
     session: Option<Session>,
+    /// The model on the NPU driver, tried before `session`
+    npu_model: Option<NpuModel>,
     /// Vocabulary for simple tokenization
     stop_words: Vec<&'static str>,
 }
@@ -22,7 +28,7 @@ pub struct EmbeddingEngine {
 impl EmbeddingEngine {
     #[cfg(feature = "timemachine")]
     pub async fn new(npu: &super::npu_delegate::NPUDelegate) -> Result<Self, Box<dyn Error>> {
-        let session = match npu.create_session("models/embeddings.onnx") {
+        let session = match npu.create_session(MODEL_PATH) {
             Ok(s) => {
                 println!("[Embeddings] ONNX model loaded successfully");
                 Some(s)
@@ -35,14 +41,16 @@ impl EmbeddingEngine {
 
         Ok(Self {
             session,
+            npu_model: npu.npu_model(MODEL_PATH),
             stop_words: Self::default_stop_words(),
         })
     }
 
     #[cfg(not(feature = "timemachine"))]
-    pub async fn new(_npu: &super::npu_delegate::NPUDelegate) -> Result<Self, Box<dyn Error>> {
+    pub async fn new(npu: &super::npu_delegate::NPUDelegate) -> Result<Self, Box<dyn Error>> {
         println!("[Embeddings] Running without ONNX (timemachine feature disabled)");
         Ok(Self {
+            npu_model: npu.npu_model(MODEL_PATH),
             stop_words: Self::default_stop_words(),
         })
     }
@@ -63,6 +71,13 @@ impl EmbeddingEngine {
 
     /// Encode text into embedding vector
     pub fn encode(&self, text: &str) -> Result<Vec<f32>, Box<dyn Error>> {
+        if let Some(ref model) = self.npu_model {
+            let input: Vec<u8> = self.token_ids(text).iter().flat_map(|id| id.to_le_bytes()).collect();
+            if let Some(output) = model.run(&input, EMBEDDING_DIM * 4) {
+                return Ok(super::npu_delegate::f32_from_le(&output));
+            }
+        }
+
         #[cfg(feature = "timemachine")]
         if let Some(ref session) = self.session {
             return self.encode_with_onnx(session, text);
@@ -81,13 +96,21 @@ impl EmbeddingEngine {
 
     #[cfg(feature = "timemachine")]
     fn tokenize_for_onnx(&self, text: &str) -> Result<Value, Box<dyn Error>> {
+        let tokens = self.token_ids(text);
+        let len = tokens.len().max(1);
+        let shape = vec![1, len];
+        let tensor = Value::from_array((shape, tokens))?;
+        Ok(tensor)
+    }
+
+    /// Token IDs for the model
+    fn token_ids(&self, text: &str) -> Vec<i64> {
         // Simple tokenization for ONNX model
         // In production, use HuggingFace tokenizers
-        let tokens: Vec<i64> = text
+        text
             .unicode_words()
             .take(512)
-            .enumerate()
-            .map(|(i, word)| {
+            .map(|word| {
                 // Create deterministic token ID from word
                 let mut hasher = Sha256::new();
                 hasher.update(word.to_lowercase().as_bytes());
@@ -96,12 +119,7 @@ impl EmbeddingEngine {
                 let id = u64::from_le_bytes(hash[0..8].try_into().unwrap()) % 30000;
                 id as i64
             })
-            .collect();
-
-        let len = tokens.len().max(1);
-        let shape = vec![1, len];
-        let tensor = Value::from_array((shape, tokens))?;
-        Ok(tensor)
+            .collect()
     }
 
     #[cfg(feature = "timemachine")]
@@ -227,6 +245,7 @@ mod tests {
         let engine = EmbeddingEngine {
             #[cfg(feature = "timemachine")]
             session: None,
+            npu_model: None,
             stop_words: EmbeddingEngine::default_stop_words(),
         };
 
@@ -243,6 +262,7 @@ mod tests {
         let engine = EmbeddingEngine {
             #[cfg(feature = "timemachine")]
             session: None,
+            npu_model: None,
             stop_words: EmbeddingEngine::default_stop_words(),
         };
 
@@ -262,6 +282,7 @@ mod tests {
         let engine = EmbeddingEngine {
             #[cfg(feature = "timemachine")]
             session: None,
+            npu_model: None,
             stop_words: EmbeddingEngine::default_stop_words(),
         };
 
@@ -274,6 +295,7 @@ mod tests {
         let engine = EmbeddingEngine {
             #[cfg(feature = "timemachine")]
             session: None,
+            npu_model: None,
             stop_words: EmbeddingEngine::default_stop_words(),
         };
 
@@ -289,6 +311,7 @@ mod tests {
         let engine = EmbeddingEngine {
             #[cfg(feature = "timemachine")]
             session: None,
+            npu_model: None,
             stop_words: EmbeddingEngine::default_stop_words(),
         };
 
@@ -306,6 +329,7 @@ mod tests {
         let engine = EmbeddingEngine {
             #[cfg(feature = "timemachine")]
             session: None,
+            npu_model: None,
             stop_words: EmbeddingEngine::default_stop_words(),
         };
 
@@ -322,6 +346,7 @@ mod tests {
         let engine = EmbeddingEngine {
             #[cfg(feature = "timemachine")]
             session: None,
+            npu_model: None,
             stop_words: EmbeddingEngine::default_stop_words(),
         };
 
@@ -338,6 +363,7 @@ mod tests {
         let engine = EmbeddingEngine {
             #[cfg(feature = "timemachine")]
             session: None,
+            npu_model: None,
             stop_words: EmbeddingEngine::default_stop_words(),
         };
 
@@ -372,6 +398,7 @@ mod tests {
         let engine = EmbeddingEngine {
             #[cfg(feature = "timemachine")]
             session: None,
+            npu_model: None,
             stop_words: EmbeddingEngine::default_stop_words(),
         };
 
@@ -386,6 +413,7 @@ mod tests {
         let engine = EmbeddingEngine {
             #[cfg(feature = "timemachine")]
             session: None,
+            npu_model: None,
             stop_words: EmbeddingEngine::default_stop_words(),
         };
 
@@ -404,6 +432,7 @@ mod tests {
         let engine = EmbeddingEngine {
             #[cfg(feature = "timemachine")]
             session: None,
+            npu_model: None,
             stop_words: EmbeddingEngine::default_stop_words(),
         };
 
@@ -0,0 +1,438 @@
+//! Approximate nearest-neighbour graph (HNSW) for the semantic index
+//!
+//! Every capture is a node linked to its most similar neighbours on a few
+//! layers, sparser towards the top. A search walks greedily down from the
+//! top layer, then runs a best-first search keeping `ef` candidates on the
+//! bottom one. The graph stores no vectors: similarities come from the index
+//! through callbacks, so it works the same over f32 and int8 storage.
+//!
+//! Removing a node only unlinks it. After many removals the graph has holes
+//! and should be rebuilt.
+
+use super::index::ByteReader;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::error::Error;
+
+/// Links per node above the bottom layer (twice as many on it)
+pub const DEFAULT_M: usize = 16;
+/// Candidates considered when linking a new node
+pub const DEFAULT_EF_CONSTRUCTION: usize = 64;
+
+/// Marks "no entry point" in a persisted graph
+const NO_ENTRY: u32 = u32::MAX;
+
+/// A slot with its similarity to the current query, ordered by similarity
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scored {
+    sim: f32,
+    slot: u32,
+}
+
+impl Eq for Scored {}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sim.total_cmp(&other.sim).then(self.slot.cmp(&other.slot))
+    }
+}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub struct Graph {
+    m: usize,
+    ef_construction: usize,
+    /// Capture ID in each slot, `None` once removed
+    ids: Vec<Option<u64>>,
+    slots: HashMap<u64, u32>,
+    /// Neighbour slots of each slot, per layer it reaches
+    links: Vec<Vec<Vec<u32>>>,
+    /// Node on the highest layer, where searches start
+    entry: Option<u32>,
+    /// Nodes removed since the graph was built
+    removed: usize,
+    rng: u64,
+}
+
+impl Default for Graph {
+    fn default() -> Self {
+        Self::new(DEFAULT_M, DEFAULT_EF_CONSTRUCTION)
+    }
+}
+
+impl Graph {
+    pub fn new(m: usize, ef_construction: usize) -> Self {
+        Self {
+            m: m.max(2),
+            ef_construction: ef_construction.max(1),
+            ids: Vec::new(),
+            slots: HashMap::new(),
+            links: Vec::new(),
+            entry: None,
+            removed: 0,
+            rng: 0x2545_F491_4F6C_DD1D,
+        }
+    }
+
+    /// Nodes in the graph
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Nodes removed since the graph was built, each leaving a hole
+    pub fn removed(&self) -> usize {
+        self.removed
+    }
+
+    /// Link `id` into the graph. `sim` scores a node against the new one;
+    /// `between` scores two nodes already in the graph.
+    pub fn insert(&mut self, id: u64, sim: impl Fn(u64) -> f32, between: impl Fn(u64, u64) -> f32) {
+        self.remove(id);
+        let level = self.random_level();
+        let slot = self.ids.len() as u32;
+
+        let Some(entry) = self.entry else {
+            self.push(id, level);
+            self.entry = Some(slot);
+            return;
+        };
+        let top = self.links[entry as usize].len() - 1;
+
+        // Find the neighbours on each layer before touching the graph
+        let mut nearest = vec![self.scored(entry, &sim)];
+        for layer in (level + 1..=top).rev() {
+            nearest = self.search_layer(&nearest, 1, layer, &sim);
+        }
+        let mut chosen = Vec::new();
+        for layer in (0..=level.min(top)).rev() {
+            nearest = self.search_layer(&nearest, self.ef_construction, layer, &sim);
+            chosen.push((layer, self.select(&nearest, self.m, &between)));
+        }
+
+        self.push(id, level);
+        for (layer, neighbours) in chosen {
+            for &neighbour in &neighbours {
+                self.link(neighbour, slot, layer, &between);
+            }
+            self.links[slot as usize][layer] = neighbours;
+        }
+        if level > top {
+            self.entry = Some(slot);
+        }
+    }
+
+    /// Unlink `id`; returns whether it was in the graph
+    pub fn remove(&mut self, id: u64) -> bool {
+        let Some(slot) = self.slots.remove(&id) else { return false };
+        self.ids[slot as usize] = None;
+        let layers = std::mem::take(&mut self.links[slot as usize]);
+        for (layer, neighbours) in layers.iter().enumerate() {
+            for &neighbour in neighbours {
+                if let Some(list) = self.links[neighbour as usize].get_mut(layer) {
+                    list.retain(|s| *s != slot);
+                }
+            }
+        }
+        self.removed += 1;
+
+        if self.entry == Some(slot) {
+            // Highest remaining node, preferring one that was linked to it
+            self.entry = layers
+                .iter()
+                .rev()
+                .flatten()
+                .copied()
+                .find(|s| self.links[*s as usize].len() == layers.len())
+                .or_else(|| (0..self.links.len() as u32).filter(|s| self.ids[*s as usize].is_some()).max_by_key(|s| self.links[*s as usize].len()));
+        }
+        true
+    }
+
+    /// Up to `ef` nodes most similar to the query `sim` scores, best first
+    pub fn search(&self, ef: usize, sim: impl Fn(u64) -> f32) -> Vec<(u64, f32)> {
+        let Some(entry) = self.entry else { return Vec::new() };
+        let mut nearest = vec![self.scored(entry, &sim)];
+        for layer in (1..self.links[entry as usize].len()).rev() {
+            nearest = self.search_layer(&nearest, 1, layer, &sim);
+        }
+        self.search_layer(&nearest, ef.max(1), 0, &sim)
+            .into_iter()
+            .filter_map(|s| Some((self.ids[s.slot as usize]?, s.sim)))
+            .collect()
+    }
+
+    /// Best-first search of one layer from `start`, keeping the `ef` best
+    /// nodes seen (returned best first)
+    fn search_layer(&self, start: &[Scored], ef: usize, layer: usize, sim: &impl Fn(u64) -> f32) -> Vec<Scored> {
+        let mut visited: HashSet<u32> = start.iter().map(|s| s.slot).collect();
+        let mut candidates: BinaryHeap<Scored> = start.iter().copied().collect();
+        let mut best: BinaryHeap<Reverse<Scored>> = start.iter().copied().map(Reverse).collect();
+        while best.len() > ef {
+            best.pop();
+        }
+
+        while let Some(current) = candidates.pop() {
+            let worst = best.peek().map_or(f32::NEG_INFINITY, |w| w.0.sim);
+            if current.sim < worst && best.len() >= ef {
+                break;
+            }
+            let Some(neighbours) = self.links[current.slot as usize].get(layer) else { continue };
+            for &neighbour in neighbours {
+                if !visited.insert(neighbour) || self.ids[neighbour as usize].is_none() {
+                    continue;
+                }
+                let scored = self.scored(neighbour, sim);
+                let worst = best.peek().map_or(f32::NEG_INFINITY, |w| w.0.sim);
+                if best.len() < ef || scored.sim > worst {
+                    candidates.push(scored);
+                    best.push(Reverse(scored));
+                    if best.len() > ef {
+                        best.pop();
+                    }
+                }
+            }
+        }
+
+        let mut found: Vec<Scored> = best.into_iter().map(|r| r.0).collect();
+        found.sort_unstable_by(|a, b| b.cmp(a));
+        found
+    }
+
+    /// Up to `max` of `candidates` (best first) to link to, preferring ones
+    /// that are closer to the node than to the neighbours already chosen,
+    /// so links point in different directions
+    fn select(&self, candidates: &[Scored], max: usize, between: &impl Fn(u64, u64) -> f32) -> Vec<u32> {
+        let mut chosen: Vec<Scored> = Vec::with_capacity(max);
+        let mut skipped = Vec::new();
+        for &candidate in candidates {
+            if chosen.len() == max {
+                break;
+            }
+            let id = self.id(candidate.slot);
+            if chosen.iter().all(|c| between(id, self.id(c.slot)) < candidate.sim) {
+                chosen.push(candidate);
+            } else {
+                skipped.push(candidate);
+            }
+        }
+        // Fill up with the closest skipped ones rather than leave links unused
+        let room = max - chosen.len();
+        chosen.extend(skipped.into_iter().take(room));
+        chosen.into_iter().map(|c| c.slot).collect()
+    }
+
+    /// Add `to` to the links of `from`, dropping its least similar
+    /// neighbour when full
+    fn link(&mut self, from: u32, to: u32, layer: usize, between: &impl Fn(u64, u64) -> f32) {
+        let max = self.max_links(layer);
+        let from_id = self.id(from);
+        let Some(list) = self.links[from as usize].get(layer) else { return };
+        if list.len() < max {
+            self.links[from as usize][layer].push(to);
+            return;
+        }
+
+        let mut scored: Vec<Scored> = list
+            .iter()
+            .chain(std::iter::once(&to))
+            .filter_map(|&slot| Some(Scored { sim: between(from_id, self.ids[slot as usize]?), slot }))
+            .collect();
+        scored.sort_unstable_by(|a, b| b.cmp(a));
+        scored.truncate(max);
+        self.links[from as usize][layer] = scored.into_iter().map(|s| s.slot).collect();
+    }
+
+    fn push(&mut self, id: u64, level: usize) {
+        let slot = self.ids.len() as u32;
+        self.ids.push(Some(id));
+        self.slots.insert(id, slot);
+        self.links.push(vec![Vec::new(); level + 1]);
+    }
+
+    fn scored(&self, slot: u32, sim: &impl Fn(u64) -> f32) -> Scored {
+        Scored { sim: sim(self.id(slot)), slot }
+    }
+
+    fn id(&self, slot: u32) -> u64 {
+        self.ids[slot as usize].unwrap_or(u64::MAX)
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 { self.m * 2 } else { self.m }
+    }
+
+    /// Layers a new node reaches: each one up with probability 1/m
+    fn random_level(&mut self) -> usize {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        let uniform = ((self.rng >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        (-uniform.ln() / (self.m as f64).ln()) as usize
+    }
+
+    /// Serialize as `m | ef_construction | entry | node count`, then per
+    /// node its ID and per layer the neighbours (positions in node order),
+    /// all little endian. Removed slots are left out.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let live: Vec<u32> = (0..self.ids.len() as u32).filter(|s| self.ids[*s as usize].is_some()).collect();
+        let position: HashMap<u32, u32> = live.iter().enumerate().map(|(i, s)| (*s, i as u32)).collect();
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&(self.m as u32).to_le_bytes());
+        out.extend_from_slice(&(self.ef_construction as u32).to_le_bytes());
+        let entry = self.entry.and_then(|e| position.get(&e).copied()).unwrap_or(NO_ENTRY);
+        out.extend_from_slice(&entry.to_le_bytes());
+        out.extend_from_slice(&(live.len() as u32).to_le_bytes());
+        for slot in live {
+            out.extend_from_slice(&self.id(slot).to_le_bytes());
+            let layers = &self.links[slot as usize];
+            out.push(layers.len() as u8);
+            for neighbours in layers {
+                let kept: Vec<u32> = neighbours.iter().filter_map(|n| position.get(n).copied()).collect();
+                out.extend_from_slice(&(kept.len() as u32).to_le_bytes());
+                kept.iter().for_each(|n| out.extend_from_slice(&n.to_le_bytes()));
+            }
+        }
+        out
+    }
+
+    /// Read a graph written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut reader = ByteReader::new(bytes);
+        let mut graph = Self::new(reader.u32()? as usize, reader.u32()? as usize);
+        let entry = reader.u32()?;
+        let count = reader.u32()?;
+        for slot in 0..count {
+            let id = reader.u64()?;
+            let layers = reader.take(1)?[0] as usize;
+            if layers == 0 {
+                return Err(format!("graph node {} has no layers", id).into());
+            }
+            graph.push(id, layers - 1);
+            for layer in 0..layers {
+                let neighbours = (0..reader.u32()?).map(|_| reader.u32()).collect::<Result<Vec<_>, _>>()?;
+                if neighbours.iter().any(|n| *n >= count) {
+                    return Err(format!("graph node {} links outside the graph", id).into());
+                }
+                graph.links[slot as usize][layer] = neighbours;
+            }
+        }
+        if !reader.is_done() {
+            return Err("trailing bytes in graph".into());
+        }
+        graph.entry = match entry {
+            NO_ENTRY if count == 0 => None,
+            e if e < count => Some(e),
+            _ => return Err("graph entry point out of range".into()),
+        };
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIM: usize = 16;
+
+    /// Deterministic pseudo-random unit vectors (xorshift)
+    fn points(n: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut state = seed;
+        (0..n)
+            .map(|_| {
+                let mut v: Vec<f32> = (0..DIM)
+                    .map(|_| {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+                    })
+                    .collect();
+                let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+                v.iter_mut().for_each(|x| *x /= norm);
+                v
+            })
+            .collect()
+    }
+
+    fn dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    fn build(vectors: &[Vec<f32>]) -> Graph {
+        let mut graph = Graph::new(8, 48);
+        for (id, v) in vectors.iter().enumerate() {
+            graph.insert(id as u64, |other| dot(v, &vectors[other as usize]), |a, b| dot(&vectors[a as usize], &vectors[b as usize]));
+        }
+        graph
+    }
+
+    fn recall(graph: &Graph, vectors: &[Vec<f32>], queries: &[Vec<f32>], skip: impl Fn(u64) -> bool) -> f32 {
+        let mut hits = 0;
+        for query in queries {
+            let mut exact: Vec<(u64, f32)> = (0..vectors.len() as u64).filter(|id| !skip(*id)).map(|id| (id, dot(query, &vectors[id as usize]))).collect();
+            exact.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let found: Vec<u64> = graph.search(32, |id| dot(query, &vectors[id as usize])).iter().take(10).map(|r| r.0).collect();
+            hits += exact.iter().take(10).filter(|(id, _)| found.contains(id)).count();
+        }
+        hits as f32 / (queries.len() * 10) as f32
+    }
+
+    #[test]
+    fn test_search_finds_nearest() {
+        let vectors = points(2000, 0x9E37_79B9_7F4A_7C15);
+        let graph = build(&vectors);
+        assert_eq!(graph.len(), 2000);
+
+        let hit = graph.search(16, |id| dot(&vectors[1234], &vectors[id as usize]));
+        assert_eq!(hit[0].0, 1234);
+        assert!(hit.windows(2).all(|w| w[0].1 >= w[1].1));
+
+        let r = recall(&graph, &vectors, &points(50, 42), |_| false);
+        assert!(r > 0.9, "recall@10 = {}", r);
+    }
+
+    #[test]
+    fn test_removed_nodes_are_never_returned() {
+        let vectors = points(1000, 7);
+        let mut graph = build(&vectors);
+        let entry = graph.entry.map(|s| graph.id(s)).unwrap();
+        for id in (0..1000).step_by(3).chain([entry]) {
+            graph.remove(id);
+        }
+        assert!(!graph.remove(0));
+        assert_eq!(graph.len(), 1000 - 334 - (entry % 3 != 0) as usize);
+        assert!(graph.removed() >= 334);
+
+        let gone = |id: u64| id % 3 == 0 || id == entry;
+        let found = graph.search(32, |id| dot(&vectors[3], &vectors[id as usize]));
+        assert!(!found.is_empty() && found.iter().all(|(id, _)| !gone(*id)));
+        let r = recall(&graph, &vectors, &points(30, 99), gone);
+        assert!(r > 0.8, "recall@10 after removals = {}", r);
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let vectors = points(300, 11);
+        let mut graph = build(&vectors);
+        graph.remove(5);
+
+        let loaded = Graph::from_bytes(&graph.to_bytes()).unwrap();
+        assert_eq!(loaded.len(), 299);
+        let query = |id: u64| dot(&vectors[77], &vectors[id as usize]);
+        assert_eq!(loaded.search(20, query), graph.search(20, query));
+
+        let bytes = graph.to_bytes();
+        assert!(Graph::from_bytes(&bytes[..bytes.len() - 2]).is_err());
+        assert!(Graph::from_bytes(&[]).is_err());
+        assert!(Graph::from_bytes(&Graph::default().to_bytes()).unwrap().is_empty());
+    }
+}
@@ -1,47 +1,818 @@
-use std::collections::HashMap;
+use super::hnsw::Graph;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Candidates scored with int8 codes before exact re-ranking (x limit)
+const RERANK_FACTOR: usize = 4;
+
+/// Bytes of per-vector header in a quantized vector (scale, offset, norm)
+const QUANT_HEADER_BYTES: usize = 12;
+
+/// First bytes of a persisted index file (format version 2: with the
+/// search graph)
+const INDEX_MAGIC: &[u8; 8] = b"EVAIDX02";
+
+/// Version 1 files have no graph; it is built when they are loaded
+const INDEX_MAGIC_V1: &[u8; 8] = b"EVAIDX01";
+
+/// Below this many vectors a linear scan is fast enough and no graph is kept
+const GRAPH_MIN_LEN: usize = 5000;
+
+/// Default candidates kept while searching the graph
+pub const DEFAULT_EF: usize = 64;
+
+/// Characters of capture text kept next to each vector
+const SNIPPET_CHARS: usize = 200;
+
+/// Int8 scalar-quantized embedding
+///
+/// Each component is stored as `code` with `value ≈ scale * code + offset`,
+/// where scale/offset are computed per vector from its min/max.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizedVector {
+    pub scale: f32,
+    pub offset: f32,
+    /// Norm of the dequantized vector (cached for cosine scoring)
+    pub norm: f32,
+    pub codes: Vec<i8>,
+}
+
+impl QuantizedVector {
+    /// Quantize an f32 vector to int8
+    pub fn quantize(vector: &[f32]) -> Self {
+        let min = vector.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = vector.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+
+        let (scale, offset, codes) = if vector.is_empty() || max - min < f32::EPSILON {
+            // Constant vector: every component equals the offset
+            let offset = if vector.is_empty() { 0.0 } else { min };
+            (0.0, offset, vec![0i8; vector.len()])
+        } else {
+            let scale = (max - min) / 255.0;
+            let offset = min + 128.0 * scale;
+            let codes = vector
+                .iter()
+                .map(|v| ((v - offset) / scale).round().clamp(-128.0, 127.0) as i8)
+                .collect();
+            (scale, offset, codes)
+        };
+
+        let mut quantized = Self { scale, offset, norm: 0.0, codes };
+        quantized.norm = quantized.dequantize().iter().map(|x| x * x).sum::<f32>().sqrt();
+        quantized
+    }
+
+    /// Reconstruct an approximate f32 vector
+    pub fn dequantize(&self) -> Vec<f32> {
+        self.codes.iter().map(|&c| self.scale * c as f32 + self.offset).collect()
+    }
+
+    /// Asymmetric cosine similarity: f32 query against the int8 codes
+    ///
+    /// `query_sum` and `query_norm` are precomputed once per search.
+    fn cosine(&self, query: &[f32], query_sum: f32, query_norm: f32) -> f32 {
+        if query.len() != self.codes.len() || query_norm == 0.0 || self.norm == 0.0 {
+            return 0.0;
+        }
+        let dot_codes: f32 = query.iter().zip(&self.codes).map(|(q, &c)| q * c as f32).sum();
+        let dot = self.scale * dot_codes + self.offset * query_sum;
+        dot / (query_norm * self.norm)
+    }
+
+    /// Cosine similarity between two quantized vectors
+    fn similarity(&self, other: &QuantizedVector) -> f32 {
+        if self.codes.len() != other.codes.len() || self.norm == 0.0 || other.norm == 0.0 {
+            return 0.0;
+        }
+        // (sa·a + oa)·(sb·b + ob) summed, with integer sums of the codes
+        let (mut dot, mut sum_a, mut sum_b) = (0i32, 0i32, 0i32);
+        for (&a, &b) in self.codes.iter().zip(&other.codes) {
+            dot += a as i32 * b as i32;
+            sum_a += a as i32;
+            sum_b += b as i32;
+        }
+        let n = self.codes.len() as f32;
+        let dot = self.scale * other.scale * dot as f32
+            + self.scale * other.offset * sum_a as f32
+            + self.offset * other.scale * sum_b as f32
+            + n * self.offset * other.offset;
+        dot / (self.norm * other.norm)
+    }
+
+    /// In-memory / on-disk footprint in bytes
+    pub fn size_bytes(&self) -> usize {
+        QUANT_HEADER_BYTES + self.codes.len()
+    }
+
+    /// Serialize as `scale | offset | norm | codes` (little endian)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.size_bytes());
+        out.extend_from_slice(&self.scale.to_le_bytes());
+        out.extend_from_slice(&self.offset.to_le_bytes());
+        out.extend_from_slice(&self.norm.to_le_bytes());
+        out.extend(self.codes.iter().map(|&c| c as u8));
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < QUANT_HEADER_BYTES {
+            return None;
+        }
+        let f = |i: usize| f32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        Some(Self {
+            scale: f(0),
+            offset: f(4),
+            norm: f(8),
+            codes: bytes[QUANT_HEADER_BYTES..].iter().map(|&b| b as i8).collect(),
+        })
+    }
+}
 
 pub struct SemanticIndex {
     // Simple mock index for now
     // In production, this would use FAISS bindings or usearch
+    /// Full-precision vectors (all of them, or only the most recent
+    /// `keep_full` when quantized)
     vectors: HashMap<u64, Vec<f32>>,
+    /// Int8 codes for every vector (quantized mode only)
+    quantized: HashMap<u64, QuantizedVector>,
+    /// Insertion order of the vectors still held at full precision
+    recent: VecDeque<u64>,
+    /// `Some(n)`: quantize, keeping f32 for the last n inserts
+    keep_full: Option<usize>,
+    /// Start of each capture's text, for showing hits without a DB lookup
+    snippets: HashMap<u64, String>,
+    /// Nearest-neighbour graph, empty while the index is small
+    graph: Graph,
+    /// Candidates kept while searching the graph (more: better recall,
+    /// slower)
+    ef: usize,
+}
+
+/// A vector to score indexed entries against
+struct Probe<'a> {
+    vector: &'a [f32],
+    sum: f32,
+    norm: f32,
+}
+
+impl<'a> Probe<'a> {
+    fn new(vector: &'a [f32]) -> Self {
+        Self {
+            vector,
+            sum: vector.iter().sum(),
+            norm: vector.iter().map(|x| x * x).sum::<f32>().sqrt(),
+        }
+    }
 }
 
 impl SemanticIndex {
     pub fn new() -> Result<Self, Box<dyn Error>> {
         Ok(Self {
             vectors: HashMap::new(),
+            quantized: HashMap::new(),
+            recent: VecDeque::new(),
+            keep_full: None,
+            snippets: HashMap::new(),
+            graph: Graph::default(),
+            ef: DEFAULT_EF,
         })
     }
-    
-    pub fn add(&mut self, id: u64, vector: Vec<f32>, _text: &str) -> Result<(), Box<dyn Error>> {
+
+    /// Candidates kept while searching the graph
+    pub fn set_ef(&mut self, ef: usize) {
+        self.ef = ef.max(1);
+    }
+
+    /// Index storing int8 codes, with f32 kept for the last `keep_full` inserts
+    pub fn quantized(keep_full: usize) -> Result<Self, Box<dyn Error>> {
+        let mut index = Self::new()?;
+        index.keep_full = Some(keep_full);
+        Ok(index)
+    }
+
+    pub fn is_quantized(&self) -> bool {
+        self.keep_full.is_some()
+    }
+
+    /// Switch an existing index to int8 storage, rewriting every vector.
+    ///
+    /// The highest IDs (most recent captures) keep their f32 originals.
+    pub fn enable_quantization(&mut self, keep_full: usize) {
+        self.keep_full = Some(keep_full);
+
+        let mut ids: Vec<u64> = self.vectors.keys().copied().collect();
+        ids.sort_unstable();
+        for id in &ids {
+            self.quantized.entry(*id).or_insert_with(|| QuantizedVector::quantize(&self.vectors[id]));
+        }
+        self.recent = ids.into();
+        self.trim_full();
+    }
+
+    pub fn add(&mut self, id: u64, vector: Vec<f32>, text: &str) -> Result<(), Box<dyn Error>> {
+        self.snippets.insert(id, text.chars().take(SNIPPET_CHARS).collect());
+        if self.keep_full.is_some() {
+            self.quantized.insert(id, QuantizedVector::quantize(&vector));
+            self.recent.retain(|r| *r != id);
+            self.recent.push_back(id);
+        }
         self.vectors.insert(id, vector);
+        self.trim_full();
+
+        if !self.graph.is_empty() {
+            self.link(id);
+        } else if self.len() >= GRAPH_MIN_LEN {
+            self.rebuild();
+        }
         Ok(())
     }
-    
+
+    /// Build the search graph again from every vector, e.g. after many
+    /// removals left holes in it
+    pub fn rebuild(&mut self) {
+        self.graph = Graph::default();
+        if self.len() < GRAPH_MIN_LEN {
+            return;
+        }
+        let mut ids: Vec<u64> = self.vectors.keys().chain(self.quantized.keys()).copied().collect();
+        ids.sort_unstable();
+        ids.dedup();
+        for id in ids {
+            self.link(id);
+        }
+    }
+
+    /// Whether removals since the graph was built call for a `rebuild`
+    pub fn needs_rebuild(&self) -> bool {
+        self.graph.removed() * 10 > self.graph.len()
+    }
+
+    fn link(&mut self, id: u64) {
+        let mut graph = std::mem::take(&mut self.graph);
+        let probe = self.vectors.get(&id).map(|full| Probe::new(full));
+        let sim = |other| match &probe {
+            Some(probe) => self.score(probe, other),
+            None => self.similarity_between(id, other),
+        };
+        graph.insert(id, sim, |a, b| self.similarity_between(a, b));
+        self.graph = graph;
+    }
+
+    /// Forget a capture; returns whether it was indexed
+    pub fn remove(&mut self, id: u64) -> bool {
+        let had_vector = self.vectors.remove(&id).is_some();
+        let had_codes = self.quantized.remove(&id).is_some();
+        self.snippets.remove(&id);
+        self.recent.retain(|r| *r != id);
+        self.graph.remove(id);
+        had_vector || had_codes
+    }
+
+    /// Drop f32 originals beyond the `keep_full` most recent
+    fn trim_full(&mut self) {
+        let Some(keep) = self.keep_full else { return };
+        while self.recent.len() > keep {
+            if let Some(old) = self.recent.pop_front() {
+                self.vectors.remove(&old);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        if self.is_quantized() { self.quantized.len() } else { self.vectors.len() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Highest capture ID in the index (0 when empty)
+    pub fn max_id(&self) -> u64 {
+        self.snippets.keys().chain(self.vectors.keys()).chain(self.quantized.keys()).copied().max().unwrap_or(0)
+    }
+
+    /// Stored start of a capture's text
+    pub fn snippet(&self, id: u64) -> Option<&str> {
+        self.snippets.get(&id).map(String::as_str)
+    }
+
+    /// Write the index to `path` (atomically, via a temporary file).
+    ///
+    /// Layout, little endian: magic, quantization (`u8` flag + `u64`
+    /// keep_full), entry count, then per entry `id`, the f32 vector, the
+    /// int8 codes and the text snippet, each length-prefixed (`u32`) and
+    /// empty when absent, then the graph (length-prefixed, empty when there
+    /// is none). A SHA-256 of everything before it closes the file.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut ids: Vec<u64> = self.vectors.keys().chain(self.quantized.keys()).copied().collect();
+        ids.sort_unstable();
+        ids.dedup();
+
+        let mut out = Vec::with_capacity(self.size_bytes() + ids.len() * 32);
+        out.extend_from_slice(INDEX_MAGIC);
+        out.push(self.keep_full.is_some() as u8);
+        out.extend_from_slice(&(self.keep_full.unwrap_or(0) as u64).to_le_bytes());
+        out.extend_from_slice(&(ids.len() as u32).to_le_bytes());
+        for id in ids {
+            out.extend_from_slice(&id.to_le_bytes());
+            let full: Vec<u8> = self.vectors.get(&id).map(|v| v.iter().flat_map(|x| x.to_le_bytes()).collect()).unwrap_or_default();
+            let codes = self.quantized.get(&id).map(QuantizedVector::to_bytes).unwrap_or_default();
+            let snippet = self.snippets.get(&id).map(|s| s.as_bytes()).unwrap_or_default();
+            for field in [&full[..], &codes[..], snippet] {
+                out.extend_from_slice(&(field.len() as u32).to_le_bytes());
+                out.extend_from_slice(field);
+            }
+        }
+        let graph = if self.graph.is_empty() { Vec::new() } else { self.graph.to_bytes() };
+        out.extend_from_slice(&(graph.len() as u32).to_le_bytes());
+        out.extend_from_slice(&graph);
+        out.extend_from_slice(&Sha256::digest(&out));
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, &out)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Read an index written by `save`; any damage is an error.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let bytes = fs::read(path)?;
+        let body_len = bytes.len().checked_sub(32).ok_or("index file truncated")?;
+        let (body, checksum) = bytes.split_at(body_len);
+        if Sha256::digest(body).as_slice() != checksum {
+            return Err("index file checksum mismatch".into());
+        }
+
+        let mut reader = ByteReader::new(body);
+        let with_graph = match reader.take(INDEX_MAGIC.len())? {
+            magic if magic == INDEX_MAGIC => true,
+            magic if magic == INDEX_MAGIC_V1 => false,
+            _ => return Err("not an index file".into()),
+        };
+        let quantized = reader.take(1)?[0] != 0;
+        let keep_full = reader.u64()? as usize;
+        let mut index = if quantized { Self::quantized(keep_full)? } else { Self::new()? };
+
+        let mut full_ids = Vec::new();
+        for _ in 0..reader.u32()? {
+            let id = reader.u64()?;
+            let full = reader.field()?;
+            if full.len() % 4 != 0 {
+                return Err(format!("index entry {} has a malformed vector", id).into());
+            }
+            if !full.is_empty() {
+                index.vectors.insert(id, full.chunks_exact(4).map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect());
+                full_ids.push(id);
+            }
+            let codes = reader.field()?;
+            if !codes.is_empty() {
+                let q = QuantizedVector::from_bytes(codes).ok_or_else(|| format!("index entry {} has malformed codes", id))?;
+                index.quantized.insert(id, q);
+            }
+            let snippet = std::str::from_utf8(reader.field()?)?;
+            index.snippets.insert(id, snippet.to_string());
+        }
+        let graph = if with_graph { reader.field()? } else { &[] };
+        if !reader.is_done() {
+            return Err("trailing bytes in index file".into());
+        }
+
+        full_ids.sort_unstable();
+        index.recent = full_ids.into();
+        if !graph.is_empty() {
+            index.graph = Graph::from_bytes(graph)?;
+        } else if index.len() >= GRAPH_MIN_LEN {
+            index.rebuild();
+        }
+        Ok(index)
+    }
+
+    /// Current index size in bytes
+    pub fn size_bytes(&self) -> usize {
+        let full: usize = self.vectors.values().map(|v| v.len() * 4).sum();
+        let quantized: usize = self.quantized.values().map(QuantizedVector::size_bytes).sum();
+        full + quantized
+    }
+
+    /// Size the same index would take with every vector stored as f32
+    pub fn f32_size_bytes(&self) -> usize {
+        if self.is_quantized() {
+            self.quantized.values().map(|q| q.codes.len() * 4).sum()
+        } else {
+            self.size_bytes()
+        }
+    }
+
     pub fn search(&self, query_vec: &[f32], limit: usize) -> Result<Vec<(u64, f32)>, Box<dyn Error>> {
-        let mut scores: Vec<(u64, f32)> = self.vectors.iter()
-            .map(|(id, vec)| {
-                let score = cosine_similarity(query_vec, vec);
-                (*id, score)
+        self.search_filtered(query_vec, limit, |_| true)
+    }
+
+    /// Search only the vectors whose ID passes `keep`.
+    ///
+    /// Large indexes are searched through the graph (approximate); when
+    /// `keep` rules out too many of the graph's candidates the search falls
+    /// back to a scan of everything.
+    pub fn search_filtered(
+        &self,
+        query_vec: &[f32],
+        limit: usize,
+        keep: impl Fn(u64) -> bool,
+    ) -> Result<Vec<(u64, f32)>, Box<dyn Error>> {
+        if self.graph.is_empty() || limit == 0 {
+            return self.search_exact(query_vec, limit, keep);
+        }
+
+        let probe = Probe::new(query_vec);
+        let hits: Vec<(u64, f32)> = self.graph
+            .search(self.ef.max(limit), |id| self.score(&probe, id))
+            .into_iter()
+            .filter(|(id, _)| keep(*id))
+            .take(limit)
+            .collect();
+        if hits.len() < limit.min(self.len()) {
+            return self.search_exact(query_vec, limit, keep);
+        }
+        Ok(hits)
+    }
+
+    /// Score every vector whose ID passes `keep` (no graph)
+    pub fn search_exact(
+        &self,
+        query_vec: &[f32],
+        limit: usize,
+        keep: impl Fn(u64) -> bool,
+    ) -> Result<Vec<(u64, f32)>, Box<dyn Error>> {
+        if !self.is_quantized() {
+            let scores = self.vectors.iter()
+                .filter(|(id, _)| keep(**id))
+                .map(|(id, vec)| (*id, cosine_similarity(query_vec, vec)))
+                .collect();
+            return Ok(top_k(scores, limit));
+        }
+
+        // 1. Asymmetric scan: f32 query against int8 codes
+        let probe = Probe::new(query_vec);
+        let approx = self.quantized.iter()
+            .filter(|(id, _)| keep(**id))
+            .map(|(id, q)| (*id, q.cosine(query_vec, probe.sum, probe.norm)))
+            .collect();
+        let candidates = top_k(approx, limit.saturating_mul(RERANK_FACTOR));
+
+        // 2. Exact re-rank where the original is still held
+        let reranked = candidates.into_iter()
+            .map(|(id, score)| match self.vectors.get(&id) {
+                Some(full) => (id, cosine_similarity(query_vec, full)),
+                None => (id, score),
             })
             .collect();
-            
-        // Sort by score desc
-        scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        
-        Ok(scores.into_iter().take(limit).collect())
+
+        Ok(top_k(reranked, limit))
+    }
+
+    /// Cosine similarity of `probe` to an entry, exact where the f32
+    /// original is held
+    fn score(&self, probe: &Probe, id: u64) -> f32 {
+        match self.vectors.get(&id) {
+            Some(full) => cosine_similarity(probe.vector, full),
+            None => self.quantized.get(&id).map_or(0.0, |q| q.cosine(probe.vector, probe.sum, probe.norm)),
+        }
+    }
+
+    /// Cosine similarity of two entries
+    fn similarity_between(&self, a: u64, b: u64) -> f32 {
+        if let Some(full) = self.vectors.get(&a) {
+            return self.score(&Probe::new(full), b);
+        }
+        if let Some(full) = self.vectors.get(&b) {
+            return self.score(&Probe::new(full), a);
+        }
+        match (self.quantized.get(&a), self.quantized.get(&b)) {
+            (Some(x), Some(y)) => x.similarity(y),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Bounds-checked cursor over a persisted index
+pub(super) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(super) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// Whether every byte was read
+    pub(super) fn is_done(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    pub(super) fn take(&mut self, n: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        let end = self.pos.checked_add(n).filter(|end| *end <= self.bytes.len()).ok_or("index file truncated")?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
     }
+
+    pub(super) fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub(super) fn u64(&mut self) -> Result<u64, Box<dyn Error>> {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+
+    /// `u32` length followed by that many bytes
+    pub(super) fn field(&mut self) -> Result<&'a [u8], Box<dyn Error>> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+/// Sort by score desc and keep the first `limit`
+fn top_k(mut scores: Vec<(u64, f32)>, limit: usize) -> Vec<(u64, f32)> {
+    scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    scores.truncate(limit);
+    scores
 }
 
 fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
-    let dot_product: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
-    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
-    
+    // One pass, eight lanes at a time so the compiler can vectorize it
+    let (mut dot, mut norm_a, mut norm_b) = ([0f32; 8], [0f32; 8], [0f32; 8]);
+    let (chunks_a, chunks_b) = (a.chunks_exact(8), b.chunks_exact(8));
+    let tail = chunks_a.remainder().iter().zip(chunks_b.remainder());
+    for (x, y) in chunks_a.zip(chunks_b) {
+        for lane in 0..8 {
+            dot[lane] += x[lane] * y[lane];
+            norm_a[lane] += x[lane] * x[lane];
+            norm_b[lane] += y[lane] * y[lane];
+        }
+    }
+    let (mut dot, mut norm_a, mut norm_b): (f32, f32, f32) = (dot.iter().sum(), norm_a.iter().sum(), norm_b.iter().sum());
+    for (x, y) in tail {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
     if norm_a == 0.0 || norm_b == 0.0 {
         return 0.0;
     }
-    
-    dot_product / (norm_a * norm_b)
+
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIM: usize = 384;
+
+    /// Deterministic pseudo-random unit vectors (xorshift)
+    fn corpus(n: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut state = seed;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+        };
+        (0..n)
+            .map(|_| {
+                let mut v: Vec<f32> = (0..DIM).map(|_| next()).collect();
+                let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+                v.iter_mut().for_each(|x| *x /= norm);
+                v
+            })
+            .collect()
+    }
+
+    /// Query close to an existing vector, so there is a clear neighbourhood
+    fn near(base: &[f32], noise: &[f32]) -> Vec<f32> {
+        base.iter().zip(noise).map(|(b, n)| b + 0.5 * n).collect()
+    }
+
+    /// Vectors scattered around `n / 100` centres, like embeddings of
+    /// captures of the same few screens
+    fn clustered(n: usize, seed: u64) -> Vec<Vec<f32>> {
+        let centres = corpus(n / 100, seed);
+        corpus(n, seed ^ 0xFFFF)
+            .into_iter()
+            .enumerate()
+            .map(|(i, noise)| centres[i % centres.len()].iter().zip(noise).map(|(c, n)| c + n).collect())
+            .collect()
+    }
+
+    fn recall_at_10(keep_full: usize) -> f32 {
+        let vectors = corpus(2000, 0x9E37_79B9_7F4A_7C15);
+        let noise = corpus(50, 42);
+
+        let mut baseline = SemanticIndex::new().unwrap();
+        let mut quantized = SemanticIndex::quantized(keep_full).unwrap();
+        for (id, v) in vectors.iter().enumerate() {
+            baseline.add(id as u64, v.clone(), "").unwrap();
+            quantized.add(id as u64, v.clone(), "").unwrap();
+        }
+
+        let mut hits = 0;
+        for (i, n) in noise.iter().enumerate() {
+            let query = near(&vectors[i * 37], n);
+            let truth: Vec<u64> = baseline.search(&query, 10).unwrap().iter().map(|r| r.0).collect();
+            let found = quantized.search(&query, 10).unwrap();
+            hits += found.iter().filter(|r| truth.contains(&r.0)).count();
+        }
+        hits as f32 / (noise.len() * 10) as f32
+    }
+
+    #[test]
+    fn test_quantize_roundtrip() {
+        let v = vec![-0.5, 0.0, 0.25, 0.5];
+        let q = QuantizedVector::quantize(&v);
+        for (a, b) in v.iter().zip(q.dequantize()) {
+            assert!((a - b).abs() <= q.scale / 2.0 + 1e-6, "{} vs {}", a, b);
+        }
+        assert_eq!(QuantizedVector::from_bytes(&q.to_bytes()), Some(q));
+
+        let constant = QuantizedVector::quantize(&[0.3; 8]);
+        assert_eq!(constant.dequantize(), vec![0.3; 8]);
+    }
+
+    #[test]
+    fn test_recall_without_rerank() {
+        // Pure int8 asymmetric scoring
+        let recall = recall_at_10(0);
+        assert!(recall > 0.95, "recall@10 = {}", recall);
+    }
+
+    #[test]
+    fn test_recall_with_rerank() {
+        let recall = recall_at_10(2000);
+        assert!(recall >= 0.99, "recall@10 = {}", recall);
+    }
+
+    #[test]
+    fn test_graph_search() {
+        let vectors = clustered(300, 3);
+        let query = near(&vectors[123], &corpus(1, 9)[0]);
+        let dir = std::env::temp_dir().join(format!("eva_test_graph_{}", std::process::id()));
+        let path = dir.join("semantic_index.bin");
+
+        for keep_full in [None, Some(0)] {
+            let mut index = match keep_full {
+                Some(n) => SemanticIndex::quantized(n).unwrap(),
+                None => SemanticIndex::new().unwrap(),
+            };
+            for (id, v) in vectors.iter().enumerate() {
+                index.add(id as u64, v.clone(), "").unwrap();
+            }
+            // Small indexes are scanned; link this one by hand
+            assert!(index.graph.is_empty());
+            for id in 0..vectors.len() as u64 {
+                index.link(id);
+            }
+
+            let exact = index.search_exact(&query, 5, |_| true).unwrap();
+            let approx = index.search(&query, 5).unwrap();
+            assert_eq!(approx[0], exact[0]);
+            assert!(approx.iter().filter(|hit| exact.contains(hit)).count() >= 4, "{:?} vs {:?}", approx, exact);
+
+            // A filter the graph's candidates barely pass falls back to a scan
+            let few = |id: u64| id % 97 == 0;
+            assert_eq!(index.search_filtered(&query, 5, few).unwrap(), index.search_exact(&query, 5, few).unwrap());
+
+            index.save(&path).unwrap();
+            let loaded = SemanticIndex::load(&path).unwrap();
+            assert_eq!(loaded.graph.len(), 300);
+            assert_eq!(loaded.search(&query, 5).unwrap(), approx);
+
+            // Removals leave holes until the graph is rebuilt
+            for id in 0..40 {
+                index.remove(id);
+            }
+            assert!(index.needs_rebuild());
+            assert!(index.search(&query, 10).unwrap().iter().all(|(id, _)| *id >= 40));
+            index.rebuild();
+            assert!(index.graph.is_empty() && !index.needs_rebuild());
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "benchmark, run with `cargo test --release`")]
+    fn test_graph_recall_and_speed_at_50k() {
+        let vectors = clustered(50_000, 0x51ED_270B);
+        let mut index = SemanticIndex::new().unwrap();
+        for (id, v) in vectors.iter().enumerate() {
+            index.add(id as u64, v.clone(), "").unwrap();
+        }
+        assert_eq!(index.graph.len(), 50_000);
+
+        let queries: Vec<Vec<f32>> = corpus(100, 77).iter().enumerate().map(|(i, n)| near(&vectors[i * 499], n)).collect();
+        let started = std::time::Instant::now();
+        let exact: Vec<_> = queries.iter().map(|q| index.search_exact(q, 10, |_| true).unwrap()).collect();
+        let exact_time = started.elapsed();
+        let started = std::time::Instant::now();
+        let approx: Vec<_> = queries.iter().map(|q| index.search(q, 10).unwrap()).collect();
+        let graph_time = started.elapsed();
+
+        let hits: usize = exact.iter().zip(&approx).map(|(e, a)| a.iter().filter(|hit| e.iter().any(|x| x.0 == hit.0)).count()).sum();
+        let recall = hits as f32 / (queries.len() * 10) as f32;
+        assert!(recall >= 0.95, "recall@10 = {}", recall);
+        assert!(graph_time * 10 < exact_time, "graph {:?}, exact {:?}", graph_time, exact_time);
+    }
+
+    #[test]
+    fn test_keeps_only_recent_full_precision() {
+        let mut index = SemanticIndex::quantized(2).unwrap();
+        for (id, v) in corpus(5, 7).into_iter().enumerate() {
+            index.add(id as u64, v, "").unwrap();
+        }
+        assert_eq!(index.len(), 5);
+        assert_eq!(index.vectors.len(), 2);
+        assert!(index.vectors.contains_key(&3) && index.vectors.contains_key(&4));
+        assert!(index.size_bytes() < index.f32_size_bytes());
+    }
+
+    #[test]
+    fn test_enable_quantization_migrates() {
+        let mut index = SemanticIndex::new().unwrap();
+        for (id, v) in corpus(10, 11).into_iter().enumerate() {
+            index.add(id as u64, v, "").unwrap();
+        }
+        let before = index.size_bytes();
+        assert_eq!(before, 10 * DIM * 4);
+
+        index.enable_quantization(0);
+        assert_eq!(index.len(), 10);
+        assert_eq!(index.f32_size_bytes(), before);
+        assert_eq!(index.size_bytes(), 10 * (DIM + QUANT_HEADER_BYTES));
+        assert!(index.size_bytes() * 3 < before);
+    }
+
+    #[test]
+    fn test_remove() {
+        let vectors = corpus(3, 7);
+        let mut index = SemanticIndex::quantized(2).unwrap();
+        for (id, v) in vectors.iter().enumerate() {
+            index.add(id as u64 + 1, v.clone(), "capture").unwrap();
+        }
+
+        assert!(index.remove(3));
+        assert!(index.remove(1));
+        assert!(!index.remove(1));
+        assert_eq!(index.len(), 1);
+        assert_eq!(index.max_id(), 2);
+        assert!(index.snippet(3).is_none());
+        let hits = index.search_filtered(&vectors[2], 10, |_| true).unwrap();
+        assert_eq!(hits.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("eva_test_index_{}", std::process::id()));
+        let path = dir.join("semantic_index.bin");
+        let vectors = corpus(6, 5);
+
+        for keep_full in [None, Some(2)] {
+            let mut index = match keep_full {
+                Some(n) => SemanticIndex::quantized(n).unwrap(),
+                None => SemanticIndex::new().unwrap(),
+            };
+            for (id, v) in vectors.iter().enumerate() {
+                index.add(id as u64 + 1, v.clone(), &format!("capture {} ✓", id)).unwrap();
+            }
+            index.save(&path).unwrap();
+
+            let loaded = SemanticIndex::load(&path).unwrap();
+            assert_eq!(loaded.is_quantized(), keep_full.is_some());
+            assert_eq!((loaded.len(), loaded.max_id()), (6, 6));
+            assert_eq!(loaded.size_bytes(), index.size_bytes());
+            assert_eq!(loaded.snippet(3), Some("capture 2 ✓"));
+            assert_eq!(loaded.search(&vectors[4], 3).unwrap(), index.search(&vectors[4], 3).unwrap());
+        }
+
+        // Damage is reported, never a panic
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[40] ^= 0xFF;
+        fs::write(&path, &bytes).unwrap();
+        assert!(SemanticIndex::load(&path).err().unwrap().to_string().contains("checksum"));
+        fs::write(&path, &bytes[..20]).unwrap();
+        assert!(SemanticIndex::load(&path).is_err());
+        assert!(SemanticIndex::load(&dir.join("missing.bin")).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
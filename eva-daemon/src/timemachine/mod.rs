@@ -1,13 +1,20 @@
 pub mod capture;
+pub mod dedup;
 pub mod embeddings;
+pub mod hnsw;
 pub mod index;
 pub mod npu_delegate;
 pub mod ocr;
+pub mod policy;
+pub mod redaction;
 pub mod search;
 pub mod storage;
 
+use crate::command_parser::{TimeHint, TimeMachineOperation};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
 
 /// Default configuration values
@@ -15,6 +22,13 @@ const DEFAULT_CAPTURE_INTERVAL_SECS: u64 = 10;
 const DEFAULT_CLEANUP_INTERVAL_CAPTURES: u64 = 100; // Run cleanup every 100 captures
 const DEFAULT_MAX_STORAGE_MB: u64 = 5000;
 const DEFAULT_RETENTION_DAYS: i64 = 30;
+const DEFAULT_DELETE_AFTER_DAYS: i64 = 365;
+const DEFAULT_FULL_PRECISION_RECENT: usize = 1000;
+const DEFAULT_DEDUP_SIMILARITY: f32 = 0.95;
+const DEFAULT_DEDUP_HISTORY: usize = 1;
+
+/// Persisted semantic index, inside the TimeMachine directory
+const INDEX_FILE: &str = "semantic_index.bin";
 
 /// TimeMachine configuration
 #[derive(Clone)]
@@ -23,10 +37,42 @@ pub struct TimeMachineConfig {
     pub capture_interval_secs: u64,
     /// Maximum storage in megabytes
     pub max_storage_mb: u64,
-    /// Retention period in days
+    /// Retention period in days (full-resolution window when shorter than
+    /// `downsample_after_days`)
     pub retention_days: i64,
+    /// Beyond this age keep one representative capture per app per hour
+    /// (per screen, in `CaptureMode::All`)
+    pub downsample_after_days: i64,
+    /// Beyond this age delete captures entirely
+    pub delete_after_days: i64,
     /// Run cleanup every N captures
     pub cleanup_interval: u64,
+    /// Store embeddings as int8 (4x smaller) instead of f32
+    pub quantize_embeddings: bool,
+    /// With quantization on, keep f32 originals for this many recent
+    /// captures so their search results are re-ranked exactly
+    pub full_precision_recent: usize,
+    /// PII blurring applied before screenshots are stored
+    pub redaction: redaction::RedactionConfig,
+    /// Skip captures at least this similar (0.0-1.0) to a recently stored
+    /// one; above 1.0 stores every capture
+    pub dedup_similarity: f32,
+    /// How many recently stored captures to compare against
+    pub dedup_history: usize,
+    /// Candidates kept while searching a large index; higher finds more of
+    /// the true best matches but is slower
+    pub search_ef: usize,
+    /// Encoding of stored captures (PNG, lossless WebP or JPEG)
+    pub image_format: storage::CaptureFormat,
+    /// JPEG quality, 1-100
+    pub quality: u8,
+    /// Downscale captures so neither side exceeds this many pixels (e.g.
+    /// 1600); `None` keeps the full resolution
+    pub max_dimension: Option<u32>,
+    /// Which screens each tick records
+    pub capture_mode: capture::CaptureMode,
+    /// Extra windows to keep out of captures, each blocked or masked
+    pub privacy_patterns: Vec<capture::PrivacyPattern>,
 }
 
 impl Default for TimeMachineConfig {
@@ -35,7 +81,20 @@ impl Default for TimeMachineConfig {
             capture_interval_secs: DEFAULT_CAPTURE_INTERVAL_SECS,
             max_storage_mb: DEFAULT_MAX_STORAGE_MB,
             retention_days: DEFAULT_RETENTION_DAYS,
+            downsample_after_days: DEFAULT_RETENTION_DAYS,
+            delete_after_days: DEFAULT_DELETE_AFTER_DAYS,
             cleanup_interval: DEFAULT_CLEANUP_INTERVAL_CAPTURES,
+            quantize_embeddings: false,
+            full_precision_recent: DEFAULT_FULL_PRECISION_RECENT,
+            redaction: redaction::RedactionConfig::default(),
+            dedup_similarity: DEFAULT_DEDUP_SIMILARITY,
+            dedup_history: DEFAULT_DEDUP_HISTORY,
+            search_ef: index::DEFAULT_EF,
+            image_format: storage::CaptureFormat::default(),
+            quality: storage::DEFAULT_QUALITY,
+            max_dimension: None,
+            capture_mode: capture::CaptureMode::default(),
+            privacy_patterns: Vec::new(),
         }
     }
 }
@@ -46,18 +105,194 @@ pub struct TimeMachineStats {
     pub total_captures: u64,
     pub successful_captures: u64,
     pub blocked_by_privacy: u64,
+    /// Captures not stored because they matched a recent one
+    pub skipped_duplicates: u64,
     pub errors: u64,
     pub storage_used_mb: f64,
+    /// Retention tiers: full resolution, downsampled, purged
+    pub full_captures: u64,
+    pub full_mb: f64,
+    pub downsampled_captures: u64,
+    pub downsampled_mb: f64,
+    pub purged_captures: u64,
+    /// Semantic index: vector count, current size and size as plain f32
+    pub index_vectors: u64,
+    pub index_bytes: u64,
+    pub index_f32_bytes: u64,
+    /// Embeddings persisted in the database
+    pub embedding_storage_bytes: u64,
+    /// Whether OCR and embeddings are running on the NPU driver
+    pub backend: npu_delegate::Backend,
+    /// Capture ticks skipped by each app policy (keyed by its app pattern)
+    pub policy_skips: BTreeMap<String, u64>,
+}
+
+/// Matches read back for a spoken search
+const SPOKEN_RESULTS: usize = 3;
+
+/// Characters of a capture's text read back or listed
+const SNIPPET_CHARS: usize = 80;
+
+/// `hour:minute` on a local day (the earlier one when clocks go back)
+fn local_time<Tz: TimeZone>(day: NaiveDate, hour: u32, minute: u32, tz: &Tz) -> Option<DateTime<Utc>> {
+    let time = day.and_hms_opt(hour, minute, 0)?;
+    tz.from_local_datetime(&time).earliest().map(|t| t.with_timezone(&Utc))
+}
+
+/// Start of the local day containing `now`
+fn local_midnight<Tz: TimeZone>(now: DateTime<Utc>, tz: &Tz) -> DateTime<Utc> {
+    local_time(now.with_timezone(tz).date_naive(), 0, 0, tz).unwrap_or(now - Duration::hours(24))
+}
+
+/// Start and end of a local day
+pub fn day_range<Tz: TimeZone>(day: NaiveDate, tz: &Tz) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = local_time(day, 0, 0, tz).unwrap_or_else(|| day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    let end = day.succ_opt().and_then(|next| local_time(next, 0, 0, tz)).unwrap_or(start + Duration::hours(24));
+    (start, end)
+}
+
+/// The moment "at 3 yesterday" refers to: the latest matching time not
+/// after now, so a bare 3 is 15:00 once that has passed and a time still
+/// ahead today means yesterday's
+pub fn timeline_moment<Tz: TimeZone>(
+    hour: u32,
+    minute: u32,
+    twelve_hour: bool,
+    yesterday: bool,
+    now: DateTime<Utc>,
+    tz: &Tz,
+) -> DateTime<Utc> {
+    let today = now.with_timezone(tz).date_naive();
+    let days: Vec<NaiveDate> = if yesterday { today.pred_opt().into_iter().collect() } else { [Some(today), today.pred_opt()].into_iter().flatten().collect() };
+    let hours: &[u32] = if twelve_hour { &[hour, hour + 12] } else { &[hour] };
+    days.iter()
+        .flat_map(|&day| hours.iter().filter_map(move |&h| local_time(day, h, minute, tz)))
+        .filter(|&t| t <= now)
+        .max()
+        .unwrap_or(now)
+}
+
+/// A capture's text on one line, cut to `SNIPPET_CHARS`
+fn snippet(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").chars().take(SNIPPET_CHARS).collect()
+}
+
+/// Capture window a spoken time hint refers to
+///
+/// "An hour ago" is vague, so `Ago` covers half the distance on either side
+/// (at least ten minutes) without reaching past now.
+pub fn hint_range<Tz: TimeZone>(hint: TimeHint, now: DateTime<Utc>, tz: &Tz) -> (DateTime<Utc>, DateTime<Utc>) {
+    match hint {
+        TimeHint::Ago { seconds } => {
+            let center = now - Duration::seconds(seconds as i64);
+            let slack = Duration::seconds((seconds as i64 / 2).max(600));
+            (center - slack, (center + slack).min(now))
+        }
+        TimeHint::Today => (local_midnight(now, tz), now),
+        TimeHint::Yesterday => {
+            let today = local_midnight(now, tz);
+            (local_midnight(today - Duration::hours(12), tz), today)
+        }
+    }
+}
+
+/// What happened to a capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CaptureOutcome {
+    Stored,
+    Duplicate,
+}
+
+/// A search result
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub id: u64,
+    pub score: f32,
+    pub text: String,
+    /// When the screenshot was taken
+    pub timestamp: DateTime<Utc>,
+    /// False when retention kept only the thumbnail
+    pub full_image: bool,
+    /// Monitor the capture shows; `None` for captures from before screens
+    /// were recorded
+    pub screen: Option<storage::ScreenRef>,
+    /// A window was masked out of the screenshot for privacy
+    pub masked: bool,
+}
+
+impl SearchResult {
+    /// Monitor number (from 1) worth mentioning: only for captures from a
+    /// multi-screen setup
+    pub fn screen_number(&self) -> Option<u32> {
+        screen_number(self.screen)
+    }
+}
+
+/// See `SearchResult::screen_number`
+pub fn screen_number(screen: Option<storage::ScreenRef>) -> Option<u32> {
+    screen.filter(|screen| screen.index > 0 || screen.group_id.is_some()).map(|screen| screen.index + 1)
+}
+
+/// One capture on the timeline
+#[derive(Debug, Clone)]
+pub struct TimelineEntry {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Application in focus, when known
+    pub app_name: Option<String>,
+    /// Start of the capture's text
+    pub snippet: String,
+    /// A thumbnail can be fetched with `TimeMachine::get_thumbnail`
+    pub thumbnail: bool,
+    /// False when retention kept only the thumbnail
+    pub full_image: bool,
+    pub screen: Option<storage::ScreenRef>,
+}
+
+impl From<storage::CaptureSummary> for TimelineEntry {
+    fn from(capture: storage::CaptureSummary) -> Self {
+        Self {
+            id: capture.id,
+            timestamp: capture.timestamp,
+            app_name: capture.app_name,
+            snippet: snippet(&capture.text),
+            thumbnail: capture.thumbnail,
+            full_image: capture.full_image,
+            screen: capture.screen,
+        }
+    }
+}
+
+impl From<storage::TextHit> for SearchResult {
+    fn from(hit: storage::TextHit) -> Self {
+        Self {
+            id: hit.id,
+            score: hit.score as f32,
+            text: hit.text,
+            timestamp: hit.timestamp,
+            full_image: hit.full_image,
+            screen: hit.screen,
+            masked: hit.masked,
+        }
+    }
 }
 
 /// Time Machine AI - Captures, indexes, and searches your digital life
 pub struct TimeMachine {
     capture: capture::ScreenCapture,
     ocr: ocr::OCREngine,
+    redactor: redaction::Redactor,
+    /// One filter per screen, so screens aren't compared with each other
+    dedup: Mutex<BTreeMap<usize, dedup::DuplicateFilter>>,
+    /// Per-app intervals and resolution caps
+    policies: Mutex<policy::PolicyTable>,
     embeddings: embeddings::EmbeddingEngine,
     index: Arc<RwLock<index::SemanticIndex>>,
+    /// Where the index is persisted
+    index_path: std::path::PathBuf,
+    /// Results of the last spoken search, for follow-ups
+    last_search: Mutex<Vec<SearchResult>>,
     storage: storage::Storage,
-    #[allow(dead_code)]
     npu: npu_delegate::NPUDelegate,
     /// Configuration
     config: TimeMachineConfig,
@@ -68,6 +303,7 @@ pub struct TimeMachine {
     capture_count: Arc<AtomicU64>,
     success_count: Arc<AtomicU64>,
     privacy_blocked_count: Arc<AtomicU64>,
+    duplicate_count: Arc<AtomicU64>,
     error_count: Arc<AtomicU64>,
 }
 
@@ -89,29 +325,95 @@ impl TimeMachine {
         let embeddings = embeddings::EmbeddingEngine::new(&npu).await?;
 
         // 3. Setup Storage (Encrypted)
-        let mut storage = storage::Storage::new("~/.eva/timemachine").await?;
+        let mut storage = storage::Storage::new(&crate::paths::timemachine_dir()?.to_string_lossy()).await?;
         storage.set_limits(config.max_storage_mb, config.retention_days);
+        storage.set_retention_tiers(
+            config.downsample_after_days.min(config.retention_days),
+            config.delete_after_days,
+        );
+        storage.set_image_encoding(config.image_format, config.quality, config.max_dimension);
+
+        // Captures are encrypted with the keystore key; older ones are
+        // moved over from the legacy key as they are read
+        let encryption_key = crate::keystore::get_or_create_key(crate::keystore::TIMEMACHINE).map_err(|e| e.to_string())?;
+        storage.set_encryption_key(&encryption_key);
+        storage.set_legacy_password(&Self::legacy_encryption_key())?;
+
+        // Rewrite stored embeddings if the quantization setting changed
+        let keep_full = config.quantize_embeddings.then_some(config.full_precision_recent);
+        storage.set_embedding_quantization(keep_full);
+        let migration = storage.migrate_embeddings().await?;
+        if migration.rewritten > 0 || migration.trimmed > 0 {
+            println!(
+                "[TimeMachine] Embeddings migrated to {} ({} rewritten): {} KB -> {} KB",
+                if keep_full.is_some() { "int8" } else { "f32" },
+                migration.rewritten,
+                migration.bytes_before / 1024,
+                migration.bytes_after / 1024
+            );
+        }
 
-        // Get encryption key securely
-        let encryption_key = Self::get_encryption_key()?;
-        storage.set_encryption_key(&encryption_key)?;
+        // 4. Setup Index (persisted; rebuilt from storage when unusable)
+        let index_path = crate::paths::timemachine_dir()?.join(INDEX_FILE);
+        let (mut index, rebuild) = match index::SemanticIndex::load(&index_path) {
+            Ok(index) if index.is_quantized() == keep_full.is_some() => (index, false),
+            Ok(_) => {
+                println!("[TimeMachine] Index quantization changed, rebuilding");
+                (Self::empty_index(keep_full)?, true)
+            }
+            Err(_) if !index_path.exists() => (Self::empty_index(keep_full)?, true),
+            Err(e) => {
+                eprintln!("[TimeMachine] Index file unreadable ({}), rebuilding", e);
+                (Self::empty_index(keep_full)?, true)
+            }
+        };
+        index.set_ef(config.search_ef);
+        let index = Arc::new(RwLock::new(index));
 
-        // 4. Setup Index
-        let index = Arc::new(RwLock::new(index::SemanticIndex::new()?));
+        // 5. Setup PII redaction (face detection only if the model loads)
+        let redactor = redaction::Redactor::new(config.redaction.clone());
+        #[cfg(feature = "timemachine")]
+        let redactor = match config.redaction.faces.then(|| redaction::OnnxFaceDetector::new(&npu)) {
+            Some(Ok(detector)) => redactor.with_face_detector(Box::new(detector)),
+            Some(Err(e)) => {
+                println!("[TimeMachine] Face redaction unavailable: {}", e);
+                redactor
+            }
+            None => redactor,
+        };
 
-        // 5. Setup Capture with privacy filter
-        let capture = capture::ScreenCapture::new();
+        // 6. Setup Capture with privacy filter and duplicate skipping
+        let mut capture = capture::ScreenCapture::new();
+        for pattern in &config.privacy_patterns {
+            capture.add_pattern(pattern.clone());
+        }
+        let policies = policy::PolicyTable::load(config.capture_interval_secs).unwrap_or_else(|e| {
+            eprintln!("[TimeMachine] Capture policies ignored: {}", e);
+            policy::PolicyTable::new(Vec::new(), config.capture_interval_secs)
+        });
+        if !policies.is_empty() {
+            println!("[TimeMachine] {} app capture policies", policies.len());
+        }
 
         println!(
-            "[TimeMachine] Ready (interval: {}s, max: {}MB, retention: {} days)",
-            config.capture_interval_secs, config.max_storage_mb, config.retention_days
+            "[TimeMachine] Ready (interval: {}s, screens: {}, max: {}MB, retention: {} days full, {} days downsampled)",
+            config.capture_interval_secs,
+            config.capture_mode.name(),
+            config.max_storage_mb,
+            config.retention_days,
+            config.delete_after_days
         );
 
-        Ok(Self {
+        let tm = Self {
             capture,
             ocr,
+            redactor,
+            dedup: Mutex::new(BTreeMap::new()),
+            policies: Mutex::new(policies),
             embeddings,
             index,
+            index_path,
+            last_search: Mutex::new(Vec::new()),
             storage,
             npu,
             config,
@@ -120,25 +422,75 @@ impl TimeMachine {
             capture_count: Arc::new(AtomicU64::new(0)),
             success_count: Arc::new(AtomicU64::new(0)),
             privacy_blocked_count: Arc::new(AtomicU64::new(0)),
+            duplicate_count: Arc::new(AtomicU64::new(0)),
             error_count: Arc::new(AtomicU64::new(0)),
-        })
+        };
+
+        // Bring the index up to date; a failure here only limits search
+        let synced = if rebuild { tm.rebuild_from_storage().await } else { tm.index_new_captures().await };
+        match synced {
+            Ok(added) if added > 0 => println!("[TimeMachine] Indexed {} stored captures", added),
+            Ok(_) => {}
+            Err(e) => eprintln!("[TimeMachine] Index rebuild failed: {}", e),
+        }
+
+        Ok(tm)
+    }
+
+    fn empty_index(keep_full: Option<usize>) -> Result<index::SemanticIndex, Box<dyn std::error::Error>> {
+        match keep_full {
+            Some(n) => index::SemanticIndex::quantized(n),
+            None => index::SemanticIndex::new(),
+        }
+    }
+
+    /// Rebuild the semantic index from every capture in storage.
+    ///
+    /// Stored embeddings are reused; captures without one have their text
+    /// encoded again. Returns the number of captures indexed.
+    pub async fn rebuild_from_storage(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let keep_full = self.config.quantize_embeddings.then_some(self.config.full_precision_recent);
+        let mut index = Self::empty_index(keep_full)?;
+        index.set_ef(self.config.search_ef);
+        *self.index.write().await = index;
+        self.index_new_captures().await
+    }
+
+    /// Index captures stored after the newest one in the index (e.g. those
+    /// lost by a crash since the last save), then persist the index.
+    async fn index_new_captures(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let after = self.index.read().await.max_id();
+        let captures = self.storage.captures_after(after).await?;
+        if captures.is_empty() {
+            return Ok(0);
+        }
+
+        let mut idx = self.index.write().await;
+        for (id, text) in &captures {
+            let embedding = match self.storage.load_embedding(*id).await {
+                Ok(stored) if !stored.is_empty() => stored,
+                _ => self.embeddings.encode(text)?,
+            };
+            idx.add(*id, embedding, text)?;
+        }
+        idx.save(&self.index_path)?;
+        Ok(captures.len())
     }
 
-    /// Get encryption key from environment or derive from machine-specific data
-    fn get_encryption_key() -> Result<String, Box<dyn std::error::Error>> {
-        // Priority 1: Environment variable
+    /// Write the semantic index to disk
+    pub async fn save_index(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.index.read().await.save(&self.index_path)
+    }
+
+    /// Password captures were encrypted with before the keystore:
+    /// `EVA_TIMEMACHINE_KEY` if it was set, else derived from machine data
+    fn legacy_encryption_key() -> String {
         if let Ok(key) = std::env::var("EVA_TIMEMACHINE_KEY") {
             if key.len() >= 16 {
-                println!("[TimeMachine] Using encryption key from environment");
-                return Ok(key);
-            } else {
-                eprintln!(
-                    "[TimeMachine] Warning: EVA_TIMEMACHINE_KEY too short (min 16 chars), using fallback"
-                );
+                return key;
             }
         }
 
-        // Priority 2: Derive from machine-specific data
         let username = std::env::var("USERNAME")
             .or_else(|_| std::env::var("USER"))
             .unwrap_or_else(|_| "eva_user".to_string());
@@ -147,13 +499,7 @@ impl TimeMachine {
             .map(|h| h.to_string_lossy().to_string())
             .unwrap_or_else(|_| "eva_host".to_string());
 
-        let derived_key = format!("eva_tm_{}_{}_secret", username, hostname);
-
-        println!(
-            "[TimeMachine] Warning: Using machine-derived key. Set EVA_TIMEMACHINE_KEY for production!"
-        );
-
-        Ok(derived_key)
+        format!("eva_tm_{}_{}_secret", username, hostname)
     }
 
     /// Start recording (non-blocking, returns immediately)
@@ -175,31 +521,56 @@ impl TimeMachine {
         self.is_paused.store(false, Ordering::SeqCst);
         println!("[TimeMachine] Recording started");
 
-        let interval = tokio::time::Duration::from_secs(self.config.capture_interval_secs);
+        // Wake often enough for every app's interval; the policies decide
+        // which ticks capture
+        let tick = self.policies.lock().unwrap().tick();
 
         while self.is_recording.load(Ordering::SeqCst) {
-            tokio::time::sleep(interval).await;
+            tokio::time::sleep(tick).await;
 
             // Skip if paused
             if self.is_paused.load(Ordering::SeqCst) {
                 continue;
             }
 
-            // Capture
+            let window = self.capture.active_window();
+            let app = window.as_ref().map(|w| w.app_name.as_str());
+            let policy::Decision::Capture { max_resolution } =
+                self.policies.lock().unwrap().decide(app, std::time::Instant::now())
+            else {
+                continue;
+            };
+
+            // Capture (privacy filtered per screen)
             self.capture_count.fetch_add(1, Ordering::SeqCst);
 
-            match self.capture_and_process().await {
-                Ok(_) => {
-                    self.success_count.fetch_add(1, Ordering::SeqCst);
+            let shots = match self.capture.take_screenshots(self.config.capture_mode, window.as_ref()) {
+                Ok(set) => {
+                    // Privacy blocks are expected, don't log as error
+                    self.privacy_blocked_count.fetch_add(set.blocked as u64, Ordering::SeqCst);
+                    set.images
                 }
                 Err(e) => {
-                    let err_msg = e.to_string();
-                    if err_msg.contains("blocked") || err_msg.contains("privacy") {
-                        self.privacy_blocked_count.fetch_add(1, Ordering::SeqCst);
-                        // Privacy blocks are expected, don't log as error
-                    } else {
+                    self.error_count.fetch_add(1, Ordering::SeqCst);
+                    eprintln!("[TimeMachine] Error: {}", e);
+                    Vec::new()
+                }
+            };
+            // Screens recorded in the same tick are linked by its time
+            let group_id = (self.config.capture_mode == capture::CaptureMode::All)
+                .then(|| chrono::Utc::now().timestamp_millis() as u64);
+
+            for shot in shots {
+                match self.capture_and_process(shot, window.as_ref(), max_resolution, group_id).await {
+                    Ok(CaptureOutcome::Stored) => {
+                        self.success_count.fetch_add(1, Ordering::SeqCst);
+                    }
+                    Ok(CaptureOutcome::Duplicate) => {
+                        self.duplicate_count.fetch_add(1, Ordering::SeqCst);
+                    }
+                    Err(e) => {
                         self.error_count.fetch_add(1, Ordering::SeqCst);
-                        eprintln!("[TimeMachine] Error: {}", err_msg);
+                        eprintln!("[TimeMachine] Error: {}", e);
                     }
                 }
             }
@@ -213,6 +584,9 @@ impl TimeMachine {
             }
         }
 
+        if let Err(e) = self.save_index().await {
+            eprintln!("[TimeMachine] Failed to save index: {}", e);
+        }
         println!("[TimeMachine] Recording stopped");
     }
 
@@ -247,54 +621,116 @@ impl TimeMachine {
     /// Get statistics
     pub async fn get_stats(&self) -> Result<TimeMachineStats, Box<dyn std::error::Error>> {
         let storage_stats = self.storage.get_stats().await?;
+        let idx = self.index.read().await;
 
         Ok(TimeMachineStats {
             total_captures: self.capture_count.load(Ordering::SeqCst),
             successful_captures: self.success_count.load(Ordering::SeqCst),
             blocked_by_privacy: self.privacy_blocked_count.load(Ordering::SeqCst),
+            skipped_duplicates: self.duplicate_count.load(Ordering::SeqCst),
             errors: self.error_count.load(Ordering::SeqCst),
             storage_used_mb: storage_stats.storage_used_mb,
+            full_captures: storage_stats.full_count,
+            full_mb: storage_stats.full_mb,
+            downsampled_captures: storage_stats.downsampled_count,
+            downsampled_mb: storage_stats.downsampled_mb,
+            purged_captures: storage_stats.purged_count,
+            index_vectors: idx.len() as u64,
+            index_bytes: idx.size_bytes() as u64,
+            index_f32_bytes: idx.f32_size_bytes() as u64,
+            embedding_storage_bytes: storage_stats.embedding_bytes,
+            backend: self.npu.backend(),
+            policy_skips: self.policies.lock().unwrap().skips(),
         })
     }
 
     /// Run cleanup (storage rotation)
     async fn run_cleanup(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // 1. Cleanup old snapshots (by retention period)
-        let deleted_by_age = self.storage.cleanup_old_snapshots().await?;
+        // 1. Tiered retention (downsample, then delete by age)
+        let by_age = self.storage.cleanup_old_snapshots().await?;
 
         // 2. Cleanup to meet storage limit
         let deleted_by_size = self.storage.cleanup_to_limit().await?;
 
-        if deleted_by_age > 0 || deleted_by_size > 0 {
+        if by_age.deleted > 0 || by_age.downsampled > 0 || deleted_by_size > 0 {
             println!(
-                "[TimeMachine] Cleanup: removed {} by age, {} by size",
-                deleted_by_age, deleted_by_size
+                "[TimeMachine] Cleanup: downsampled {}, removed {} by age, {} by size",
+                by_age.downsampled, by_age.deleted, deleted_by_size
             );
         }
 
-        Ok(())
+        // 3. Persist the index so a crash loses at most one cleanup cycle
+        self.save_index().await
     }
 
-    /// Capture and process a single screenshot
-    async fn capture_and_process(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // 1. Capture (Privacy filtered)
-        let screenshot = self.capture.take_screenshot()?;
+    /// Process one screen's capture, taken while `window` had focus, scaled
+    /// down to `max_resolution` on its longest side if given
+    async fn capture_and_process(
+        &self,
+        shot: capture::ScreenImage,
+        window: Option<&capture::ActiveWindow>,
+        max_resolution: Option<u32>,
+        group_id: Option<u64>,
+    ) -> Result<CaptureOutcome, Box<dyn std::error::Error>> {
+        // 1. Capture (already privacy filtered)
+        let mut screenshot = shot.image;
+        if let Some(max) = max_resolution.filter(|max| screenshot.width().max(screenshot.height()) > *max) {
+            screenshot = screenshot.resize(max, max, image::imageops::FilterType::Triangle);
+        }
+        // Other screens don't show the focused window, and a masked one
+        // doesn't get to name the capture
+        let window = window.filter(|_| shot.shows_window && !shot.masked);
+        let title = window.map(|w| w.title.clone());
+        let app_name = window.map(|w| w.app_name.clone());
+
+        // Nothing changed since a recent capture of this screen: skip OCR,
+        // embedding and storage
+        let hash = dedup::ImageHash::of(&screenshot);
+        let is_duplicate = self
+            .dedup
+            .lock()
+            .unwrap()
+            .entry(shot.screen_index)
+            .or_insert_with(|| dedup::DuplicateFilter::new(self.config.dedup_similarity, self.config.dedup_history))
+            .is_duplicate(&hash);
+        if is_duplicate {
+            return Ok(CaptureOutcome::Duplicate);
+        }
 
         // 2. OCR
-        let text = self.ocr.extract_text(&screenshot)?;
+        let ocr_result = self.ocr.extract(&screenshot, title.as_deref())?;
+        let text = ocr_result.text();
+
+        // 3. Redact PII before anything touches disk
+        let redactions = if self.config.redaction.any() {
+            self.redactor.redact(&mut screenshot, &ocr_result).applied
+        } else {
+            0
+        };
 
-        // 3. Embed
+        // 4. Embed
         let embedding = self.embeddings.encode(&text)?;
 
-        // 4. Storage (Encrypted)
-        let screenshot_id = self.storage.save_screenshot(screenshot).await?;
+        // 5. Storage (Encrypted)
+        let screen = storage::ScreenRef { index: shot.screen_index as u32, group_id };
+        let screenshot_id = self.storage.save_screenshot(screenshot, screen).await?;
         self.storage.save_metadata(screenshot_id, &text).await?;
+        self.storage.save_redactions(screenshot_id, redactions).await?;
+        if shot.masked {
+            self.storage.save_masked(screenshot_id).await?;
+        }
+        self.storage
+            .save_context(screenshot_id, app_name.as_deref(), None, &embedding)
+            .await?;
 
-        // 5. Index
+        // 6. Index
         let mut idx = self.index.write().await;
         idx.add(screenshot_id, embedding, &text)?;
 
-        Ok(())
+        if let Some(filter) = self.dedup.lock().unwrap().get_mut(&shot.screen_index) {
+            filter.record(hash);
+        }
+        Ok(CaptureOutcome::Stored)
     }
 
     /// Search by semantic similarity
@@ -302,16 +738,56 @@ impl TimeMachine {
         &self,
         query: &str,
         limit: usize,
-    ) -> Result<Vec<(u64, f32, String)>, Box<dyn std::error::Error>> {
+    ) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+        self.search_ranked(query, None, limit).await
+    }
+
+    /// Search by semantic similarity among captures taken in `[from, to)`
+    pub async fn search_in_range(
+        &self,
+        query: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+        self.search_ranked(query, Some((from, to)), limit).await
+    }
+
+    async fn search_ranked(
+        &self,
+        query: &str,
+        range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
         let query_vec = self.embeddings.encode(query)?;
 
+        // Narrow to the time window first so ranking only sees those captures
+        let in_range = match range {
+            Some((from, to)) => Some(self.storage.ids_in_range(from, to).await?),
+            None => None,
+        };
+
         let idx = self.index.read().await;
-        let results = idx.search(&query_vec, limit)?;
+        let results = idx.search_filtered(&query_vec, limit, |id| {
+            in_range.as_ref().map_or(true, |ids| ids.contains(&id))
+        })?;
+        drop(idx);
 
         let mut final_results = Vec::new();
         for (id, score) in results {
-            let metadata = self.storage.load_metadata(id).await?;
-            final_results.push((id, score, metadata.text));
+            // Captures purged by retention may still be in the in-memory index
+            let Ok(metadata) = self.storage.load_metadata(id).await else {
+                continue;
+            };
+            final_results.push(SearchResult {
+                id,
+                score,
+                text: metadata.text,
+                timestamp: metadata.timestamp,
+                full_image: metadata.full_image,
+                screen: metadata.screen,
+                masked: metadata.masked,
+            });
         }
 
         Ok(final_results)
@@ -322,34 +798,369 @@ impl TimeMachine {
         &self,
         query: &str,
         limit: usize,
-    ) -> Result<Vec<(u64, String, f64)>, Box<dyn std::error::Error>> {
-        self.storage.search_text(query, limit).await
+    ) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+        let hits = self.storage.search_text(query, limit).await?;
+        Ok(hits.into_iter().map(SearchResult::from).collect())
+    }
+
+    /// Search by full-text among captures taken in `[from, to)`
+    pub async fn search_text_in_range(
+        &self,
+        query: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+        let hits = self.storage.search_text_in_range(query, from, to, limit).await?;
+        Ok(hits.into_iter().map(SearchResult::from).collect())
+    }
+
+    /// Every capture of a local day, oldest first, for browsing by time
+    pub async fn timeline<Tz: TimeZone>(&self, day: NaiveDate, tz: &Tz) -> Result<Vec<TimelineEntry>, Box<dyn std::error::Error>> {
+        let (from, to) = day_range(day, tz);
+        let captures = self.storage.list_range(from, to).await?;
+        Ok(captures.into_iter().map(TimelineEntry::from).collect())
     }
 
     /// Get a screenshot by ID
-    pub async fn get_screenshot(&self, id: u64) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    pub async fn get_screenshot(&self, id: u64) -> Result<storage::StoredImage, Box<dyn std::error::Error>> {
         self.storage.load_screenshot(id).await
     }
 
-    /// Delete history for today (privacy feature)
+    /// Get a screenshot's thumbnail by ID (available after downsampling too)
+    pub async fn get_thumbnail(&self, id: u64) -> Result<storage::StoredImage, Box<dyn std::error::Error>> {
+        self.storage.load_thumbnail(id).await
+    }
+
+    /// Delete history for today, local midnight until now (privacy feature)
     pub async fn delete_today(&self) -> Result<u64, Box<dyn std::error::Error>> {
-        // This would need to be implemented in storage
-        // For now, just trigger cleanup
-        self.run_cleanup().await?;
-        Ok(0)
+        let now = Utc::now();
+        self.delete_range(local_midnight(now, &chrono::Local), now + Duration::seconds(1)).await
+    }
+
+    /// Delete the captures taken in `[from, to)`: files, database rows and
+    /// index entries. Returns how many captures were removed.
+    pub async fn delete_range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<u64, Box<dyn std::error::Error>> {
+        let ids = self.storage.ids_in_range(from, to).await?;
+        let deleted = self.storage.delete_range(from, to).await?;
+
+        // Captures whose files could not be removed are still in storage
+        let kept = self.storage.ids_in_range(from, to).await?;
+        let mut idx = self.index.write().await;
+        for id in ids.difference(&kept) {
+            idx.remove(*id);
+        }
+        if idx.needs_rebuild() {
+            idx.rebuild();
+        }
+        idx.save(&self.index_path)?;
+
+        println!("[TimeMachine] Deleted {} captures", deleted);
+        Ok(deleted)
+    }
+
+    /// Check stored captures for damaged, missing and leftover files (see
+    /// `Storage::verify_all`)
+    pub async fn verify(&self, delete_orphans: bool) -> Result<storage::VerifyReport, Box<dyn std::error::Error>> {
+        let report = self.storage.verify_all(delete_orphans).await?;
+        println!(
+            "[TimeMachine] Verified {} files: {} corrupted, {} missing, {} orphaned ({} removed)",
+            report.files(),
+            report.corrupted.len(),
+            report.missing.len(),
+            report.orphans.len(),
+            report.orphans_deleted
+        );
+        Ok(report)
+    }
+
+    /// Results of the last spoken search (empty before the first)
+    pub fn last_search(&self) -> Vec<SearchResult> {
+        self.last_search.lock().unwrap().clone()
+    }
+
+    /// Carry out a spoken Time Machine command, returning the reply
+    pub async fn apply<Tz: TimeZone>(
+        &self,
+        op: TimeMachineOperation,
+        now: DateTime<Utc>,
+        tz: &Tz,
+        portuguese: bool,
+    ) -> Result<String, String>
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        let pick = |en: String, pt: String| if portuguese { pt } else { en };
+
+        let reply = match op {
+            TimeMachineOperation::Pause => {
+                self.pause();
+                pick("Time Machine paused".to_string(), "Gravação pausada".to_string())
+            }
+            TimeMachineOperation::Resume => {
+                self.resume();
+                pick("Time Machine recording again".to_string(), "Gravação retomada".to_string())
+            }
+            TimeMachineOperation::Status => {
+                let stats = self.get_stats().await.map_err(|e| e.to_string())?;
+                let (en, pt) = if !self.is_recording() {
+                    ("not recording", "não está gravando")
+                } else if self.is_paused() {
+                    ("paused", "pausada")
+                } else {
+                    ("recording", "gravando")
+                };
+                pick(
+                    format!("Time Machine is {}, {} captures stored ({:.1} MB)", en, stats.successful_captures, stats.storage_used_mb),
+                    format!("Time Machine {}, {} capturas salvas ({:.1} MB)", pt, stats.successful_captures, stats.storage_used_mb),
+                )
+            }
+            TimeMachineOperation::DeleteToday => {
+                let deleted = self.delete_today().await.map_err(|e| e.to_string())?;
+                pick(format!("Deleted {} captures from today", deleted), format!("Apaguei {} capturas de hoje", deleted))
+            }
+            TimeMachineOperation::Verify { delete_orphans } => {
+                let report = self.verify(delete_orphans).await.map_err(|e| e.to_string())?;
+                describe_verify(&report, portuguese)
+            }
+            TimeMachineOperation::Search { query, time_hint } => {
+                let results = match time_hint {
+                    Some(hint) => {
+                        let (from, to) = hint_range(hint, now, tz);
+                        self.search_in_range(&query, from, to, SPOKEN_RESULTS).await
+                    }
+                    None => self.search(&query, SPOKEN_RESULTS).await,
+                }
+                .map_err(|e| e.to_string())?;
+                *self.last_search.lock().unwrap() = results.clone();
+
+                if results.is_empty() {
+                    pick(format!("Nothing about {} on screen", query), format!("Nada sobre {} na tela", query))
+                } else {
+                    let lines: Vec<String> = results
+                        .iter()
+                        .map(|r| {
+                            let snippet = snippet(&r.text);
+                            let time = r.timestamp.with_timezone(tz).format("%H:%M");
+                            let mut notes = Vec::new();
+                            if let Some(n) = r.screen_number() {
+                                notes.push(format!("{} {}", if portuguese { "tela" } else { "screen" }, n));
+                            }
+                            if r.masked {
+                                notes.push(if portuguese { "janela oculta" } else { "window masked" }.to_string());
+                            }
+                            if notes.is_empty() {
+                                format!("{}: {}", time, snippet)
+                            } else {
+                                format!("{} ({}): {}", time, notes.join(", "), snippet)
+                            }
+                        })
+                        .collect();
+                    pick(
+                        format!("On screen about {}:\n{}", query, lines.join("\n")),
+                        format!("Na tela sobre {}:\n{}", query, lines.join("\n")),
+                    )
+                }
+            }
+            TimeMachineOperation::Show { id } => {
+                let image = self.get_screenshot(id).await.map_err(|e| e.to_string())?;
+                let dir = crate::paths::exports_dir().map_err(|e| e.to_string())?;
+                let path = dir.join(format!("capture-{}.{}", id, image.format.name()));
+                std::fs::write(&path, &image.bytes).map_err(|e| e.to_string())?;
+                pick(format!("Capture saved to {}", path.display()), format!("Captura salva em {}", path.display()))
+            }
+            TimeMachineOperation::Timeline { hour, minute, twelve_hour, yesterday } => {
+                let moment = timeline_moment(hour, minute, twelve_hour, yesterday, now, tz);
+                let entries = self.timeline(moment.with_timezone(tz).date_naive(), tz).await.map_err(|e| e.to_string())?;
+                describe_timeline(moment, &entries, tz, portuguese)
+            }
+        };
+        Ok(reply)
+    }
+}
+
+/// Spoken/typed reply to "what was I doing at 3": the captures nearest
+/// `moment`, in the order they were taken
+fn describe_timeline<Tz: TimeZone>(moment: DateTime<Utc>, entries: &[TimelineEntry], tz: &Tz, portuguese: bool) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let at = moment.with_timezone(tz).format("%H:%M");
+    let mut nearest: Vec<&TimelineEntry> = entries.iter().collect();
+    nearest.sort_by_key(|e| (e.timestamp - moment).num_seconds().abs());
+    nearest.truncate(SPOKEN_RESULTS);
+    nearest.sort_by_key(|e| e.timestamp);
+    if nearest.is_empty() {
+        return if portuguese { format!("Nada gravado por volta das {}", at) } else { format!("Nothing recorded around {}", at) };
+    }
+
+    let lines: Vec<String> = nearest
+        .iter()
+        .map(|e| {
+            let time = e.timestamp.with_timezone(tz).format("%H:%M");
+            match &e.app_name {
+                Some(app) => format!("{} {}: {}", time, app, e.snippet),
+                None => format!("{}: {}", time, e.snippet),
+            }
+        })
+        .collect();
+    if portuguese {
+        format!("Por volta das {}:\n{}", at, lines.join("\n"))
+    } else {
+        format!("Around {}:\n{}", at, lines.join("\n"))
     }
 }
 
+/// Spoken/typed reply to "check my recordings"; damaged and missing files
+/// are listed by name
+fn describe_verify(report: &storage::VerifyReport, portuguese: bool) -> String {
+    if report.is_clean() {
+        return if portuguese {
+            format!("Verifiquei {} arquivos: todos intactos", report.files())
+        } else {
+            format!("Checked {} files: all intact", report.files())
+        };
+    }
+    let mut reply = if portuguese {
+        format!(
+            "Verifiquei {} arquivos: {} corrompidos, {} ausentes, {} sem captura ({} removidos)",
+            report.files(),
+            report.corrupted.len(),
+            report.missing.len(),
+            report.orphans.len(),
+            report.orphans_deleted
+        )
+    } else {
+        format!(
+            "Checked {} files: {} corrupted, {} missing, {} orphaned ({} removed)",
+            report.files(),
+            report.corrupted.len(),
+            report.missing.len(),
+            report.orphans.len(),
+            report.orphans_deleted
+        )
+    };
+    let (corrupted, missing) = if portuguese { ("corrompido", "ausente") } else { ("corrupted", "missing") };
+    for (label, paths) in [(corrupted, &report.corrupted), (missing, &report.missing)] {
+        for path in paths {
+            reply.push_str(&format!("\n{}: {}", label, path));
+        }
+    }
+    reply
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_describe_verify() {
+        let clean = storage::VerifyReport { verified: 4, ..Default::default() };
+        assert_eq!(describe_verify(&clean, false), "Checked 4 files: all intact");
+
+        let report = storage::VerifyReport {
+            verified: 2,
+            corrupted: vec!["screenshots/2026-03-10/10-00-00-000.enc".to_string()],
+            orphans: vec!["screenshots/2026-03-10/stray.enc".to_string()],
+            orphans_deleted: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            describe_verify(&report, false),
+            "Checked 3 files: 1 corrupted, 0 missing, 1 orphaned (1 removed)\ncorrupted: screenshots/2026-03-10/10-00-00-000.enc"
+        );
+        assert!(describe_verify(&report, true).starts_with("Verifiquei 3 arquivos: 1 corrompidos"));
+    }
+
+    #[test]
+    fn test_screen_number_only_for_multi_screen_captures() {
+        let result = |screen| SearchResult { id: 1, score: 1.0, text: String::new(), timestamp: Utc::now(), full_image: true, screen, masked: false };
+        assert_eq!(result(None).screen_number(), None);
+        assert_eq!(result(Some(storage::ScreenRef { index: 0, group_id: None })).screen_number(), None);
+        assert_eq!(result(Some(storage::ScreenRef { index: 0, group_id: Some(7) })).screen_number(), Some(1));
+        assert_eq!(result(Some(storage::ScreenRef { index: 1, group_id: None })).screen_number(), Some(2));
+    }
+
+    #[test]
+    fn test_hint_range() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 10, 15, 0, 0).unwrap();
+
+        let (from, to) = hint_range(TimeHint::Ago { seconds: 3600 }, now, &Utc);
+        assert_eq!((from, to), (now - Duration::minutes(90), now - Duration::minutes(30)));
+        // Short hints still get ten minutes of slack, but never past now
+        let (from, to) = hint_range(TimeHint::Ago { seconds: 120 }, now, &Utc);
+        assert_eq!((from, to), (now - Duration::minutes(12), now));
+
+        let midnight = Utc.with_ymd_and_hms(2026, 3, 10, 0, 0, 0).unwrap();
+        assert_eq!(hint_range(TimeHint::Today, now, &Utc), (midnight, now));
+        assert_eq!(hint_range(TimeHint::Yesterday, now, &Utc), (midnight - Duration::hours(24), midnight));
+
+        // Local days follow the time zone, not UTC
+        let brt = chrono::FixedOffset::west_opt(3 * 3600).unwrap();
+        assert_eq!(hint_range(TimeHint::Today, now, &brt).0, midnight + Duration::hours(3));
+    }
+
+    #[test]
+    fn test_timeline_moment() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 10, 15, 30, 0).unwrap();
+        let at = |day: u32, hour: u32, minute: u32| Utc.with_ymd_and_hms(2026, 3, day, hour, minute, 0).unwrap();
+
+        // "at 3": 15:00 has passed today; yesterday's afternoon when asked for
+        assert_eq!(timeline_moment(3, 0, true, false, now, &Utc), at(10, 15, 0));
+        assert_eq!(timeline_moment(3, 0, true, true, now, &Utc), at(9, 15, 0));
+        // "at 4" is still ahead in the afternoon, so this morning's
+        assert_eq!(timeline_moment(4, 0, true, false, now, &Utc), at(10, 4, 0));
+        // A 24-hour time still ahead today means yesterday's
+        assert_eq!(timeline_moment(18, 45, false, false, now, &Utc), at(9, 18, 45));
+        assert_eq!(timeline_moment(0, 0, false, false, now, &Utc), at(10, 0, 0));
+
+        let brt = chrono::FixedOffset::west_opt(3 * 3600).unwrap();
+        assert_eq!(timeline_moment(9, 0, false, true, now, &brt), at(9, 12, 0));
+        assert_eq!(day_range(NaiveDate::from_ymd_opt(2026, 3, 10).unwrap(), &brt), (at(10, 3, 0), at(11, 3, 0)));
+    }
+
+    #[test]
+    fn test_describe_timeline_picks_nearest_entries() {
+        let entry = |id: u64, hour: u32, minute: u32, app: Option<&str>| TimelineEntry {
+            id,
+            timestamp: Utc.with_ymd_and_hms(2026, 3, 9, hour, minute, 0).unwrap(),
+            app_name: app.map(str::to_string),
+            snippet: format!("capture {}", id),
+            thumbnail: true,
+            full_image: true,
+            screen: None,
+        };
+        let entries = [
+            entry(1, 9, 0, Some("mail")),
+            entry(2, 14, 40, Some("editor")),
+            entry(3, 14, 58, Some("browser")),
+            entry(4, 15, 10, None),
+            entry(5, 18, 0, Some("editor")),
+        ];
+        let moment = Utc.with_ymd_and_hms(2026, 3, 9, 15, 0, 0).unwrap();
+
+        assert_eq!(
+            describe_timeline(moment, &entries, &Utc, false),
+            "Around 15:00:\n14:40 editor: capture 2\n14:58 browser: capture 3\n15:10: capture 4"
+        );
+        assert!(describe_timeline(moment, &entries, &Utc, true).starts_with("Por volta das 15:00:\n14:40 editor"));
+        assert_eq!(describe_timeline(moment, &[], &Utc, false), "Nothing recorded around 15:00");
+    }
+
     #[test]
     fn test_config_default() {
         let config = TimeMachineConfig::default();
         assert_eq!(config.capture_interval_secs, 10);
         assert_eq!(config.max_storage_mb, 5000);
         assert_eq!(config.retention_days, 30);
+        assert_eq!(config.downsample_after_days, 30);
+        assert_eq!(config.delete_after_days, 365);
+        assert!(!config.quantize_embeddings);
+        assert!(config.redaction.emails && config.redaction.card_numbers);
+        assert!(!config.redaction.faces);
+        assert_eq!(config.dedup_similarity, 0.95);
+        assert_eq!(config.dedup_history, 1);
+        assert_eq!(config.search_ef, 64);
     }
 
     #[test]
@@ -357,5 +1168,6 @@ mod tests {
         let stats = TimeMachineStats::default();
         assert_eq!(stats.total_captures, 0);
         assert_eq!(stats.successful_captures, 0);
+        assert_eq!(stats.skipped_duplicates, 0);
     }
 }
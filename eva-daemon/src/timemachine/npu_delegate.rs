@@ -1,18 +1,287 @@
 //! NPU Delegate - Hardware acceleration for ONNX models
 //! Uses ONNX Runtime when available, otherwise provides stub implementation
+//!
+//! Models too large for the accelerator (or rejected by it with an
+//! out-of-memory error) are retried on a CPU-only environment.
+//!
+//! On Redox the userspace NPU driver exposes an `npu:` scheme; when it is
+//! there, models are run through its job queue (`npu:submit`) instead, and
+//! a job that fails falls back to the CPU path for that call.
 
 #[cfg(feature = "timemachine")]
 use ort::{Environment, ExecutionProvider, Session, SessionBuilder};
 
-#[cfg(feature = "timemachine")]
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Largest model (bytes on disk) placed on the accelerator; mirrors the
+/// driver's per-job budget on Meteor Lake
+pub const NPU_MODEL_BUDGET_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Where a model runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Placement {
+    Npu,
+    Cpu,
+}
+
+/// Whether an accelerator error means the model did not fit in device memory
+pub fn is_out_of_memory(err: &(dyn std::error::Error + 'static)) -> bool {
+    let msg = err.to_string().to_lowercase();
+    ["out of memory", "out_of_resources", "outofmemory"]
+        .iter()
+        .any(|needle| msg.contains(needle))
+}
+
+/// Run `attempt` on the NPU, falling back to the CPU for oversized models.
+///
+/// Models over `budget` skip the NPU entirely; anything else is tried on
+/// the NPU first and retried once on the CPU if the device runs out of
+/// memory. Other errors are returned as-is.
+pub fn with_cpu_fallback<T>(
+    model_bytes: u64,
+    budget: u64,
+    mut attempt: impl FnMut(Placement) -> Result<T, Box<dyn std::error::Error>>,
+) -> Result<(T, Placement), Box<dyn std::error::Error>> {
+    if model_bytes > budget {
+        println!(
+            "[NPU] Model is {} MB, over the {} MB NPU budget - using CPU",
+            model_bytes / (1024 * 1024),
+            budget / (1024 * 1024)
+        );
+        return attempt(Placement::Cpu).map(|t| (t, Placement::Cpu));
+    }
+
+    match attempt(Placement::Npu) {
+        Ok(t) => Ok((t, Placement::Npu)),
+        Err(e) if is_out_of_memory(e.as_ref()) => {
+            println!("[NPU] Out of device memory ({}), retrying on CPU", e);
+            attempt(Placement::Cpu).map(|t| (t, Placement::Cpu))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Driver model cache endpoint (`npu:model/<sha256>`)
+const DRIVER_MODEL_SCHEME: &str = "/scheme/npu/model";
+
+/// Whether the NPU driver already holds a model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DriverCache {
+    /// Cached (possibly from before a driver restart): submit by hash
+    Warm,
+    /// Unknown or corrupt on the driver side: stream the bytes
+    Cold,
+    /// No `npu:` scheme on this system
+    Unavailable,
+}
+
+/// Content hash the driver uses to identify a model
+pub fn model_hash_hex(model: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(model).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parse the driver's "warm" / "cold" answer
+pub fn parse_cache_reply(reply: &str) -> DriverCache {
+    match reply.trim() {
+        "warm" => DriverCache::Warm,
+        _ => DriverCache::Cold,
+    }
+}
+
+/// Ask the driver whether `model` needs to be uploaded
+pub fn driver_cache_state(model: &[u8]) -> DriverCache {
+    let path = format!("{}/{}", DRIVER_MODEL_SCHEME, model_hash_hex(model));
+    match std::fs::read_to_string(path) {
+        Ok(reply) => parse_cache_reply(&reply),
+        Err(_) => DriverCache::Unavailable,
+    }
+}
+
+/// Make sure the driver holds `model`, uploading it only on a cold cache
+pub fn ensure_model_on_driver(model: &[u8]) -> Result<DriverCache, Box<dyn std::error::Error>> {
+    let state = driver_cache_state(model);
+    if state == DriverCache::Cold {
+        let path = format!("{}/{}", DRIVER_MODEL_SCHEME, model_hash_hex(model));
+        std::fs::write(path, model)?;
+        println!("[NPU] Uploaded model to driver cache ({} MB)", model.len() / (1024 * 1024));
+    }
+    Ok(state)
+}
+
+/// Root of the driver's scheme; present only while the driver runs
+const DRIVER_SCHEME: &str = "/scheme/npu";
+/// Job endpoint (`npu:submit`)
+const DRIVER_SUBMIT_SCHEME: &str = "/scheme/npu/submit";
+
+/// Size of the header that starts every job
+pub const JOB_HEADER_SIZE: usize = 16;
+/// `InferenceOp::Infer` in the driver
+const OP_INFER: u32 = 0x0001;
+/// The driver's "device out of memory" errno (same value on Redox and Linux)
+const ENOMEM: i32 = 12;
+
+/// How often a running job is polled for its output
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(2);
+/// Longest a job may take before it counts as failed
+const JOB_TIMEOUT: Duration = Duration::from_secs(10);
+/// Resubmissions while the driver's command queue is full
+const QUEUE_FULL_RETRIES: u32 = 5;
+
+/// Which path inference is taking
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Backend {
+    /// Jobs go to the driver's `npu:` scheme
+    Npu,
+    /// ONNX Runtime (or the built-in fallbacks) on the CPU
+    #[default]
+    Cpu,
+}
+
+/// Job header: opcode, model size, input size, output size (little-endian u32)
+pub fn job_header(model_size: usize, input_size: usize, output_size: usize) -> Result<[u8; JOB_HEADER_SIZE], Box<dyn std::error::Error>> {
+    let mut header = [0u8; JOB_HEADER_SIZE];
+    for (i, field) in [model_size, input_size, output_size].into_iter().enumerate() {
+        let field = u32::try_from(field).map_err(|_| "NPU job buffer over 4 GB")?;
+        header[4 + i * 4..8 + i * 4].copy_from_slice(&field.to_le_bytes());
+    }
+    header[..4].copy_from_slice(&OP_INFER.to_le_bytes());
+    Ok(header)
+}
+
+/// One open job on the driver
+pub trait JobHandle: Read + Write {}
+impl<T: Read + Write> JobHandle for T {}
+
+type Opener = Box<dyn Fn() -> io::Result<Box<dyn JobHandle>> + Send + Sync>;
+
+/// Submits jobs to the driver and waits for their output
+///
+/// Each job is one handle: the header, the model bytes and the input bytes
+/// are written, then reads return `EAGAIN` until the output is ready.
+pub struct SchemeTransport {
+    open: Opener,
+    timeout: Duration,
+}
+
+impl SchemeTransport {
+    /// The driver's job endpoint, if the `npu:` scheme exists
+    pub fn detect() -> Option<Self> {
+        if !std::path::Path::new(DRIVER_SCHEME).exists() {
+            return None;
+        }
+        Some(Self::with_opener(Box::new(|| {
+            let file = std::fs::OpenOptions::new().read(true).write(true).open(DRIVER_SUBMIT_SCHEME)?;
+            Ok(Box::new(file) as Box<dyn JobHandle>)
+        })))
+    }
+
+    /// Jobs go to whatever `open` connects to (tests use a mock driver)
+    pub fn with_opener(open: Opener) -> Self {
+        Self { open, timeout: JOB_TIMEOUT }
+    }
+
+    /// Run `model` on `input` and return its `output_size` bytes of output
+    pub fn submit(&self, model: &[u8], input: &[u8], output_size: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let header = job_header(model.len(), input.len(), output_size)?;
+        for _ in 0..QUEUE_FULL_RETRIES {
+            let mut handle = (self.open)()?;
+            handle.write_all(&header)?;
+            handle.write_all(model)?;
+            // The job is queued with its last input byte; a full queue
+            // refuses it and it has to be sent again on a new handle
+            match handle.write_all(input) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(JOB_POLL_INTERVAL);
+                    continue;
+                }
+                written => written.map_err(job_error)?,
+            }
+            return self.read_output(handle.as_mut(), output_size);
+        }
+        Err("NPU command queue stayed full".into())
+    }
+
+    fn read_output(&self, handle: &mut dyn JobHandle, output_size: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let deadline = Instant::now() + self.timeout;
+        let mut output = Vec::with_capacity(output_size);
+        let mut buf = [0u8; 4096];
+        loop {
+            match handle.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => output.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    if Instant::now() > deadline {
+                        return Err(format!("NPU job timed out after {}s", self.timeout.as_secs()).into());
+                    }
+                    std::thread::sleep(JOB_POLL_INTERVAL);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(job_error(e)),
+            }
+        }
+        if output.len() != output_size {
+            return Err(format!("NPU job returned {} bytes, expected {}", output.len(), output_size).into());
+        }
+        Ok(output)
+    }
+}
+
+/// Driver errno as an error `is_out_of_memory` recognises
+fn job_error(e: io::Error) -> Box<dyn std::error::Error> {
+    match e.raw_os_error() {
+        Some(ENOMEM) => "NPU out of memory".into(),
+        _ => format!("NPU job failed: {}", e).into(),
+    }
+}
+
+/// A model run through the driver, sent along with every job
+pub struct NpuModel {
+    path: String,
+    bytes: Vec<u8>,
+    transport: Arc<SchemeTransport>,
+    /// Cleared while jobs are failing, so the delegate reports the CPU path
+    healthy: Arc<AtomicBool>,
+}
+
+impl NpuModel {
+    /// Output of one job, or `None` when the NPU could not run it and the
+    /// caller should use its CPU path
+    pub fn run(&self, input: &[u8], output_size: usize) -> Option<Vec<u8>> {
+        match self.transport.submit(&self.bytes, input, output_size) {
+            Ok(output) => {
+                self.healthy.store(true, Ordering::Relaxed);
+                Some(output)
+            }
+            Err(e) => {
+                if self.healthy.swap(false, Ordering::Relaxed) {
+                    println!("[NPU] {} failed on the NPU ({}), falling back to CPU", self.path, e);
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Little-endian f32s, as tensors cross the driver boundary
+pub fn f32_from_le(bytes: &[u8]) -> Vec<f32> {
+    bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()
+}
 
 /// NPU Delegate for hardware-accelerated inference
 pub struct NPUDelegate {
     #[cfg(feature = "timemachine")]
     env: Arc<Environment>,
-    #[cfg(not(feature = "timemachine"))]
-    _phantom: (),
+    /// CPU-only environment for models that do not fit on the NPU
+    #[cfg(feature = "timemachine")]
+    cpu_env: Arc<Environment>,
+    npu_budget: u64,
+    /// The driver's job queue, when the `npu:` scheme is present
+    transport: Option<Arc<SchemeTransport>>,
+    healthy: Arc<AtomicBool>,
 }
 
 impl NPUDelegate {
@@ -30,24 +299,237 @@ impl NPUDelegate {
 
         let env = builder.build()?.into_arc();
 
+        let cpu_env = Environment::builder()
+            .with_name("EVA-TimeMachine-CPU")
+            .with_execution_providers([ExecutionProvider::CPU(Default::default())])
+            .build()?
+            .into_arc();
+
         println!("[NPU] Initialized ONNX Runtime environment");
 
-        Ok(Self { env })
+        Ok(Self { env, cpu_env, npu_budget: NPU_MODEL_BUDGET_BYTES, transport: Self::detect_driver(), healthy: Arc::default() })
     }
 
     #[cfg(not(feature = "timemachine"))]
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
         println!("[NPU] Stub mode - timemachine feature not enabled");
-        Ok(Self { _phantom: () })
+        Ok(Self { npu_budget: NPU_MODEL_BUDGET_BYTES, transport: Self::detect_driver(), healthy: Arc::default() })
+    }
+
+    fn detect_driver() -> Option<Arc<SchemeTransport>> {
+        let transport = SchemeTransport::detect()?;
+        println!("[NPU] Driver found, offloading inference to npu:");
+        Some(Arc::new(transport))
+    }
+
+    /// Where inference is currently running
+    pub fn backend(&self) -> Backend {
+        match self.transport {
+            Some(_) if self.healthy.load(Ordering::Relaxed) => Backend::Npu,
+            _ => Backend::Cpu,
+        }
+    }
+
+    /// `model_path` for running through the driver; `None` without a
+    /// driver, or for a missing or oversized model
+    pub fn npu_model(&self, model_path: &str) -> Option<NpuModel> {
+        let transport = Arc::clone(self.transport.as_ref()?);
+        let bytes = std::fs::read(model_path).ok()?;
+        if bytes.len() as u64 > self.npu_budget {
+            println!("[NPU] {} is over the NPU budget - using CPU", model_path);
+            return None;
+        }
+        // Warm the driver's cache; jobs still carry the model
+        if let Err(e) = ensure_model_on_driver(&bytes) {
+            println!("[NPU] Driver model upload failed ({}), continuing", e);
+        }
+        self.healthy.store(true, Ordering::Relaxed);
+        Some(NpuModel { path: model_path.to_string(), bytes, transport, healthy: Arc::clone(&self.healthy) })
+    }
+
+    /// Override the model size above which sessions go straight to the CPU
+    pub fn set_npu_budget(&mut self, bytes: u64) {
+        self.npu_budget = bytes;
     }
 
     #[cfg(feature = "timemachine")]
     pub fn create_session(&self, model_path: &str) -> Result<Session, Box<dyn std::error::Error>> {
-        let session = SessionBuilder::new(&self.env)?
-            .with_optimization_level(ort::GraphOptimizationLevel::Level3)?
-            .with_intra_threads(4)?
-            .with_model_from_file(model_path)?;
+        let model_bytes = std::fs::metadata(model_path)?.len();
+
+        // Stream the model to the Intel NPU driver only if its cache is cold
+        if model_bytes <= self.npu_budget && std::path::Path::new(DRIVER_MODEL_SCHEME).exists() {
+            match ensure_model_on_driver(&std::fs::read(model_path)?) {
+                Ok(DriverCache::Warm) => println!("[NPU] {} already cached by the driver", model_path),
+                Ok(_) => {}
+                Err(e) => println!("[NPU] Driver model upload failed ({}), continuing", e),
+            }
+        }
 
+        let (session, placement) = with_cpu_fallback(model_bytes, self.npu_budget, |placement| {
+            let env = match placement {
+                Placement::Npu => &self.env,
+                Placement::Cpu => &self.cpu_env,
+            };
+            let session = SessionBuilder::new(env)?
+                .with_optimization_level(ort::GraphOptimizationLevel::Level3)?
+                .with_intra_threads(4)?
+                .with_model_from_file(model_path)?;
+            Ok(session)
+        })?;
+
+        println!("[NPU] Loaded {} on {:?}", model_path, placement);
         Ok(session)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A job as the mock driver received it
+    #[cfg(unix)]
+    #[derive(Debug, PartialEq)]
+    struct ReceivedJob {
+        opcode: u32,
+        model: Vec<u8>,
+        input: Vec<u8>,
+        output_size: usize,
+    }
+
+    /// Each handle is a socket to a thread that speaks the driver's job
+    /// framing: it reads the header and exactly the payload it announces,
+    /// then answers with the input reversed and padded to the output size.
+    /// `broken` drivers hang up instead of answering.
+    #[cfg(unix)]
+    fn mock_driver(broken: bool) -> (SchemeTransport, Arc<std::sync::Mutex<Vec<ReceivedJob>>>) {
+        use std::os::unix::net::UnixStream;
+        let jobs = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let received = Arc::clone(&jobs);
+        let transport = SchemeTransport::with_opener(Box::new(move || {
+            let (client, mut driver) = UnixStream::pair()?;
+            let jobs = Arc::clone(&received);
+            std::thread::spawn(move || {
+                let mut header = [0u8; JOB_HEADER_SIZE];
+                driver.read_exact(&mut header).unwrap();
+                let field = |i: usize| u32::from_le_bytes(header[i * 4..i * 4 + 4].try_into().unwrap()) as usize;
+                let mut model = vec![0u8; field(1)];
+                let mut input = vec![0u8; field(2)];
+                driver.read_exact(&mut model).unwrap();
+                driver.read_exact(&mut input).unwrap();
+                let output_size = field(3);
+
+                let mut output: Vec<u8> = input.iter().rev().copied().collect();
+                output.resize(output_size, 0);
+                jobs.lock().unwrap().push(ReceivedJob { opcode: field(0) as u32, model, input, output_size });
+                if !broken {
+                    driver.write_all(&output).unwrap();
+                }
+            });
+            Ok(Box::new(client) as Box<dyn JobHandle>)
+        }));
+        (transport, jobs)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_jobs_are_framed_for_the_driver() {
+        let (transport, jobs) = mock_driver(false);
+        let output = transport.submit(b"onnx-model", &[1, 2, 3], 8).unwrap();
+        assert_eq!(output, vec![3, 2, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            jobs.lock().unwrap()[0],
+            ReceivedJob { opcode: OP_INFER, model: b"onnx-model".to_vec(), input: vec![1, 2, 3], output_size: 8 }
+        );
+
+        assert_eq!(
+            job_header(10, 3, 8).unwrap(),
+            [1, 0, 0, 0, 10, 0, 0, 0, 3, 0, 0, 0, 8, 0, 0, 0]
+        );
+        assert_eq!(f32_from_le(&1.5f32.to_le_bytes()), vec![1.5]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failed_job_falls_back_to_cpu() {
+        let delegate = |broken: bool| {
+            let (transport, _) = mock_driver(broken);
+            let mut npu = NPUDelegate::new().unwrap();
+            npu.transport = Some(Arc::new(transport));
+            npu.healthy.store(true, Ordering::Relaxed);
+            npu
+        };
+        let model = |npu: &NPUDelegate| NpuModel {
+            path: "models/test.onnx".to_string(),
+            bytes: b"onnx-model".to_vec(),
+            transport: Arc::clone(npu.transport.as_ref().unwrap()),
+            healthy: Arc::clone(&npu.healthy),
+        };
+
+        let working = delegate(false);
+        assert_eq!(model(&working).run(&[7], 1), Some(vec![7]));
+        assert_eq!(working.backend(), Backend::Npu);
+
+        let broken = delegate(true);
+        assert_eq!(model(&broken).run(&[7], 1), None);
+        assert_eq!(broken.backend(), Backend::Cpu);
+
+        // No driver at all
+        assert_eq!(NPUDelegate::new().unwrap().npu_model("models/test.onnx").map(|_| ()), None);
+    }
+
+    #[test]
+    fn test_fits_runs_on_npu() {
+        let (value, placement) = with_cpu_fallback(10, 100, |p| Ok(p)).unwrap();
+        assert_eq!(value, Placement::Npu);
+        assert_eq!(placement, Placement::Npu);
+    }
+
+    #[test]
+    fn test_oversized_model_skips_npu() {
+        let mut tried = Vec::new();
+        let (_, placement) = with_cpu_fallback(200, 100, |p| {
+            tried.push(p);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(placement, Placement::Cpu);
+        assert_eq!(tried, vec![Placement::Cpu]);
+    }
+
+    #[test]
+    fn test_device_oom_retries_on_cpu() {
+        let mut tried = Vec::new();
+        let (_, placement) = with_cpu_fallback(10, 100, |p| {
+            tried.push(p);
+            match p {
+                Placement::Npu => Err("NPU out of memory: job needs 900 MB, device budget is 512 MB".into()),
+                Placement::Cpu => Ok(()),
+            }
+        })
+        .unwrap();
+        assert_eq!(placement, Placement::Cpu);
+        assert_eq!(tried, vec![Placement::Npu, Placement::Cpu]);
+    }
+
+    #[test]
+    fn test_driver_cache_protocol() {
+        assert_eq!(
+            model_hash_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(parse_cache_reply("warm\n"), DriverCache::Warm);
+        assert_eq!(parse_cache_reply("cold\n"), DriverCache::Cold);
+        assert_eq!(parse_cache_reply(""), DriverCache::Cold);
+    }
+
+    #[test]
+    fn test_other_errors_are_not_retried() {
+        let mut attempts = 0;
+        let result: Result<((), Placement), _> = with_cpu_fallback(10, 100, |_| {
+            attempts += 1;
+            Err("invalid model graph".into())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+}
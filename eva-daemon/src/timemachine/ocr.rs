@@ -4,12 +4,261 @@ use std::error::Error;
 #[cfg(feature = "timemachine")]
 use ort::{Session, Value};
 
+use super::npu_delegate::NpuModel;
+
+const MODEL_PATH: &str = "models/ocr-model.onnx";
+/// Recognition model input: one line of dark text on white, scaled to this
+/// height and padded to this width
+const LINE_HEIGHT: u32 = 32;
+const LINE_WIDTH: u32 = 256;
+/// Output steps per line (one per 4 input columns)
+const TIME_STEPS: usize = LINE_WIDTH as usize / 4;
+
+/// Characters the recognition model emits. Class 0 is the CTC blank and
+/// class `i + 1` is the `i`th character here.
+const CHARSET: &str = concat!(
+    " !\"#$%&'()*+,-./0123456789:;<=>?@",
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`",
+    "abcdefghijklmnopqrstuvwxyz{|}~",
+    "ÀÁÂÃÇÉÊÍÓÔÕÚàáâãçéêíóôõú",
+);
+
+/// Band heights that can be a line of text; thinner bands are rules and
+/// borders, taller ones pictures
+const MIN_LINE_HEIGHT: u32 = 6;
+const MAX_LINE_HEIGHT: u32 = 96;
+/// Blank rows bridged inside one line (i dots, accents)
+const MAX_ROW_GAP: u32 = 2;
+/// Blank columns, in line heights, that separate two boxes on one band
+const COLUMN_GAP_LINES: u32 = 2;
+
+/// Pixel rectangle in screenshot coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// One recognized line of text
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrLine {
+    pub text: String,
+    /// Where the line is on screen (None when the engine cannot localize it)
+    pub bbox: Option<BoundingBox>,
+    pub confidence: f32,
+}
+
+/// Structured OCR output: lines with their positions
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OcrResult {
+    pub lines: Vec<OcrLine>,
+}
+
+impl OcrResult {
+    /// All line texts joined, as stored and indexed
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|l| l.text.as_str())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Which pixels are text
+struct InkMask {
+    width: u32,
+    height: u32,
+    ink: Vec<bool>,
+    /// Dark text on a light background
+    dark_text: bool,
+}
+
+impl InkMask {
+    fn at(&self, x: u32, y: u32) -> bool {
+        self.ink[(y * self.width + x) as usize]
+    }
+
+    /// Whether column `x` has ink between rows `top` and `bottom` (inclusive)
+    fn column_has_ink(&self, x: u32, top: u32, bottom: u32) -> bool {
+        (top..=bottom).any(|y| self.at(x, y))
+    }
+}
+
+/// Threshold between background and text that best separates the two
+/// (Otsu's method). When several levels separate equally well (a screen
+/// with few distinct shades) the middle one is taken.
+fn otsu_threshold(gray: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for p in gray.pixels() {
+        histogram[p[0] as usize] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    let sum_all: f64 = histogram.iter().enumerate().map(|(i, &n)| i as f64 * n as f64).sum();
+
+    let (mut weight_low, mut sum_low) = (0u64, 0f64);
+    let (mut best, mut best_until, mut best_variance) = (0usize, 0usize, -1.0);
+    for (level, &count) in histogram.iter().enumerate() {
+        weight_low += count;
+        if weight_low == 0 {
+            continue;
+        }
+        let weight_high = total - weight_low;
+        if weight_high == 0 {
+            break;
+        }
+        sum_low += level as f64 * count as f64;
+        let mean_low = sum_low / weight_low as f64;
+        let mean_high = (sum_all - sum_low) / weight_high as f64;
+        let variance = weight_low as f64 * weight_high as f64 * (mean_low - mean_high).powi(2);
+        if variance > best_variance * (1.0 + 1e-9) {
+            best_variance = variance;
+            (best, best_until) = (level, level);
+        } else if variance >= best_variance * (1.0 - 1e-9) {
+            best_until = level;
+        }
+    }
+    ((best + best_until) / 2) as u8
+}
+
+/// Horizontal text lines, found where rows with ink run together and then
+/// split where a band has a wide blank stretch (side-by-side panes)
+fn segment_lines(mask: &InkMask) -> Vec<BoundingBox> {
+    let row_has_ink = |y: u32| (0..mask.width).any(|x| mask.at(x, y));
+
+    let mut bands = Vec::new();
+    let mut current: Option<(u32, u32)> = None;
+    for y in (0..mask.height).filter(|&y| row_has_ink(y)) {
+        current = match current {
+            Some((top, bottom)) if y - bottom - 1 <= MAX_ROW_GAP => Some((top, y)),
+            Some(band) => {
+                bands.push(band);
+                Some((y, y))
+            }
+            None => Some((y, y)),
+        };
+    }
+    bands.extend(current);
+
+    let mut lines = Vec::new();
+    for (top, bottom) in bands {
+        let height = bottom - top + 1;
+        if !(MIN_LINE_HEIGHT..=MAX_LINE_HEIGHT).contains(&height) {
+            continue;
+        }
+        let max_gap = height * COLUMN_GAP_LINES;
+        let mut run: Option<(u32, u32)> = None;
+        for x in (0..mask.width).filter(|&x| mask.column_has_ink(x, top, bottom)) {
+            run = match run {
+                Some((left, right)) if x - right - 1 <= max_gap => Some((left, x)),
+                Some((left, right)) => {
+                    lines.push(BoundingBox { x: left, y: top, width: right - left + 1, height });
+                    Some((x, x))
+                }
+                None => Some((x, x)),
+            };
+        }
+        if let Some((left, right)) = run {
+            lines.push(BoundingBox { x: left, y: top, width: right - left + 1, height });
+        }
+    }
+    lines
+}
+
+/// Pieces of `line` narrow enough for the model once scaled to its input
+/// height, cut in the middle of the widest blank stretch near each limit
+/// so words stay whole
+fn split_for_model(mask: &InkMask, line: BoundingBox) -> Vec<BoundingBox> {
+    let max_width = (line.height * LINE_WIDTH / LINE_HEIGHT).max(1);
+    let bottom = line.y + line.height - 1;
+    let mut pieces = Vec::new();
+    let mut start = line.x;
+    let end = line.x + line.width;
+
+    while end - start > max_width {
+        let limit = start + max_width;
+        let mut best: Option<(u32, u32)> = None;
+        let mut blank_from = None;
+        for x in start + max_width / 2..limit {
+            if mask.column_has_ink(x, line.y, bottom) {
+                blank_from = None;
+                continue;
+            }
+            let from = *blank_from.get_or_insert(x);
+            if best.map_or(true, |(l, r)| x - from > r - l) {
+                best = Some((from, x));
+            }
+        }
+        let cut = best.map_or(limit, |(l, r)| (l + r + 1) / 2).max(start + 1);
+        pieces.push(BoundingBox { x: start, y: line.y, width: cut - start, height: line.height });
+        start = cut;
+    }
+    pieces.push(BoundingBox { x: start, y: line.y, width: end - start, height: line.height });
+    pieces
+}
+
+/// `piece` as model input: dark text on white in [0, 1], scaled to
+/// `LINE_HEIGHT` and padded with white to `LINE_WIDTH`
+fn line_pixels(gray: &GrayImage, piece: BoundingBox, dark_text: bool) -> Vec<f32> {
+    let crop = image::imageops::crop_imm(gray, piece.x, piece.y, piece.width, piece.height).to_image();
+    let width = (piece.width * LINE_HEIGHT / piece.height).clamp(1, LINE_WIDTH);
+    let scaled = image::imageops::resize(&crop, width, LINE_HEIGHT, image::imageops::FilterType::Triangle);
+
+    let mut pixels = vec![1.0f32; (LINE_HEIGHT * LINE_WIDTH) as usize];
+    for (x, y, p) in scaled.enumerate_pixels() {
+        let value = p[0] as f32 / 255.0;
+        pixels[(y * LINE_WIDTH + x) as usize] = if dark_text { value } else { 1.0 - value };
+    }
+    pixels
+}
+
+/// Greedy CTC decoding of `TIME_STEPS` rows of logits: the likeliest class
+/// per step, repeats collapsed, blanks dropped. Confidence is the mean
+/// probability of the characters kept.
+pub fn ctc_decode(logits: &[f32]) -> (String, f32) {
+    let charset: Vec<char> = CHARSET.chars().collect();
+    let classes = charset.len() + 1;
+
+    let mut text = String::new();
+    let mut probabilities = Vec::new();
+    let mut previous = 0;
+    for step in logits.chunks_exact(classes) {
+        let (best, &score) = step
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap_or((0, &0.0));
+        if best != 0 && best != previous {
+            text.push(charset[best - 1]);
+            let total: f32 = step.iter().map(|&s| (s - score).exp()).sum();
+            probabilities.push(1.0 / total);
+        }
+        previous = best;
+    }
+
+    let confidence = if probabilities.is_empty() {
+        0.0
+    } else {
+        probabilities.iter().sum::<f32>() / probabilities.len() as f32
+    };
+    (text.trim().to_string(), confidence)
+}
+
 /// OCR Engine for extracting text from screenshots
 ///
-/// Uses ONNX model when available, falls back to edge-based text region detection
+/// The screenshot is binarized, split into text lines by row and column
+/// projections, and each line is read by a recognition model (on the NPU
+/// driver or ONNX Runtime) whose output is CTC-decoded. Without a model the
+/// lines still come back as boxes, and the active window's title is the
+/// searchable text.
 pub struct OCREngine {
     #[cfg(feature = "timemachine")]
     session: Option<Session>,
+    /// The model on the NPU driver, tried before `session`
+    npu_model: Option<NpuModel>,
     /// Minimum contrast threshold for text detection
     contrast_threshold: u8,
 }
@@ -17,28 +266,30 @@ pub struct OCREngine {
 impl OCREngine {
     #[cfg(feature = "timemachine")]
     pub async fn new(npu: &super::npu_delegate::NPUDelegate) -> Result<Self, Box<dyn Error>> {
-        // Try to load ONNX model, fall back to heuristic if not available
-        let session = match npu.create_session("models/ocr-model.onnx") {
+        // Try to load ONNX model, fall back to line boxes if not available
+        let session = match npu.create_session(MODEL_PATH) {
             Ok(s) => {
                 println!("[OCR] ONNX model loaded successfully");
                 Some(s)
             }
             Err(e) => {
-                println!("[OCR] ONNX model not available ({}), using heuristic extraction", e);
+                println!("[OCR] ONNX model not available ({}), indexing window titles only", e);
                 None
             }
         };
 
         Ok(Self {
             session,
+            npu_model: npu.npu_model(MODEL_PATH),
             contrast_threshold: 50,
         })
     }
 
     #[cfg(not(feature = "timemachine"))]
-    pub async fn new(_npu: &super::npu_delegate::NPUDelegate) -> Result<Self, Box<dyn Error>> {
+    pub async fn new(npu: &super::npu_delegate::NPUDelegate) -> Result<Self, Box<dyn Error>> {
         println!("[OCR] Running without ONNX (timemachine feature disabled)");
         Ok(Self {
+            npu_model: npu.npu_model(MODEL_PATH),
             contrast_threshold: 50,
         })
     }
@@ -46,93 +297,97 @@ impl OCREngine {
     /// Extract text from image
     ///
     /// Returns extracted text content from the screenshot
-    pub fn extract_text(&self, image: &DynamicImage) -> Result<String, Box<dyn Error>> {
-        #[cfg(feature = "timemachine")]
-        if let Some(ref session) = self.session {
-            return self.extract_with_onnx(session, image);
-        }
-
-        // Fallback: Heuristic text extraction
-        self.extract_with_heuristics(image)
+    pub fn extract_text(&self, image: &DynamicImage, window_title: Option<&str>) -> Result<String, Box<dyn Error>> {
+        Ok(self.extract(image, window_title)?.text())
     }
 
-    #[cfg(feature = "timemachine")]
-    fn extract_with_onnx(&self, session: &Session, image: &DynamicImage) -> Result<String, Box<dyn Error>> {
-        let input_tensor = self.preprocess_image(image)?;
-
-        let outputs = session.run(vec![input_tensor])?;
-
-        self.decode_onnx_output(&outputs)
-    }
+    /// Extract text lines with their bounding boxes
+    ///
+    /// `window_title` (the active window's, if known) comes first: it is
+    /// exact and often names the document on screen.
+    pub fn extract(&self, image: &DynamicImage, window_title: Option<&str>) -> Result<OcrResult, Box<dyn Error>> {
+        let mut lines = Vec::new();
+        if let Some(title) = window_title.map(str::trim).filter(|t| !t.is_empty()) {
+            lines.push(OcrLine { text: title.to_string(), bbox: None, confidence: 1.0 });
+        }
 
-    #[cfg(feature = "timemachine")]
-    fn preprocess_image(&self, image: &DynamicImage) -> Result<Value, Box<dyn Error>> {
-        // Resize to model input size
-        let resized = image.resize_exact(224, 224, image::imageops::FilterType::Lanczos3);
-
-        let rgb = resized.to_rgb8();
-        let pixels: Vec<f32> = rgb
-            .pixels()
-            .flat_map(|p| vec![p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0])
-            .collect();
+        let gray = image.to_luma8();
+        let Some(mask) = self.binarize(&gray) else {
+            return Ok(OcrResult { lines });
+        };
 
-        // Shape: [Batch, Channel, Height, Width]
-        let shape = vec![1, 3, 224, 224];
-        let tensor = Value::from_array((shape, pixels))?;
+        let can_read = self.has_model();
+        for bbox in segment_lines(&mask) {
+            let mut words = Vec::new();
+            let mut confidence = 0.0;
+            if can_read {
+                let pieces = split_for_model(&mask, bbox);
+                let count = pieces.len() as f32;
+                for piece in pieces {
+                    let Some(logits) = self.recognize(&line_pixels(&gray, piece, mask.dark_text)) else {
+                        break;
+                    };
+                    let (text, piece_confidence) = ctc_decode(&logits);
+                    words.push(text);
+                    confidence += piece_confidence / count;
+                }
+            }
+            let text = words.iter().filter(|w| !w.is_empty()).cloned().collect::<Vec<_>>().join(" ");
+            lines.push(OcrLine { text, bbox: Some(bbox), confidence });
+        }
 
-        Ok(tensor)
+        Ok(OcrResult { lines })
     }
 
-    #[cfg(feature = "timemachine")]
-    fn decode_onnx_output(&self, outputs: &[Value]) -> Result<String, Box<dyn Error>> {
-        // Real implementation would decode CTC output here
-        // For now, try to extract from tensor
-        if outputs.is_empty() {
-            return Ok(String::new());
+    /// Ink mask of the screenshot, `None` for a flat one (nothing to read)
+    fn binarize(&self, gray: &GrayImage) -> Option<InkMask> {
+        let (width, height) = gray.dimensions();
+        if width < 3 || height < 3 || self.calculate_text_density(gray) == 0.0 {
+            return None;
         }
-
-        // Placeholder: In production, implement proper CTC decoding
-        Ok("[ONNX OCR Output]".to_string())
+        let threshold = otsu_threshold(gray);
+        // The background is most of the screen, so the mean sits on its side
+        let dark_text = self.analyze_image_stats(gray).mean_brightness > threshold as f64;
+        let ink = gray.pixels().map(|p| (p[0] <= threshold) == dark_text).collect();
+        Some(InkMask { width, height, ink, dark_text })
     }
 
-    /// Heuristic-based text extraction (fallback when ONNX not available)
-    ///
-    /// Uses image analysis to identify text-like regions and extract
-    /// relevant information about screen content
-    fn extract_with_heuristics(&self, image: &DynamicImage) -> Result<String, Box<dyn Error>> {
-        let (width, height) = image.dimensions();
-        let gray = image.to_luma8();
-
-        let mut extracted_info = Vec::new();
-
-        // 1. Analyze image statistics
-        let stats = self.analyze_image_stats(&gray);
-        extracted_info.push(format!("Screen: {}x{}", width, height));
-
-        // 2. Detect high-contrast regions (likely text areas)
-        let text_density = self.calculate_text_density(&gray);
-        extracted_info.push(format!("Text density: {:.1}%", text_density * 100.0));
-
-        // 3. Identify dominant colors (UI context)
-        let dominant_colors = self.identify_dominant_regions(image);
-        if !dominant_colors.is_empty() {
-            extracted_info.push(format!("UI regions: {}", dominant_colors.join(", ")));
+    fn has_model(&self) -> bool {
+        #[cfg(feature = "timemachine")]
+        if self.session.is_some() {
+            return true;
         }
+        self.npu_model.is_some()
+    }
 
-        // 4. Edge detection for text-like patterns
-        let edge_density = self.calculate_edge_density(&gray);
-        if edge_density > 0.1 {
-            extracted_info.push("High detail content detected".to_string());
+    /// Logits for one line piece, from the NPU if it can run it, else ONNX
+    fn recognize(&self, pixels: &[f32]) -> Option<Vec<f32>> {
+        let classes = CHARSET.chars().count() + 1;
+        if let Some(ref model) = self.npu_model {
+            let input: Vec<u8> = pixels.iter().flat_map(|p| p.to_le_bytes()).collect();
+            if let Some(output) = model.run(&input, TIME_STEPS * classes * 4) {
+                return Some(super::npu_delegate::f32_from_le(&output));
+            }
         }
 
-        // 5. Brightness analysis (dark mode detection)
-        if stats.mean_brightness < 80.0 {
-            extracted_info.push("Dark theme".to_string());
-        } else if stats.mean_brightness > 200.0 {
-            extracted_info.push("Light theme".to_string());
+        #[cfg(feature = "timemachine")]
+        if let Some(ref session) = self.session {
+            match self.recognize_with_onnx(session, pixels) {
+                Ok(logits) => return Some(logits),
+                Err(e) => println!("[OCR] Line recognition failed: {}", e),
+            }
         }
+        None
+    }
 
-        Ok(extracted_info.join(" | "))
+    #[cfg(feature = "timemachine")]
+    fn recognize_with_onnx(&self, session: &Session, pixels: &[f32]) -> Result<Vec<f32>, Box<dyn Error>> {
+        // Shape: [Batch, Channel, Height, Width]
+        let shape = vec![1, 1, LINE_HEIGHT as usize, LINE_WIDTH as usize];
+        let input = Value::from_array((shape, pixels.to_vec()))?;
+        let outputs = session.run(vec![input])?;
+        let logits = outputs.first().ok_or("OCR model returned no output")?.try_extract::<f32>()?;
+        Ok(logits.view().iter().copied().collect())
     }
 
     /// Analyze basic image statistics
@@ -160,7 +415,6 @@ impl OCREngine {
         // Simple Sobel-like edge detection
         for y in 1..height - 1 {
             for x in 1..width - 1 {
-                let p = gray.get_pixel(x, y)[0] as i32;
                 let left = gray.get_pixel(x - 1, y)[0] as i32;
                 let right = gray.get_pixel(x + 1, y)[0] as i32;
                 let up = gray.get_pixel(x, y - 1)[0] as i32;
@@ -179,66 +433,6 @@ impl OCREngine {
         let total_pixels = (width - 2) * (height - 2);
         edge_pixels as f64 / total_pixels as f64
     }
-
-    /// Calculate overall edge density
-    fn calculate_edge_density(&self, gray: &GrayImage) -> f64 {
-        self.calculate_text_density(gray)
-    }
-
-    /// Identify dominant color regions
-    fn identify_dominant_regions(&self, image: &DynamicImage) -> Vec<String> {
-        let rgb = image.to_rgb8();
-        let (width, height) = rgb.dimensions();
-
-        // Sample pixels for color analysis
-        let sample_step = 20;
-        let mut light_count = 0;
-        let mut dark_count = 0;
-        let mut blue_count = 0;
-        let mut red_count = 0;
-
-        for y in (0..height).step_by(sample_step) {
-            for x in (0..width).step_by(sample_step) {
-                let p = rgb.get_pixel(x, y);
-                let r = p[0] as u32;
-                let g = p[1] as u32;
-                let b = p[2] as u32;
-                let brightness = (r + g + b) / 3;
-
-                if brightness > 200 {
-                    light_count += 1;
-                } else if brightness < 50 {
-                    dark_count += 1;
-                }
-
-                if b > r + 30 && b > g + 30 {
-                    blue_count += 1;
-                }
-                if r > g + 30 && r > b + 30 {
-                    red_count += 1;
-                }
-            }
-        }
-
-        let total = (width / sample_step as u32) * (height / sample_step as u32);
-        let threshold = total / 10; // 10% threshold
-
-        let mut regions = Vec::new();
-        if light_count > threshold {
-            regions.push("white-bg".to_string());
-        }
-        if dark_count > threshold {
-            regions.push("dark-bg".to_string());
-        }
-        if blue_count > threshold {
-            regions.push("blue-accent".to_string());
-        }
-        if red_count > threshold {
-            regions.push("red-accent".to_string());
-        }
-
-        regions
-    }
 }
 
 struct ImageStats {
@@ -250,6 +444,54 @@ struct ImageStats {
 mod tests {
     use super::*;
 
+    fn engine() -> OCREngine {
+        OCREngine {
+            #[cfg(feature = "timemachine")]
+            session: None,
+            npu_model: None,
+            contrast_threshold: 50,
+        }
+    }
+
+    /// 5x7 glyphs for the fixture strings
+    fn glyph(c: char) -> [&'static str; 7] {
+        match c {
+            'A' => ["01110", "10001", "10001", "11111", "10001", "10001", "10001"],
+            'D' => ["11110", "10001", "10001", "10001", "10001", "10001", "11110"],
+            'E' => ["11111", "10000", "10000", "11110", "10000", "10000", "11111"],
+            'O' => ["01110", "10001", "10001", "10001", "10001", "10001", "01110"],
+            'R' => ["11110", "10001", "10001", "11110", "10100", "10010", "10001"],
+            'V' => ["10001", "10001", "10001", "10001", "10001", "01010", "00100"],
+            'X' => ["10001", "10001", "01010", "00100", "01010", "10001", "10001"],
+            _ => ["00000"; 7],
+        }
+    }
+
+    /// Fixture screenshot: `lines` drawn at `scale` pixels per glyph dot,
+    /// one line every 16 dots, on a light (or dark) background
+    fn render(lines: &[&str], scale: u32, dark_theme: bool) -> GrayImage {
+        let (background, ink) = if dark_theme { (30u8, 220u8) } else { (245u8, 20u8) };
+        let mut img = GrayImage::from_pixel(400, 40 + lines.len() as u32 * 16 * scale, Luma([background]));
+        for (row, line) in lines.iter().enumerate() {
+            let top = 20 + row as u32 * 16 * scale;
+            for (col, c) in line.chars().enumerate() {
+                let left = 10 + col as u32 * 6 * scale;
+                for (dy, bits) in glyph(c).iter().enumerate() {
+                    for (dx, bit) in bits.chars().enumerate() {
+                        if bit == '1' {
+                            for py in 0..scale {
+                                for px in 0..scale {
+                                    img.put_pixel(left + dx as u32 * scale + px, top + dy as u32 * scale + py, Luma([ink]));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        img
+    }
+
     #[test]
     fn test_image_stats() {
         // Create a simple test image
@@ -258,6 +500,7 @@ mod tests {
         let ocr = OCREngine {
             #[cfg(feature = "timemachine")]
             session: None,
+            npu_model: None,
             contrast_threshold: 50,
         };
 
@@ -268,17 +511,103 @@ mod tests {
     #[test]
     fn test_edge_detection() {
         // Create image with edges
-        let mut img = GrayImage::from_fn(100, 100, |x, _| {
+        let img = GrayImage::from_fn(100, 100, |x, _| {
             if x < 50 { Luma([0u8]) } else { Luma([255u8]) }
         });
 
         let ocr = OCREngine {
             #[cfg(feature = "timemachine")]
             session: None,
+            npu_model: None,
             contrast_threshold: 50,
         };
 
         let density = ocr.calculate_text_density(&img);
         assert!(density > 0.0); // Should detect the edge
     }
+
+    #[test]
+    fn test_lines_are_segmented_in_either_theme() {
+        for dark_theme in [false, true] {
+            let mut img = render(&["EVA", "REDOX"], 3, dark_theme);
+            // A one-pixel rule under everything is not a line
+            let rule_y = img.height() - 5;
+            for x in 0..img.width() {
+                img.put_pixel(x, rule_y, *img.get_pixel(12, 21));
+            }
+
+            let mask = engine().binarize(&img).unwrap();
+            assert_eq!(mask.dark_text, !dark_theme);
+            let lines = segment_lines(&mask);
+            assert_eq!(
+                lines,
+                vec![
+                    BoundingBox { x: 10, y: 20, width: 3 * (6 * 3 - 1), height: 21 },
+                    BoundingBox { x: 10, y: 68, width: 3 * (6 * 5 - 1), height: 21 },
+                ],
+                "dark theme: {}",
+                dark_theme
+            );
+        }
+    }
+
+    #[test]
+    fn test_wide_lines_are_cut_between_words() {
+        let img = render(&["EVA EVA EVA EVA EVA EVA EVA EVA EVA EVA"], 1, false);
+        let mask = engine().binarize(&img).unwrap();
+        let line = segment_lines(&mask)[0];
+        let pieces = split_for_model(&mask, line);
+        assert!(pieces.len() > 1);
+        assert_eq!(pieces.iter().map(|p| p.width).sum::<u32>(), line.width);
+        for piece in &pieces {
+            assert!(piece.width * LINE_HEIGHT / piece.height <= LINE_WIDTH);
+            // Each cut lands in a blank column
+            assert!(!mask.column_has_ink(piece.x, line.y, line.y + line.height - 1) || piece.x == line.x);
+        }
+    }
+
+    #[test]
+    fn test_ctc_decoding() {
+        let charset: Vec<char> = CHARSET.chars().collect();
+        let class = |c: char| charset.iter().position(|&x| x == c).unwrap() + 1;
+        let classes = charset.len() + 1;
+        // H H _ E L _ L O _ : repeats collapse unless a blank separates them
+        let steps = [class('H'), class('H'), 0, class('E'), class('L'), 0, class('L'), class('O'), 0];
+        let logits: Vec<f32> = steps
+            .iter()
+            .flat_map(|&best| (0..classes).map(move |c| if c == best { 10.0 } else { 0.0 }))
+            .collect();
+
+        let (text, confidence) = ctc_decode(&logits);
+        assert_eq!(text, "HELLO");
+        assert!(confidence > 0.9 && confidence <= 1.0);
+        assert_eq!(ctc_decode(&[]), (String::new(), 0.0));
+    }
+
+    #[test]
+    fn test_without_model_boxes_and_title_remain() {
+        let img = DynamicImage::ImageLuma8(render(&["EVA", "REDOX"], 3, false));
+        let result = engine().extract(&img, Some("Quarterly report - Writer")).unwrap();
+
+        assert_eq!(result.lines[0].text, "Quarterly report - Writer");
+        assert_eq!(result.lines.iter().filter(|l| l.bbox.is_some()).count(), 2);
+        // Only real words get indexed, no "Screen: 400x..." descriptions
+        assert_eq!(result.text(), "Quarterly report - Writer");
+
+        // A blank screen has nothing to read
+        let blank = DynamicImage::ImageLuma8(GrayImage::from_pixel(64, 64, Luma([200])));
+        assert_eq!(engine().extract(&blank, None).unwrap(), OcrResult::default());
+    }
+
+    #[tokio::test]
+    async fn test_fixture_text_is_read_when_model_is_available() {
+        let npu = super::super::npu_delegate::NPUDelegate::new().unwrap();
+        let ocr = OCREngine::new(&npu).await.unwrap();
+        if !ocr.has_model() {
+            return;
+        }
+        let img = DynamicImage::ImageLuma8(render(&["EVA", "REDOX"], 4, false));
+        let text = ocr.extract_text(&img, None).unwrap();
+        assert!(text.contains("EVA") && text.contains("REDOX"), "{}", text);
+    }
 }
@@ -0,0 +1,257 @@
+//! Per-application capture policies
+//!
+//! `policies.json` in the Time Machine directory lists rules for the app
+//! owning the active window: how often to capture it, the largest
+//! resolution to keep, or not to capture it at all. The first rule whose
+//! `app` appears (case-insensitively) in the app name applies; other apps
+//! follow the global interval.
+//!
+//! ```json
+//! [
+//!   { "app": "code", "interval_secs": 10 },
+//!   { "app": "firefox", "interval_secs": 60 },
+//!   { "app": "vlc", "max_resolution": 720 },
+//!   { "app": "steam", "enabled": false }
+//! ]
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Policy file, inside the Time Machine directory
+const POLICY_FILE: &str = "policies.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapturePolicy {
+    /// Part of the app name this rule applies to
+    pub app: String,
+    /// Seconds between captures of this app (the global interval if unset)
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+    /// Screenshots are scaled down to fit this many pixels on their longest
+    /// side
+    #[serde(default)]
+    pub max_resolution: Option<u32>,
+    /// `false` never captures this app
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// What to do at a capture tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Capture { max_resolution: Option<u32> },
+    Skip,
+}
+
+/// Policies plus when each last let a capture through
+pub struct PolicyTable {
+    policies: Vec<CapturePolicy>,
+    default_interval: Duration,
+    /// Last capture per policy; `None` is apps no policy matches, so
+    /// switching between apps doesn't reset each other's interval
+    last_capture: HashMap<Option<usize>, Instant>,
+    /// Ticks each policy held a capture back
+    skips: HashMap<usize, u64>,
+}
+
+impl PolicyTable {
+    pub fn new(policies: Vec<CapturePolicy>, default_interval_secs: u64) -> Self {
+        Self {
+            policies: policies.into_iter().map(|p| CapturePolicy { app: p.app.to_lowercase(), ..p }).collect(),
+            default_interval: Duration::from_secs(default_interval_secs.max(1)),
+            last_capture: HashMap::new(),
+            skips: HashMap::new(),
+        }
+    }
+
+    /// Policies from the Time Machine directory (none if the file is missing)
+    pub fn load(default_interval_secs: u64) -> Result<Self, Box<dyn Error>> {
+        Self::load_from(&crate::paths::timemachine_dir()?.join(POLICY_FILE), default_interval_secs)
+    }
+
+    pub fn load_from(path: &Path, default_interval_secs: u64) -> Result<Self, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Self::new(Vec::new(), default_interval_secs));
+        }
+        let policies: Vec<CapturePolicy> =
+            serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Self::new(policies, default_interval_secs))
+    }
+
+    pub fn len(&self) -> usize {
+        self.policies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// Index of the first policy matching `app`
+    fn policy_for(&self, app: Option<&str>) -> Option<usize> {
+        let app = app?.to_lowercase();
+        self.policies.iter().position(|p| app.contains(&p.app))
+    }
+
+    fn interval(&self, policy: Option<usize>) -> Duration {
+        policy
+            .and_then(|i| self.policies[i].interval_secs)
+            .map_or(self.default_interval, |secs| Duration::from_secs(secs.max(1)))
+    }
+
+    /// How often the capture loop should wake: often enough to honour every
+    /// interval exactly (their greatest common divisor)
+    pub fn tick(&self) -> Duration {
+        let gcd = |a: u64, b: u64| {
+            let (mut a, mut b) = (a, b);
+            while b != 0 {
+                (a, b) = (b, a % b);
+            }
+            a
+        };
+        let secs = self.policies.iter().filter(|p| p.enabled).filter_map(|p| p.interval_secs).fold(self.default_interval.as_secs(), gcd);
+        Duration::from_secs(secs.max(1))
+    }
+
+    /// Whether to capture now, with `app` owning the active window
+    pub fn decide(&mut self, app: Option<&str>, now: Instant) -> Decision {
+        let policy = self.policy_for(app);
+        if policy.is_some_and(|i| !self.policies[i].enabled) {
+            return self.skip(policy);
+        }
+
+        // Ticks land a little late or early; half a tick of slack keeps an
+        // interval that is a multiple of the tick from slipping a whole tick
+        let due = self.interval(policy).saturating_sub(self.tick() / 2);
+        if self.last_capture.get(&policy).is_some_and(|last| now.saturating_duration_since(*last) < due) {
+            return self.skip(policy);
+        }
+
+        self.last_capture.insert(policy, now);
+        Decision::Capture { max_resolution: policy.and_then(|i| self.policies[i].max_resolution) }
+    }
+
+    fn skip(&mut self, policy: Option<usize>) -> Decision {
+        if let Some(i) = policy {
+            *self.skips.entry(i).or_insert(0) += 1;
+        }
+        Decision::Skip
+    }
+
+    /// Skipped ticks by policy `app`
+    pub fn skips(&self) -> BTreeMap<String, u64> {
+        self.skips.iter().map(|(i, n)| (self.policies[*i].app.clone(), *n)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(app: &str, interval_secs: Option<u64>) -> CapturePolicy {
+        CapturePolicy { app: app.to_string(), interval_secs, max_resolution: None, enabled: true }
+    }
+
+    /// Drive the table tick by tick through `(app, seconds)` stretches of
+    /// window focus; returns the capture times (seconds) of each app
+    fn simulate(table: &mut PolicyTable, focus: &[(&str, u64)]) -> HashMap<String, Vec<u64>> {
+        let start = Instant::now();
+        let tick = table.tick().as_secs();
+        let mut captures: HashMap<String, Vec<u64>> = HashMap::new();
+        let mut t = 0;
+        for (app, secs) in focus {
+            let end = t + secs;
+            while t < end {
+                if let Decision::Capture { .. } = table.decide(Some(app), start + Duration::from_secs(t)) {
+                    captures.entry(app.to_string()).or_default().push(t);
+                }
+                t += tick;
+            }
+        }
+        captures
+    }
+
+    fn gaps(times: &[u64]) -> Vec<u64> {
+        times.windows(2).map(|w| w[1] - w[0]).collect()
+    }
+
+    #[test]
+    fn test_effective_interval_per_app() {
+        let mut table = PolicyTable::new(vec![policy("code", Some(10)), policy("firefox", Some(60))], 30);
+        assert_eq!(table.tick(), Duration::from_secs(10));
+
+        let captures = simulate(&mut table, &[("Code", 120), ("Firefox", 180), ("Terminal", 90)]);
+        assert!(gaps(&captures["Code"]).iter().all(|g| *g == 10), "{:?}", captures["Code"]);
+        assert_eq!(captures["Firefox"], vec![120, 180, 240]);
+        assert_eq!(captures["Terminal"], vec![300, 330, 360]);
+
+        // Ticks the browser rule held back while it had focus
+        assert_eq!(table.skips().get("firefox"), Some(&15));
+        assert_eq!(table.skips().get("code"), None);
+    }
+
+    #[test]
+    fn test_switching_back_keeps_each_apps_interval() {
+        let mut table = PolicyTable::new(vec![policy("code", Some(10)), policy("firefox", Some(60))], 10);
+
+        // Browser, a short stretch in the editor, then the browser again:
+        // the browser's minute runs on from its own last capture
+        let captures = simulate(&mut table, &[("firefox", 20), ("code", 20), ("firefox", 60)]);
+        assert_eq!(captures["firefox"], vec![0, 60]);
+        assert_eq!(captures["code"], vec![20, 30]);
+    }
+
+    #[test]
+    fn test_uneven_intervals_are_kept_exactly() {
+        let mut table = PolicyTable::new(vec![policy("slack", Some(15))], 10);
+        assert_eq!(table.tick(), Duration::from_secs(5));
+
+        let captures = simulate(&mut table, &[("Slack", 60), ("other", 40)]);
+        assert_eq!(captures["Slack"], vec![0, 15, 30, 45]);
+        assert_eq!(captures["other"], vec![60, 70, 80, 90]);
+    }
+
+    #[test]
+    fn test_disabled_apps_and_resolution_cap() {
+        let vlc = CapturePolicy { max_resolution: Some(720), ..policy("vlc", None) };
+        let steam = CapturePolicy { enabled: false, ..policy("Steam", Some(1)) };
+        let mut table = PolicyTable::new(vec![vlc, steam], 10);
+        // Disabled rules don't make the loop wake more often
+        assert_eq!(table.tick(), Duration::from_secs(10));
+
+        let now = Instant::now();
+        assert_eq!(table.decide(Some("VLC media player"), now), Decision::Capture { max_resolution: Some(720) });
+        assert_eq!(table.decide(Some("steam"), now), Decision::Skip);
+        assert_eq!(table.decide(Some("steamwebhelper"), now), Decision::Skip);
+        assert_eq!(table.decide(None, now), Decision::Capture { max_resolution: None });
+        assert_eq!(table.skips(), BTreeMap::from([("steam".to_string(), 2)]));
+    }
+
+    #[test]
+    fn test_load_from_file() {
+        let dir = std::env::temp_dir().join(format!("eva_test_policies_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(POLICY_FILE);
+
+        assert_eq!(PolicyTable::load_from(&path, 10).unwrap().len(), 0);
+
+        fs::write(&path, r#"[{ "app": "code", "interval_secs": 10 }, { "app": "vlc", "max_resolution": 720 }]"#).unwrap();
+        let table = PolicyTable::load_from(&path, 10).unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.policies[1], CapturePolicy { max_resolution: Some(720), ..policy("vlc", None) });
+
+        fs::write(&path, r#"[{ "interval_secs": 10 }]"#).unwrap();
+        let err = PolicyTable::load_from(&path, 10).err().unwrap().to_string();
+        assert!(err.contains("policies.json") && err.contains("app"), "{}", err);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
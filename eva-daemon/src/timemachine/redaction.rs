@@ -0,0 +1,458 @@
+//! PII redaction - blurs sensitive regions of a screenshot before storage
+//!
+//! Text categories (emails, phone numbers, card numbers) are located in the
+//! OCR line boxes; faces come from an optional detector. Every region is
+//! blurred in place and the number of redactions is stored per capture.
+
+use super::ocr::{BoundingBox, OcrResult};
+use image::{imageops, DynamicImage, GenericImageView};
+use regex::Regex;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "timemachine")]
+use ort::{Session, Value};
+
+/// Default time cap for one redaction pass
+const DEFAULT_TIME_BUDGET_MS: u64 = 250;
+
+/// Blur strength (Gaussian sigma, pixels)
+const BLUR_SIGMA: f32 = 8.0;
+
+/// Extra pixels blurred around each region
+const REGION_PADDING: u32 = 2;
+
+/// Minimum face detector score
+const FACE_SCORE_THRESHOLD: f32 = 0.7;
+
+/// Kinds of sensitive content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PiiCategory {
+    Email,
+    Phone,
+    CardNumber,
+    Face,
+}
+
+/// Which categories to redact and how long a pass may take
+#[derive(Debug, Clone)]
+pub struct RedactionConfig {
+    pub emails: bool,
+    pub phones: bool,
+    pub card_numbers: bool,
+    /// Needs a face detector model
+    pub faces: bool,
+    /// Stop redacting (and log) once a pass exceeds this
+    pub time_budget: Duration,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            emails: true,
+            phones: true,
+            card_numbers: true,
+            faces: false,
+            time_budget: Duration::from_millis(DEFAULT_TIME_BUDGET_MS),
+        }
+    }
+}
+
+impl RedactionConfig {
+    pub fn enabled(&self, category: PiiCategory) -> bool {
+        match category {
+            PiiCategory::Email => self.emails,
+            PiiCategory::Phone => self.phones,
+            PiiCategory::CardNumber => self.card_numbers,
+            PiiCategory::Face => self.faces,
+        }
+    }
+
+    /// Whether a pass would do anything at all
+    pub fn any(&self) -> bool {
+        self.emails || self.phones || self.card_numbers || self.faces
+    }
+}
+
+/// Outcome of one redaction pass
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RedactionReport {
+    /// Regions blurred
+    pub applied: u32,
+    /// The time cap was hit before every region was handled
+    pub over_budget: bool,
+    pub elapsed: Duration,
+}
+
+/// Finds face rectangles in a screenshot
+pub trait FaceDetector: Send + Sync {
+    fn detect(&self, image: &DynamicImage) -> Vec<BoundingBox>;
+}
+
+/// A sensitive match inside one line of text (char offsets)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiiMatch {
+    pub category: PiiCategory,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Screenshot redactor
+pub struct Redactor {
+    config: RedactionConfig,
+    email: Regex,
+    phone: Regex,
+    card: Regex,
+    faces: Option<Box<dyn FaceDetector>>,
+}
+
+impl Redactor {
+    pub fn new(config: RedactionConfig) -> Self {
+        Self {
+            config,
+            email: Regex::new(r"[\w.+-]+@[\w-]+(?:\.[\w-]+)+").unwrap(),
+            // +55 11 91234-5678, (212) 555-0100, 555-0100 ...
+            phone: Regex::new(r"\+?\(?\d{2,3}\)?[\s.-]?\d{3,5}[\s.-]\d{4}\b").unwrap(),
+            card: Regex::new(r"\b(?:\d[ -]?){12,18}\d\b").unwrap(),
+            faces: None,
+        }
+    }
+
+    /// Use `detector` for face redaction (only when `config.faces` is on)
+    pub fn with_face_detector(mut self, detector: Box<dyn FaceDetector>) -> Self {
+        self.faces = Some(detector);
+        self
+    }
+
+    /// Sensitive spans in one line of text
+    pub fn find_pii(&self, text: &str) -> Vec<PiiMatch> {
+        let mut found = Vec::new();
+        let char_offset = |byte: usize| text[..byte].chars().count();
+
+        let mut push = |category: PiiCategory, m: regex::Match| {
+            found.push(PiiMatch { category, start: char_offset(m.start()), end: char_offset(m.end()) });
+        };
+
+        if self.config.enabled(PiiCategory::Email) {
+            self.email.find_iter(text).for_each(|m| push(PiiCategory::Email, m));
+        }
+        // Card numbers first: their digit groups would also look like phones
+        let mut card_spans = Vec::new();
+        if self.config.enabled(PiiCategory::CardNumber) {
+            for m in self.card.find_iter(text).filter(|m| luhn_valid(m.as_str())) {
+                card_spans.push(m.range());
+                push(PiiCategory::CardNumber, m);
+            }
+        }
+        if self.config.enabled(PiiCategory::Phone) {
+            for m in self.phone.find_iter(text) {
+                let inside_card = card_spans.iter().any(|r| r.start <= m.start() && m.end() <= r.end);
+                if !inside_card {
+                    push(PiiCategory::Phone, m);
+                }
+            }
+        }
+        found
+    }
+
+    /// Blur sensitive regions of `image` in place
+    pub fn redact(&self, image: &mut DynamicImage, ocr: &OcrResult) -> RedactionReport {
+        let start = Instant::now();
+        let mut report = RedactionReport::default();
+
+        let mut regions: Vec<BoundingBox> = Vec::new();
+        for line in &ocr.lines {
+            let Some(bbox) = line.bbox else { continue };
+            let len = line.text.chars().count();
+            for m in self.find_pii(&line.text) {
+                regions.push(span_box(bbox, m.start, m.end, len));
+            }
+        }
+
+        if self.config.enabled(PiiCategory::Face) {
+            if let Some(ref detector) = self.faces {
+                regions.extend(detector.detect(image));
+            }
+        }
+
+        for region in regions {
+            if start.elapsed() > self.config.time_budget {
+                report.over_budget = true;
+                println!(
+                    "[Redaction] Time cap of {}ms exceeded, skipping remaining regions",
+                    self.config.time_budget.as_millis()
+                );
+                break;
+            }
+            if blur_region(image, region) {
+                report.applied += 1;
+            }
+        }
+
+        report.elapsed = start.elapsed();
+        report
+    }
+}
+
+/// Approximate box of chars `start..end` within a line box, assuming
+/// roughly even character widths
+fn span_box(line: BoundingBox, start: usize, end: usize, len: usize) -> BoundingBox {
+    if len == 0 {
+        return line;
+    }
+    let x0 = line.x + (line.width as u64 * start as u64 / len as u64) as u32;
+    let x1 = line.x + (line.width as u64 * end as u64).div_ceil(len as u64) as u32;
+    BoundingBox { x: x0, y: line.y, width: x1.saturating_sub(x0), height: line.height }
+}
+
+/// Blur one (padded, clamped) region; false if it falls outside the image
+fn blur_region(image: &mut DynamicImage, region: BoundingBox) -> bool {
+    let (width, height) = image.dimensions();
+    let x = region.x.saturating_sub(REGION_PADDING);
+    let y = region.y.saturating_sub(REGION_PADDING);
+    let right = (region.x + region.width + REGION_PADDING).min(width);
+    let bottom = (region.y + region.height + REGION_PADDING).min(height);
+    if x >= right || y >= bottom {
+        return false;
+    }
+
+    let patch = image.crop_imm(x, y, right - x, bottom - y);
+    let blurred = imageops::blur(&patch, BLUR_SIGMA);
+    let mut rgba = image.to_rgba8();
+    imageops::replace(&mut rgba, &blurred, x as i64, y as i64);
+    *image = DynamicImage::ImageRgba8(rgba);
+    true
+}
+
+/// Luhn checksum, so order numbers and timestamps are not mistaken for cards
+fn luhn_valid(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| if i % 2 == 1 { if d * 2 > 9 { d * 2 - 9 } else { d * 2 } } else { d })
+        .sum();
+    sum % 10 == 0
+}
+
+/// Decode detector output: `scores` as [N, 2] (background, face) and
+/// `boxes` as [N, 4] normalized (x0, y0, x1, y1)
+pub fn decode_face_boxes(scores: &[f32], boxes: &[f32], width: u32, height: u32) -> Vec<BoundingBox> {
+    scores
+        .chunks_exact(2)
+        .zip(boxes.chunks_exact(4))
+        .filter(|(s, _)| s[1] >= FACE_SCORE_THRESHOLD)
+        .map(|(_, b)| {
+            let x0 = (b[0].clamp(0.0, 1.0) * width as f32) as u32;
+            let y0 = (b[1].clamp(0.0, 1.0) * height as f32) as u32;
+            let x1 = (b[2].clamp(0.0, 1.0) * width as f32) as u32;
+            let y1 = (b[3].clamp(0.0, 1.0) * height as f32) as u32;
+            BoundingBox { x: x0, y: y0, width: x1.saturating_sub(x0), height: y1.saturating_sub(y0) }
+        })
+        .filter(|b| b.width > 0 && b.height > 0)
+        .collect()
+}
+
+/// Small ONNX face detector (UltraFace-style 320x240 input) run through
+/// the NPU delegate
+#[cfg(feature = "timemachine")]
+pub struct OnnxFaceDetector {
+    session: Session,
+}
+
+#[cfg(feature = "timemachine")]
+impl OnnxFaceDetector {
+    pub fn new(npu: &super::npu_delegate::NPUDelegate) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self { session: npu.create_session("models/face-detector.onnx")? })
+    }
+
+    fn run(&self, image: &DynamicImage) -> Result<Vec<BoundingBox>, Box<dyn std::error::Error>> {
+        let resized = image.resize_exact(320, 240, imageops::FilterType::Triangle).to_rgb8();
+        let mut pixels = vec![0f32; 3 * 240 * 320];
+        for (x, y, p) in resized.enumerate_pixels() {
+            for c in 0..3 {
+                pixels[c * 240 * 320 + (y * 320 + x) as usize] = (p[c] as f32 - 127.0) / 128.0;
+            }
+        }
+        let input = Value::from_array((vec![1, 3, 240, 320], pixels))?;
+        let outputs = self.session.run(vec![input])?;
+        if outputs.len() < 2 {
+            return Ok(Vec::new());
+        }
+        let scores = outputs[0].try_extract::<f32>()?;
+        let boxes = outputs[1].try_extract::<f32>()?;
+        let (width, height) = image.dimensions();
+        Ok(decode_face_boxes(
+            scores.view().as_slice().unwrap_or(&[]),
+            boxes.view().as_slice().unwrap_or(&[]),
+            width,
+            height,
+        ))
+    }
+}
+
+#[cfg(feature = "timemachine")]
+impl FaceDetector for OnnxFaceDetector {
+    fn detect(&self, image: &DynamicImage) -> Vec<BoundingBox> {
+        self.run(image).unwrap_or_else(|e| {
+            eprintln!("[Redaction] Face detection failed: {}", e);
+            Vec::new()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timemachine::ocr::OcrLine;
+    use image::{Rgba, RgbaImage};
+
+    /// White canvas with black "glyph" stripes drawn inside `boxes`
+    fn canvas_with_text(boxes: &[BoundingBox]) -> DynamicImage {
+        let mut img = RgbaImage::from_pixel(400, 200, Rgba([255, 255, 255, 255]));
+        for b in boxes {
+            for y in b.y..b.y + b.height {
+                for x in (b.x..b.x + b.width).filter(|x| x % 4 < 2) {
+                    img.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+                }
+            }
+        }
+        DynamicImage::ImageRgba8(img)
+    }
+
+    fn line(text: &str, bbox: BoundingBox) -> OcrLine {
+        OcrLine { text: text.to_string(), bbox: Some(bbox), confidence: 0.9 }
+    }
+
+    fn changed_inside(before: &DynamicImage, after: &DynamicImage, b: BoundingBox) -> bool {
+        (b.y..b.y + b.height).any(|y| (b.x..b.x + b.width).any(|x| before.get_pixel(x, y) != after.get_pixel(x, y)))
+    }
+
+    fn unchanged_outside(before: &DynamicImage, after: &DynamicImage, regions: &[BoundingBox]) -> bool {
+        let pad = REGION_PADDING;
+        let inside = |x: u32, y: u32| {
+            regions.iter().any(|b| {
+                x + pad >= b.x && x < b.x + b.width + pad && y + pad >= b.y && y < b.y + b.height + pad
+            })
+        };
+        before
+            .pixels()
+            .filter(|(x, y, _)| !inside(*x, *y))
+            .all(|(x, y, p)| after.get_pixel(x, y) == p)
+    }
+
+    #[test]
+    fn test_find_pii_categories() {
+        let redactor = Redactor::new(RedactionConfig::default());
+        let cats = |t: &str| redactor.find_pii(t).into_iter().map(|m| m.category).collect::<Vec<_>>();
+
+        assert_eq!(cats("mail ana.souza@example.com.br now"), vec![PiiCategory::Email]);
+        assert_eq!(cats("call +55 11 91234-5678"), vec![PiiCategory::Phone]);
+        assert_eq!(cats("card 4111 1111 1111 1111"), vec![PiiCategory::CardNumber]);
+        // Fails the Luhn check: not a card
+        assert!(!cats("order 1234 5678 9012 3456").contains(&PiiCategory::CardNumber));
+        assert!(cats("meeting at 10:30 in room 4").is_empty());
+    }
+
+    #[test]
+    fn test_span_box_locates_substring() {
+        let bbox = BoundingBox { x: 100, y: 10, width: 200, height: 20 };
+        let redactor = Redactor::new(RedactionConfig::default());
+        let text = "email: bob@x.io";
+        let m = &redactor.find_pii(text)[0];
+        let span = span_box(bbox, m.start, m.end, text.chars().count());
+        assert!(span.x > bbox.x && span.x + span.width <= bbox.x + bbox.width);
+    }
+
+    #[test]
+    fn test_redact_blurs_only_sensitive_lines() {
+        let email_box = BoundingBox { x: 20, y: 20, width: 160, height: 16 };
+        let plain_box = BoundingBox { x: 20, y: 80, width: 160, height: 16 };
+        let original = canvas_with_text(&[email_box, plain_box]);
+        let mut image = original.clone();
+
+        let ocr = OcrResult {
+            lines: vec![line("ana@example.com", email_box), line("quarterly report", plain_box)],
+        };
+        let report = Redactor::new(RedactionConfig::default()).redact(&mut image, &ocr);
+
+        assert_eq!(report.applied, 1);
+        assert!(!report.over_budget);
+        assert!(changed_inside(&original, &image, email_box));
+        assert!(!changed_inside(&original, &image, plain_box));
+        assert!(unchanged_outside(&original, &image, &[email_box]));
+    }
+
+    #[test]
+    fn test_disabled_category_is_kept() {
+        let phone_box = BoundingBox { x: 10, y: 10, width: 120, height: 12 };
+        let original = canvas_with_text(&[phone_box]);
+        let mut image = original.clone();
+        let ocr = OcrResult { lines: vec![line("(212) 555-0100", phone_box)] };
+
+        let config = RedactionConfig { phones: false, ..Default::default() };
+        let report = Redactor::new(config).redact(&mut image, &ocr);
+        assert_eq!(report.applied, 0);
+        assert_eq!(original, image);
+    }
+
+    struct FixedFaces(Vec<BoundingBox>);
+
+    impl FaceDetector for FixedFaces {
+        fn detect(&self, _image: &DynamicImage) -> Vec<BoundingBox> {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn test_face_regions_blurred_when_enabled() {
+        let face = BoundingBox { x: 250, y: 50, width: 60, height: 80 };
+        let original = canvas_with_text(&[face]);
+        let detector = || Box::new(FixedFaces(vec![face]));
+
+        let mut off = original.clone();
+        Redactor::new(RedactionConfig::default()).with_face_detector(detector()).redact(&mut off, &OcrResult::default());
+        assert_eq!(off, original);
+
+        let mut on = original.clone();
+        let config = RedactionConfig { faces: true, ..Default::default() };
+        let report = Redactor::new(config).with_face_detector(detector()).redact(&mut on, &OcrResult::default());
+        assert_eq!(report.applied, 1);
+        assert!(changed_inside(&original, &on, face));
+        assert!(unchanged_outside(&original, &on, &[face]));
+    }
+
+    #[test]
+    fn test_time_cap_skips_remaining_regions() {
+        let b = BoundingBox { x: 0, y: 0, width: 50, height: 10 };
+        let mut image = canvas_with_text(&[b]);
+        let ocr = OcrResult { lines: vec![line("a@b.co c@d.co", b)] };
+        let config = RedactionConfig { time_budget: Duration::ZERO, ..Default::default() };
+
+        // Sleeping detector guarantees the cap is exceeded before blurring
+        struct Slow;
+        impl FaceDetector for Slow {
+            fn detect(&self, _image: &DynamicImage) -> Vec<BoundingBox> {
+                std::thread::sleep(Duration::from_millis(2));
+                Vec::new()
+            }
+        }
+        let config = RedactionConfig { faces: true, ..config };
+        let report = Redactor::new(config).with_face_detector(Box::new(Slow)).redact(&mut image, &ocr);
+        assert!(report.over_budget);
+        assert_eq!(report.applied, 0);
+    }
+
+    #[test]
+    fn test_decode_face_boxes() {
+        let scores = [0.9, 0.1, 0.2, 0.8];
+        let boxes = [0.0, 0.0, 0.5, 0.5, 0.5, 0.5, 1.0, 1.0];
+        assert_eq!(
+            decode_face_boxes(&scores, &boxes, 100, 100),
+            vec![BoundingBox { x: 50, y: 50, width: 50, height: 50 }]
+        );
+    }
+}
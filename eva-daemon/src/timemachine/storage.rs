@@ -10,30 +10,187 @@ use chrono::{DateTime, Duration, Utc};
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
-use image::DynamicImage;
+use image::{DynamicImage, GenericImageView};
 use rusqlite::{params, Connection};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::error::Error;
 use std::fs;
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::index::QuantizedVector;
 
 /// Default storage limits
 const DEFAULT_MAX_STORAGE_MB: u64 = 5000; // 5GB default
 const DEFAULT_RETENTION_DAYS: i64 = 30;   // 30 days default
+const DEFAULT_DELETE_AFTER_DAYS: i64 = 365; // 1 year for downsampled captures
+
+/// Thumbnail bounding box kept for downsampled captures
+const THUMBNAIL_SIZE: (u32, u32) = (320, 180);
+
+/// JPEG quality when none is configured
+pub const DEFAULT_QUALITY: u8 = 80;
+
+/// Encoding of stored captures and their thumbnails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptureFormat {
+    /// Lossless, largest
+    #[default]
+    Png,
+    /// Lossless WebP (the image crate only encodes lossless), usually well
+    /// under PNG for screen content
+    WebP,
+    /// Lossy at the configured quality; smallest
+    Jpeg,
+}
+
+impl CaptureFormat {
+    /// Name stored per row and used in configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            CaptureFormat::Png => "png",
+            CaptureFormat::WebP => "webp",
+            CaptureFormat::Jpeg => "jpeg",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "png" => Some(CaptureFormat::Png),
+            "webp" => Some(CaptureFormat::WebP),
+            "jpeg" | "jpg" => Some(CaptureFormat::Jpeg),
+            _ => None,
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            CaptureFormat::Png => "image/png",
+            CaptureFormat::WebP => "image/webp",
+            CaptureFormat::Jpeg => "image/jpeg",
+        }
+    }
+
+    /// Encode `image`; `quality` (1-100) applies to JPEG only
+    fn encode(&self, image: &DynamicImage, quality: u8) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut bytes = Vec::new();
+        match self {
+            CaptureFormat::Png => image.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)?,
+            CaptureFormat::WebP => {
+                // The WebP encoder takes 8-bit RGB(A) only
+                let encoder = image::codecs::webp::WebPEncoder::new_lossless(&mut bytes);
+                match image {
+                    DynamicImage::ImageRgb8(_) | DynamicImage::ImageRgba8(_) => image.write_with_encoder(encoder)?,
+                    _ => DynamicImage::ImageRgba8(image.to_rgba8()).write_with_encoder(encoder)?,
+                }
+            }
+            CaptureFormat::Jpeg => {
+                // No alpha in JPEG
+                let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, quality.clamp(1, 100));
+                DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)?;
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+/// A decrypted capture (or thumbnail) and how it is encoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredImage {
+    pub bytes: Vec<u8>,
+    pub format: CaptureFormat,
+}
+
+impl StoredImage {
+    pub fn mime_type(&self) -> &'static str {
+        self.format.mime_type()
+    }
+}
+
+/// Which monitor a capture shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScreenRef {
+    /// Position in the system's screen list
+    pub index: u32,
+    /// Shared by the captures of every screen taken in the same tick
+    pub group_id: Option<u64>,
+}
 
+/// Files on disk younger than this are never called orphans: a capture's
+/// files are written a moment before its row
+const ORPHAN_GRACE: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long a write waits for another connection's lock before failing
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Clones share the database connection, so work can be moved onto the
+/// blocking thread pool
+#[derive(Clone)]
 pub struct Storage {
     base_path: PathBuf,
     db_path: PathBuf,
+    /// Single connection for every query (WAL mode)
+    conn: Arc<Mutex<Connection>>,
     cipher: Option<Aes256Gcm>,
+    /// Key of captures stored before the keystore; they are re-encrypted
+    /// with `cipher` when read
+    legacy_cipher: Option<Aes256Gcm>,
     /// Maximum storage in megabytes
     max_storage_mb: u64,
-    /// Retention period in days
+    /// Keep everything at full resolution for this many days
     retention_days: i64,
+    /// Delete downsampled captures entirely after this many days
+    delete_after_days: i64,
+    /// `Some(n)`: store embeddings as int8, keeping f32 for the last n captures
+    quantize_keep_full: Option<usize>,
+    /// Encoding of new captures
+    format: CaptureFormat,
+    /// JPEG quality, 1-100
+    quality: u8,
+    /// Downscale new captures so neither side exceeds this
+    max_dimension: Option<u32>,
 }
 
 pub struct Metadata {
     pub timestamp: DateTime<Utc>,
     pub text: String,
+    /// False once retention has dropped the full-resolution image
+    pub full_image: bool,
+    /// `None` for captures from before screens were recorded
+    pub screen: Option<ScreenRef>,
+    /// A window was masked out of the image by a privacy pattern
+    pub masked: bool,
+}
+
+/// A full-text search hit
+#[derive(Debug, Clone)]
+pub struct TextHit {
+    pub id: u64,
+    pub text: String,
+    /// bm25 score (lower is better)
+    pub score: f64,
+    pub timestamp: DateTime<Utc>,
+    /// False once retention has dropped the full-resolution image
+    pub full_image: bool,
+    pub screen: Option<ScreenRef>,
+    pub masked: bool,
+}
+
+/// A capture as the timeline lists it
+#[derive(Debug, Clone)]
+pub struct CaptureSummary {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Application in focus; `None` when unknown or its window was masked
+    pub app_name: Option<String>,
+    pub text: String,
+    /// False once retention has dropped the full-resolution image
+    pub full_image: bool,
+    /// A thumbnail was stored with it (`load_thumbnail`)
+    pub thumbnail: bool,
+    pub screen: Option<ScreenRef>,
 }
 
 pub struct StorageStats {
@@ -41,29 +198,91 @@ pub struct StorageStats {
     pub storage_used_mb: f64,
     pub oldest_screenshot: Option<DateTime<Utc>>,
     pub newest_screenshot: Option<DateTime<Utc>>,
+    /// Tier 1: captures kept at full resolution
+    pub full_count: u64,
+    pub full_mb: f64,
+    /// Tier 2: representative captures (thumbnail + text + embedding)
+    pub downsampled_count: u64,
+    pub downsampled_mb: f64,
+    /// Tier 3: captures removed entirely (cumulative)
+    pub purged_count: u64,
+    /// Bytes taken by stored embeddings (f32 + int8 columns)
+    pub embedding_bytes: u64,
+}
+
+/// Result of rewriting stored embeddings to the configured format
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct EmbeddingMigration {
+    /// Captures whose embedding was converted (to int8, or back to f32)
+    pub rewritten: u64,
+    /// f32 originals dropped (int8 codes kept) or int8 copies dropped
+    pub trimmed: u64,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Result of `Storage::verify_all`; paths are relative to the storage root
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VerifyReport {
+    /// Files whose checksum matched
+    pub verified: u64,
+    /// Files stored before checksums were recorded: present, not checked
+    pub unchecked: u64,
+    /// Files a capture refers to that are not on disk
+    pub missing: Vec<String>,
+    /// Files whose bytes no longer match their checksum (truncated by a
+    /// crash, or altered)
+    pub corrupted: Vec<String>,
+    /// Files on disk no capture refers to
+    pub orphans: Vec<String>,
+    /// Orphans removed, when asked to
+    pub orphans_deleted: u64,
+}
+
+impl VerifyReport {
+    /// Files looked at, good or bad (orphans aside)
+    pub fn files(&self) -> u64 {
+        self.verified + self.unchecked + self.corrupted.len() as u64 + self.missing.len() as u64
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.corrupted.is_empty() && self.orphans.is_empty()
+    }
+}
+
+/// Result of a retention pass
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CleanupReport {
+    /// Captures reduced to a thumbnail
+    pub downsampled: u64,
+    /// Captures removed entirely
+    pub deleted: u64,
 }
 
 impl Storage {
     pub async fn new(path_str: &str) -> Result<Self, Box<dyn Error>> {
-        let base_path = if path_str.starts_with("~") {
-            let home = std::env::var("USERPROFILE").or_else(|_| std::env::var("HOME"))?;
-            PathBuf::from(path_str.replace("~", &home))
-        } else {
-            PathBuf::from(path_str)
-        };
-
-        if !base_path.exists() {
-            fs::create_dir_all(&base_path)?;
-        }
+        let base_path = PathBuf::from(crate::paths::expand_home(path_str));
+        crate::paths::ensure_private_dir(&base_path)?;
 
         let db_path = base_path.join("metadata.db");
+        let conn = Connection::open(&db_path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // WAL lets readers proceed while a capture is being written
+        conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get::<_, String>(0))?;
 
         let storage = Self {
             base_path,
             db_path,
+            conn: Arc::new(Mutex::new(conn)),
             cipher: None,
+            legacy_cipher: None,
             max_storage_mb: DEFAULT_MAX_STORAGE_MB,
             retention_days: DEFAULT_RETENTION_DAYS,
+            delete_after_days: DEFAULT_DELETE_AFTER_DAYS,
+            quantize_keep_full: None,
+            format: CaptureFormat::default(),
+            quality: DEFAULT_QUALITY,
+            max_dimension: None,
         };
 
         storage.init_db()?;
@@ -71,9 +290,26 @@ impl Storage {
         Ok(storage)
     }
 
+    /// The shared connection (still usable if a previous holder panicked)
+    fn db(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Run SQLite and file work on the blocking thread pool so the capture
+    /// loop never stalls the async runtime
+    async fn blocking<T, F>(&self, f: F) -> Result<T, Box<dyn Error>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Storage) -> Result<T, Box<dyn Error>> + Send + 'static,
+    {
+        let storage = self.clone();
+        let result = tokio::task::spawn_blocking(move || f(&storage).map_err(|e| e.to_string())).await?;
+        Ok(result?)
+    }
+
     /// Initialize database with proper indices
     fn init_db(&self) -> Result<(), Box<dyn Error>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db();
 
         // Create main table
         conn.execute(
@@ -88,6 +324,36 @@ impl Storage {
             [],
         )?;
 
+        // Columns added for tiered retention (older databases get migrated)
+        Self::ensure_column(&conn, "app_name", "TEXT")?;
+        Self::ensure_column(&conn, "ocr_confidence", "REAL")?;
+        Self::ensure_column(&conn, "embedding", "BLOB")?;
+        Self::ensure_column(&conn, "embedding_q8", "BLOB")?;
+        Self::ensure_column(&conn, "thumb_path", "TEXT")?;
+        Self::ensure_column(&conn, "thumb_size", "INTEGER DEFAULT 0")?;
+        Self::ensure_column(&conn, "downsampled", "INTEGER DEFAULT 0")?;
+        Self::ensure_column(&conn, "redactions_applied", "INTEGER DEFAULT 0")?;
+        // NULL for rows stored before formats were configurable: PNG
+        Self::ensure_column(&conn, "image_format", "TEXT")?;
+        // NULL for rows stored before screens were recorded
+        Self::ensure_column(&conn, "screen_index", "INTEGER")?;
+        Self::ensure_column(&conn, "group_id", "INTEGER")?;
+        Self::ensure_column(&conn, "privacy_masked", "INTEGER DEFAULT 0")?;
+        // SHA-256 of the file as written and the length of the image inside;
+        // NULL for rows stored before checksums
+        Self::ensure_column(&conn, "checksum", "TEXT")?;
+        Self::ensure_column(&conn, "plain_size", "INTEGER")?;
+        Self::ensure_column(&conn, "thumb_checksum", "TEXT")?;
+        Self::ensure_column(&conn, "thumb_plain_size", "INTEGER")?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS retention_state (
+                key TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
         // Create indices for faster queries
         conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_screenshots_timestamp ON screenshots(timestamp)",
@@ -129,14 +395,106 @@ impl Storage {
         Ok(())
     }
 
+    /// Add a column to the screenshots table if it does not exist yet
+    fn ensure_column(conn: &Connection, name: &str, decl: &str) -> Result<(), Box<dyn Error>> {
+        let mut stmt = conn.prepare("PRAGMA table_info(screenshots)")?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|r| r.ok())
+            .any(|col| col == name);
+
+        if !exists {
+            conn.execute(&format!("ALTER TABLE screenshots ADD COLUMN {} {}", name, decl), [])?;
+        }
+        Ok(())
+    }
+
     /// Set storage limits
     pub fn set_limits(&mut self, max_storage_mb: u64, retention_days: i64) {
         self.max_storage_mb = max_storage_mb;
         self.retention_days = retention_days;
     }
 
-    pub fn set_encryption_key(&mut self, password: &str) -> Result<(), Box<dyn Error>> {
-        // Use a fixed salt for deterministic key derivation
+    /// Set the retention tiers: full resolution until `downsample_after_days`,
+    /// one representative per app per hour until `delete_after_days`
+    pub fn set_retention_tiers(&mut self, downsample_after_days: i64, delete_after_days: i64) {
+        self.retention_days = downsample_after_days;
+        self.delete_after_days = delete_after_days.max(downsample_after_days);
+    }
+
+    /// Store embeddings as int8 (`Some(n)` keeps f32 for the n most recent
+    /// captures) or as plain f32 (`None`). Run `migrate_embeddings` after
+    /// changing this so existing rows match.
+    pub fn set_embedding_quantization(&mut self, keep_full: Option<usize>) {
+        self.quantize_keep_full = keep_full;
+    }
+
+    /// Encode new captures as `format` (JPEG at `quality`), downscaled so
+    /// neither side exceeds `max_dimension`. Existing captures keep the
+    /// format they were stored in.
+    pub fn set_image_encoding(&mut self, format: CaptureFormat, quality: u8, max_dimension: Option<u32>) {
+        self.format = format;
+        self.quality = quality.clamp(1, 100);
+        self.max_dimension = max_dimension.filter(|&max| max > 0);
+    }
+
+    /// Compress and (if configured) encrypt a blob for disk
+    fn seal(&self, bytes: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes)?;
+        let compressed_bytes = encoder.finish()?;
+
+        if let Some(cipher) = &self.cipher {
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+            let ciphertext = cipher
+                .encrypt(&nonce, compressed_bytes.as_ref())
+                .map_err(|e| format!("Encryption failed: {}", e))?;
+
+            let mut result = nonce.to_vec();
+            result.extend(ciphertext);
+            Ok(result)
+        } else {
+            eprintln!("[Storage] Warning: Saving unencrypted screenshot!");
+            Ok(compressed_bytes)
+        }
+    }
+
+    /// Reverse of `seal`; the flag is set when the legacy key was needed
+    fn unseal(&self, data: Vec<u8>) -> Result<(Vec<u8>, bool), Box<dyn Error>> {
+        let (compressed_data, legacy) = if let Some(cipher) = &self.cipher {
+            if data.len() < 12 {
+                return Err("Encrypted data too short".into());
+            }
+
+            let nonce = Nonce::from_slice(&data[..12]);
+            let ciphertext = &data[12..];
+
+            match (cipher.decrypt(nonce, ciphertext), &self.legacy_cipher) {
+                (Ok(plain), _) => (plain, false),
+                (Err(e), Some(legacy)) => match legacy.decrypt(nonce, ciphertext) {
+                    Ok(plain) => (plain, true),
+                    Err(_) => return Err(format!("Decryption failed: {}", e).into()),
+                },
+                (Err(e), None) => return Err(format!("Decryption failed: {}", e).into()),
+            }
+        } else {
+            (data, false)
+        };
+
+        let mut decoder = GzDecoder::new(&compressed_data[..]);
+        let mut decompressed = Vec::new();
+        decoder.read_to_end(&mut decompressed)?;
+        Ok((decompressed, legacy))
+    }
+
+    /// Encrypt new captures with `key` (from the keystore)
+    pub fn set_encryption_key(&mut self, key: &[u8; 32]) {
+        self.cipher = Some(Aes256Gcm::new(key.into()));
+    }
+
+    /// Also read captures stored under the old password-derived key
+    pub fn set_legacy_password(&mut self, password: &str) -> Result<(), Box<dyn Error>> {
+        // The fixed salt the old key was derived with
         let salt = SaltString::from_b64("RXZhVGltZU1hY2hpbmU")
             .map_err(|e| format!("Salt error: {}", e))?;
 
@@ -151,61 +509,107 @@ impl Storage {
         let len = std::cmp::min(hash.len(), 32);
         key_bytes[..len].copy_from_slice(&hash.as_bytes()[..len]);
 
-        self.cipher = Some(Aes256Gcm::new(&key_bytes.into()));
+        self.legacy_cipher = Some(Aes256Gcm::new(&key_bytes.into()));
         Ok(())
     }
 
-    pub async fn save_screenshot(&self, image: DynamicImage) -> Result<u64, Box<dyn Error>> {
-        let timestamp = Utc::now();
-        let timestamp_str = timestamp.to_rfc3339();
+    /// Read and decrypt a stored blob, rewriting it under the current key
+    /// if it was still encrypted with the legacy one (the new checksum is
+    /// returned then)
+    ///
+    /// The file is checked against the `checksum` and `plain_size` recorded
+    /// when it was saved, so a damaged file is reported as such rather than
+    /// as a failed decryption, which would also mean a wrong key.
+    fn open_sealed(&self, path: &Path, checksum: Option<&str>, plain_size: Option<i64>) -> Result<(Vec<u8>, Option<String>), Box<dyn Error>> {
+        let data = fs::read(path)?;
+        if checksum.is_some_and(|expected| sha256_hex(&data) != expected) {
+            return Err(format!("{} is corrupted: checksum mismatch ({} bytes on disk)", path.display(), data.len()).into());
+        }
+        let (bytes, legacy) = self.unseal(data)?;
+        if plain_size.is_some_and(|size| size != bytes.len() as i64) {
+            return Err(format!("{} is corrupted: {} bytes decrypted, {} stored", path.display(), bytes.len(), plain_size.unwrap_or(0)).into());
+        }
+        if legacy {
+            let sealed = self.seal(&bytes)?;
+            fs::write(path, &sealed)?;
+            return Ok((bytes, Some(sha256_hex(&sealed))));
+        }
+        Ok((bytes, None))
+    }
 
-        // 1. Convert image to bytes (PNG)
-        let mut image_bytes = Vec::new();
-        image.write_to(
-            &mut std::io::Cursor::new(&mut image_bytes),
-            image::ImageFormat::Png,
-        )?;
+    pub async fn save_screenshot(&self, image: DynamicImage, screen: ScreenRef) -> Result<u64, Box<dyn Error>> {
+        let timestamp = Utc::now();
+        self.blocking(move |s| s.save_screenshot_at(image, timestamp, screen)).await
+    }
 
-        // 2. Compress
-        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-        encoder.write_all(&image_bytes)?;
-        let compressed_bytes = encoder.finish()?;
+    fn save_screenshot_at(&self, image: DynamicImage, timestamp: DateTime<Utc>, screen: ScreenRef) -> Result<u64, Box<dyn Error>> {
+        let timestamp_str = timestamp.to_rfc3339();
 
-        // 3. Encrypt
-        let final_bytes = if let Some(cipher) = &self.cipher {
-            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
-            let ciphertext = cipher
-                .encrypt(&nonce, compressed_bytes.as_ref())
-                .map_err(|e| format!("Encryption failed: {}", e))?;
+        // 1. Downscale, then encode in the configured format
+        let (w, h) = image.dimensions();
+        let image = match self.max_dimension {
+            Some(max) if w > max || h > max => image.resize(max, max, image::imageops::FilterType::Triangle),
+            _ => image,
+        };
+        let image_bytes = self.format.encode(&image, self.quality)?;
 
-            let mut result = nonce.to_vec();
-            result.extend(ciphertext);
-            result
+        // 2. Thumbnail, kept after retention drops the full image
+        let (w, h) = image.dimensions();
+        let thumb = if w > THUMBNAIL_SIZE.0 || h > THUMBNAIL_SIZE.1 {
+            image.thumbnail(THUMBNAIL_SIZE.0, THUMBNAIL_SIZE.1)
         } else {
-            eprintln!("[Storage] Warning: Saving unencrypted screenshot!");
-            compressed_bytes
+            image
         };
+        let thumb_bytes = self.format.encode(&thumb, self.quality)?;
+
+        // 3. Compress + encrypt
+        let final_bytes = self.seal(&image_bytes)?;
+        let final_thumb = self.seal(&thumb_bytes)?;
 
         // 4. Save to disk
         let date_folder = timestamp.format("%Y-%m-%d").to_string();
-        let file_name = format!("{}.enc", timestamp.format("%H-%M-%S-%3f"));
+        // Screens captured in the same millisecond get their own files
+        let stem = match screen.index {
+            0 => timestamp.format("%H-%M-%S-%3f").to_string(),
+            index => format!("{}-screen{}", timestamp.format("%H-%M-%S-%3f"), index),
+        };
+        let file_name = format!("{}.enc", stem);
+        let thumb_name = format!("{}.thumb.enc", stem);
 
         let dir_path = self.base_path.join("screenshots").join(&date_folder);
         if !dir_path.exists() {
             fs::create_dir_all(&dir_path)?;
         }
 
-        let file_path = dir_path.join(&file_name);
         let file_size = final_bytes.len() as i64;
-        fs::write(&file_path, &final_bytes)?;
+        let thumb_size = final_thumb.len() as i64;
+        fs::write(dir_path.join(&file_name), &final_bytes)?;
+        fs::write(dir_path.join(&thumb_name), &final_thumb)?;
 
         // 5. Insert into DB with file path
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db();
         let relative_path = format!("screenshots/{}/{}", date_folder, file_name);
+        let thumb_path = format!("screenshots/{}/{}", date_folder, thumb_name);
 
         conn.execute(
-            "INSERT INTO screenshots (timestamp, text_content, file_path, file_size) VALUES (?1, ?2, ?3, ?4)",
-            params![timestamp_str, "", relative_path, file_size],
+            "INSERT INTO screenshots (timestamp, text_content, file_path, file_size, thumb_path, thumb_size, image_format,
+                                      screen_index, group_id, checksum, plain_size, thumb_checksum, thumb_plain_size)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                timestamp_str,
+                "",
+                relative_path,
+                file_size,
+                thumb_path,
+                thumb_size,
+                self.format.name(),
+                screen.index,
+                screen.group_id.map(|g| g as i64),
+                sha256_hex(&final_bytes),
+                image_bytes.len() as i64,
+                sha256_hex(&final_thumb),
+                thumb_bytes.len() as i64
+            ],
         )?;
 
         let id = conn.last_insert_rowid() as u64;
@@ -213,121 +617,361 @@ impl Storage {
     }
 
     pub async fn save_metadata(&self, id: u64, text: &str) -> Result<(), Box<dyn Error>> {
-        let conn = Connection::open(&self.db_path)?;
-        conn.execute(
-            "UPDATE screenshots SET text_content = ?1 WHERE id = ?2",
-            params![text, id],
-        )?;
-        Ok(())
+        let text = text.to_string();
+        self.blocking(move |s| {
+            s.db().execute(
+                "UPDATE screenshots SET text_content = ?1 WHERE id = ?2",
+                params![text, id],
+            )?;
+            Ok(())
+        })
+        .await
     }
 
-    pub async fn load_metadata(&self, id: u64) -> Result<Metadata, Box<dyn Error>> {
-        let conn = Connection::open(&self.db_path)?;
-        let mut stmt =
-            conn.prepare("SELECT timestamp, text_content FROM screenshots WHERE id = ?1")?;
-
-        let metadata = stmt.query_row(params![id], |row| {
-            let ts_str: String = row.get(0)?;
-            let text: String = row.get(1)?;
-            let timestamp = DateTime::parse_from_rfc3339(&ts_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .map_err(|e| {
-                    rusqlite::Error::FromSqlConversionFailure(
-                        0,
-                        rusqlite::types::Type::Text,
-                        Box::new(e),
-                    )
-                })?;
+    /// Record how many regions were blurred before the capture was stored
+    pub async fn save_redactions(&self, id: u64, applied: u32) -> Result<(), Box<dyn Error>> {
+        self.blocking(move |s| {
+            s.db().execute(
+                "UPDATE screenshots SET redactions_applied = ?1 WHERE id = ?2",
+                params![applied, id],
+            )?;
+            Ok(())
+        })
+        .await
+    }
 
-            Ok(Metadata { timestamp, text })
-        })?;
+    /// Record that a privacy pattern masked a window out of the capture
+    pub async fn save_masked(&self, id: u64) -> Result<(), Box<dyn Error>> {
+        self.blocking(move |s| {
+            s.db().execute("UPDATE screenshots SET privacy_masked = 1 WHERE id = ?1", params![id])?;
+            Ok(())
+        })
+        .await
+    }
 
-        Ok(metadata)
+    /// Store capture context used by retention (active app, OCR confidence, embedding)
+    pub async fn save_context(
+        &self,
+        id: u64,
+        app_name: Option<&str>,
+        ocr_confidence: Option<f32>,
+        embedding: &[f32],
+    ) -> Result<(), Box<dyn Error>> {
+        let blob = f32_blob(embedding);
+        let q8 = self.quantize_keep_full.map(|_| QuantizedVector::quantize(embedding).to_bytes());
+        let app_name = app_name.map(str::to_string);
+        self.blocking(move |s| {
+            let conn = s.db();
+            conn.execute(
+                "UPDATE screenshots SET app_name = ?1, ocr_confidence = ?2, embedding = ?3, embedding_q8 = ?4
+                 WHERE id = ?5",
+                params![app_name, ocr_confidence, blob, q8, id],
+            )?;
+            s.trim_full_embeddings(&conn)?;
+            Ok(())
+        })
+        .await
     }
 
-    /// Load and decrypt a screenshot by ID
-    pub async fn load_screenshot(&self, id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
-        let conn = Connection::open(&self.db_path)?;
-        let file_path: String = conn.query_row(
-            "SELECT file_path FROM screenshots WHERE id = ?1",
-            params![id],
+    /// Drop f32 embeddings that have an int8 copy, except the most recent ones
+    fn trim_full_embeddings(&self, conn: &Connection) -> Result<u64, Box<dyn Error>> {
+        let Some(keep) = self.quantize_keep_full else { return Ok(0) };
+        let trimmed = conn.execute(
+            "UPDATE screenshots SET embedding = NULL
+             WHERE embedding IS NOT NULL AND embedding_q8 IS NOT NULL
+               AND id NOT IN (SELECT id FROM screenshots WHERE embedding IS NOT NULL
+                              ORDER BY id DESC LIMIT ?1)",
+            params![keep as i64],
+        )?;
+        Ok(trimmed as u64)
+    }
+
+    fn embedding_bytes(conn: &Connection) -> Result<u64, Box<dyn Error>> {
+        let bytes: i64 = conn.query_row(
+            "SELECT COALESCE(SUM(COALESCE(LENGTH(embedding), 0) + COALESCE(LENGTH(embedding_q8), 0)), 0)
+             FROM screenshots",
+            [],
             |row| row.get(0),
         )?;
+        Ok(bytes as u64)
+    }
+
+    /// Rewrite stored embeddings to the configured format.
+    ///
+    /// Quantizing adds int8 codes to every row and keeps f32 only for the
+    /// most recent captures; turning quantization off restores f32 (from
+    /// the original when still present, else dequantized) and drops codes.
+    pub async fn migrate_embeddings(&self) -> Result<EmbeddingMigration, Box<dyn Error>> {
+        self.blocking(move |s| {
+            let conn = s.db();
+            let bytes_before = Self::embedding_bytes(&conn)?;
+            let mut rewritten = 0;
+            let trimmed;
+
+            if s.quantize_keep_full.is_some() {
+                let rows: Vec<(u64, Vec<u8>)> = conn
+                    .prepare("SELECT id, embedding FROM screenshots WHERE embedding IS NOT NULL AND embedding_q8 IS NULL")?
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .filter_map(|r| r.ok())
+                    .collect();
+                for (id, blob) in rows {
+                    let q8 = QuantizedVector::quantize(&f32_from_blob(&blob)).to_bytes();
+                    conn.execute("UPDATE screenshots SET embedding_q8 = ?1 WHERE id = ?2", params![q8, id])?;
+                    rewritten += 1;
+                }
+                trimmed = s.trim_full_embeddings(&conn)?;
+            } else {
+                let rows: Vec<(u64, Vec<u8>)> = conn
+                    .prepare("SELECT id, embedding_q8 FROM screenshots WHERE embedding IS NULL AND embedding_q8 IS NOT NULL")?
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .filter_map(|r| r.ok())
+                    .collect();
+                for (id, q8) in rows {
+                    let restored = QuantizedVector::from_bytes(&q8).map(|q| q.dequantize()).unwrap_or_default();
+                    conn.execute("UPDATE screenshots SET embedding = ?1 WHERE id = ?2", params![f32_blob(&restored), id])?;
+                    rewritten += 1;
+                }
+                trimmed = conn.execute(
+                    "UPDATE screenshots SET embedding_q8 = NULL WHERE embedding_q8 IS NOT NULL",
+                    [],
+                )? as u64;
+            }
 
-        let full_path = self.base_path.join(&file_path);
+            Ok(EmbeddingMigration { rewritten, trimmed, bytes_before, bytes_after: Self::embedding_bytes(&conn)? })
+        })
+        .await
+    }
 
-        if !full_path.exists() {
-            return Err(format!("Screenshot file not found: {}", file_path).into());
-        }
+    /// Load the stored embedding of a capture
+    pub async fn load_embedding(&self, id: u64) -> Result<Vec<f32>, Box<dyn Error>> {
+        self.blocking(move |s| {
+            let conn = s.db();
+            let (blob, q8): (Option<Vec<u8>>, Option<Vec<u8>>) = conn.query_row(
+                "SELECT embedding, embedding_q8 FROM screenshots WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+
+            // Prefer the original; older captures only have int8 codes
+            Ok(match (blob, q8.and_then(|q| QuantizedVector::from_bytes(&q))) {
+                (Some(blob), _) => f32_from_blob(&blob),
+                (None, Some(q)) => q.dequantize(),
+                (None, None) => Vec::new(),
+            })
+        })
+        .await
+    }
 
-        let encrypted_data = fs::read(&full_path)?;
+    pub async fn load_metadata(&self, id: u64) -> Result<Metadata, Box<dyn Error>> {
+        self.blocking(move |s| {
+            let conn = s.db();
+            let mut stmt = conn.prepare(
+                "SELECT timestamp, text_content, COALESCE(downsampled, 0), screen_index, group_id,
+                        COALESCE(privacy_masked, 0)
+                 FROM screenshots WHERE id = ?1",
+            )?;
+
+            let metadata = stmt.query_row(params![id], |row| {
+                let timestamp = timestamp_column(row, 0)?;
+                let text: String = row.get(1)?;
+                let downsampled: i64 = row.get(2)?;
+                Ok(Metadata {
+                    timestamp,
+                    text,
+                    full_image: downsampled == 0,
+                    screen: screen_columns(row, 3)?,
+                    masked: row.get::<_, i64>(5)? != 0,
+                })
+            })?;
+
+            Ok(metadata)
+        })
+        .await
+    }
 
-        // Decrypt
-        let compressed_data = if let Some(cipher) = &self.cipher {
-            if encrypted_data.len() < 12 {
-                return Err("Encrypted data too short".into());
-            }
+    /// ID and text of every capture newer than `after_id`, oldest first
+    pub async fn captures_after(&self, after_id: u64) -> Result<Vec<(u64, String)>, Box<dyn Error>> {
+        self.blocking(move |s| {
+            let conn = s.db();
+            let mut stmt = conn.prepare("SELECT id, text_content FROM screenshots WHERE id > ?1 ORDER BY id")?;
+            let captures = stmt
+                .query_map(params![after_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<(u64, String)>, _>>()?;
+            Ok(captures)
+        })
+        .await
+    }
 
-            let nonce = Nonce::from_slice(&encrypted_data[..12]);
-            let ciphertext = &encrypted_data[12..];
+    /// IDs of the captures taken in `[from, to)`
+    pub async fn ids_in_range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<HashSet<u64>, Box<dyn Error>> {
+        self.blocking(move |s| {
+            let conn = s.db();
+            let mut stmt = conn.prepare("SELECT id FROM screenshots WHERE timestamp >= ?1 AND timestamp < ?2")?;
+            let ids = stmt
+                .query_map(params![from.to_rfc3339(), to.to_rfc3339()], |row| row.get(0))?
+                .collect::<Result<HashSet<u64>, _>>()?;
+            Ok(ids)
+        })
+        .await
+    }
 
-            cipher
-                .decrypt(nonce, ciphertext)
-                .map_err(|e| format!("Decryption failed: {}", e))?
-        } else {
-            encrypted_data
-        };
+    /// Every capture taken in `[from, to)`, oldest first
+    pub async fn list_range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<CaptureSummary>, Box<dyn Error>> {
+        self.blocking(move |s| {
+            let conn = s.db();
+            let mut stmt = conn.prepare(
+                "SELECT id, timestamp, app_name, COALESCE(text_content, ''), COALESCE(downsampled, 0),
+                        thumb_path IS NOT NULL, screen_index, group_id
+                 FROM screenshots WHERE timestamp >= ?1 AND timestamp < ?2
+                 ORDER BY timestamp, id",
+            )?;
+            let captures = stmt
+                .query_map(params![from.to_rfc3339(), to.to_rfc3339()], |row| {
+                    Ok(CaptureSummary {
+                        id: row.get(0)?,
+                        timestamp: timestamp_column(row, 1)?,
+                        app_name: row.get(2)?,
+                        text: row.get(3)?,
+                        full_image: row.get::<_, i64>(4)? == 0,
+                        thumbnail: row.get(5)?,
+                        screen: screen_columns(row, 6)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(captures)
+        })
+        .await
+    }
 
-        // Decompress
-        let mut decoder = GzDecoder::new(&compressed_data[..]);
-        let mut decompressed = Vec::new();
-        decoder.read_to_end(&mut decompressed)?;
+    /// Load and decrypt a screenshot by ID
+    pub async fn load_screenshot(&self, id: u64) -> Result<StoredImage, Box<dyn Error>> {
+        self.blocking(move |s| {
+            let conn = s.db();
+            let (file_path, downsampled, format, checksum, plain_size): (Option<String>, i64, Option<String>, Option<String>, Option<i64>) =
+                conn.query_row(
+                    "SELECT file_path, COALESCE(downsampled, 0), image_format, checksum, plain_size FROM screenshots WHERE id = ?1",
+                    params![id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+                )?;
+
+            let file_path = match file_path {
+                Some(p) if downsampled == 0 => p,
+                _ => return Err("Full image removed by retention; only the thumbnail is kept".into()),
+            };
+
+            let full_path = s.base_path.join(&file_path);
+
+            if !full_path.exists() {
+                return Err(format!("Screenshot file not found: {}", file_path).into());
+            }
 
-        Ok(decompressed)
+            let (bytes, resealed) = s.open_sealed(&full_path, checksum.as_deref(), plain_size)?;
+            if let Some(checksum) = resealed {
+                conn.execute(
+                    "UPDATE screenshots SET checksum = ?1, plain_size = ?2 WHERE id = ?3",
+                    params![checksum, bytes.len() as i64, id],
+                )?;
+            }
+            Ok(StoredImage { bytes, format: stored_format(format) })
+        })
+        .await
+    }
+
+    /// Load and decrypt the thumbnail of a screenshot by ID
+    pub async fn load_thumbnail(&self, id: u64) -> Result<StoredImage, Box<dyn Error>> {
+        self.blocking(move |s| {
+            let conn = s.db();
+            let (thumb_path, format, checksum, plain_size): (Option<String>, Option<String>, Option<String>, Option<i64>) = conn.query_row(
+                "SELECT thumb_path, image_format, thumb_checksum, thumb_plain_size FROM screenshots WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )?;
+
+            let thumb_path = thumb_path.ok_or("No thumbnail stored for this capture")?;
+            let full_path = s.base_path.join(&thumb_path);
+
+            if !full_path.exists() {
+                return Err(format!("Thumbnail file not found: {}", thumb_path).into());
+            }
+
+            let (bytes, resealed) = s.open_sealed(&full_path, checksum.as_deref(), plain_size)?;
+            if let Some(checksum) = resealed {
+                conn.execute(
+                    "UPDATE screenshots SET thumb_checksum = ?1, thumb_plain_size = ?2 WHERE id = ?3",
+                    params![checksum, bytes.len() as i64, id],
+                )?;
+            }
+            Ok(StoredImage { bytes, format: stored_format(format) })
+        })
+        .await
     }
 
     /// Get storage statistics
     pub async fn get_stats(&self) -> Result<StorageStats, Box<dyn Error>> {
-        let conn = Connection::open(&self.db_path)?;
-
-        let total_screenshots: u64 = conn.query_row(
-            "SELECT COUNT(*) FROM screenshots",
-            [],
-            |row| row.get(0),
-        )?;
+        self.blocking(move |s| {
+            let conn = s.db();
 
-        let total_size: i64 = conn
-            .query_row(
-                "SELECT COALESCE(SUM(file_size), 0) FROM screenshots",
+            let total_screenshots: u64 = conn.query_row(
+                "SELECT COUNT(*) FROM screenshots",
                 [],
                 |row| row.get(0),
-            )
-            .unwrap_or(0);
+            )?;
 
-        let oldest: Option<String> = conn
-            .query_row(
-                "SELECT MIN(timestamp) FROM screenshots",
-                [],
-                |row| row.get(0),
-            )
-            .ok();
+            let oldest: Option<String> = conn
+                .query_row(
+                    "SELECT MIN(timestamp) FROM screenshots",
+                    [],
+                    |row| row.get(0),
+                )
+                .ok();
 
-        let newest: Option<String> = conn
-            .query_row(
-                "SELECT MAX(timestamp) FROM screenshots",
-                [],
-                |row| row.get(0),
-            )
-            .ok();
-
-        Ok(StorageStats {
-            total_screenshots,
-            storage_used_mb: total_size as f64 / 1024.0 / 1024.0,
-            oldest_screenshot: oldest.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
-                .map(|dt| dt.with_timezone(&Utc)),
-            newest_screenshot: newest.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
-                .map(|dt| dt.with_timezone(&Utc)),
+            let newest: Option<String> = conn
+                .query_row(
+                    "SELECT MAX(timestamp) FROM screenshots",
+                    [],
+                    |row| row.get(0),
+                )
+                .ok();
+
+            let tier = |downsampled: i64| -> (u64, f64) {
+                conn.query_row(
+                    "SELECT COUNT(*), COALESCE(SUM(COALESCE(file_size, 0) + COALESCE(thumb_size, 0)), 0)
+                     FROM screenshots WHERE COALESCE(downsampled, 0) = ?1",
+                    params![downsampled],
+                    |row| Ok((row.get::<_, u64>(0)?, row.get::<_, i64>(1)?)),
+                )
+                .map(|(count, bytes)| (count, bytes as f64 / 1024.0 / 1024.0))
+                .unwrap_or((0, 0.0))
+            };
+            let (full_count, full_mb) = tier(0);
+            let (downsampled_count, downsampled_mb) = tier(1);
+
+            let purged_count: u64 = conn
+                .query_row(
+                    "SELECT value FROM retention_state WHERE key = 'purged'",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap_or(0);
+
+            let embedding_bytes = Self::embedding_bytes(&conn).unwrap_or(0);
+
+            Ok(StorageStats {
+                total_screenshots,
+                storage_used_mb: full_mb + downsampled_mb,
+                oldest_screenshot: oldest.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                    .map(|dt| dt.with_timezone(&Utc)),
+                newest_screenshot: newest.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                    .map(|dt| dt.with_timezone(&Utc)),
+                full_count,
+                full_mb,
+                downsampled_count,
+                downsampled_mb,
+                purged_count,
+                embedding_bytes,
+            })
         })
+        .await
     }
 
     /// Get current storage usage in MB
@@ -336,92 +980,300 @@ impl Storage {
         Ok(stats.storage_used_mb)
     }
 
-    /// Cleanup old screenshots based on retention policy
-    pub async fn cleanup_old_snapshots(&self) -> Result<u64, Box<dyn Error>> {
-        let cutoff = Utc::now() - Duration::days(self.retention_days);
-        let cutoff_str = cutoff.to_rfc3339();
+    /// Apply tiered retention
+    ///
+    /// - younger than `retention_days`: everything is kept
+    /// - older: one representative capture per app per hour is kept (highest
+    ///   OCR confidence, then longest text) with its thumbnail, text and
+    ///   embedding; its full image and all other captures of that hour go
+    /// - older than `delete_after_days`: removed entirely
+    pub async fn cleanup_old_snapshots(&self) -> Result<CleanupReport, Box<dyn Error>> {
+        let now = Utc::now();
+        self.blocking(move |s| s.cleanup_at(now)).await
+    }
 
-        let conn = Connection::open(&self.db_path)?;
+    fn cleanup_at(&self, now: DateTime<Utc>) -> Result<CleanupReport, Box<dyn Error>> {
+        let conn = self.db();
+        let mut report = CleanupReport::default();
+
+        // Tier 3: delete entirely
+        let delete_cutoff = (now - Duration::days(self.delete_after_days)).to_rfc3339();
+        let expired: Vec<u64> = {
+            let mut stmt = conn.prepare("SELECT id FROM screenshots WHERE timestamp < ?1")?;
+            let ids = stmt
+                .query_map(params![delete_cutoff], |row| row.get(0))?
+                .filter_map(|r| r.ok())
+                .collect();
+            ids
+        };
+        for id in expired {
+            if self.delete_capture(&conn, id)? {
+                report.deleted += 1;
+            }
+        }
 
-        // Get files to delete
-        let mut stmt = conn.prepare(
-            "SELECT id, file_path FROM screenshots WHERE timestamp < ?1"
-        )?;
+        // Tier 2: downsample whole hours that are past the retention window
+        let downsample_cutoff = now - Duration::days(self.retention_days);
+        let hour_cutoff = downsample_cutoff.format("%Y-%m-%dT%H").to_string();
+
+        let candidates: Vec<(u64, String, String, f64, i64, i64, i64)> = {
+            let mut stmt = conn.prepare(
+                "SELECT id, COALESCE(app_name, ''), substr(timestamp, 1, 13),
+                        COALESCE(ocr_confidence, 0), length(COALESCE(text_content, '')),
+                        COALESCE(downsampled, 0), COALESCE(screen_index, 0)
+                 FROM screenshots
+                 WHERE substr(timestamp, 1, 13) < ?1
+                 ORDER BY timestamp",
+            )?;
+            let rows = stmt
+                .query_map(params![hour_cutoff], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?))
+                })?
+                .filter_map(|r| r.ok())
+                .collect();
+            rows
+        };
 
-        let files_to_delete: Vec<(u64, String)> = stmt
-            .query_map(params![cutoff_str], |row| {
-                Ok((row.get(0)?, row.get(1)?))
-            })?
-            .filter_map(|r| r.ok())
-            .collect();
+        // One representative per app, hour and screen
+        let mut groups: std::collections::BTreeMap<(String, String, i64), Vec<(u64, f64, i64, i64)>> =
+            std::collections::BTreeMap::new();
+        for (id, app, hour, confidence, text_len, downsampled, screen) in candidates {
+            groups.entry((app, hour, screen)).or_default().push((id, confidence, text_len, downsampled));
+        }
 
-        let mut deleted_count = 0;
+        for members in groups.values() {
+            // Already reduced to a single representative
+            if members.len() == 1 && members[0].3 == 1 {
+                continue;
+            }
 
-        for (id, file_path) in &files_to_delete {
-            // Delete file from disk
-            let full_path = self.base_path.join(file_path);
-            if full_path.exists() {
-                if let Err(e) = fs::remove_file(&full_path) {
-                    eprintln!("[Storage] Failed to delete file {}: {}", file_path, e);
-                    continue;
+            let keep = members
+                .iter()
+                .max_by(|a, b| {
+                    a.1.partial_cmp(&b.1)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                        .then(a.2.cmp(&b.2))
+                        .then(b.0.cmp(&a.0))
+                })
+                .map(|m| m.0);
+
+            for &(id, _, _, downsampled) in members {
+                if Some(id) == keep {
+                    if downsampled == 0 {
+                        self.drop_full_image(&conn, id)?;
+                        report.downsampled += 1;
+                    }
+                } else if self.delete_capture(&conn, id)? {
+                    report.deleted += 1;
                 }
             }
+        }
 
-            // Delete from database
-            conn.execute("DELETE FROM screenshots WHERE id = ?1", params![id])?;
-            deleted_count += 1;
+        if report.deleted > 0 {
+            conn.execute(
+                "INSERT INTO retention_state (key, value) VALUES ('purged', ?1)
+                 ON CONFLICT(key) DO UPDATE SET value = value + ?1",
+                params![report.deleted as i64],
+            )?;
         }
 
         // Clean up empty date folders
         self.cleanup_empty_folders()?;
 
-        if deleted_count > 0 {
-            println!("[Storage] Cleaned up {} old screenshots", deleted_count);
+        if report.deleted > 0 || report.downsampled > 0 {
+            println!(
+                "[Storage] Retention: downsampled {}, deleted {} screenshots",
+                report.downsampled, report.deleted
+            );
         }
 
-        Ok(deleted_count)
+        Ok(report)
     }
 
-    /// Cleanup to meet storage limits
-    pub async fn cleanup_to_limit(&self) -> Result<u64, Box<dyn Error>> {
-        let mut deleted_count = 0;
+    /// Remove a capture's files and row; returns false if a file could not be removed
+    fn delete_capture(&self, conn: &Connection, id: u64) -> Result<bool, Box<dyn Error>> {
+        let (file_path, thumb_path): (Option<String>, Option<String>) = conn.query_row(
+            "SELECT file_path, thumb_path FROM screenshots WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
 
-        loop {
-            let used_mb = self.get_used_space_mb().await?;
+        for path in [file_path, thumb_path].into_iter().flatten() {
+            let full_path = self.base_path.join(&path);
+            if full_path.exists() {
+                if let Err(e) = fs::remove_file(&full_path) {
+                    eprintln!("[Storage] Failed to delete file {}: {}", path, e);
+                    return Ok(false);
+                }
+            }
+        }
+
+        conn.execute("DELETE FROM screenshots WHERE id = ?1", params![id])?;
+        Ok(true)
+    }
 
-            if used_mb <= self.max_storage_mb as f64 {
-                break;
+    /// Delete every capture taken in `[from, to)`, files included.
+    ///
+    /// Returns how many were removed; a capture whose file could not be
+    /// removed is kept (and logged) so it can be retried.
+    pub async fn delete_range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<u64, Box<dyn Error>> {
+        self.blocking(move |s| {
+            let conn = s.db();
+            let ids = conn
+                .prepare("SELECT id FROM screenshots WHERE timestamp >= ?1 AND timestamp < ?2 ORDER BY id")?
+                .query_map(params![from.to_rfc3339(), to.to_rfc3339()], |row| row.get(0))?
+                .collect::<Result<Vec<u64>, _>>()?;
+
+            let mut deleted = 0;
+            for id in ids {
+                if s.delete_capture(&conn, id)? {
+                    deleted += 1;
+                }
             }
 
-            // Delete oldest screenshot
-            let conn = Connection::open(&self.db_path)?;
+            if deleted > 0 {
+                s.cleanup_empty_folders()?;
+            }
+            Ok(deleted)
+        })
+        .await
+    }
 
-            let oldest: Option<(u64, String)> = conn
-                .query_row(
-                    "SELECT id, file_path FROM screenshots ORDER BY timestamp ASC LIMIT 1",
-                    [],
-                    |row| Ok((row.get(0)?, row.get(1)?)),
-                )
-                .ok();
+    /// Drop the full-resolution image, keeping thumbnail, text and embedding
+    fn drop_full_image(&self, conn: &Connection, id: u64) -> Result<(), Box<dyn Error>> {
+        let file_path: Option<String> = conn.query_row(
+            "SELECT file_path FROM screenshots WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
 
-            if let Some((id, file_path)) = oldest {
-                let full_path = self.base_path.join(&file_path);
-                if full_path.exists() {
-                    fs::remove_file(&full_path)?;
-                }
-                conn.execute("DELETE FROM screenshots WHERE id = ?1", params![id])?;
-                deleted_count += 1;
-            } else {
-                break; // No more screenshots to delete
+        if let Some(path) = file_path {
+            let full_path = self.base_path.join(&path);
+            if full_path.exists() {
+                fs::remove_file(&full_path)?;
             }
         }
 
-        if deleted_count > 0 {
-            self.cleanup_empty_folders()?;
-            println!("[Storage] Removed {} screenshots to meet storage limit", deleted_count);
+        conn.execute(
+            "UPDATE screenshots SET downsampled = 1, file_path = NULL, file_size = 0, checksum = NULL, plain_size = NULL
+             WHERE id = ?1",
+            params![id],
+        )?;
+        Ok(())
+    }
+
+    /// Check every stored file against its checksum, and look for files no
+    /// capture refers to (left by a crash between writing a capture and
+    /// recording it), removing those if `delete_orphans`
+    pub async fn verify_all(&self, delete_orphans: bool) -> Result<VerifyReport, Box<dyn Error>> {
+        self.blocking(move |s| s.verify_files(delete_orphans, ORPHAN_GRACE)).await
+    }
+
+    fn verify_files(&self, delete_orphans: bool, grace: std::time::Duration) -> Result<VerifyReport, Box<dyn Error>> {
+        // Taken in one go, so captures aren't held up while files are hashed
+        let stored: Vec<(String, Option<String>)> = {
+            let conn = self.db();
+            let mut stmt = conn.prepare(
+                "SELECT file_path, checksum FROM screenshots WHERE file_path IS NOT NULL
+                 UNION ALL
+                 SELECT thumb_path, thumb_checksum FROM screenshots WHERE thumb_path IS NOT NULL",
+            )?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<_, _>>()?;
+            rows
+        };
+
+        let mut report = VerifyReport::default();
+        for (path, checksum) in &stored {
+            let full_path = self.base_path.join(path);
+            if !full_path.exists() {
+                report.missing.push(path.clone());
+                continue;
+            }
+            match checksum {
+                None => report.unchecked += 1,
+                Some(checksum) if sha256_hex(&fs::read(&full_path)?) == *checksum => report.verified += 1,
+                Some(_) => report.corrupted.push(path.clone()),
+            }
         }
 
-        Ok(deleted_count)
+        // Orphans: screenshots/<date>/<file> with no row
+        let known: HashSet<&str> = stored.iter().map(|(path, _)| path.as_str()).collect();
+        let screenshots_dir = self.base_path.join("screenshots");
+        if screenshots_dir.exists() {
+            for folder in fs::read_dir(&screenshots_dir)? {
+                let folder = folder?.path();
+                if !folder.is_dir() {
+                    continue;
+                }
+                let date = folder.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                for entry in fs::read_dir(&folder)? {
+                    let entry = entry?;
+                    let relative = format!("screenshots/{}/{}", date, entry.file_name().to_string_lossy());
+                    let fresh = entry.metadata()?.modified().ok().and_then(|t| t.elapsed().ok()).is_none_or(|age| age < grace);
+                    if known.contains(relative.as_str()) || fresh {
+                        continue;
+                    }
+                    if delete_orphans {
+                        match fs::remove_file(entry.path()) {
+                            Ok(()) => report.orphans_deleted += 1,
+                            Err(e) => eprintln!("[Storage] Failed to delete orphan {}: {}", relative, e),
+                        }
+                    }
+                    report.orphans.push(relative);
+                }
+            }
+            if report.orphans_deleted > 0 {
+                self.cleanup_empty_folders()?;
+            }
+        }
+
+        report.missing.sort();
+        report.corrupted.sort();
+        report.orphans.sort();
+        Ok(report)
+    }
+
+    /// Cleanup to meet storage limits
+    pub async fn cleanup_to_limit(&self) -> Result<u64, Box<dyn Error>> {
+        self.blocking(|s| {
+            let conn = s.db();
+            let limit_bytes = s.max_storage_mb as f64 * 1024.0 * 1024.0;
+            let mut deleted_count = 0;
+
+            while Self::used_bytes(&conn)? as f64 > limit_bytes {
+                // Delete oldest screenshot
+                let oldest: Option<u64> = conn
+                    .query_row(
+                        "SELECT id FROM screenshots ORDER BY timestamp ASC LIMIT 1",
+                        [],
+                        |row| row.get(0),
+                    )
+                    .ok();
+
+                match oldest {
+                    Some(id) if s.delete_capture(&conn, id)? => deleted_count += 1,
+                    _ => break, // Nothing left, or a file could not be removed
+                }
+            }
+
+            if deleted_count > 0 {
+                s.cleanup_empty_folders()?;
+                println!("[Storage] Removed {} screenshots to meet storage limit", deleted_count);
+            }
+
+            Ok(deleted_count)
+        })
+        .await
+    }
+
+    /// Bytes taken by stored images and thumbnails
+    fn used_bytes(conn: &Connection) -> Result<i64, Box<dyn Error>> {
+        let bytes = conn.query_row(
+            "SELECT COALESCE(SUM(COALESCE(file_size, 0) + COALESCE(thumb_size, 0)), 0) FROM screenshots",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(bytes)
     }
 
     /// Remove empty date folders
@@ -448,21 +1300,60 @@ impl Storage {
         Ok(())
     }
 
-    /// Full-text search in screenshots
-    pub async fn search_text(&self, query: &str, limit: usize) -> Result<Vec<(u64, String, f64)>, Box<dyn Error>> {
-        let conn = Connection::open(&self.db_path)?;
+    /// Full-text search in screenshots, best match first
+    pub async fn search_text(&self, query: &str, limit: usize) -> Result<Vec<TextHit>, Box<dyn Error>> {
+        let query = query.to_string();
+        self.blocking(move |s| s.find_text(&query, None, limit)).await
+    }
+
+    /// Full-text search limited to captures taken in `[from, to)`
+    pub async fn search_text_in_range(
+        &self,
+        query: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<TextHit>, Box<dyn Error>> {
+        let query = query.to_string();
+        self.blocking(move |s| s.find_text(&query, Some((from, to)), limit)).await
+    }
+
+    fn find_text(
+        &self,
+        query: &str,
+        range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+        limit: usize,
+    ) -> Result<Vec<TextHit>, Box<dyn Error>> {
+        let conn = self.db();
 
         let mut stmt = conn.prepare(
-            "SELECT id, text_content, bm25(screenshots_fts) as score
+            "SELECT screenshots_fts.rowid, screenshots_fts.text_content, bm25(screenshots_fts) as score,
+                    s.timestamp, COALESCE(s.downsampled, 0), s.screen_index, s.group_id,
+                    COALESCE(s.privacy_masked, 0)
              FROM screenshots_fts
-             WHERE text_content MATCH ?1
+             JOIN screenshots s ON s.id = screenshots_fts.rowid
+             WHERE screenshots_fts.text_content MATCH ?1
+               AND (?3 IS NULL OR s.timestamp >= ?3)
+               AND (?4 IS NULL OR s.timestamp < ?4)
              ORDER BY score
              LIMIT ?2"
         )?;
 
-        let results: Vec<(u64, String, f64)> = stmt
-            .query_map(params![query, limit as i64], |row| {
-                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        let (from, to) = match range {
+            Some((from, to)) => (Some(from.to_rfc3339()), Some(to.to_rfc3339())),
+            None => (None, None),
+        };
+        let results: Vec<TextHit> = stmt
+            .query_map(params![query, limit as i64, from, to], |row| {
+                Ok(TextHit {
+                    id: row.get(0)?,
+                    text: row.get(1)?,
+                    score: row.get(2)?,
+                    timestamp: timestamp_column(row, 3)?,
+                    full_image: row.get::<_, i64>(4)? == 0,
+                    screen: screen_columns(row, 5)?,
+                    masked: row.get::<_, i64>(7)? != 0,
+                })
             })?
             .filter_map(|r| r.ok())
             .collect();
@@ -471,9 +1362,45 @@ impl Storage {
     }
 }
 
+/// Read an RFC 3339 timestamp column
+fn timestamp_column(row: &rusqlite::Row, index: usize) -> rusqlite::Result<DateTime<Utc>> {
+    let ts_str: String = row.get(index)?;
+    DateTime::parse_from_rfc3339(&ts_str)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+/// `screen_index` and `group_id` starting at column `index`
+fn screen_columns(row: &rusqlite::Row, index: usize) -> rusqlite::Result<Option<ScreenRef>> {
+    let screen: Option<u32> = row.get(index)?;
+    let group_id: Option<i64> = row.get(index + 1)?;
+    Ok(screen.map(|index| ScreenRef { index, group_id: group_id.map(|g| g as u64) }))
+}
+
+/// Format of a row's images; PNG for rows from before the column existed
+fn stored_format(name: Option<String>) -> CaptureFormat {
+    name.as_deref().and_then(CaptureFormat::from_name).unwrap_or(CaptureFormat::Png)
+}
+
+/// Hex SHA-256, as stored in the checksum columns
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn f32_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn f32_from_blob(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
 
     #[tokio::test]
     async fn test_storage_creation() {
@@ -497,4 +1424,421 @@ mod tests {
         // Cleanup
         let _ = fs::remove_dir_all(&temp_dir);
     }
+
+    fn test_image(shade: u8) -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_pixel(640, 360, image::Rgb([shade, shade, shade])))
+    }
+
+    #[tokio::test]
+    async fn test_legacy_key_captures_are_reencrypted() {
+        let temp_dir = std::env::temp_dir().join(format!("eva_test_legacy_key_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        // Stored by a build that derived the key from a password
+        let mut old = Storage::new(temp_dir.to_str().unwrap()).await.unwrap();
+        old.set_legacy_password("eva_tm_user_host_secret").unwrap();
+        old.cipher = old.legacy_cipher.take();
+        let id = old.save_screenshot(test_image(90), ScreenRef::default()).await.unwrap();
+
+        let mut storage = Storage::new(temp_dir.to_str().unwrap()).await.unwrap();
+        storage.set_encryption_key(&[7u8; 32]);
+        assert!(storage.load_screenshot(id).await.is_err());
+
+        storage.set_legacy_password("eva_tm_user_host_secret").unwrap();
+        let image = storage.load_screenshot(id).await.unwrap();
+        let thumb = storage.load_thumbnail(id).await.unwrap();
+
+        // Rewritten under the new key: readable without the legacy one
+        storage.legacy_cipher = None;
+        assert_eq!(storage.load_screenshot(id).await.unwrap(), image);
+        assert_eq!(storage.load_thumbnail(id).await.unwrap(), thumb);
+        assert!(old.load_screenshot(id).await.is_err());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_verify_catches_corruption_missing_files_and_orphans() {
+        let temp_dir = std::env::temp_dir().join(format!("eva_test_verify_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        let mut storage = Storage::new(temp_dir.to_str().unwrap()).await.unwrap();
+        storage.set_encryption_key(&[3u8; 32]);
+
+        let now = Utc::now();
+        let ids: Vec<u64> = (0..3)
+            .map(|i| storage.save_screenshot_at(test_image(i * 40), now + Duration::seconds(i as i64), ScreenRef::default()).unwrap())
+            .collect();
+        let report = storage.verify_files(false, std::time::Duration::ZERO).unwrap();
+        assert_eq!(report, VerifyReport { verified: 6, ..VerifyReport::default() });
+        assert!(report.is_clean());
+
+        let path = |id: u64, column: &str| -> String {
+            storage.db().query_row(&format!("SELECT {} FROM screenshots WHERE id = ?1", column), params![id], |row| row.get(0)).unwrap()
+        };
+        // A crash mid-write: the file is cut short
+        let truncated = path(ids[0], "file_path");
+        let data = fs::read(temp_dir.join(&truncated)).unwrap();
+        fs::write(temp_dir.join(&truncated), &data[..data.len() / 2]).unwrap();
+        // A lost thumbnail, and a file nothing refers to
+        let lost = path(ids[1], "thumb_path");
+        fs::remove_file(temp_dir.join(&lost)).unwrap();
+        let orphan = format!("{}/stray.enc", truncated.rsplit_once('/').unwrap().0);
+        fs::write(temp_dir.join(&orphan), b"left behind").unwrap();
+
+        let report = storage.verify_files(false, std::time::Duration::ZERO).unwrap();
+        assert_eq!(report.verified, 4);
+        assert_eq!(report.corrupted, vec![truncated.clone()]);
+        assert_eq!(report.missing, vec![lost]);
+        assert_eq!(report.orphans, vec![orphan.clone()]);
+        assert_eq!((report.files(), report.orphans_deleted), (6, 0));
+        assert!(temp_dir.join(&orphan).exists());
+
+        // Reported as damage, not as a key problem
+        let err = storage.load_screenshot(ids[0]).await.unwrap_err().to_string();
+        assert!(err.contains("corrupted") && err.contains("checksum"), "{}", err);
+        assert!(storage.load_screenshot(ids[2]).await.is_ok());
+
+        // Young files may belong to a capture being written
+        assert!(storage.verify_all(true).await.unwrap().orphans.is_empty());
+        let report = storage.verify_files(true, std::time::Duration::ZERO).unwrap();
+        assert_eq!((report.orphans.len(), report.orphans_deleted), (1, 1));
+        assert!(!temp_dir.join(&orphan).exists());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_list_range_orders_a_day() {
+        let temp_dir = std::env::temp_dir().join(format!("eva_test_list_range_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        let storage = Storage::new(temp_dir.to_str().unwrap()).await.unwrap();
+        let day = Utc.with_ymd_and_hms(2026, 3, 10, 0, 0, 0).unwrap();
+
+        // Saved out of order, across the day and either side of it
+        let mut saved = Vec::new();
+        for (hour, app) in [(15, Some("browser")), (9, Some("editor")), (23, None), (0, Some("terminal"))] {
+            let id = storage.save_screenshot_at(test_image(hour as u8), day + Duration::hours(hour), ScreenRef::default()).unwrap();
+            storage.save_metadata(id, &format!("work at {}", hour)).await.unwrap();
+            storage.save_context(id, app, None, &[]).await.unwrap();
+            saved.push((hour, id));
+        }
+        storage.save_screenshot_at(test_image(1), day - Duration::minutes(1), ScreenRef::default()).unwrap();
+        storage.save_screenshot_at(test_image(2), day + Duration::hours(24), ScreenRef::default()).unwrap();
+
+        let listed = storage.list_range(day, day + Duration::hours(24)).await.unwrap();
+        saved.sort();
+        assert_eq!(listed.iter().map(|c| c.id).collect::<Vec<_>>(), saved.iter().map(|(_, id)| *id).collect::<Vec<_>>());
+        assert!(listed.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+        assert_eq!(listed[0].app_name.as_deref(), Some("terminal"));
+        assert_eq!(listed[1].text, "work at 9");
+        assert_eq!(listed[3].app_name, None);
+        assert!(listed.iter().all(|c| c.thumbnail && c.full_image));
+        for capture in &listed {
+            let thumb = storage.load_thumbnail(capture.id).await.unwrap();
+            let decoded = image::load_from_memory(&thumb.bytes).unwrap();
+            assert!(decoded.width() <= THUMBNAIL_SIZE.0 && decoded.height() <= THUMBNAIL_SIZE.1);
+        }
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_tiered_retention() {
+        let temp_dir = std::env::temp_dir().join(format!("eva_test_retention_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        let mut storage = Storage::new(temp_dir.to_str().unwrap()).await.unwrap();
+        storage.set_retention_tiers(30, 365);
+
+        let now = Utc::now();
+        let old_hour = (now - Duration::days(40)).date_naive().and_hms_opt(10, 0, 0).unwrap().and_utc();
+
+        // Three captures of the same app in one old hour; the longest text wins
+        let mut old_ids = Vec::new();
+        for (i, text) in ["short", "the most descriptive capture text", "mid text"].iter().enumerate() {
+            let id = storage
+                .save_screenshot_at(test_image(i as u8), old_hour + Duration::minutes(i as i64 * 10), ScreenRef::default())
+                .unwrap();
+            storage.save_metadata(id, text).await.unwrap();
+            storage.save_context(id, Some("editor"), None, &[0.5, 0.25]).await.unwrap();
+            old_ids.push(id);
+        }
+
+        // Another app in the same hour keeps its own representative
+        let other = storage.save_screenshot_at(test_image(9), old_hour + Duration::minutes(5), ScreenRef::default()).unwrap();
+        storage.save_context(other, Some("browser"), None, &[]).await.unwrap();
+
+        // Past the delete horizon
+        let ancient = storage.save_screenshot_at(test_image(7), now - Duration::days(400), ScreenRef::default()).unwrap();
+
+        // Recent capture stays untouched
+        let recent = storage.save_screenshot_at(test_image(3), now - Duration::days(1), ScreenRef::default()).unwrap();
+        storage.save_metadata(recent, "recent capture text").await.unwrap();
+
+        let report = storage.cleanup_at(now).unwrap();
+        assert_eq!(report.downsampled, 2);
+        assert_eq!(report.deleted, 3);
+
+        let kept = old_ids[1];
+        let meta = storage.load_metadata(kept).await.unwrap();
+        assert!(!meta.full_image);
+        assert!(storage.load_screenshot(kept).await.is_err());
+        assert!(!storage.load_thumbnail(kept).await.unwrap().bytes.is_empty());
+        assert_eq!(storage.load_embedding(kept).await.unwrap(), vec![0.5, 0.25]);
+        assert!(storage.load_metadata(old_ids[0]).await.is_err());
+        assert!(storage.load_metadata(ancient).await.is_err());
+        assert!(storage.load_metadata(recent).await.unwrap().full_image);
+        assert!(!storage.load_screenshot(recent).await.unwrap().bytes.is_empty());
+
+        // Search still finds the downsampled capture, marked as thumbnail-only
+        let hits = storage.search_text("descriptive", 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, kept);
+        assert!(!hits[0].full_image);
+
+        let stats = storage.get_stats().await.unwrap();
+        assert_eq!(stats.full_count, 1);
+        assert_eq!(stats.downsampled_count, 2);
+        assert_eq!(stats.purged_count, 3);
+
+        // A second pass is a no-op
+        assert_eq!(storage.cleanup_at(now).unwrap(), CleanupReport::default());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_embedding_quantization_migration() {
+        let temp_dir = std::env::temp_dir().join(format!("eva_test_quant_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        let mut storage = Storage::new(temp_dir.to_str().unwrap()).await.unwrap();
+
+        let embedding: Vec<f32> = (0..64).map(|i| (i as f32 / 64.0) - 0.5).collect();
+        let mut ids = Vec::new();
+        for shade in 0..4 {
+            let id = storage.save_screenshot_at(test_image(shade), Utc::now(), ScreenRef::default()).unwrap();
+            storage.save_context(id, None, None, &embedding).await.unwrap();
+            ids.push(id);
+        }
+        let f32_bytes = storage.get_stats().await.unwrap().embedding_bytes;
+        assert_eq!(f32_bytes, 4 * 64 * 4);
+
+        // Quantize existing rows, keeping f32 for the newest capture only
+        storage.set_embedding_quantization(Some(1));
+        let report = storage.migrate_embeddings().await.unwrap();
+        assert_eq!(report.rewritten, 4);
+        assert_eq!(report.trimmed, 3);
+        assert_eq!(report.bytes_before, f32_bytes);
+        assert_eq!(report.bytes_after, 64 * 4 + 4 * (12 + 64));
+
+        assert_eq!(storage.load_embedding(ids[3]).await.unwrap(), embedding);
+        let approx = storage.load_embedding(ids[0]).await.unwrap();
+        assert_eq!(approx.len(), 64);
+        assert!(approx.iter().zip(&embedding).all(|(a, b)| (a - b).abs() < 0.01));
+
+        // Already migrated: nothing to do
+        assert_eq!(storage.migrate_embeddings().await.unwrap().rewritten, 0);
+
+        // And back to f32
+        storage.set_embedding_quantization(None);
+        let report = storage.migrate_embeddings().await.unwrap();
+        assert_eq!(report.rewritten, 3);
+        assert_eq!(storage.get_stats().await.unwrap().embedding_bytes, f32_bytes);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_search_in_time_range() {
+        let temp_dir = std::env::temp_dir().join(format!("eva_test_range_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        let storage = Storage::new(temp_dir.to_str().unwrap()).await.unwrap();
+
+        let day = Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc() - Duration::days(1);
+        let mut ids = Vec::new();
+        for (hour, text) in [(9, "budget spreadsheet morning"), (14, "budget spreadsheet afternoon"), (20, "budget spreadsheet evening")] {
+            let at = day + Duration::hours(hour);
+            let id = storage.save_screenshot_at(test_image(hour as u8), at, ScreenRef::default()).unwrap();
+            storage.save_metadata(id, text).await.unwrap();
+            assert_eq!(storage.load_metadata(id).await.unwrap().timestamp, at);
+            ids.push(id);
+        }
+
+        let (from, to) = (day + Duration::hours(12), day + Duration::hours(18));
+        let hits = storage.search_text_in_range("budget", from, to, 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, ids[1]);
+        assert_eq!(hits[0].timestamp, day + Duration::hours(14));
+
+        assert_eq!(storage.search_text("budget", 10).await.unwrap().len(), 3);
+        let after_first = storage.captures_after(ids[0]).await.unwrap();
+        assert_eq!(after_first, vec![(ids[1], "budget spreadsheet afternoon".to_string()), (ids[2], "budget spreadsheet evening".to_string())]);
+        assert_eq!(storage.ids_in_range(from, to).await.unwrap(), HashSet::from([ids[1]]));
+        // `to` is exclusive
+        let until_evening = storage.ids_in_range(day, day + Duration::hours(20)).await.unwrap();
+        assert_eq!(until_evening, HashSet::from([ids[0], ids[1]]));
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_delete_range() {
+        let temp_dir = std::env::temp_dir().join(format!("eva_test_delete_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        let storage = Storage::new(temp_dir.to_str().unwrap()).await.unwrap();
+
+        let day = Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc() - Duration::days(1);
+        let mut ids = Vec::new();
+        for (hour, text) in [(9, "bank statement"), (11, "bank password reset"), (15, "bank holiday")] {
+            let id = storage.save_screenshot_at(test_image(hour as u8), day + Duration::hours(hour), ScreenRef::default()).unwrap();
+            storage.save_metadata(id, text).await.unwrap();
+            ids.push(id);
+        }
+        let files = |id: u64| -> Vec<PathBuf> {
+            let conn = storage.db();
+            let (file, thumb): (String, String) = conn
+                .query_row("SELECT file_path, thumb_path FROM screenshots WHERE id = ?1", params![id], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })
+                .unwrap();
+            vec![storage.base_path.join(file), storage.base_path.join(thumb)]
+        };
+        let deleted_files: Vec<PathBuf> = ids[..2].iter().flat_map(|&id| files(id)).collect();
+        assert!(deleted_files.iter().all(|f| f.exists()));
+
+        let removed = storage.delete_range(day, day + Duration::hours(12)).await.unwrap();
+        assert_eq!(removed, 2);
+        assert!(deleted_files.iter().all(|f| !f.exists()));
+        assert!(storage.load_metadata(ids[0]).await.is_err());
+        assert!(storage.load_metadata(ids[2]).await.is_ok());
+
+        // The delete trigger dropped the rows from the FTS index too
+        let fts_rows: i64 = storage
+            .db()
+            .query_row("SELECT COUNT(*) FROM screenshots_fts WHERE screenshots_fts MATCH 'password'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(fts_rows, 0);
+        let hits = storage.search_text("bank", 10).await.unwrap();
+        assert_eq!(hits.iter().map(|h| h.id).collect::<Vec<_>>(), vec![ids[2]]);
+
+        assert_eq!(storage.delete_range(day, day + Duration::hours(12)).await.unwrap(), 0);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_screens_of_one_tick() {
+        let temp_dir = std::env::temp_dir().join(format!("eva_test_screens_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        let storage = Storage::new(temp_dir.to_str().unwrap()).await.unwrap();
+
+        // Both screens in the same millisecond: separate files, one group
+        let at = Utc::now();
+        let left = ScreenRef { index: 0, group_id: Some(42) };
+        let right = ScreenRef { index: 1, group_id: Some(42) };
+        let left_id = storage.save_screenshot_at(test_image(10), at, left).unwrap();
+        let right_id = storage.save_screenshot_at(test_image(200), at, right).unwrap();
+        storage.save_metadata(left_id, "terminal build log").await.unwrap();
+        storage.save_metadata(right_id, "design review notes").await.unwrap();
+
+        assert_ne!(storage.load_screenshot(left_id).await.unwrap(), storage.load_screenshot(right_id).await.unwrap());
+        assert_eq!(storage.load_metadata(right_id).await.unwrap().screen, Some(right));
+        let hits = storage.search_text("review", 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].screen, Some(right));
+
+        // A masked window is disclosed with the capture
+        assert!(!hits[0].masked);
+        storage.save_masked(right_id).await.unwrap();
+        assert!(storage.load_metadata(right_id).await.unwrap().masked);
+        assert!(storage.search_text("review", 10).await.unwrap()[0].masked);
+
+        // Rows from before the columns existed have no screen
+        storage.db().execute("UPDATE screenshots SET screen_index = NULL, group_id = NULL WHERE id = ?1", params![left_id]).unwrap();
+        assert_eq!(storage.load_metadata(left_id).await.unwrap().screen, None);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_capture_formats_round_trip_and_shrink() {
+        let temp_dir = std::env::temp_dir().join(format!("eva_test_formats_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        let mut storage = Storage::new(temp_dir.to_str().unwrap()).await.unwrap();
+        // Smooth but never repeating, like a photo on screen
+        let detailed = || {
+            DynamicImage::ImageRgb8(image::RgbImage::from_fn(800, 480, |x, y| {
+                let wave = |a: f32, b: f32| (128.0 + 127.0 * (x as f32 / a).sin() * (y as f32 / b).cos()) as u8;
+                image::Rgb([wave(23.0, 31.0), wave(41.0, 17.0), wave(13.0, 57.0)])
+            }))
+        };
+        let full_mb = |stats: StorageStats| stats.full_mb;
+
+        let png = storage.save_screenshot_at(detailed(), Utc::now(), ScreenRef::default()).unwrap();
+        let png_mb = full_mb(storage.get_stats().await.unwrap());
+
+        // Lossless WebP: same pixels back, and the row says what it is
+        storage.set_image_encoding(CaptureFormat::WebP, DEFAULT_QUALITY, None);
+        let webp = storage.save_screenshot_at(detailed(), Utc::now(), ScreenRef::default()).unwrap();
+        let loaded = storage.load_screenshot(webp).await.unwrap();
+        assert_eq!(loaded.mime_type(), "image/webp");
+        let decoded = image::load_from_memory_with_format(&loaded.bytes, image::ImageFormat::WebP).unwrap();
+        assert_eq!(decoded.to_rgb8(), detailed().to_rgb8());
+        assert_eq!(storage.load_thumbnail(webp).await.unwrap().format, CaptureFormat::WebP);
+        // Earlier captures keep the format they were stored in
+        assert_eq!(storage.load_screenshot(png).await.unwrap().mime_type(), "image/png");
+
+        // Downscaled JPEG: a fraction of the PNG, as the stats show
+        storage.set_image_encoding(CaptureFormat::Jpeg, 60, Some(400));
+        let before = full_mb(storage.get_stats().await.unwrap());
+        let jpeg = storage.save_screenshot_at(detailed(), Utc::now(), ScreenRef::default()).unwrap();
+        let jpeg_mb = full_mb(storage.get_stats().await.unwrap()) - before;
+        assert!(jpeg_mb < png_mb / 4.0, "jpeg {} MB vs png {} MB", jpeg_mb, png_mb);
+        let loaded = storage.load_screenshot(jpeg).await.unwrap();
+        assert_eq!(loaded.mime_type(), "image/jpeg");
+        let decoded = image::load_from_memory_with_format(&loaded.bytes, image::ImageFormat::Jpeg).unwrap();
+        assert_eq!(decoded.dimensions(), (400, 240));
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_save_and_search() {
+        let temp_dir = std::env::temp_dir().join(format!("eva_test_concurrent_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        let storage = Arc::new(Storage::new(temp_dir.to_str().unwrap()).await.unwrap());
+        // A second connection to the same database, as another process would have
+        let other = Arc::new(Storage::new(temp_dir.to_str().unwrap()).await.unwrap());
+
+        let mut tasks = Vec::new();
+        for writer in 0..4u8 {
+            let storage = if writer % 2 == 0 { storage.clone() } else { other.clone() };
+            tasks.push(tokio::spawn(async move {
+                for i in 0..10u8 {
+                    let id = storage.save_screenshot(test_image(writer * 10 + i), ScreenRef::default()).await.map_err(|e| e.to_string())?;
+                    let text = format!("meeting notes writer{} item{}", writer, i);
+                    storage.save_metadata(id, &text).await.map_err(|e| e.to_string())?;
+                    storage.save_context(id, Some("editor"), None, &[0.5; 8]).await.map_err(|e| e.to_string())?;
+                }
+                Ok::<_, String>(())
+            }));
+        }
+        for reader in 0..4 {
+            let storage = if reader % 2 == 0 { storage.clone() } else { other.clone() };
+            tasks.push(tokio::spawn(async move {
+                for _ in 0..20 {
+                    storage.search_text("meeting", 5).await.map_err(|e| e.to_string())?;
+                    storage.get_stats().await.map_err(|e| e.to_string())?;
+                }
+                Ok::<_, String>(())
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(storage.get_stats().await.unwrap().total_screenshots, 40);
+        assert_eq!(other.search_text("meeting", 100).await.unwrap().len(), 40);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
 }
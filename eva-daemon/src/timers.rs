@@ -0,0 +1,522 @@
+use crate::command_parser::TimerOperation;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, LocalResult, NaiveTime, TimeZone, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Simple weekly recurrence: a local wall-clock time on a set of weekdays
+///
+/// Stored alongside the absolute UTC fire time so the next occurrence can be
+/// recomputed in the local zone, which keeps "every weekday at 7" at 07:00
+/// across DST transitions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recurrence {
+    pub weekdays: Vec<Weekday>,
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl Recurrence {
+    /// Every day at the given time
+    pub fn daily(hour: u32, minute: u32) -> Self {
+        Self::on(ALL_DAYS.to_vec(), hour, minute)
+    }
+
+    /// Monday to Friday at the given time
+    pub fn weekdays(hour: u32, minute: u32) -> Self {
+        Self::on(ALL_DAYS[..5].to_vec(), hour, minute)
+    }
+
+    /// Specific days at the given time
+    pub fn on(weekdays: Vec<Weekday>, hour: u32, minute: u32) -> Self {
+        Self { weekdays, hour: hour.min(23), minute: minute.min(59) }
+    }
+
+    /// First occurrence strictly after `after`, evaluated in `tz`
+    ///
+    /// A time skipped by a spring-forward gap fires at the same wall-clock
+    /// offset after the gap (02:30 becomes 03:30); a time repeated by a
+    /// fall-back fires once, at its first occurrence.
+    pub fn next_after<Tz: TimeZone>(&self, after: DateTime<Utc>, tz: &Tz) -> Option<DateTime<Utc>> {
+        if self.weekdays.is_empty() {
+            return None;
+        }
+        let time = NaiveTime::from_hms_opt(self.hour, self.minute, 0)?;
+        let start = after.with_timezone(tz).date_naive();
+
+        // Eight days covers "same weekday next week" when today's slot has passed
+        for offset in 0..8 {
+            let date = start + ChronoDuration::days(offset);
+            if !self.weekdays.contains(&date.weekday()) {
+                continue;
+            }
+
+            let naive = date.and_time(time);
+            let local = match tz.from_local_datetime(&naive) {
+                LocalResult::Single(t) => t,
+                LocalResult::Ambiguous(first, _) => first,
+                LocalResult::None => match tz.from_local_datetime(&(naive + ChronoDuration::hours(1))).earliest() {
+                    Some(t) => t,
+                    None => continue,
+                },
+            };
+
+            let utc = local.with_timezone(&Utc);
+            if utc > after {
+                return Some(utc);
+            }
+        }
+        None
+    }
+
+    /// Human description, e.g. "weekdays at 07:00"
+    pub fn describe(&self) -> String {
+        let days = if self.weekdays.len() == 7 {
+            "every day".to_string()
+        } else if self.weekdays == ALL_DAYS[..5] {
+            "weekdays".to_string()
+        } else {
+            self.weekdays.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")
+        };
+        format!("{} at {:02}:{:02}", days, self.hour, self.minute)
+    }
+}
+
+const ALL_DAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// A pending timer or reminder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timer {
+    pub id: u32,
+    pub label: String,
+    /// Absolute fire time; survives restarts and wall-clock changes
+    pub fire_at: DateTime<Utc>,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+}
+
+impl Timer {
+    /// Time left until the timer fires (zero if overdue)
+    pub fn remaining(&self, now: DateTime<Utc>) -> Duration {
+        (self.fire_at - now).to_std().unwrap_or_default()
+    }
+}
+
+/// Timer store (timers.json in the data directory)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TimerManager {
+    timers: Vec<Timer>,
+    next_id: u32,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl TimerManager {
+    /// Create an empty, in-memory manager
+    pub fn new() -> Self {
+        Self { timers: Vec::new(), next_id: 1, path: None }
+    }
+
+    /// Load timers from the data directory (empty if missing)
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load_from(Self::get_timers_path()?)
+    }
+
+    /// Load timers from a specific file; saves go back to the same file
+    pub fn load_from(path: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut manager = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            Self::new()
+        };
+        manager.next_id = manager.next_id.max(1);
+        manager.path = Some(path);
+        Ok(manager)
+    }
+
+    /// Persist timers (no-op for in-memory managers)
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(path) = &self.path else { return Ok(()) };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Get timers file path
+    fn get_timers_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        crate::paths::data_file("timers.json")
+    }
+
+    /// Start a one-shot timer; returns its ID
+    pub fn add(&mut self, label: &str, after: Duration, now: DateTime<Utc>) -> u32 {
+        let fire_at = now + ChronoDuration::from_std(after).unwrap_or_else(|_| ChronoDuration::zero());
+        let id = self.next_id;
+        self.next_id += 1;
+        self.timers.push(Timer { id, label: label.to_string(), fire_at, recurrence: None });
+        id
+    }
+
+    /// Number of pending timers
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    /// Whether no timer is pending
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    /// Find a timer by (partial) label; without a label, the newest one
+    pub fn find(&self, label: Option<&str>) -> Option<&Timer> {
+        self.index_of(label).map(|i| &self.timers[i])
+    }
+
+    fn index_of(&self, label: Option<&str>) -> Option<usize> {
+        match label {
+            Some(label) => {
+                let label = label.to_lowercase();
+                self.timers.iter().position(|t| t.label.to_lowercase() == label).or_else(|| {
+                    self.timers.iter().position(|t| t.label.to_lowercase().contains(&label))
+                })
+            }
+            None => self.timers.iter().enumerate().max_by_key(|(_, t)| t.id).map(|(i, _)| i),
+        }
+    }
+
+    /// Time left on a timer; without a label, the one firing soonest
+    pub fn remaining(&self, label: Option<&str>, now: DateTime<Utc>) -> Option<(&Timer, Duration)> {
+        let timer = match label {
+            Some(_) => self.find(label)?,
+            None => self.timers.iter().min_by_key(|t| t.fire_at)?,
+        };
+        Some((timer, timer.remaining(now)))
+    }
+
+    /// Push a timer back; an overdue timer is snoozed from now
+    pub fn snooze(&mut self, label: Option<&str>, by: Duration, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+        let idx = self.index_of(label).ok_or_else(|| not_found(label))?;
+        let timer = &mut self.timers[idx];
+        let by = ChronoDuration::from_std(by).map_err(|e| e.to_string())?;
+        timer.fire_at = timer.fire_at.max(now) + by;
+        Ok(timer.fire_at)
+    }
+
+    /// Turn a timer into a recurring one; returns the next fire time
+    pub fn set_recurrence<Tz: TimeZone>(
+        &mut self,
+        label: Option<&str>,
+        rule: Recurrence,
+        now: DateTime<Utc>,
+        tz: &Tz,
+    ) -> Result<DateTime<Utc>, String> {
+        let idx = self.index_of(label).ok_or_else(|| not_found(label))?;
+        let next = rule.next_after(now, tz).ok_or("Recurrence has no days")?;
+        let timer = &mut self.timers[idx];
+        timer.fire_at = next;
+        timer.recurrence = Some(rule);
+        Ok(next)
+    }
+
+    /// Remove a timer
+    pub fn cancel(&mut self, label: Option<&str>) -> Option<Timer> {
+        self.index_of(label).map(|i| self.timers.remove(i))
+    }
+
+    /// Collect timers that are due, rescheduling recurring ones
+    ///
+    /// Occurrences missed while the daemon was down (or skipped by a clock
+    /// jump) fire once, then the timer moves to its next future slot.
+    pub fn due<Tz: TimeZone>(&mut self, now: DateTime<Utc>, tz: &Tz) -> Vec<Timer> {
+        let mut fired = Vec::new();
+
+        self.timers.retain_mut(|timer| {
+            if timer.fire_at > now {
+                return true;
+            }
+            fired.push(timer.clone());
+
+            match timer.recurrence.as_ref().and_then(|r| r.next_after(now, tz)) {
+                Some(next) => {
+                    timer.fire_at = next;
+                    true
+                }
+                None => false,
+            }
+        });
+
+        fired
+    }
+
+    /// Pending timers with their countdowns, soonest first
+    pub fn active(&self, now: DateTime<Utc>) -> Vec<(String, Duration)> {
+        let mut timers: Vec<_> = self.timers.iter().collect();
+        timers.sort_by_key(|t| t.fire_at);
+        timers.into_iter().map(|t| (t.label.clone(), t.remaining(now))).collect()
+    }
+}
+
+impl TimerManager {
+    /// Carry out a parsed timer command; returns the reply to speak
+    pub fn apply<Tz: TimeZone>(
+        &mut self,
+        op: TimerOperation,
+        now: DateTime<Utc>,
+        tz: &Tz,
+        portuguese: bool,
+    ) -> Result<String, String>
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        let local = |t: DateTime<Utc>| t.with_timezone(tz).format("%H:%M").to_string();
+        let pick = |en: String, pt: String| if portuguese { pt } else { en };
+
+        let reply = match op {
+            TimerOperation::Set { label, seconds } => {
+                let label = label.unwrap_or_else(|| "timer".to_string());
+                let d = Duration::from_secs(seconds);
+                self.add(&label, d, now);
+                let spoken = speak_duration(d, portuguese);
+                pick(format!("{} timer set for {}", label, spoken), format!("Timer {} definido para {}", label, spoken))
+            }
+            TimerOperation::Query { label } => {
+                let (timer, left) = self.remaining(label.as_deref(), now).ok_or_else(|| not_found(label.as_deref()))?;
+                let spoken = speak_duration(left, portuguese);
+                pick(format!("{} left on the {} timer", spoken, timer.label), format!("Faltam {} no timer {}", spoken, timer.label))
+            }
+            TimerOperation::Snooze { label, seconds } => {
+                let fire_at = self.snooze(label.as_deref(), Duration::from_secs(seconds), now)?;
+                let spoken = speak_duration(Duration::from_secs(seconds), portuguese);
+                pick(
+                    format!("Snoozed for {}, it will go off at {}", spoken, local(fire_at)),
+                    format!("Adiado por {}, vai tocar às {}", spoken, local(fire_at)),
+                )
+            }
+            TimerOperation::Repeat { label, rule } => {
+                let description = rule.describe();
+                let next = self.set_recurrence(label.as_deref(), rule, now, tz)?;
+                pick(
+                    format!("Repeating {}, next at {}", description, local(next)),
+                    format!("Repetindo {}, próximo às {}", description, local(next)),
+                )
+            }
+        };
+
+        self.save().map_err(|e| e.to_string())?;
+        Ok(reply)
+    }
+}
+
+fn not_found(label: Option<&str>) -> String {
+    match label {
+        Some(label) => format!("No timer called '{}'", label),
+        None => "No active timers".to_string(),
+    }
+}
+
+/// Speakable duration, e.g. "4 minutes and 30 seconds" / "4 minutos e 30 segundos"
+pub fn speak_duration(d: Duration, portuguese: bool) -> String {
+    let secs = d.as_secs();
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+
+    let unit = |n: u64, en: &str, pt: &str| {
+        let word = if portuguese { pt } else { en };
+        if n == 1 {
+            format!("1 {}", word)
+        } else {
+            format!("{} {}s", n, word)
+        }
+    };
+
+    let mut parts = Vec::new();
+    if h > 0 {
+        parts.push(unit(h, "hour", "hora"));
+    }
+    if m > 0 {
+        parts.push(unit(m, "minute", "minuto"));
+    }
+    if s > 0 && h == 0 {
+        parts.push(unit(s, "second", "segundo"));
+    }
+    if parts.is_empty() {
+        return if portuguese { "menos de um segundo" } else { "less than a second" }.to_string();
+    }
+
+    let and = if portuguese { " e " } else { " and " };
+    match parts.len() {
+        1 => parts.remove(0),
+        _ => {
+            let last = parts.pop().unwrap_or_default();
+            format!("{}{}{}", parts.join(", "), and, last)
+        }
+    }
+}
+
+/// Compact countdown for the TUI, e.g. "4:05" or "1:02:03"
+pub fn format_countdown(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::America::{New_York, Sao_Paulo};
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_query_and_snooze() {
+        let now = utc("2024-05-01T12:00:00Z");
+        let mut timers = TimerManager::new();
+        timers.add("pasta", Duration::from_secs(600), now);
+        timers.add("laundry reminder", Duration::from_secs(1800), now);
+
+        let (timer, left) = timers.remaining(Some("pasta"), now + ChronoDuration::seconds(90)).unwrap();
+        assert_eq!(timer.label, "pasta");
+        assert_eq!(left, Duration::from_secs(510));
+        assert_eq!(speak_duration(left, false), "8 minutes and 30 seconds");
+        assert_eq!(speak_duration(left, true), "8 minutos e 30 segundos");
+
+        // Without a label the soonest timer answers
+        assert_eq!(timers.remaining(None, now).unwrap().0.label, "pasta");
+
+        let fire = timers.snooze(Some("laundry"), Duration::from_secs(600), now).unwrap();
+        assert_eq!(fire, utc("2024-05-01T12:40:00Z"));
+
+        // Overdue timers are snoozed from now, not from the old fire time
+        let late = now + ChronoDuration::hours(1);
+        let fire = timers.snooze(Some("pasta"), Duration::from_secs(300), late).unwrap();
+        assert_eq!(fire, utc("2024-05-01T13:05:00Z"));
+
+        assert!(timers.snooze(Some("oven"), Duration::from_secs(60), now).is_err());
+    }
+
+    #[test]
+    fn test_one_shot_fires_once() {
+        let now = utc("2024-05-01T12:00:00Z");
+        let mut timers = TimerManager::new();
+        timers.add("tea", Duration::from_secs(180), now);
+
+        assert!(timers.due(now, &Utc).is_empty());
+        let fired = timers.due(now + ChronoDuration::minutes(3), &Utc);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(timers.len(), 0);
+    }
+
+    #[test]
+    fn test_recurring_weekday_timer() {
+        // Friday 2024-05-03 06:00 in São Paulo (UTC-3, no DST)
+        let now = utc("2024-05-03T09:00:00Z");
+        let mut timers = TimerManager::new();
+        timers.add("alarm", Duration::from_secs(60), now);
+
+        let next = timers.set_recurrence(None, Recurrence::weekdays(7, 0), now, &Sao_Paulo).unwrap();
+        assert_eq!(next, utc("2024-05-03T10:00:00Z"));
+
+        // Fires Friday, then skips the weekend to Monday
+        let fired = timers.due(next, &Sao_Paulo);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(timers.find(None).unwrap().fire_at, utc("2024-05-06T10:00:00Z"));
+    }
+
+    #[test]
+    fn test_recurrence_across_spring_forward() {
+        // New York springs forward on Sunday 2024-03-10 (EST -5 -> EDT -4)
+        let rule = Recurrence::weekdays(7, 0);
+        let friday = utc("2024-03-08T12:00:00Z"); // Fri 07:00 EST
+        assert_eq!(rule.next_after(friday - ChronoDuration::seconds(1), &New_York), Some(friday));
+
+        // Still 07:00 local on Monday, which is now 11:00 UTC
+        assert_eq!(rule.next_after(friday, &New_York), Some(utc("2024-03-11T11:00:00Z")));
+
+        // 02:30 does not exist on the transition day: fires at 03:30 EDT
+        let gap = Recurrence::daily(2, 30);
+        let sat = utc("2024-03-09T08:00:00Z");
+        assert_eq!(gap.next_after(sat, &New_York), Some(utc("2024-03-10T07:30:00Z")));
+    }
+
+    #[test]
+    fn test_recurrence_across_fall_back() {
+        // New York falls back on Sunday 2024-11-03: 01:30 happens twice
+        let rule = Recurrence::daily(1, 30);
+        let sat = utc("2024-11-02T12:00:00Z");
+
+        let first = rule.next_after(sat, &New_York).unwrap();
+        assert_eq!(first, utc("2024-11-03T05:30:00Z")); // 01:30 EDT
+
+        // Only once: the repeated 01:30 EST is not a second occurrence
+        let next = rule.next_after(first, &New_York).unwrap();
+        assert_eq!(next, utc("2024-11-04T06:30:00Z")); // Monday 01:30 EST
+    }
+
+    #[test]
+    fn test_persistence_across_restart() {
+        let path = std::env::temp_dir().join(format!("eva_timers_test_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let now = utc("2024-05-03T09:00:00Z");
+
+        let mut timers = TimerManager::load_from(path.clone()).unwrap();
+        timers.add("laundry", Duration::from_secs(3600), now);
+        timers.add("standup", Duration::from_secs(60), now);
+        timers.set_recurrence(Some("standup"), Recurrence::weekdays(9, 30), now, &Sao_Paulo).unwrap();
+        timers.save().unwrap();
+
+        // "Restart" three days later: missed occurrences fire once, then reschedule
+        let later = utc("2024-05-06T15:00:00Z");
+        let mut reloaded = TimerManager::load_from(path.clone()).unwrap();
+        let fired = reloaded.due(later, &Sao_Paulo);
+        assert_eq!(fired.len(), 2);
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded.find(Some("standup")).unwrap().fire_at, utc("2024-05-07T12:30:00Z"));
+
+        // IDs keep increasing after a reload
+        assert_eq!(reloaded.add("tea", Duration::from_secs(60), later), 3);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_apply_spoken_replies() {
+        let now = utc("2024-05-01T12:00:00Z");
+        let mut timers = TimerManager::new();
+
+        let set = TimerOperation::Set { label: Some("pasta".to_string()), seconds: 600 };
+        assert_eq!(timers.apply(set, now, &Utc, false).unwrap(), "pasta timer set for 10 minutes");
+
+        let query = TimerOperation::Query { label: Some("pasta".to_string()) };
+        let later = now + ChronoDuration::seconds(61);
+        assert_eq!(timers.apply(query, later, &Utc, true).unwrap(), "Faltam 8 minutos e 59 segundos no timer pasta");
+
+        let snooze = TimerOperation::Snooze { label: None, seconds: 300 };
+        assert_eq!(timers.apply(snooze, now, &Utc, false).unwrap(), "Snoozed for 5 minutes, it will go off at 12:15");
+
+        let missing = TimerOperation::Query { label: Some("oven".to_string()) };
+        assert!(timers.apply(missing, now, &Utc, false).is_err());
+    }
+
+    #[test]
+    fn test_format_countdown() {
+        assert_eq!(format_countdown(Duration::from_secs(245)), "4:05");
+        assert_eq!(format_countdown(Duration::from_secs(3723)), "1:02:03");
+        assert_eq!(Recurrence::weekdays(7, 0).describe(), "weekdays at 07:00");
+    }
+}
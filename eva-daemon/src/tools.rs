@@ -0,0 +1,237 @@
+//! Gemini tool calling
+//!
+//! Commands the model can run are declared as functions in the session
+//! setup. The model calls one only when the user actually asked for it, so
+//! a reply that merely mentions deleting a file deletes nothing. Calls are
+//! mapped back to `CommandIntent`s and run by the command executor, with the
+//! same confirmation and policy checks as spoken commands.
+
+use crate::command_executor::{CommandExecutor, ExecutionOutcome};
+use crate::command_parser::{
+    CommandIntent, FileOperation, ProcessOperation, SystemOperation, TimeHint, TimeMachineOperation,
+};
+use crate::gemini::FunctionCall;
+use crate::timemachine::TimeMachine;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+/// The `tools` entry of the setup message
+pub fn declarations() -> Value {
+    let path = json!({ "type": "STRING", "description": "File or folder path, relative to EVA's sandbox unless absolute" });
+    let object = |properties: Value, required: &[&str]| json!({ "type": "OBJECT", "properties": properties, "required": required });
+    let none = json!({ "type": "OBJECT", "properties": {} });
+
+    json!([{
+        "function_declarations": [
+            {
+                "name": "create_file",
+                "description": "Create a file, optionally with text content",
+                "parameters": object(json!({ "path": path, "content": { "type": "STRING" } }), &["path"])
+            },
+            {
+                "name": "read_file",
+                "description": "Read a text file",
+                "parameters": object(json!({ "path": path }), &["path"])
+            },
+            {
+                "name": "list_files",
+                "description": "List the files in a folder (the sandbox if no path is given)",
+                "parameters": object(json!({ "path": path }), &[])
+            },
+            {
+                "name": "copy_file",
+                "description": "Copy a file",
+                "parameters": object(json!({ "from": path, "to": path }), &["from", "to"])
+            },
+            {
+                "name": "move_file",
+                "description": "Move or rename a file. Only when the user asked for it.",
+                "parameters": object(json!({ "from": path, "to": path }), &["from", "to"])
+            },
+            {
+                "name": "delete_file",
+                "description": "Delete a file. Only when the user explicitly asked to delete it.",
+                "parameters": object(json!({ "path": path }), &["path"])
+            },
+            {
+                "name": "list_processes",
+                "description": "List running processes",
+                "parameters": none
+            },
+            {
+                "name": "start_process",
+                "description": "Open an application or program by name",
+                "parameters": object(json!({ "name": { "type": "STRING" } }), &["name"])
+            },
+            {
+                "name": "process_status",
+                "description": "Whether a program started with start_process is still running, or how it exited",
+                "parameters": object(json!({ "name_or_pid": { "type": "STRING" } }), &["name_or_pid"])
+            },
+            {
+                "name": "process_output",
+                "description": "The captured output of a program started with start_process",
+                "parameters": object(json!({ "pid": { "type": "INTEGER" } }), &["pid"])
+            },
+            {
+                "name": "kill_process",
+                "description": "Stop a running process by PID. Only when the user asked for it.",
+                "parameters": object(json!({ "pid": { "type": "INTEGER" } }), &["pid"])
+            },
+            {
+                "name": "system_info",
+                "description": "Memory, disk, CPU or uptime of this computer",
+                "parameters": object(
+                    json!({ "kind": { "type": "STRING", "enum": ["memory", "disk", "cpu", "uptime"] } }),
+                    &["kind"]
+                )
+            },
+            {
+                "name": "search_timemachine",
+                "description": "Search what was on the user's screen earlier (Time Machine)",
+                "parameters": object(json!({
+                    "query": { "type": "STRING", "description": "What to look for" },
+                    "minutes_ago": { "type": "INTEGER", "description": "Roughly how long ago it was seen" },
+                    "day": { "type": "STRING", "enum": ["today", "yesterday"] }
+                }), &["query"])
+            }
+        ]
+    }])
+}
+
+/// Whether a parsed command has a tool, so the model should decide if it
+/// was really meant
+pub fn covers(intent: &CommandIntent) -> bool {
+    matches!(intent, CommandIntent::File(_) | CommandIntent::Process(_) | CommandIntent::System(_))
+}
+
+/// The command a tool call stands for
+pub fn to_intent(call: &FunctionCall) -> Result<CommandIntent, String> {
+    let text = |name: &str| {
+        call.args.get(name).and_then(Value::as_str).map(str::to_string).ok_or_else(|| format!("{}: missing '{}'", call.name, name))
+    };
+    let optional = |name: &str| call.args.get(name).and_then(Value::as_str).map(str::to_string);
+
+    let intent = match call.name.as_str() {
+        "create_file" => CommandIntent::File(FileOperation::Create { path: text("path")?, content: optional("content") }),
+        "read_file" => CommandIntent::File(FileOperation::Read { path: text("path")? }),
+        "list_files" => CommandIntent::File(FileOperation::List { path: optional("path") }),
+        "copy_file" => CommandIntent::File(FileOperation::Copy { from: text("from")?, to: text("to")? }),
+        "move_file" => CommandIntent::File(FileOperation::Move { from: text("from")?, to: text("to")? }),
+        "delete_file" => CommandIntent::File(FileOperation::Delete { path: text("path")? }),
+        "list_processes" => CommandIntent::Process(ProcessOperation::List),
+        "start_process" => CommandIntent::Process(ProcessOperation::Start { name: text("name")? }),
+        "kill_process" => {
+            let pid = call.args.get("pid").and_then(Value::as_u64).and_then(|p| u32::try_from(p).ok());
+            CommandIntent::Process(ProcessOperation::Kill { pid: pid.ok_or("kill_process: missing 'pid'")? })
+        }
+        "process_status" => CommandIntent::Process(ProcessOperation::Status { name_or_pid: text("name_or_pid")? }),
+        "process_output" => {
+            let pid = call.args.get("pid").and_then(Value::as_u64).and_then(|p| u32::try_from(p).ok());
+            CommandIntent::Process(ProcessOperation::Output { pid: pid.ok_or("process_output: missing 'pid'")? })
+        }
+        "system_info" => CommandIntent::System(match text("kind")?.as_str() {
+            "memory" => SystemOperation::MemoryInfo,
+            "disk" => SystemOperation::DiskInfo,
+            "cpu" => SystemOperation::CpuInfo,
+            "uptime" => SystemOperation::Uptime,
+            other => return Err(format!("system_info: unknown kind '{}'", other)),
+        }),
+        "search_timemachine" => {
+            let time_hint = match (call.args.get("minutes_ago").and_then(Value::as_u64), optional("day").as_deref()) {
+                (Some(minutes), _) => Some(TimeHint::Ago { seconds: minutes * 60 }),
+                (None, Some("today")) => Some(TimeHint::Today),
+                (None, Some("yesterday")) => Some(TimeHint::Yesterday),
+                _ => None,
+            };
+            CommandIntent::TimeMachine(TimeMachineOperation::Search { query: text("query")?, time_hint })
+        }
+        other => return Err(format!("Unknown tool '{}'", other)),
+    };
+    Ok(intent)
+}
+
+/// What a tool call can reach while the model is answering
+pub struct ToolContext<'a> {
+    pub executor: &'a mut CommandExecutor,
+    pub timemachine: Option<&'a TimeMachine>,
+    pub now: DateTime<Utc>,
+    pub portuguese: bool,
+    /// Commands that ran this turn and their results, for the command history
+    pub ran: Vec<(CommandIntent, Result<String, String>)>,
+}
+
+impl ToolContext<'_> {
+    /// Run a tool call; the result goes back to the model to narrate
+    pub async fn run(&mut self, call: &FunctionCall) -> Result<String, String> {
+        match to_intent(call)? {
+            CommandIntent::TimeMachine(op) => match self.timemachine {
+                Some(tm) => tm.apply(op, self.now, &chrono::Local, self.portuguese).await,
+                None => Err("Time Machine is not running".to_string()),
+            },
+            intent => match self.executor.execute(intent.clone()).await {
+                // Held for the user's "yes", or a dry run: nothing happened yet
+                Ok(outcome @ (ExecutionOutcome::NeedsConfirmation(_) | ExecutionOutcome::WouldRun(_))) => {
+                    Ok(outcome.into_message())
+                }
+                ran => {
+                    let result = ran.map(ExecutionOutcome::into_message).map_err(|e| e.to_string());
+                    self.ran.push((intent, result.clone()));
+                    result
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, args: Value) -> FunctionCall {
+        FunctionCall { id: Some("1".to_string()), name: name.to_string(), args }
+    }
+
+    #[test]
+    fn test_calls_map_to_intents() {
+        assert_eq!(
+            to_intent(&call("delete_file", json!({ "path": "foo.txt" }))),
+            Ok(CommandIntent::File(FileOperation::Delete { path: "foo.txt".to_string() }))
+        );
+        assert_eq!(
+            to_intent(&call("list_files", json!({}))),
+            Ok(CommandIntent::File(FileOperation::List { path: None }))
+        );
+        assert_eq!(
+            to_intent(&call("kill_process", json!({ "pid": 4242 }))),
+            Ok(CommandIntent::Process(ProcessOperation::Kill { pid: 4242 }))
+        );
+        assert_eq!(
+            to_intent(&call("process_output", json!({ "pid": 4242 }))),
+            Ok(CommandIntent::Process(ProcessOperation::Output { pid: 4242 }))
+        );
+        assert_eq!(
+            to_intent(&call("search_timemachine", json!({ "query": "rust", "minutes_ago": 60 }))),
+            Ok(CommandIntent::TimeMachine(TimeMachineOperation::Search {
+                query: "rust".to_string(),
+                time_hint: Some(TimeHint::Ago { seconds: 3600 })
+            }))
+        );
+
+        assert!(to_intent(&call("delete_file", json!({}))).unwrap_err().contains("path"));
+        assert!(to_intent(&call("system_info", json!({ "kind": "gpu" }))).is_err());
+        assert!(to_intent(&call("format_disk", json!({}))).unwrap_err().contains("Unknown tool"));
+    }
+
+    #[test]
+    fn test_every_declared_tool_is_handled() {
+        let declared = declarations();
+        let functions = declared[0]["function_declarations"].as_array().unwrap();
+        assert_eq!(functions.len(), 13);
+        for function in functions {
+            let name = function["name"].as_str().unwrap();
+            let err = to_intent(&call(name, json!({}))).err().unwrap_or_default();
+            assert!(!err.contains("Unknown tool"), "{} is declared but not handled", name);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! Local speech for replies that arrive as text only
+//!
+//! Gemini's replies normally carry their own audio, but tool results, local
+//! answers, command output and some errors are text, and EVA would show
+//! them without a sound. `SpeechFallback` reads those out through a
+//! `TextToSpeech` backend: espeak-ng (the `espeak-tts` feature) where it is
+//! installed, or a tone placeholder that at least marks that EVA answered
+//! (Redox has no synthesizer yet). The backend and speaking rate come from
+//! the profile's `tts` section.
+
+use crate::accessibility::plain_text;
+use crate::audio_player::PLAYBACK_SAMPLE_RATE;
+use serde::{Deserialize, Serialize};
+
+/// Speech synthesizer
+pub trait TextToSpeech: Send {
+    /// `text` as mono samples at `PLAYBACK_SAMPLE_RATE`; empty if it
+    /// couldn't be spoken
+    fn synth(&mut self, text: &str) -> Vec<f32>;
+}
+
+/// Which synthesizer reads text-only replies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TtsBackend {
+    /// espeak-ng if it can be run, else tones
+    #[default]
+    Auto,
+    Espeak,
+    Tone,
+    /// Text-only replies stay silent
+    Off,
+}
+
+/// Local speech settings (stored in the profile)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TtsConfig {
+    #[serde(default)]
+    pub backend: TtsBackend,
+    /// Speaking rate, 1.0 = normal (0.5 to 2.0)
+    #[serde(default = "default_rate")]
+    pub rate: f32,
+    /// espeak-ng voice; follows the profile language when unset
+    #[serde(default)]
+    pub voice: Option<String>,
+}
+
+fn default_rate() -> f32 {
+    1.0
+}
+
+impl Default for TtsConfig {
+    fn default() -> Self {
+        Self { backend: TtsBackend::Auto, rate: default_rate(), voice: None }
+    }
+}
+
+/// Longest tone placeholder, however long the text
+const TONE_MAX_WORDS: usize = 12;
+
+/// One soft beep per word, rising and falling like a sentence
+pub struct ToneTts {
+    rate: f32,
+}
+
+impl ToneTts {
+    pub fn new(rate: f32) -> Self {
+        Self { rate: rate.clamp(0.5, 2.0) }
+    }
+}
+
+impl TextToSpeech for ToneTts {
+    fn synth(&mut self, text: &str) -> Vec<f32> {
+        let words: Vec<&str> = text.split_whitespace().take(TONE_MAX_WORDS).collect();
+        let ms = |ms: f32| (PLAYBACK_SAMPLE_RATE as f32 * ms / 1000.0 / self.rate) as usize;
+        let fade = ms(5.0).max(1);
+        let mut out = Vec::new();
+        for (n, word) in words.iter().enumerate() {
+            let len = ms(60.0 + 12.0 * word.chars().count().min(10) as f32);
+            let freq = if n % 2 == 0 { 440.0 } else { 494.0 } - if n + 1 == words.len() { 60.0 } else { 0.0 };
+            out.extend((0..len).map(|i| {
+                let envelope = (i.min(len - i) as f32 / fade as f32).min(1.0);
+                0.15 * envelope * (2.0 * std::f32::consts::PI * freq * i as f32 / PLAYBACK_SAMPLE_RATE as f32).sin()
+            }));
+            out.extend(std::iter::repeat(0.0).take(ms(40.0)));
+        }
+        out
+    }
+}
+
+/// espeak-ng's speed at rate 1.0, in words per minute
+#[cfg(feature = "espeak-tts")]
+const ESPEAK_WPM: f32 = 175.0;
+
+/// espeak-ng run as a subprocess, its WAV output converted to the
+/// playback rate
+#[cfg(feature = "espeak-tts")]
+pub struct EspeakTts {
+    voice: String,
+    rate: f32,
+}
+
+#[cfg(feature = "espeak-tts")]
+impl EspeakTts {
+    /// The configured voice, or one for the profile `language`
+    pub fn new(config: &TtsConfig, language: &str) -> Self {
+        let voice = config.voice.clone().unwrap_or_else(|| espeak_voice(language).to_string());
+        Self { voice, rate: config.rate.clamp(0.5, 2.0) }
+    }
+
+    /// Whether `espeak-ng` can be run
+    pub fn available() -> bool {
+        std::process::Command::new("espeak-ng")
+            .arg("--version")
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    }
+}
+
+#[cfg(feature = "espeak-tts")]
+impl TextToSpeech for EspeakTts {
+    fn synth(&mut self, text: &str) -> Vec<f32> {
+        let output = std::process::Command::new("espeak-ng")
+            .args(["--stdout", "-v", &self.voice, "-s"])
+            .arg(((ESPEAK_WPM * self.rate) as u32).to_string())
+            .arg("--")
+            .arg(text)
+            .stderr(std::process::Stdio::null())
+            .output();
+        let (rate, samples) = match output {
+            Ok(output) if output.status.success() => match parse_wav(&output.stdout) {
+                Some(wav) => wav,
+                None => {
+                    eprintln!("[TTS] espeak-ng wrote no usable audio");
+                    return Vec::new();
+                }
+            },
+            Ok(output) => {
+                eprintln!("[TTS] espeak-ng failed ({})", output.status);
+                return Vec::new();
+            }
+            Err(e) => {
+                eprintln!("[TTS] Could not run espeak-ng: {}", e);
+                return Vec::new();
+            }
+        };
+        let mut resampler = crate::resample::SincResampler::new(rate, PLAYBACK_SAMPLE_RATE);
+        let mut out = resampler.process(&samples);
+        // Push the last samples out of the filter
+        out.extend(resampler.process(&[0.0; 64]));
+        out
+    }
+}
+
+/// Sample rate and mono samples of a 16-bit PCM WAV file
+#[cfg(feature = "espeak-tts")]
+fn parse_wav(bytes: &[u8]) -> Option<(u32, Vec<f32>)> {
+    if bytes.len() < 12 || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return None;
+    }
+    let mut format = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let declared = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().ok()?) as usize;
+        // espeak-ng streams to stdout, so its data chunk size may be a placeholder
+        let body = &bytes[pos + 8..(pos + 8).saturating_add(declared).min(bytes.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let channels = u16::from_le_bytes([body[2], body[3]]).max(1) as usize;
+                let rate = u32::from_le_bytes(body[4..8].try_into().ok()?);
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                format = (bits == 16).then_some((rate, channels));
+            }
+            b"data" => {
+                let (rate, channels) = format?;
+                let samples = body
+                    .chunks_exact(2 * channels)
+                    .map(|frame| i16::from_le_bytes([frame[0], frame[1]]) as f32 / i16::MAX as f32)
+                    .collect();
+                return Some((rate, samples));
+            }
+            _ => {}
+        }
+        pos += 8 + declared + declared % 2;
+    }
+    None
+}
+
+/// Speaks the replies that came without audio
+pub struct SpeechFallback {
+    backend: Option<Box<dyn TextToSpeech>>,
+}
+
+impl SpeechFallback {
+    /// The backend the profile asks for, voiced for `language` unless it
+    /// names a voice
+    pub fn new(config: &TtsConfig, language: &str) -> Self {
+        let backend: Option<Box<dyn TextToSpeech>> = match config.backend {
+            TtsBackend::Off => None,
+            TtsBackend::Tone => Some(Box::new(ToneTts::new(config.rate))),
+            #[cfg(feature = "espeak-tts")]
+            TtsBackend::Espeak => Some(Box::new(EspeakTts::new(config, language))),
+            #[cfg(not(feature = "espeak-tts"))]
+            TtsBackend::Espeak => {
+                eprintln!("[TTS] Built without espeak-tts, using tones ({})", language);
+                Some(Box::new(ToneTts::new(config.rate)))
+            }
+            #[cfg(feature = "espeak-tts")]
+            TtsBackend::Auto if EspeakTts::available() => Some(Box::new(EspeakTts::new(config, language))),
+            TtsBackend::Auto => Some(Box::new(ToneTts::new(config.rate))),
+        };
+        Self::with_backend(backend)
+    }
+
+    pub fn with_backend(backend: Option<Box<dyn TextToSpeech>>) -> Self {
+        Self { backend }
+    }
+
+    /// What to play for a reply: its text, synthesized, when no audio came
+    /// with it (`voiced` is false); `None` when there's nothing to say
+    pub fn speech_for(&mut self, reply: &str, voiced: bool) -> Option<Vec<f32>> {
+        if voiced {
+            return None;
+        }
+        let text = plain_text(reply);
+        if text.trim().is_empty() {
+            return None;
+        }
+        let samples = self.backend.as_mut()?.synth(text.trim());
+        (!samples.is_empty()).then_some(samples)
+    }
+}
+
+/// espeak-ng voice for a profile language ("pt-BR" -> "pt-br")
+#[cfg(feature = "espeak-tts")]
+fn espeak_voice(language: &str) -> &'static str {
+    if language.to_lowercase().starts_with("pt") {
+        "pt-br"
+    } else {
+        "en-us"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Records what it was asked to say
+    struct MockTts(Arc<Mutex<Vec<String>>>);
+
+    impl TextToSpeech for MockTts {
+        fn synth(&mut self, text: &str) -> Vec<f32> {
+            self.0.lock().unwrap().push(text.to_string());
+            vec![0.1; 240]
+        }
+    }
+
+    #[test]
+    fn test_only_text_only_replies_are_synthesized() {
+        let said = Arc::new(Mutex::new(Vec::new()));
+        let mut speech = SpeechFallback::with_backend(Some(Box::new(MockTts(said.clone()))));
+
+        // Audio came with it: Gemini already spoke
+        assert_eq!(speech.speech_for("It is ten o'clock.", true), None);
+        assert!(said.lock().unwrap().is_empty());
+
+        // Text only: read out, without the emoji
+        assert_eq!(speech.speech_for("⏰ Timer set for 5 minutes", false).map(|s| s.len()), Some(240));
+        assert_eq!(*said.lock().unwrap(), vec!["Timer set for 5 minutes".to_string()]);
+
+        // Nothing to say
+        assert_eq!(speech.speech_for(" 🔊 ", false), None);
+        assert_eq!(said.lock().unwrap().len(), 1);
+
+        let mut off = SpeechFallback::new(&TtsConfig { backend: TtsBackend::Off, ..Default::default() }, "en-US");
+        assert_eq!(off.speech_for("hello", false), None);
+    }
+
+    #[test]
+    fn test_tone_placeholder_follows_rate() {
+        let normal = ToneTts::new(1.0).synth("three short words");
+        let fast = ToneTts::new(2.0).synth("three short words");
+        assert!(!normal.is_empty());
+        assert!(fast.len() * 2 <= normal.len() + 4, "{} vs {}", fast.len(), normal.len());
+        assert!(normal.iter().all(|s| s.abs() <= 0.15));
+
+        // Long text is capped
+        let long = ToneTts::new(1.0).synth(&"word ".repeat(500));
+        assert_eq!(long.len(), ToneTts::new(1.0).synth(&"word ".repeat(TONE_MAX_WORDS)).len());
+    }
+
+    #[test]
+    fn test_config_defaults_and_profile_json() {
+        let config: TtsConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(config, TtsConfig::default());
+        let config: TtsConfig = serde_json::from_str(r#"{"backend":"tone","rate":1.5,"voice":"pt-br"}"#).unwrap();
+        assert_eq!(config.backend, TtsBackend::Tone);
+        assert_eq!(config.rate, 1.5);
+    }
+
+    #[cfg(feature = "espeak-tts")]
+    #[test]
+    fn test_parse_wav() {
+        assert_eq!(espeak_voice("pt-BR"), "pt-br");
+        assert_eq!(espeak_voice("en-GB"), "en-us");
+
+        let mut wav = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+        wav.extend(16u32.to_le_bytes());
+        wav.extend([1, 0, 1, 0]);
+        wav.extend(22050u32.to_le_bytes());
+        wav.extend((22050u32 * 2).to_le_bytes());
+        wav.extend([2, 0, 16, 0]);
+        wav.extend(b"data");
+        // Streamed: size unknown
+        wav.extend(u32::MAX.to_le_bytes());
+        wav.extend([0x00, 0x40, 0x00, 0xC0]);
+        let (rate, samples) = parse_wav(&wav).unwrap();
+        assert_eq!(rate, 22050);
+        assert_eq!(samples.len(), 2);
+        assert!((samples[0] - 0.5).abs() < 0.001 && (samples[1] + 0.5).abs() < 0.001);
+        assert!(parse_wav(b"not a wav").is_none());
+    }
+}
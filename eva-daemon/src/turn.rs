@@ -0,0 +1,162 @@
+//! Per-turn routing: what a typed line or transcript is, before anything
+//! runs. The main loop and the scenario tests under `tests/` both call
+//! these, so a scripted turn goes where a real one would.
+//!
+//! `intercept` picks out the requests about EVA herself (guest mode,
+//! accessibility mode, forgetting or branching the conversation), which
+//! are handled outside the session. Everything else goes through
+//! `Router::plan`: an answer to a held command, the user's custom
+//! commands, a follow-up chip, the parser, the local answers and, last,
+//! the guest-mode gate.
+
+use crate::answers::{Answer, AnswerRouter};
+use crate::command_history::CommandHistory;
+use crate::command_parser::{CommandIntent, CommandParser, SessionOperation, TimeMachineOperation};
+use crate::command_executor::parse_confirmation;
+use crate::custom_commands::{CustomCommandManager, CustomOutcome};
+use crate::guest_mode::{self, GuestCommand, GuestMode};
+use crate::offline::{self, TurnRoute};
+use crate::statistics::AnswerSource;
+use crate::suggestions::{FollowUpAction, FollowUps};
+use chrono::{DateTime, TimeZone, Utc};
+use std::time::Instant;
+
+/// A request about EVA herself rather than a turn of the conversation
+#[derive(Debug, Clone, PartialEq)]
+pub enum Intercept {
+    Guest(GuestCommand),
+    /// "turn on accessibility mode"
+    Accessibility(bool),
+    /// "forget that" / "branch this conversation": rewrite the session
+    Session(SessionOperation),
+}
+
+/// Check `text` for a request handled outside the session
+pub fn intercept(parser: &CommandParser, text: &str) -> Option<Intercept> {
+    if let Some(command) = guest_mode::parse_guest_command(text) {
+        return Some(Intercept::Guest(command));
+    }
+    if let Some(enabled) = crate::accessibility::parse_toggle(text) {
+        return Some(Intercept::Accessibility(enabled));
+    }
+    match offline::route(parser, text) {
+        TurnRoute::Command(CommandIntent::Session(op @ (SessionOperation::ForgetLastExchange | SessionOperation::Branch))) => {
+            Some(Intercept::Session(op))
+        }
+        _ => None,
+    }
+}
+
+/// Where a turn goes
+#[derive(Debug)]
+pub enum Plan {
+    /// "yes" / "no" to the command the executor is holding
+    Confirm(bool),
+    /// One of the user's custom commands, or its next question
+    Custom(CustomOutcome),
+    /// Answered without the network
+    Served { reply: String, source: AnswerSource },
+    /// Not available to the guest who asked
+    Refused(String),
+    /// Run or ask; `chose` when it came from a follow-up chip, which runs
+    /// without the model second-guessing it
+    Route { route: TurnRoute, chose: bool },
+}
+
+/// What routing reads and updates
+pub struct Router<'a> {
+    pub parser: &'a CommandParser,
+    pub answers: &'a mut AnswerRouter,
+    pub follow_ups: &'a mut FollowUps,
+    pub history: &'a CommandHistory,
+    /// `None` where custom commands aren't loaded
+    pub custom: Option<&'a mut CustomCommandManager>,
+    pub guest: &'a GuestMode,
+    /// Whether the executor holds a command for a "yes"
+    pub confirming: bool,
+}
+
+impl Router<'_> {
+    /// Route one turn
+    pub fn plan<Tz: TimeZone>(&mut self, text: &str, now: DateTime<Utc>, tz: &Tz, at: Instant, portuguese: bool) -> Plan {
+        if let Some(answer) = self.confirming.then(|| parse_confirmation(text)).flatten() {
+            return Plan::Confirm(answer);
+        }
+        // The user's own commands (and answers to their parameter questions);
+        // guests can't run the owner's
+        let owner = !self.guest.is_active();
+        if let Some(outcome) = self.custom.as_deref_mut().filter(|_| owner).and_then(|custom| custom.take_input(text)) {
+            return Plan::Custom(outcome);
+        }
+        // "the second one" (or the chip's subject) picks a follow-up
+        // while they are on screen; it runs without re-parsing
+        let picked = self.follow_ups.accept_phrase(text, at);
+        let chose = picked.is_some();
+        let mut route = match picked.map(|chip| chip.action) {
+            Some(FollowUpAction::Run(intent)) => TurnRoute::Command(intent),
+            Some(FollowUpAction::ShowScreenshot(id)) => TurnRoute::TimeMachine(TimeMachineOperation::Show { id }),
+            None => offline::route(self.parser, text),
+        };
+        // "do that again" and "run command 3 again" name an entry of the
+        // owner's command history; one that isn't there reaches the executor
+        if owner {
+            match &mut route {
+                TurnRoute::Command(intent) => *intent = self.history.expand(intent.clone()),
+                TurnRoute::Sequence(intents) => intents.iter_mut().for_each(|intent| *intent = self.history.expand(intent.clone())),
+                _ => {}
+            }
+        }
+        // The time, sums, conversions, system info and what the model
+        // just answered are served without the network
+        if !chose {
+            match self.answers.answer(text, &route, now, tz, at, portuguese) {
+                Some(Answer::Local(reply)) => return Plan::Served { reply, source: AnswerSource::Local },
+                Some(Answer::Cached(reply)) => return Plan::Served { reply, source: AnswerSource::Cached },
+                Some(Answer::System(intent)) => route = TurnRoute::Command(intent),
+                None => {}
+            }
+        }
+        // A guest can't reach the owner's macros, captures, profile, timers or memory
+        match self.guest.admit(&route) {
+            Ok(()) => Plan::Route { route, chose },
+            Err(reason) => Plan::Refused(reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_parser::TimerOperation;
+
+    fn plan(router: &mut Router, text: &str) -> Plan {
+        router.plan(text, Utc::now(), &Utc, Instant::now(), false)
+    }
+
+    #[test]
+    fn test_requests_about_eva_are_intercepted() {
+        let parser = CommandParser::new();
+        assert_eq!(intercept(&parser, "forget the last exchange"), Some(Intercept::Session(SessionOperation::ForgetLastExchange)));
+        assert!(matches!(intercept(&parser, "EVA, guest mode"), Some(Intercept::Guest(GuestCommand::Enter))));
+        assert_eq!(intercept(&parser, "list files"), None);
+    }
+
+    #[test]
+    fn test_confirmation_only_while_a_command_is_held() {
+        let parser = CommandParser::new();
+        let (mut answers, mut follow_ups, history, guest) = (AnswerRouter::new(), FollowUps::new(), CommandHistory::new(), GuestMode::new(None));
+        let mut router = Router { parser: &parser, answers: &mut answers, follow_ups: &mut follow_ups, history: &history, custom: None, guest: &guest, confirming: true };
+        assert!(matches!(plan(&mut router, "yes"), Plan::Confirm(true)));
+        router.confirming = false;
+        assert!(matches!(plan(&mut router, "yes"), Plan::Route { route: TurnRoute::Model, chose: false }));
+    }
+
+    #[test]
+    fn test_local_answers_and_timers() {
+        let parser = CommandParser::new();
+        let (mut answers, mut follow_ups, history, guest) = (AnswerRouter::new(), FollowUps::new(), CommandHistory::new(), GuestMode::new(None));
+        let mut router = Router { parser: &parser, answers: &mut answers, follow_ups: &mut follow_ups, history: &history, custom: None, guest: &guest, confirming: false };
+        assert!(matches!(plan(&mut router, "what is 12 times 7"), Plan::Served { source: AnswerSource::Local, .. }));
+        assert!(matches!(plan(&mut router, "set a timer for 5 minutes"), Plan::Route { route: TurnRoute::Timer(TimerOperation::Set { .. }), .. }));
+    }
+}
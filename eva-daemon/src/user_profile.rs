@@ -1,32 +1,157 @@
+use crate::accessibility::AccessibilityConfig;
+use crate::animations::AnimationConfig;
+use crate::command_parser::ProfileOperation;
+use crate::command_executor::AllowedPath;
+use crate::gemini::QuotaLimits;
+use crate::stt::Language;
+use crate::tts::TtsConfig;
+use crate::vad::VadTuning;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+
+/// Name used until the user tells EVA theirs
+pub const DEFAULT_NAME: &str = "User";
+
+/// Fields `UserProfile::set` accepts, as spoken or typed
+pub const SETTABLE_FIELDS: &[&str] = &["name", "language", "voice_speed", "voice_pitch", "personality", "wake_word_sensitivity"];
+
+/// How often `ProfileWatcher::spawn` looks at profile.json
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The profile as read by the main loop and background tasks
+pub type SharedProfile = Arc<RwLock<UserProfile>>;
+
+/// How EVA picks the language of her replies
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", content = "language", rename_all = "snake_case")]
+pub enum ResponseLanguageMode {
+    /// Answer in whatever language the user just spoke
+    #[default]
+    Mirror,
+    /// Always answer in the given locale (e.g. "en-US")
+    Always(String),
+}
+
+/// Personality preset: voice and default delivery
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Personality {
+    #[default]
+    Friendly,
+    Calm,
+    Energetic,
+}
+
+impl Personality {
+    /// Gemini prebuilt voice
+    pub fn voice_name(&self) -> &'static str {
+        match self {
+            Personality::Friendly => "Aoede",
+            Personality::Calm => "Charon",
+            Personality::Energetic => "Puck",
+        }
+    }
+
+    /// Multiplied into the profile's voice speed
+    pub fn rate_factor(&self) -> f32 {
+        match self {
+            Personality::Friendly => 1.0,
+            Personality::Calm => 0.9,
+            Personality::Energetic => 1.1,
+        }
+    }
+
+    /// Added to the profile's pitch, in semitones
+    pub fn pitch_offset(&self) -> f32 {
+        match self {
+            Personality::Friendly => 0.0,
+            Personality::Calm => -2.0,
+            Personality::Energetic => 2.0,
+        }
+    }
+}
 
 /// User profile with preferences and settings
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UserProfile {
     pub name: String,
     pub language: String,
     pub voice_speed: f32,
+    /// Semitones relative to the voice's natural pitch
+    #[serde(default)]
+    pub voice_pitch: f32,
+    #[serde(default)]
+    pub personality: Personality,
     pub wake_word_sensitivity: f32,
     pub custom_wake_word: Option<String>,
     pub preferences: HashMap<String, String>,
+    #[serde(default)]
+    pub response_language: ResponseLanguageMode,
+    #[serde(default)]
+    pub accessibility: AccessibilityConfig,
+    /// Folders outside the sandbox that file commands may use
+    #[serde(default)]
+    pub allowed_paths: Vec<AllowedPath>,
+    /// Input device to capture from (a name from `AudioDevice::list_devices`);
+    /// the system default when unset or unplugged
+    #[serde(default)]
+    pub microphone: Option<String>,
+    /// Custom persona for Gemini; EVA's built-in one when unset
+    #[serde(default)]
+    pub system_instruction: Option<String>,
+    /// Voice detection levels measured by `eva-daemon --calibrate`
+    #[serde(default)]
+    pub vad: Option<VadTuning>,
+    /// Reply volume, 0.0 to 1.0 ("volume down")
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+    /// Soft limits on Gemini API use, checked before each turn
+    #[serde(default)]
+    pub gemini_quota: QuotaLimits,
+    /// Status spinners and reduced motion
+    #[serde(default)]
+    pub animations: AnimationConfig,
+    /// Local speech for replies that arrive without audio
+    #[serde(default)]
+    pub tts: TtsConfig,
 }
 
-impl UserProfile {
-    /// Create default user profile
-    pub fn default() -> Self {
+fn default_volume() -> f32 {
+    1.0
+}
+
+impl Default for UserProfile {
+    /// Default user profile
+    fn default() -> Self {
         Self {
-            name: "User".to_string(),
+            name: DEFAULT_NAME.to_string(),
             language: "en-US".to_string(),
             voice_speed: 1.0,
+            voice_pitch: 0.0,
+            personality: Personality::Friendly,
             wake_word_sensitivity: 0.6,
             custom_wake_word: None,
             preferences: HashMap::new(),
+            response_language: ResponseLanguageMode::Mirror,
+            accessibility: AccessibilityConfig::default(),
+            allowed_paths: Vec::new(),
+            microphone: None,
+            system_instruction: None,
+            vad: None,
+            volume: default_volume(),
+            gemini_quota: QuotaLimits::default(),
+            animations: AnimationConfig::default(),
+            tts: TtsConfig::default(),
         }
     }
+}
 
+impl UserProfile {
     /// Load user profile from disk
     pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
         let path = Self::get_profile_path()?;
@@ -37,37 +162,49 @@ impl UserProfile {
             return Ok(profile);
         }
 
+        Self::load_from(&path)
+    }
+
+    /// Read a profile file
+    pub fn load_from(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(path)?;
         Ok(serde_json::from_str(&content)?)
     }
 
+    /// Wrap the profile for sharing between tasks
+    pub fn shared(self) -> SharedProfile {
+        Arc::new(RwLock::new(self))
+    }
+
     /// Save user profile to disk
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let path = Self::get_profile_path()?;
+        self.save_to(&Self::get_profile_path()?)
+    }
 
+    /// Write the profile to `path`
+    pub fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
         // Ensure directory exists
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
 
+        // Written aside and renamed over, so an interrupted save leaves the
+        // old profile intact
         let json = serde_json::to_string_pretty(self)?;
-        fs::write(path, json)?;
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, json)?;
+        fs::rename(&partial, path)?;
         Ok(())
     }
 
     /// Get profile file path
-    fn get_profile_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
-        #[cfg(target_os = "windows")]
-        {
-            let home = std::env::var("USERPROFILE")?;
-            Ok(PathBuf::from(home).join(".eva").join("profile.json"))
-        }
+    pub fn get_profile_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        crate::paths::data_file("profile.json")
+    }
 
-        #[cfg(not(target_os = "windows"))]
-        {
-            let home = std::env::var("HOME")?;
-            Ok(PathBuf::from(home).join(".eva").join("profile.json"))
-        }
+    /// Turn accessibility mode on or off (voice toggle)
+    pub fn set_accessibility(&mut self, enabled: bool) {
+        self.accessibility.enabled = enabled;
     }
 
     /// Set a preference
@@ -85,6 +222,11 @@ impl UserProfile {
         self.language = language.to_string();
     }
 
+    /// Choose how the reply language is picked
+    pub fn set_response_language(&mut self, mode: ResponseLanguageMode) {
+        self.response_language = mode;
+    }
+
     /// Update wake word sensitivity
     pub fn set_wake_word_sensitivity(&mut self, sensitivity: f32) {
         self.wake_word_sensitivity = sensitivity.clamp(0.0, 1.0);
@@ -95,15 +237,200 @@ impl UserProfile {
         self.voice_speed = speed.clamp(0.5, 2.0);
     }
 
+    /// Update voice pitch (semitones)
+    pub fn set_voice_pitch(&mut self, semitones: f32) {
+        self.voice_pitch = semitones.clamp(-10.0, 10.0);
+    }
+
+    /// Apply "speak slower" / "fale mais rápido" style requests.
+    /// Returns true when the voice settings changed.
+    pub fn adjust_speech(&mut self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        let has = |phrases: &[&str]| phrases.iter().any(|p| text.contains(p));
+        let (speed, pitch) = (self.voice_speed, self.voice_pitch);
+
+        if has(&["speak slower", "talk slower", "slow down", "mais devagar"]) {
+            self.set_voice_speed(self.voice_speed - 0.1);
+        } else if has(&["speak faster", "talk faster", "speed up", "mais rápido", "mais rapido"]) {
+            self.set_voice_speed(self.voice_speed + 0.1);
+        } else if has(&["normal speed", "velocidade normal"]) {
+            self.set_voice_speed(1.0);
+        }
+
+        if has(&["deeper voice", "lower pitch", "voz mais grave"]) {
+            self.set_voice_pitch(self.voice_pitch - 1.0);
+        } else if has(&["higher voice", "higher pitch", "voz mais aguda"]) {
+            self.set_voice_pitch(self.voice_pitch + 1.0);
+        }
+
+        // Round away float drift from repeated steps
+        self.voice_speed = (self.voice_speed * 100.0).round() / 100.0;
+        self.voice_speed != speed || self.voice_pitch != pitch
+    }
+
     /// Set custom wake word
     pub fn set_custom_wake_word(&mut self, wake_word: Option<String>) {
         self.custom_wake_word = wake_word;
     }
+
+    /// Change one field from a spoken or typed value ("language" →
+    /// "English", "name" → "Daniel"); see `SETTABLE_FIELDS`.
+    ///
+    /// Values are checked rather than clamped: a misheard "voice speed 20"
+    /// is an error to read back, not a silent 2.0.
+    pub fn set(&mut self, field: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        let number = |range: std::ops::RangeInclusive<f32>| {
+            value
+                .replace(',', ".")
+                .parse::<f32>()
+                .ok()
+                .filter(|n| range.contains(n))
+                .ok_or_else(|| format!("{} must be a number from {} to {}", field, range.start(), range.end()))
+        };
+
+        match field {
+            "name" => {
+                if value.is_empty() || value.chars().count() > 40 || value.chars().any(char::is_control) {
+                    return Err("name must be 1 to 40 printable characters".to_string());
+                }
+                self.name = value.to_string();
+            }
+            "language" => {
+                let language = Language::from_name(value)
+                    .or_else(|| Language::from_tag(value))
+                    .ok_or_else(|| format!("unsupported language '{}'", value))?;
+                self.set_language(language.locale());
+            }
+            "voice_speed" => self.voice_speed = number(0.5..=2.0)?,
+            "voice_pitch" => self.voice_pitch = number(-10.0..=10.0)?,
+            "wake_word_sensitivity" => self.wake_word_sensitivity = number(0.0..=1.0)?,
+            "personality" => {
+                self.personality = match value.to_lowercase().as_str() {
+                    "friendly" | "amigável" | "amigavel" => Personality::Friendly,
+                    "calm" | "calma" => Personality::Calm,
+                    "energetic" | "animada" | "energética" | "energetica" => Personality::Energetic,
+                    _ => return Err("personality must be friendly, calm or energetic".to_string()),
+                };
+            }
+            _ => return Err(format!("'{}' can't be changed (try: {})", field, SETTABLE_FIELDS.join(", "))),
+        }
+        Ok(())
+    }
 }
 
-impl Default for UserProfile {
-    fn default() -> Self {
-        Self::default()
+/// Keep the reply volume chosen by voice, saving the profile to `path`
+pub fn save_volume(profile: &SharedProfile, volume: f32, path: &Path) -> Result<(), String> {
+    let mut shared = profile.write().unwrap_or_else(|e| e.into_inner());
+    let mut updated = shared.clone();
+    updated.volume = volume.clamp(0.0, 1.0);
+    updated.save_to(path).map_err(|e| format!("Could not save the profile: {}", e))?;
+    *shared = updated;
+    Ok(())
+}
+
+/// Carry out a voice profile command on the shared profile, saving it to
+/// `path`; the reply says what changed. Nothing changes if the value is
+/// rejected or the file can't be written.
+pub fn apply_operation(profile: &SharedProfile, op: ProfileOperation, path: &Path, portuguese: bool) -> Result<String, String> {
+    let ProfileOperation::Set { field, value } = op;
+    let mut shared = profile.write().unwrap_or_else(|e| e.into_inner());
+    let mut updated = shared.clone();
+    updated.set(&field, &value)?;
+    updated.save_to(path).map_err(|e| format!("Could not save the profile: {}", e))?;
+    *shared = updated;
+
+    let language = Language::from_tag(&shared.language).map_or(shared.language.as_str(), |l| l.name());
+    Ok(match (field.as_str(), portuguese) {
+        ("name", false) => format!("OK, I'll call you {}.", shared.name),
+        ("name", true) => format!("Certo, vou te chamar de {}.", shared.name),
+        ("language", false) => format!("Language switched to {}.", language),
+        ("language", true) => format!("Idioma alterado para {}.", shared.language),
+        (field, false) => format!("{} set to {}.", field.replace('_', " "), value),
+        (field, true) => format!("{} alterado para {}.", field.replace('_', " "), value),
+    })
+}
+
+/// Which of the fields that need propagating differ between two profiles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProfileChange {
+    /// Speech recognition and the reply language follow it
+    pub language: bool,
+    /// Part of the system instruction
+    pub name: bool,
+}
+
+impl ProfileChange {
+    pub fn between(old: &UserProfile, new: &UserProfile) -> Self {
+        Self { language: old.language != new.language, name: old.name != new.name }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.language && !self.name
+    }
+}
+
+/// Picks up edits to profile.json by checking its modification time
+pub struct ProfileWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ProfileWatcher {
+    /// Watch the profile at its usual location
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_path(UserProfile::get_profile_path()?))
+    }
+
+    /// Watch `path`; its current contents count as already loaded
+    pub fn with_path(path: PathBuf) -> Self {
+        let modified = Self::mtime(&path);
+        Self { path, modified }
+    }
+
+    fn mtime(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Reload into `shared` if the file changed since the last check.
+    ///
+    /// `Ok(true)` when the reloaded profile differs from the shared one;
+    /// a file that fails to parse (a half-saved edit) leaves it untouched.
+    pub fn poll(&mut self, shared: &SharedProfile) -> Result<bool, String> {
+        let modified = Self::mtime(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return Ok(false);
+        }
+        self.modified = modified;
+
+        let fresh = UserProfile::load_from(&self.path).map_err(|e| format!("profile.json: {}", e))?;
+        let mut profile = shared.write().unwrap_or_else(|e| e.into_inner());
+        if *profile == fresh {
+            return Ok(false);
+        }
+        *profile = fresh;
+        Ok(true)
+    }
+
+    /// Poll every `interval` on a background task; a message arrives each
+    /// time the shared profile was replaced (or the file was unreadable)
+    pub fn spawn(mut self, shared: SharedProfile, interval: Duration) -> mpsc::UnboundedReceiver<Result<(), String>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let update = match self.poll(&shared) {
+                    Ok(false) => continue,
+                    Ok(true) => Ok(()),
+                    Err(e) => Err(e),
+                };
+                if tx.send(update).is_err() {
+                    return;
+                }
+            }
+        });
+        rx
     }
 }
 
@@ -159,4 +486,113 @@ mod tests {
         profile.set_voice_speed(0.1);
         assert_eq!(profile.voice_speed, 0.5);
     }
+
+    #[test]
+    fn test_response_language_defaults_for_old_profiles() {
+        let json = r#"{"name":"Ana","language":"pt-BR","voice_speed":1.0,"wake_word_sensitivity":0.6,"custom_wake_word":null,"preferences":{}}"#;
+        let profile: UserProfile = serde_json::from_str(json).unwrap();
+        assert_eq!(profile.response_language, ResponseLanguageMode::Mirror);
+        assert!(!profile.accessibility.enabled);
+        assert!(profile.accessibility.earcons);
+        assert!(profile.allowed_paths.is_empty());
+        assert_eq!(profile.volume, 1.0);
+
+        let mut profile = profile;
+        profile.set_response_language(ResponseLanguageMode::Always("en-US".to_string()));
+        let json = serde_json::to_string(&profile).unwrap();
+        assert!(json.contains(r#""mode":"always""#));
+    }
+
+    #[test]
+    fn test_adjust_speech() {
+        let mut profile = UserProfile::default();
+        assert!(profile.adjust_speech("EVA, please speak slower"));
+        assert_eq!(profile.voice_speed, 0.9);
+        assert!(profile.adjust_speech("fale mais rápido"));
+        assert_eq!(profile.voice_speed, 1.0);
+        assert!(profile.adjust_speech("use a deeper voice"));
+        assert_eq!(profile.voice_pitch, -1.0);
+        assert!(!profile.adjust_speech("what time is it"));
+        assert_eq!(profile.personality, Personality::Friendly);
+    }
+
+    #[test]
+    fn test_set_validates_values() {
+        let mut profile = UserProfile::default();
+        profile.set("name", " Daniel ").unwrap();
+        assert_eq!(profile.name, "Daniel");
+        profile.set("language", "Português").unwrap();
+        assert_eq!(profile.language, "pt-BR");
+        profile.set("language", "en").unwrap();
+        assert_eq!(profile.language, "en-US");
+        profile.set("voice_speed", "1,5").unwrap();
+        assert_eq!(profile.voice_speed, 1.5);
+        profile.set("personality", "calm").unwrap();
+        assert_eq!(profile.personality, Personality::Calm);
+
+        // Rejected values leave the field alone
+        let before = profile.clone();
+        assert!(profile.set("language", "klingon").unwrap_err().contains("klingon"));
+        assert!(profile.set("voice_speed", "20").is_err());
+        assert!(profile.set("name", "").is_err());
+        assert!(profile.set("personality", "grumpy").is_err());
+        assert!(profile.set("allowed_paths", "/").unwrap_err().contains("language"));
+        assert_eq!(profile, before);
+    }
+
+    #[test]
+    fn test_voice_operation_saves_and_replies() {
+        let path = std::env::temp_dir().join(format!("eva_profile_op_{}.json", std::process::id()));
+        let profile = UserProfile::default().shared();
+        let set = |field: &str, value: &str| ProfileOperation::Set { field: field.to_string(), value: value.to_string() };
+
+        let reply = apply_operation(&profile, set("name", "Daniel"), &path, false).unwrap();
+        assert_eq!(reply, "OK, I'll call you Daniel.");
+        let reply = apply_operation(&profile, set("language", "português"), &path, true).unwrap();
+        assert_eq!(reply, "Idioma alterado para pt-BR.");
+        let saved = UserProfile::load_from(&path).unwrap();
+        assert_eq!((saved.name.as_str(), saved.language.as_str()), ("Daniel", "pt-BR"));
+
+        assert!(apply_operation(&profile, set("language", "klingon"), &path, false).is_err());
+        assert_eq!(*profile.read().unwrap(), saved);
+
+        save_volume(&profile, 1.4, &path).unwrap();
+        assert_eq!(UserProfile::load_from(&path).unwrap().volume, 1.0);
+        save_volume(&profile, 0.3, &path).unwrap();
+        assert_eq!(UserProfile::load_from(&path).unwrap().volume, 0.3);
+        assert_eq!(profile.read().unwrap().volume, 0.3);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_watcher_reloads_changed_file() {
+        let path = std::env::temp_dir().join(format!("eva_profile_watch_{}.json", std::process::id()));
+        let write = |profile: &UserProfile| fs::write(&path, serde_json::to_string(profile).unwrap()).unwrap();
+        let mut profile = UserProfile::default();
+        write(&profile);
+        let shared = profile.clone().shared();
+        let mut watcher = ProfileWatcher::with_path(path.clone());
+        assert_eq!(watcher.poll(&shared), Ok(false));
+
+        // mtime granularity can be coarse: make sure the edit looks newer
+        profile.set("name", "Ana").unwrap();
+        profile.set("language", "pt-BR").unwrap();
+        write(&profile);
+        watcher.modified = Some(SystemTime::UNIX_EPOCH);
+        assert_eq!(watcher.poll(&shared), Ok(true));
+        let reloaded = shared.read().unwrap().clone();
+        assert_eq!(reloaded.name, "Ana");
+        let change = ProfileChange::between(&UserProfile::default(), &reloaded);
+        assert!(change.name && change.language);
+        assert!(ProfileChange::between(&reloaded, &reloaded).is_empty());
+
+        // Same contents again, or a half-written file: nothing replaced
+        watcher.modified = Some(SystemTime::UNIX_EPOCH);
+        assert_eq!(watcher.poll(&shared), Ok(false));
+        fs::write(&path, "{\"name\":").unwrap();
+        watcher.modified = Some(SystemTime::UNIX_EPOCH);
+        assert!(watcher.poll(&shared).is_err());
+        assert_eq!(shared.read().unwrap().name, "Ana");
+        let _ = fs::remove_file(&path);
+    }
 }
@@ -1,74 +1,192 @@
+use crate::audio_frontend::FeatureFrame;
+use serde::{Deserialize, Serialize};
+
+/// VAD timing and sensitivity
+#[derive(Debug, Clone, PartialEq)]
+pub struct VadConfig {
+    pub sample_rate: u32,
+    /// Voiced audio needed before a speech segment starts
+    pub min_speech_ms: u32,
+    /// Quiet audio needed before a speech segment ends
+    pub min_silence_ms: u32,
+    /// Speech starts above `noise_floor * ratio` (attack)
+    pub ratio: f32,
+    /// Speech continues while above `noise_floor * release_ratio`
+    pub release_ratio: f32,
+    /// How fast the noise floor follows non-speech frames (0..1 per frame)
+    pub floor_adapt: f32,
+    /// Lowest noise floor, so digital silence doesn't make every click speech
+    pub min_floor: f32,
+    /// Give up on a turn where nobody spoke at all
+    pub no_speech_timeout_ms: u32,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: crate::audio::SAMPLE_RATE,
+            min_speech_ms: 300,
+            min_silence_ms: 1000,
+            ratio: 3.0,
+            release_ratio: 1.8,
+            floor_adapt: 0.05,
+            min_floor: 0.002,
+            no_speech_timeout_ms: 5000,
+        }
+    }
+}
+
+/// Current levels, for the statistics panel
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct VadEnergy {
+    /// RMS of the last frame
+    pub energy: f32,
+    pub noise_floor: f32,
+    /// Level a frame must pass to start speech
+    pub attack: f32,
+    /// Level a frame must stay above to continue speech
+    pub release: f32,
+    pub in_speech: bool,
+}
+
+/// Levels measured for one microphone by `eva-daemon --calibrate`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VadTuning {
+    /// Background level of the room; the noise floor never drops below it
+    pub noise_floor: f32,
+    /// Speech starts above `noise_floor * ratio`
+    pub ratio: f32,
+    /// Lowest attack level, in place of the built-in 0.02
+    pub energy_threshold: f32,
+}
+
 /// Voice Activity Detection (VAD) module
+///
+/// Speech is energy well above a running estimate of the room's background
+/// noise, with separate start/continue thresholds so a segment doesn't
+/// flicker on and off around a single level.
 pub struct VAD {
+    config: VadConfig,
+    /// Absolute minimum for the attack threshold
     energy_threshold: f32,
     zcr_threshold: f32,
-    silence_frames: usize,
-    speech_frames: usize,
-    current_silence: usize,
-    current_speech: usize,
+    noise_floor: f32,
+    last_energy: f32,
+    in_speech: bool,
+    /// Set once a segment started since the last reset
+    heard_speech: bool,
+    /// Consecutive voiced/quiet time, in ms
+    current_silence: u32,
+    current_speech: u32,
 }
 
 impl VAD {
     /// Create a new VAD with default thresholds
     pub fn new() -> Self {
+        Self::with_config(VadConfig::default())
+    }
+
+    pub fn with_config(config: VadConfig) -> Self {
         Self {
+            noise_floor: config.min_floor,
+            config,
             energy_threshold: 0.02,
             zcr_threshold: 0.1,
-            silence_frames: 10,  // ~1 second at 100ms chunks
-            speech_frames: 3,    // ~300ms
+            last_energy: 0.0,
+            in_speech: false,
+            heard_speech: false,
             current_silence: 0,
             current_speech: 0,
         }
     }
 
-    /// Check if audio contains speech
+    fn attack_level(&self) -> f32 {
+        (self.noise_floor * self.config.ratio).max(self.energy_threshold)
+    }
+
+    fn release_level(&self) -> f32 {
+        // Never above the attack level, whatever the ratios
+        (self.noise_floor * self.config.release_ratio).min(self.attack_level())
+    }
+
+    /// Feed one frame; true while inside a speech segment
     pub fn is_speech(&mut self, samples: &[f32]) -> bool {
-        let energy = self.calculate_energy(samples);
-        let zcr = self.zero_crossing_rate(samples);
-        
-        // Speech detected if both energy and ZCR are above thresholds
-        let is_active = energy > self.energy_threshold && zcr > self.zcr_threshold;
-        
-        if is_active {
-            self.current_speech += 1;
+        self.is_speech_frame(&FeatureFrame::levels(samples))
+    }
+
+    /// Same, from features the frontend already computed
+    pub fn is_speech_frame(&mut self, frame: &FeatureFrame) -> bool {
+        let energy = frame.rms;
+        self.last_energy = energy;
+        let frame_ms = (frame.samples.len() as u64 * 1000 / self.config.sample_rate.max(1) as u64) as u32;
+
+        let voiced = if self.in_speech {
+            energy > self.release_level()
+        } else {
+            energy > self.attack_level() && frame.zcr > self.zcr_threshold
+        };
+
+        if voiced {
+            self.current_speech += frame_ms;
             self.current_silence = 0;
-            
-            // Require minimum speech frames
-            self.current_speech >= self.speech_frames
+            if self.current_speech >= self.config.min_speech_ms {
+                self.in_speech = true;
+                self.heard_speech = true;
+            }
         } else {
-            self.current_silence += 1;
+            self.current_silence += frame_ms;
             self.current_speech = 0;
-            
-            // Not silence yet if we haven't had enough silence frames
-            self.current_silence < self.silence_frames
+            if self.current_silence >= self.config.min_silence_ms {
+                self.in_speech = false;
+            }
         }
-    }
 
-    /// Calculate audio energy (RMS)
-    fn calculate_energy(&self, samples: &[f32]) -> f32 {
-        if samples.is_empty() {
-            return 0.0;
+        if !voiced && !self.in_speech {
+            self.adapt_floor(energy);
         }
-        
-        let sum: f32 = samples.iter().map(|&s| s * s).sum();
-        (sum / samples.len() as f32).sqrt()
+
+        self.in_speech
+    }
+
+    /// Feed audio heard while nobody is talking to EVA (e.g. while waiting
+    /// for the wake word), so the floor is known before the first utterance
+    /// even in a room that is louder than the fixed threshold
+    pub fn observe_background(&mut self, samples: &[f32]) {
+        self.observe_background_frame(&FeatureFrame::levels(samples));
     }
 
-    /// Calculate zero-crossing rate
-    fn zero_crossing_rate(&self, samples: &[f32]) -> f32 {
-        if samples.len() < 2 {
-            return 0.0;
+    /// Same, from features the frontend already computed
+    pub fn observe_background_frame(&mut self, frame: &FeatureFrame) {
+        self.last_energy = frame.rms;
+        self.adapt_floor(frame.rms);
+    }
+
+    /// Drop at once when the room gets quieter, rise slowly when it gets
+    /// louder so a word or two barely moves it
+    fn adapt_floor(&mut self, energy: f32) {
+        let floor = self.noise_floor;
+        let next = if energy < floor { energy } else { floor + (energy - floor) * self.config.floor_adapt };
+        self.noise_floor = next.max(self.config.min_floor);
+    }
+
+    /// The user spoke and has since been quiet for `min_silence_ms`, or
+    /// never started within `no_speech_timeout_ms`
+    pub fn is_end_of_utterance(&self) -> bool {
+        if self.heard_speech {
+            !self.in_speech && self.current_silence >= self.config.min_silence_ms
+        } else {
+            self.current_silence >= self.config.no_speech_timeout_ms
         }
-        
-        let mut crossings = 0;
-        for i in 1..samples.len() {
-            if (samples[i] >= 0.0 && samples[i - 1] < 0.0) ||
-               (samples[i] < 0.0 && samples[i - 1] >= 0.0) {
-                crossings += 1;
-            }
+    }
+
+    pub fn energy_snapshot(&self) -> VadEnergy {
+        VadEnergy {
+            energy: self.last_energy,
+            noise_floor: self.noise_floor,
+            attack: self.attack_level(),
+            release: self.release_level(),
+            in_speech: self.in_speech,
         }
-        
-        crossings as f32 / samples.len() as f32
     }
 
     /// Set energy threshold
@@ -81,10 +199,27 @@ impl VAD {
         self.zcr_threshold = threshold.max(0.0);
     }
 
-    /// Reset VAD state
+    /// `config` with calibrated levels; release keeps its default
+    /// proportion to the attack ratio
+    pub fn with_tuning(config: VadConfig, tuning: VadTuning) -> Self {
+        let defaults = VadConfig::default();
+        let config = VadConfig {
+            min_floor: tuning.noise_floor,
+            ratio: tuning.ratio,
+            release_ratio: tuning.ratio * defaults.release_ratio / defaults.ratio,
+            ..config
+        };
+        let mut vad = Self::with_config(config);
+        vad.set_energy_threshold(tuning.energy_threshold);
+        vad
+    }
+
+    /// Start a new utterance; the learned noise floor is kept
     pub fn reset(&mut self) {
         self.current_silence = 0;
         self.current_speech = 0;
+        self.in_speech = false;
+        self.heard_speech = false;
     }
 
     /// Get current energy threshold
@@ -104,6 +239,72 @@ impl Default for VAD {
     }
 }
 
+/// Analysis window for `trim_silence`
+const TRIM_FRAME_MS: u32 = 20;
+/// Quiet kept around the speech when an utterance is trimmed
+pub const TRIM_PAD_MS: u32 = 150;
+
+/// `samples` without the quiet before the first and after the last frame
+/// louder than `threshold` (RMS), keeping `pad_ms` on either side so word
+/// onsets and tails survive. Pauses between words are left alone; with
+/// nothing above the threshold the audio is returned as is.
+pub fn trim_silence(samples: &[f32], sample_rate: u32, threshold: f32, pad_ms: u32) -> &[f32] {
+    let frame = (sample_rate * TRIM_FRAME_MS / 1000).max(1) as usize;
+    let loud = |chunk: &[f32]| (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt() > threshold;
+
+    let frames: Vec<&[f32]> = samples.chunks(frame).collect();
+    let (Some(first), Some(last)) = (frames.iter().position(|c| loud(c)), frames.iter().rposition(|c| loud(c))) else {
+        return samples;
+    };
+    let pad = (sample_rate as u64 * pad_ms as u64 / 1000) as usize;
+    let start = (first * frame).saturating_sub(pad);
+    let end = ((last + 1) * frame + pad).min(samples.len());
+    &samples[start..end]
+}
+
+/// Detects the user talking over EVA's reply
+///
+/// Mic chunks are only counted as speech when they are louder than what
+/// EVA's own voice could leak back from the speakers, so the reply can't
+/// interrupt itself.
+pub struct BargeInDetector {
+    vad: VAD,
+    /// Floor on the mic level, even with nothing playing
+    min_energy: f32,
+    /// Fraction of the playback level expected to reach the mic
+    echo_coupling: f32,
+    /// Consecutive voiced chunks needed (~300ms)
+    frames_needed: usize,
+    current: usize,
+}
+
+impl BargeInDetector {
+    pub fn new() -> Self {
+        Self { vad: VAD::new(), min_energy: 0.05, echo_coupling: 0.5, frames_needed: 3, current: 0 }
+    }
+
+    /// Feed a mic chunk captured while EVA speaks; `playback_level` is the
+    /// RMS of the audio being played. True once speech is sustained.
+    pub fn update(&mut self, samples: &[f32], playback_level: f32) -> bool {
+        let gate = self.min_energy.max(self.vad.energy_threshold) + playback_level * self.echo_coupling;
+        let levels = FeatureFrame::levels(samples);
+        let voiced = levels.rms > gate && levels.zcr > self.vad.zcr_threshold;
+
+        self.current = if voiced { self.current + 1 } else { 0 };
+        self.current >= self.frames_needed
+    }
+
+    pub fn reset(&mut self) {
+        self.current = 0;
+    }
+}
+
+impl Default for BargeInDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -115,37 +316,6 @@ mod tests {
         assert_eq!(vad.zcr_threshold, 0.1);
     }
 
-    #[test]
-    fn test_energy_calculation() {
-        let vad = VAD::new();
-        
-        // Silence
-        let silence = vec![0.0; 100];
-        assert_eq!(vad.calculate_energy(&silence), 0.0);
-        
-        // Full scale
-        let loud = vec![1.0; 100];
-        assert_eq!(vad.calculate_energy(&loud), 1.0);
-        
-        // Half scale
-        let medium = vec![0.5; 100];
-        assert_eq!(vad.calculate_energy(&medium), 0.5);
-    }
-
-    #[test]
-    fn test_zero_crossing_rate() {
-        let vad = VAD::new();
-        
-        // No crossings (DC signal)
-        let dc = vec![0.5; 100];
-        assert_eq!(vad.zero_crossing_rate(&dc), 0.0);
-        
-        // Maximum crossings (alternating signal)
-        let alternating: Vec<f32> = (0..100).map(|i| if i % 2 == 0 { 1.0 } else { -1.0 }).collect();
-        let zcr = vad.zero_crossing_rate(&alternating);
-        assert!(zcr > 0.9); // Should be close to 1.0
-    }
-
     #[test]
     fn test_speech_detection_silence() {
         let mut vad = VAD::new();
@@ -170,8 +340,8 @@ mod tests {
             .map(|i| (2.0 * PI * i as f32 / 10.0).sin() * 0.5)
             .collect();
         
-        // Feed speech multiple times to trigger detection
-        for _ in 0..5 {
+        // Feed speech until min_speech_ms (100 samples = 6 ms at 16 kHz)
+        for _ in 0..50 {
             vad.is_speech(&speech);
         }
         
@@ -194,6 +364,29 @@ mod tests {
         assert_eq!(vad.energy_threshold(), 0.0);
     }
 
+    #[test]
+    fn test_barge_in_ignores_echo_of_own_voice() {
+        use std::f32::consts::PI;
+        let tone = |amp: f32| -> Vec<f32> { (0..1600).map(|i| (2.0 * PI * i as f32 / 8.0).sin() * amp).collect() };
+
+        // EVA playing at 0.3 RMS, about a third of it leaking into the mic
+        let mut barge_in = BargeInDetector::new();
+        for _ in 0..10 {
+            assert!(!barge_in.update(&tone(0.14), 0.3));
+        }
+
+        // The user speaking up over it, sustained
+        assert!(!barge_in.update(&tone(0.5), 0.3));
+        assert!(!barge_in.update(&tone(0.5), 0.3));
+        assert!(barge_in.update(&tone(0.5), 0.3));
+
+        // A single loud chunk (a cough) is not enough
+        barge_in.reset();
+        assert!(!barge_in.update(&tone(0.5), 0.3));
+        assert!(!barge_in.update(&tone(0.0), 0.3));
+        assert!(!barge_in.update(&tone(0.5), 0.3));
+    }
+
     #[test]
     fn test_reset() {
         let mut vad = VAD::new();
@@ -207,4 +400,128 @@ mod tests {
         assert_eq!(vad.current_silence, 0);
         assert_eq!(vad.current_speech, 0);
     }
+
+    #[test]
+    fn test_trim_silence_keeps_inner_pauses() {
+        let rate = 1000;
+        let ms = |n: usize, rms: f32| chunk(rms)[..n].to_vec();
+        // 300 ms quiet, "send the", 240 ms pause, "report", 400 ms quiet
+        let audio: Vec<f32> = [ms(300, 0.001), ms(200, 0.2), ms(240, 0.001), ms(200, 0.2), ms(400, 0.001)].concat();
+
+        let trimmed = trim_silence(&audio, rate, 0.01, 40);
+        // Speech runs 300..940 ms, plus 40 ms either side
+        assert_eq!(trimmed.len(), 640 + 80);
+        assert_eq!(trimmed.as_ptr(), audio[260..].as_ptr());
+
+        // No padding past the ends
+        let edge: Vec<f32> = [ms(100, 0.2), ms(100, 0.001)].concat();
+        assert_eq!(trim_silence(&edge, rate, 0.01, 40).len(), 140);
+
+        // Nothing loud: nothing to anchor on, keep it all
+        let quiet = ms(500, 0.001);
+        assert_eq!(trim_silence(&quiet, rate, 0.01, 40).len(), 500);
+    }
+
+    /// 100 ms chunks of noise-like signal (ZCR 0.25) at the given RMS
+    fn chunk(rms: f32) -> Vec<f32> {
+        let pattern = [1.0, 0.6, -0.8, -1.2];
+        let scale = rms / (pattern.iter().map(|p: &f32| p * p).sum::<f32>() / 4.0).sqrt();
+        (0..1600).map(|i| pattern[i % 4] * scale).collect()
+    }
+
+    #[test]
+    fn test_noise_floor_follows_room() {
+        let mut vad = VAD::new();
+        // A fan at 0.05 RMS, louder than the fixed 0.02 threshold used to
+        // be, heard while idle
+        for _ in 0..60 {
+            vad.observe_background(&chunk(0.05));
+        }
+        vad.is_speech(&chunk(0.05));
+        let snapshot = vad.energy_snapshot();
+        assert!(!snapshot.in_speech);
+        assert!((snapshot.noise_floor - 0.05).abs() < 0.01);
+        assert!(snapshot.attack > 0.1 && snapshot.release < snapshot.attack);
+
+        // Speaking over the fan, then stopping
+        for _ in 0..10 {
+            vad.is_speech(&chunk(0.3));
+        }
+        assert!(vad.energy_snapshot().in_speech);
+        for _ in 0..9 {
+            vad.is_speech(&chunk(0.05));
+            assert!(!vad.is_end_of_utterance());
+        }
+        vad.is_speech(&chunk(0.05));
+        assert!(vad.is_end_of_utterance());
+        // The speech didn't drag the floor up
+        assert!(vad.energy_snapshot().noise_floor < 0.06);
+
+        // The room going quiet is picked up at once
+        vad.is_speech(&chunk(0.01));
+        assert!((vad.energy_snapshot().noise_floor - 0.01).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_tuning_for_a_quiet_microphone() {
+        // Speech at 0.015 never passes the built-in 0.02 attack
+        let mut untuned = VAD::new();
+        for _ in 0..10 {
+            assert!(!untuned.is_speech(&chunk(0.015)));
+        }
+
+        let tuning = VadTuning { noise_floor: 0.003, ratio: 3.0, energy_threshold: 0.008 };
+        let mut vad = VAD::with_tuning(VadConfig::default(), tuning);
+        // Digital silence doesn't pull the floor below the measured room
+        vad.observe_background(&chunk(0.0));
+        let snapshot = vad.energy_snapshot();
+        assert_eq!(snapshot.noise_floor, 0.003);
+        assert!((snapshot.attack - 0.009).abs() < 1e-6);
+        assert!((snapshot.release - 0.0054).abs() < 1e-6);
+
+        for _ in 0..3 {
+            vad.is_speech(&chunk(0.015));
+        }
+        assert!(vad.energy_snapshot().in_speech);
+    }
+
+    #[test]
+    fn test_hysteresis_keeps_soft_syllables() {
+        let mut vad = VAD::new();
+        for _ in 0..60 {
+            vad.observe_background(&chunk(0.02));
+        }
+        // Loud onset, then a trailing-off word below attack but above release
+        for _ in 0..3 {
+            vad.is_speech(&chunk(0.2));
+        }
+        for _ in 0..8 {
+            assert!(vad.is_speech(&chunk(0.045)));
+        }
+        assert!(!vad.is_end_of_utterance());
+
+        // The same level can't start speech on its own
+        vad.reset();
+        for _ in 0..8 {
+            assert!(!vad.is_speech(&chunk(0.045)));
+        }
+    }
+
+    #[test]
+    fn test_short_noise_and_no_speech() {
+        let mut vad = VAD::with_config(VadConfig { no_speech_timeout_ms: 2000, ..VadConfig::default() });
+        // A door knock shorter than min_speech_ms
+        vad.is_speech(&chunk(0.4));
+        vad.is_speech(&chunk(0.4));
+        for _ in 0..19 {
+            assert!(!vad.is_speech(&chunk(0.0)));
+            assert!(!vad.is_end_of_utterance());
+        }
+        vad.is_speech(&chunk(0.0));
+        assert!(vad.is_end_of_utterance());
+
+        vad.reset();
+        assert!(!vad.is_end_of_utterance());
+    }
+
 }
@@ -1,4 +1,6 @@
-use std::collections::VecDeque;
+use crate::audio_frontend::{envelope_len, spectral_frame_len, AudioFrontend, FeatureFrame, SpectralFrame};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 #[cfg(feature = "timemachine")]
 use ort::{Session, Value};
@@ -12,11 +14,15 @@ pub enum DetectionStrategy {
     Mfcc,
     /// ONNX model-based detection (best accuracy, requires model)
     Onnx,
+    /// DTW match against a template trained from the user's own voice
+    Template,
 }
 
 /// Configuration for wake word detection
 #[derive(Debug, Clone)]
 pub struct WakeWordConfig {
+    /// Phrase to listen for; the energy pattern is derived from its spelling
+    pub phrase: String,
     /// Detection strategy
     pub strategy: DetectionStrategy,
     /// Detection threshold (0.0 to 1.0)
@@ -32,26 +38,241 @@ pub struct WakeWordConfig {
 impl Default for WakeWordConfig {
     fn default() -> Self {
         Self {
+            phrase: DEFAULT_PHRASE.to_string(),
             strategy: DetectionStrategy::Mfcc,
             threshold: 0.65,
             min_duration_ms: 400,
             cooldown_ms: 1000,
-            sample_rate: 16000,
+            sample_rate: crate::audio::SAMPLE_RATE,
+        }
+    }
+}
+
+pub const DEFAULT_PHRASE: &str = "Hey EVA";
+
+/// Pause between words, in 10 ms envelope frames
+const WORD_GAP: [f32; 3] = [0.2, 0.15, 0.1];
+
+/// Vowel, counting 'y' as one except at the start of a word
+fn is_vowel(c: char, index: usize) -> bool {
+    "aeiouáàâãéêíóôõúü".contains(c) || (c == 'y' && index > 0)
+}
+
+/// Relative loudness of a vowel nucleus (open vowels are loudest)
+fn vowel_energy(c: char) -> f32 {
+    match c {
+        'a' | 'á' | 'à' | 'â' | 'ã' => 0.9,
+        'o' | 'ó' | 'ô' | 'õ' => 0.85,
+        'e' | 'é' | 'ê' => 0.8,
+        _ => 0.7,
+    }
+}
+
+/// Relative loudness of a consonant: aspirates < plosives < fricatives < sonorants
+fn consonant_energy(c: char) -> f32 {
+    match c {
+        'h' => 0.2,
+        'p' | 't' | 'k' | 'b' | 'd' | 'g' | 'c' | 'q' => 0.3,
+        'f' | 's' | 'v' | 'z' | 'x' | 'j' | 'ç' => 0.4,
+        'm' | 'n' | 'l' | 'r' | 'w' | 'y' => 0.5,
+        _ => 0.35,
+    }
+}
+
+fn phrase_words(phrase: &str) -> Vec<Vec<char>> {
+    phrase
+        .split_whitespace()
+        .map(|w| w.to_lowercase().chars().filter(|c| c.is_alphabetic()).collect::<Vec<_>>())
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/// Syllables (vowel groups) in a phrase: "Hey EVA" = 3, "Ok Computer" = 4
+pub fn syllable_count(phrase: &str) -> usize {
+    phrase_words(phrase)
+        .iter()
+        .map(|word| {
+            (0..word.len())
+                .filter(|&i| is_vowel(word[i], i) && (i == 0 || !is_vowel(word[i - 1], i - 1)))
+                .count()
+                .max(1)
+        })
+        .sum()
+}
+
+/// Energy envelope template for a phrase, one value per 10 ms frame
+///
+/// Consonants are a single low frame, each vowel group a rising-sustained
+/// nucleus, and words are separated by a short pause. Normalized to 0..=1.
+pub fn phrase_energy_pattern(phrase: &str) -> Vec<f32> {
+    let mut pattern = Vec::new();
+    for word in phrase_words(phrase) {
+        if !pattern.is_empty() {
+            pattern.extend(WORD_GAP);
+        }
+        for (i, &c) in word.iter().enumerate() {
+            if !is_vowel(c, i) {
+                pattern.push(consonant_energy(c));
+            } else if i > 0 && is_vowel(word[i - 1], i - 1) {
+                // Diphthong: glide out of the previous vowel
+                pattern.extend([vowel_energy(c) * 0.9, vowel_energy(c) * 0.8]);
+            } else {
+                let peak = vowel_energy(c);
+                pattern.extend([peak * 0.7, peak, peak, peak * 0.85]);
+            }
+        }
+    }
+
+    let max = pattern.iter().cloned().fold(0.0f32, f32::max);
+    if max > 0.0 {
+        for e in &mut pattern {
+            *e /= max;
+        }
+    }
+    pattern
+}
+
+/// Reference features of the wake phrase spoken by the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WakeWordTemplate {
+    pub phrase: String,
+    /// Normalized spectral frames of the most typical training utterance
+    frames: Vec<Vec<f32>>,
+    /// Mean DTW distance between the training utterances
+    spread: f32,
+}
+
+impl WakeWordTemplate {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// 0.0..=1.0, 1.0 for an exact match; about 0.6 at the training spread
+    fn score(&self, distance: f32) -> f32 {
+        (-distance / (2.0 * self.spread)).exp()
+    }
+}
+
+/// Length-normalized dynamic time warping distance
+fn dtw_distance(a: &[Vec<f32>], b: &[Vec<f32>]) -> f32 {
+    if a.is_empty() || b.is_empty() {
+        return f32::INFINITY;
+    }
+
+    let (n, m) = (a.len(), b.len());
+    let mut cost = vec![f32::INFINITY; (n + 1) * (m + 1)];
+    cost[0] = 0.0;
+    for i in 1..=n {
+        for j in 1..=m {
+            let d = a[i - 1].iter().zip(&b[j - 1]).map(|(x, y)| (x - y).powi(2)).sum::<f32>().sqrt();
+            let best = cost[(i - 1) * (m + 1) + j].min(cost[i * (m + 1) + j - 1]).min(cost[(i - 1) * (m + 1) + j - 1]);
+            cost[i * (m + 1) + j] = d + best;
+        }
+    }
+    cost[n * (m + 1) + m] / (n + m) as f32
+}
+
+/// Features of the last two seconds heard, which the strategies score
+struct FeatureWindow {
+    /// Longest stretch kept, in samples
+    capacity: usize,
+    envelope_len: usize,
+    frame_len: usize,
+    /// Stream positions of the first sample kept and one past the last
+    start: u64,
+    end: u64,
+    envelope: Vec<f32>,
+    /// Spectral frames on every phase, oldest first
+    spectra: Vec<SpectralFrame>,
+    /// The audio itself, kept only for the ONNX model
+    raw: Vec<f32>,
+}
+
+impl FeatureWindow {
+    fn new(sample_rate: u32, capacity: usize) -> Self {
+        Self {
+            capacity,
+            envelope_len: envelope_len(sample_rate),
+            frame_len: spectral_frame_len(sample_rate),
+            start: 0,
+            end: 0,
+            envelope: Vec::new(),
+            spectra: Vec::new(),
+            raw: Vec::new(),
+        }
+    }
+
+    /// Audio covered, in samples
+    fn samples(&self) -> usize {
+        (self.end - self.start) as usize
+    }
+
+    /// Add a chunk's features and drop what fell out of the window
+    fn push(&mut self, frame: &FeatureFrame, keep_raw: bool) {
+        // A chunk that doesn't follow the last one starts over
+        if self.samples() == 0 || frame.position != self.end {
+            self.clear();
+            self.start = frame.position;
+        }
+        self.end = frame.position + frame.samples.len() as u64;
+        self.start = self.start.max(self.end.saturating_sub(self.capacity as u64));
+
+        self.envelope.extend_from_slice(&frame.envelope);
+        keep_last(&mut self.envelope, self.capacity / self.envelope_len);
+        self.spectra.extend(frame.spectra.iter().cloned());
+        let start = self.start;
+        let expired = self.spectra.partition_point(|f| f.start < start);
+        self.spectra.drain(..expired);
+        if keep_raw {
+            self.raw.extend_from_slice(frame.samples);
+            let samples = self.samples();
+            keep_last(&mut self.raw, samples);
         }
     }
+
+    /// The spectral frames of the window analyzed on its own: a hop apart
+    /// from its first sample, each ending before its last
+    fn frames(&self) -> Vec<Vec<f32>> {
+        let hop = (self.frame_len / 2) as u64;
+        self.spectra
+            .iter()
+            .filter(|f| (f.start - self.start).is_multiple_of(hop) && f.start + (self.frame_len as u64) < self.end)
+            .map(|f| f.coefficients.clone())
+            .collect()
+    }
+
+    fn clear(&mut self) {
+        self.start = 0;
+        self.end = 0;
+        self.envelope.clear();
+        self.spectra.clear();
+        self.raw.clear();
+    }
 }
 
-/// Wake word detector for "Hey EVA"
+fn keep_last<T>(items: &mut Vec<T>, len: usize) {
+    let excess = items.len().saturating_sub(len);
+    items.drain(..excess);
+}
+
+/// Wake word detector for a configurable phrase ("Hey EVA" by default)
 ///
 /// Supports multiple detection strategies with configurable sensitivity.
+/// The listener feeds it the `FeatureFrame`s it shares with VAD
+/// (`detect_frame`); `detect` analyzes raw samples itself.
 pub struct WakeWordDetector {
     config: WakeWordConfig,
-    /// Audio buffer for analysis
-    buffer: VecDeque<f32>,
+    /// What the strategies score
+    window: FeatureWindow,
+    /// Analysis for `detect`, created on first use
+    frontend: Option<AudioFrontend>,
     /// Energy pattern for correlation-based detection
     energy_pattern: Vec<f32>,
-    /// MFCC feature buffer
-    mfcc_buffer: VecDeque<Vec<f32>>,
     /// Last detection timestamp
     last_detection_ms: u64,
     /// Running timestamp
@@ -61,6 +282,8 @@ pub struct WakeWordDetector {
     onnx_session: Option<Session>,
     /// Detection count (for anti-spam)
     detection_count: u32,
+    /// Trained from the user's voice (`train_from_samples`)
+    template: Option<WakeWordTemplate>,
 }
 
 impl WakeWordDetector {
@@ -71,31 +294,90 @@ impl WakeWordDetector {
 
     /// Create a wake word detector with custom configuration
     pub fn with_config(config: WakeWordConfig) -> Self {
-        // Energy pattern for "Hey EVA" (normalized phoneme energies)
-        let energy_pattern = vec![
-            // "Hey" - rising energy, aspirated H, diphthong EI
-            0.2, 0.4, 0.6, 0.8, 0.9, 0.85, 0.7, 0.5,
-            // Brief pause
-            0.2, 0.15, 0.1,
-            // "E" - vowel, steady energy
-            0.3, 0.5, 0.7, 0.8, 0.75, 0.6,
-            // "VA" - fricative V, open A
-            0.4, 0.6, 0.8, 0.9, 0.85, 0.7, 0.5, 0.3,
-        ];
-
-        let buffer_size = (config.sample_rate as usize * config.min_duration_ms as usize) / 1000;
+        let energy_pattern = phrase_energy_pattern(&config.phrase);
+        let window = FeatureWindow::new(config.sample_rate, config.sample_rate as usize * 2);
 
         Self {
             config,
-            buffer: VecDeque::with_capacity(buffer_size),
+            window,
+            frontend: None,
             energy_pattern,
-            mfcc_buffer: VecDeque::with_capacity(50), // ~500ms of MFCC frames
             last_detection_ms: 0,
             current_ms: 0,
             #[cfg(feature = "timemachine")]
             onnx_session: None,
             detection_count: 0,
+            template: None,
+        }
+    }
+
+    /// Listen for another phrase; a template trained for the old one is dropped
+    pub fn set_phrase(&mut self, phrase: &str) {
+        if phrase == self.config.phrase {
+            return;
+        }
+        self.config.phrase = phrase.to_string();
+        self.energy_pattern = phrase_energy_pattern(phrase);
+        if self.template.take().is_some() {
+            self.config.strategy = DetectionStrategy::Mfcc;
+        }
+    }
+
+    /// Current wake phrase
+    pub fn phrase(&self) -> &str {
+        &self.config.phrase
+    }
+
+    /// Build a reference template from 3 to 5 recordings of the phrase and
+    /// switch to template matching
+    ///
+    /// The recording closest to all others (by DTW) becomes the template;
+    /// how far apart the recordings are sets the distance tolerance.
+    pub fn train_from_samples(&mut self, utterances: &[Vec<f32>]) -> Result<(), String> {
+        if !(3..=5).contains(&utterances.len()) {
+            return Err(format!("Say the wake phrase 3 to 5 times (got {})", utterances.len()));
+        }
+        let features: Vec<Vec<Vec<f32>>> = utterances.iter().map(|u| Self::template_features(u, self.config.sample_rate)).collect();
+        if let Some(i) = features.iter().position(|f| f.len() < 3) {
+            return Err(format!("Recording {} is too short or silent", i + 1));
+        }
+
+        let n = features.len();
+        let mut totals = vec![0.0f32; n];
+        let mut pairs = Vec::new();
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let d = dtw_distance(&features[i], &features[j]);
+                totals[i] += d;
+                totals[j] += d;
+                pairs.push(d);
+            }
+        }
+        let medoid = (0..n).min_by(|&a, &b| totals[a].total_cmp(&totals[b])).unwrap_or(0);
+        let spread = (pairs.iter().sum::<f32>() / pairs.len() as f32).max(0.05);
+
+        self.template = Some(WakeWordTemplate {
+            phrase: self.config.phrase.clone(),
+            frames: features[medoid].clone(),
+            spread,
+        });
+        self.config.strategy = DetectionStrategy::Template;
+        Ok(())
+    }
+
+    /// The trained template, to save with `WakeWordTemplate::save`
+    pub fn template(&self) -> Option<&WakeWordTemplate> {
+        self.template.as_ref()
+    }
+
+    /// Use a saved template; ignored when it was trained for another phrase
+    pub fn set_template(&mut self, template: WakeWordTemplate) -> bool {
+        if template.phrase != self.config.phrase {
+            return false;
         }
+        self.template = Some(template);
+        self.config.strategy = DetectionStrategy::Template;
+        true
     }
 
     /// Load ONNX model for ML-based detection
@@ -109,10 +391,22 @@ impl WakeWordDetector {
 
     /// Detect wake word in audio samples
     ///
-    /// Returns true if "Hey EVA" is detected
+    /// Returns true if the wake phrase is detected
     pub fn detect(&mut self, samples: &[f32]) -> bool {
+        let mut frontend = self.frontend.take().unwrap_or_else(|| AudioFrontend::new(self.config.sample_rate));
+        // Frames start over with the first audio kept after a detection
+        if self.window.samples() == 0 {
+            frontend.reset();
+        }
+        let detected = self.detect_frame(&frontend.analyze(samples));
+        self.frontend = Some(frontend);
+        detected
+    }
+
+    /// Same, from features the frontend already computed
+    pub fn detect_frame(&mut self, frame: &FeatureFrame) -> bool {
         // Update timestamp
-        let samples_ms = (samples.len() as u64 * 1000) / self.config.sample_rate as u64;
+        let samples_ms = (frame.samples.len() as u64 * 1000) / self.config.sample_rate as u64;
         self.current_ms += samples_ms;
 
         // Check cooldown
@@ -120,55 +414,66 @@ impl WakeWordDetector {
             return false;
         }
 
-        // Add samples to buffer
-        for &sample in samples {
-            self.buffer.push_back(sample);
-        }
-
-        // Limit buffer size
-        let max_buffer = self.config.sample_rate as usize * 2; // 2 seconds max
-        while self.buffer.len() > max_buffer {
-            self.buffer.pop_front();
-        }
+        self.window.push(frame, self.config.strategy == DetectionStrategy::Onnx);
 
         // Check minimum duration
         let min_samples = (self.config.sample_rate * self.config.min_duration_ms / 1000) as usize;
-        if self.buffer.len() < min_samples {
+        if self.window.samples() < min_samples {
             return false;
         }
 
-        // Detect based on strategy
-        let detected = match self.config.strategy {
-            DetectionStrategy::Energy => self.detect_energy(),
-            DetectionStrategy::Mfcc => self.detect_mfcc(),
-            DetectionStrategy::Onnx => self.detect_onnx(),
-        };
-
-        if detected {
+        if self.score(&self.window) > self.config.threshold {
             self.last_detection_ms = self.current_ms;
             self.detection_count += 1;
-            self.buffer.clear();
+            self.window.clear();
             true
         } else {
             false
         }
     }
 
-    /// Energy-based detection using cross-correlation
-    fn detect_energy(&mut self) -> bool {
-        if self.buffer.len() < self.energy_pattern.len() * 2 {
-            return false;
+    /// How much the window sounds like the phrase under the current
+    /// strategy, 0.0..=1.0; `detect` fires above the threshold
+    fn score(&self, window: &FeatureWindow) -> f32 {
+        match self.config.strategy {
+            DetectionStrategy::Energy => self.energy_score(window),
+            DetectionStrategy::Mfcc => self.mfcc_score(window),
+            DetectionStrategy::Onnx => self.onnx_score(window),
+            DetectionStrategy::Template => self.template_score(window),
         }
+    }
 
-        // Compute energy envelope
-        let frame_size = self.config.sample_rate as usize / 100; // 10ms frames
-        let mut energy_envelope = Vec::new();
+    /// Best score `detect` would have reached while hearing `recording`
+    /// chunk by chunk, for calibration
+    ///
+    /// Cooldown and earlier detections are ignored, so every recording is
+    /// scored the same way.
+    pub fn peak_score(&self, recording: &[f32]) -> f32 {
+        let mut frontend = AudioFrontend::new(self.config.sample_rate);
+        let mut window = FeatureWindow::new(self.config.sample_rate, self.config.sample_rate as usize * 2);
+        let min_samples = (self.config.sample_rate * self.config.min_duration_ms / 1000) as usize;
+        recording
+            .chunks(crate::audio::CHUNK_SIZE)
+            .map(|chunk| {
+                window.push(&frontend.analyze(chunk), self.config.strategy == DetectionStrategy::Onnx);
+                if window.samples() >= min_samples { self.score(&window) } else { 0.0 }
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// Threshold currently applied to scores (1.0 - sensitivity)
+    pub fn threshold(&self) -> f32 {
+        self.config.threshold
+    }
 
-        for chunk in self.buffer.make_contiguous().chunks(frame_size) {
-            let energy: f32 = chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32;
-            energy_envelope.push(energy.sqrt());
+    /// Energy-based detection using cross-correlation
+    fn energy_score(&self, window: &FeatureWindow) -> f32 {
+        if window.samples() < self.energy_pattern.len() * 2 {
+            return 0.0;
         }
 
+        let mut energy_envelope = window.envelope.clone();
+
         // Normalize envelope
         let max_energy = energy_envelope.iter().cloned().fold(0.0f32, f32::max);
         if max_energy > 0.01 {
@@ -176,29 +481,26 @@ impl WakeWordDetector {
                 *e /= max_energy;
             }
         } else {
-            return false; // Too quiet
+            return 0.0; // Too quiet
         }
 
         // Cross-correlate with pattern
-        let correlation = self.cross_correlate(&energy_envelope, &self.energy_pattern);
-
-        correlation > self.config.threshold
+        self.cross_correlate(&energy_envelope, &self.energy_pattern)
     }
 
     /// MFCC-based detection
-    fn detect_mfcc(&self) -> bool {
-        if self.buffer.len() < self.config.sample_rate as usize / 2 {
-            return false;
+    fn mfcc_score(&self, window: &FeatureWindow) -> f32 {
+        if window.samples() < self.config.sample_rate as usize / 2 {
+            return 0.0;
         }
 
-        // Compute MFCC features
-        let mfcc = self.compute_mfcc();
+        let mfcc = &window.frames();
 
-        // Check for characteristic "Hey EVA" pattern in MFCCs
-        // Look for: rising energy -> brief dip -> sustained energy
+        // Check for the phrase's word rhythm in MFCCs
+        // Look for: rising energy -> brief dip -> rising again, once per word
 
         if mfcc.len() < 3 {
-            return false;
+            return 0.0;
         }
 
         // Simplified MFCC pattern matching
@@ -209,48 +511,53 @@ impl WakeWordDetector {
         // Check energy progression (first MFCC coefficient is energy)
         let energies: Vec<f32> = mfcc.iter().map(|m| m.get(0).copied().unwrap_or(0.0)).collect();
 
-        // Look for the "Hey EVA" pattern:
+        // Expected pattern, e.g. "Hey EVA":
         // 1. Initial rise (Hey)
         // 2. Brief dip (pause)
         // 3. Rise again (EVA)
+        // Rises share 0.6 of the score and pauses 0.2 (single words: 0.8 for the rise)
+        let words = phrase_words(&self.config.phrase).len().max(1);
+        let (rise_weight, dip_weight) = if words > 1 {
+            (0.6 / words as f32, 0.2 / (words - 1) as f32)
+        } else {
+            (0.8, 0.0)
+        };
 
-        let mut found_hey = false;
-        let mut found_pause = false;
-        let mut found_eva = false;
-
+        let mut rises = 0;
+        let mut awaiting_dip = false;
         for i in 1..energies.len() {
-            if !found_hey && energies[i] > energies[i - 1] * 1.2 {
-                found_hey = true;
-                score += 0.3;
-            } else if found_hey && !found_pause && energies[i] < energies[i - 1] * 0.7 {
-                found_pause = true;
-                score += 0.2;
-            } else if found_pause && !found_eva && energies[i] > energies[i - 1] * 1.2 {
-                found_eva = true;
-                score += 0.3;
+            if rises == words {
+                break;
+            }
+            if awaiting_dip {
+                if energies[i] < energies[i - 1] * 0.7 {
+                    awaiting_dip = false;
+                    score += dip_weight;
+                }
+            } else if energies[i] > energies[i - 1] * 1.2 {
+                rises += 1;
+                score += rise_weight;
+                awaiting_dip = rises < words;
             }
         }
 
         // Check spectral consistency (higher MFCCs should be relatively stable)
-        let spectral_variance = self.compute_spectral_variance(&mfcc);
+        let spectral_variance = self.compute_spectral_variance(mfcc);
         if spectral_variance < 0.5 {
             score += 0.2;
         }
 
-        score > self.config.threshold
+        score
     }
 
     /// ONNX model-based detection
-    fn detect_onnx(&self) -> bool {
+    fn onnx_score(&self, window: &FeatureWindow) -> f32 {
         #[cfg(feature = "timemachine")]
         {
             if let Some(ref session) = self.onnx_session {
-                // Prepare input tensor
-                let samples: Vec<f32> = self.buffer.iter().cloned().collect();
-
                 // Resample to model input size (typically 16000 * 1.5 = 24000 samples)
                 let model_input_size = 24000;
-                let resampled = self.resample(&samples, model_input_size);
+                let resampled = self.resample(&window.raw, model_input_size);
 
                 // Create tensor
                 if let Ok(tensor) = Value::from_array((vec![1, model_input_size], resampled)) {
@@ -258,7 +565,7 @@ impl WakeWordDetector {
                         if let Some(output) = outputs.get(0) {
                             // Extract probability
                             // Placeholder: in production, properly extract from output tensor
-                            return true; // Model detected wake word
+                            return 1.0; // Model detected wake word
                         }
                     }
                 }
@@ -266,54 +573,59 @@ impl WakeWordDetector {
         }
 
         // Fallback to MFCC if ONNX not available
-        self.detect_mfcc()
+        self.mfcc_score(window)
     }
 
-    /// Compute simplified MFCC features
-    fn compute_mfcc(&self) -> Vec<Vec<f32>> {
-        let frame_size = 512;
-        let hop_size = 256;
-        let num_mfcc = 13;
-
-        let samples: Vec<f32> = self.buffer.iter().cloned().collect();
-        let mut mfcc_frames = Vec::new();
+    /// Template match against the end of the window
+    fn template_score(&self, window: &FeatureWindow) -> f32 {
+        let Some(ref template) = self.template else {
+            return self.mfcc_score(window);
+        };
 
-        for start in (0..samples.len().saturating_sub(frame_size)).step_by(hop_size) {
-            let frame = &samples[start..start + frame_size];
+        let mut features = Self::template_frames(&window.frames());
+        if features.len() < template.frames.len() / 2 {
+            return 0.0;
+        }
+        // Earlier speech in the window is not part of the phrase
+        let window = template.frames.len() * 3 / 2;
+        if features.len() > window {
+            features.drain(..features.len() - window);
+        }
 
-            // Apply Hamming window
-            let windowed: Vec<f32> = frame
-                .iter()
-                .enumerate()
-                .map(|(i, &s)| {
-                    let window = 0.54 - 0.46 * (2.0 * std::f32::consts::PI * i as f32 / frame_size as f32).cos();
-                    s * window
-                })
-                .collect();
+        template.score(dtw_distance(&template.frames, &features))
+    }
 
-            // Compute energy in frequency bands (simplified mel filterbank)
-            let mut mfcc = vec![0.0f32; num_mfcc];
-
-            // Energy (zeroth coefficient)
-            mfcc[0] = windowed.iter().map(|s| s * s).sum::<f32>().sqrt();
-
-            // Simplified spectral features (using time-domain approximations)
-            for (i, coef) in mfcc.iter_mut().enumerate().skip(1) {
-                let freq = (i as f32 / num_mfcc as f32) * 0.5;
-                let phase = 2.0 * std::f32::consts::PI * freq;
-                *coef = windowed
-                    .iter()
-                    .enumerate()
-                    .map(|(j, &s)| s * (phase * j as f32).cos())
-                    .sum::<f32>()
-                    .abs()
-                    / frame_size as f32;
-            }
+    /// Loudness-independent frames for template matching: silence trimmed
+    /// from both ends, energy dropped, spectral shape scaled to unit length
+    fn template_features(samples: &[f32], sample_rate: u32) -> Vec<Vec<f32>> {
+        Self::template_frames(&Self::mfcc_of(samples, sample_rate))
+    }
 
-            mfcc_frames.push(mfcc);
+    /// Same, from spectral frames already computed
+    fn template_frames(frames: &[Vec<f32>]) -> Vec<Vec<f32>> {
+        let max_energy = frames.iter().map(|f| f[0]).fold(0.0f32, f32::max);
+        if max_energy < 1e-3 {
+            return Vec::new();
         }
 
-        mfcc_frames
+        let loud = |f: &Vec<f32>| f[0] >= max_energy * 0.1;
+        let (Some(first), Some(last)) = (frames.iter().position(loud), frames.iter().rposition(loud)) else {
+            return Vec::new();
+        };
+        frames[first..=last]
+            .iter()
+            .map(|f| {
+                let norm = f[1..].iter().map(|c| c * c).sum::<f32>().sqrt().max(1e-6);
+                f[1..].iter().map(|c| c / norm).collect()
+            })
+            .collect()
+    }
+
+    /// Spectral frames of a whole recording (see `audio_frontend`)
+    fn mfcc_of(samples: &[f32], sample_rate: u32) -> Vec<Vec<f32>> {
+        let mut window = FeatureWindow::new(sample_rate, samples.len());
+        window.push(&AudioFrontend::new(sample_rate).analyze(samples), false);
+        window.frames()
     }
 
     /// Compute spectral variance across MFCC frames
@@ -406,8 +718,10 @@ impl WakeWordDetector {
 
     /// Reset detector state
     pub fn reset(&mut self) {
-        self.buffer.clear();
-        self.mfcc_buffer.clear();
+        self.window.clear();
+        if let Some(frontend) = &mut self.frontend {
+            frontend.reset();
+        }
         self.detection_count = 0;
     }
 }
@@ -459,10 +773,11 @@ mod tests {
         let mut detector = WakeWordDetector::new();
 
         detector.detect(&vec![0.5; 8000]);
-        assert!(!detector.buffer.is_empty());
+        assert!(detector.window.samples() > 0);
 
         detector.reset();
-        assert!(detector.buffer.is_empty());
+        assert_eq!(detector.window.samples(), 0);
+        assert!(detector.window.spectra.is_empty());
         assert_eq!(detector.detection_count(), 0);
     }
 
@@ -479,7 +794,8 @@ mod tests {
 
     #[test]
     fn test_mfcc_computation() {
-        let mut detector = WakeWordDetector::new();
+        let mut detector = WakeWordDetector::with_config(WakeWordConfig { cooldown_ms: 0, ..WakeWordConfig::default() });
+        detector.set_sensitivity(0.0);
 
         // Add some audio
         let audio: Vec<f32> = (0..8000)
@@ -487,11 +803,12 @@ mod tests {
             .collect();
 
         for chunk in audio.chunks(1600) {
-            detector.buffer.extend(chunk.iter());
+            detector.detect(chunk);
         }
 
-        let mfcc = detector.compute_mfcc();
+        let mfcc = &detector.window.frames();
         assert!(!mfcc.is_empty());
+        assert_eq!(mfcc, &WakeWordDetector::mfcc_of(&audio, 16000));
         assert_eq!(mfcc[0].len(), 13); // 13 MFCC coefficients
     }
 
@@ -534,8 +851,11 @@ mod tests {
         let large_audio: Vec<f32> = vec![0.1; 48000]; // 3 seconds
         detector.detect(&large_audio);
 
-        // Buffer should be limited to max_buffer size
-        assert!(detector.buffer.len() <= 32000);
+        // The window should be limited to two seconds
+        assert!(detector.window.samples() <= 32000);
+        assert!(detector.window.envelope.len() <= 200);
+        assert!(detector.window.frames().len() <= 124);
+        assert!(detector.window.spectra.iter().all(|f| f.start >= detector.window.start));
     }
 
     #[test]
@@ -603,6 +923,7 @@ mod tests {
     #[test]
     fn test_custom_config() {
         let config = WakeWordConfig {
+            phrase: "Ok Computer".to_string(),
             strategy: DetectionStrategy::Energy,
             threshold: 0.8,
             min_duration_ms: 500,
@@ -616,6 +937,8 @@ mod tests {
         assert_eq!(detector.config.min_duration_ms, 500);
         assert_eq!(detector.config.cooldown_ms, 2000);
         assert_eq!(detector.config.sample_rate, 48000);
+        assert_eq!(detector.phrase(), "Ok Computer");
+        assert_eq!(detector.energy_pattern, phrase_energy_pattern("Ok Computer"));
     }
 
     #[test]
@@ -639,4 +962,168 @@ mod tests {
         // Should not detect anything with insufficient duration
         assert!(!result);
     }
+
+    #[test]
+    fn test_phrase_pattern_follows_syllables() {
+        assert_eq!(syllable_count("Hey EVA"), 3);
+        assert_eq!(syllable_count("Ok Computer"), 4);
+        assert_eq!(syllable_count("Olá, Eva!"), 4);
+
+        let hey_eva = phrase_energy_pattern("Hey EVA");
+        let ok_computer = phrase_energy_pattern("Ok Computer");
+        assert_ne!(hey_eva, ok_computer);
+        assert!(ok_computer.len() > hey_eva.len());
+        assert!(hey_eva.iter().all(|&e| (0.0..=1.0).contains(&e)));
+        assert_eq!(hey_eva.iter().cloned().fold(0.0f32, f32::max), 1.0);
+        // The pause between words dips below both neighbours
+        // "hey" takes 7 frames: h, e nucleus, y glide
+        assert!(hey_eva[7..10].iter().all(|&e| e < hey_eva[6].min(hey_eva[11])));
+    }
+
+    #[test]
+    fn test_set_phrase_drops_template() {
+        let mut detector = WakeWordDetector::new();
+        let takes: Vec<Vec<f32>> = (0..3).map(|i| utterance(&[0.05, 0.2, 0.12], 1.0 + i as f32 * 0.05)).collect();
+        detector.train_from_samples(&takes).unwrap();
+        assert_eq!(detector.config.strategy, DetectionStrategy::Template);
+
+        detector.set_phrase("Ok Computer");
+        assert!(detector.template().is_none());
+        assert_eq!(detector.config.strategy, DetectionStrategy::Mfcc);
+        assert!(!detector.set_template(WakeWordTemplate { phrase: "Hey EVA".to_string(), frames: vec![], spread: 1.0 }));
+    }
+
+    /// Syllable-like tone bursts at the given frequencies (cycles/sample),
+    /// `stretch` slows the whole utterance down
+    fn utterance(tones: &[f32], stretch: f32) -> Vec<f32> {
+        let mut out = vec![0.0; 1600];
+        for &freq in tones {
+            let len = (3200.0 * stretch) as usize;
+            out.extend((0..len).map(|i| {
+                let envelope = (i.min(len - i) as f32 / 400.0).min(1.0);
+                0.5 * envelope * (2.0 * std::f32::consts::PI * freq * i as f32).sin()
+            }));
+            out.extend(vec![0.0; 800]);
+        }
+        out.extend(vec![0.0; 1600]);
+        out
+    }
+
+    #[test]
+    fn test_trained_template_detects_user_phrase() {
+        let phrase = [0.05, 0.2, 0.12, 0.3];
+        let mut detector = WakeWordDetector::with_config(WakeWordConfig {
+            phrase: "Ok Computer".to_string(),
+            cooldown_ms: 0,
+            ..WakeWordConfig::default()
+        });
+        detector.set_sensitivity(0.6);
+
+        assert!(detector.train_from_samples(&[utterance(&phrase, 1.0)]).is_err());
+        assert!(detector.train_from_samples(&[vec![0.0; 16000], vec![0.0; 16000], vec![0.0; 16000]]).is_err());
+        let takes: Vec<Vec<f32>> = [0.95, 1.0, 1.1].iter().map(|&k| utterance(&phrase, k)).collect();
+        detector.train_from_samples(&takes).unwrap();
+
+        // Said a bit faster and quieter than in training
+        let quieter: Vec<f32> = utterance(&phrase, 0.9).iter().map(|s| s * 0.6).collect();
+        assert!(detector.detect(&quieter));
+
+        // Other words: different syllables
+        detector.reset();
+        assert!(!detector.detect(&utterance(&[0.3, 0.08, 0.25, 0.15], 1.0)));
+        assert!(!detector.detect(&vec![0.0; 16000]));
+
+        // The template survives a save/load round trip
+        let path = std::env::temp_dir().join(format!("eva_test_wake_{}.json", std::process::id()));
+        detector.template().unwrap().save(&path).unwrap();
+        let mut fresh = WakeWordDetector::with_config(WakeWordConfig { phrase: "Ok Computer".to_string(), ..WakeWordConfig::default() });
+        fresh.set_sensitivity(0.6);
+        assert!(fresh.set_template(WakeWordTemplate::load(&path).unwrap()));
+        assert!(fresh.detect(&utterance(&phrase, 1.05)));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// The phrase, other words, the phrase again slower and quieter and a
+    /// long run of other speech, over deterministic room noise
+    fn scene(phrase: &[f32]) -> Vec<f32> {
+        let quiet = |len: usize| vec![0.0; len];
+        let audio = [
+            quiet(8000),
+            utterance(phrase, 1.0),
+            quiet(16000),
+            utterance(&[0.3, 0.08, 0.25], 1.0),
+            quiet(16000),
+            utterance(phrase, 1.1).iter().map(|s| s * 0.6).collect(),
+            quiet(16000),
+            utterance(&[0.07, 0.18, 0.33, 0.11, 0.22, 0.09], 0.8),
+            quiet(8000),
+        ]
+        .concat();
+        let mut seed = 0x2545_f491u32;
+        audio
+            .iter()
+            .map(|s| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                s + ((seed >> 8) as f32 / (1u32 << 24) as f32 - 0.5) * 0.004
+            })
+            .collect()
+    }
+
+    /// Chunks where `detect` fired on `scene`, recorded before VAD and
+    /// the detector shared one frontend
+    #[test]
+    fn test_detection_unchanged_on_recorded_scene() {
+        let phrase = [0.05, 0.2, 0.12];
+        let audio = scene(&phrase);
+        let recorded: [(DetectionStrategy, f32, &[usize]); 10] = [
+            (DetectionStrategy::Energy, 0.2, &[12, 25, 45, 65, 79]),
+            (DetectionStrategy::Energy, 0.5, &[12, 25, 45, 65, 78]),
+            (DetectionStrategy::Mfcc, 0.2, &[25, 47, 67]),
+            (DetectionStrategy::Mfcc, 0.35, &[13, 27, 47, 66]),
+            (DetectionStrategy::Mfcc, 0.7, &[13, 27, 45, 65, 79]),
+            (DetectionStrategy::Mfcc, 0.9, &[13, 27, 41, 55, 69]),
+            (DetectionStrategy::Template, 0.35, &[]),
+            (DetectionStrategy::Template, 0.5, &[]),
+            (DetectionStrategy::Template, 0.7, &[52]),
+            (DetectionStrategy::Template, 0.9, &[12, 45, 59, 72]),
+        ];
+        let takes: Vec<Vec<f32>> = [0.95, 1.0, 1.1].iter().map(|&k| utterance(&phrase, k)).collect();
+        for (strategy, sensitivity, expected) in recorded {
+            let mut detector = WakeWordDetector::new();
+            detector.train_from_samples(&takes).unwrap();
+            detector.set_strategy(strategy);
+            detector.set_sensitivity(sensitivity);
+            let fired: Vec<usize> = audio
+                .chunks(crate::audio::CHUNK_SIZE)
+                .enumerate()
+                .filter(|(_, chunk)| detector.detect(chunk))
+                .map(|(i, _)| i)
+                .collect();
+            assert_eq!(fired, expected, "{:?} at sensitivity {}", strategy, sensitivity);
+        }
+    }
+
+    #[test]
+    fn test_peak_score_agrees_with_detect() {
+        let phrase = [0.05, 0.2, 0.12];
+        let mut detector = WakeWordDetector::with_config(WakeWordConfig { cooldown_ms: 0, ..WakeWordConfig::default() });
+        detector.set_sensitivity(0.6);
+        let takes: Vec<Vec<f32>> = [0.95, 1.0, 1.1].iter().map(|&k| utterance(&phrase, k)).collect();
+        detector.train_from_samples(&takes).unwrap();
+
+        let take = utterance(&phrase, 1.05);
+        let other = utterance(&[0.3, 0.08, 0.25], 1.0);
+        let (take_score, other_score) = (detector.peak_score(&take), detector.peak_score(&other));
+        assert!(take_score > detector.threshold() && other_score < detector.threshold(), "{} {}", take_score, other_score);
+        assert_eq!(detector.peak_score(&[0.0; 16000]), 0.0);
+        assert_eq!(detector.peak_score(&[]), 0.0);
+
+        // Scoring doesn't touch the live window or counters
+        assert_eq!(detector.window.samples(), 0);
+        let detected = take.chunks(crate::audio::CHUNK_SIZE).any(|chunk| detector.detect(chunk));
+        assert!(detected);
+        detector.reset();
+        assert!(!other.chunks(crate::audio::CHUNK_SIZE).any(|chunk| detector.detect(chunk)));
+    }
+
 }
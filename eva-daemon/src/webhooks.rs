@@ -0,0 +1,789 @@
+//! Outgoing event webhooks
+//!
+//! Pushes selected EVA events (timer fired, command executed, NPU recovered,
+//! daily digest ready) to user-configured HTTP(S) endpoints. Configuration
+//! lives in `webhooks.toml` in the data directory; signing secrets are looked
+//! up by name in `secrets.toml` so the webhook file can be shared without
+//! leaking them.
+//!
+//! Each endpoint has its own bounded queue and delivery task, so a slow or
+//! failing endpoint never holds up the others. Each retries with
+//! exponential backoff and trips a circuit breaker after repeated failures.
+
+use crate::tls::TlsManager;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use url::Url;
+
+/// Maximum queued events per endpoint before new ones are dropped
+const QUEUE_CAPACITY: usize = 64;
+
+/// Consecutive failed deliveries before an endpoint's circuit opens
+const BREAKER_THRESHOLD: u32 = 5;
+
+/// How long an open circuit stays open before a trial delivery
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(300);
+
+/// Per-request network timeout
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Paths on EVA's own control API that must never be webhook targets
+const SELF_TRIGGER_PATHS: &[&str] = &["/say", "/command", "/speak"];
+
+/// Events that can be pushed to webhooks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    TimerFired,
+    CommandExecuted,
+    NpuRecovered,
+    DailyDigestReady,
+}
+
+impl WebhookEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventKind::TimerFired => "timer_fired",
+            WebhookEventKind::CommandExecuted => "command_executed",
+            WebhookEventKind::NpuRecovered => "npu_recovered",
+            WebhookEventKind::DailyDigestReady => "daily_digest_ready",
+        }
+    }
+}
+
+/// Where an event originated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventOrigin {
+    /// Produced by EVA itself (voice turn, timer, driver, ...)
+    Local,
+    /// Produced while handling a request that came in through a webhook
+    /// callback; never forwarded, to avoid feedback loops
+    Webhook,
+}
+
+/// An event to deliver
+#[derive(Debug, Clone)]
+pub struct WebhookEvent {
+    pub kind: WebhookEventKind,
+    pub data: Value,
+    pub timestamp: chrono::DateTime<chrono::Local>,
+    pub origin: EventOrigin,
+}
+
+impl WebhookEvent {
+    pub fn new(kind: WebhookEventKind, data: Value) -> Self {
+        Self { kind, data, timestamp: chrono::Local::now(), origin: EventOrigin::Local }
+    }
+
+    /// Mark the event as caused by a webhook callback
+    pub fn from_webhook(mut self) -> Self {
+        self.origin = EventOrigin::Webhook;
+        self
+    }
+
+    /// `command_executed` for a command EVA ran
+    pub fn command_executed(intent: &crate::command_parser::CommandIntent, result: &Result<String, String>) -> Self {
+        let data = match result {
+            Ok(output) => json!({ "command": crate::command_history::describe_intent(intent), "ok": true, "output": output }),
+            Err(error) => json!({ "command": crate::command_history::describe_intent(intent), "ok": false, "error": error }),
+        };
+        Self::new(WebhookEventKind::CommandExecuted, data)
+    }
+
+    /// Default JSON payload
+    pub fn to_json(&self) -> Value {
+        json!({
+            "event": self.kind.as_str(),
+            "timestamp": self.timestamp.to_rfc3339(),
+            "data": self.data,
+        })
+    }
+}
+
+/// Retry policy for one endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    #[serde(default = "default_attempts")]
+    pub attempts: u32,
+    #[serde(default = "default_backoff_ms")]
+    pub backoff_ms: u64,
+}
+
+fn default_attempts() -> u32 {
+    3
+}
+
+fn default_backoff_ms() -> u64 {
+    500
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { attempts: default_attempts(), backoff_ms: default_backoff_ms() }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the given retry (1-based), doubling each time
+    pub fn backoff(&self, retry: u32) -> Duration {
+        Duration::from_millis(self.backoff_ms.saturating_mul(1u64 << retry.saturating_sub(1).min(10)))
+    }
+}
+
+/// One configured endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub name: String,
+    pub url: String,
+    /// Events to push; empty means all
+    #[serde(default)]
+    pub events: Vec<WebhookEventKind>,
+    /// Name of the signing secret in secrets.toml
+    #[serde(default)]
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// Payload template; `{{event}}`, `{{timestamp}}`, `{{data}}` and
+    /// `{{data.<field>}}` are substituted. Defaults to the JSON event.
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+impl WebhookEndpoint {
+    /// Check whether the endpoint wants this event
+    pub fn accepts(&self, kind: WebhookEventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+
+    /// Render the request body for an event
+    pub fn render(&self, event: &WebhookEvent) -> String {
+        match &self.template {
+            Some(template) => render_template(template, event),
+            None => event.to_json().to_string(),
+        }
+    }
+}
+
+/// Contents of webhooks.toml
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebhooksConfig {
+    #[serde(default, rename = "webhook")]
+    pub webhooks: Vec<WebhookEndpoint>,
+}
+
+impl WebhooksConfig {
+    /// Load webhooks.toml (empty if missing)
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = crate::paths::data_file("webhooks.toml")?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parse and validate a webhooks file
+    pub fn parse(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let config: Self = toml::from_str(content)?;
+        for endpoint in &config.webhooks {
+            check_url(&endpoint.url)
+                .map_err(|e| format!("webhook '{}': {}", endpoint.name, e))?;
+        }
+        Ok(config)
+    }
+}
+
+/// Signing secrets from secrets.toml (`[webhooks]` table)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WebhookSecrets {
+    #[serde(default)]
+    webhooks: HashMap<String, String>,
+}
+
+impl WebhookSecrets {
+    /// Load secrets.toml (empty if missing)
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = crate::paths::data_file("secrets.toml")?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.webhooks.get(name).map(|s| s.as_str())
+    }
+}
+
+
+/// Validate a webhook URL, rejecting EVA's own control endpoints
+pub fn check_url(raw: &str) -> Result<Url, String> {
+    let url = Url::parse(raw).map_err(|e| format!("invalid URL: {}", e))?;
+
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(format!("unsupported scheme '{}'", url.scheme()));
+    }
+
+    let loopback = match url.host() {
+        Some(url::Host::Domain(d)) => d.eq_ignore_ascii_case("localhost"),
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback() || ip.is_unspecified(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback() || ip.is_unspecified(),
+        None => return Err("missing host".to_string()),
+    };
+
+    if loopback && SELF_TRIGGER_PATHS.iter().any(|p| url.path().starts_with(p)) {
+        return Err(format!("'{}' points back at EVA and would create a feedback loop", raw));
+    }
+
+    Ok(url)
+}
+
+/// Substitute template placeholders
+pub fn render_template(template: &str, event: &WebhookEvent) -> String {
+    let mut out = template
+        .replace("{{event}}", event.kind.as_str())
+        .replace("{{timestamp}}", &event.timestamp.to_rfc3339())
+        .replace("{{data}}", &event.data.to_string());
+
+    if let Some(fields) = event.data.as_object() {
+        for (key, value) in fields {
+            let text = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            out = out.replace(&format!("{{{{data.{}}}}}", key), &text);
+        }
+    }
+
+    out
+}
+
+/// HMAC-SHA256 signature of a payload, hex encoded
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Delivery statistics for one endpoint
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EndpointStats {
+    pub delivered: u64,
+    pub failed: u64,
+    pub dropped: u64,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    #[serde(skip)]
+    pub circuit_open_until: Option<Instant>,
+}
+
+impl EndpointStats {
+    /// Whether the circuit breaker is currently open
+    pub fn circuit_open(&self) -> bool {
+        self.circuit_open_until.map(|t| Instant::now() < t).unwrap_or(false)
+    }
+
+    fn record_success(&mut self) {
+        self.delivered += 1;
+        self.consecutive_failures = 0;
+        self.circuit_open_until = None;
+    }
+
+    fn record_failure(&mut self, error: String) {
+        self.failed += 1;
+        self.consecutive_failures += 1;
+        self.last_error = Some(error);
+        if self.consecutive_failures >= BREAKER_THRESHOLD {
+            self.circuit_open_until = Some(Instant::now() + BREAKER_COOLDOWN);
+        }
+    }
+}
+
+type SharedStats = Arc<Mutex<HashMap<String, EndpointStats>>>;
+
+/// One endpoint's queue, drained by its own delivery task
+struct EndpointQueue {
+    endpoint: WebhookEndpoint,
+    tx: mpsc::Sender<WebhookEvent>,
+}
+
+/// Background webhook dispatcher
+pub struct WebhookDispatcher {
+    queues: Vec<EndpointQueue>,
+    stats: SharedStats,
+}
+
+impl WebhookDispatcher {
+    /// Start one delivery task per endpoint (must be called inside a tokio
+    /// runtime)
+    pub fn start(config: WebhooksConfig, secrets: WebhookSecrets) -> Self {
+        let stats: SharedStats = Arc::new(Mutex::new(
+            config.webhooks.iter().map(|w| (w.name.clone(), EndpointStats::default())).collect(),
+        ));
+        let tls = if config.webhooks.is_empty() {
+            None
+        } else {
+            match TlsManager::new() {
+                Ok(t) => Some(Arc::new(t)),
+                Err(e) => {
+                    eprintln!("[Webhooks] TLS unavailable, https endpoints disabled: {}", e);
+                    None
+                }
+            }
+        };
+
+        let queues = config
+            .webhooks
+            .into_iter()
+            .map(|endpoint| {
+                let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+                let secret = endpoint.secret.as_deref().and_then(|name| secrets.get(name)).map(str::to_string);
+                tokio::spawn(delivery_loop(endpoint.clone(), secret, tls.clone(), rx, stats.clone()));
+                EndpointQueue { endpoint, tx }
+            })
+            .collect();
+
+        Self { queues, stats }
+    }
+
+    /// Queue an event for every endpoint that wants it; never blocks, and
+    /// drops it for an endpoint whose queue is full
+    pub fn emit(&self, event: WebhookEvent) {
+        if event.origin == EventOrigin::Webhook {
+            return;
+        }
+
+        for queue in self.queues.iter().filter(|q| q.endpoint.accepts(event.kind)) {
+            if queue.tx.try_send(event.clone()).is_err() {
+                if let Ok(mut stats) = self.stats.lock() {
+                    stats.entry(queue.endpoint.name.clone()).or_default().dropped += 1;
+                }
+            }
+        }
+    }
+
+    /// Number of configured endpoints
+    pub fn endpoint_count(&self) -> usize {
+        self.queues.len()
+    }
+
+    /// Snapshot of per-endpoint stats, in config order
+    pub fn stats(&self) -> Vec<(String, EndpointStats)> {
+        let stats = match self.stats.lock() {
+            Ok(s) => s,
+            Err(_) => return Vec::new(),
+        };
+        self.queues
+            .iter()
+            .filter_map(|q| stats.get(&q.endpoint.name).map(|s| (q.endpoint.name.clone(), s.clone())))
+            .collect()
+    }
+
+    /// Stats as JSON for the control API
+    pub fn stats_json(&self) -> Value {
+        let entries: Vec<Value> = self
+            .stats()
+            .into_iter()
+            .map(|(name, s)| {
+                json!({
+                    "name": name,
+                    "delivered": s.delivered,
+                    "failed": s.failed,
+                    "dropped": s.dropped,
+                    "circuit_open": s.circuit_open(),
+                    "last_error": s.last_error,
+                })
+            })
+            .collect();
+        json!({ "webhooks": entries })
+    }
+}
+
+/// Deliver one endpoint's events in order
+async fn delivery_loop(
+    endpoint: WebhookEndpoint,
+    secret: Option<String>,
+    tls: Option<Arc<TlsManager>>,
+    mut rx: mpsc::Receiver<WebhookEvent>,
+    stats: SharedStats,
+) {
+    while let Some(event) = rx.recv().await {
+        let open = stats
+            .lock()
+            .ok()
+            .and_then(|s| s.get(&endpoint.name).map(|e| e.circuit_open()))
+            .unwrap_or(false);
+        if open {
+            if let Ok(mut s) = stats.lock() {
+                s.entry(endpoint.name.clone()).or_default().dropped += 1;
+            }
+            continue;
+        }
+
+        let body = endpoint.render(&event);
+        let signature = secret.as_deref().map(|secret| sign(secret, &body));
+
+        let mut result = Err(String::new());
+        for attempt in 0..endpoint.retry.attempts.max(1) {
+            if attempt > 0 {
+                tokio::time::sleep(endpoint.retry.backoff(attempt)).await;
+            }
+            result = post(tls.as_deref(), &endpoint.url, &body, event.kind, signature.as_deref()).await;
+            if result.is_ok() {
+                break;
+            }
+        }
+
+        if let Ok(mut s) = stats.lock() {
+            let entry = s.entry(endpoint.name.clone()).or_default();
+            match result {
+                Ok(()) => entry.record_success(),
+                Err(e) => {
+                    eprintln!("[Webhooks] {} delivery failed: {}", endpoint.name, e);
+                    entry.record_failure(e);
+                }
+            }
+        }
+    }
+}
+
+/// Watches the NPU driver's state for `npu_recovered`: working again
+/// after it was seen dead
+#[derive(Debug, Default)]
+pub struct NpuWatch {
+    dead: bool,
+}
+
+impl NpuWatch {
+    /// The driver's latest state (`READY`, `DEAD`, ...), `None` while it
+    /// isn't running
+    pub fn observe(&mut self, state: Option<&str>) -> Option<WebhookEvent> {
+        match state {
+            Some("DEAD") => {
+                self.dead = true;
+                None
+            }
+            Some(state @ ("READY" | "BUSY")) if self.dead => {
+                self.dead = false;
+                Some(WebhookEvent::new(WebhookEventKind::NpuRecovered, json!({ "state": state })))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What happened during one local day, for `daily_digest_ready` once the
+/// day is over
+#[derive(Debug)]
+pub struct DailyDigest {
+    day: chrono::NaiveDate,
+    turns: usize,
+    commands: usize,
+    timers_fired: usize,
+}
+
+impl DailyDigest {
+    /// Start counting `day`; `turns` and `commands` are the running totals
+    pub fn new(day: chrono::NaiveDate, turns: usize, commands: usize) -> Self {
+        Self { day, turns, commands, timers_fired: 0 }
+    }
+
+    pub fn timer_fired(&mut self) {
+        self.timers_fired += 1;
+    }
+
+    /// The digest of the day that ended, once `today` is another day;
+    /// counting starts over from the totals given
+    pub fn roll(&mut self, today: chrono::NaiveDate, turns: usize, commands: usize) -> Option<WebhookEvent> {
+        if today == self.day {
+            return None;
+        }
+        let event = WebhookEvent::new(
+            WebhookEventKind::DailyDigestReady,
+            json!({
+                "date": self.day.format("%Y-%m-%d").to_string(),
+                "turns": turns.saturating_sub(self.turns),
+                "commands": commands.saturating_sub(self.commands),
+                "timers_fired": self.timers_fired,
+            }),
+        );
+        *self = Self::new(today, turns, commands);
+        Some(event)
+    }
+}
+
+/// POST a payload and require a 2xx status
+async fn post(
+    tls: Option<&TlsManager>,
+    raw_url: &str,
+    body: &str,
+    kind: WebhookEventKind,
+    signature: Option<&str>,
+) -> Result<(), String> {
+    let url = check_url(raw_url)?;
+    let host = url.host_str().ok_or("missing host")?.to_string();
+    let port = url.port_or_known_default().ok_or("missing port")?;
+    let path = match url.query() {
+        Some(q) => format!("{}?{}", url.path(), q),
+        None => url.path().to_string(),
+    };
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: eva-daemon\r\nContent-Type: application/json\r\nContent-Length: {}\r\nX-Eva-Event: {}\r\n",
+        path, host, body.len(), kind.as_str()
+    );
+    if let Some(sig) = signature {
+        request.push_str(&format!("X-Eva-Signature: sha256={}\r\n", sig));
+    }
+    request.push_str("Connection: close\r\n\r\n");
+    request.push_str(body);
+
+    let exchange = async {
+        if url.scheme() == "https" {
+            let tls = tls.ok_or("TLS unavailable")?;
+            let stream = tls.connect(&host, port).await.map_err(|e| e.to_string())?;
+            send_request(stream, &request).await
+        } else {
+            let stream = TcpStream::connect((host.as_str(), port)).await.map_err(|e| e.to_string())?;
+            send_request(stream, &request).await
+        }
+    };
+
+    let status = tokio::time::timeout(REQUEST_TIMEOUT, exchange)
+        .await
+        .map_err(|_| "timeout".to_string())??;
+
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(format!("HTTP {}", status))
+    }
+}
+
+async fn send_request<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S, request: &str) -> Result<u16, String> {
+    stream.write_all(request.as_bytes()).await.map_err(|e| e.to_string())?;
+    stream.flush().await.map_err(|e| e.to_string())?;
+
+    let mut head = [0u8; 64];
+    let n = stream.read(&mut head).await.map_err(|e| e.to_string())?;
+    parse_status(&head[..n]).ok_or_else(|| "malformed HTTP response".to_string())
+}
+
+/// Parse the status code from an HTTP status line
+fn parse_status(head: &[u8]) -> Option<u16> {
+    let line = std::str::from_utf8(head).ok()?.lines().next()?;
+    let mut parts = line.split_whitespace();
+    if !parts.next()?.starts_with("HTTP/") {
+        return None;
+    }
+    parts.next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+[[webhook]]
+name = "home"
+url = "https://example.com/hooks/eva"
+events = ["timer_fired", "npu_recovered"]
+secret = "home"
+template = '{"text": "{{event}}: {{data.label}}"}'
+
+[[webhook]]
+name = "all"
+url = "http://192.168.1.10:8080/eva"
+retry = { attempts = 5, backoff_ms = 100 }
+"#;
+
+    #[test]
+    fn test_parse_config() {
+        let config = WebhooksConfig::parse(SAMPLE).unwrap();
+        assert_eq!(config.webhooks.len(), 2);
+
+        let home = &config.webhooks[0];
+        assert!(home.accepts(WebhookEventKind::TimerFired));
+        assert!(!home.accepts(WebhookEventKind::CommandExecuted));
+        assert_eq!(home.retry.attempts, 3);
+
+        let all = &config.webhooks[1];
+        assert!(all.accepts(WebhookEventKind::DailyDigestReady));
+        assert_eq!(all.retry.attempts, 5);
+        assert_eq!(all.retry.backoff(1), Duration::from_millis(100));
+        assert_eq!(all.retry.backoff(3), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn test_render_template() {
+        let config = WebhooksConfig::parse(SAMPLE).unwrap();
+        let event = WebhookEvent::new(WebhookEventKind::TimerFired, json!({"label": "tea"}));
+        assert_eq!(config.webhooks[0].render(&event), r#"{"text": "timer_fired: tea"}"#);
+
+        let body: Value = serde_json::from_str(&config.webhooks[1].render(&event)).unwrap();
+        assert_eq!(body["event"], "timer_fired");
+        assert_eq!(body["data"]["label"], "tea");
+    }
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_loopback_protection() {
+        assert!(check_url("http://localhost:7700/say").is_err());
+        assert!(check_url("http://127.0.0.1:7700/say?text=hi").is_err());
+        assert!(check_url("http://[::1]/command").is_err());
+        assert!(check_url("http://localhost:9000/notify").is_ok());
+        assert!(check_url("https://example.com/say").is_ok());
+        assert!(check_url("ftp://example.com/").is_err());
+        assert!(WebhooksConfig::parse("[[webhook]]\nname = \"x\"\nurl = \"http://localhost/say\"\n").is_err());
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let mut stats = EndpointStats::default();
+        for _ in 0..BREAKER_THRESHOLD - 1 {
+            stats.record_failure("HTTP 500".to_string());
+        }
+        assert!(!stats.circuit_open());
+
+        stats.record_failure("HTTP 500".to_string());
+        assert!(stats.circuit_open());
+        assert_eq!(stats.last_error.as_deref(), Some("HTTP 500"));
+
+        stats.record_success();
+        assert!(!stats.circuit_open());
+        assert_eq!(stats.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(parse_status(b"HTTP/1.1 204 No Content\r\n"), Some(204));
+        assert_eq!(parse_status(b"garbage"), None);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_origin_not_forwarded() {
+        let dispatcher = WebhookDispatcher::start(WebhooksConfig::default(), WebhookSecrets::default());
+        let event = WebhookEvent::new(WebhookEventKind::CommandExecuted, json!({})).from_webhook();
+        dispatcher.emit(event);
+        assert_eq!(dispatcher.endpoint_count(), 0);
+        assert_eq!(dispatcher.stats_json()["webhooks"].as_array().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_npu_recovery_needs_a_dead_state_first() {
+        let mut watch = NpuWatch::default();
+        assert!(watch.observe(Some("READY")).is_none());
+        assert!(watch.observe(Some("DEAD")).is_none());
+        assert!(watch.observe(Some("BOOTING")).is_none());
+        let event = watch.observe(Some("READY")).unwrap();
+        assert_eq!(event.kind, WebhookEventKind::NpuRecovered);
+        assert!(watch.observe(Some("READY")).is_none());
+    }
+
+    #[test]
+    fn test_daily_digest_counts_the_day() {
+        let monday = chrono::NaiveDate::from_ymd_opt(2026, 3, 9).unwrap();
+        let mut digest = DailyDigest::new(monday, 10, 4);
+        digest.timer_fired();
+        assert!(digest.roll(monday, 15, 6).is_none());
+
+        let event = digest.roll(monday.succ_opt().unwrap(), 18, 7).unwrap();
+        assert_eq!(event.kind, WebhookEventKind::DailyDigestReady);
+        assert_eq!(event.data, json!({ "date": "2026-03-09", "turns": 8, "commands": 3, "timers_fired": 1 }));
+        assert!(digest.roll(monday.succ_opt().unwrap(), 20, 7).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_slow_endpoint_does_not_hold_up_others() {
+        // Accepts the connection and never answers
+        let stalled = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stalled_port = stalled.local_addr().unwrap().port();
+        let hold = tokio::spawn(async move {
+            let (sock, _) = stalled.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            drop(sock);
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let _ = sock.read(&mut buf).await.unwrap();
+            sock.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").await.unwrap();
+        });
+
+        let toml = format!(
+            "[[webhook]]\nname = \"slow\"\nurl = \"http://127.0.0.1:{}/hook\"\n\n[[webhook]]\nname = \"fast\"\nurl = \"http://127.0.0.1:{}/hook\"\n",
+            stalled_port, port
+        );
+        let dispatcher = WebhookDispatcher::start(WebhooksConfig::parse(&toml).unwrap(), WebhookSecrets::default());
+        dispatcher.emit(WebhookEvent::new(WebhookEventKind::TimerFired, json!({ "label": "tea" })));
+
+        tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+        for _ in 0..50 {
+            if dispatcher.stats()[1].1.delivered == 1 {
+                assert_eq!(dispatcher.stats()[0].1.delivered, 0);
+                hold.abort();
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("fast endpoint waited for the slow one: {:?}", dispatcher.stats());
+    }
+
+    #[tokio::test]
+    async fn test_delivery_to_local_server() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let n = sock.read(&mut buf).await.unwrap();
+            sock.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+
+        let toml = format!(
+            "[[webhook]]\nname = \"local\"\nurl = \"http://127.0.0.1:{}/hook\"\nsecret = \"k\"\n",
+            port
+        );
+        let config = WebhooksConfig::parse(&toml).unwrap();
+        let secrets: WebhookSecrets = toml::from_str("[webhooks]\nk = \"s3cret\"\n").unwrap();
+        let dispatcher = WebhookDispatcher::start(config, secrets);
+        dispatcher.emit(WebhookEvent::new(WebhookEventKind::NpuRecovered, json!({"attempts": 2})));
+
+        let request = tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1"));
+        assert!(request.contains("X-Eva-Event: npu_recovered"));
+        assert!(request.contains("X-Eva-Signature: sha256="));
+
+        for _ in 0..50 {
+            if dispatcher.stats()[0].1.delivered == 1 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("delivery not recorded: {:?}", dispatcher.stats());
+    }
+}
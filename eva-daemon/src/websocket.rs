@@ -1,15 +1,250 @@
-use tokio_tungstenite::{connect_async, tungstenite::Message, WebSocketStream, MaybeTlsStream};
-use tokio::net::TcpStream;
-use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
 use url::Url;
+use std::fmt::Display;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Future returned by `Transport` and `Connector` methods
+pub type WsFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, Box<dyn std::error::Error>>> + 'a>>;
+
+/// Message channel a client talks over; implemented by `WebSocketClient`
+/// and by scripted connections in tests
+pub trait Transport {
+    fn send_text<'a>(&'a mut self, text: &'a str) -> WsFuture<'a, ()>;
+    /// `Ok(None)` once the connection is closed
+    fn receive(&mut self) -> WsFuture<'_, Option<Message>>;
+    fn ping(&mut self) -> WsFuture<'_, ()>;
+    fn close(self: Box<Self>) -> WsFuture<'static, ()>;
+    /// Messages waiting to be written
+    fn queue_depth(&self) -> usize {
+        0
+    }
+}
+
+/// Opens a `Transport` to a URL
+pub trait Connector {
+    fn connect<'a>(&'a self, url: &'a str) -> WsFuture<'a, Box<dyn Transport>>;
+}
+
+/// Real WebSocket connections
+pub struct WsConnector;
+
+impl Connector for WsConnector {
+    fn connect<'a>(&'a self, url: &'a str) -> WsFuture<'a, Box<dyn Transport>> {
+        Box::pin(async move { Ok(Box::new(WebSocketClient::connect(url).await?) as Box<dyn Transport>) })
+    }
+}
+
+/// Messages that can wait to be written before senders have to wait too
+pub const SEND_QUEUE_CAPACITY: usize = 32;
+
+/// When an idle connection is pinged, and how long it has to answer
+#[derive(Debug, Clone, Copy)]
+pub struct Keepalive {
+    /// Time without writing anything before a ping goes out
+    pub interval: Duration,
+    /// Time a ping (or a single write) may go unanswered before the
+    /// connection is given up as dead
+    pub timeout: Duration,
+}
+
+impl Default for Keepalive {
+    fn default() -> Self {
+        Self { interval: Duration::from_secs(15), timeout: Duration::from_secs(10) }
+    }
+}
+
+/// Health of a connection, shared by its writer and reader tasks
+#[derive(Default)]
+struct Link {
+    /// Messages received so far; any of them shows the peer is alive
+    heard: AtomicU64,
+    /// Why the connection died, once it has
+    dead: watch::Sender<Option<String>>,
+}
+
+impl Link {
+    fn heard(&self) -> u64 {
+        self.heard.load(Ordering::Relaxed)
+    }
+
+    /// Mark the connection dead; the first reason sticks
+    fn kill(&self, reason: String) {
+        self.dead.send_if_modified(|dead| {
+            if dead.is_some() {
+                return false;
+            }
+            println!("❌ WS {}", reason);
+            *dead = Some(reason);
+            true
+        });
+    }
+
+    fn dead(&self) -> Option<String> {
+        self.dead.borrow().clone()
+    }
+}
+
+/// Outbound messages, written in order by a dedicated task
+///
+/// Sending only enqueues, so a burst of large audio payloads never
+/// interleaves writes on the socket; once `capacity` messages are waiting,
+/// `send` waits for room instead. The same task pings the peer after
+/// `Keepalive::interval` of idle and marks the link dead when nothing is
+/// heard back in time. A failed write also kills the link, and every later
+/// send reports why.
+struct SendQueue {
+    sender: mpsc::Sender<Message>,
+    link: Arc<Link>,
+    writer: JoinHandle<()>,
+}
+
+impl SendQueue {
+    fn spawn<S>(sink: S, link: Arc<Link>, keepalive: Keepalive, capacity: usize) -> Self
+    where
+        S: Sink<Message> + Unpin + Send + 'static,
+        S::Error: Display,
+    {
+        let (sender, queue) = mpsc::channel(capacity);
+        let writer = tokio::spawn(write_loop(sink, queue, Arc::clone(&link), keepalive));
+        Self { sender, link, writer }
+    }
+
+    /// Enqueue `message`, waiting while the queue is full
+    async fn send(&self, message: Message) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(reason) = self.link.dead() {
+            return Err(reason.into());
+        }
+        self.sender
+            .send(message)
+            .await
+            .map_err(|_| self.link.dead().unwrap_or_else(|| "WebSocket closed".to_string()).into())
+    }
+
+    fn depth(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+
+    /// Write what is still queued, then close the sink
+    async fn close(self) -> Result<(), Box<dyn std::error::Error>> {
+        drop(self.sender);
+        self.writer.await?;
+        match self.link.dead() {
+            Some(reason) => Err(reason.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+async fn write_loop<S>(mut sink: S, mut queue: mpsc::Receiver<Message>, link: Arc<Link>, keepalive: Keepalive)
+where
+    S: Sink<Message> + Unpin,
+    S::Error: Display,
+{
+    let mut last_write = Instant::now();
+    // When the unanswered ping went out, and how much had been heard then
+    let mut ping: Option<(Instant, u64)> = None;
+
+    loop {
+        if ping.is_some_and(|(_, heard)| link.heard() > heard) {
+            ping = None;
+        }
+        let deadline = match ping {
+            Some((sent, _)) => sent + keepalive.timeout,
+            None => last_write + keepalive.interval,
+        };
+
+        let message = tokio::select! {
+            next = queue.recv() => match next {
+                Some(message) => message,
+                None => break,
+            },
+            _ = tokio::time::sleep_until(deadline) => {
+                if let Some((_, heard)) = ping {
+                    if link.heard() == heard {
+                        link.kill(format!("No answer to ping in {}s", keepalive.timeout.as_secs_f32()));
+                        return;
+                    }
+                    continue;
+                }
+                ping = Some((Instant::now(), link.heard()));
+                Message::Ping(Vec::new())
+            }
+        };
+
+        match tokio::time::timeout(keepalive.timeout, sink.send(message)).await {
+            Ok(Ok(())) => last_write = Instant::now(),
+            Ok(Err(e)) => {
+                link.kill(format!("Send failed: {}", e));
+                return;
+            }
+            Err(_) => {
+                link.kill(format!("Send stalled for {}s", keepalive.timeout.as_secs_f32()));
+                return;
+            }
+        }
+    }
+
+    // Everything queued has been written
+    let _ = sink.close().await;
+}
+
+/// Forward incoming messages to the client, noting that the peer is alive
+async fn read_loop<R, E>(mut source: R, link: Arc<Link>, incoming: mpsc::UnboundedSender<Message>)
+where
+    R: Stream<Item = Result<Message, E>> + Unpin,
+    E: Display,
+{
+    while let Some(next) = source.next().await {
+        match next {
+            Ok(msg) => {
+                link.heard.fetch_add(1, Ordering::Relaxed);
+                if let Message::Pong(_) = msg {
+                    println!("📥 WS Pong");
+                    continue;
+                }
+                if incoming.send(msg).is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                link.kill(format!("WebSocket error: {}", e));
+                return;
+            }
+        }
+    }
+}
+
+/// Stops the reader task with its client
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
 
 pub struct WebSocketClient {
-    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    queue: SendQueue,
+    incoming: mpsc::UnboundedReceiver<Message>,
+    link: Arc<Link>,
+    _reader: AbortOnDrop,
 }
 
 impl WebSocketClient {
     /// Connect to a WebSocket server with automatic TLS support
     pub async fn connect(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::connect_with(url, Keepalive::default()).await
+    }
+
+    /// Connect, pinging the server as `keepalive` says
+    pub async fn connect_with(url: &str, keepalive: Keepalive) -> Result<Self, Box<dyn std::error::Error>> {
         println!("🔗 Conectando ao WebSocket: {}", url);
 
         let url = Url::parse(url)?;
@@ -17,63 +252,197 @@ impl WebSocketClient {
 
         println!("✅ WebSocket conectado! Status: {}", response.status());
 
-        Ok(Self { stream: ws_stream })
+        let (sink, source) = ws_stream.split();
+        let link = Arc::new(Link::default());
+        let (incoming_tx, incoming) = mpsc::unbounded_channel();
+        let reader = tokio::spawn(read_loop(source, Arc::clone(&link), incoming_tx));
+
+        Ok(Self {
+            queue: SendQueue::spawn(sink, Arc::clone(&link), keepalive, SEND_QUEUE_CAPACITY),
+            incoming,
+            link,
+            _reader: AbortOnDrop(reader),
+        })
     }
 
-    /// Send text message
+    /// Queue a text message
     pub async fn send_text(&mut self, text: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.stream.send(Message::Text(text.to_string())).await?;
-        Ok(())
+        self.queue.send(Message::Text(text.to_string())).await
     }
 
-    /// Send binary message (for audio PCM data)
+    /// Queue a binary message (for audio PCM data)
     pub async fn send_binary(&mut self, data: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
-        self.stream.send(Message::Binary(data)).await?;
-        Ok(())
+        self.queue.send(Message::Binary(data)).await
     }
 
     /// Receive next message
     pub async fn receive(&mut self) -> Result<Option<Message>, Box<dyn std::error::Error>> {
-        match self.stream.next().await {
-            Some(Ok(msg)) => {
+        let mut dead = self.link.dead.subscribe();
+        let next = tokio::select! {
+            biased;
+            next = self.incoming.recv() => next,
+            _ = dead.wait_for(Option::is_some) => None,
+        };
+        match next {
+            Some(msg) => {
                 // Log message type for debugging
                 match &msg {
                     Message::Text(t) => println!("📥 WS Text: {} bytes", t.len()),
                     Message::Binary(b) => println!("📥 WS Binary: {} bytes", b.len()),
                     Message::Close(c) => println!("📥 WS Close: {:?}", c),
                     Message::Ping(_) => println!("📥 WS Ping"),
-                    Message::Pong(_) => println!("📥 WS Pong"),
                     _ => println!("📥 WS Other"),
                 }
                 Ok(Some(msg))
             },
-            Some(Err(e)) => {
-                println!("❌ WS Error: {:?}", e);
-                Err(format!("WebSocket error: {}", e).into())
-            },
-            None => {
-                println!("⚠️ WS Stream ended");
-                Ok(None)
+            None => match self.link.dead() {
+                Some(reason) => Err(reason.into()),
+                None => {
+                    println!("⚠️ WS Stream ended");
+                    Ok(None)
+                }
             },
         }
     }
 
-    /// Close the WebSocket connection
-    pub async fn close(mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.stream.close(None).await?;
-        Ok(())
+    /// Close the WebSocket connection once everything queued is written
+    pub async fn close(self) -> Result<(), Box<dyn std::error::Error>> {
+        self.queue.close().await
     }
 
-    /// Send ping to keep connection alive
+    /// Ping now rather than waiting for the connection to go idle
     pub async fn ping(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.stream.send(Message::Ping(vec![])).await?;
-        Ok(())
+        self.queue.send(Message::Ping(vec![])).await
+    }
+
+    /// Messages waiting to be written
+    pub fn queue_depth(&self) -> usize {
+        self.queue.depth()
+    }
+}
+
+impl Transport for WebSocketClient {
+    fn send_text<'a>(&'a mut self, text: &'a str) -> WsFuture<'a, ()> {
+        Box::pin(WebSocketClient::send_text(self, text))
+    }
+
+    fn receive(&mut self) -> WsFuture<'_, Option<Message>> {
+        Box::pin(WebSocketClient::receive(self))
+    }
+
+    fn ping(&mut self) -> WsFuture<'_, ()> {
+        Box::pin(WebSocketClient::ping(self))
+    }
+
+    fn close(self: Box<Self>) -> WsFuture<'static, ()> {
+        Box::pin(WebSocketClient::close(*self))
+    }
+
+    fn queue_depth(&self) -> usize {
+        WebSocketClient::queue_depth(self)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
+    use tokio::sync::Semaphore;
+
+    type Written = Arc<Mutex<Vec<Message>>>;
+
+    /// A sink that records what it is given, one write per permit in `gate`
+    fn mock_sink(gate: Arc<Semaphore>) -> (Pin<Box<dyn Sink<Message, Error = String> + Send>>, Written) {
+        let written = Written::default();
+        let sink = futures_util::sink::unfold(Arc::clone(&written), move |written, msg: Message| {
+            let gate = Arc::clone(&gate);
+            async move {
+                gate.acquire().await.map_err(|e| e.to_string())?.forget();
+                written.lock().unwrap().push(msg);
+                Ok::<_, String>(written)
+            }
+        });
+        (Box::pin(sink), written)
+    }
+
+    fn text(n: u32) -> Message {
+        Message::Text(n.to_string())
+    }
+
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    #[tokio::test]
+    async fn test_send_queue_keeps_order_and_applies_backpressure() {
+        let gate = Arc::new(Semaphore::new(0));
+        let (sink, written) = mock_sink(Arc::clone(&gate));
+        let keepalive = Keepalive { interval: Duration::from_secs(60), timeout: Duration::from_secs(60) };
+        let queue = Arc::new(SendQueue::spawn(sink, Arc::default(), keepalive, 2));
+
+        // The writer holds the first message, two more fill the queue
+        for n in 1..=3 {
+            queue.send(text(n)).await.unwrap();
+            settle().await;
+        }
+        assert_eq!(queue.depth(), 2);
+
+        // A fourth has to wait for room
+        let blocked = tokio::spawn({
+            let queue = Arc::clone(&queue);
+            async move { queue.send(text(4)).await.map_err(|e| e.to_string()) }
+        });
+        settle().await;
+        assert!(!blocked.is_finished());
+
+        gate.add_permits(10);
+        blocked.await.unwrap().unwrap();
+        settle().await;
+        assert_eq!(queue.depth(), 0);
+        assert_eq!(*written.lock().unwrap(), (1..=4).map(text).collect::<Vec<_>>());
+
+        let queue = Arc::try_unwrap(queue).ok().unwrap();
+        queue.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_idle_connection_is_pinged_until_it_stops_answering() {
+        let (sink, written) = mock_sink(Arc::new(Semaphore::new(Semaphore::MAX_PERMITS)));
+        let link = Arc::new(Link::default());
+        let keepalive = Keepalive { interval: Duration::from_millis(50), timeout: Duration::from_millis(100) };
+        let queue = SendQueue::spawn(sink, Arc::clone(&link), keepalive, 4);
+        let pings = || written.lock().unwrap().iter().filter(|m| matches!(m, Message::Ping(_))).count();
+
+        tokio::time::sleep(Duration::from_millis(75)).await;
+        assert_eq!(pings(), 1);
+        assert!(link.dead().is_none());
+
+        // The pong arrives, so the connection is pinged again once idle
+        link.heard.fetch_add(1, Ordering::Relaxed);
+        let start = Instant::now();
+        while link.dead().is_none() && start.elapsed() < Duration::from_secs(2) {
+            settle().await;
+        }
+
+        // ...and that ping is never answered
+        assert!(pings() >= 2);
+        let err = queue.send(text(1)).await.unwrap_err();
+        assert!(err.to_string().contains("ping"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_kills_the_link() {
+        let gate = Arc::new(Semaphore::new(0));
+        let (sink, _) = mock_sink(Arc::clone(&gate));
+        gate.close();
+        let link = Arc::new(Link::default());
+        let queue = SendQueue::spawn(sink, Arc::clone(&link), Keepalive::default(), 4);
+
+        queue.send(text(1)).await.unwrap();
+        settle().await;
+        let err = queue.send(text(2)).await.unwrap_err();
+        assert!(err.to_string().contains("Send failed"), "{}", err);
+    }
 
     #[tokio::test]
     async fn test_websocket_echo() {
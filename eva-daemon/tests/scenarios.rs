@@ -0,0 +1,555 @@
+//! Scenario harness: plays a scripted stretch of EVA usage against the real
+//! components on a virtual clock.
+//!
+//! Scripts live in `tests/scenarios/*.yaml`. Each is a list of `steps`
+//! tagged by `event`: things that happen (an utterance, time passing, a
+//! window gaining focus, a restart, an NPU fault) and `expect` checkpoints
+//! that compare observable outcomes (session turns, stored captures,
+//! counters, notifications). Nothing touches audio devices, the network or
+//! the real clock, so every run is identical.
+//!
+//! Utterances are routed by `eva_daemon::turn`, the same code the main
+//! loop calls. The cloud backend is an in-process stand-in that answers
+//! with the replies queued by `reply` steps, or is unreachable after
+//! `offline`. TimeMachine captures are kept in memory and survive restarts
+//! like the database would; session and timers go through their real files
+//! in a scratch directory.
+
+use chrono::{TimeZone, Utc};
+use eva_daemon::answers::AnswerRouter;
+use eva_daemon::clock::{Clock, VirtualClock};
+use eva_daemon::command_history::CommandHistory;
+use eva_daemon::command_parser::{CommandParser, SessionOperation};
+use eva_daemon::error_speech::ErrorAnnouncer;
+use eva_daemon::errors::EvaError;
+use eva_daemon::guest_mode::{self, GuestCommand, GuestMode};
+use eva_daemon::offline::{self, TurnRoute};
+use eva_daemon::session::{self, ConversationSession, Role, SessionStore};
+use eva_daemon::statistics::{AnswerSource, Statistics};
+use eva_daemon::suggestions::FollowUps;
+use eva_daemon::summary;
+use eva_daemon::timemachine::capture::privacy_match;
+use eva_daemon::timemachine::npu_delegate::{with_cpu_fallback, Placement, NPU_MODEL_BUDGET_BYTES};
+use eva_daemon::timers::TimerManager;
+use eva_daemon::turn::{self, Intercept, Plan, Router};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Size reported for the embedding model (fits the NPU budget)
+const EMBEDDING_MODEL_BYTES: u64 = 90 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Script {
+    name: String,
+    #[serde(default = "default_language")]
+    language: String,
+    /// Leaves guest mode when spoken after "exit guest mode"
+    #[serde(default)]
+    guest_passphrase: Option<String>,
+    steps: Vec<Step>,
+}
+
+fn default_language() -> String {
+    "en-US".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Step {
+    /// The user says something after the wake word
+    Say { text: String },
+    /// Queue the backend's answer to the next turn for the model
+    Reply { text: String },
+    /// Move the virtual clock forward, firing whatever falls due
+    Advance {
+        #[serde(default)]
+        minutes: u64,
+        #[serde(default)]
+        seconds: u64,
+    },
+    Offline,
+    Online,
+    /// A window gains focus
+    Window {
+        app: String,
+        #[serde(default)]
+        title: String,
+    },
+    /// The capture interval elapses with this text on screen
+    Capture { text: String },
+    /// Index pending captures
+    Index,
+    /// The next NPU job runs out of device memory
+    NpuFault,
+    /// Stop and start the daemon
+    Restart,
+    Expect(Expectations),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Expectations {
+    session_turns: Option<usize>,
+    turns: Option<usize>,
+    commands: Option<usize>,
+    active_timers: Option<usize>,
+    captures_stored: Option<usize>,
+    captures_blocked: Option<usize>,
+    indexed_on_npu: Option<usize>,
+    indexed_on_cpu: Option<usize>,
+    /// Turns that reached the backend, whether or not it was online
+    backend_requests: Option<usize>,
+    answered_locally: Option<usize>,
+    answered_from_cache: Option<usize>,
+    /// Each must appear in some notification (TUI line or speech)
+    #[serde(default)]
+    notifications: Vec<String>,
+    /// None of these may appear in any notification
+    #[serde(default)]
+    no_notifications: Vec<String>,
+    /// Sessions saved in the store (the original and its branches)
+    stored_sessions: Option<usize>,
+    /// Each must appear in some session turn
+    #[serde(default)]
+    remembered: Vec<String>,
+    /// None of these may appear in a session turn or in what the backend
+    /// still holds of the conversation
+    #[serde(default)]
+    forgotten: Vec<String>,
+    /// Each must appear in the text of some stored capture
+    #[serde(default)]
+    captured: Vec<String>,
+    /// None of these may appear in a stored capture
+    #[serde(default)]
+    not_captured: Vec<String>,
+    /// Whether a guest session is running
+    guest: Option<bool>,
+}
+
+/// Stand-in for EVA-Mind / Gemini
+struct MockBackend {
+    online: bool,
+    replies: VecDeque<String>,
+    requests: usize,
+    /// The conversation as the server holds it
+    context: Vec<String>,
+}
+
+struct StoredCapture {
+    text: String,
+    indexed: Option<Placement>,
+}
+
+/// NPU whose next job can be made to fail like a real out-of-memory
+#[derive(Default)]
+struct FakeNpu {
+    fault_pending: bool,
+}
+
+impl FakeNpu {
+    fn embed(&mut self, placement: Placement) -> Result<(), Box<dyn std::error::Error>> {
+        if placement == Placement::Npu && std::mem::take(&mut self.fault_pending) {
+            return Err("NPU job failed: OUT_OF_RESOURCES".into());
+        }
+        Ok(())
+    }
+}
+
+/// Everything that lives in the daemon process; rebuilt on restart
+struct Process {
+    parser: CommandParser,
+    answers: AnswerRouter,
+    follow_ups: FollowUps,
+    history: CommandHistory,
+    guest: GuestMode,
+    session: ConversationSession,
+    timers: TimerManager,
+    stats: Statistics,
+    announcer: ErrorAnnouncer,
+}
+
+/// The daemon plus the world around it
+struct World {
+    clock: VirtualClock,
+    dir: PathBuf,
+    language: String,
+    passphrase: Option<String>,
+    process: Process,
+    backend: MockBackend,
+    npu: FakeNpu,
+    window: Option<(String, String)>,
+    captures: Vec<StoredCapture>,
+    blocked: usize,
+    notifications: Vec<String>,
+}
+
+impl World {
+    fn new(script: &Script) -> Self {
+        let slug: String = script.name.chars().map(|c| if c.is_alphanumeric() { c } else { '_' }).collect();
+        let dir = std::env::temp_dir().join(format!("eva_scenario_{}_{}", slug, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // A Monday morning, so "every weekday" rules behave
+        let clock = VirtualClock::new(Utc.with_ymd_and_hms(2026, 1, 5, 8, 0, 0).unwrap());
+        let process = Self::start(&dir, &script.language, script.guest_passphrase.clone());
+        Self {
+            clock,
+            dir,
+            language: script.language.clone(),
+            passphrase: script.guest_passphrase.clone(),
+            process,
+            backend: MockBackend { online: true, replies: VecDeque::new(), requests: 0, context: Vec::new() },
+            npu: FakeNpu::default(),
+            window: None,
+            captures: Vec::new(),
+            blocked: 0,
+            notifications: Vec::new(),
+        }
+    }
+
+    /// Daemon startup: state comes back from disk
+    fn start(dir: &std::path::Path, language: &str, passphrase: Option<String>) -> Process {
+        let session_path = dir.join("session.json");
+        let session = if session_path.exists() {
+            ConversationSession::load_from_file(&session_path).unwrap()
+        } else {
+            ConversationSession::new()
+        };
+        Process {
+            parser: CommandParser::new(),
+            answers: AnswerRouter::new(),
+            follow_ups: FollowUps::new(),
+            history: CommandHistory::new(),
+            guest: GuestMode::new(passphrase),
+            session,
+            timers: TimerManager::load_from(dir.join("timers.json")).unwrap(),
+            stats: Statistics::new(),
+            announcer: ErrorAnnouncer::new(language),
+        }
+    }
+
+    fn portuguese(&self) -> bool {
+        self.language.to_lowercase().starts_with("pt")
+    }
+
+    fn notify(&mut self, text: String) {
+        self.notifications.push(text);
+    }
+
+    /// What `report_error` shows and says
+    fn report(&mut self, error: EvaError) {
+        let announcement = self.process.announcer.announce_at(&error, self.clock.instant());
+        self.notify(format!("⚠️  {}", announcement.detail));
+        if let Some(spoken) = announcement.spoken {
+            self.notify(format!("EVA: {}", spoken));
+        }
+    }
+
+    fn say(&mut self, text: &str) -> Result<(), String> {
+        let pt = self.portuguese();
+        match turn::intercept(&self.process.parser, text) {
+            Some(intercepted) => self.intercepted(intercepted, pt),
+            None => self.turn(text, pt),
+        }
+    }
+
+    /// Requests about EVA herself: they rewrite the session, so they aren't turns of it
+    fn intercepted(&mut self, intercepted: Intercept, pt: bool) -> Result<(), String> {
+        let p = &mut self.process;
+        let reply = match intercepted {
+            Intercept::Guest(command) => {
+                let changed = match command {
+                    GuestCommand::Enter => p.guest.enter(&mut p.session, None).map(|()| guest_mode::entered_reply(pt).to_string()),
+                    GuestCommand::Exit { passphrase } => {
+                        p.guest.exit_with_passphrase(&passphrase, &mut p.session, None).map(|summary| guest_mode::exited_reply(&summary, pt))
+                    }
+                };
+                // The backend session knew the other persona and conversation
+                if changed.is_ok() {
+                    self.backend.context.clear();
+                }
+                changed.map_err(|e| e.to_string())
+            }
+            Intercept::Accessibility(_) => return Err("accessibility mode isn't simulated".to_string()),
+            Intercept::Session(op) => {
+                p.stats.increment_commands();
+                match op {
+                    SessionOperation::ForgetLastExchange => {
+                        let removed = p.session.forget_last_exchange().len();
+                        // The backend session is re-established with what is left
+                        if removed > 0 {
+                            self.backend.context = p.session.turns().iter().map(|t| t.content.clone()).collect();
+                        }
+                        Ok(session::forgotten_reply(removed, pt).to_string())
+                    }
+                    _ if !p.guest.allows_persistence() => Err("Branching is not available in guest mode".to_string()),
+                    _ => {
+                        let store = SessionStore::new(self.dir.join("sessions")).map_err(|e| e.to_string())?;
+                        let branch = store.branch(&p.session).map_err(|e| e.to_string())?;
+                        let parent = std::mem::replace(&mut p.session, branch);
+                        Ok(session::branched_reply(&p.session, parent.session_id(), pt))
+                    }
+                }
+            }
+        };
+        match reply {
+            Ok(reply) => self.notify(format!("EVA: {}", reply)),
+            Err(e) => self.report(EvaError::CommandFailed(e)),
+        }
+        Ok(())
+    }
+
+    fn turn(&mut self, text: &str, pt: bool) -> Result<(), String> {
+        let now = self.clock.now();
+        let at = self.clock.instant();
+        let p = &mut self.process;
+        p.stats.increment_turns();
+        p.session.add_turn(Role::User, text.to_string());
+
+        let plan = Router {
+            parser: &p.parser,
+            answers: &mut p.answers,
+            follow_ups: &mut p.follow_ups,
+            history: &p.history,
+            custom: None,
+            guest: &p.guest,
+            confirming: false,
+        }
+        .plan(text, now, &Utc, at, pt);
+        let mut source = AnswerSource::Local;
+        let from_model = matches!(plan, Plan::Served { source: AnswerSource::Cached, .. } | Plan::Route { route: TurnRoute::Model, .. });
+        let reply = match plan {
+            Plan::Served { reply, source: served } => {
+                source = served;
+                Ok(reply)
+            }
+            Plan::Refused(reason) => Err(EvaError::CommandFailed(reason)),
+            Plan::Route { route: TurnRoute::Timer(op), .. } => {
+                p.stats.increment_commands();
+                p.timers.apply(op, now, &Utc, pt).map_err(EvaError::CommandFailed)
+            }
+            Plan::Route { route: TurnRoute::Remember { key, value }, .. } => {
+                p.session.remember(&key, &value);
+                Ok(summary::remembered_reply(&key, &value, pt))
+            }
+            Plan::Route { route: TurnRoute::Model, .. } if self.backend.online => {
+                self.backend.requests += 1;
+                source = AnswerSource::Remote;
+                let reply = self.backend.replies.pop_front().ok_or("backend has no reply queued")?;
+                self.backend.context.extend([text.to_string(), reply.clone()]);
+                p.answers.remember(text, &reply, at);
+                Ok(reply)
+            }
+            Plan::Route { route: TurnRoute::Model, .. } => {
+                self.backend.requests += 1;
+                self.report(EvaError::ConnectFailed("offline".to_string()));
+                Ok(offline::offline_reply(pt).to_string())
+            }
+            plan => return Err(format!("{:?} isn't simulated", plan)),
+        };
+        let p = &mut self.process;
+        p.stats.record_answer(source);
+        match reply {
+            Ok(reply) => {
+                if from_model {
+                    p.session.add_turn(Role::Assistant, reply.clone());
+                } else {
+                    p.session.add_command_result(reply.clone());
+                }
+                self.notify(format!("EVA: {}", reply));
+            }
+            Err(e) => self.report(e),
+        }
+        Ok(())
+    }
+
+    fn advance(&mut self, by: Duration) {
+        // Step a minute at a time so timers fire in order
+        let mut left = by;
+        while !left.is_zero() {
+            let step = left.min(Duration::from_secs(60));
+            self.clock.advance(step);
+            left -= step;
+            self.tick();
+        }
+    }
+
+    /// What the main loop does every frame
+    fn tick(&mut self) {
+        let now = self.clock.now();
+        let fired = self.process.timers.due(now, &Utc);
+        for timer in &fired {
+            self.notify(format!("⏰ {}", timer.label));
+        }
+        if !fired.is_empty() {
+            self.process.timers.save().unwrap();
+        }
+        self.process.stats.update_timers(self.process.timers.active(now));
+    }
+
+    fn capture(&mut self, text: &str) {
+        let (app, title) = self.window.clone().unwrap_or_default();
+        if privacy_match(&title, &app, &[]).is_some() {
+            self.blocked += 1;
+            return;
+        }
+        self.captures.push(StoredCapture { text: text.to_string(), indexed: None });
+    }
+
+    fn index(&mut self) -> Result<(), String> {
+        let npu = &mut self.npu;
+        for capture in self.captures.iter_mut().filter(|c| c.indexed.is_none()) {
+            let ((), placement) =
+                with_cpu_fallback(EMBEDDING_MODEL_BYTES, NPU_MODEL_BUDGET_BYTES, |placement| npu.embed(placement))
+                    .map_err(|e| e.to_string())?;
+            capture.indexed = Some(placement);
+        }
+        Ok(())
+    }
+
+    fn restart(&mut self) {
+        // A guest's conversation is never stored
+        if self.process.guest.allows_persistence() {
+            self.process.session.save_to_file(self.dir.join("session.json")).unwrap();
+        }
+        self.process.timers.save().unwrap();
+        self.process = Self::start(&self.dir, &self.language, self.passphrase.clone());
+        self.notify("EVA OS Started".to_string());
+        self.tick();
+    }
+
+    fn check(&self, expect: &Expectations) -> Vec<String> {
+        let mut failures = Vec::new();
+        let mut count = |what: &str, expected: Option<usize>, actual: usize| {
+            if let Some(expected) = expected {
+                if expected != actual {
+                    failures.push(format!("{}: expected {}, got {}", what, expected, actual));
+                }
+            }
+        };
+        let p = &self.process;
+        let indexed = |on: Placement| self.captures.iter().filter(|c| c.indexed == Some(on)).count();
+        count("session_turns", expect.session_turns, p.session.turn_count());
+        count("turns", expect.turns, p.stats.turns);
+        count("commands", expect.commands, p.stats.commands_executed);
+        count("active_timers", expect.active_timers, p.timers.len());
+        count("captures_stored", expect.captures_stored, self.captures.len());
+        count("captures_blocked", expect.captures_blocked, self.blocked);
+        count("indexed_on_npu", expect.indexed_on_npu, indexed(Placement::Npu));
+        count("indexed_on_cpu", expect.indexed_on_cpu, indexed(Placement::Cpu));
+        count("backend_requests", expect.backend_requests, self.backend.requests);
+        count("answered_locally", expect.answered_locally, p.stats.answers(AnswerSource::Local) as usize);
+        count("answered_from_cache", expect.answered_from_cache, p.stats.answers(AnswerSource::Cached) as usize);
+        if let Some(expected) = expect.stored_sessions {
+            let stored = SessionStore::new(self.dir.join("sessions")).and_then(|store| store.list()).map_or(0, |ids| ids.len());
+            count("stored_sessions", Some(expected), stored);
+        }
+        if let Some(expected) = expect.guest {
+            if expected != p.guest.is_active() {
+                failures.push(format!("guest: expected {}, got {}", expected, p.guest.is_active()));
+            }
+        }
+
+        let notified = |needle: &String| self.notifications.iter().any(|n| n.contains(needle.as_str()));
+        let captured = |needle: &String| self.captures.iter().any(|c| c.text.contains(needle.as_str()));
+        let in_session = |needle: &String| p.session.turns().iter().any(|t| t.content.contains(needle.as_str()));
+        let in_backend = |needle: &String| self.backend.context.iter().any(|t| t.contains(needle.as_str()));
+        for needle in &expect.notifications {
+            if !notified(needle) {
+                failures.push(format!("no notification contains {:?} (got {:?})", needle, self.notifications));
+            }
+        }
+        for needle in expect.no_notifications.iter().filter(|n| notified(n)) {
+            failures.push(format!("unexpected notification containing {:?}", needle));
+        }
+        for needle in expect.remembered.iter().filter(|n| !in_session(n)) {
+            failures.push(format!("no session turn contains {:?}", needle));
+        }
+        for needle in expect.forgotten.iter().filter(|n| in_session(n) || in_backend(n)) {
+            failures.push(format!("{:?} was not forgotten", needle));
+        }
+        for needle in expect.captured.iter().filter(|n| !captured(n)) {
+            failures.push(format!("no capture contains {:?}", needle));
+        }
+        for needle in expect.not_captured.iter().filter(|n| captured(n)) {
+            failures.push(format!("capture unexpectedly contains {:?}", needle));
+        }
+        failures
+    }
+}
+
+impl Drop for World {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Run a script; the error names the first failing step
+fn run(source: &str) -> Result<(), String> {
+    let script: Script = serde_yaml::from_str(source).map_err(|e| format!("invalid scenario: {}", e))?;
+    let mut world = World::new(&script);
+
+    for (i, step) in script.steps.iter().enumerate() {
+        let at = |e: String| format!("{}: step {} ({:?}): {}", script.name, i + 1, step, e);
+        match step {
+            Step::Say { text } => world.say(text).map_err(at)?,
+            Step::Reply { text } => world.backend.replies.push_back(text.clone()),
+            Step::Advance { minutes, seconds } => world.advance(Duration::from_secs(minutes * 60 + seconds)),
+            Step::Offline => world.backend.online = false,
+            Step::Online => world.backend.online = true,
+            Step::Window { app, title } => world.window = Some((app.clone(), title.clone())),
+            Step::Capture { text } => world.capture(text),
+            Step::Index => world.index().map_err(at)?,
+            Step::NpuFault => world.npu.fault_pending = true,
+            Step::Restart => world.restart(),
+            Step::Expect(expect) => {
+                let failures = world.check(expect);
+                if !failures.is_empty() {
+                    return Err(at(failures.join("; ")));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+macro_rules! scenario {
+    ($name:ident) => {
+        #[test]
+        fn $name() {
+            if let Err(e) = run(include_str!(concat!("scenarios/", stringify!($name), ".yaml"))) {
+                panic!("{}", e);
+            }
+        }
+    };
+}
+
+scenario!(normal_conversation);
+scenario!(offline_fallback);
+scenario!(privacy_blocked_capture);
+scenario!(timer_across_restart);
+scenario!(npu_recovery_during_indexing);
+scenario!(local_answers);
+scenario!(forget_and_branch);
+scenario!(guest_mode);
+
+#[test]
+fn test_failed_expectation_names_the_step() {
+    let script = r#"
+name: broken
+steps:
+  - event: reply
+    text: hi
+  - event: say
+    text: hello
+  - event: expect
+    session_turns: 5
+"#;
+    let err = run(script).unwrap_err();
+    assert!(err.contains("step 3"), "{}", err);
+    assert!(err.contains("session_turns: expected 5, got 2"), "{}", err);
+}